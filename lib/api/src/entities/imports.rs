@@ -1,7 +1,7 @@
 //! The import module contains the implementation data structures and helper functions used to
 //! manipulate and access a wasm module's imports including memories, tables, globals, and
 //! functions.
-use crate::{Exports, Extern, Module, error::LinkError};
+use crate::{AsStoreRef, Exports, Extern, ExternType, FunctionType, Module, error::LinkError};
 use std::collections::HashMap;
 use std::fmt;
 use wasmer_types::ImportError;
@@ -60,6 +60,19 @@ impl Imports {
         None
     }
 
+    /// Gets an export from a namespace that was registered under a prefix,
+    /// see [`Imports::register_namespace_prefixed`]
+    ///
+    /// # Usage
+    /// ```no_run
+    /// # use wasmer::Imports;
+    /// let mut import_object = Imports::new();
+    /// import_object.get_export_prefixed("a_", "env", "name");
+    /// ```
+    pub fn get_export_prefixed(&self, prefix: &str, module: &str, name: &str) -> Option<Extern> {
+        self.get_export(&format!("{prefix}{module}"), name)
+    }
+
     /// Returns if an export exist for a given module and name.
     ///
     /// # Usage
@@ -102,6 +115,32 @@ impl Imports {
         }
     }
 
+    /// Register a list of externs into the namespace `ns` prefixed with
+    /// `prefix`, which allows the same namespace to be registered several
+    /// times (e.g. for multiple copies of a library).
+    ///
+    /// # Usage:
+    /// ```no_run
+    /// # use wasmer::{Imports, Exports, Memory};
+    /// # fn foo_test(memory: Memory) {
+    /// let mut exports = Exports::new();
+    /// exports.insert("memory", memory);
+    ///
+    /// let mut import_object = Imports::new();
+    /// // Registers the namespace `a_env`
+    /// import_object.register_namespace_prefixed("a_", "env", exports);
+    /// // ...
+    /// # }
+    /// ```
+    pub fn register_namespace_prefixed(
+        &mut self,
+        prefix: &str,
+        ns: &str,
+        contents: impl IntoIterator<Item = (String, Extern)>,
+    ) {
+        self.register_namespace(&format!("{prefix}{ns}"), contents);
+    }
+
     /// Add a single import with a namespace `ns` and name `name`.
     ///
     /// # Usage
@@ -156,12 +195,111 @@ impl Imports {
         Ok(ret)
     }
 
+    /// Builds the imports that `module` needs, and nothing more, from a
+    /// registry of host externs keyed by `(namespace, name)`.
+    ///
+    /// All the imports that the registry doesn't have are returned in the
+    /// order in which the module declares them. Only the presence of the
+    /// imports is checked, an extern of the wrong type is reported when the
+    /// module is instantiated.
+    ///
+    /// # Usage
+    /// ```no_run
+    /// # use std::collections::HashMap;
+    /// # use wasmer::{Extern, Imports, Module};
+    /// # fn foo_test(module: Module, registry: HashMap<(String, String), Extern>) {
+    /// match Imports::resolve_from_registry(&module, &registry) {
+    ///     Ok(imports) => { /* instantiate the module */ }
+    ///     Err(missing) => {
+    ///         for import in missing {
+    ///             eprintln!("missing import: {import}");
+    ///         }
+    ///     }
+    /// }
+    /// # }
+    /// ```
+    pub fn resolve_from_registry(
+        module: &Module,
+        registry: &HashMap<(String, String), Extern>,
+    ) -> Result<Self, Vec<MissingImport>> {
+        let mut imports = Self::new();
+        let mut missing = Vec::new();
+        for import in module.imports() {
+            let key = (import.module().to_string(), import.name().to_string());
+            match registry.get(&key) {
+                Some(ext) => {
+                    imports.map.insert(key, ext.clone());
+                }
+                None => missing.push(MissingImport {
+                    module: key.0,
+                    name: key.1,
+                    ty: import.ty().clone(),
+                }),
+            }
+        }
+        if missing.is_empty() {
+            Ok(imports)
+        } else {
+            Err(missing)
+        }
+    }
+
+    /// Returns the signatures of all the functions in this structure, keyed
+    /// by `(namespace, name)`, which can be compared with the imports that a
+    /// module needs before it is instantiated. The other externs are left out.
+    ///
+    /// # Usage
+    /// ```no_run
+    /// # use wasmer::{ExternType, Imports, Module, Store};
+    /// # fn foo_test(store: &Store, imports: &Imports, module: Module) {
+    /// let signatures = imports.function_signatures(store);
+    /// for import in module.imports() {
+    ///     if let ExternType::Function(ty) = import.ty() {
+    ///         let key = (import.module().to_string(), import.name().to_string());
+    ///         if signatures.get(&key) != Some(ty) {
+    ///             eprintln!("incompatible import: {key:?}");
+    ///         }
+    ///     }
+    /// }
+    /// # }
+    /// ```
+    pub fn function_signatures(
+        &self,
+        store: &impl AsStoreRef,
+    ) -> HashMap<(String, String), FunctionType> {
+        self.map
+            .iter()
+            .filter_map(|(key, ext)| match ext {
+                Extern::Function(func) => Some((key.clone(), func.ty(store))),
+                _ => None,
+            })
+            .collect()
+    }
+
     /// Iterates through all the imports in this structure
     pub fn iter(&self) -> ImportsIterator<'_> {
         ImportsIterator::new(self)
     }
 }
 
+/// An import of a module that could not be found, see
+/// [`Imports::resolve_from_registry`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MissingImport {
+    /// The namespace of the import
+    pub module: String,
+    /// The name of the import
+    pub name: String,
+    /// The type that the module expects
+    pub ty: ExternType,
+}
+
+impl fmt::Display for MissingImport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}::{} ({:?})", self.module, self.name, self.ty)
+    }
+}
+
 /// An iterator over module imports.
 pub struct ImportsIterator<'a> {
     iter: std::collections::hash_map::Iter<'a, (String, String), Extern>,
@@ -304,6 +442,7 @@ macro_rules! import_namespace {
 mod test {
     use crate::Extern;
     use crate::Global;
+    use crate::Imports;
     use crate::store::Store;
     use crate::value::Value;
     use wasmer_types::Type;
@@ -328,6 +467,24 @@ mod test {
         });
     }
 
+    #[test]
+    fn prefixed_namespace() {
+        let mut store = Store::default();
+        let g1 = Global::new(&mut store, Value::I32(0));
+        let g2 = Global::new(&mut store, Value::I64(0));
+
+        let mut imports = Imports::new();
+        imports.register_namespace_prefixed("a_", "env", namespace! { "g" => g1 });
+        imports.register_namespace_prefixed("b_", "env", namespace! { "g" => g2 });
+
+        assert!(!imports.contains_namespace("env"));
+        let a = imports.get_export("a_env", "g").unwrap();
+        assert!(matches!(&a, Extern::Global(g) if g.get(&mut store).ty() == Type::I32));
+        let b = imports.get_export_prefixed("b_", "env", "g").unwrap();
+        assert!(matches!(&b, Extern::Global(g) if g.get(&mut store).ty() == Type::I64));
+        assert!(imports.get_export_prefixed("c_", "env", "g").is_none());
+    }
+
     #[test]
     fn imports_macro_allows_trailing_comma_and_none() {
         use crate::Function;
@@ -380,6 +537,79 @@ mod test {
         };
     }
 
+    #[test]
+    fn resolve_from_registry_reports_missing_imports() {
+        use crate::{Function, Module};
+        use std::collections::HashMap;
+
+        let mut store = Store::default();
+        let module = Module::new(
+            &store,
+            r#"(module
+                (import "env" "a" (func (param i32)))
+                (import "env" "b" (func))
+                (import "env" "c" (global i32)))"#,
+        )
+        .unwrap();
+
+        let mut registry = HashMap::new();
+        registry.insert(
+            ("env".to_string(), "a".to_string()),
+            Extern::from(Function::new_typed(&mut store, |_: i32| {})),
+        );
+        registry.insert(
+            ("env".to_string(), "c".to_string()),
+            Extern::from(Global::new(&mut store, Value::I32(0))),
+        );
+        registry.insert(
+            ("env".to_string(), "unused".to_string()),
+            Extern::from(Function::new_typed(&mut store, || {})),
+        );
+
+        let missing = Imports::resolve_from_registry(&module, &registry).unwrap_err();
+        assert_eq!(missing.len(), 1);
+        assert_eq!(
+            (missing[0].module.as_str(), missing[0].name.as_str()),
+            ("env", "b")
+        );
+        assert!(matches!(missing[0].ty, crate::ExternType::Function(_)));
+
+        // Once the registry has everything only the needed imports are taken
+        registry.insert(
+            ("env".to_string(), "b".to_string()),
+            Extern::from(Function::new_typed(&mut store, || {})),
+        );
+        let imports = Imports::resolve_from_registry(&module, &registry).unwrap();
+        assert_eq!(imports.iter().count(), 3);
+        assert!(!imports.exists("env", "unused"));
+        crate::Instance::new(&mut store, &module, &imports).unwrap();
+    }
+
+    #[test]
+    fn function_signatures_only_has_the_functions() {
+        use crate::{Function, FunctionType};
+
+        let mut store = Store::default();
+        let imports = imports! {
+            "env" => {
+                "add" => Function::new_typed(&mut store, |a: i32, b: i32| a + b),
+                "nop" => Function::new_typed(&mut store, || {}),
+                "global" => Global::new(&mut store, Value::I32(0)),
+            },
+        };
+
+        let signatures = imports.function_signatures(&store);
+        assert_eq!(signatures.len(), 2);
+        assert_eq!(
+            signatures[&("env".to_string(), "add".to_string())],
+            FunctionType::new([Type::I32, Type::I32], [Type::I32])
+        );
+        assert_eq!(
+            signatures[&("env".to_string(), "nop".to_string())],
+            FunctionType::new([], [])
+        );
+    }
+
     #[test]
     fn chaining_works() {
         let mut store = Store::default();
@@ -19,7 +19,7 @@ use std::sync::Arc;
 #[cfg(feature = "webc_runner")]
 use wasmer_api::{AsStoreMut, Imports, Module};
 use wasmer_wasix::{
-    Pipe, PluggableRuntime, WasiEnv, WasiEnvBuilder, WasiFunctionEnv, WasiVersion,
+    AbsentStdin, Pipe, PluggableRuntime, WasiEnv, WasiEnvBuilder, WasiFunctionEnv, WasiVersion,
     default_fs_backing, get_wasi_version,
     runtime::task_manager::{block_on, tokio::TokioTaskManager},
     virtual_fs::AsyncReadExt,
@@ -363,6 +363,9 @@ pub unsafe extern "C" fn wasi_env_new(
     }
 
     // TODO: impl capturer for stdin
+    if config.inherit_stdin {
+        config.builder.set_absent_stdin(AbsentStdin::Inherit);
+    }
 
     let env = c_try!(
         config
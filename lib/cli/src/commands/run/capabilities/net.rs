@@ -12,9 +12,9 @@ use anyhow::Context;
 use colored::Colorize;
 use dialoguer::theme::ColorfulTheme;
 use virtual_net::{
-    DynVirtualNetworking, IpCidr, IpRoute, NetworkError, Result, StreamSecurity,
+    DynVirtualNetworking, IpCidr, IpRoute, NetworkError, NetworkInterface, Result, StreamSecurity,
     UnsupportedVirtualNetworking, VirtualIcmpSocket, VirtualNetworking, VirtualRawSocket,
-    VirtualTcpListener, VirtualTcpSocket, VirtualUdpSocket,
+    VirtualTcpBinding, VirtualTcpListener, VirtualTcpSocket, VirtualUdpSocket,
 };
 
 /// A custom implementation of the [`virtual_net::VirtualNetwork`] that asks users if they want to
@@ -212,6 +212,11 @@ impl VirtualNetworking for AskingNetworking {
         call!(self, mac);
     }
 
+    /// Lists the network interfaces along with their addresses
+    async fn interfaces(&self) -> Result<Vec<NetworkInterface>> {
+        call!(self, interfaces);
+    }
+
     /// Adds a default gateway to the routing table
     async fn gateway_set(&self, ip: IpAddr) -> Result<()> {
         call!(self, gateway_set, ip);
@@ -269,6 +274,18 @@ impl VirtualNetworking for AskingNetworking {
         call!(self, listen_tcp, addr, only_v6, reuse_port, reuse_addr);
     }
 
+    /// Binds a TCP socket to a specific IP and Port combination without listening
+    /// or connecting yet
+    async fn bind_tcp(
+        &self,
+        addr: SocketAddr,
+        only_v6: bool,
+        reuse_port: bool,
+        reuse_addr: bool,
+    ) -> Result<Box<dyn VirtualTcpBinding + Sync>> {
+        call!(self, bind_tcp, addr, only_v6, reuse_port, reuse_addr);
+    }
+
     /// Opens a UDP socket that listens on a specific IP and Port combination
     /// Multiple servers (processes or threads) can bind to the same port if they each set
     /// the reuse-port and-or reuse-addr flags
@@ -46,7 +46,7 @@ use wasmer_types::ModuleHash;
 #[cfg(feature = "journal")]
 use wasmer_wasix::journal::{LogFileJournal, SnapshotTrigger};
 use wasmer_wasix::{
-    Runtime, SpawnError, WasiError,
+    AbsentStdin, Runtime, SpawnError, WasiError,
     bin_factory::{BinaryPackage, BinaryPackageCommand},
     journal::CompactingLogFileJournal,
     runners::{
@@ -607,6 +607,7 @@ impl Run {
 
         runner
             .with_args(&self.args)
+            .with_absent_stdin(AbsentStdin::Inherit)
             .with_injected_packages(packages)
             .with_envs(self.wasi.env_vars.clone())
             .with_mapped_host_commands(self.wasi.build_mapped_commands()?)
@@ -615,6 +616,10 @@ impl Run {
             .with_forward_host_env(self.wasi.forward_host_env)
             .with_capabilities(self.wasi.capabilities());
 
+        if let Some(size) = self.wasi.write_buffer_size {
+            runner.with_write_buffer_size(size);
+        }
+
         if let Some(cwd) = self.wasi.cwd.as_ref() {
             if !cwd.starts_with("/") {
                 bail!("The argument to --cwd must be an absolute path");
@@ -20,8 +20,8 @@ use wasmer_types::ModuleHash;
 #[cfg(feature = "journal")]
 use wasmer_wasix::journal::{LogFileJournal, SnapshotTrigger};
 use wasmer_wasix::{
-    PluggableRuntime, RewindState, Runtime, WasiEnv, WasiEnvBuilder, WasiError, WasiFunctionEnv,
-    WasiVersion,
+    AbsentStdin, PluggableRuntime, RewindState, Runtime, WasiEnv, WasiEnvBuilder, WasiError,
+    WasiFunctionEnv, WasiVersion,
     bin_factory::BinaryPackage,
     capabilities::Capabilities,
     default_fs_backing, get_wasi_versions,
@@ -157,6 +157,12 @@ pub struct Wasi {
     #[clap(long = "enable-cpu-backoff")]
     pub enable_cpu_backoff: Option<u64>,
 
+    /// Coalesces the writes to files that are opened for writing in a
+    /// buffer of this many bytes, which saves a host syscall for every
+    /// small write (default = off)
+    #[clap(long = "write-buffer-size")]
+    pub write_buffer_size: Option<usize>,
+
     /// Specifies one or more journal files that Wasmer will use to restore
     /// the state of the WASM process as it executes.
     ///
@@ -339,6 +345,7 @@ impl Wasi {
             .runtime(Arc::clone(&rt))
             .args(args)
             .envs(self.env_vars.clone())
+            .absent_stdin(AbsentStdin::Inherit)
             .uses(uses)
             .map_commands(map_commands);
 
@@ -385,6 +392,10 @@ impl Wasi {
 
         *builder.capabilities_mut() = self.capabilities();
 
+        if let Some(size) = self.write_buffer_size {
+            builder.set_write_buffer_size(size);
+        }
+
         #[cfg(feature = "journal")]
         {
             for trigger in self.snapshot_on.iter().cloned() {
@@ -90,6 +90,8 @@ pub enum JournalEntryRecordType {
     DuplicateFileDescriptorV2 = 62,
     FileDescriptorSetFdFlagsV1 = 63,
     SocketPairV1 = 64,
+    SocketSetOptStrV1 = 65,
+    SocketPairV2 = 66,
 }
 
 impl JournalEntryRecordType {
@@ -255,6 +257,9 @@ impl JournalEntryRecordType {
             JournalEntryRecordType::SocketPairV1 => {
                 ArchivedJournalEntry::SocketPairV1(unsafe { rkyv::access_unchecked(data) })
             }
+            JournalEntryRecordType::SocketPairV2 => {
+                ArchivedJournalEntry::SocketPairV2(unsafe { rkyv::access_unchecked(data) })
+            }
             JournalEntryRecordType::SocketListenV1 => {
                 ArchivedJournalEntry::SocketListenV1(unsafe { rkyv::access_unchecked(data) })
             }
@@ -302,6 +307,9 @@ impl JournalEntryRecordType {
             JournalEntryRecordType::SocketSetOptSizeV1 => {
                 ArchivedJournalEntry::SocketSetOptSizeV1(unsafe { rkyv::access_unchecked(data) })
             }
+            JournalEntryRecordType::SocketSetOptStrV1 => {
+                ArchivedJournalEntry::SocketSetOptStrV1(unsafe { rkyv::access_unchecked(data) })
+            }
             JournalEntryRecordType::SocketSetOptTimeV1 => {
                 ArchivedJournalEntry::SocketSetOptTimeV1(unsafe { rkyv::access_unchecked(data) })
             }
@@ -382,6 +390,7 @@ impl JournalEntry<'_> {
             Self::PortRouteDelV1 { .. } => JournalEntryRecordType::PortRouteDelV1,
             Self::SocketOpenV1 { .. } => JournalEntryRecordType::SocketOpenV1,
             Self::SocketPairV1 { .. } => JournalEntryRecordType::SocketPairV1,
+            Self::SocketPairV2 { .. } => JournalEntryRecordType::SocketPairV2,
             Self::SocketListenV1 { .. } => JournalEntryRecordType::SocketListenV1,
             Self::SocketBindV1 { .. } => JournalEntryRecordType::SocketBindV1,
             Self::SocketConnectedV1 { .. } => JournalEntryRecordType::SocketConnectedV1,
@@ -403,6 +412,7 @@ impl JournalEntry<'_> {
             Self::SocketSendV1 { .. } => JournalEntryRecordType::SocketSendV1,
             Self::SocketSetOptFlagV1 { .. } => JournalEntryRecordType::SocketSetOptFlagV1,
             Self::SocketSetOptSizeV1 { .. } => JournalEntryRecordType::SocketSetOptSizeV1,
+            Self::SocketSetOptStrV1 { .. } => JournalEntryRecordType::SocketSetOptStrV1,
             Self::SocketSetOptTimeV1 { .. } => JournalEntryRecordType::SocketSetOptTimeV1,
             Self::SocketShutdownV1 { .. } => JournalEntryRecordType::SocketShutdownV1,
             Self::SnapshotV1 { .. } => JournalEntryRecordType::SnapshotV1,
@@ -819,6 +829,14 @@ impl JournalEntry<'_> {
             JournalEntry::SocketPairV1 { fd1, fd2 } => {
                 serialize_using(&JournalEntrySocketPairV1 { fd1, fd2 }, serializer)
             }
+            JournalEntry::SocketPairV2 { fd1, fd2, ty } => serialize_using(
+                &JournalEntrySocketPairV2 {
+                    fd1,
+                    fd2,
+                    ty: ty.into(),
+                },
+                serializer,
+            ),
             JournalEntry::SocketListenV1 { fd, backlog } => {
                 serialize_using(&JournalEntrySocketListenV1 { fd, backlog }, serializer)
             }
@@ -963,6 +981,14 @@ impl JournalEntry<'_> {
                 },
                 serializer,
             ),
+            JournalEntry::SocketSetOptStrV1 { fd, opt, value } => serialize_using(
+                &JournalEntrySocketSetOptStrV1 {
+                    fd,
+                    opt: opt.into(),
+                    value: value.into(),
+                },
+                serializer,
+            ),
             JournalEntry::SocketSetOptTimeV1 { fd, ty, time } => serialize_using(
                 &JournalEntrySocketSetOptTimeV1 {
                     fd,
@@ -1054,6 +1080,7 @@ pub enum ArchivedJournalEntry<'a> {
     PortRouteDelV1(&'a ArchivedJournalEntryPortRouteDelV1),
     SocketOpenV1(&'a ArchivedJournalEntrySocketOpenV1),
     SocketPairV1(&'a ArchivedJournalEntrySocketPairV1),
+    SocketPairV2(&'a ArchivedJournalEntrySocketPairV2),
     SocketListenV1(&'a ArchivedJournalEntrySocketListenV1),
     SocketBindV1(&'a ArchivedJournalEntrySocketBindV1),
     SocketConnectedV1(&'a ArchivedJournalEntrySocketConnectedV1),
@@ -1067,6 +1094,7 @@ pub enum ArchivedJournalEntry<'a> {
     SocketSendV1(&'a ArchivedJournalEntrySocketSendV1<'a>),
     SocketSetOptFlagV1(&'a ArchivedJournalEntrySocketSetOptFlagV1),
     SocketSetOptSizeV1(&'a ArchivedJournalEntrySocketSetOptSizeV1),
+    SocketSetOptStrV1(&'a ArchivedJournalEntrySocketSetOptStrV1<'a>),
     SocketSetOptTimeV1(&'a ArchivedJournalEntrySocketSetOptTimeV1),
     SocketShutdownV1(&'a ArchivedJournalEntrySocketShutdownV1),
     SnapshotV1(&'a ArchivedJournalEntrySnapshotV1),
@@ -1507,6 +1535,16 @@ pub struct JournalEntrySocketPairV1 {
     pub fd2: u32,
 }
 
+#[repr(C)]
+#[repr(align(8))]
+#[derive(Debug, Clone, RkyvSerialize, RkyvDeserialize, Archive)]
+#[rkyv(derive(Debug), attr(repr(align(8))))]
+pub struct JournalEntrySocketPairV2 {
+    pub fd1: u32,
+    pub fd2: u32,
+    pub ty: JournalSocktypeV1,
+}
+
 #[repr(C)]
 #[repr(align(8))]
 #[derive(Debug, Clone, RkyvSerialize, RkyvDeserialize, Archive)]
@@ -1642,6 +1680,16 @@ pub struct JournalEntrySocketSetOptSizeV1 {
     pub size: u64,
 }
 
+#[repr(C)]
+#[repr(align(8))]
+#[derive(Debug, Clone, RkyvSerialize, RkyvDeserialize, Archive)]
+#[rkyv(attr(repr(align(8))))]
+pub struct JournalEntrySocketSetOptStrV1<'a> {
+    pub fd: u32,
+    pub opt: JournalSockoptionV1,
+    pub value: AlignedCowStr<'a>,
+}
+
 #[repr(C)]
 #[repr(align(8))]
 #[derive(Debug, Clone, RkyvSerialize, RkyvDeserialize, Archive)]
@@ -1897,6 +1945,8 @@ pub enum JournalSockoptionV1 {
     MulticastTtlV4,
     Type,
     Proto,
+    BindToDevice,
+    RecvTtl,
 }
 
 #[repr(C)]
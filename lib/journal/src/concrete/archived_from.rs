@@ -436,6 +436,8 @@ impl From<wasi::Sockoption> for JournalSockoptionV1 {
             wasi::Sockoption::MulticastTtlV4 => JournalSockoptionV1::MulticastTtlV4,
             wasi::Sockoption::Type => JournalSockoptionV1::Type,
             wasi::Sockoption::Proto => JournalSockoptionV1::Proto,
+            wasi::Sockoption::BindToDevice => JournalSockoptionV1::BindToDevice,
+            wasi::Sockoption::RecvTtl => JournalSockoptionV1::RecvTtl,
             _ => panic!("Unsupported Sockoption variant"),
         }
     }
@@ -471,6 +473,8 @@ impl From<JournalSockoptionV1> for wasi::Sockoption {
             JournalSockoptionV1::MulticastTtlV4 => wasi::Sockoption::MulticastTtlV4,
             JournalSockoptionV1::Type => wasi::Sockoption::Type,
             JournalSockoptionV1::Proto => wasi::Sockoption::Proto,
+            JournalSockoptionV1::BindToDevice => wasi::Sockoption::BindToDevice,
+            JournalSockoptionV1::RecvTtl => wasi::Sockoption::RecvTtl,
         }
     }
 }
@@ -505,6 +509,8 @@ impl From<&'_ ArchivedJournalSockoptionV1> for wasi::Sockoption {
             ArchivedJournalSockoptionV1::MulticastTtlV4 => wasi::Sockoption::MulticastTtlV4,
             ArchivedJournalSockoptionV1::Type => wasi::Sockoption::Type,
             ArchivedJournalSockoptionV1::Proto => wasi::Sockoption::Proto,
+            ArchivedJournalSockoptionV1::BindToDevice => wasi::Sockoption::BindToDevice,
+            ArchivedJournalSockoptionV1::RecvTtl => wasi::Sockoption::RecvTtl,
         }
     }
 }
@@ -1063,6 +1069,15 @@ impl<'a> TryFrom<ArchivedJournalEntry<'a>> for JournalEntry<'a> {
                     fd2: fd2.to_native(),
                 }
             }
+            ArchivedJournalEntry::SocketPairV2(ArchivedJournalEntrySocketPairV2 {
+                fd1,
+                fd2,
+                ty,
+            }) => Self::SocketPairV2 {
+                fd1: fd1.to_native(),
+                fd2: fd2.to_native(),
+                ty: ty.into(),
+            },
             ArchivedJournalEntry::SocketListenV1(ArchivedJournalEntrySocketListenV1 {
                 fd,
                 backlog,
@@ -1188,6 +1203,15 @@ impl<'a> TryFrom<ArchivedJournalEntry<'a>> for JournalEntry<'a> {
                 opt: opt.into(),
                 flag: *flag,
             },
+            ArchivedJournalEntry::SocketSetOptStrV1(ArchivedJournalEntrySocketSetOptStrV1 {
+                fd,
+                opt,
+                value,
+            }) => Self::SocketSetOptStrV1 {
+                fd: fd.to_native(),
+                opt: opt.into(),
+                value: String::from_utf8_lossy(value.as_ref()),
+            },
             ArchivedJournalEntry::SocketSetOptSizeV1(ArchivedJournalEntrySocketSetOptSizeV1 {
                 fd,
                 opt,
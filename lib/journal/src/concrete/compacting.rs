@@ -630,6 +630,7 @@ impl WritableJournal for CompactingJournalTx {
             | JournalEntry::SocketSendV1 { fd, .. }
             | JournalEntry::SocketSetOptFlagV1 { fd, .. }
             | JournalEntry::SocketSetOptSizeV1 { fd, .. }
+            | JournalEntry::SocketSetOptStrV1 { fd, .. }
             | JournalEntry::SocketSetOptTimeV1 { fd, .. }
             | JournalEntry::SocketShutdownV1 { fd, .. }
             | JournalEntry::SocketListenV1 { fd, .. }
@@ -784,7 +785,8 @@ impl WritableJournal for CompactingJournalTx {
                 let lookup = state.insert_new_sub_events(event_index);
                 state.open_sockets.insert(*fd, lookup);
             }
-            JournalEntry::SocketPairV1 { fd1, fd2 } => {
+            JournalEntry::SocketPairV1 { fd1, fd2 }
+            | JournalEntry::SocketPairV2 { fd1, fd2, .. } => {
                 let lookup = state.insert_new_sub_events(event_index);
                 state.open_sockets.insert(*fd1, lookup);
                 state.open_sockets.insert(*fd2, lookup);
@@ -341,6 +341,7 @@ impl<W: WritableJournal> WritableJournal for FilteredJournalTx<W> {
             | JournalEntry::PortRouteDelV1 { .. }
             | JournalEntry::SocketOpenV1 { .. }
             | JournalEntry::SocketPairV1 { .. }
+            | JournalEntry::SocketPairV2 { .. }
             | JournalEntry::SocketListenV1 { .. }
             | JournalEntry::SocketBindV1 { .. }
             | JournalEntry::SocketConnectedV1 { .. }
@@ -354,6 +355,7 @@ impl<W: WritableJournal> WritableJournal for FilteredJournalTx<W> {
             | JournalEntry::SocketSendV1 { .. }
             | JournalEntry::SocketSetOptFlagV1 { .. }
             | JournalEntry::SocketSetOptSizeV1 { .. }
+            | JournalEntry::SocketSetOptStrV1 { .. }
             | JournalEntry::SocketSetOptTimeV1 { .. }
             | JournalEntry::SocketShutdownV1 { .. } => {
                 if self.config.filter_net {
@@ -256,6 +256,9 @@ impl fmt::Display for JournalEntry<'_> {
             JournalEntry::SocketPairV1 { fd1, fd2 } => {
                 write!(f, "sock-pair (fd1={fd1}, fd2={fd2})")
             }
+            JournalEntry::SocketPairV2 { fd1, fd2, ty } => {
+                write!(f, "sock-pair (fd1={fd1}, fd2={fd2}, ty={ty:?})")
+            }
             JournalEntry::SocketListenV1 { fd, backlog } => {
                 write!(f, "sock-listen (fd={fd}, backlog={backlog})")
             }
@@ -341,6 +344,9 @@ impl fmt::Display for JournalEntry<'_> {
             JournalEntry::SocketSetOptSizeV1 { fd, opt, size } => {
                 write!(f, "sock-set-opt (fd={fd}, opt={opt:?}, size={size})")
             }
+            JournalEntry::SocketSetOptStrV1 { fd, opt, value } => {
+                write!(f, "sock-set-opt (fd={fd}, opt={opt:?}, value={value})")
+            }
             JournalEntry::SocketSetOptTimeV1 { fd, ty, time } => {
                 write!(f, "sock-set-opt (fd={fd}, opt={ty:?}, time={time:?})")
             }
@@ -472,6 +472,16 @@ pub fn test_record_socket_open() {
     });
 }
 
+#[tracing_test::traced_test]
+#[test]
+pub fn test_record_socket_pair() {
+    run_test(JournalEntry::SocketPairV2 {
+        fd1: 3,
+        fd2: 4,
+        ty: wasi::Socktype::Dgram,
+    });
+}
+
 #[tracing_test::traced_test]
 #[test]
 pub fn test_record_socket_listen() {
@@ -597,6 +607,16 @@ pub fn test_record_socket_set_opt_flag() {
     });
 }
 
+#[tracing_test::traced_test]
+#[test]
+pub fn test_record_socket_set_opt_str() {
+    run_test(JournalEntry::SocketSetOptStrV1 {
+        fd: 0,
+        opt: wasi::Sockoption::BindToDevice,
+        value: "eth0".into(),
+    });
+}
+
 #[tracing_test::traced_test]
 #[test]
 pub fn test_record_socket_set_opt_size() {
@@ -700,6 +720,7 @@ pub fn test_record_alignment() {
     assert_eq!(std::mem::align_of::<JournalEntryPortRouteAddV1>(), 8);
     assert_eq!(std::mem::align_of::<JournalEntryPortRouteDelV1>(), 8);
     assert_eq!(std::mem::align_of::<JournalEntrySocketOpenV1>(), 8);
+    assert_eq!(std::mem::align_of::<JournalEntrySocketPairV2>(), 8);
     assert_eq!(std::mem::align_of::<JournalEntrySocketListenV1>(), 8);
     assert_eq!(std::mem::align_of::<JournalEntrySocketBindV1>(), 8);
     assert_eq!(std::mem::align_of::<JournalEntrySocketConnectedV1>(), 8);
@@ -725,6 +746,7 @@ pub fn test_record_alignment() {
     assert_eq!(std::mem::align_of::<JournalEntrySocketSendV1>(), 8);
     assert_eq!(std::mem::align_of::<JournalEntrySocketSetOptFlagV1>(), 8);
     assert_eq!(std::mem::align_of::<JournalEntrySocketSetOptSizeV1>(), 8);
+    assert_eq!(std::mem::align_of::<JournalEntrySocketSetOptStrV1>(), 8);
     assert_eq!(std::mem::align_of::<JournalEntrySocketSetOptTimeV1>(), 8);
     assert_eq!(std::mem::align_of::<JournalEntrySocketShutdownV1>(), 8);
     assert_eq!(std::mem::align_of::<JournalEntrySnapshotV1>(), 8);
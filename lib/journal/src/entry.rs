@@ -305,6 +305,11 @@ pub enum JournalEntry<'a> {
         fd1: Fd,
         fd2: Fd,
     },
+    SocketPairV2 {
+        fd1: Fd,
+        fd2: Fd,
+        ty: Socktype,
+    },
     SocketListenV1 {
         fd: Fd,
         backlog: u32,
@@ -379,6 +384,11 @@ pub enum JournalEntry<'a> {
         opt: Sockoption,
         size: u64,
     },
+    SocketSetOptStrV1 {
+        fd: Fd,
+        opt: Sockoption,
+        value: Cow<'a, str>,
+    },
     SocketSetOptTimeV1 {
         fd: Fd,
         ty: SocketOptTimeType,
@@ -664,6 +674,7 @@ impl JournalEntry<'_> {
             Self::PortRouteDelV1 { ip } => JournalEntry::PortRouteDelV1 { ip },
             Self::SocketOpenV1 { af, ty, pt, fd } => JournalEntry::SocketOpenV1 { af, ty, pt, fd },
             Self::SocketPairV1 { fd1, fd2 } => JournalEntry::SocketPairV1 { fd1, fd2 },
+            Self::SocketPairV2 { fd1, fd2, ty } => JournalEntry::SocketPairV2 { fd1, fd2, ty },
             Self::SocketListenV1 { fd, backlog } => JournalEntry::SocketListenV1 { fd, backlog },
             Self::SocketBindV1 { fd, addr } => JournalEntry::SocketBindV1 { fd, addr },
             Self::SocketConnectedV1 {
@@ -767,6 +778,11 @@ impl JournalEntry<'_> {
             Self::SocketSetOptSizeV1 { fd, opt, size } => {
                 JournalEntry::SocketSetOptSizeV1 { fd, opt, size }
             }
+            Self::SocketSetOptStrV1 { fd, opt, value } => JournalEntry::SocketSetOptStrV1 {
+                fd,
+                opt,
+                value: value.into_owned().into(),
+            },
             Self::SocketSetOptTimeV1 { fd, ty, time } => {
                 JournalEntry::SocketSetOptTimeV1 { fd, ty, time }
             }
@@ -840,6 +856,7 @@ impl JournalEntry<'_> {
             JournalEntry::PortRouteDelV1 { .. } => base_size,
             JournalEntry::SocketOpenV1 { .. } => base_size,
             JournalEntry::SocketPairV1 { .. } => base_size,
+            JournalEntry::SocketPairV2 { .. } => base_size,
             JournalEntry::SocketListenV1 { .. } => base_size,
             JournalEntry::SocketBindV1 { .. } => base_size,
             JournalEntry::SocketConnectedV1 { .. } => base_size,
@@ -853,6 +870,7 @@ impl JournalEntry<'_> {
             JournalEntry::SocketSendV1 { data, .. } => base_size + data.len(),
             JournalEntry::SocketSetOptFlagV1 { .. } => base_size,
             JournalEntry::SocketSetOptSizeV1 { .. } => base_size,
+            JournalEntry::SocketSetOptStrV1 { value, .. } => base_size + value.len(),
             JournalEntry::SocketSetOptTimeV1 { .. } => base_size,
             JournalEntry::SocketShutdownV1 { .. } => base_size,
             JournalEntry::SnapshotV1 { .. } => base_size,
@@ -4,7 +4,7 @@ use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use virtual_fs::{AsyncReadExt, FileSystem};
 use wasmer_wasix::{
-    Pipe, PluggableRuntime, WasiError,
+    AbsentStdin, Pipe, PluggableRuntime, WasiError,
     runners::wasi::{RuntimeOrEngine, WasiRunner},
     runtime::task_manager::tokio::TokioTaskManager,
 };
@@ -101,6 +101,7 @@ pub fn run_wasix_main_capture_stdio_with_ctx(
 
         let mut runner = WasiRunner::new();
         runner
+            .with_absent_stdin(AbsentStdin::Inherit)
             .with_stdout(Box::new(stdout_tx))
             .with_stderr(Box::new(stderr_tx))
             .with_args(args.iter().cloned());
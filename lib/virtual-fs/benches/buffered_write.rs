@@ -0,0 +1,48 @@
+use std::io::SeekFrom;
+
+use criterion::{Criterion, criterion_group, criterion_main};
+use tokio::io::{AsyncSeekExt, AsyncWriteExt};
+use virtual_fs::{FileSystem, host_fs};
+
+const WRITES: usize = 1024;
+const WRITE_SIZE: usize = 64;
+
+/// Writes a file the way WASIX does it for a guest, which is a seek to the
+/// offset of the descriptor followed by the write
+fn write_file(rt: &tokio::runtime::Runtime, fs: &host_fs::FileSystem) {
+    rt.block_on(async {
+        let mut file = fs
+            .new_open_options()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open("/file")
+            .unwrap();
+
+        let chunk = [b'x'; WRITE_SIZE];
+        for n in 0..WRITES {
+            file.seek(SeekFrom::Start((n * WRITE_SIZE) as u64))
+                .await
+                .unwrap();
+            file.write_all(&chunk).await.unwrap();
+        }
+        file.flush().await.unwrap();
+    });
+}
+
+pub fn small_writes(c: &mut Criterion) {
+    let rt = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap();
+    let dir = tempfile::TempDir::new().unwrap();
+    let fs = host_fs::FileSystem::new(rt.handle().clone(), dir.path()).unwrap();
+
+    let mut group = c.benchmark_group("64 byte writes to a host file");
+    group.bench_function("unbuffered", |b| b.iter(|| write_file(&rt, &fs)));
+    let fs = fs.with_write_buffer_size(8192);
+    group.bench_function("buffered", |b| b.iter(|| write_file(&rt, &fs)));
+    group.finish();
+}
+
+criterion_group!(benches, small_writes);
+criterion_main!(benches);
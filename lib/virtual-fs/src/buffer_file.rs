@@ -87,9 +87,9 @@ impl VirtualFile for BufferFile {
     fn unlink(&mut self) -> crate::Result<()> {
         Ok(())
     }
-    fn poll_read_ready(mut self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<usize>> {
-        let cur = self.data.stream_position().unwrap_or_default();
-        let len = self.data.seek(SeekFrom::End(0)).unwrap_or_default();
+    fn poll_read_ready(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<usize>> {
+        let cur = self.data.position();
+        let len = self.data.get_ref().len() as u64;
         if cur < len {
             Poll::Ready(Ok((len - cur) as usize))
         } else {
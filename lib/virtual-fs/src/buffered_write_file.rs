@@ -0,0 +1,361 @@
+use std::{
+    io::{self, SeekFrom},
+    pin::Pin,
+    task::{Context, Poll, ready},
+};
+
+use tokio::io::{AsyncRead, AsyncSeek, AsyncWrite};
+
+use crate::VirtualFile;
+
+/// Wraps a [`VirtualFile`] and coalesces small writes into a buffer that is
+/// written to the inner file in one go.
+///
+/// The buffer is written out when it is full, when the file is flushed or
+/// shut down, and before anything that needs to see the data (reads, seeks
+/// to a position other than the end of the buffered data, `set_len`, ...).
+/// Seeking to the end of the buffered data, as is done before every write,
+/// keeps the data buffered.
+///
+/// Closing the file (`poll_shutdown`) writes out the buffered data. Data that
+/// is still buffered when the file is dropped is only written out if the
+/// inner file takes it right away, otherwise it is lost, so owners of the
+/// file flush or shut it down before dropping it.
+#[derive(Debug)]
+pub struct BufferedWriteFile {
+    inner: Box<dyn VirtualFile + Send + Sync + 'static>,
+    capacity: usize,
+    buffer: Vec<u8>,
+    /// How much of the buffer was already written to the inner file
+    flushed: usize,
+    /// Position of the cursor of the inner file (if known), which is also
+    /// where the buffered data starts
+    cursor: Option<u64>,
+    /// Seek that was requested but not yet passed on to the inner file
+    pending_seek: Option<SeekFrom>,
+    /// The inner file is busy with a seek
+    seeking: bool,
+}
+
+impl BufferedWriteFile {
+    pub fn new(inner: Box<dyn VirtualFile + Send + Sync + 'static>, capacity: usize) -> Self {
+        Self {
+            inner,
+            capacity,
+            buffer: Vec::with_capacity(capacity),
+            flushed: 0,
+            cursor: None,
+            pending_seek: None,
+            seeking: false,
+        }
+    }
+
+    /// Position right after the buffered data, if there is any
+    fn buffered_end(&self) -> Option<u64> {
+        if self.buffer.is_empty() {
+            return None;
+        }
+        self.cursor.map(|cursor| cursor + self.buffer.len() as u64)
+    }
+
+    /// Writes the buffered data to the inner file
+    fn poll_write_buffer(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        while self.flushed < self.buffer.len() {
+            let written =
+                ready!(Pin::new(&mut self.inner).poll_write(cx, &self.buffer[self.flushed..]))?;
+            if written == 0 {
+                return Poll::Ready(Err(io::ErrorKind::WriteZero.into()));
+            }
+            self.flushed += written;
+        }
+        let written = self.buffer.len() as u64;
+        self.cursor = self.cursor.map(|cursor| cursor + written);
+        self.buffer.clear();
+        self.flushed = 0;
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl Drop for BufferedWriteFile {
+    fn drop(&mut self) {
+        if self.buffer.is_empty() {
+            return;
+        }
+        // Blocking on the inner file here could stall the runtime that is
+        // dropping the file, so it only gets one chance to take the data
+        let waker = futures::task::noop_waker();
+        let mut cx = Context::from_waker(&waker);
+        let result = match self.poll_write_buffer(&mut cx) {
+            Poll::Ready(Ok(())) => Pin::new(&mut self.inner).poll_flush(&mut cx),
+            other => other,
+        };
+        match result {
+            Poll::Ready(Ok(())) => {}
+            Poll::Ready(Err(err)) => tracing::warn!(
+                lost = self.buffer.len() - self.flushed,
+                error = %err,
+                "failed to write out the buffered data of a dropped file"
+            ),
+            Poll::Pending => tracing::warn!(
+                lost = self.buffer.len() - self.flushed,
+                "buffered file was dropped without being flushed"
+            ),
+        }
+    }
+}
+
+impl VirtualFile for BufferedWriteFile {
+    fn last_accessed(&self) -> u64 {
+        self.inner.last_accessed()
+    }
+
+    fn last_modified(&self) -> u64 {
+        self.inner.last_modified()
+    }
+
+    fn created_time(&self) -> u64 {
+        self.inner.created_time()
+    }
+
+    fn set_times(&mut self, atime: Option<u64>, mtime: Option<u64>) -> crate::Result<()> {
+        self.inner.set_times(atime, mtime)
+    }
+
+    fn size(&self) -> u64 {
+        let size = self.inner.size();
+        self.buffered_end().map_or(size, |end| size.max(end))
+    }
+
+    fn set_len(&mut self, new_size: u64) -> crate::Result<()> {
+        // The buffered data can't be written out here so whatever falls
+        // beyond the new size is dropped from the buffer instead
+        if let Some(start) = self.cursor
+            && !self.buffer.is_empty()
+        {
+            let keep = new_size.saturating_sub(start).min(self.buffer.len() as u64);
+            self.buffer.truncate(keep as usize);
+            self.flushed = self.flushed.min(self.buffer.len());
+        }
+        self.inner.set_len(new_size)
+    }
+
+    fn unlink(&mut self) -> crate::Result<()> {
+        self.inner.unlink()
+    }
+
+    fn is_open(&self) -> bool {
+        self.inner.is_open()
+    }
+
+    fn get_special_fd(&self) -> Option<u32> {
+        self.inner.get_special_fd()
+    }
+
+    fn try_lock_host(&mut self) -> crate::Result<()> {
+        self.inner.try_lock_host()
+    }
+
+    fn poll_read_ready(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<usize>> {
+        ready!(self.poll_write_buffer(cx))?;
+        Pin::new(self.inner.as_mut()).poll_read_ready(cx)
+    }
+
+    fn poll_write_ready(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<usize>> {
+        if self.buffer.len() >= self.capacity {
+            ready!(self.poll_write_buffer(cx))?;
+        }
+        Poll::Ready(Ok(self.capacity - self.buffer.len()))
+    }
+}
+
+impl AsyncRead for BufferedWriteFile {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut tokio::io::ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        ready!(self.poll_write_buffer(cx))?;
+        let before = buf.filled().len();
+        ready!(Pin::new(&mut self.inner).poll_read(cx, buf))?;
+        let read = (buf.filled().len() - before) as u64;
+        self.cursor = self.cursor.map(|cursor| cursor + read);
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl AsyncWrite for BufferedWriteFile {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        // Without knowing where the data would land it can't be buffered
+        let Some(cursor) = self.cursor else {
+            return Pin::new(&mut self.inner).poll_write(cx, buf);
+        };
+
+        if self.buffer.len() + buf.len() > self.capacity {
+            ready!(self.poll_write_buffer(cx))?;
+        }
+        if buf.len() >= self.capacity {
+            let written = ready!(Pin::new(&mut self.inner).poll_write(cx, buf))?;
+            self.cursor = Some(cursor + written as u64);
+            return Poll::Ready(Ok(written));
+        }
+
+        self.buffer.extend_from_slice(buf);
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        ready!(self.poll_write_buffer(cx))?;
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        ready!(self.poll_write_buffer(cx))?;
+        Pin::new(&mut self.inner).poll_shutdown(cx)
+    }
+}
+
+impl AsyncSeek for BufferedWriteFile {
+    fn start_seek(mut self: Pin<&mut Self>, position: SeekFrom) -> io::Result<()> {
+        if self.pending_seek.is_some() || self.seeking {
+            return Err(io::Error::other(
+                "other seek operation is pending, call poll_complete before start_seek",
+            ));
+        }
+        self.pending_seek = Some(position);
+        Ok(())
+    }
+
+    fn poll_complete(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<u64>> {
+        if let Some(position) = self.pending_seek {
+            if let Some(end) = self.buffered_end() {
+                let target = match position {
+                    SeekFrom::Start(offset) => Some(offset),
+                    SeekFrom::Current(delta) => end.checked_add_signed(delta),
+                    SeekFrom::End(_) => None,
+                };
+                if target == Some(end) {
+                    self.pending_seek = None;
+                    return Poll::Ready(Ok(end));
+                }
+            }
+
+            ready!(self.poll_write_buffer(cx))?;
+            // Wait for the inner file to finish whatever it is doing, like
+            // the `seek` future of tokio does before starting a seek
+            ready!(Pin::new(&mut self.inner).poll_complete(cx))?;
+            self.pending_seek = None;
+            Pin::new(&mut self.inner).start_seek(position)?;
+            self.seeking = true;
+        }
+
+        if !self.seeking
+            && let Some(cursor) = self.cursor
+        {
+            return Poll::Ready(Ok(cursor + self.buffer.len() as u64));
+        }
+
+        let result = ready!(Pin::new(&mut self.inner).poll_complete(cx));
+        self.seeking = false;
+        self.cursor = result.as_ref().ok().copied();
+        Poll::Ready(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+
+    use super::*;
+    use crate::mem_fs;
+
+    fn open(fs: &mem_fs::FileSystem) -> Box<dyn VirtualFile + Send + Sync + 'static> {
+        crate::FileSystem::new_open_options(fs)
+            .read(true)
+            .write(true)
+            .create(true)
+            .open("/file")
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_small_writes_are_coalesced() {
+        let fs = mem_fs::FileSystem::default();
+        let mut file = BufferedWriteFile::new(open(&fs), 16);
+
+        for (offset, chunk) in [b"abcd", b"efgh", b"ijkl"].iter().enumerate() {
+            file.seek(SeekFrom::Start(offset as u64 * 4)).await.unwrap();
+            file.write_all(*chunk).await.unwrap();
+        }
+        assert_eq!(file.size(), 12);
+        assert_eq!(open(&fs).size(), 0, "the writes are still buffered");
+
+        // Filling up the buffer writes it out
+        file.seek(SeekFrom::Start(12)).await.unwrap();
+        file.write_all(b"mnopq").await.unwrap();
+        assert_eq!(open(&fs).size(), 12);
+
+        file.flush().await.unwrap();
+        let mut contents = String::new();
+        open(&fs).read_to_string(&mut contents).await.unwrap();
+        assert_eq!(contents, "abcdefghijklmnopq");
+    }
+
+    #[tokio::test]
+    async fn test_reads_and_seeks_observe_buffered_data() {
+        let fs = mem_fs::FileSystem::default();
+        let mut file = BufferedWriteFile::new(open(&fs), 64);
+
+        file.seek(SeekFrom::Start(0)).await.unwrap();
+        file.write_all(b"hello world").await.unwrap();
+        assert_eq!(file.stream_position().await.unwrap(), 11);
+        assert_eq!(file.seek(SeekFrom::End(-5)).await.unwrap(), 6);
+
+        let mut contents = String::new();
+        file.read_to_string(&mut contents).await.unwrap();
+        assert_eq!(contents, "world");
+
+        // Writing somewhere else doesn't mix up the buffered data
+        file.seek(SeekFrom::Start(0)).await.unwrap();
+        file.write_all(b"J").await.unwrap();
+        file.seek(SeekFrom::Start(6)).await.unwrap();
+        file.write_all(b"W").await.unwrap();
+        file.seek(SeekFrom::Start(0)).await.unwrap();
+        let mut contents = String::new();
+        file.read_to_string(&mut contents).await.unwrap();
+        assert_eq!(contents, "Jello World");
+    }
+
+    #[tokio::test]
+    async fn test_buffered_data_is_written_out_on_shutdown() {
+        let fs = mem_fs::FileSystem::default();
+        let mut file = BufferedWriteFile::new(open(&fs), 64);
+
+        file.seek(SeekFrom::Start(0)).await.unwrap();
+        file.write_all(b"closed").await.unwrap();
+        assert_eq!(open(&fs).size(), 0);
+
+        file.shutdown().await.unwrap();
+        let mut contents = String::new();
+        open(&fs).read_to_string(&mut contents).await.unwrap();
+        assert_eq!(contents, "closed");
+    }
+
+    #[tokio::test]
+    async fn test_buffered_data_is_written_out_on_drop_if_ready() {
+        let fs = mem_fs::FileSystem::default();
+        let mut file = BufferedWriteFile::new(open(&fs), 64);
+
+        file.seek(SeekFrom::Start(0)).await.unwrap();
+        file.write_all(b"not lost").await.unwrap();
+        assert_eq!(open(&fs).size(), 0);
+
+        drop(file);
+        let mut contents = String::new();
+        open(&fs).read_to_string(&mut contents).await.unwrap();
+        assert_eq!(contents, "not lost");
+    }
+}
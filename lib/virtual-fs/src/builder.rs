@@ -1,4 +1,5 @@
-use crate::random_file::RandomFile;
+use crate::full_file::FullFile;
+use crate::random_file::{RandomFile, RandomSource};
 use crate::{FileSystem, VirtualFile};
 use std::path::{Path, PathBuf};
 use tracing::*;
@@ -86,15 +87,7 @@ impl RootFileSystemBuilder {
                 .insert_device_file(PathBuf::from("/bin/wasmer"), Box::<NullFile>::default());
         }
         if self.default_dev_files {
-            let _ = tmp
-                .new_open_options_ext()
-                .insert_device_file(PathBuf::from("/dev/null"), Box::<NullFile>::default());
-            let _ = tmp
-                .new_open_options_ext()
-                .insert_device_file(PathBuf::from("/dev/zero"), Box::<ZeroFile>::default());
-            let _ = tmp
-                .new_open_options_ext()
-                .insert_device_file(PathBuf::from("/dev/urandom"), Box::<RandomFile>::default());
+            insert_default_devices(&tmp, Path::new("/dev"));
             let _ = tmp.new_open_options_ext().insert_device_file(
                 PathBuf::from("/dev/stdin"),
                 self.stdin
@@ -121,12 +114,104 @@ impl RootFileSystemBuilder {
     }
 }
 
+/// Adds the device files that behave the same for every process to `dir`
+/// (which is created if it is missing), normally this is `/dev`:
+///
+/// - `null` discards writes and is always at EOF
+/// - `zero` discards writes and reads an endless stream of zeros
+/// - `full` reads zeros but writes fail with `ENOSPC`
+/// - `random` and `urandom` read random bytes
+pub fn insert_default_devices(tmp: &TmpFileSystem, dir: &Path) {
+    if let Err(err) = tmp.create_dir(dir) {
+        trace!("failed to create dir [{}] - {}", dir.display(), err);
+    }
+
+    let devices: [(&str, Box<dyn VirtualFile + Send + Sync>); 5] = [
+        ("null", Box::<NullFile>::default()),
+        ("zero", Box::<ZeroFile>::default()),
+        ("full", Box::<FullFile>::default()),
+        ("random", Box::<RandomFile>::default()),
+        ("urandom", Box::<RandomFile>::default()),
+    ];
+    for (name, file) in devices {
+        let path = dir.join(name);
+        if let Err(err) = tmp
+            .new_open_options_ext()
+            .insert_device_file(path.clone(), file)
+        {
+            debug!(
+                "failed to create device file [{}] - {}",
+                path.display(),
+                err
+            );
+        }
+    }
+}
+
+/// Replaces the `random` and `urandom` device files in `dir` (if there are
+/// any) by ones that read their bytes from `source`
+pub fn bind_random_devices(tmp: &TmpFileSystem, dir: &Path, source: RandomSource) {
+    for name in ["random", "urandom"] {
+        let path = dir.join(name);
+        if tmp.metadata(&path).is_err() {
+            continue;
+        }
+        let file = Box::new(RandomFile::with_source(source.clone()));
+        if let Err(err) = tmp
+            .new_open_options_ext()
+            .insert_device_file(path.clone(), file)
+        {
+            debug!(
+                "failed to replace device file [{}] - {}",
+                path.display(),
+                err
+            );
+        }
+    }
+}
+
 #[cfg(test)]
 mod test_builder {
-    use crate::{FileSystem, RootFileSystemBuilder};
+    use crate::{FileSystem, RootFileSystemBuilder, bind_random_devices};
     use std::path::Path;
+    use std::sync::Arc;
     use tokio::io::{AsyncReadExt, AsyncWriteExt};
 
+    #[tokio::test]
+    async fn test_bound_random_devices_read_from_the_source() {
+        let root_fs = RootFileSystemBuilder::new().build();
+        bind_random_devices(
+            &root_fs,
+            Path::new("/dev"),
+            Arc::new(|buf: &mut [u8]| {
+                buf.fill(7);
+                Ok(())
+            }),
+        );
+        let mut dev_random = root_fs
+            .new_open_options()
+            .read(true)
+            .open("/dev/random")
+            .unwrap();
+        let mut buf = [0u8; 16];
+        dev_random.read_exact(&mut buf[..]).await.unwrap();
+        assert_eq!(buf, [7; 16]);
+
+        // A source that fails makes the reads fail instead of handing out
+        // bytes that are not random
+        bind_random_devices(
+            &root_fs,
+            Path::new("/dev"),
+            Arc::new(|_: &mut [u8]| Err(std::io::Error::other("no entropy"))),
+        );
+        let mut dev_urandom = root_fs
+            .new_open_options()
+            .read(true)
+            .open("/dev/urandom")
+            .unwrap();
+        assert!(dev_urandom.read(&mut buf[..]).await.is_err());
+    }
+
     #[tokio::test]
     async fn test_root_file_system() {
         let root_fs = RootFileSystemBuilder::new().build();
@@ -154,6 +239,27 @@ mod test_builder {
         assert_eq!(buf, vec![0; 10]);
         assert!(dev_zero.get_special_fd().is_none());
 
+        let mut dev_full = root_fs
+            .new_open_options()
+            .read(true)
+            .write(true)
+            .open("/dev/full")
+            .unwrap();
+        assert_eq!(
+            dev_full.write(b"hello").await.unwrap_err().kind(),
+            std::io::ErrorKind::StorageFull
+        );
+        let mut buf = vec![1; 10];
+        dev_full.read_exact(&mut buf[..]).await.unwrap();
+        assert_eq!(buf, vec![0; 10]);
+
+        for path in ["/dev/random", "/dev/urandom"] {
+            let mut dev_random = root_fs.new_open_options().read(true).open(path).unwrap();
+            let mut buf = [0u8; 64];
+            dev_random.read_exact(&mut buf[..]).await.unwrap();
+            assert!(buf.iter().any(|b| *b != 0));
+        }
+
         let mut dev_tty = root_fs
             .new_open_options()
             .read(true)
@@ -24,6 +24,8 @@ pub struct FileSystem {
     #[cfg_attr(feature = "enable-serde", serde(skip, default = "default_handle"))]
     handle: Handle,
     root: PathBuf,
+    #[cfg_attr(feature = "enable-serde", serde(default))]
+    write_buffer_size: Option<usize>,
 }
 
 #[allow(dead_code)]
@@ -71,7 +73,19 @@ impl FileSystem {
     pub fn new(handle: Handle, root: impl Into<PathBuf>) -> Result<Self> {
         let root = canonicalize(&root.into())?;
 
-        Ok(FileSystem { handle, root })
+        Ok(FileSystem {
+            handle,
+            root,
+            write_buffer_size: None,
+        })
+    }
+
+    /// Coalesces the writes to files that are opened for writing in a
+    /// buffer of `size` bytes, which saves a host syscall for every small
+    /// write (see [`BufferedWriteFile`](crate::BufferedWriteFile))
+    pub fn with_write_buffer_size(mut self, size: usize) -> Self {
+        self.write_buffer_size = Some(size).filter(|size| *size > 0);
+        self
     }
 }
 
@@ -324,14 +338,18 @@ impl crate::FileOpener for FileSystem {
             .open(&path)
             .map_err(Into::into)
             .map(|file| {
-                Box::new(File::new(
+                let file = Box::new(File::new(
                     self.handle.clone(),
                     file,
                     path.to_owned(),
                     read,
                     write,
                     append,
-                )) as Box<dyn VirtualFile + Send + Sync + 'static>
+                )) as Box<dyn VirtualFile + Send + Sync + 'static>;
+                match self.write_buffer_size {
+                    Some(size) if write => Box::new(crate::BufferedWriteFile::new(file, size)),
+                    _ => file,
+                }
             })
     }
 }
@@ -542,6 +560,67 @@ impl VirtualFile for File {
         None
     }
 
+    fn try_lock_host(&mut self) -> Result<()> {
+        self.inner_std.try_lock().map_err(|err| match err {
+            fs::TryLockError::WouldBlock => FsError::WouldBlock,
+            fs::TryLockError::Error(err) => err.into(),
+        })
+    }
+
+    #[cfg(target_os = "linux")]
+    fn copy_range_to(
+        &mut self,
+        src_offset: u64,
+        dst: &mut (dyn VirtualFile + Send + Sync),
+        dst_offset: u64,
+        len: u64,
+    ) -> Result<u64> {
+        use std::os::fd::AsRawFd;
+
+        let Some(dst) = dst.upcast_any_mut().downcast_mut::<File>() else {
+            return Err(FsError::Unsupported);
+        };
+
+        let mut src_offset =
+            libc::loff_t::try_from(src_offset).map_err(|_| FsError::InvalidInput)?;
+        let mut dst_offset =
+            libc::loff_t::try_from(dst_offset).map_err(|_| FsError::InvalidInput)?;
+        let mut copied = 0u64;
+        while copied < len {
+            let chunk = (len - copied).min(isize::MAX as u64) as usize;
+            let ret = unsafe {
+                libc::copy_file_range(
+                    self.inner_std.as_raw_fd(),
+                    &mut src_offset,
+                    dst.inner_std.as_raw_fd(),
+                    &mut dst_offset,
+                    chunk,
+                    0,
+                )
+            };
+            match ret {
+                // The end of the source file
+                0 => break,
+                ret if ret > 0 => copied += ret as u64,
+                _ => {
+                    let err = io::Error::last_os_error();
+                    return match err.raw_os_error() {
+                        // The kernel can't copy between these files, once
+                        // something was copied the rest fails the same way
+                        Some(libc::EXDEV | libc::ENOSYS | libc::EOPNOTSUPP | libc::EINVAL)
+                            if copied == 0 =>
+                        {
+                            Err(FsError::Unsupported)
+                        }
+                        _ if copied > 0 => Ok(copied),
+                        _ => Err(err.into()),
+                    };
+                }
+            }
+        }
+        Ok(copied)
+    }
+
     fn poll_read_ready(mut self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<usize>> {
         let cursor = match self.inner_std.stream_position() {
             Ok(a) => a,
@@ -1454,4 +1533,94 @@ mod tests {
             panic!("next: {s:?}");
         }
     }
+
+    #[tokio::test]
+    async fn test_write_buffer() {
+        use tokio::io::{AsyncSeekExt, AsyncWriteExt};
+
+        let temp = TempDir::new().unwrap();
+        let fs = FileSystem::new(Handle::current(), temp.path())
+            .expect("get filesystem")
+            .with_write_buffer_size(1024);
+
+        let mut file = fs
+            .new_open_options()
+            .write(true)
+            .create(true)
+            .open(Path::new("/a.txt"))
+            .unwrap();
+        for (n, line) in ["hello\n", "buffered\n", "world\n"].iter().enumerate() {
+            let offset = file.seek(std::io::SeekFrom::End(0)).await.unwrap();
+            assert_eq!(offset as usize, [0, 6, 15][n]);
+            file.write_all(line.as_bytes()).await.unwrap();
+        }
+        assert_eq!(file.size(), 21, "the size includes the buffered data");
+        assert_eq!(
+            std::fs::read(temp.path().join("a.txt")).unwrap(),
+            b"hello\nbuffered\n"
+        );
+
+        file.flush().await.unwrap();
+        assert_eq!(
+            std::fs::read_to_string(temp.path().join("a.txt")).unwrap(),
+            "hello\nbuffered\nworld\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_try_lock_host() {
+        let temp = TempDir::new().unwrap();
+        let fs = FileSystem::new(Handle::current(), temp.path()).expect("get filesystem");
+        let open = || {
+            fs.new_open_options()
+                .read(true)
+                .write(true)
+                .create(true)
+                .open(Path::new("/a.txt"))
+                .unwrap()
+        };
+
+        let mut first = open();
+        let mut second = open();
+        first.try_lock_host().unwrap();
+        assert_eq!(second.try_lock_host(), Err(FsError::WouldBlock));
+
+        // Closing the file releases the lock
+        drop(first);
+        second.try_lock_host().unwrap();
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_copy_range_to() {
+        let temp = TempDir::new().unwrap();
+        std::fs::write(temp.path().join("src.txt"), b"hello world").unwrap();
+        std::fs::write(temp.path().join("dst.txt"), b"0123456789").unwrap();
+        let fs = FileSystem::new(Handle::current(), temp.path()).expect("get filesystem");
+        let open = |path: &str| {
+            fs.new_open_options()
+                .read(true)
+                .write(true)
+                .open(Path::new(path))
+                .unwrap()
+        };
+
+        let mut src = open("/src.txt");
+        let mut dst = open("/dst.txt");
+        assert_eq!(src.copy_range_to(6, dst.as_mut(), 2, 5), Ok(5));
+
+        // The copy stops at the end of the source file
+        assert_eq!(src.copy_range_to(9, dst.as_mut(), 8, 100), Ok(2));
+        assert_eq!(
+            std::fs::read(temp.path().join("dst.txt")).unwrap(),
+            b"01world7ld"
+        );
+
+        // Only host files can be copied like this
+        let mut pipe = crate::Pipe::new();
+        assert_eq!(
+            src.copy_range_to(0, &mut pipe, 0, 5),
+            Err(FsError::Unsupported)
+        );
+    }
 }
@@ -21,18 +21,25 @@ pub mod arc_box_file;
 pub mod arc_file;
 pub mod arc_fs;
 pub mod buffer_file;
+pub mod buffered_write_file;
 pub mod builder;
 pub mod combine_file;
 pub mod cow_file;
 pub mod dual_write_file;
 pub mod empty_fs;
+pub mod full_file;
 #[cfg(feature = "host-fs")]
 pub mod host_fs;
+pub mod line_observer_file;
 pub mod mem_fs;
+pub mod merged_output_file;
 pub mod null_file;
 pub mod passthru_fs;
+pub mod pending_file;
 pub mod random_file;
 pub mod special_file;
+pub mod stream_backed_file;
+pub mod text_mode_file;
 pub mod tmp_fs;
 pub mod union_fs;
 pub mod zero_file;
@@ -54,18 +61,24 @@ pub use arc_box_file::*;
 pub use arc_file::*;
 pub use arc_fs::*;
 pub use buffer_file::*;
+pub use buffered_write_file::*;
 pub use builder::*;
 pub use combine_file::*;
 pub use cow_file::*;
 pub use dual_write_file::*;
 pub use empty_fs::*;
 pub use filesystems::FileSystems;
+pub use line_observer_file::*;
+pub use merged_output_file::*;
 pub use null_file::*;
 pub use overlay_fs::OverlayFileSystem;
 pub use passthru_fs::*;
+pub use pending_file::*;
 pub use pipe::*;
 pub use special_file::*;
 pub use static_file::StaticFile;
+pub use stream_backed_file::*;
+pub use text_mode_file::*;
 pub use tmp_fs::*;
 pub use trace_fs::TraceFileSystem;
 pub use union_fs::*;
@@ -379,6 +392,30 @@ pub trait VirtualFile:
         None
     }
 
+    /// Takes an exclusive advisory lock on the file that the other processes
+    /// of the host respect as well, it is released once the file is closed.
+    /// Returns [`FsError::WouldBlock`] when someone else holds the lock and
+    /// [`FsError::Unsupported`] for files that don't live on the host
+    fn try_lock_host(&mut self) -> Result<()> {
+        Err(FsError::Unsupported)
+    }
+
+    /// Copies `len` bytes from `src_offset` in this file to `dst_offset` in
+    /// `dst` without them passing through memory, which only works between
+    /// files on the host. Returns the number of bytes that were copied,
+    /// which is less than `len` when the end of this file is reached, and
+    /// [`FsError::Unsupported`] when the files can't be copied like this
+    /// (the caller copies them with reads and writes instead)
+    fn copy_range_to(
+        &mut self,
+        _src_offset: u64,
+        _dst: &mut (dyn VirtualFile + Send + Sync),
+        _dst_offset: u64,
+        _len: u64,
+    ) -> Result<u64> {
+        Err(FsError::Unsupported)
+    }
+
     /// Writes to this file using an mmap offset and reference
     /// (this method only works for mmap optimized file systems)
     fn write_from_mmap(&mut self, _offset: u64, _len: u64) -> std::io::Result<()> {
@@ -0,0 +1,198 @@
+use super::*;
+
+use crate::VirtualFile;
+
+/// Wraps a [`VirtualFile`] and delivers everything that is written to it,
+/// one line at a time, to a provided function.
+///
+/// Lines are passed on without their trailing newline. A partial line is
+/// held back until the newline arrives, or until the file is shut down or
+/// dropped (e.g. when the process exits), at which point it is delivered
+/// as is.
+#[derive(derive_more::Debug)]
+pub struct LineObserverFile {
+    inner: Box<dyn VirtualFile + Send + Sync + 'static>,
+    #[allow(clippy::type_complexity)]
+    #[debug(ignore)]
+    on_line: Box<dyn FnMut(&[u8]) + Send + Sync + 'static>,
+    partial: Vec<u8>,
+}
+
+impl LineObserverFile {
+    pub fn new(
+        inner: Box<dyn VirtualFile + Send + Sync + 'static>,
+        funct: impl FnMut(&[u8]) + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            inner,
+            on_line: Box::new(funct),
+            partial: Vec::new(),
+        }
+    }
+
+    fn observe(&mut self, mut data: &[u8]) {
+        while let Some(pos) = data.iter().position(|b| *b == b'\n') {
+            if self.partial.is_empty() {
+                (self.on_line)(&data[..pos]);
+            } else {
+                self.partial.extend_from_slice(&data[..pos]);
+                let line = std::mem::take(&mut self.partial);
+                (self.on_line)(&line);
+            }
+            data = &data[pos + 1..];
+        }
+        self.partial.extend_from_slice(data);
+    }
+
+    /// Delivers the partial line that is waiting for a newline, if any.
+    pub fn flush_partial_line(&mut self) {
+        if !self.partial.is_empty() {
+            let line = std::mem::take(&mut self.partial);
+            (self.on_line)(&line);
+        }
+    }
+}
+
+impl Drop for LineObserverFile {
+    fn drop(&mut self) {
+        self.flush_partial_line();
+    }
+}
+
+impl VirtualFile for LineObserverFile {
+    fn last_accessed(&self) -> u64 {
+        self.inner.last_accessed()
+    }
+
+    fn last_modified(&self) -> u64 {
+        self.inner.last_modified()
+    }
+
+    fn created_time(&self) -> u64 {
+        self.inner.created_time()
+    }
+
+    fn set_times(&mut self, atime: Option<u64>, mtime: Option<u64>) -> crate::Result<()> {
+        self.inner.set_times(atime, mtime)
+    }
+
+    fn size(&self) -> u64 {
+        self.inner.size()
+    }
+
+    fn set_len(&mut self, new_size: u64) -> crate::Result<()> {
+        self.inner.set_len(new_size)
+    }
+
+    fn unlink(&mut self) -> Result<()> {
+        self.inner.unlink()
+    }
+
+    fn poll_read_ready(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<usize>> {
+        Pin::new(self.inner.as_mut()).poll_read_ready(cx)
+    }
+
+    fn poll_write_ready(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<usize>> {
+        Pin::new(self.inner.as_mut()).poll_write_ready(cx)
+    }
+}
+
+impl AsyncWrite for LineObserverFile {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        match Pin::new(&mut self.inner).poll_write(cx, buf) {
+            Poll::Ready(Ok(amt)) => {
+                self.observe(&buf[..amt]);
+                Poll::Ready(Ok(amt))
+            }
+            res => res,
+        }
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        // Flushing doesn't end the line, the guest may still finish it later
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        self.flush_partial_line();
+        Pin::new(&mut self.inner).poll_shutdown(cx)
+    }
+}
+
+impl AsyncRead for LineObserverFile {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+        buf: &mut tokio::io::ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_read(cx, buf)
+    }
+}
+
+impl AsyncSeek for LineObserverFile {
+    fn start_seek(mut self: Pin<&mut Self>, position: io::SeekFrom) -> io::Result<()> {
+        Pin::new(&mut self.inner).start_seek(position)
+    }
+
+    fn poll_complete(
+        mut self: Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> Poll<io::Result<u64>> {
+        Pin::new(&mut self.inner).poll_complete(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use tokio::io::AsyncWriteExt;
+
+    use super::*;
+    use crate::NullFile;
+
+    fn collect_into(lines: &Arc<Mutex<Vec<String>>>) -> impl FnMut(&[u8]) + Send + Sync + 'static {
+        let lines = lines.clone();
+        move |line: &[u8]| {
+            lines
+                .lock()
+                .unwrap()
+                .push(String::from_utf8_lossy(line).into_owned())
+        }
+    }
+
+    #[tokio::test]
+    async fn complete_lines_are_delivered_as_they_are_written() {
+        let lines = Arc::new(Mutex::new(Vec::new()));
+        let mut file = LineObserverFile::new(Box::<NullFile>::default(), collect_into(&lines));
+
+        file.write_all(b"hello\nwor").await.unwrap();
+        assert_eq!(*lines.lock().unwrap(), vec!["hello"]);
+
+        // Flushing doesn't cut the partial line short
+        file.flush().await.unwrap();
+        assert_eq!(*lines.lock().unwrap(), vec!["hello"]);
+
+        file.write_all(b"ld\n\nlast").await.unwrap();
+        assert_eq!(*lines.lock().unwrap(), vec!["hello", "world", ""]);
+
+        // The trailing partial line is emitted once the file goes away
+        drop(file);
+        assert_eq!(*lines.lock().unwrap(), vec!["hello", "world", "", "last"]);
+    }
+
+    #[tokio::test]
+    async fn shutdown_emits_the_partial_line_once() {
+        let lines = Arc::new(Mutex::new(Vec::new()));
+        let mut file = LineObserverFile::new(Box::<NullFile>::default(), collect_into(&lines));
+
+        file.write_all(b"no newline").await.unwrap();
+        file.shutdown().await.unwrap();
+        drop(file);
+        assert_eq!(*lines.lock().unwrap(), vec!["no newline"]);
+    }
+}
@@ -13,6 +13,7 @@ use super::*;
 use crate::limiter::TrackedVec;
 use crate::{CopyOnWriteFile, FsError, Result, VirtualFile};
 use std::cmp;
+use std::collections::BTreeMap;
 use std::convert::TryInto;
 use std::fmt;
 use std::io;
@@ -202,8 +203,7 @@ impl VirtualFile for FileHandle {
         let inode = fs.storage.get_mut(self.inode);
         match inode {
             Some(Node::File(FileNode { file, metadata, .. })) => {
-                file.buffer
-                    .resize(new_size.try_into().map_err(|_| FsError::UnknownError)?, 0)?;
+                file.resize(new_size.try_into().map_err(|_| FsError::UnknownError)?)?;
                 metadata.len = new_size;
             }
             Some(Node::OffloadedFile(OffloadedFileNode { file, metadata, .. })) => {
@@ -394,7 +394,7 @@ impl VirtualFile for FileHandle {
         let inode = fs.storage.get_mut(self.inode);
         match inode {
             Some(Node::File(node)) => {
-                let remaining = node.file.buffer.len() - (self.cursor as usize);
+                let remaining = node.file.len().saturating_sub(self.cursor as usize);
                 Poll::Ready(Ok(remaining))
             }
             Some(Node::OffloadedFile(node)) => {
@@ -1031,7 +1031,7 @@ impl AsyncWrite for FileHandle {
                         .find(|b| !b.is_empty())
                         .map_or(&[][..], |b| &**b);
                     let bytes_written = node.file.write(buf, &mut cursor)?;
-                    node.metadata.len = node.file.buffer.len() as u64;
+                    node.metadata.len = node.file.len() as u64;
                     Poll::Ready(Ok(bytes_written))
                 }
                 Some(Node::OffloadedFile(node)) => {
@@ -1315,6 +1315,55 @@ mod test_read_write_seek {
         assert_contents(&file, b"helloBOOMrld.goodOUCH!");
     }
 
+    #[test]
+    pub fn writing_beyond_the_end_leaves_a_hole() {
+        const GB: u64 = 1024 * 1024 * 1024;
+
+        let mut file = File::new(None);
+
+        let mut cursor = 0;
+        file.seek(io::SeekFrom::Start(GB), &mut cursor).unwrap();
+        assert_eq!(cursor, GB);
+        file.write(b"x", &mut cursor).unwrap();
+        assert_eq!(file.len() as u64, GB + 1);
+
+        // Only the chunk that was written to is allocated
+        assert!(file.allocated() <= 64 * 1024);
+
+        // The hole reads as zeros
+        let mut buf = [0xff; 4];
+        cursor = GB - 3;
+        assert_eq!(file.read(&mut buf, &mut cursor).unwrap(), 4);
+        assert_eq!(&buf, b"\0\0\0x");
+
+        // Shrinking drops the data, growing again only adds a hole
+        file.resize(GB as usize).unwrap();
+        file.resize(GB as usize + 1).unwrap();
+        assert_eq!(file.allocated(), 0);
+        cursor = GB;
+        assert_eq!(file.read(&mut buf, &mut cursor).unwrap(), 1);
+        assert_eq!(buf[0], 0);
+    }
+
+    #[tokio::test]
+    async fn test_size_of_a_sparse_file() {
+        let fs = FileSystem::default();
+
+        let mut file = fs
+            .new_open_options()
+            .read(true)
+            .write(true)
+            .create_new(true)
+            .open(path!("/foo.txt"))
+            .expect("failed to create a new file");
+
+        file.seek(io::SeekFrom::Start(1 << 30)).await.unwrap();
+        file.write_all(b"x").await.unwrap();
+
+        assert_eq!(file.size(), (1 << 30) + 1);
+        assert_eq!(fs.metadata(path!("/foo.txt")).unwrap().len(), (1 << 30) + 1);
+    }
+
     #[tokio::test]
     async fn test_reading() {
         let fs = FileSystem::default();
@@ -1477,38 +1526,92 @@ impl fmt::Debug for FileHandle {
     }
 }
 
+/// Size of the chunks the data of a [`File`] is stored in.
+const CHUNK_SIZE: usize = 64 * 1024;
+
 /// The real file! It is simply a buffer of bytes with a cursor that
 /// represents a read/write position in the buffer.
+///
+/// The data is stored sparsely, in chunks that are only allocated once
+/// they are written to. The holes (the parts of the file that were never
+/// written, for instance after seeking beyond the end) read as zeros and
+/// don't use any memory.
 #[derive(Debug)]
 pub(super) struct File {
-    buffer: TrackedVec,
+    /// The chunks that hold data, by index. A chunk may be shorter than
+    /// [`CHUNK_SIZE`], the rest of it is a hole.
+    chunks: BTreeMap<usize, TrackedVec>,
+    /// The logical size of the file
+    len: usize,
+    limiter: Option<crate::limiter::DynFsMemoryLimiter>,
 }
 
 impl File {
     pub(super) fn new(limiter: Option<crate::limiter::DynFsMemoryLimiter>) -> Self {
         Self {
-            buffer: TrackedVec::new(limiter),
+            chunks: BTreeMap::new(),
+            len: 0,
+            limiter,
         }
     }
 
     pub(super) fn truncate(&mut self) {
-        self.buffer.clear();
+        self.chunks.clear();
+        self.len = 0;
     }
 
     pub(super) fn len(&self) -> usize {
-        self.buffer.len()
+        self.len
+    }
+
+    /// Changes the logical size of the file, growing it only adds a hole.
+    pub(super) fn resize(&mut self, new_len: usize) -> Result<()> {
+        if new_len < self.len {
+            let last = new_len.div_ceil(CHUNK_SIZE);
+            self.chunks.split_off(&last);
+            let offset = new_len % CHUNK_SIZE;
+            if offset != 0
+                && let Some(chunk) = self.chunks.get_mut(&(new_len / CHUNK_SIZE))
+                && chunk.len() > offset
+            {
+                chunk.resize(offset, 0)?;
+            }
+        }
+        self.len = new_len;
+        Ok(())
+    }
+
+    /// The number of bytes that are allocated to hold the data of the file.
+    #[cfg(test)]
+    pub(super) fn allocated(&self) -> usize {
+        self.chunks.values().map(|chunk| chunk.len()).sum()
     }
 }
 
 impl File {
     pub fn read(&self, buf: &mut [u8], cursor: &mut u64) -> io::Result<usize> {
         let cur_pos = *cursor as usize;
-        let max_to_read = cmp::min(self.buffer.len() - cur_pos, buf.len());
-        let data_to_copy = &self.buffer[cur_pos..][..max_to_read];
+        let max_to_read = cmp::min(self.len.saturating_sub(cur_pos), buf.len());
+
+        let mut read = 0;
+        while read < max_to_read {
+            let position = cur_pos + read;
+            let offset = position % CHUNK_SIZE;
+            let amt = cmp::min(CHUNK_SIZE - offset, max_to_read - read);
+            let dst = &mut buf[read..read + amt];
+
+            // What the chunk doesn't hold is a hole.
+            let data = match self.chunks.get(&(position / CHUNK_SIZE)) {
+                Some(chunk) if chunk.len() > offset => {
+                    &chunk[offset..cmp::min(chunk.len(), offset + amt)]
+                }
+                _ => &[][..],
+            };
+            dst[..data.len()].copy_from_slice(data);
+            dst[data.len()..].fill(0);
 
-        // SAFETY: `buf[..max_to_read]` and `data_to_copy` have the same size, due to
-        // how `max_to_read` is computed.
-        buf[..max_to_read].copy_from_slice(data_to_copy);
+            read += amt;
+        }
 
         *cursor += max_to_read as u64;
 
@@ -1525,9 +1628,9 @@ impl File {
             // Calculate from the beginning, so `0 + offset`.
             io::SeekFrom::Start(offset) => offset.try_into().map_err(to_err)?,
 
-            // Calculate from the end, so `buffer.len() + offset`.
+            // Calculate from the end, so `len + offset`.
             io::SeekFrom::End(offset) => {
-                TryInto::<i64>::try_into(self.buffer.len()).map_err(to_err)? + offset
+                TryInto::<i64>::try_into(self.len).map_err(to_err)? + offset
             }
 
             // Calculate from the current cursor, so `cursor + offset`.
@@ -1544,10 +1647,9 @@ impl File {
             ));
         }
 
-        // In this implementation, it's an error to seek beyond the
-        // end of the buffer.
-        let next_cursor = next_cursor.try_into().map_err(to_err)?;
-        *cursor = cmp::min(self.buffer.len() as u64, next_cursor);
+        // Seeking beyond the end is fine, writing there leaves a hole
+        // between the end and the cursor.
+        *cursor = next_cursor.try_into().map_err(to_err)?;
 
         let cursor = *cursor;
         Ok(cursor)
@@ -1556,17 +1658,31 @@ impl File {
 
 impl File {
     pub fn write(&mut self, buf: &[u8], cursor: &mut u64) -> io::Result<usize> {
-        let position = *cursor as usize;
-
-        if position + buf.len() > self.buffer.len() {
-            // Writing past the end of the current buffer, must reallocate
-            let len_after_end = (position + buf.len()) - self.buffer.len();
-            let let_to_end = buf.len() - len_after_end;
-            self.buffer[position..position + let_to_end].copy_from_slice(&buf[0..let_to_end]);
-            self.buffer.extend_from_slice(&buf[let_to_end..buf.len()])?;
-        } else {
-            self.buffer[position..position + buf.len()].copy_from_slice(buf);
+        let position: usize = (*cursor)
+            .try_into()
+            .map_err(|_| io::ErrorKind::InvalidInput)?;
+        let end = position
+            .checked_add(buf.len())
+            .ok_or(io::ErrorKind::InvalidInput)?;
+
+        let mut written = 0;
+        while written < buf.len() {
+            let offset = (position + written) % CHUNK_SIZE;
+            let amt = cmp::min(CHUNK_SIZE - offset, buf.len() - written);
+
+            let chunk = self
+                .chunks
+                .entry((position + written) / CHUNK_SIZE)
+                .or_insert_with(|| TrackedVec::new(self.limiter.clone()));
+            if chunk.len() < offset + amt {
+                // Writing past the end of the chunk, must reallocate
+                chunk.resize(offset + amt, 0)?;
+            }
+            chunk[offset..offset + amt].copy_from_slice(&buf[written..written + amt]);
+
+            written += amt;
         }
+        self.len = cmp::max(self.len, end);
 
         *cursor += buf.len() as u64;
 
@@ -312,6 +312,42 @@ impl FileSystem {
         fs.add_child_to_node(inode_of_parent, inode_of_symlink)?;
         Ok(())
     }
+
+    /// Changes the access and modification times of the file or directory
+    /// at `path`, the ones that are `None` are left as they are. Unlike
+    /// [`VirtualFile::set_times`](crate::VirtualFile::set_times) this covers
+    /// directories, which can't be opened.
+    pub fn set_times(&self, path: &Path, atime: Option<u64>, mtime: Option<u64>) -> Result<()> {
+        let mut guard = self.inner.write().map_err(|_| FsError::Lock)?;
+        match guard.inode_of(path)? {
+            InodeResolution::Found(inode) => {
+                let metadata = guard
+                    .storage
+                    .get_mut(inode)
+                    .ok_or(FsError::UnknownError)?
+                    .metadata_mut();
+                if let Some(atime) = atime {
+                    metadata.accessed = atime;
+                }
+                if let Some(mtime) = mtime {
+                    metadata.modified = mtime;
+                }
+                Ok(())
+            }
+            InodeResolution::Redirect(fs, path) => {
+                drop(guard);
+                let fs_ref: &dyn crate::FileSystem = fs.as_ref();
+                if let Some(mem_fs) = fs_ref.downcast_ref::<Self>() {
+                    return mem_fs.set_times(path.as_path(), atime, mtime);
+                }
+                if let Some(tmp_fs) = fs_ref.downcast_ref::<crate::tmp_fs::TmpFileSystem>() {
+                    return tmp_fs.set_times(path.as_path(), atime, mtime);
+                }
+                let mut file = fs.new_open_options().read(true).open(path)?;
+                file.set_times(atime, mtime)
+            }
+        }
+    }
 }
 
 impl crate::FileSystem for FileSystem {
@@ -1734,6 +1770,27 @@ mod test_filesystem {
         );
     }
 
+    #[tokio::test]
+    async fn test_set_times() {
+        let fs = FileSystem::default();
+        assert_eq!(fs.create_dir(path!("/foo")), Ok(()));
+        let before = fs.metadata(path!("/foo")).unwrap();
+
+        assert_eq!(fs.set_times(path!("/foo"), Some(1000), None), Ok(()));
+        let after = fs.metadata(path!("/foo")).unwrap();
+        assert_eq!(after.accessed, 1000);
+        assert_eq!(after.modified, before.modified);
+
+        assert_eq!(fs.set_times(path!("/foo"), None, Some(2000)), Ok(()));
+        let after = fs.metadata(path!("/foo")).unwrap();
+        assert_eq!((after.accessed, after.modified), (1000, 2000));
+
+        assert_eq!(
+            fs.set_times(path!("/bar"), Some(1000), None),
+            Err(FsError::EntryNotFound)
+        );
+    }
+
     #[tokio::test]
     async fn test_remove_file() {
         let fs = FileSystem::default();
@@ -0,0 +1,163 @@
+use std::sync::{Arc, Mutex};
+
+use super::*;
+
+use crate::VirtualFile;
+
+/// One of the streams (e.g. `stdout` and `stderr`) that are merged into a
+/// single sink by [`MergedOutputFile::pair`].
+///
+/// Every write is passed on to the sink while it is locked, so the sink
+/// receives the writes of both streams in the order in which they were
+/// made and a write is never split up by a write to the other stream. The
+/// sink is only flushed when one of the streams is shut down, it stays
+/// open for the other one.
+#[derive(Debug, Clone)]
+pub struct MergedOutputFile {
+    sink: Arc<Mutex<MergedOutput>>,
+}
+
+#[derive(Debug)]
+struct MergedOutput {
+    sink: Box<dyn VirtualFile + Send + Sync + 'static>,
+    /// The number of writes that reached the sink so far
+    sequence: u64,
+}
+
+impl MergedOutputFile {
+    /// Creates the two streams that are merged into `sink`
+    pub fn pair(sink: Box<dyn VirtualFile + Send + Sync + 'static>) -> (Self, Self) {
+        let merged = Self {
+            sink: Arc::new(Mutex::new(MergedOutput { sink, sequence: 0 })),
+        };
+        (merged.clone(), merged)
+    }
+
+    /// The number of writes of both streams that reached the sink so far,
+    /// which is the sequence number of the next write
+    pub fn sequence(&self) -> u64 {
+        self.sink.lock().unwrap().sequence
+    }
+}
+
+impl VirtualFile for MergedOutputFile {
+    fn last_accessed(&self) -> u64 {
+        self.sink.lock().unwrap().sink.last_accessed()
+    }
+
+    fn last_modified(&self) -> u64 {
+        self.sink.lock().unwrap().sink.last_modified()
+    }
+
+    fn created_time(&self) -> u64 {
+        self.sink.lock().unwrap().sink.created_time()
+    }
+
+    fn set_times(&mut self, atime: Option<u64>, mtime: Option<u64>) -> crate::Result<()> {
+        self.sink.lock().unwrap().sink.set_times(atime, mtime)
+    }
+
+    fn size(&self) -> u64 {
+        self.sink.lock().unwrap().sink.size()
+    }
+
+    fn set_len(&mut self, new_size: u64) -> crate::Result<()> {
+        self.sink.lock().unwrap().sink.set_len(new_size)
+    }
+
+    fn unlink(&mut self) -> Result<()> {
+        self.sink.lock().unwrap().sink.unlink()
+    }
+
+    fn poll_read_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<usize>> {
+        let mut merged = self.sink.lock().unwrap();
+        Pin::new(merged.sink.as_mut()).poll_read_ready(cx)
+    }
+
+    fn poll_write_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<usize>> {
+        let mut merged = self.sink.lock().unwrap();
+        Pin::new(merged.sink.as_mut()).poll_write_ready(cx)
+    }
+}
+
+impl AsyncWrite for MergedOutputFile {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        let mut merged = self.sink.lock().unwrap();
+        let res = Pin::new(merged.sink.as_mut()).poll_write(cx, buf);
+        if let Poll::Ready(Ok(amt)) = res
+            && amt > 0
+        {
+            merged.sequence += 1;
+        }
+        res
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        let mut merged = self.sink.lock().unwrap();
+        Pin::new(merged.sink.as_mut()).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        // The other stream may still write to the sink
+        let mut merged = self.sink.lock().unwrap();
+        Pin::new(merged.sink.as_mut()).poll_flush(cx)
+    }
+}
+
+impl AsyncRead for MergedOutputFile {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+        buf: &mut tokio::io::ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let mut merged = self.sink.lock().unwrap();
+        Pin::new(merged.sink.as_mut()).poll_read(cx, buf)
+    }
+}
+
+impl AsyncSeek for MergedOutputFile {
+    fn start_seek(self: Pin<&mut Self>, position: io::SeekFrom) -> io::Result<()> {
+        let mut merged = self.sink.lock().unwrap();
+        Pin::new(merged.sink.as_mut()).start_seek(position)
+    }
+
+    fn poll_complete(
+        self: Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> Poll<io::Result<u64>> {
+        let mut merged = self.sink.lock().unwrap();
+        Pin::new(merged.sink.as_mut()).poll_complete(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    use super::*;
+    use crate::Pipe;
+
+    #[tokio::test]
+    async fn writes_of_both_streams_keep_their_order() {
+        let (tx, mut rx) = Pipe::channel();
+        let (mut stdout, mut stderr) = MergedOutputFile::pair(Box::new(tx));
+
+        stdout.write_all(b"out 1\n").await.unwrap();
+        stderr.write_all(b"err 1\n").await.unwrap();
+        stdout.write_all(b"out 2\n").await.unwrap();
+        assert_eq!(stderr.sequence(), 3);
+
+        // Shutting one of the streams down leaves the other one working
+        stdout.shutdown().await.unwrap();
+        stderr.write_all(b"err 2\n").await.unwrap();
+        drop((stdout, stderr));
+
+        let mut output = String::new();
+        rx.read_to_string(&mut output).await.unwrap();
+        assert_eq!(output, "out 1\nerr 1\nout 2\nerr 2\n");
+    }
+}
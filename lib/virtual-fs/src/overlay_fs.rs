@@ -509,6 +509,12 @@ where
                 sub_conf.create_new = false;
                 sub_conf.append = false;
                 sub_conf.truncate = false;
+                if require_mutations {
+                    // The secondary is never written to, it only needs to be
+                    // readable so that it can be copied to the primary
+                    sub_conf.read = true;
+                    sub_conf.write = false;
+                }
                 match fs.new_open_options().options(sub_conf.clone()).open(path) {
                     Err(e) if should_continue(e) => continue,
                     Ok(file) if require_mutations => {
@@ -1617,6 +1623,44 @@ mod tests {
         assert!(!ops::is_file(&fs.primary, "/secondary/file.txt"));
     }
 
+    /// Files that are opened without the read permission still need to be
+    /// copied to the primary before they are modified
+    #[tokio::test]
+    async fn test_overlayfs_write_only_files_are_copied() {
+        let primary = MemFS::default();
+        let secondary = MemFS::default();
+        ops::create_dir_all(&secondary, "/secondary").unwrap();
+        ops::write(&secondary, "/secondary/file.txt", b"Hello")
+            .await
+            .unwrap();
+
+        let fs = OverlayFileSystem::new(primary, [secondary]);
+
+        {
+            let mut f = fs
+                .new_open_options()
+                .write(true)
+                .append(true)
+                .open(Path::new("/secondary/file.txt"))
+                .unwrap();
+            f.write_all(b", World!").await.unwrap();
+            f.flush().await.unwrap();
+        }
+
+        assert_eq!(
+            ops::read_to_string(&fs, "/secondary/file.txt")
+                .await
+                .unwrap(),
+            "Hello, World!"
+        );
+        assert_eq!(
+            ops::read_to_string(&fs.secondaries[0], "/secondary/file.txt")
+                .await
+                .unwrap(),
+            "Hello"
+        );
+    }
+
     // OLD tests that used WebcFileSystem.
     // Should be re-implemented with WebcVolumeFs
     // #[tokio::test]
@@ -4,6 +4,8 @@ use std::sync::Arc;
 use std::sync::Mutex;
 use std::task::Context;
 use std::task::Poll;
+use std::task::Waker;
+use std::task::ready;
 use std::{io::IoSlice, sync::MutexGuard};
 use std::{
     io::{self, Read, Seek, SeekFrom},
@@ -36,6 +38,67 @@ pub struct PipeTx {
     /// Sends bytes down the pipe
     tx: Option<mpsc::UnboundedSender<Vec<u8>>>,
     rx_end: Weak<Mutex<PipeReceiver>>,
+    /// Limits how much data can be in the pipe (if it is bounded)
+    capacity: Option<Arc<PipeCapacity>>,
+    /// Every write is a datagram that is sent whole or not at all
+    datagram: bool,
+}
+
+/// Keeps track of the data that was written to a bounded pipe but not yet
+/// read, writers wait while the pipe is full
+#[derive(Debug)]
+struct PipeCapacity {
+    limit: usize,
+    state: Mutex<PipeCapacityState>,
+}
+
+#[derive(Debug, Default)]
+struct PipeCapacityState {
+    buffered: usize,
+    /// Writers that wait for the pipe to have room again
+    wakers: Vec<Waker>,
+}
+
+impl PipeCapacity {
+    /// Returns how many bytes can be written right now, when the pipe is
+    /// full the waker is woken up once some data was read
+    fn poll_free(&self, cx: &mut Context<'_>) -> Poll<usize> {
+        self.poll_room(cx, 1)
+    }
+
+    /// Same as [`PipeCapacity::poll_free`] but waits until there is room
+    /// for (at least) `needed` bytes
+    fn poll_room(&self, cx: &mut Context<'_>, needed: usize) -> Poll<usize> {
+        let mut state = self.state.lock().unwrap();
+        let free = self.limit.saturating_sub(state.buffered);
+        if free >= needed {
+            return Poll::Ready(free);
+        }
+        if !state.wakers.iter().any(|w| w.will_wake(cx.waker())) {
+            state.wakers.push(cx.waker().clone());
+        }
+        Poll::Pending
+    }
+
+    fn free(&self) -> usize {
+        let state = self.state.lock().unwrap();
+        self.limit.saturating_sub(state.buffered)
+    }
+
+    fn written(&self, amt: usize) {
+        self.state.lock().unwrap().buffered += amt;
+    }
+
+    fn consumed(&self, amt: usize) {
+        if amt == 0 {
+            return;
+        }
+        let mut state = self.state.lock().unwrap();
+        state.buffered = state.buffered.saturating_sub(amt);
+        for waker in state.wakers.drain(..) {
+            waker.wake();
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -53,25 +116,47 @@ impl PipeRx {
         // Should return how much actual data was read from the provided slice
         write: impl FnOnce(&[u8]) -> Option<usize>,
     ) -> Option<usize> {
-        rx.buffer.as_mut().and_then(|read_buffer| {
-            let buf_len = read_buffer.len();
-            if buf_len > 0 {
-                let mut read = buf_len.min(max_len);
-                let inner_buf = &read_buffer[..read];
-                // read = ?;
-                read = write(inner_buf)?;
-                read_buffer.advance(read);
-                Some(read)
-            } else {
-                None
-            }
-        })
+        let datagram = rx.datagram;
+        let read_buffer = rx.buffer.as_mut()?;
+        let buf_len = read_buffer.len();
+        if buf_len == 0 {
+            return None;
+        }
+        let read = buf_len.min(max_len);
+        let read = write(&read_buffer[..read])?;
+        read_buffer.advance(read);
+        // A datagram is read in one go, whatever didn't fit is lost
+        if datagram {
+            rx.buffer.take();
+        }
+        rx.consumed += if datagram { buf_len } else { read } as u64;
+        if let Some(capacity) = rx.capacity.as_ref() {
+            capacity.consumed(if datagram { buf_len } else { read });
+        }
+        Some(read)
     }
 
     pub fn close(&mut self) {
         _ = self.rx.take();
     }
 
+    /// Returns true if the pipe keeps the boundaries between writes (see
+    /// [`Pipe::new_datagram`])
+    pub fn is_datagram(&self) -> bool {
+        self.rx
+            .as_ref()
+            .is_some_and(|rx| rx.lock().unwrap().datagram)
+    }
+
+    /// The number of bytes that were read from the pipe so far, including
+    /// the parts of datagrams that were discarded
+    pub fn bytes_read(&self) -> u64 {
+        self.rx
+            .as_ref()
+            .map(|rx| rx.lock().unwrap().consumed)
+            .unwrap_or_default()
+    }
+
     pub fn try_read(&mut self, buf: &mut [u8]) -> Option<usize> {
         let Some(ref mut rx) = self.rx else {
             return Some(0);
@@ -100,6 +185,45 @@ impl PipeRx {
         }
     }
 
+    /// The number of bytes that can be read without blocking, this pulls
+    /// everything that was sent so far into the read buffer.
+    pub fn bytes_available(&self) -> usize {
+        let Some(ref rx) = self.rx else {
+            return 0;
+        };
+
+        let mut rx = rx.lock().unwrap();
+        if rx.datagram {
+            // Only the next datagram can be read in one go
+            if rx.buffer.as_ref().is_none_or(|buffer| buffer.is_empty())
+                && let Ok(data) = rx.chan.try_recv()
+            {
+                rx.buffer.replace(Bytes::from(data));
+            }
+            return rx
+                .buffer
+                .as_ref()
+                .map(|buffer| buffer.len())
+                .unwrap_or_default();
+        }
+        let mut pending = Vec::new();
+        while let Ok(data) = rx.chan.try_recv() {
+            if pending.is_empty()
+                && let Some(buffer) = rx.buffer.take()
+            {
+                pending.extend_from_slice(&buffer);
+            }
+            pending.extend_from_slice(&data);
+        }
+        if !pending.is_empty() {
+            rx.buffer.replace(Bytes::from(pending));
+        }
+        rx.buffer
+            .as_ref()
+            .map(|buffer| buffer.len())
+            .unwrap_or_default()
+    }
+
     pub fn poll_read_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<usize>> {
         let Some(ref rx) = self.rx else {
             return Poll::Ready(Err(std::io::Error::new(
@@ -130,11 +254,16 @@ impl PipeRx {
         }
     }
 
-    pub fn set_interest_handler(&self, interest_handler: Box<dyn InterestHandler>) {
+    pub fn set_interest_handler(&self, mut interest_handler: Box<dyn InterestHandler>) {
         let Some(ref rx) = self.rx else {
             return;
         };
         let mut rx = rx.lock().unwrap();
+        // The writers may already be gone, in which case the hangup has to
+        // be reported right away as there is nothing left to wait for
+        if rx.chan.is_closed() {
+            interest_handler.push_interest(InterestType::Closed);
+        }
         rx.interest_handler.replace(interest_handler);
     }
 
@@ -152,29 +281,110 @@ struct PipeReceiver {
     chan: mpsc::UnboundedReceiver<Vec<u8>>,
     buffer: Option<Bytes>,
     interest_handler: Option<Box<dyn InterestHandler>>,
+    /// Every write is a message of its own that is read in one go
+    datagram: bool,
+    /// Shared with the writers of a bounded pipe
+    capacity: Option<Arc<PipeCapacity>>,
+    /// The number of bytes that were written to the pipe so far
+    written: u64,
+    /// The number of bytes that were read from the pipe so far
+    consumed: u64,
+}
+
+impl Drop for PipeReceiver {
+    fn drop(&mut self) {
+        // Nothing will be read anymore, the writers find out that the pipe
+        // is closed once they wake up
+        if let Some(capacity) = self.capacity.as_ref() {
+            capacity.consumed(usize::MAX);
+        }
+    }
 }
 
 impl Pipe {
     pub fn new() -> Self {
+        Self::with_datagrams(false)
+    }
+
+    /// Creates a pipe that holds at most `limit` bytes that were written but
+    /// not read yet, writers wait (and report that they are not ready) while
+    /// the pipe is full
+    pub fn with_capacity(limit: usize) -> Self {
+        Self::build(
+            false,
+            Some(Arc::new(PipeCapacity {
+                limit: limit.max(1),
+                state: Default::default(),
+            })),
+        )
+    }
+
+    /// Creates a pipe that keeps the boundaries between writes, every read
+    /// returns (at most) the data of a single write and discards whatever
+    /// part of it didn't fit in the read buffer, like a datagram socket
+    pub fn new_datagram() -> Self {
+        Self::with_datagrams(true)
+    }
+
+    /// Same as [`Pipe::new_datagram`] but the pipe holds at most `limit`
+    /// bytes that were written but not read yet, a datagram is only written
+    /// once there is room for all of it (see [`Pipe::with_capacity`])
+    pub fn datagram_with_capacity(limit: usize) -> Self {
+        Self::build(
+            true,
+            Some(Arc::new(PipeCapacity {
+                limit: limit.max(1),
+                state: Default::default(),
+            })),
+        )
+    }
+
+    fn with_datagrams(datagram: bool) -> Self {
+        Self::build(datagram, None)
+    }
+
+    fn build(datagram: bool, capacity: Option<Arc<PipeCapacity>>) -> Self {
         let (tx, rx) = mpsc::unbounded_channel();
 
         let recv = Arc::new(Mutex::new(PipeReceiver {
             chan: rx,
             buffer: None,
             interest_handler: None,
+            datagram,
+            capacity: capacity.clone(),
+            written: 0,
+            consumed: 0,
         }));
         Pipe {
             send: PipeTx {
                 tx: Some(tx),
                 rx_end: Arc::downgrade(&recv),
+                capacity,
+                datagram,
             },
             recv: PipeRx { rx: Some(recv) },
         }
     }
 
     pub fn channel() -> (Pipe, Pipe) {
-        let (tx1, rx1) = Pipe::new().split();
-        let (tx2, rx2) = Pipe::new().split();
+        Self::channel_of(Pipe::new(), Pipe::new())
+    }
+
+    /// Same as [`Pipe::channel`] but both directions hold at most `limit`
+    /// bytes (see [`Pipe::with_capacity`])
+    pub fn channel_with_capacity(limit: usize) -> (Pipe, Pipe) {
+        Self::channel_of(Pipe::with_capacity(limit), Pipe::with_capacity(limit))
+    }
+
+    /// Same as [`Pipe::channel`] but for pipes that keep the boundaries
+    /// between writes (see [`Pipe::new_datagram`])
+    pub fn datagram_channel() -> (Pipe, Pipe) {
+        Self::channel_of(Pipe::new_datagram(), Pipe::new_datagram())
+    }
+
+    fn channel_of(pipe1: Pipe, pipe2: Pipe) -> (Pipe, Pipe) {
+        let (tx1, rx1) = pipe1.split();
+        let (tx2, rx2) = pipe2.split();
 
         let end1 = Pipe::combine(tx1, rx2);
         let end2 = Pipe::combine(tx2, rx1);
@@ -193,6 +403,28 @@ impl Pipe {
         self.recv.try_read(buf)
     }
 
+    pub fn bytes_available(&self) -> usize {
+        self.recv.bytes_available()
+    }
+
+    /// Returns true if the pipe keeps the boundaries between writes (see
+    /// [`Pipe::new_datagram`])
+    pub fn is_datagram(&self) -> bool {
+        self.recv.is_datagram()
+    }
+
+    /// The number of bytes that were written to the other end so far, see
+    /// [`PipeTx::bytes_written`]
+    pub fn bytes_written(&self) -> u64 {
+        self.send.bytes_written()
+    }
+
+    /// The number of bytes that were read from this end so far, see
+    /// [`PipeRx::bytes_read`]
+    pub fn bytes_read(&self) -> u64 {
+        self.recv.bytes_read()
+    }
+
     pub fn close(&mut self) {
         self.send.close();
         self.recv.close();
@@ -215,10 +447,12 @@ impl Default for Pipe {
 
 impl PipeTx {
     pub fn close(&mut self) {
-        _ = self.tx.take();
+        if self.tx.take().is_some() {
+            self.mark_other_end_hung_up();
+        }
     }
 
-    pub fn poll_write_ready(self: Pin<&mut Self>) -> Poll<io::Result<usize>> {
+    pub fn poll_write_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<usize>> {
         let Some(ref tx) = self.tx else {
             return Poll::Ready(Err(std::io::Error::new(
                 std::io::ErrorKind::BrokenPipe,
@@ -227,19 +461,94 @@ impl PipeTx {
         };
 
         if tx.is_closed() {
-            Poll::Ready(Ok(0))
-        } else {
-            Poll::Ready(Ok(8192))
+            return Poll::Ready(Ok(0));
+        }
+        match self.capacity.as_ref() {
+            Some(capacity) => capacity.poll_free(cx).map(Ok),
+            None => Poll::Ready(Ok(8192)),
         }
     }
 
-    fn mark_other_end_readable(&self) {
-        if let Some(rx_end) = self.rx_end.upgrade() {
-            let mut guard = rx_end.lock().unwrap();
+    /// The number of bytes that can be written right now without waiting,
+    /// this is `None` for a pipe that is not bounded
+    pub fn free_capacity(&self) -> Option<usize> {
+        self.capacity.as_ref().map(|capacity| capacity.free())
+    }
+
+    /// The number of bytes that were written to the pipe so far (by any of
+    /// the writers), which is where the next write starts in the stream
+    pub fn bytes_written(&self) -> u64 {
+        self.rx_end
+            .upgrade()
+            .map(|rx_end| rx_end.lock().unwrap().written)
+            .unwrap_or_default()
+    }
+
+    /// Sends the data down the pipe and returns how much of it was sent,
+    /// a bounded pipe only takes what fits (and a datagram is sent whole or
+    /// not at all)
+    fn send(&self, buf: &[u8]) -> io::Result<usize> {
+        let Some(ref tx) = self.tx else {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::BrokenPipe,
+                "PipeTx is closed",
+            ));
+        };
+
+        let amt = match self.capacity.as_ref() {
+            Some(capacity) if self.datagram => {
+                if buf.len() > capacity.limit {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        "the datagram is larger than the pipe",
+                    ));
+                }
+                if buf.len() > capacity.free() {
+                    return Err(io::ErrorKind::WouldBlock.into());
+                }
+                buf.len()
+            }
+            Some(capacity) => buf.len().min(capacity.free()),
+            None => buf.len(),
+        };
+        if amt == 0 && !buf.is_empty() {
+            return Err(io::ErrorKind::WouldBlock.into());
+        }
+        // The data is counted as it is sent so that the writers agree on
+        // where each write starts in the stream
+        let rx_end = self.rx_end.upgrade();
+        let mut guard = rx_end.as_ref().map(|rx_end| rx_end.lock().unwrap());
+        tx.send(buf[..amt].to_vec())
+            .map_err(|_| Into::<std::io::Error>::into(std::io::ErrorKind::BrokenPipe))?;
+        if let Some(capacity) = self.capacity.as_ref() {
+            capacity.written(amt);
+        }
+        if let Some(guard) = guard.as_mut() {
+            guard.written += amt as u64;
             if let Some(interest_handler) = guard.interest_handler.as_mut() {
                 interest_handler.push_interest(InterestType::Readable);
             }
         }
+        Ok(amt)
+    }
+
+    /// Wakes up the reader once the last writer is gone, so that it drains
+    /// whatever is left in the pipe and then sees that it hung up
+    fn mark_other_end_hung_up(&self) {
+        if let Some(rx_end) = self.rx_end.upgrade() {
+            let mut guard = rx_end.lock().unwrap();
+            if guard.chan.is_closed()
+                && let Some(interest_handler) = guard.interest_handler.as_mut()
+            {
+                interest_handler.push_interest(InterestType::Closed);
+            }
+        }
+    }
+}
+
+impl Drop for PipeTx {
+    fn drop(&mut self) {
+        self.close();
     }
 }
 
@@ -321,17 +630,7 @@ impl std::io::Write for Pipe {
 
 impl std::io::Write for PipeTx {
     fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
-        let Some(ref tx) = self.tx else {
-            return Err(std::io::Error::new(
-                std::io::ErrorKind::BrokenPipe,
-                "PipeTx is closed",
-            ));
-        };
-
-        tx.send(buf.to_vec())
-            .map_err(|_| Into::<std::io::Error>::into(std::io::ErrorKind::BrokenPipe))?;
-        self.mark_other_end_readable();
-        Ok(buf.len())
+        self.send(buf)
     }
 
     fn flush(&mut self) -> std::io::Result<()> {
@@ -405,25 +704,22 @@ impl AsyncWrite for Pipe {
 impl AsyncWrite for PipeTx {
     fn poll_write(
         self: Pin<&mut Self>,
-        _cx: &mut Context<'_>,
+        cx: &mut Context<'_>,
         buf: &[u8],
     ) -> Poll<io::Result<usize>> {
-        let Some(ref tx) = self.tx else {
-            return Poll::Ready(Err(std::io::Error::new(
-                std::io::ErrorKind::BrokenPipe,
-                "PipeTx is closed",
-            )));
-        };
-
-        match tx.send(buf.to_vec()) {
-            Ok(()) => {
-                self.mark_other_end_readable();
-                Poll::Ready(Ok(buf.len()))
-            }
-            Err(_) => Poll::Ready(Err(Into::<std::io::Error>::into(
-                std::io::ErrorKind::BrokenPipe,
-            ))),
+        // A bounded pipe makes the writer wait until there is room (for
+        // all of it when it is a datagram)
+        if let Some(capacity) = self.capacity.as_ref()
+            && !buf.is_empty()
+            && self.tx.as_ref().is_some_and(|tx| !tx.is_closed())
+        {
+            let needed = match self.datagram {
+                true => buf.len().min(capacity.limit),
+                false => 1,
+            };
+            ready!(capacity.poll_room(cx, needed));
         }
+        Poll::Ready(self.send(buf))
     }
 
     fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
@@ -530,11 +826,8 @@ impl VirtualFile for Pipe {
     }
 
     /// Polls the file for when it is available for writing
-    fn poll_write_ready(
-        mut self: Pin<&mut Self>,
-        _cx: &mut Context<'_>,
-    ) -> Poll<io::Result<usize>> {
-        Pin::new(&mut self.send).poll_write_ready()
+    fn poll_write_ready(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.send).poll_write_ready(cx)
     }
 }
 
@@ -601,3 +894,36 @@ impl DuplexPipe {
 /// Shared version of BidiPipe for situations where you need
 /// to emulate the old behaviour of `Pipe` (both send and recv on one channel).
 pub type WasiBidirectionalSharedPipePair = ArcFile<DuplexPipe>;
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use tokio::io::AsyncReadExt;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn bounded_datagrams_are_written_whole_or_not_at_all() {
+        let (mut tx, mut rx) = Pipe::datagram_with_capacity(8).split();
+
+        assert_eq!(tx.write(b"abcde").unwrap(), 5);
+        // Only 3 bytes are free, the datagram is not cut short
+        let err = tx.write(b"fghi").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::WouldBlock);
+        assert_eq!(tx.write(b"fgh").unwrap(), 3);
+
+        // A datagram that never fits is refused right away
+        let err = tx.write(b"123456789").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+
+        let mut buf = [0u8; 16];
+        let read = rx.read(&mut buf).await.unwrap();
+        assert_eq!(&buf[..read], b"abcde");
+        assert_eq!(tx.write(b"fghi").unwrap(), 4);
+        let read = rx.read(&mut buf).await.unwrap();
+        assert_eq!(&buf[..read], b"fgh");
+        let read = rx.read(&mut buf).await.unwrap();
+        assert_eq!(&buf[..read], b"fghi");
+    }
+}
@@ -1,16 +1,40 @@
-//! Used for /dev/zero - infinitely returns zero
-//! which is useful for commands like `dd if=/dev/zero of=bigfile.img size=1G`
+//! Used for /dev/random and /dev/urandom - infinitely returns random bytes,
+//! which come from the host unless another source is given
 
+use std::fmt;
 use std::io::{self, *};
 use std::pin::Pin;
+use std::sync::Arc;
 use std::task::{Context, Poll};
 
 use tokio::io::{AsyncRead, AsyncSeek, AsyncWrite};
 
 use crate::VirtualFile;
 
-#[derive(Debug, Default)]
-pub struct RandomFile {}
+/// Fills the whole buffer with random bytes
+pub type RandomSource = Arc<dyn Fn(&mut [u8]) -> io::Result<()> + Send + Sync>;
+
+#[derive(Default)]
+pub struct RandomFile {
+    source: Option<RandomSource>,
+}
+
+impl RandomFile {
+    /// Reads the random bytes from `source` rather than from the host
+    pub fn with_source(source: RandomSource) -> Self {
+        Self {
+            source: Some(source),
+        }
+    }
+}
+
+impl fmt::Debug for RandomFile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RandomFile")
+            .field("host", &self.source.is_none())
+            .finish()
+    }
+}
 
 impl AsyncSeek for RandomFile {
     fn start_seek(self: Pin<&mut Self>, _position: SeekFrom) -> io::Result<()> {
@@ -54,7 +78,10 @@ impl AsyncRead for RandomFile {
         buf: &mut tokio::io::ReadBuf<'_>,
     ) -> Poll<io::Result<()>> {
         let mut data = vec![0u8; buf.remaining()];
-        getrandom::fill(&mut data).ok();
+        match self.source.as_ref() {
+            Some(source) => source(&mut data)?,
+            None => getrandom::fill(&mut data).map_err(io::Error::other)?,
+        }
         buf.put_slice(&data[..]);
         Poll::Ready(Ok(()))
     }
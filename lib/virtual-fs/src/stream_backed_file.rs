@@ -0,0 +1,234 @@
+use std::{
+    io::{self, SeekFrom},
+    pin::Pin,
+    task::{Context, Poll, ready},
+};
+
+use bytes::{Buf, Bytes};
+use futures::{Sink, Stream};
+use tokio::io::{AsyncRead, AsyncSeek, AsyncWrite};
+
+use crate::VirtualFile;
+
+/// Stream of data that the embedder provides to be read by the guest, in
+/// the style of an `input-stream` of WASI preview2.
+///
+/// The end of the stream is the end of the file. This is implemented for
+/// every [`Stream`] of [`Bytes`].
+pub trait InputStream: Stream<Item = io::Result<Bytes>> + Send + Sync + 'static {}
+
+impl<T> InputStream for T where T: Stream<Item = io::Result<Bytes>> + Send + Sync + 'static {}
+
+/// Sink that receives the data written by the guest, in the style of an
+/// `output-stream` of WASI preview2.
+///
+/// Flushing and shutting down the file flushes and closes the sink. This
+/// is implemented for every [`Sink`] of [`Bytes`].
+pub trait OutputStream: Sink<Bytes, Error = io::Error> + Send + Sync + 'static {}
+
+impl<T> OutputStream for T where T: Sink<Bytes, Error = io::Error> + Send + Sync + 'static {}
+
+/// A [`VirtualFile`] that is backed by streams of the embedder, which
+/// makes it possible to back a WASI file descriptor with host code.
+///
+/// Reads are served from the [`InputStream`] and writes are sent to the
+/// [`OutputStream`], the readiness of the streams is what `poll_oneoff`
+/// reports for the file. Without an input stream the file reads as empty
+/// and without an output stream writes fail with a broken pipe.
+#[derive(derive_more::Debug)]
+pub struct StreamBackedFile {
+    #[debug(ignore)]
+    input: Option<Pin<Box<dyn InputStream>>>,
+    #[debug(ignore)]
+    output: Option<Pin<Box<dyn OutputStream>>>,
+    /// Rest of the last chunk of the input stream that was not read yet
+    pending: Bytes,
+}
+
+impl StreamBackedFile {
+    pub fn new(input: impl InputStream, output: impl OutputStream) -> Self {
+        Self {
+            input: Some(Box::pin(input)),
+            output: Some(Box::pin(output)),
+            pending: Bytes::new(),
+        }
+    }
+
+    /// Creates a file that can only be read from
+    pub fn from_input(input: impl InputStream) -> Self {
+        Self {
+            input: Some(Box::pin(input)),
+            output: None,
+            pending: Bytes::new(),
+        }
+    }
+
+    /// Creates a file that can only be written to
+    pub fn from_output(output: impl OutputStream) -> Self {
+        Self {
+            input: None,
+            output: Some(Box::pin(output)),
+            pending: Bytes::new(),
+        }
+    }
+
+    /// Waits for data from the input stream, zero means the end was reached
+    fn poll_fill(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<usize>> {
+        while self.pending.is_empty() {
+            let Some(input) = self.input.as_mut() else {
+                return Poll::Ready(Ok(0));
+            };
+            match ready!(input.as_mut().poll_next(cx)) {
+                Some(Ok(chunk)) => self.pending = chunk,
+                Some(Err(err)) => return Poll::Ready(Err(err)),
+                None => self.input = None,
+            }
+        }
+        Poll::Ready(Ok(self.pending.len()))
+    }
+
+    fn output(&mut self) -> io::Result<Pin<&mut (dyn OutputStream + 'static)>> {
+        self.output
+            .as_mut()
+            .map(|output| output.as_mut())
+            .ok_or_else(|| io::ErrorKind::BrokenPipe.into())
+    }
+}
+
+impl VirtualFile for StreamBackedFile {
+    fn last_accessed(&self) -> u64 {
+        0
+    }
+
+    fn last_modified(&self) -> u64 {
+        0
+    }
+
+    fn created_time(&self) -> u64 {
+        0
+    }
+
+    fn size(&self) -> u64 {
+        0
+    }
+
+    fn set_len(&mut self, _new_size: u64) -> crate::Result<()> {
+        Err(crate::FsError::PermissionDenied)
+    }
+
+    fn unlink(&mut self) -> crate::Result<()> {
+        Ok(())
+    }
+
+    fn poll_read_ready(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<usize>> {
+        self.poll_fill(cx)
+    }
+
+    fn poll_write_ready(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<usize>> {
+        ready!(self.output()?.poll_ready(cx))?;
+        Poll::Ready(Ok(8192))
+    }
+}
+
+impl AsyncRead for StreamBackedFile {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut tokio::io::ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let available = ready!(self.poll_fill(cx))?;
+        let amt = available.min(buf.remaining());
+        buf.put_slice(&self.pending[..amt]);
+        self.pending.advance(amt);
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl AsyncWrite for StreamBackedFile {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let mut output = self.output()?;
+        ready!(output.as_mut().poll_ready(cx))?;
+        output.start_send(Bytes::copy_from_slice(buf))?;
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.output.as_mut() {
+            Some(output) => output.as_mut().poll_flush(cx),
+            None => Poll::Ready(Ok(())),
+        }
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.output.as_mut() {
+            Some(output) => output.as_mut().poll_close(cx),
+            None => Poll::Ready(Ok(())),
+        }
+    }
+}
+
+impl AsyncSeek for StreamBackedFile {
+    fn start_seek(self: Pin<&mut Self>, _position: SeekFrom) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn poll_complete(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<u64>> {
+        Poll::Ready(Ok(0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::{SinkExt, StreamExt, channel::mpsc};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_reads_and_writes_go_through_the_streams() {
+        let (input_tx, input_rx) = mpsc::unbounded::<io::Result<Bytes>>();
+        let (output_tx, mut output_rx) = mpsc::unbounded::<Bytes>();
+        let output = output_tx.sink_map_err(io::Error::other);
+        let mut file = StreamBackedFile::new(input_rx, output);
+
+        let waker = futures::task::noop_waker();
+        let mut cx = Context::from_waker(&waker);
+        assert!(Pin::new(&mut file).poll_read_ready(&mut cx).is_pending());
+
+        input_tx.unbounded_send(Ok(Bytes::from("hello "))).unwrap();
+        input_tx.unbounded_send(Ok(Bytes::from("world"))).unwrap();
+        drop(input_tx);
+        assert!(matches!(
+            Pin::new(&mut file).poll_read_ready(&mut cx),
+            Poll::Ready(Ok(6))
+        ));
+
+        let mut buf = [0u8; 4];
+        file.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"hell");
+        let mut rest = String::new();
+        file.read_to_string(&mut rest).await.unwrap();
+        assert_eq!(rest, "o world");
+
+        file.write_all(b"reply").await.unwrap();
+        file.shutdown().await.unwrap();
+        let written: Vec<Bytes> = output_rx.by_ref().collect().await;
+        assert_eq!(written, vec![Bytes::from("reply")]);
+    }
+
+    #[tokio::test]
+    async fn test_missing_streams() {
+        let (output_tx, _output_rx) = mpsc::unbounded::<Bytes>();
+        let mut file = StreamBackedFile::from_output(output_tx.sink_map_err(io::Error::other));
+        let mut contents = Vec::new();
+        assert_eq!(file.read_to_end(&mut contents).await.unwrap(), 0);
+
+        let mut file = StreamBackedFile::from_input(futures::stream::empty());
+        let err = file.write_all(b"data").await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::BrokenPipe);
+    }
+}
@@ -0,0 +1,272 @@
+use std::{
+    collections::VecDeque,
+    io,
+    pin::Pin,
+    task::{Context, Poll, ready},
+};
+
+use tokio::io::{AsyncRead, AsyncSeek, AsyncWrite, ReadBuf};
+
+use crate::VirtualFile;
+
+/// Wraps a [`VirtualFile`] and translates line endings the way text mode
+/// streams do on Windows: every `\n` that is written becomes `\r\n` and
+/// every `\r\n` that is read becomes `\n`.
+///
+/// This is meant for the standard streams, seeking or looking at the size
+/// of the file doesn't take the translation into account. Written data that
+/// the inner file didn't accept yet is held back until the next write, flush
+/// or shutdown, it is lost if the file is dropped before that. A `\r` that
+/// ends the input that is available so far is read as is, even when the
+/// `\n` that follows it arrives later.
+#[derive(Debug)]
+pub struct TextModeFile {
+    inner: Box<dyn VirtualFile + Send + Sync + 'static>,
+    /// Translated data that still needs to be written to the inner file
+    write_pending: Vec<u8>,
+    /// How much of `write_pending` was already written
+    written: usize,
+    /// Translated data that was read but not yet returned
+    read_pending: VecDeque<u8>,
+    /// The last byte that was read is a `\r`, which is dropped if the next
+    /// one is a `\n`
+    read_cr: bool,
+}
+
+impl TextModeFile {
+    pub fn new(inner: Box<dyn VirtualFile + Send + Sync + 'static>) -> Self {
+        Self {
+            inner,
+            write_pending: Vec::new(),
+            written: 0,
+            read_pending: VecDeque::new(),
+            read_cr: false,
+        }
+    }
+
+    /// Writes the translated data that is held back to the inner file
+    fn poll_write_pending(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        while self.written < self.write_pending.len() {
+            let written = ready!(
+                Pin::new(&mut self.inner).poll_write(cx, &self.write_pending[self.written..])
+            )?;
+            if written == 0 {
+                return Poll::Ready(Err(io::ErrorKind::WriteZero.into()));
+            }
+            self.written += written;
+        }
+        self.write_pending.clear();
+        self.written = 0;
+        Poll::Ready(Ok(()))
+    }
+
+    /// Translates data that was read from the inner file into `read_pending`
+    fn translate_read(&mut self, data: &[u8]) {
+        for &byte in data {
+            if self.read_cr && byte != b'\n' {
+                self.read_pending.push_back(b'\r');
+            }
+            self.read_cr = byte == b'\r';
+            if !self.read_cr {
+                self.read_pending.push_back(byte);
+            }
+        }
+    }
+}
+
+impl VirtualFile for TextModeFile {
+    fn last_accessed(&self) -> u64 {
+        self.inner.last_accessed()
+    }
+
+    fn last_modified(&self) -> u64 {
+        self.inner.last_modified()
+    }
+
+    fn created_time(&self) -> u64 {
+        self.inner.created_time()
+    }
+
+    fn set_times(&mut self, atime: Option<u64>, mtime: Option<u64>) -> crate::Result<()> {
+        self.inner.set_times(atime, mtime)
+    }
+
+    fn size(&self) -> u64 {
+        self.inner.size()
+    }
+
+    fn set_len(&mut self, new_size: u64) -> crate::Result<()> {
+        self.inner.set_len(new_size)
+    }
+
+    fn unlink(&mut self) -> crate::Result<()> {
+        self.inner.unlink()
+    }
+
+    fn poll_read_ready(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<usize>> {
+        if !self.read_pending.is_empty() {
+            return Poll::Ready(Ok(self.read_pending.len()));
+        }
+        Pin::new(self.inner.as_mut()).poll_read_ready(cx)
+    }
+
+    fn poll_write_ready(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<usize>> {
+        ready!(self.poll_write_pending(cx))?;
+        Pin::new(self.inner.as_mut()).poll_write_ready(cx)
+    }
+}
+
+impl AsyncWrite for TextModeFile {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        ready!(self.poll_write_pending(cx))?;
+
+        for &byte in buf {
+            if byte == b'\n' {
+                self.write_pending.push(b'\r');
+            }
+            self.write_pending.push(byte);
+        }
+
+        // Once the inner file took part of it the data belongs to this file
+        // and the rest is written before anything else, an error before that
+        // is returned and the data is dropped so that it isn't written twice
+        if let Poll::Ready(Err(err)) = self.poll_write_pending(cx) {
+            if self.written == 0 {
+                self.write_pending.clear();
+                return Poll::Ready(Err(err));
+            }
+            tracing::debug!("failed to write the rest of the translated data - {err}");
+        }
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        ready!(self.poll_write_pending(cx))?;
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        ready!(self.poll_write_pending(cx))?;
+        Pin::new(&mut self.inner).poll_shutdown(cx)
+    }
+}
+
+impl AsyncRead for TextModeFile {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        if buf.remaining() == 0 {
+            return Poll::Ready(Ok(()));
+        }
+        while self.read_pending.is_empty() {
+            let mut chunk = [0u8; 8192];
+            let mut data = ReadBuf::new(&mut chunk[..buf.remaining().min(8192)]);
+            match Pin::new(&mut self.inner).poll_read(cx, &mut data) {
+                Poll::Ready(res) => res?,
+                // A `\r` that was read last isn't held back while waiting
+                // for more input, it is returned as is
+                Poll::Pending if self.read_cr => {
+                    self.read_cr = false;
+                    self.read_pending.push_back(b'\r');
+                    break;
+                }
+                Poll::Pending => return Poll::Pending,
+            }
+            if data.filled().is_empty() {
+                // A `\r` at the very end of the input is kept as is
+                if std::mem::take(&mut self.read_cr) {
+                    self.read_pending.push_back(b'\r');
+                    break;
+                }
+                return Poll::Ready(Ok(()));
+            }
+            let data = data.filled().to_vec();
+            self.translate_read(&data);
+        }
+
+        let amt = self.read_pending.len().min(buf.remaining());
+        let (front, back) = self.read_pending.as_slices();
+        let from_front = amt.min(front.len());
+        buf.put_slice(&front[..from_front]);
+        buf.put_slice(&back[..amt - from_front]);
+        self.read_pending.drain(..amt);
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl AsyncSeek for TextModeFile {
+    fn start_seek(mut self: Pin<&mut Self>, position: io::SeekFrom) -> io::Result<()> {
+        Pin::new(&mut self.inner).start_seek(position)
+    }
+
+    fn poll_complete(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<u64>> {
+        Pin::new(&mut self.inner).poll_complete(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    use super::*;
+    use crate::Pipe;
+
+    #[tokio::test]
+    async fn newlines_are_written_as_crlf() {
+        let (tx, mut rx) = Pipe::channel();
+        let mut file = TextModeFile::new(Box::new(tx));
+
+        file.write_all(b"hello\nworld\n\n").await.unwrap();
+        file.flush().await.unwrap();
+        drop(file);
+
+        let mut out = Vec::new();
+        rx.read_to_end(&mut out).await.unwrap();
+        assert_eq!(out, b"hello\r\nworld\r\n\r\n");
+    }
+
+    #[tokio::test]
+    async fn crlf_is_read_as_newlines() {
+        let (mut tx, rx) = Pipe::channel();
+        let mut file = TextModeFile::new(Box::new(rx));
+
+        // The `\r\n` that is split over two writes is translated as well,
+        // other carriage returns are left alone
+        tx.write_all(b"a\r\nb\r").await.unwrap();
+        tx.write_all(b"\nc\rd\r").await.unwrap();
+        drop(tx);
+
+        let mut out = Vec::new();
+        file.read_to_end(&mut out).await.unwrap();
+        assert_eq!(out, b"a\nb\nc\rd\r");
+    }
+
+    #[tokio::test]
+    async fn trailing_cr_is_read_without_waiting() {
+        let (mut tx, rx) = Pipe::channel();
+        let mut file = TextModeFile::new(Box::new(rx));
+
+        tx.write_all(b"a\r").await.unwrap();
+        let mut out = [0u8; 8];
+        let read = file.read(&mut out).await.unwrap();
+        assert_eq!(&out[..read], b"a");
+        let read = file.read(&mut out).await.unwrap();
+        assert_eq!(&out[..read], b"\r");
+    }
+
+    #[tokio::test]
+    async fn write_errors_are_returned() {
+        let (tx, rx) = Pipe::channel();
+        let mut file = TextModeFile::new(Box::new(tx));
+        drop(rx);
+
+        let err = file.write(b"hello\n").await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::BrokenPipe);
+    }
+}
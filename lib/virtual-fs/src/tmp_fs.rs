@@ -62,6 +62,12 @@ impl TmpFileSystem {
     pub fn create_symlink(&self, source: &Path, target: &Path) -> Result<()> {
         self.fs.create_symlink(source, target)
     }
+
+    /// Changes the access and modification times of a file or a directory,
+    /// see [`mem_fs::FileSystem::set_times`]
+    pub fn set_times(&self, path: &Path, atime: Option<u64>, mtime: Option<u64>) -> Result<()> {
+        self.fs.set_times(path, atime, mtime)
+    }
 }
 
 impl FileSystem for TmpFileSystem {
@@ -46,6 +46,7 @@ use virtual_mio::block_on;
 use crate::IpCidr;
 use crate::IpRoute;
 use crate::NetworkError;
+use crate::NetworkInterface;
 use crate::StreamSecurity;
 use crate::VirtualConnectedSocket;
 use crate::VirtualConnectionlessSocket;
@@ -665,6 +666,17 @@ impl VirtualNetworking for RemoteNetworkingClient {
         }
     }
 
+    async fn interfaces(&self) -> Result<Vec<NetworkInterface>> {
+        match self.common.io_iface(RequestType::GetInterfaces).await {
+            ResponseType::Err(err) => Err(err),
+            ResponseType::InterfaceList(interfaces) => Ok(interfaces),
+            res => {
+                tracing::debug!("invalid response to interfaces request - {res:?}");
+                Err(NetworkError::IOError)
+            }
+        }
+    }
+
     async fn gateway_set(&self, ip: IpAddr) -> Result<()> {
         self.common
             .io_iface_fire_and_forget(RequestType::GatewaySet(ip))
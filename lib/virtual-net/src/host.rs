@@ -2,9 +2,10 @@
 use crate::ruleset::{Direction, Ruleset};
 #[allow(unused_imports)]
 use crate::{
-    IpCidr, IpRoute, NetworkError, Result, SocketStatus, StreamSecurity, VirtualConnectedSocket,
-    VirtualConnectionlessSocket, VirtualIcmpSocket, VirtualNetworking, VirtualRawSocket,
-    VirtualSocket, VirtualTcpListener, VirtualTcpSocket, VirtualUdpSocket,
+    IpCidr, IpRoute, NetworkError, NetworkInterface, Result, SocketStatus, StreamSecurity,
+    VirtualConnectedSocket, VirtualConnectionlessSocket, VirtualIcmpSocket, VirtualNetworking,
+    VirtualRawSocket, VirtualSocket, VirtualTcpBinding, VirtualTcpListener, VirtualTcpSocket,
+    VirtualUdpSocket,
 };
 use crate::{VirtualIoSource, io_err_into_net_error};
 use bytes::{Buf, BytesMut};
@@ -66,9 +67,173 @@ impl Default for LocalNetworking {
     }
 }
 
+/// Lists the network interfaces of the host with `getifaddrs`, which
+/// returns an entry for every address of every interface
+#[cfg(not(target_os = "windows"))]
+fn host_interfaces() -> io::Result<Vec<NetworkInterface>> {
+    let mut ifaddrs: *mut libc::ifaddrs = std::ptr::null_mut();
+    if unsafe { libc::getifaddrs(&mut ifaddrs) } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+
+    let mut interfaces: Vec<NetworkInterface> = Vec::new();
+    let mut next = ifaddrs;
+    while !next.is_null() {
+        let ifaddr = unsafe { &*next };
+        next = ifaddr.ifa_next;
+
+        let name = unsafe { std::ffi::CStr::from_ptr(ifaddr.ifa_name) }
+            .to_string_lossy()
+            .into_owned();
+        let index = match interfaces.iter().position(|i| i.name == name) {
+            Some(index) => index,
+            None => {
+                let flags = ifaddr.ifa_flags as libc::c_int;
+                interfaces.push(NetworkInterface {
+                    name,
+                    addrs: Vec::new(),
+                    up: flags & libc::IFF_UP != 0,
+                    loopback: flags & libc::IFF_LOOPBACK != 0,
+                    multicast: flags & libc::IFF_MULTICAST != 0,
+                });
+                interfaces.len() - 1
+            }
+        };
+
+        if let Some(ip) = unsafe { sockaddr_ip(ifaddr.ifa_addr) } {
+            let prefix = match unsafe { sockaddr_ip(ifaddr.ifa_netmask) } {
+                Some(IpAddr::V4(mask)) => u32::from(mask).count_ones() as u8,
+                Some(IpAddr::V6(mask)) => u128::from(mask).count_ones() as u8,
+                None if ip.is_ipv4() => 32,
+                None => 128,
+            };
+            interfaces[index].addrs.push(IpCidr { ip, prefix });
+        }
+    }
+
+    unsafe { libc::freeifaddrs(ifaddrs) };
+    Ok(interfaces)
+}
+
+/// The IP address in a `sockaddr`, `None` for the other address families
+#[cfg(not(target_os = "windows"))]
+unsafe fn sockaddr_ip(addr: *const libc::sockaddr) -> Option<IpAddr> {
+    if addr.is_null() {
+        return None;
+    }
+    unsafe {
+        match (*addr).sa_family as libc::c_int {
+            libc::AF_INET => {
+                let addr = &*(addr as *const libc::sockaddr_in);
+                Some(IpAddr::V4(Ipv4Addr::from(u32::from_be(
+                    addr.sin_addr.s_addr,
+                ))))
+            }
+            libc::AF_INET6 => {
+                let addr = &*(addr as *const libc::sockaddr_in6);
+                Some(IpAddr::V6(Ipv6Addr::from(addr.sin6_addr.s6_addr)))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Returns the number of bytes that the host queued on a socket, `request`
+/// is `FIONREAD` for the ones that were received and `TIOCOUTQ` for the ones
+/// that were not sent yet
+#[cfg(target_os = "linux")]
+fn queued_bytes(fd: RawFd, request: libc::Ioctl) -> io::Result<usize> {
+    let mut queued: libc::c_int = 0;
+    if unsafe { libc::ioctl(fd, request, &mut queued) } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(queued as usize)
+}
+
+/// Returns the number of connections in the accept queue of a listening
+/// socket of the host, Linux reports it as the unacknowledged segments
+#[cfg(target_os = "linux")]
+fn accept_queue_len(fd: RawFd) -> io::Result<usize> {
+    let mut info: libc::tcp_info = unsafe { std::mem::zeroed() };
+    let mut len = std::mem::size_of::<libc::tcp_info>() as libc::socklen_t;
+    let ret = unsafe {
+        libc::getsockopt(
+            fd,
+            libc::IPPROTO_TCP,
+            libc::TCP_INFO,
+            (&mut info as *mut libc::tcp_info).cast(),
+            &mut len,
+        )
+    };
+    if ret == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(info.tcpi_unacked as usize)
+}
+
+/// Receives a datagram with `recvmsg` to read the TTL (or hop limit) from
+/// the control messages that come along with it
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn recv_from_with_ttl(
+    socket: &mio::net::UdpSocket,
+    buf: &mut [MaybeUninit<u8>],
+    peek: bool,
+) -> io::Result<(usize, SocketAddr, Option<u8>)> {
+    let mut iov = libc::iovec {
+        iov_base: buf.as_mut_ptr().cast(),
+        iov_len: buf.len(),
+    };
+    // The TTL and the hop limit are both an `int`, this leaves plenty of
+    // room for their headers (and an aligned buffer)
+    let mut control = [0u64; 8];
+    let mut ttl = None;
+    let (amt, addr) = unsafe {
+        socket2::SockAddr::try_init(|storage, len| {
+            let mut msg: libc::msghdr = std::mem::zeroed();
+            msg.msg_name = storage.cast();
+            msg.msg_namelen = *len;
+            msg.msg_iov = &mut iov;
+            msg.msg_iovlen = 1;
+            msg.msg_control = control.as_mut_ptr().cast();
+            msg.msg_controllen = std::mem::size_of_val(&control) as _;
+
+            let flags = if peek { libc::MSG_PEEK } else { 0 };
+            let amt = libc::recvmsg(socket.as_raw_fd(), &mut msg, flags);
+            if amt < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            *len = msg.msg_namelen;
+
+            let mut cmsg = libc::CMSG_FIRSTHDR(&msg);
+            while !cmsg.is_null() {
+                let hdr = &*cmsg;
+                if (hdr.cmsg_level == libc::IPPROTO_IP && hdr.cmsg_type == libc::IP_TTL)
+                    || (hdr.cmsg_level == libc::IPPROTO_IPV6
+                        && hdr.cmsg_type == libc::IPV6_HOPLIMIT)
+                {
+                    let value =
+                        std::ptr::read_unaligned(libc::CMSG_DATA(cmsg) as *const libc::c_int);
+                    ttl = u8::try_from(value).ok();
+                }
+                cmsg = libc::CMSG_NXTHDR(&msg, cmsg);
+            }
+            Ok(amt as usize)
+        })?
+    };
+    let addr = addr
+        .as_socket()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "not an IP address"))?;
+    Ok((amt, addr, ttl))
+}
+
 #[async_trait::async_trait]
 #[allow(unused_variables)]
 impl VirtualNetworking for LocalNetworking {
+    #[cfg(not(target_os = "windows"))]
+    async fn interfaces(&self) -> Result<Vec<NetworkInterface>> {
+        host_interfaces().map_err(io_err_into_net_error)
+    }
+
     async fn listen_tcp(
         &self,
         addr: SocketAddr,
@@ -100,6 +265,51 @@ impl VirtualNetworking for LocalNetworking {
         Ok(listener)
     }
 
+    async fn bind_tcp(
+        &self,
+        addr: SocketAddr,
+        only_v6: bool,
+        reuse_port: bool,
+        reuse_addr: bool,
+    ) -> Result<Box<dyn VirtualTcpBinding + Sync>> {
+        use socket2::{Domain, Socket, Type};
+
+        if let Some(ruleset) = self.ruleset.as_ref()
+            && !ruleset.allows_socket(addr, Direction::Inbound)
+        {
+            tracing::warn!(%addr, "bind_tcp blocked by firewall rule");
+            return Err(NetworkError::PermissionDenied);
+        }
+
+        let domain = if addr.is_ipv4() {
+            Domain::IPV4
+        } else {
+            Domain::IPV6
+        };
+        let socket = Socket::new(domain, Type::STREAM, None).map_err(io_err_into_net_error)?;
+        socket
+            .set_nonblocking(true)
+            .map_err(io_err_into_net_error)?;
+        if addr.is_ipv6() {
+            socket.set_only_v6(only_v6).map_err(io_err_into_net_error)?;
+        }
+        socket
+            .set_reuse_address(reuse_addr)
+            .map_err(io_err_into_net_error)?;
+        #[cfg(not(windows))]
+        socket
+            .set_reuse_port(reuse_port)
+            .map_err(io_err_into_net_error)?;
+        socket.bind(&addr.into()).map_err(io_err_into_net_error)?;
+
+        Ok(Box::new(LocalTcpBinding {
+            socket,
+            selector: self.selector.clone(),
+            handle: self.handle.clone(),
+            ruleset: self.ruleset.clone(),
+        }))
+    }
+
     async fn bind_udp(
         &self,
         addr: SocketAddr,
@@ -147,6 +357,8 @@ impl VirtualNetworking for LocalNetworking {
             handler_guard: HandlerGuardState::None,
             backlog: Default::default(),
             ruleset: self.ruleset.clone(),
+            #[cfg(any(target_os = "linux", target_os = "android"))]
+            recv_ttl: false,
         };
 
         // In windows we can not poll the socket as it is not supported and hence
@@ -231,6 +443,68 @@ impl VirtualNetworking for LocalNetworking {
     }
 }
 
+#[derive(Debug)]
+pub struct LocalTcpBinding {
+    socket: socket2::Socket,
+    selector: Arc<Selector>,
+    handle: Handle,
+    ruleset: Option<Ruleset>,
+}
+
+#[async_trait::async_trait]
+impl VirtualTcpBinding for LocalTcpBinding {
+    fn addr_local(&self) -> Result<SocketAddr> {
+        self.socket
+            .local_addr()
+            .map_err(io_err_into_net_error)?
+            .as_socket()
+            .ok_or(NetworkError::InvalidData)
+    }
+
+    fn listen(self: Box<Self>, backlog: usize) -> Result<Box<dyn VirtualTcpListener + Sync>> {
+        let backlog = backlog.clamp(1, i32::MAX as usize) as i32;
+        self.socket.listen(backlog).map_err(io_err_into_net_error)?;
+        Ok(Box::new(LocalTcpListener {
+            stream: mio::net::TcpListener::from_std(self.socket.into()),
+            selector: self.selector,
+            handler_guard: HandlerGuardState::None,
+            no_delay: None,
+            keep_alive: None,
+            backlog: Default::default(),
+            ruleset: self.ruleset,
+        }))
+    }
+
+    async fn connect(
+        self: Box<Self>,
+        mut peer: SocketAddr,
+    ) -> Result<Box<dyn VirtualTcpSocket + Sync>> {
+        if let Some(ruleset) = self.ruleset.as_ref()
+            && !ruleset.allows_socket(peer, Direction::Outbound)
+        {
+            tracing::warn!(%peer, "connect_tcp blocked by firewall rule");
+            return Err(NetworkError::PermissionDenied);
+        }
+
+        // The connect runs on the stored runtime handle for the same reason
+        // as in `connect_tcp`, the bound port is kept as the local port
+        let socket = tokio::net::TcpSocket::from_std_stream(self.socket.into());
+        let stream = self
+            .handle
+            .spawn(socket.connect(peer))
+            .await
+            .map_err(|_| NetworkError::IOError)?
+            .map_err(io_err_into_net_error)?;
+        let stream = stream.into_std().map_err(io_err_into_net_error)?;
+        let stream = mio::net::TcpStream::from_std(stream);
+
+        if let Ok(p) = stream.peer_addr() {
+            peer = p;
+        }
+        Ok(Box::new(LocalTcpStream::new(self.selector, stream, peer)))
+    }
+}
+
 #[derive(Debug)]
 pub struct LocalTcpListener {
     stream: mio::net::TcpListener,
@@ -324,6 +598,12 @@ impl VirtualTcpListener for LocalTcpListener {
             .map(|ttl| ttl as u8)
             .map_err(io_err_into_net_error)
     }
+
+    #[cfg(target_os = "linux")]
+    fn accept_queued(&self) -> Result<usize> {
+        let queued = accept_queue_len(self.stream.as_raw_fd()).map_err(io_err_into_net_error)?;
+        Ok(self.backlog.len() + queued)
+    }
 }
 
 impl LocalTcpListener {
@@ -617,6 +897,19 @@ impl VirtualSocket for LocalTcpStream {
         Ok(SocketStatus::Opened)
     }
 
+    #[cfg(target_os = "linux")]
+    fn recv_queued(&self) -> Result<usize> {
+        // What was read ahead while polling is queued too
+        let queued =
+            queued_bytes(self.stream.as_raw_fd(), libc::FIONREAD).map_err(io_err_into_net_error)?;
+        Ok(self.buffer.len() + queued)
+    }
+
+    #[cfg(target_os = "linux")]
+    fn send_queued(&self) -> Result<usize> {
+        queued_bytes(self.stream.as_raw_fd(), libc::TIOCOUTQ).map_err(io_err_into_net_error)
+    }
+
     fn set_handler(&mut self, mut handler: Box<dyn InterestHandler + Send + Sync>) -> Result<()> {
         if let HandlerGuardState::ExternalHandler(guard) = &mut self.handler_guard {
             match guard.replace_handler(handler) {
@@ -763,6 +1056,9 @@ pub struct LocalUdpSocket {
     handler_guard: HandlerGuardState,
     backlog: VecDeque<(BytesMut, SocketAddr)>,
     ruleset: Option<Ruleset>,
+    /// The TTL of the datagrams is received along with them
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    recv_ttl: bool,
 }
 
 impl LocalUdpSocket {
@@ -831,6 +1127,48 @@ impl VirtualUdpSocket for LocalUdpSocket {
             .map_err(io_err_into_net_error)
     }
 
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    fn set_recv_ttl(&mut self, val: bool) -> Result<()> {
+        let (level, name) = match self.socket.local_addr().map_err(io_err_into_net_error)? {
+            SocketAddr::V4(_) => (libc::IPPROTO_IP, libc::IP_RECVTTL),
+            SocketAddr::V6(_) => (libc::IPPROTO_IPV6, libc::IPV6_RECVHOPLIMIT),
+        };
+        let enable = val as libc::c_int;
+        let ret = unsafe {
+            libc::setsockopt(
+                self.socket.as_raw_fd(),
+                level,
+                name,
+                (&enable as *const libc::c_int).cast(),
+                std::mem::size_of::<libc::c_int>() as libc::socklen_t,
+            )
+        };
+        if ret != 0 {
+            return Err(io_err_into_net_error(io::Error::last_os_error()));
+        }
+        self.recv_ttl = val;
+        Ok(())
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    fn recv_ttl(&self) -> Result<bool> {
+        Ok(self.recv_ttl)
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    fn try_recv_from_ttl(
+        &mut self,
+        buf: &mut [MaybeUninit<u8>],
+        peek: bool,
+    ) -> Result<(usize, SocketAddr, Option<u8>)> {
+        if !self.recv_ttl {
+            return self
+                .try_recv_from(buf, peek)
+                .map(|(amt, addr)| (amt, addr, None));
+        }
+        recv_from_with_ttl(&self.socket, buf, peek).map_err(io_err_into_net_error)
+    }
+
     fn join_multicast_v4(&mut self, multiaddr: Ipv4Addr, iface: Ipv4Addr) -> Result<()> {
         self.with_sock_ref(|s| s.join_multicast_v4(&multiaddr, &iface))
             .map_err(io_err_into_net_error)
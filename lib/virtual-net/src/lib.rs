@@ -71,6 +71,20 @@ pub struct IpRoute {
     pub expires_at: Option<Duration>,
 }
 
+/// A network interface and the addresses that are assigned to it
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "rkyv", derive(RkyvSerialize, RkyvDeserialize, Archive))]
+pub struct NetworkInterface {
+    pub name: String,
+    pub addrs: Vec<IpCidr>,
+    /// The interface is up
+    pub up: bool,
+    /// The interface only loops back to the host
+    pub loopback: bool,
+    /// The interface supports multicast
+    pub multicast: bool,
+}
+
 /// Represents an IO source
 pub trait VirtualIoSource: fmt::Debug + Send + Sync + 'static {
     /// Removes a previously registered waker using a token
@@ -133,6 +147,12 @@ pub trait VirtualNetworking: fmt::Debug + Send + Sync + 'static {
         Err(NetworkError::Unsupported)
     }
 
+    /// Lists the network interfaces along with their addresses, a sandbox
+    /// can hand out whatever interfaces it wants the guests to see
+    async fn interfaces(&self) -> Result<Vec<NetworkInterface>> {
+        Err(NetworkError::Unsupported)
+    }
+
     /// Adds a default gateway to the routing table
     async fn gateway_set(&self, ip: IpAddr) -> Result<()> {
         Err(NetworkError::Unsupported)
@@ -170,6 +190,13 @@ pub trait VirtualNetworking: fmt::Debug + Send + Sync + 'static {
         Err(NetworkError::Unsupported)
     }
 
+    /// Returns a view of this network where every socket that is bound,
+    /// listens or connects goes through the network interface `device`
+    /// (similar to `SO_BINDTODEVICE`)
+    fn bind_to_device(&self, device: &str) -> Result<DynVirtualNetworking> {
+        Err(NetworkError::Unsupported)
+    }
+
     /// Lists for TCP connections on a specific IP and Port combination
     /// Multiple servers (processes or threads) can bind to the same port if they each set
     /// the reuse-port and-or reuse-addr flags
@@ -183,6 +210,19 @@ pub trait VirtualNetworking: fmt::Debug + Send + Sync + 'static {
         Err(NetworkError::Unsupported)
     }
 
+    /// Binds a TCP socket to a specific IP and Port combination without listening
+    /// or connecting yet, which holds on to the port (an ephemeral one when the
+    /// port is 0) until the socket is turned into a listener or a connection
+    async fn bind_tcp(
+        &self,
+        addr: SocketAddr,
+        only_v6: bool,
+        reuse_port: bool,
+        reuse_addr: bool,
+    ) -> Result<Box<dyn VirtualTcpBinding + Sync>> {
+        Err(NetworkError::Unsupported)
+    }
+
     /// Opens a UDP socket that listens on a specific IP and Port combination
     /// Multiple servers (processes or threads) can bind to the same port if they each set
     /// the reuse-port and-or reuse-addr flags
@@ -239,6 +279,28 @@ pub trait VirtualTcpListener: VirtualIoSource + fmt::Debug + Send + Sync + 'stat
 
     /// Returns the maximum number of network hops before packets are dropped
     fn ttl(&self) -> Result<u8>;
+
+    /// Returns the number of connections that were made but are not
+    /// accepted yet
+    fn accept_queued(&self) -> Result<usize> {
+        Err(NetworkError::Unsupported)
+    }
+}
+
+/// A TCP socket that is bound to a local address but neither listens nor is
+/// connected yet (see [`VirtualNetworking::bind_tcp`])
+#[async_trait::async_trait]
+pub trait VirtualTcpBinding: fmt::Debug + Send + Sync + 'static {
+    /// Returns the local address that the socket is bound to
+    fn addr_local(&self) -> Result<SocketAddr>;
+
+    /// Starts listening for TCP connections on the bound address, with room
+    /// for `backlog` connections that are not accepted yet
+    fn listen(self: Box<Self>, backlog: usize) -> Result<Box<dyn VirtualTcpListener + Sync>>;
+
+    /// Opens a TCP connection to `peer` from the bound address
+    async fn connect(self: Box<Self>, peer: SocketAddr)
+    -> Result<Box<dyn VirtualTcpSocket + Sync>>;
 }
 
 #[async_trait::async_trait]
@@ -290,6 +352,18 @@ pub trait VirtualSocket: VirtualIoSource + fmt::Debug + Send + Sync + 'static {
     /// Returns the status/state of the socket
     fn status(&self) -> Result<SocketStatus>;
 
+    /// Returns the number of bytes that were received but not read yet
+    /// (the equivalent of `FIONREAD`)
+    fn recv_queued(&self) -> Result<usize> {
+        Err(NetworkError::Unsupported)
+    }
+
+    /// Returns the number of bytes that were written but not sent to the
+    /// peer yet (the equivalent of `SIOCOUTQ`)
+    fn send_queued(&self) -> Result<usize> {
+        Err(NetworkError::Unsupported)
+    }
+
     /// Registers a waker for when this connection is ready to receive
     /// more data. Uses a stack machine which means more than one waker
     /// can be registered
@@ -708,6 +782,7 @@ impl<'a> AsyncWrite for Box<dyn VirtualTcpSocket + Sync + 'a> {
     }
 }
 
+#[allow(unused_variables)]
 pub trait VirtualUdpSocket:
     VirtualConnectionlessSocket + fmt::Debug + Send + Sync + 'static
 {
@@ -748,6 +823,32 @@ pub trait VirtualUdpSocket:
     /// number of network hops before the packet is dropped
     fn multicast_ttl_v4(&self) -> Result<u32>;
 
+    /// Sets a flag that means that the TTL (or the hop limit for IPv6)
+    /// that each datagram arrived with is reported by
+    /// [`try_recv_from_ttl`](VirtualUdpSocket::try_recv_from_ttl)
+    /// (like `IP_RECVTTL`)
+    fn set_recv_ttl(&mut self, val: bool) -> Result<()> {
+        Err(NetworkError::Unsupported)
+    }
+
+    /// Indicates if the flag set by
+    /// [`set_recv_ttl`](VirtualUdpSocket::set_recv_ttl) is set
+    fn recv_ttl(&self) -> Result<bool> {
+        Err(NetworkError::Unsupported)
+    }
+
+    /// Receives a datagram along with the TTL that it arrived with, which
+    /// is only known when the flag set by
+    /// [`set_recv_ttl`](VirtualUdpSocket::set_recv_ttl) is set
+    fn try_recv_from_ttl(
+        &mut self,
+        buf: &mut [MaybeUninit<u8>],
+        peek: bool,
+    ) -> Result<(usize, SocketAddr, Option<u8>)> {
+        self.try_recv_from(buf, peek)
+            .map(|(amt, addr)| (amt, addr, None))
+    }
+
     /// Tells this interface that it will subscribe to a
     /// particular multicast address. This applies to IPv4 addresses
     fn join_multicast_v4(&mut self, multiaddr: Ipv4Addr, iface: Ipv4Addr) -> Result<()>;
@@ -6,8 +6,8 @@ use std::{collections::HashMap, sync::Arc};
 
 use crate::tcp_pair::TcpSocketHalf;
 use crate::{
-    InterestHandler, IpAddr, IpCidr, Ipv4Addr, Ipv6Addr, NetworkError, VirtualIoSource,
-    VirtualNetworking, VirtualTcpListener, VirtualTcpSocket,
+    InterestHandler, IpAddr, IpCidr, Ipv4Addr, Ipv6Addr, NetworkError, NetworkInterface,
+    VirtualIoSource, VirtualNetworking, VirtualTcpListener, VirtualTcpSocket,
 };
 use virtual_mio::InterestType;
 
@@ -113,6 +113,17 @@ impl VirtualNetworking for LoopbackNetworking {
         Ok(state.ip_addresses.clone())
     }
 
+    async fn interfaces(&self) -> crate::Result<Vec<NetworkInterface>> {
+        let state: std::sync::MutexGuard<'_, LoopbackNetworkingState> = self.state.lock().unwrap();
+        Ok(vec![NetworkInterface {
+            name: "lo".to_string(),
+            addrs: state.ip_addresses.clone(),
+            up: true,
+            loopback: true,
+            multicast: false,
+        }])
+    }
+
     async fn listen_tcp(
         &self,
         mut addr: SocketAddr,
@@ -9,6 +9,7 @@ use serde::{Deserialize, Serialize};
 pub use super::IpCidr;
 pub use super::IpRoute;
 pub use super::NetworkError;
+pub use super::NetworkInterface;
 pub use super::SocketStatus;
 pub use super::StreamSecurity;
 
@@ -69,6 +70,8 @@ pub enum RequestType {
     GetIpList,
     /// Returns the hardware MAC address for this interface
     GetMac,
+    /// Lists the network interfaces and the addresses assigned to them
+    GetInterfaces,
     /// Adds a default gateway to the routing table
     GatewaySet(IpAddr),
     /// Adds a specific route to the routing table
@@ -263,6 +266,8 @@ pub enum ResponseType {
     SocketAddr(SocketAddr),
     /// Represents a MAC address
     Mac([u8; 6]),
+    /// List of network interfaces
+    InterfaceList(Vec<NetworkInterface>),
     /// List of CIDR routes from a routing table
     CidrList(Vec<IpCidr>),
     /// List of IP routes from a routing table
@@ -1,6 +1,6 @@
 use crate::meta::{FrameSerializationFormat, ResponseType};
 use crate::rx_tx::{RemoteRx, RemoteTx, RemoteTxWakers};
-use crate::{IpCidr, IpRoute, NetworkError, StreamSecurity, VirtualIcmpSocket};
+use crate::{IpCidr, IpRoute, NetworkError, NetworkInterface, StreamSecurity, VirtualIcmpSocket};
 use crate::{
     VirtualNetworking, VirtualRawSocket, VirtualTcpListener, VirtualTcpSocket, VirtualUdpSocket,
     meta::{MessageRequest, MessageResponse, RequestType, SocketId},
@@ -228,6 +228,10 @@ impl VirtualNetworking for RemoteNetworkingServer {
         self.inner.mac().await
     }
 
+    async fn interfaces(&self) -> Result<Vec<NetworkInterface>, NetworkError> {
+        self.inner.interfaces().await
+    }
+
     async fn gateway_set(&self, ip: IpAddr) -> Result<(), NetworkError> {
         self.inner.gateway_set(ip).await
     }
@@ -708,6 +712,16 @@ impl RemoteNetworkingServerDriver {
                     req_id,
                 )
             }
+            RequestType::GetInterfaces => self.process_async_inner(
+                move |inner: Arc<dyn VirtualNetworking + Send + Sync>| async move {
+                    inner.interfaces().await
+                },
+                |ret| match ret {
+                    Ok(interfaces) => ResponseType::InterfaceList(interfaces),
+                    Err(err) => ResponseType::Err(err),
+                },
+                req_id,
+            ),
             RequestType::GatewaySet(ip) => self.process_async_noop(
                 move |inner: Arc<dyn VirtualNetworking + Send + Sync>| async move {
                     inner.gateway_set(ip).await
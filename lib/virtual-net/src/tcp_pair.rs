@@ -189,17 +189,17 @@ impl SocketBuffer {
             }
             return Err(NetworkError::WouldBlock);
         }
-        if data.len() > available {
-            if all_or_nothing {
-                if let Some(waker) = waker {
-                    state.add_waker(waker)
-                }
-                return Err(NetworkError::WouldBlock);
+        if data.len() > available && all_or_nothing {
+            if let Some(waker) = waker {
+                state.add_waker(waker)
             }
-            let amt = state.buffer.enqueue_slice(&data[..available]);
-            return Ok(amt);
+            return Err(NetworkError::WouldBlock);
         }
-        let amt = state.buffer.enqueue_slice(data);
+        // Whatever fits is sent, the reader has to hear about a partial send
+        // just as much as about a full one
+        let amt = state
+            .buffer
+            .enqueue_slice(&data[..data.len().min(available)]);
 
         if let Some(handler) = state.push_handler.as_mut() {
             handler.push_interest(InterestType::Readable);
@@ -682,3 +682,62 @@ impl TcpSocketHalf {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Default, Clone)]
+    struct RecordingHandler {
+        interests: Arc<Mutex<Vec<InterestType>>>,
+    }
+    impl InterestHandler for RecordingHandler {
+        fn push_interest(&mut self, interest: InterestType) {
+            self.interests.lock().unwrap().push(interest);
+        }
+
+        fn pop_interest(&mut self, _interest: InterestType) -> bool {
+            false
+        }
+
+        fn has_interest(&self, _interest: InterestType) -> bool {
+            false
+        }
+    }
+
+    fn recv(socket: &mut TcpSocketHalf) -> Vec<u8> {
+        let mut buf = [std::mem::MaybeUninit::new(0u8); 64];
+        let amt = socket.try_recv(&mut buf, false).unwrap();
+        buf[..amt]
+            .iter()
+            .map(|b| unsafe { b.assume_init() })
+            .collect()
+    }
+
+    #[test]
+    fn send_reports_the_bytes_that_fit_in_the_buffer() {
+        let addr: SocketAddr = "127.0.0.1:80".parse().unwrap();
+        let (mut a, mut b) = TcpSocketHalf::channel(8, addr, addr);
+        let handler = RecordingHandler::default();
+        b.set_handler(Box::new(handler.clone())).unwrap();
+
+        assert_eq!(a.try_send(b"hello").unwrap(), 5);
+        assert_eq!(a.try_send(b"world").unwrap(), 3);
+        assert!(matches!(a.try_send(b"!"), Err(NetworkError::WouldBlock)));
+
+        // The reader is told about the partial send as well
+        let readable = handler
+            .interests
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|interest| **interest == InterestType::Readable)
+            .count();
+        assert_eq!(readable, 2);
+        assert_eq!(recv(&mut b), b"hellowor");
+
+        // Once the buffer was drained there is room again
+        assert_eq!(a.try_send(b"ld").unwrap(), 2);
+        assert_eq!(recv(&mut b), b"ld");
+    }
+}
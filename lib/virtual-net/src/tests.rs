@@ -132,6 +132,18 @@ async fn test_tcp_with_mpsc() {
     test_tcp(client, server).await
 }
 
+#[cfg(feature = "remote")]
+#[cfg_attr(windows, ignore)]
+#[traced_test]
+#[tokio::test(flavor = "multi_thread")]
+#[serial_test::serial]
+async fn test_interfaces_with_mpsc() {
+    let (client, _server) = setup_mpsc().await;
+
+    let local = LocalNetworking::new().interfaces().await.unwrap();
+    assert_eq!(client.interfaces().await.unwrap(), local);
+}
+
 // Disabled on musl due to flakiness.
 // See https://github.com/wasmerio/wasmer/issues/4425
 #[cfg(not(target_env = "musl"))]
@@ -30,6 +30,19 @@ pub mod file {
 
     pub const __WASI_LOOKUP_SYMLINK_FOLLOW: LookupFlags = 1;
 
+    /// Request code of `fd_ioctl`, the values match the ones used by Linux
+    pub type IoctlRequest = u32;
+
+    /// Sets (non-zero) or clears (zero) `Fdflags::NONBLOCK`, the argument
+    /// is an `i32`
+    pub const IOCTL_FIONBIO: IoctlRequest = 0x5421;
+    /// Writes the number of bytes that can be read without blocking into
+    /// the `i32` argument
+    pub const IOCTL_FIONREAD: IoctlRequest = 0x541B;
+    /// Writes the size of the terminal into the argument, which is a
+    /// `struct winsize` (four `u16` for rows, columns, width and height)
+    pub const IOCTL_TIOCGWINSZ: IoctlRequest = 0x5413;
+
     /// function for debugging rights issues
     #[allow(dead_code)]
     pub fn print_right_set(rights: Rights) {
@@ -285,6 +298,7 @@ pub mod net {
     pub const __WASI_SOCK_SEND_INPUT_DONT_WAIT: SiFlags = 1 << 0;
 
     pub const __WASI_SOCK_RECV_OUTPUT_DATA_TRUNCATED: RoFlags = 1 << 0;
+    pub const __WASI_SOCK_RECV_OUTPUT_FDS_TRUNCATED: RoFlags = 1 << 1;
 
     pub const __WASI_SHUT_RD: SdFlags = 1 << 0;
     pub const __WASI_SHUT_WR: SdFlags = 1 << 1;
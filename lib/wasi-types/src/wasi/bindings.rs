@@ -688,6 +688,9 @@ wai_bindgen_rust::bitflags::bitflags! {
     pub struct Fdflagsext : u16 {
         #[doc = " Close this file in the child process when spawning one."]
         const CLOEXEC = 1 << 0;
+        #[doc = " Hold an exclusive lock on the file while the file is open, which"]
+        #[doc = " other processes of the host respect as well."]
+        const EXCLUSIVE = 1 << 1;
     }
 }
 impl Fdflagsext {
@@ -1057,6 +1060,8 @@ pub enum Sockoption {
     MulticastTtlV4,
     Type,
     Proto,
+    BindToDevice,
+    RecvTtl,
 }
 impl core::fmt::Debug for Sockoption {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
@@ -1088,6 +1093,8 @@ impl core::fmt::Debug for Sockoption {
             Sockoption::MulticastTtlV4 => f.debug_tuple("Sockoption::MulticastTtlV4").finish(),
             Sockoption::Type => f.debug_tuple("Sockoption::Type").finish(),
             Sockoption::Proto => f.debug_tuple("Sockoption::Proto").finish(),
+            Sockoption::BindToDevice => f.debug_tuple("Sockoption::BindToDevice").finish(),
+            Sockoption::RecvTtl => f.debug_tuple("Sockoption::RecvTtl").finish(),
         }
     }
 }
@@ -3209,6 +3216,8 @@ unsafe impl wasmer::FromToNativeWasmType for Sockoption {
             24 => Self::MulticastTtlV4,
             25 => Self::Type,
             26 => Self::Proto,
+            27 => Self::BindToDevice,
+            28 => Self::RecvTtl,
 
             q => {
                 tracing::debug!("could not serialize number {q} to enum Sockoption");
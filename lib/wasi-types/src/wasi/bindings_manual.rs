@@ -154,6 +154,8 @@ impl std::fmt::Display for Sockoption {
             Self::MulticastTtlV4 => "Sockoption::MulticastTtlV4",
             Self::Type => "Sockoption::Type",
             Self::Proto => "Sockoption::Proto",
+            Self::BindToDevice => "Sockoption::BindToDevice",
+            Self::RecvTtl => "Sockoption::RecvTtl",
         };
         write!(f, "{s}")
     }
@@ -370,6 +372,7 @@ impl From<std::io::Error> for Errno {
             ErrorKind::Other => Errno::Io,
             ErrorKind::UnexpectedEof => Errno::Io,
             ErrorKind::Unsupported => Errno::Notsup,
+            ErrorKind::StorageFull => Errno::Nospc,
             _ => Errno::Io,
         }
     }
@@ -392,6 +395,24 @@ unsafe impl wasmer::FromToNativeWasmType for JoinFlags {
     }
 }
 
+// TODO: if necessary, must be implemented in wit-bindgen
+unsafe impl wasmer::FromToNativeWasmType for Subclockflags {
+    type Native = i32;
+
+    fn to_native(self) -> Self::Native {
+        self.bits() as i32
+    }
+
+    fn from_native(n: Self::Native) -> Self {
+        Self::from_bits_truncate(n as u16)
+    }
+
+    fn is_from_store(&self, _store: &impl wasmer::AsStoreRef) -> bool {
+        // TODO: find correct implementation
+        false
+    }
+}
+
 // TODO: if necessary, must be implemented in wit-bindgen
 unsafe impl wasmer::FromToNativeWasmType for DlFlags {
     type Native = i32;
@@ -518,3 +518,275 @@ unsafe impl<M: MemorySize> ValueType for ProcSpawnFdOp<M> {
     #[inline]
     fn zero_padding_bytes(&self, _bytes: &mut [MaybeUninit<u8>]) {}
 }
+
+/// Value of a resource limit that means there is no limit
+pub const RLIM_INFINITY: u64 = u64::MAX;
+
+#[doc = " Resource whose limit is queried or changed, the values match the ones used by Linux."]
+#[repr(u32)]
+#[derive(Clone, Copy, PartialEq, Eq, num_enum :: TryFromPrimitive, Hash)]
+#[cfg_attr(feature = "enable-serde", derive(Serialize, Deserialize))]
+pub enum RlimitResource {
+    #[doc = " CPU time in seconds."]
+    Cpu = 0,
+    #[doc = " Number of threads of the process."]
+    Nproc = 6,
+    #[doc = " One more than the highest file descriptor number that can be opened."]
+    Nofile = 7,
+    #[doc = " Size of the linear memory in bytes."]
+    As = 9,
+    #[doc = " Unknown."]
+    Unknown = 255,
+}
+impl core::fmt::Debug for RlimitResource {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            RlimitResource::Cpu => f.debug_tuple("RLIMIT_CPU").finish(),
+            RlimitResource::Nproc => f.debug_tuple("RLIMIT_NPROC").finish(),
+            RlimitResource::Nofile => f.debug_tuple("RLIMIT_NOFILE").finish(),
+            RlimitResource::As => f.debug_tuple("RLIMIT_AS").finish(),
+            RlimitResource::Unknown => f.debug_tuple("Unknown").finish(),
+        }
+    }
+}
+// TODO: if necessary, must be implemented in wit-bindgen
+unsafe impl ValueType for RlimitResource {
+    #[inline]
+    fn zero_padding_bytes(&self, _bytes: &mut [MaybeUninit<u8>]) {}
+}
+
+unsafe impl wasmer::FromToNativeWasmType for RlimitResource {
+    type Native = i32;
+
+    fn to_native(self) -> Self::Native {
+        self as i32
+    }
+
+    fn from_native(n: Self::Native) -> Self {
+        match n {
+            0 => Self::Cpu,
+            6 => Self::Nproc,
+            7 => Self::Nofile,
+            9 => Self::As,
+
+            q => {
+                tracing::debug!("could not serialize number {q} to enum RlimitResource");
+                Self::Unknown
+            }
+        }
+    }
+
+    fn is_from_store(&self, _store: &impl wasmer::AsStoreRef) -> bool {
+        false
+    }
+}
+
+#[doc = " Soft and hard limit of a resource, either of them can be `RLIM_INFINITY`."]
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "enable-serde", derive(Serialize, Deserialize))]
+pub struct Rlimit {
+    #[doc = " The limit that is enforced, it can be raised up to the hard limit."]
+    pub rlim_cur: u64,
+    #[doc = " The ceiling of the soft limit, it can only be lowered."]
+    pub rlim_max: u64,
+}
+impl Default for Rlimit {
+    fn default() -> Self {
+        Self {
+            rlim_cur: RLIM_INFINITY,
+            rlim_max: RLIM_INFINITY,
+        }
+    }
+}
+unsafe impl ValueType for Rlimit {
+    #[inline]
+    fn zero_padding_bytes(&self, _bytes: &mut [MaybeUninit<u8>]) {}
+}
+
+#[doc = " Information about the system that a guest runs on, see `sysinfo`."]
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "enable-serde", derive(Serialize, Deserialize))]
+pub struct Sysinfo {
+    #[doc = " Number of logical CPUs that are online."]
+    pub nprocs: u32,
+    #[doc = " Size of a page of memory in bytes."]
+    pub page_size: u32,
+    #[doc = " Total amount of memory in bytes, zero if it is not known."]
+    pub total_memory: u64,
+    #[doc = " Amount of memory in bytes that is available, zero if it is not known."]
+    pub available_memory: u64,
+    #[doc = " Time since the system started in nanoseconds."]
+    pub uptime: u64,
+}
+unsafe impl ValueType for Sysinfo {
+    #[inline]
+    fn zero_padding_bytes(&self, _bytes: &mut [MaybeUninit<u8>]) {}
+}
+
+wai_bindgen_rust::bitflags::bitflags! {
+    #[doc = " Flags of a memory mapping of a file, a mapping can always be read."]
+    #[cfg_attr(feature = "enable-serde", derive(Serialize, Deserialize))]
+    pub struct Mmapflags : u16 {
+        #[doc = " The mapping can be written to (the equivalent of `PROT_WRITE`)."]
+        const WRITE = 1 << 0;
+        #[doc = " Changes to the mapping are written back to the file (the equivalent"]
+        #[doc = " of `MAP_SHARED`), otherwise they stay private to the mapping."]
+        const SHARED = 1 << 1;
+    }
+}
+
+unsafe impl wasmer::FromToNativeWasmType for Mmapflags {
+    type Native = i32;
+
+    fn to_native(self) -> Self::Native {
+        self.bits() as i32
+    }
+
+    fn from_native(n: Self::Native) -> Self {
+        Self::from_bits_truncate(n as u16)
+    }
+
+    fn is_from_store(&self, _store: &impl wasmer::AsStoreRef) -> bool {
+        false
+    }
+}
+
+wai_bindgen_rust::bitflags::bitflags! {
+    #[doc = " Flags of an advisory lock on a file, without `EXCLUSIVE` the lock is shared."]
+    #[cfg_attr(feature = "enable-serde", derive(Serialize, Deserialize))]
+    pub struct Lockflags : u16 {
+        #[doc = " Nobody else can hold a lock on the file (the equivalent of `LOCK_EX`),"]
+        #[doc = " otherwise others can hold shared locks alongside (`LOCK_SH`)."]
+        const EXCLUSIVE = 1 << 0;
+        #[doc = " Fails with `Errno::Again` instead of waiting for a lock that is held"]
+        #[doc = " by someone else (the equivalent of `LOCK_NB`)."]
+        const NONBLOCK = 1 << 1;
+    }
+}
+
+unsafe impl wasmer::FromToNativeWasmType for Lockflags {
+    type Native = i32;
+
+    fn to_native(self) -> Self::Native {
+        self.bits() as i32
+    }
+
+    fn from_native(n: Self::Native) -> Self {
+        Self::from_bits_truncate(n as u16)
+    }
+
+    fn is_from_store(&self, _store: &impl wasmer::AsStoreRef) -> bool {
+        false
+    }
+}
+
+wai_bindgen_rust::bitflags::bitflags! {
+    #[doc = " Flags of a network interface, see `net_interfaces`."]
+    #[cfg_attr(feature = "enable-serde", derive(Serialize, Deserialize))]
+    pub struct Netifflags : u16 {
+        #[doc = " The interface is up (the equivalent of `IFF_UP`)."]
+        const UP = 1 << 0;
+        #[doc = " The interface only loops back to the host (`IFF_LOOPBACK`)."]
+        const LOOPBACK = 1 << 1;
+        #[doc = " The interface supports multicast (`IFF_MULTICAST`)."]
+        const MULTICAST = 1 << 2;
+    }
+}
+
+#[doc = " A network interface, see `net_interfaces`."]
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "enable-serde", derive(Serialize, Deserialize))]
+pub struct Netif {
+    #[doc = " Name of the interface, padded with zeros (the name is cut short"]
+    #[doc = " when it doesn't fit with a zero after it)."]
+    pub name: [u8; 16],
+    #[doc = " The bits of the `Netifflags` of the interface."]
+    pub flags: u16,
+    pub _padding: u16,
+    #[doc = " Index of the first address of the interface in the addresses."]
+    pub addrs_offset: u32,
+    #[doc = " Number of addresses of the interface."]
+    pub naddrs: u32,
+}
+unsafe impl ValueType for Netif {
+    #[inline]
+    fn zero_padding_bytes(&self, _bytes: &mut [MaybeUninit<u8>]) {}
+}
+
+wai_bindgen_rust::bitflags::bitflags! {
+    #[doc = " Changes to the file system that a watch reports, see `fs_watch_add`."]
+    #[cfg_attr(feature = "enable-serde", derive(Serialize, Deserialize))]
+    pub struct Fswatchmask : u16 {
+        #[doc = " A file or directory was created (the equivalent of `IN_CREATE`)."]
+        const CREATE = 1 << 0;
+        #[doc = " A file was written to (the equivalent of `IN_MODIFY`)."]
+        const MODIFY = 1 << 1;
+        #[doc = " A file or directory was removed (the equivalent of `IN_DELETE`)."]
+        const DELETE = 1 << 2;
+        #[doc = " Events were lost because the guest didn't read them fast enough"]
+        #[doc = " (the equivalent of `IN_Q_OVERFLOW`), this is always reported."]
+        const OVERFLOW = 1 << 3;
+    }
+}
+
+unsafe impl wasmer::FromToNativeWasmType for Fswatchmask {
+    type Native = i32;
+
+    fn to_native(self) -> Self::Native {
+        self.bits() as i32
+    }
+
+    fn from_native(n: Self::Native) -> Self {
+        Self::from_bits_truncate(n as u16)
+    }
+
+    fn is_from_store(&self, _store: &impl wasmer::AsStoreRef) -> bool {
+        false
+    }
+}
+
+#[doc = " An event that is read from a watch, see `fs_watch_add`. The name of"]
+#[doc = " the entry follows right after the event, it is empty when the change"]
+#[doc = " is to the watched path itself."]
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "enable-serde", derive(Serialize, Deserialize))]
+pub struct Fsevent {
+    #[doc = " The bits of the `Fswatchmask` of the change."]
+    pub mask: u16,
+    pub _padding: u16,
+    #[doc = " Number of bytes of the name that follows the event."]
+    pub name_len: u32,
+}
+unsafe impl ValueType for Fsevent {
+    #[inline]
+    fn zero_padding_bytes(&self, _bytes: &mut [MaybeUninit<u8>]) {}
+}
+
+#[doc = " Statistics of a socket, see `sock_stats`. The queues that the networking"]
+#[doc = " can't tell about are zero."]
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "enable-serde", derive(Serialize, Deserialize))]
+pub struct Sockstats {
+    #[doc = " Number of bytes that were sent through the socket."]
+    pub bytes_sent: u64,
+    #[doc = " Number of bytes that were received from the socket."]
+    pub bytes_received: u64,
+    #[doc = " Number of bytes that were written but not sent to the peer yet."]
+    pub send_queued: u64,
+    #[doc = " Number of bytes that were received but not read yet."]
+    pub recv_queued: u64,
+    #[doc = " Number of connections that wait to be accepted by a listening socket."]
+    pub accept_queued: u64,
+    #[doc = " The `Sockstatus` of the socket."]
+    pub status: u8,
+    pub _padding: [u8; 7],
+}
+unsafe impl ValueType for Sockstats {
+    #[inline]
+    fn zero_padding_bytes(&self, _bytes: &mut [MaybeUninit<u8>]) {}
+}
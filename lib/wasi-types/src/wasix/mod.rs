@@ -20,9 +20,10 @@ pub struct WasiMemoryLayout {
     pub stack_upper: u64,
     /// This is the bottom part of the stack (anything more below this is a stack overflow)
     pub stack_lower: u64,
-    /// Piece of memory that is marked as none readable/writable so stack overflows cause an exception
-    /// TODO: This field will need to be used to mark the guard memory as inaccessible
-    #[allow(dead_code)]
+    /// Piece of memory right below the stack that it must never grow into, a thread
+    /// whose stack pointer ends up in there (or even lower) has overflowed its stack.
+    /// The memory itself stays accessible, the stack pointer is checked whenever the
+    /// thread makes a syscall and after it traps.
     pub guard_size: u64,
     /// Total size of the stack
     pub stack_size: u64,
@@ -32,3 +33,11 @@ pub struct WasiMemoryLayout {
     /// TLS base of the main module.
     pub tls_base: Option<u64>,
 }
+
+impl WasiMemoryLayout {
+    /// Returns true if the stack pointer has left the stack, which happens
+    /// through its bottom (possibly wrapping around the start of the memory)
+    pub fn is_stack_overflow(&self, stack_pointer: u64) -> bool {
+        self.stack_size > 0 && !(self.stack_lower..=self.stack_upper).contains(&stack_pointer)
+    }
+}
@@ -0,0 +1,135 @@
+use std::{io, net::TcpListener, sync::Arc};
+
+use criterion::{Criterion, criterion_group, criterion_main};
+use virtual_fs::{AsyncWriteExt, FileSystem, TmpFileSystem};
+use virtual_mio::block_on;
+use wasmer::{Engine, Module};
+use wasmer_types::ModuleHash;
+use wasmer_wasix::runners::wasi::{RuntimeOrEngine, WasiRunner};
+
+const FILE_SIZE: usize = 1024 * 1024;
+
+/// Connects to the port and sends `data/file` down the connection with the
+/// function named `send`, either `$send_file` (which uses `sock_send_file`)
+/// or `$read_and_send` (which reads the file into the memory of the guest
+/// and sends it from there, the way a guest does it without
+/// `sock_send_file`)
+fn program(port: u16, send: &str) -> String {
+    format!(
+        r#"
+    (module
+        (import "wasi_snapshot_preview1" "path_open" (func $path_open (param i32 i32 i32 i32 i32 i64 i64 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "fd_pread" (func $fd_pread (param i32 i32 i32 i64 i32) (result i32)))
+        (import "wasix_32v1" "sock_open" (func $sock_open (param i32 i32 i32 i32) (result i32)))
+        (import "wasix_32v1" "sock_connect" (func $sock_connect (param i32 i32) (result i32)))
+        (import "wasix_32v1" "sock_send" (func $sock_send (param i32 i32 i32 i32 i32) (result i32)))
+        (import "wasix_32v1" "sock_send_file" (func $sock_send_file (param i32 i32 i64 i64 i32) (result i32)))
+
+        (memory 2)
+        (export "memory" (memory 0))
+
+        (data (i32.const 100) "data/file")
+
+        ;; 127.0.0.1:{port}
+        (data (i32.const 304) "\7f\00\00\01")
+
+        (func $check (param i32)
+            (if (local.get 0) (then unreachable))
+        )
+
+        (func $send_file (param $sock i32) (param $file i32)
+            (call $check (call $sock_send_file (local.get $sock) (local.get $file)
+                (i64.const 0) (i64.const {FILE_SIZE}) (i32.const 8)))
+        )
+
+        ;; Reads the file in chunks of 64 KiB into the second page and sends
+        ;; each chunk, which may take more than one `sock_send`
+        (func $read_and_send (param $sock i32) (param $file i32)
+            (local $offset i64)
+            (local $sent i32)
+            (loop $chunks
+                (i32.store (i32.const 16) (i32.const 65536))
+                (i32.store (i32.const 20) (i32.const 65536))
+                (call $check (call $fd_pread (local.get $file) (i32.const 16) (i32.const 1) (local.get $offset) (i32.const 24)))
+                (local.set $sent (i32.const 0))
+                (loop $partial
+                    (i32.store (i32.const 32) (i32.add (i32.const 65536) (local.get $sent)))
+                    (i32.store (i32.const 36) (i32.sub (i32.load (i32.const 24)) (local.get $sent)))
+                    (call $check (call $sock_send (local.get $sock) (i32.const 32) (i32.const 1) (i32.const 0) (i32.const 40)))
+                    (local.set $sent (i32.add (local.get $sent) (i32.load (i32.const 40))))
+                    (br_if $partial (i32.lt_u (local.get $sent) (i32.load (i32.const 24))))
+                )
+                (local.set $offset (i64.add (local.get $offset) (i64.extend_i32_u (local.get $sent))))
+                (br_if $chunks (i64.lt_u (local.get $offset) (i64.const {FILE_SIZE})))
+            )
+        )
+
+        (func $main (export "_start")
+            (call $check (call $path_open (i32.const 3) (i32.const 0) (i32.const 100) (i32.const 9)
+                (i32.const 0) (i64.const -1) (i64.const -1) (i32.const 0) (i32.const 200)))
+            (call $check (call $sock_open (i32.const 1) (i32.const 1) (i32.const 6) (i32.const 204)))
+            (i32.store16 (i32.const 300) (i32.const 1))
+            (i32.store16 (i32.const 302) (i32.const {port}))
+            (call $check (call $sock_connect (i32.load (i32.const 204)) (i32.const 300)))
+            (call ${send} (i32.load (i32.const 204)) (i32.load (i32.const 200)))
+        )
+    )
+    "#
+    )
+}
+
+/// Accepts every connection to the listener and throws away what is sent
+/// down it
+fn drain(listener: TcpListener) {
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            std::thread::spawn(move || io::copy(&mut stream, &mut io::sink()));
+        }
+    });
+}
+
+pub fn send_file(c: &mut Criterion) {
+    let rt = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .unwrap();
+    let _guard = rt.enter();
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    drain(listener);
+
+    let data = Arc::new(TmpFileSystem::new());
+    let mut file = data
+        .new_open_options()
+        .create(true)
+        .write(true)
+        .open("/file")
+        .unwrap();
+    block_on(file.write_all(&vec![b'x'; FILE_SIZE])).unwrap();
+
+    let engine = Engine::default();
+    let run = |module: &Module| {
+        let mut runner = WasiRunner::new();
+        runner.with_mount("/data".to_string(), data.clone());
+        runner
+            .run_wasm(
+                RuntimeOrEngine::Engine(engine.clone()),
+                "bench",
+                module.clone(),
+                ModuleHash::random(),
+            )
+            .unwrap();
+    };
+
+    let mut group = c.benchmark_group("sending a 1 MiB file down a TCP connection");
+    let module = Module::new(&engine, program(port, "send_file")).unwrap();
+    group.bench_function("sock_send_file", |b| b.iter(|| run(&module)));
+    let module = Module::new(&engine, program(port, "read_and_send")).unwrap();
+    group.bench_function("fd_pread and sock_send", |b| b.iter(|| run(&module)));
+    group.finish();
+}
+
+criterion_group!(benches, send_file);
+criterion_main!(benches);
@@ -4,24 +4,55 @@ use crate::{
     RewindState, SpawnError, WasiError, WasiRuntimeError,
     os::task::{
         TaskJoinHandle,
-        thread::{RewindResultType, WasiThreadRunGuard},
+        thread::{RewindResultType, WasiThreadError, WasiThreadRunGuard},
     },
     runtime::{
         ModuleInput, TaintReason,
         module_cache::HashedModuleData,
         task_manager::{
-            TaskWasm, TaskWasmRecycle, TaskWasmRecycleProperties, TaskWasmRunProperties,
+            SpawnType, TaskWasm, TaskWasmRecycle, TaskWasmRecycleProperties, TaskWasmRunProperties,
         },
     },
     state::context_switching::ContextSwitchingEnvironment,
     syscalls::rewind_ext,
+    utils::core_dump::write_core_dump,
 };
 use crate::{Runtime, WasiEnv, WasiFunctionEnv};
-use std::{borrow::Cow, sync::Arc};
+use std::{borrow::Cow, collections::HashMap, sync::Arc};
 use tracing::*;
 use virtual_mio::block_on;
-use wasmer::{Function, Memory32, Memory64, Module, RuntimeError, Store, Value};
-use wasmer_wasix_types::wasi::Errno;
+use wasmer::{
+    AsStoreMut, ExternType, Function, Imports, Memory32, Memory64, MemoryError, Module, Pages,
+    RuntimeError, Store, Value,
+};
+use wasmer_wasix_types::wasi::{Errno, Fd as WasiFd};
+
+/// Which of the file descriptors in the environment a spawned process
+/// inherits, everything else is closed before it starts running.
+///
+/// Descriptors that are marked as close-on-exec are closed regardless and
+/// the preopened directories are always kept.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum FdInheritance {
+    /// Inherit every descriptor, this is how `exec` behaves
+    All,
+    /// Inherit only stdin, stdout and stderr
+    #[default]
+    Stdio,
+    /// Inherit only the listed descriptors
+    Only(Vec<WasiFd>),
+}
+
+impl FdInheritance {
+    /// Returns true if the descriptor is inherited by the spawned process
+    pub fn inherits(&self, fd: WasiFd) -> bool {
+        match self {
+            Self::All => true,
+            Self::Stdio => fd <= 2,
+            Self::Only(fds) => fds.contains(&fd),
+        }
+    }
+}
 
 #[tracing::instrument(level = "trace", skip_all, fields(%name, package_id=%binary.id))]
 pub async fn spawn_exec(
@@ -29,6 +60,7 @@ pub async fn spawn_exec(
     name: &str,
     env: WasiEnv,
     runtime: &Arc<dyn Runtime + Send + Sync + 'static>,
+    inherit: FdInheritance,
 ) -> Result<TaskJoinHandle, SpawnError> {
     spawn_union_fs(&env, &binary).await?;
 
@@ -40,7 +72,8 @@ pub async fn spawn_exec(
     // any longer
     drop(binary);
 
-    spawn_exec_module(module, env, runtime)
+    env.process.set_title(name);
+    spawn_exec_module(module, env, runtime, inherit)
 }
 
 #[tracing::instrument(level = "trace", skip_all, fields(%name))]
@@ -49,10 +82,12 @@ pub async fn spawn_exec_wasm(
     name: &str,
     env: WasiEnv,
     runtime: &Arc<dyn Runtime + Send + Sync + 'static>,
+    inherit: FdInheritance,
 ) -> Result<TaskJoinHandle, SpawnError> {
     let module = spawn_load_module(name, wasm, runtime).await?;
 
-    spawn_exec_module(module, env, runtime)
+    env.process.set_title(name);
+    spawn_exec_module(module, env, runtime, inherit)
 }
 
 pub fn package_command_by_name<'a>(
@@ -124,7 +159,12 @@ pub fn spawn_exec_module(
     module: Module,
     env: WasiEnv,
     runtime: &Arc<dyn Runtime + Send + Sync + 'static>,
+    inherit: FdInheritance,
 ) -> Result<TaskJoinHandle, SpawnError> {
+    // A memory that the runtime can't provide would otherwise only fail
+    // once the task is launched, with a far less helpful error
+    check_memory_import(&module, &env)?;
+
     // Create a new task manager
     let tasks = runtime.task_manager();
 
@@ -139,9 +179,15 @@ pub fn spawn_exec_module(
         tasks_outer
             .task_wasm(
                 TaskWasm::new(Box::new(run_exec), env, module, true, true).with_pre_run(Box::new(
-                    |ctx, store| {
+                    move |ctx, store| {
                         Box::pin(async move {
-                            ctx.data(store).state.fs.close_cloexec_fds().await;
+                            let fs = &ctx.data(store).state.fs;
+                            // The module starts out with a memory of its own
+                            fs.mappings.lock().unwrap().clear();
+                            fs.close_cloexec_fds().await;
+                            if inherit != FdInheritance::All {
+                                fs.close_uninherited_fds(|fd| inherit.inherits(fd)).await;
+                            }
                         })
                     },
                 )),
@@ -155,6 +201,96 @@ pub fn spawn_exec_module(
     Ok(join_handle)
 }
 
+/// Checks that the memory imported by the module (if any) fits within the
+/// limit of the environment and that the runtime can actually create it,
+/// the way it does when the module is spawned
+pub(crate) fn check_memory_import(module: &Module, env: &WasiEnv) -> Result<(), SpawnError> {
+    let Some(requested) = module
+        .imports()
+        .memories()
+        .next()
+        .map(|import| *import.ty())
+    else {
+        return Ok(());
+    };
+
+    // Memories without a maximum are created with the largest one there is
+    let max_requested = requested.maximum.unwrap_or(Pages::max_value());
+    let result = match env.max_memory_pages {
+        Some(max_allowed) if max_requested > max_allowed => {
+            Err(MemoryError::MaximumMemoryTooLarge {
+                max_requested,
+                max_allowed,
+            })
+        }
+        _ => {
+            let mut store = env.runtime().new_store();
+            env.tasks()
+                .build_memory(
+                    &mut store.as_store_mut(),
+                    &SpawnType::CreateMemoryOfType(requested),
+                )
+                .map(|_| ())
+                .map_err(|err| match err {
+                    WasiThreadError::MemoryCreateFailed(err) => err,
+                    err => MemoryError::Generic(err.to_string()),
+                })
+        }
+    };
+
+    if let Err(error) = result {
+        error!(
+            "wasi[{}]::module imports a {} memory that can't be provided: {}",
+            env.pid(),
+            requested,
+            error
+        );
+        return Err(SpawnError::UnsatisfiableMemoryImport { requested, error });
+    }
+    Ok(())
+}
+
+/// Checks that everything the module imports is provided by WASIX or by the
+/// runtime, the way [`WasiEnv::instantiate`] resolves the imports. Memories
+/// are checked by [`check_memory_import`] and the imports of dynamically
+/// linked modules are resolved by the linker instead.
+pub(crate) fn check_imports(module: &Module, env: &WasiEnv) -> Result<(), SpawnError> {
+    if crate::state::is_dynamically_linked(module) {
+        return Ok(());
+    }
+
+    let mut store = env.runtime().new_store();
+    let func_env = WasiFunctionEnv::new(&mut store, env.clone());
+    let mut registry: HashMap<_, _> =
+        crate::import_object_for_all_wasi_versions(module, &mut store, &func_env.env)
+            .into_iter()
+            .collect();
+    let additional_imports = env
+        .runtime()
+        .additional_imports(module, &mut store.as_store_mut())
+        .map_err(|err| SpawnError::Other(err.into()))?;
+    for (key, value) in additional_imports.into_iter() {
+        registry.entry(key).or_insert(value);
+    }
+
+    let Err(missing) = Imports::resolve_from_registry(module, &registry) else {
+        return Ok(());
+    };
+    let imports: Vec<_> = missing
+        .into_iter()
+        .filter(|import| !matches!(import.ty, ExternType::Memory(_)))
+        .collect();
+    if imports.is_empty() {
+        return Ok(());
+    }
+    error!(
+        "wasi[{}]::module imports externs that are not provided: {:?}",
+        env.pid(),
+        imports
+    );
+    Err(SpawnError::MissingImports { imports })
+}
+
 /// # SAFETY
 /// This must be executed from the same thread that owns the instance as
 /// otherwise it will cause a panic
@@ -177,8 +313,20 @@ unsafe fn run_recycle(
 }
 
 pub fn run_exec(props: TaskWasmRunProperties) {
+    run_exec_inner(props, true)
+}
+
+/// Runs a module whose `_initialize` function already ran, that is one
+/// whose memory and globals were cloned from a
+/// [`ProcessTemplate`](crate::runtime::template::ProcessTemplate)
+pub(crate) fn run_exec_initialized(props: TaskWasmRunProperties) {
+    run_exec_inner(props, false)
+}
+
+fn run_exec_inner(props: TaskWasmRunProperties, call_initialize: bool) {
     let ctx = props.ctx;
     let mut store = props.store;
+    let _span = ctx.data(&store).process.span().clone().entered();
 
     // Create the WasiFunctionEnv
     let thread = WasiThreadRunGuard::new(ctx.data(&store).thread.clone());
@@ -186,14 +334,15 @@ pub fn run_exec(props: TaskWasmRunProperties) {
 
     // Perform the initialization
     // If this module exports an _initialize function, run that first.
-    if let Ok(initialize) = ctx
-        .data(&store)
-        .inner()
-        .main_module_instance_handles()
-        .instance
-        .exports
-        .get_function("_initialize")
-        .cloned()
+    if call_initialize
+        && let Ok(initialize) = ctx
+            .data(&store)
+            .inner()
+            .main_module_instance_handles()
+            .instance
+            .exports
+            .get_function("_initialize")
+            .cloned()
     {
         // This does not need a context switching environment as the documentation
         // states that that is only available after the first call to main
@@ -333,7 +482,9 @@ fn call_module(
                 // Create the callback that will be invoked when the thread respawns after a deep sleep
                 let rewind = deep.rewind;
                 let respawn = {
-                    move |ctx, store, rewind_result| {
+                    move |ctx: WasiFunctionEnv, store: Store, rewind_result| {
+                        ctx.data(&store).process.record_deep_sleep_resume();
+
                         // Call the thread
                         call_module(
                             ctx,
@@ -364,6 +515,7 @@ fn call_module(
                 Err(WasiError::DlSymbolResolutionFailed(symbol).into())
             }
             Err(err) => {
+                write_core_dump(&ctx, &mut store, &err);
                 runtime.on_taint(TaintReason::RuntimeError(err.clone()));
                 Err(WasiRuntimeError::from(err))
             }
@@ -447,6 +599,7 @@ fn resume_vfork(
         child_env.owned_handles.push(vfork.handle);
 
         // Terminate the child process
+        child_env.process.finish_vfork();
         child_env.process.terminate(code);
 
         // If the vfork contained a context-switching environment, exit now
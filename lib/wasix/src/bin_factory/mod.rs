@@ -1,5 +1,6 @@
 #![allow(clippy::result_large_err)]
 use std::{
+    borrow::Cow,
     collections::HashMap,
     future::Future,
     ops::Deref,
@@ -11,23 +12,25 @@ use std::{
 use anyhow::Context;
 use shared_buffer::OwnedBuffer;
 use virtual_fs::{AsyncReadExt, FileSystem};
-use wasmer::FunctionEnvMut;
+use wasmer::{FunctionEnvMut, Module};
+use wasmer_config::package::PackageId;
 use wasmer_package::utils::from_bytes;
 
 mod binary_package;
 mod exec;
 
+pub(crate) use self::exec::{check_imports, check_memory_import, run_exec_initialized};
 pub use self::{
     binary_package::*,
     exec::{
-        package_command_by_name, run_exec, spawn_exec, spawn_exec_module, spawn_exec_wasm,
-        spawn_load_module, spawn_union_fs,
+        FdInheritance, package_command_by_name, run_exec, spawn_exec, spawn_exec_module,
+        spawn_exec_wasm, spawn_load_module, spawn_union_fs,
     },
 };
 use crate::{
     Runtime, SpawnError, WasiEnv,
     os::{command::Commands, task::TaskJoinHandle},
-    runtime::module_cache::HashedModuleData,
+    runtime::{ModuleInput, module_cache::HashedModuleData},
 };
 
 #[derive(Debug, Clone)]
@@ -64,7 +67,7 @@ impl BinFactory {
         self.get_executable(name, fs)
             .await
             .and_then(|executable| match executable {
-                Executable::Wasm(_) => None,
+                Executable::Wasm(_) | Executable::Script(_) => None,
                 Executable::BinaryPackage(pkg) => Some(pkg),
             })
     }
@@ -75,33 +78,129 @@ impl BinFactory {
         env: WasiEnv,
     ) -> Pin<Box<dyn Future<Output = Result<TaskJoinHandle, SpawnError>> + 'a>> {
         Box::pin(async move {
-            // Find the binary (or die trying) and make the spawn type
-            let res = self
-                .get_executable(name.as_str(), Some(env.fs_root()))
-                .await
-                .ok_or_else(|| SpawnError::BinaryNotFound {
-                    binary: name.clone(),
-                });
-            let executable = res?;
-
-            // Execute
-            match executable {
-                Executable::Wasm(bytes) => {
-                    let data = HashedModuleData::new(bytes.clone());
-                    spawn_exec_wasm(data, name.as_str(), env, &self.runtime).await
-                }
-                Executable::BinaryPackage(pkg) => {
-                    {
-                        let cmd = package_command_by_name(&pkg, name.as_str())?;
-                        env.prepare_spawn(cmd);
-                    }
+            let executable = self.load(name.as_str(), &env).await?;
+            self.spawn_loaded(executable, env).await
+        })
+    }
 
-                    spawn_exec(pkg.as_ref().clone(), name.as_str(), env, &self.runtime).await
-                }
+    /// Finds the executable, compiles it and checks that its imports can be
+    /// resolved without running it, so that callers that have to give
+    /// something up to run it (like `exec` does with the process that calls
+    /// it) can first check that it is runnable.
+    pub async fn load(&self, name: &str, env: &WasiEnv) -> Result<LoadedExecutable, SpawnError> {
+        // Find the binary (or die trying) and make the spawn type
+        let executable = self
+            .get_executable(name, Some(env.fs_root()))
+            .await
+            .ok_or_else(|| SpawnError::BinaryNotFound {
+                binary: name.to_string(),
+            })?;
+
+        // A script is run by its interpreter
+        let (script, name, executable) = match executable {
+            Executable::Script(script) => {
+                let interpreter = self
+                    .get_executable(script.interpreter.as_str(), Some(env.fs_root()))
+                    .await
+                    .ok_or_else(|| SpawnError::InterpreterNotFound {
+                        script: name.to_string(),
+                        interpreter: script.interpreter.clone(),
+                    })?;
+                let interpreter_name = script.interpreter.clone();
+                (
+                    Some((name.to_string(), script)),
+                    interpreter_name,
+                    interpreter,
+                )
             }
+            executable => (None, name.to_string(), executable),
+        };
+
+        let (module, package) = match executable {
+            Executable::Wasm(bytes) => {
+                let data = HashedModuleData::new(bytes);
+                let module = spawn_load_module(name.as_str(), data, &self.runtime).await?;
+                (module, None)
+            }
+            Executable::BinaryPackage(pkg) => {
+                let cmd = package_command_by_name(&pkg, name.as_str())?;
+                let input = ModuleInput::Command(Cow::Borrowed(cmd));
+                let module = self.runtime.resolve_module(input, None, None).await?;
+                (module, Some(pkg))
+            }
+            // Interpreters that are scripts themselves are not supported
+            Executable::Script(_) => return Err(SpawnError::Unsupported),
+        };
+        check_memory_import(&module, env)?;
+        check_imports(&module, env)?;
+
+        Ok(LoadedExecutable {
+            name,
+            module,
+            package,
+            script,
         })
     }
 
+    /// Runs an executable that was [loaded](BinFactory::load) before in a
+    /// new process with the given environment.
+    pub async fn spawn_loaded(
+        &self,
+        executable: LoadedExecutable,
+        env: WasiEnv,
+    ) -> Result<TaskJoinHandle, SpawnError> {
+        // The interpreter gets the path of the script ahead of the original
+        // arguments, the process is still named after the script
+        if let Some((script_name, script)) = executable.script {
+            env.process.set_title(script_name.as_str());
+            let mut args = env.state.args.lock().unwrap();
+            let mut script_args = vec![script.interpreter];
+            script_args.extend(script.arg);
+            script_args.push(script_name);
+            script_args.extend(args.drain(..).skip(1));
+            *args = script_args;
+        } else {
+            env.process.set_title(executable.name.as_str());
+        }
+
+        if let Some(pkg) = executable.package {
+            let cmd = package_command_by_name(&pkg, executable.name.as_str())?;
+            env.prepare_spawn(cmd);
+            spawn_union_fs(&env, &pkg).await?;
+        }
+
+        spawn_exec_module(executable.module, env, &self.runtime, FdInheritance::All)
+    }
+
+    /// Compiles the commands of the given packages ahead of time and saves
+    /// them to the module cache, so that spawning them later is a cache hit.
+    ///
+    /// The packages are warmed concurrently. A package that fails to compile
+    /// doesn't stop the others, instead its error is returned.
+    pub async fn warm_cache(&self, packages: &[BinaryPackage]) -> Vec<(PackageId, SpawnError)> {
+        let warm_ups = packages.iter().map(|pkg| async move {
+            for cmd in &pkg.commands {
+                let input = ModuleInput::Command(Cow::Borrowed(cmd));
+                if let Err(err) = self.runtime.resolve_module(input, None, None).await {
+                    tracing::warn!(
+                        package_id = %pkg.id,
+                        command = cmd.name(),
+                        error = &err as &dyn std::error::Error,
+                        "Unable to warm the module cache",
+                    );
+                    return Some((pkg.id.clone(), err));
+                }
+            }
+            None
+        });
+
+        futures::future::join_all(warm_ups)
+            .await
+            .into_iter()
+            .flatten()
+            .collect()
+    }
+
     pub fn try_built_in(
         &self,
         name: String,
@@ -172,9 +271,61 @@ impl BinFactory {
     }
 }
 
+/// An executable that was found and compiled but is not running yet, see
+/// [`BinFactory::load`]
+pub struct LoadedExecutable {
+    /// Name of the command (the interpreter for scripts)
+    name: String,
+    module: Module,
+    /// The package of the command, its file system is merged into the one of
+    /// the process when it is spawned
+    package: Option<Arc<BinaryPackage>>,
+    /// The path of the script that is run by the interpreter
+    script: Option<(String, Shebang)>,
+}
+
 pub enum Executable {
     Wasm(OwnedBuffer),
     BinaryPackage(Arc<BinaryPackage>),
+    Script(Shebang),
+}
+
+/// The interpreter named by the `#!` line at the start of a script
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Shebang {
+    pub interpreter: String,
+    /// The rest of the line (if any), which is passed to the interpreter as
+    /// a single argument
+    pub arg: Option<String>,
+}
+
+impl Shebang {
+    /// Only this many bytes of the `#!` line are looked at, like on Linux
+    const MAX_LEN: usize = 256;
+
+    /// Parses the `#!` line at the start of a file, returns `None` when the
+    /// file doesn't start with one (for instance because it is a binary)
+    pub fn parse(data: &[u8]) -> Option<Self> {
+        let line = data.strip_prefix(b"#!")?;
+        let line = &line[..line.len().min(Self::MAX_LEN - 2)];
+        let line = match line.iter().position(|b| *b == b'\n') {
+            Some(end) => &line[..end],
+            None => line,
+        };
+        let line = std::str::from_utf8(line).ok()?.trim();
+
+        let (interpreter, arg) = match line.split_once([' ', '\t']) {
+            Some((interpreter, arg)) => (interpreter, Some(arg.trim())),
+            None => (line, None),
+        };
+        if interpreter.is_empty() {
+            return None;
+        }
+        Some(Self {
+            interpreter: interpreter.to_string(),
+            arg: arg.filter(|arg| !arg.is_empty()).map(str::to_string),
+        })
+    }
 }
 
 async fn load_executable_from_filesystem(
@@ -191,6 +342,9 @@ async fn load_executable_from_filesystem(
     // Fast path if the file is fully available in memory.
     // Prevents redundant copying of the file data.
     if let Some(buf) = f.as_owned_buffer() {
+        if let Some(script) = Shebang::parse(buf.as_slice()) {
+            return Ok(Executable::Script(script));
+        }
         if wasmer_package::utils::is_container(buf.as_slice()) {
             let bytes = buf.clone().into_bytes();
             if let Ok(container) = from_bytes(bytes.clone()) {
@@ -206,6 +360,9 @@ async fn load_executable_from_filesystem(
     } else {
         let mut data = Vec::with_capacity(f.size() as usize);
         f.read_to_end(&mut data).await.context("Read failed")?;
+        if let Some(script) = Shebang::parse(&data) {
+            return Ok(Executable::Script(script));
+        }
 
         let bytes: bytes::Bytes = data.into();
 
@@ -220,3 +377,112 @@ async fn load_executable_from_filesystem(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+
+    use crate::{
+        PluggableRuntime,
+        runtime::{
+            module_cache::ModuleCache, package_loader::BuiltinPackageLoader,
+            task_manager::VirtualTaskManager,
+        },
+    };
+
+    use super::*;
+
+    fn task_manager() -> Arc<dyn VirtualTaskManager + Send + Sync> {
+        cfg_if::cfg_if! {
+            if #[cfg(feature = "sys-thread")] {
+                Arc::new(crate::runtime::task_manager::tokio::TokioTaskManager::new(tokio::runtime::Handle::current()))
+            } else {
+                unimplemented!("Unable to get the task manager")
+            }
+        }
+    }
+
+    async fn package_with_atom(
+        name: &str,
+        atom: &[u8],
+        runtime: &PluggableRuntime,
+    ) -> (TempDir, BinaryPackage) {
+        let temp = TempDir::new().unwrap();
+        let wasmer_toml = format!(
+            r#"
+            [package]
+            name = "{name}"
+            version = "0.0.0"
+            description = "a dummy package"
+
+            [[module]]
+            name = "foo"
+            source = "foo.wasm"
+            abi = "wasi"
+
+            [[command]]
+            name = "cmd"
+            module = "foo"
+        "#
+        );
+        std::fs::write(temp.path().join("wasmer.toml"), wasmer_toml).unwrap();
+        std::fs::write(temp.path().join("foo.wasm"), atom).unwrap();
+
+        let pkg = BinaryPackage::from_dir(temp.path(), runtime).await.unwrap();
+        (temp, pkg)
+    }
+
+    #[tokio::test]
+    #[cfg_attr(
+        not(feature = "sys-thread"),
+        ignore = "The tokio task manager isn't available on this platform"
+    )]
+    async fn warm_cache_saves_modules_and_collects_failures() {
+        let mut runtime = PluggableRuntime::new(task_manager());
+        runtime.set_package_loader(
+            BuiltinPackageLoader::new()
+                .with_shared_http_client(runtime.http_client().unwrap().clone()),
+        );
+
+        let (_good_dir, good) = package_with_atom("some/good", b"\0asm\x01\0\0\0", &runtime).await;
+        let (_bad_dir, bad) = package_with_atom("some/bad", b"not wasm", &runtime).await;
+
+        let runtime = Arc::new(runtime);
+        let factory = BinFactory::new(runtime.clone());
+        let failures = factory.warm_cache(&[good.clone(), bad.clone()]).await;
+
+        // Only the broken package is reported
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0].0, bad.id);
+
+        // The working package can now be loaded straight from the cache
+        let cmd = good.get_command("cmd").unwrap();
+        runtime
+            .module_cache()
+            .load(*cmd.hash(), &runtime.engine())
+            .await
+            .unwrap();
+    }
+
+    #[test]
+    fn shebang_names_the_interpreter_and_its_argument() {
+        assert_eq!(
+            Shebang::parse(b"#!/bin/sh\necho hello\n"),
+            Some(Shebang {
+                interpreter: "/bin/sh".to_string(),
+                arg: None,
+            })
+        );
+        assert_eq!(
+            Shebang::parse(b"#! /usr/bin/env  python3 -u \r\n"),
+            Some(Shebang {
+                interpreter: "/usr/bin/env".to_string(),
+                arg: Some("python3 -u".to_string()),
+            })
+        );
+
+        // Binaries and empty `#!` lines are not scripts
+        assert_eq!(Shebang::parse(b"\0asm\x01\0\0\0"), None);
+        assert_eq!(Shebang::parse(b"#!\n"), None);
+    }
+}
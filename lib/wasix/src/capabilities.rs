@@ -1,4 +1,11 @@
-use std::time::Duration;
+use std::{
+    collections::BTreeSet,
+    num::NonZeroU32,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use wasmer_wasix_types::wasi::{Errno, Snapshot0Clockid};
 
 use crate::http::HttpClientCapabilityV1;
 
@@ -8,6 +15,16 @@ pub struct Capabilities {
     pub insecure_allow_all: bool,
     pub http_client: HttpClientCapabilityV1,
     pub threading: CapabilityThreadingV1,
+    pub sandbox: SandboxPolicy,
+    /// Flag that indicates if the guest may change its hostname with
+    /// `sethostname`.
+    /// (default = false)
+    pub allow_set_hostname: bool,
+    /// Flag that indicates if the guest may read the high resolution
+    /// performance counter with `perf_counter_read`, which can be used as
+    /// a timing side-channel.
+    /// (default = false)
+    pub allow_perf_counters: bool,
 }
 
 impl Capabilities {
@@ -16,6 +33,9 @@ impl Capabilities {
             insecure_allow_all: false,
             http_client: Default::default(),
             threading: Default::default(),
+            sandbox: Default::default(),
+            allow_set_hostname: false,
+            allow_perf_counters: false,
         }
     }
 
@@ -26,10 +46,16 @@ impl Capabilities {
             insecure_allow_all,
             http_client,
             threading,
+            sandbox,
+            allow_set_hostname,
+            allow_perf_counters,
         } = other;
         self.insecure_allow_all |= insecure_allow_all;
+        self.allow_set_hostname |= allow_set_hostname;
+        self.allow_perf_counters |= allow_perf_counters;
         self.http_client.update(http_client);
         self.threading.update(threading);
+        self.sandbox.update(sandbox);
     }
 }
 
@@ -64,6 +90,15 @@ pub struct CapabilityThreadingV1 {
     /// Switches to a blocking sleep implementation instead
     /// of the asynchronous runtime based implementation
     pub enable_blocking_sleep: bool,
+
+    /// Number of forks per second that a process can make before its
+    /// forks are delayed, the more it goes over the limit the longer the
+    /// delay (see [`ControlPlaneConfig::max_forks_per_second`]).
+    ///
+    /// [`None`] means no limit.
+    ///
+    /// [`ControlPlaneConfig::max_forks_per_second`]: crate::os::task::control_plane::ControlPlaneConfig::max_forks_per_second
+    pub max_forks_per_second: Option<NonZeroU32>,
 }
 
 impl Default for CapabilityThreadingV1 {
@@ -74,6 +109,7 @@ impl Default for CapabilityThreadingV1 {
             enable_deep_sleep: false,
             enable_exponential_cpu_backoff: None,
             enable_blocking_sleep: false,
+            max_forks_per_second: None,
         }
     }
 }
@@ -86,6 +122,7 @@ impl CapabilityThreadingV1 {
             enable_deep_sleep,
             enable_exponential_cpu_backoff,
             enable_blocking_sleep,
+            max_forks_per_second,
         } = other;
         self.enable_asynchronous_threading |= enable_asynchronous_threading;
         self.enable_deep_sleep |= enable_deep_sleep;
@@ -94,5 +131,247 @@ impl CapabilityThreadingV1 {
         }
         self.max_threads = max_threads.or(self.max_threads);
         self.enable_blocking_sleep |= enable_blocking_sleep;
+        self.max_forks_per_second = max_forks_per_second.or(self.max_forks_per_second);
+    }
+}
+
+/// Central sandbox policy that is consulted when a syscall is entered.
+///
+/// The policy complements the per-fd rights: a syscall that is denied
+/// here fails regardless of the rights held by the file descriptors it
+/// operates on.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SandboxPolicy {
+    /// Flag that indicates if the guest is allowed to use networking.
+    /// (default = true)
+    pub allow_network: bool,
+
+    /// Names of the syscalls that the guest is not allowed to call
+    /// (e.g. `proc_fork`). Denied syscalls fail with
+    /// [`Errno::Notcapable`].
+    pub denied_syscalls: BTreeSet<String>,
+
+    /// Host paths (and everything below them) that the guest is allowed
+    /// to access. Every syscall that resolves a path (and `chdir`) fails
+    /// with [`Errno::Notcapable`] for the paths outside of them.
+    ///
+    /// [`None`] means no restriction.
+    pub allowed_paths: Option<BTreeSet<PathBuf>>,
+
+    /// Clocks that the guest is allowed to read, set, sleep on or wait for
+    /// with `poll_oneoff` (e.g. only `Monotonic` to hide the wall-clock).
+    /// The other clocks fail with [`Errno::Notcapable`].
+    ///
+    /// [`None`] means no restriction.
+    pub allowed_clocks: Option<Vec<Snapshot0Clockid>>,
+}
+
+impl Default for SandboxPolicy {
+    fn default() -> Self {
+        Self {
+            allow_network: true,
+            denied_syscalls: BTreeSet::new(),
+            allowed_paths: None,
+            allowed_clocks: None,
+        }
+    }
+}
+
+impl SandboxPolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Denies the syscall with the given name.
+    pub fn deny_syscall(mut self, name: impl Into<String>) -> Self {
+        self.denied_syscalls.insert(name.into());
+        self
+    }
+
+    /// Adds a host path (and everything below it) to the set of paths
+    /// that the guest is allowed to access.
+    pub fn allow_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.allowed_paths
+            .get_or_insert_with(Default::default)
+            .insert(path.into());
+        self
+    }
+
+    /// Enables or disables networking for the guest.
+    pub fn with_network(mut self, allow: bool) -> Self {
+        self.allow_network = allow;
+        self
+    }
+
+    /// Adds a clock to the set of clocks that the guest is allowed to use.
+    pub fn allow_clock(mut self, clock: Snapshot0Clockid) -> Self {
+        let allowed = self.allowed_clocks.get_or_insert_with(Default::default);
+        if !allowed.contains(&clock) {
+            allowed.push(clock);
+        }
+        self
+    }
+
+    /// Returns true if the guest may call the syscall with this name.
+    pub fn is_syscall_allowed(&self, name: &str) -> bool {
+        !self.denied_syscalls.contains(name)
+    }
+
+    /// Returns true if the guest may access this host path.
+    pub fn is_path_allowed(&self, path: &Path) -> bool {
+        match &self.allowed_paths {
+            Some(allowed) => allowed.iter().any(|prefix| path.starts_with(prefix)),
+            None => true,
+        }
+    }
+
+    /// Returns true if the guest may use this clock.
+    pub fn is_clock_allowed(&self, clock: Snapshot0Clockid) -> bool {
+        match &self.allowed_clocks {
+            Some(allowed) => allowed.contains(&clock),
+            None => true,
+        }
+    }
+
+    pub fn check_syscall(&self, name: &str) -> Result<(), Errno> {
+        if self.is_syscall_allowed(name) {
+            Ok(())
+        } else {
+            Err(Errno::Notcapable)
+        }
+    }
+
+    pub fn check_network(&self) -> Result<(), Errno> {
+        if self.allow_network {
+            Ok(())
+        } else {
+            Err(Errno::Perm)
+        }
+    }
+
+    pub fn check_path(&self, path: &Path) -> Result<(), Errno> {
+        if self.is_path_allowed(path) {
+            Ok(())
+        } else {
+            Err(Errno::Notcapable)
+        }
+    }
+
+    pub fn check_clock(&self, clock: Snapshot0Clockid) -> Result<(), Errno> {
+        if self.is_clock_allowed(clock) {
+            Ok(())
+        } else {
+            Err(Errno::Notcapable)
+        }
+    }
+
+    /// Merges another [`SandboxPolicy`] into this one. Policies compose
+    /// restrictively, anything denied by either policy stays denied.
+    pub fn update(&mut self, other: SandboxPolicy) {
+        let SandboxPolicy {
+            allow_network,
+            denied_syscalls,
+            allowed_paths,
+            allowed_clocks,
+        } = other;
+        self.allow_network &= allow_network;
+        self.denied_syscalls.extend(denied_syscalls);
+        self.allowed_paths = match (self.allowed_paths.take(), allowed_paths) {
+            // A path is only allowed when both policies allow it, which
+            // means keeping the deeper of each pair of nested prefixes
+            (Some(ours), Some(theirs)) => Some(
+                ours.iter()
+                    .flat_map(|a| {
+                        theirs.iter().filter_map(move |b| {
+                            if a.starts_with(b) {
+                                Some(a.clone())
+                            } else if b.starts_with(a) {
+                                Some(b.clone())
+                            } else {
+                                None
+                            }
+                        })
+                    })
+                    .collect(),
+            ),
+            (ours, theirs) => ours.or(theirs),
+        };
+        self.allowed_clocks = match (self.allowed_clocks.take(), allowed_clocks) {
+            (Some(ours), Some(theirs)) => Some(
+                ours.into_iter()
+                    .filter(|clock| theirs.contains(clock))
+                    .collect(),
+            ),
+            (ours, theirs) => ours.or(theirs),
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sandbox_policy_defaults_to_allow_all() {
+        let policy = SandboxPolicy::default();
+        assert!(policy.check_network().is_ok());
+        assert!(policy.check_syscall("proc_fork").is_ok());
+        assert!(policy.check_path(Path::new("/etc/passwd")).is_ok());
+        assert!(policy.check_clock(Snapshot0Clockid::Realtime).is_ok());
+    }
+
+    #[test]
+    fn sandbox_policy_denials() {
+        let policy = SandboxPolicy::new()
+            .with_network(false)
+            .deny_syscall("proc_fork")
+            .allow_path("/data");
+        assert_eq!(policy.check_network(), Err(Errno::Perm));
+        assert_eq!(policy.check_syscall("proc_fork"), Err(Errno::Notcapable));
+        assert!(policy.check_syscall("fd_write").is_ok());
+        assert!(policy.check_path(Path::new("/data/file.txt")).is_ok());
+        assert!(policy.check_path(Path::new("/data")).is_ok());
+        assert_eq!(
+            policy.check_path(Path::new("/database")),
+            Err(Errno::Notcapable)
+        );
+    }
+
+    #[test]
+    fn sandbox_policy_composes_restrictively() {
+        let mut policy = SandboxPolicy::new()
+            .deny_syscall("proc_fork")
+            .allow_path("/data")
+            .allow_path("/tmp");
+        policy.update(
+            SandboxPolicy::new()
+                .with_network(false)
+                .deny_syscall("proc_exec")
+                .allow_path("/data/public"),
+        );
+
+        assert!(!policy.allow_network);
+        assert!(!policy.is_syscall_allowed("proc_fork"));
+        assert!(!policy.is_syscall_allowed("proc_exec"));
+        assert!(policy.is_path_allowed(Path::new("/data/public/a")));
+        assert!(!policy.is_path_allowed(Path::new("/data/private")));
+        assert!(!policy.is_path_allowed(Path::new("/tmp")));
+    }
+
+    #[test]
+    fn sandbox_policy_keeps_the_clocks_both_allow() {
+        let mut policy = SandboxPolicy::new()
+            .allow_clock(Snapshot0Clockid::Monotonic)
+            .allow_clock(Snapshot0Clockid::Realtime);
+        assert!(policy.check_clock(Snapshot0Clockid::Realtime).is_ok());
+        assert_eq!(
+            policy.check_clock(Snapshot0Clockid::ProcessCputimeId),
+            Err(Errno::Notcapable)
+        );
+
+        policy.update(SandboxPolicy::new().allow_clock(Snapshot0Clockid::Monotonic));
+        policy.update(SandboxPolicy::new());
+        assert!(policy.is_clock_allowed(Snapshot0Clockid::Monotonic));
+        assert!(!policy.is_clock_allowed(Snapshot0Clockid::Realtime));
     }
 }
@@ -1,8 +1,11 @@
 use std::{
     borrow::Cow,
-    collections::HashMap,
+    collections::{HashMap, VecDeque},
     path::PathBuf,
-    sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard, atomic::AtomicU64},
+    sync::{
+        Arc, Mutex, RwLock, RwLockReadGuard, RwLockWriteGuard,
+        atomic::{AtomicU64, Ordering},
+    },
 };
 
 #[cfg(feature = "enable-serde")]
@@ -13,7 +16,10 @@ use wasmer_wasix_types::wasi::{Fd as WasiFd, Fdflags, Fdflagsext, Filestat, Righ
 use crate::net::socket::InodeSocket;
 use crate::os::epoll::EpollState;
 
-use super::{InodeGuard, InodeWeakGuard, NotificationInner};
+use super::{
+    FileDescription, FileLock, InodeGuard, InodeWeakGuard, NotificationInner,
+    SignalNotificationInner,
+};
 
 #[derive(Debug, Clone)]
 #[cfg_attr(feature = "enable-serde", derive(Serialize, Deserialize))]
@@ -39,6 +45,10 @@ pub struct FdInner {
     pub flags: Fdflags,         // This is file table related flags, not fd flags
     pub offset: Arc<AtomicU64>, // This also belongs in the file table
     pub fd_flags: Fdflagsext,   // This is the actual FD flags that belongs here
+    /// The open file description that the descriptor belongs to, which it
+    /// shares with its duplicates
+    #[cfg_attr(feature = "enable-serde", serde(skip))]
+    pub description: Arc<FileDescription>,
 }
 
 impl Fd {
@@ -69,6 +79,9 @@ pub struct InodeVal {
     pub is_preopened: bool,
     pub name: RwLock<Cow<'static, str>>,
     pub kind: RwLock<Kind>,
+    /// The advisory locks that are held on the file
+    #[cfg_attr(feature = "enable-serde", serde(skip))]
+    pub lock: Arc<FileLock>,
 }
 
 impl InodeVal {
@@ -115,6 +128,8 @@ pub enum Kind {
     #[cfg_attr(feature = "enable-serde", serde(skip))]
     DuplexPipe {
         pipe: Pipe,
+        /// Descriptors that are passed along with the data
+        fds: PassedFds,
     },
     #[cfg_attr(feature = "enable-serde", serde(skip))]
     Epoll {
@@ -156,4 +171,120 @@ pub enum Kind {
     EventNotifications {
         inner: Arc<NotificationInner>,
     },
+    /// Reports the signals of a thread, see `fd_signal`
+    #[cfg_attr(feature = "enable-serde", serde(skip))]
+    SignalNotifications {
+        inner: Arc<SignalNotificationInner>,
+    },
+}
+
+/// Descriptors that are in flight between the two ends of a socket pair,
+/// they are sent with `sock_send_fds` and installed into the descriptor table
+/// of the receiver by `sock_recv_fds`.
+///
+/// Every send queues one batch along with the message it was sent with,
+/// that is with the position of the message in the stream of the pipe. The
+/// read that consumes the start of the message gets the batch, when it is
+/// not done by `sock_recv_fds` the descriptors are closed.
+#[derive(Debug, Clone, Default)]
+pub struct PassedFds {
+    tx: Arc<Mutex<PassedBatches>>,
+    rx: Arc<Mutex<PassedBatches>>,
+}
+
+/// The batches of descriptors that are queued on one end
+type PassedBatches = VecDeque<PassedBatch>;
+
+#[derive(Debug)]
+struct PassedBatch {
+    id: u64,
+    /// Where the message that the batch was sent with starts in the stream
+    position: u64,
+    fds: Vec<PassedFd>,
+}
+
+/// Identifies the batches of descriptors that were sent, so that a batch
+/// can be taken back even when others were queued after it
+static NEXT_BATCH: AtomicU64 = AtomicU64::new(0);
+
+impl PassedFds {
+    /// Creates the queues for both ends of a socket pair.
+    pub fn channel() -> (Self, Self) {
+        let end1 = Self::default();
+        let end2 = Self {
+            tx: end1.rx.clone(),
+            rx: end1.tx.clone(),
+        };
+        (end1, end2)
+    }
+
+    /// Queues a batch of descriptors with the message that starts at
+    /// `position` in the stream and returns the id to take it back with.
+    pub fn send(&self, position: u64, fds: Vec<Fd>) -> u64 {
+        let id = NEXT_BATCH.fetch_add(1, Ordering::Relaxed);
+        let fds = fds.into_iter().map(PassedFd::new).collect();
+        self.tx
+            .lock()
+            .unwrap()
+            .push_back(PassedBatch { id, position, fds });
+        id
+    }
+
+    /// Takes back the batch with this id, if it was not received yet.
+    pub fn unsend(&self, id: u64) -> Option<Vec<PassedFd>> {
+        let mut tx = self.tx.lock().unwrap();
+        let idx = tx.iter().position(|batch| batch.id == id)?;
+        tx.remove(idx).map(|batch| batch.fds)
+    }
+
+    /// Closes the descriptors of the messages that were read up to
+    /// `position` without receiving them, which is called before every read
+    pub fn discard(&self, position: u64) {
+        let discarded = self.take(position);
+        if !discarded.is_empty() {
+            tracing::trace!(
+                nfds = discarded.len(),
+                "closing passed file descriptors that were not received"
+            );
+        }
+    }
+
+    /// Takes the descriptors of the messages that were read up to
+    /// `position`, which is where the stream is after the read
+    pub fn recv(&self, position: u64) -> Vec<PassedFd> {
+        self.take(position)
+    }
+
+    fn take(&self, position: u64) -> Vec<PassedFd> {
+        let mut rx = self.rx.lock().unwrap();
+        let mut fds = Vec::new();
+        while rx.front().is_some_and(|batch| batch.position < position) {
+            fds.extend(rx.pop_front().unwrap().fds);
+        }
+        fds
+    }
+}
+
+/// A descriptor that is in flight, it keeps its file open just like an open
+/// descriptor does until it is dropped.
+#[derive(Debug)]
+pub struct PassedFd(Fd);
+
+impl PassedFd {
+    fn new(fd: Fd) -> Self {
+        fd.inode.acquire_handle();
+        Self(fd)
+    }
+
+    /// The descriptor that the receiver installs, the file stays open as
+    /// long as `self` is alive so it has to be installed before dropping it.
+    pub fn fd(&self) -> Fd {
+        self.0.clone()
+    }
+}
+
+impl Drop for PassedFd {
+    fn drop(&mut self) {
+        self.0.inode.drop_one_handle();
+    }
 }
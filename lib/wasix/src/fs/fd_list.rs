@@ -313,6 +313,7 @@ mod tests {
                 inner: Arc::new(InodeVal {
                     is_preopened: false,
                     kind: RwLock::new(Kind::Buffer { buffer: vec![] }),
+                    lock: Default::default(),
                     name: RwLock::new(Cow::Borrowed("")),
                     stat: RwLock::new(Default::default()),
                 }),
@@ -321,6 +322,7 @@ mod tests {
             is_stdio: false,
             inner: FdInner {
                 offset: Arc::new(AtomicU64::new(0)),
+                description: Default::default(),
                 rights: Rights::empty(),
                 rights_inheriting: Rights::empty(),
                 flags: Fdflags::from_bits_preserve(n),
@@ -15,7 +15,9 @@ use wasmer_wasix_types::{
     wasi::{Errno, EventFdReadwrite, Eventrwflags, Subscription},
 };
 
-use super::{InodeGuard, Kind, notification::NotificationInner};
+use super::{
+    InodeGuard, Kind, notification::NotificationInner, signal_notification::SignalNotificationInner,
+};
 use crate::{
     net::socket::{InodeSocketInner, InodeSocketKind},
     state::{PollEvent, PollEventSet, WasiState, iterate_poll_events},
@@ -27,6 +29,7 @@ use crate::{
 pub(crate) enum InodeValFilePollGuardMode {
     File(Arc<RwLock<Box<dyn VirtualFile + Send + Sync + 'static>>>),
     EventNotifications(Arc<NotificationInner>),
+    SignalNotifications(Arc<SignalNotificationInner>),
     Socket { inner: Arc<InodeSocketInner> },
     PipeRx { rx: Arc<RwLock<Box<PipeRx>>> },
     PipeTx { tx: Arc<RwLock<Box<PipeTx>>> },
@@ -51,6 +54,9 @@ impl InodeValFilePollGuard {
             Kind::EventNotifications { inner, .. } => {
                 InodeValFilePollGuardMode::EventNotifications(inner.clone())
             }
+            Kind::SignalNotifications { inner } => {
+                InodeValFilePollGuardMode::SignalNotifications(inner.clone())
+            }
             Kind::Socket { socket, .. } => InodeValFilePollGuardMode::Socket {
                 inner: socket.inner.clone(),
             },
@@ -64,7 +70,7 @@ impl InodeValFilePollGuard {
             Kind::PipeTx { tx } => InodeValFilePollGuardMode::PipeTx {
                 tx: Arc::new(RwLock::new(Box::new(tx.clone()))),
             },
-            Kind::DuplexPipe { pipe } => InodeValFilePollGuardMode::DuplexPipe {
+            Kind::DuplexPipe { pipe, .. } => InodeValFilePollGuardMode::DuplexPipe {
                 pipe: Arc::new(RwLock::new(Box::new(pipe.clone()))),
             },
             _ => {
@@ -89,6 +95,9 @@ impl std::fmt::Debug for InodeValFilePollGuard {
             InodeValFilePollGuardMode::EventNotifications { .. } => {
                 write!(f, "guard-notifications(fd={}, peb={})", self.fd, self.peb)
             }
+            InodeValFilePollGuardMode::SignalNotifications { .. } => {
+                write!(f, "guard-signals(fd={}, peb={})", self.fd, self.peb)
+            }
             InodeValFilePollGuardMode::Socket { inner } => {
                 let inner = inner.protected.read().unwrap();
                 match &inner.kind {
@@ -194,6 +203,7 @@ impl Future for InodeValFilePollGuardJoin {
                     file.poll_read_ready(cx)
                 }
                 InodeValFilePollGuardMode::EventNotifications(inner) => inner.poll(waker).map(Ok),
+                InodeValFilePollGuardMode::SignalNotifications(inner) => inner.poll(waker).map(Ok),
                 InodeValFilePollGuardMode::Socket { inner } => {
                     let mut guard = inner.protected.write().unwrap();
                     guard.poll_read_ready(cx)
@@ -293,6 +303,12 @@ impl Future for InodeValFilePollGuardJoin {
                     file.poll_write_ready(cx)
                 }
                 InodeValFilePollGuardMode::EventNotifications(inner) => inner.poll(waker).map(Ok),
+                InodeValFilePollGuardMode::SignalNotifications(_) => {
+                    Poll::Ready(Err(std::io::Error::new(
+                        std::io::ErrorKind::InvalidInput,
+                        "Cannot write to a signal descriptor",
+                    )))
+                }
                 InodeValFilePollGuardMode::Socket { inner } => {
                     let mut guard = inner.protected.write().unwrap();
                     guard.poll_write_ready(cx)
@@ -304,7 +320,7 @@ impl Future for InodeValFilePollGuardJoin {
                 InodeValFilePollGuardMode::PipeTx { tx } => {
                     let mut guard = tx.write().unwrap();
                     let tx = Pin::new(guard.as_mut());
-                    tx.poll_write_ready()
+                    tx.poll_write_ready(cx)
                 }
                 InodeValFilePollGuardMode::DuplexPipe { pipe } => {
                     let mut guard = pipe.write().unwrap();
@@ -0,0 +1,264 @@
+//! Advisory locks on files (the equivalent of `flock`)
+//!
+//! A lock is held by an open file description (see [`FileDescription`]),
+//! which is shared by the descriptors that are duplicated from the one that
+//! took the lock and by the ones that a forked process inherits. It is
+//! released by `fd_unlock` or once the description is gone, which is when
+//! the last of these descriptors is closed. The locks are advisory, they
+//! only get in the way of others that take locks on the same file.
+//!
+//! A file that is opened with `Fdflagsext::EXCLUSIVE` is locked exclusively
+//! as it is opened (like `O_EXLOCK`). When the file lives on the host, the
+//! lock is also taken on the host file so that the other processes of the
+//! host are kept out as well.
+
+use std::{
+    future::poll_fn,
+    sync::{
+        Arc, Mutex, Weak,
+        atomic::{AtomicU64, Ordering},
+    },
+    task::{Poll, Waker},
+};
+
+use virtual_fs::{FsError, VirtualFile};
+use wasmer_wasix_types::wasi::Errno;
+
+use super::fs_error_into_wasi_err;
+
+static NEXT_FILE_DESCRIPTION_ID: AtomicU64 = AtomicU64::new(1);
+
+/// An open file description, which is shared by the descriptors that are
+/// duplicated from the one that opened the file and by the ones that a
+/// forked process inherits (see [`FdInner::description`]). It is dropped
+/// when the last of them is closed, which releases the lock it holds.
+///
+/// [`FdInner::description`]: super::FdInner::description
+#[derive(Debug)]
+pub struct FileDescription {
+    id: u64,
+    /// The lock that the description holds, which is on the file that it
+    /// opened
+    lock: Mutex<Option<Weak<FileLock>>>,
+}
+
+impl Default for FileDescription {
+    fn default() -> Self {
+        Self {
+            id: NEXT_FILE_DESCRIPTION_ID.fetch_add(1, Ordering::Relaxed),
+            lock: Mutex::new(None),
+        }
+    }
+}
+
+impl FileDescription {
+    /// Identifies the description among all the open file descriptions
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+}
+
+impl Drop for FileDescription {
+    fn drop(&mut self) {
+        let lock = self.lock.get_mut().unwrap().take();
+        if let Some(lock) = lock.and_then(|lock| lock.upgrade()) {
+            lock.release(self.id);
+        }
+    }
+}
+
+/// The advisory locks that are held on a file
+#[derive(Debug, Default)]
+pub struct FileLock {
+    state: Mutex<FileLockState>,
+}
+
+#[derive(Debug, Default)]
+struct FileLockState {
+    /// The descriptions that hold the lock and if they hold it exclusively
+    holders: Vec<(u64, bool)>,
+    /// The tasks that wait for the lock to be released
+    waiters: Vec<Waker>,
+    /// The handles on the host files that hold the host locks of the holders
+    host: Vec<(u64, Box<dyn VirtualFile + Send + Sync>)>,
+}
+
+impl FileLockState {
+    fn try_lock(&mut self, owner: u64, exclusive: bool) -> bool {
+        let blocked = self
+            .holders
+            .iter()
+            .filter(|(holder, _)| *holder != owner)
+            .any(|(_, held_exclusively)| exclusive || *held_exclusively);
+        if blocked {
+            return false;
+        }
+
+        // Taking the lock again converts the one that is already held
+        self.unlock(owner);
+        self.holders.push((owner, exclusive));
+        true
+    }
+
+    fn unlock(&mut self, owner: u64) {
+        let before = self.holders.len();
+        self.holders.retain(|(holder, _)| *holder != owner);
+        if self.holders.len() != before {
+            for waker in self.waiters.drain(..) {
+                waker.wake();
+            }
+        }
+    }
+}
+
+impl FileLock {
+    /// Takes the lock unless someone else holds it in a way that conflicts
+    pub(crate) fn try_lock(self: &Arc<Self>, owner: &FileDescription, exclusive: bool) -> bool {
+        let locked = self.state.lock().unwrap().try_lock(owner.id, exclusive);
+        if locked {
+            self.held_by(owner);
+        }
+        locked
+    }
+
+    /// Waits until nobody else holds the lock in a way that conflicts and
+    /// takes it
+    pub(crate) async fn lock(self: &Arc<Self>, owner: &FileDescription, exclusive: bool) {
+        poll_fn(|cx| {
+            let mut state = self.state.lock().unwrap();
+            if state.try_lock(owner.id, exclusive) {
+                return Poll::Ready(());
+            }
+            state.waiters.push(cx.waker().clone());
+            Poll::Pending
+        })
+        .await;
+        self.held_by(owner);
+    }
+
+    /// Takes the lock exclusively without waiting, along with the host lock
+    /// of `host_file` when it lives on the host. `host_file` is a handle of
+    /// its own on the file, which is kept until the lock is released.
+    pub(crate) fn try_lock_exclusive_with_host(
+        self: &Arc<Self>,
+        owner: &FileDescription,
+        host_file: Option<Box<dyn VirtualFile + Send + Sync>>,
+    ) -> Result<(), Errno> {
+        let mut state = self.state.lock().unwrap();
+        if !state.try_lock(owner.id, true) {
+            return Err(Errno::Busy);
+        }
+        self.held_by(owner);
+        let Some(mut host_file) = host_file else {
+            return Ok(());
+        };
+        let err = match host_file.try_lock_host() {
+            Ok(()) => {
+                state.host.push((owner.id, host_file));
+                return Ok(());
+            }
+            // Files that don't live on the host only have the lock above
+            Err(FsError::Unsupported) => return Ok(()),
+            Err(FsError::WouldBlock) => Errno::Busy,
+            Err(err) => fs_error_into_wasi_err(err),
+        };
+        state.unlock(owner.id);
+        Err(err)
+    }
+
+    /// Releases the lock that `owner` holds, if any
+    pub(crate) fn unlock(&self, owner: &FileDescription) {
+        self.release(owner.id);
+    }
+
+    /// Remembers that `owner` holds the lock, so that it is released when
+    /// the description is gone
+    fn held_by(self: &Arc<Self>, owner: &FileDescription) {
+        *owner.lock.lock().unwrap() = Some(Arc::downgrade(self));
+    }
+
+    /// Releases the lock of the description with the id `owner` and wakes
+    /// up the ones that wait for it
+    fn release(&self, owner: u64) {
+        let mut state = self.state.lock().unwrap();
+        state.unlock(owner);
+        // Closing the handle releases the host lock
+        state.host.retain(|(holder, _)| *holder != owner);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::atomic::AtomicBool,
+        task::{Context, Wake},
+    };
+
+    use super::*;
+
+    #[derive(Default)]
+    struct Woken(AtomicBool);
+
+    impl Wake for Woken {
+        fn wake(self: Arc<Self>) {
+            self.0.store(true, Ordering::SeqCst);
+        }
+    }
+
+    #[test]
+    fn shared_locks_exclude_exclusive_ones() {
+        let lock = Arc::new(FileLock::default());
+        let (a, b, c) = (
+            FileDescription::default(),
+            FileDescription::default(),
+            FileDescription::default(),
+        );
+
+        assert!(lock.try_lock(&a, false));
+        assert!(lock.try_lock(&b, false));
+        assert!(!lock.try_lock(&c, true));
+
+        lock.unlock(&a);
+        lock.unlock(&b);
+        assert!(lock.try_lock(&c, true));
+        assert!(!lock.try_lock(&a, false));
+    }
+
+    #[test]
+    fn a_lock_can_be_converted() {
+        let lock = Arc::new(FileLock::default());
+        let (a, b) = (FileDescription::default(), FileDescription::default());
+
+        assert!(lock.try_lock(&a, true));
+        assert!(lock.try_lock(&a, false));
+        assert!(lock.try_lock(&b, false));
+        assert!(!lock.try_lock(&a, true));
+    }
+
+    #[test]
+    fn a_lock_is_released_with_its_owner() {
+        let lock = Arc::new(FileLock::default());
+        let (a, b) = (FileDescription::default(), FileDescription::default());
+
+        assert!(lock.try_lock(&a, true));
+        drop(a);
+        assert!(lock.try_lock(&b, true));
+    }
+
+    #[test]
+    fn the_waiters_are_woken_when_the_owner_is_gone() {
+        let lock = Arc::new(FileLock::default());
+        let (a, b) = (FileDescription::default(), FileDescription::default());
+        assert!(lock.try_lock(&a, true));
+
+        let woken = Arc::new(Woken::default());
+        let waker = Waker::from(woken.clone());
+        let mut cx = Context::from_waker(&waker);
+        let mut waiting = Box::pin(lock.lock(&b, true));
+        assert!(waiting.as_mut().poll(&mut cx).is_pending());
+
+        drop(a);
+        assert!(woken.0.load(Ordering::SeqCst));
+        assert!(waiting.as_mut().poll(&mut cx).is_ready());
+    }
+}
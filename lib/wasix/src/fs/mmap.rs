@@ -0,0 +1,349 @@
+//! Memory mappings of files into the linear memory, see `fd_mmap`.
+//!
+//! The mappings are not demand paged: the mapped region of the file is
+//! copied into linear memory when it is mapped and (for writable synced
+//! mappings) copied back to the file by `fd_msync` and `fd_munmap`. Changes
+//! made to the file in the meantime are only seen by a synced mapping once
+//! it is synced again and a mapping never changes the size of the file.
+//!
+//! A range of linear memory can't alias the memory of another process (nor
+//! another range of the same memory), so a mapping that the guest asks to
+//! be shared (`Mmapflags::SHARED`) is a synced mapping rather than a shared
+//! segment of memory: the one thing that is shared, for instance with a
+//! forked process, is the file. Every process has its own copy of the
+//! mapped region, which is only coherent with the file (and with the copies
+//! of the other processes) after it was synced. Within a process two synced
+//! mappings of the same region would be independent copies that don't see
+//! each other's writes, so they are refused rather than silently diverging
+//! (see `WasiMappings::maps_synced`).
+//!
+//! A synced mapping remembers what it held when it was last synced, so that
+//! only the bytes that changed since then are written back. This is what
+//! lets two synced mappings of the same file (for instance the ones of a
+//! parent and of the child it forked) communicate through it without
+//! overwriting each other's changes.
+
+use std::{
+    collections::BTreeMap,
+    ops::Range,
+    sync::{Arc, Mutex, RwLock},
+};
+
+use virtual_fs::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt, VirtualFile};
+use wasmer::MemoryView;
+use wasmer_wasix_types::wasi::{Errno, Mmapflags};
+
+use crate::syscalls::map_io_err;
+
+pub(crate) type MappedFile = Arc<RwLock<Box<dyn VirtualFile + Send + Sync + 'static>>>;
+
+/// A region of a file that was copied into linear memory
+#[derive(Debug, Clone)]
+pub(crate) struct FileMapping {
+    /// Where the mapping starts in linear memory
+    pub addr: u64,
+    /// The length of the mapping
+    pub len: u64,
+    /// The length of the range that is reserved for the mapping in linear
+    /// memory, which is always a number of whole pages
+    pub reserved: u64,
+    /// Where the mapped region starts in the file
+    pub offset: u64,
+    /// The number of bytes at the start of the mapping that were part of
+    /// the file when it was mapped, the rest of the mapping is beyond the
+    /// end of the file and it is never written back
+    pub file_len: u64,
+    pub flags: Mmapflags,
+    pub file: MappedFile,
+    /// What the part of a synced mapping that is within the file held when
+    /// it was last synced with the file (empty for private mappings)
+    pub synced: Arc<Mutex<Vec<u8>>>,
+}
+
+impl FileMapping {
+    /// Returns true if changes to the mapping are written back to the file
+    pub fn is_written_back(&self) -> bool {
+        self.flags.contains(Mmapflags::WRITE | Mmapflags::SHARED)
+    }
+
+    /// Returns true if the mapping is synced with the file, which is what
+    /// the guest gets when it asks for a shared mapping
+    pub fn is_synced(&self) -> bool {
+        self.flags.contains(Mmapflags::SHARED)
+    }
+
+    /// Syncs the part of the mapping between the addresses `start` and
+    /// `end` (as far as it was part of the file) with the file: the bytes
+    /// that changed since the last sync are written back and then, when
+    /// `refresh` is set, the mapping is updated with what the other
+    /// mappings of the file wrote back in the meantime
+    #[allow(clippy::await_holding_lock)]
+    pub async fn sync(
+        &self,
+        memory: &MemoryView<'_>,
+        start: u64,
+        end: u64,
+        refresh: bool,
+    ) -> Result<(), Errno> {
+        let start = start.max(self.addr);
+        let end = end.min(self.addr + self.file_len);
+        if !self.is_synced() || start >= end {
+            return Ok(());
+        }
+        let range = (start - self.addr) as usize..(end - self.addr) as usize;
+        let file_offset = self.offset + range.start as u64;
+
+        let mut data = vec![0u8; range.len()];
+        memory.read(start, &mut data).map_err(|_| Errno::Fault)?;
+
+        let mut synced = self.synced.lock().map_err(|_| Errno::Fault)?;
+        let mut file = self.file.write().map_err(|_| Errno::Fault)?;
+        if self.is_written_back() {
+            for run in changed_runs(&synced[range.clone()], &data) {
+                file.seek(std::io::SeekFrom::Start(file_offset + run.start as u64))
+                    .await
+                    .map_err(map_io_err)?;
+                file.write_all(&data[run]).await.map_err(map_io_err)?;
+            }
+            file.flush().await.map_err(map_io_err)?;
+        }
+
+        if refresh {
+            // The part that is now beyond the end of the file keeps what
+            // the mapping holds
+            file.seek(std::io::SeekFrom::Start(file_offset))
+                .await
+                .map_err(map_io_err)?;
+            let mut fresh = Vec::new();
+            (&mut *file)
+                .take(data.len() as u64)
+                .read_to_end(&mut fresh)
+                .await
+                .map_err(map_io_err)?;
+            data[..fresh.len()].copy_from_slice(&fresh);
+            memory.write(start, &data).map_err(|_| Errno::Fault)?;
+        }
+        synced[range].copy_from_slice(&data);
+        Ok(())
+    }
+}
+
+/// The ranges of bytes that differ between `old` and `new`
+fn changed_runs(old: &[u8], new: &[u8]) -> Vec<Range<usize>> {
+    let mut runs = Vec::new();
+    let mut i = 0;
+    while i < new.len() {
+        if old[i] == new[i] {
+            i += 1;
+            continue;
+        }
+        let start = i;
+        while i < new.len() && old[i] != new[i] {
+            i += 1;
+        }
+        runs.push(start..i);
+    }
+    runs
+}
+
+/// Reads up to `len` bytes of a file starting at `offset`, less when the
+/// end of the file comes first
+#[allow(clippy::await_holding_lock)]
+pub(crate) async fn read_region(file: MappedFile, offset: u64, len: u64) -> Result<Vec<u8>, Errno> {
+    let mut file = file.write().map_err(|_| Errno::Fault)?;
+    file.seek(std::io::SeekFrom::Start(offset))
+        .await
+        .map_err(map_io_err)?;
+
+    let mut data = Vec::new();
+    (&mut *file)
+        .take(len)
+        .read_to_end(&mut data)
+        .await
+        .map_err(map_io_err)?;
+    Ok(data)
+}
+
+/// The file mappings of a process
+#[derive(Debug, Default, Clone)]
+pub(crate) struct WasiMappings {
+    /// The mappings by the address that they start at
+    mappings: BTreeMap<u64, FileMapping>,
+    /// Ranges of linear memory (address and length) that were reserved for
+    /// mappings that are gone, they are used for new mappings before the
+    /// memory is grown again
+    free: Vec<(u64, u64)>,
+}
+
+impl WasiMappings {
+    /// Takes a range of `reserved` bytes from the ranges that were released
+    /// by previous mappings and returns its address
+    pub(crate) fn take_free(&mut self, reserved: u64) -> Option<u64> {
+        let index = self.free.iter().position(|(_, len)| *len >= reserved)?;
+        let (addr, len) = self.free[index];
+        if len == reserved {
+            self.free.remove(index);
+        } else {
+            self.free[index] = (addr + reserved, len - reserved);
+        }
+        Some(addr)
+    }
+
+    pub(crate) fn insert(&mut self, mapping: FileMapping) {
+        self.mappings.insert(mapping.addr, mapping);
+    }
+
+    /// Returns the mappings that overlap the range from `addr` to `addr + len`
+    pub(crate) fn overlapping(&self, addr: u64, len: u64) -> Vec<FileMapping> {
+        let end = addr.saturating_add(len);
+        self.mappings
+            .range(..end)
+            .map(|(_, mapping)| mapping)
+            .filter(|mapping| mapping.addr + mapping.len > addr)
+            .cloned()
+            .collect()
+    }
+
+    /// Returns true if a synced mapping of `file` already covers part of
+    /// the region of the file from `offset` to `offset + len`
+    pub(crate) fn maps_synced(&self, file: &MappedFile, offset: u64, len: u64) -> bool {
+        let end = offset.saturating_add(len);
+        self.mappings.values().any(|mapping| {
+            mapping.is_synced()
+                && Arc::ptr_eq(&mapping.file, file)
+                && mapping.offset < end
+                && mapping.offset + mapping.len > offset
+        })
+    }
+
+    /// Removes the mappings in the range from `addr` to `addr + len`, a
+    /// mapping can only be removed as a whole so nothing is removed when
+    /// the range only covers part of one
+    pub(crate) fn remove(&mut self, addr: u64, len: u64) -> Result<Vec<FileMapping>, Errno> {
+        let end = addr.saturating_add(len);
+        let removed = self.overlapping(addr, len);
+        if removed
+            .iter()
+            .any(|mapping| mapping.addr < addr || mapping.addr + mapping.len > end)
+        {
+            return Err(Errno::Inval);
+        }
+
+        for mapping in removed.iter() {
+            self.mappings.remove(&mapping.addr);
+            self.free.push((mapping.addr, mapping.reserved));
+        }
+        Ok(removed)
+    }
+
+    /// The mappings of a forked process, which are attached to the same
+    /// files but remember what they last synced on their own (as they live
+    /// in a copy of the memory)
+    pub(crate) fn fork(&self) -> Self {
+        let mut forked = self.clone();
+        for mapping in forked.mappings.values_mut() {
+            let synced = mapping.synced.lock().unwrap().clone();
+            mapping.synced = Arc::new(Mutex::new(synced));
+        }
+        forked
+    }
+
+    /// Forgets all the mappings, which happens when the process starts
+    /// running a new module with a memory of its own
+    pub(crate) fn clear(&mut self) {
+        self.mappings.clear();
+        self.free.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use virtual_fs::NullFile;
+
+    use super::*;
+
+    fn mapping(addr: u64, len: u64) -> FileMapping {
+        FileMapping {
+            addr,
+            len,
+            reserved: len.next_multiple_of(wasmer::WASM_PAGE_SIZE as u64),
+            offset: 0,
+            file_len: len,
+            flags: Mmapflags::empty(),
+            file: Arc::new(RwLock::new(Box::new(NullFile::default()))),
+            synced: Default::default(),
+        }
+    }
+
+    #[test]
+    fn mappings_are_only_removed_as_a_whole() {
+        let mut mappings = WasiMappings::default();
+        mappings.insert(mapping(0x10000, 100));
+        mappings.insert(mapping(0x20000, 100));
+
+        assert_eq!(mappings.overlapping(0x10050, 0x10000).len(), 2);
+        assert_eq!(mappings.remove(0x10050, 0x10000).unwrap_err(), Errno::Inval);
+
+        let removed = mappings.remove(0x10000, 0x10000).unwrap();
+        assert_eq!(removed.len(), 1);
+        assert_eq!(removed[0].addr, 0x10000);
+        assert!(mappings.overlapping(0x10000, 0x10000).is_empty());
+        assert_eq!(mappings.overlapping(0, u64::MAX).len(), 1);
+    }
+
+    #[test]
+    fn released_ranges_are_reused() {
+        let mut mappings = WasiMappings::default();
+        mappings.insert(mapping(0x10000, 0x18000));
+        mappings.remove(0x10000, 0x18000).unwrap();
+
+        // The range of the mapping was two pages long
+        assert_eq!(mappings.take_free(0x10000), Some(0x10000));
+        assert_eq!(mappings.take_free(0x20000), None);
+        assert_eq!(mappings.take_free(0x10000), Some(0x20000));
+        assert_eq!(mappings.take_free(0x10000), None);
+    }
+
+    #[test]
+    fn overlapping_synced_regions_are_detected() {
+        let mut mappings = WasiMappings::default();
+        let mut synced = mapping(0x10000, 100);
+        synced.offset = 50;
+        synced.flags = Mmapflags::SHARED;
+        let file = synced.file.clone();
+        mappings.insert(synced);
+        let private = mapping(0x20000, 100);
+        let other = private.file.clone();
+        mappings.insert(private);
+
+        assert!(mappings.maps_synced(&file, 0, 51));
+        assert!(mappings.maps_synced(&file, 149, 10));
+        assert!(!mappings.maps_synced(&file, 0, 50));
+        assert!(!mappings.maps_synced(&file, 150, 10));
+        // Private mappings are copies anyway
+        assert!(!mappings.maps_synced(&other, 0, 100));
+    }
+
+    #[test]
+    fn only_the_changed_bytes_are_written_back() {
+        assert!(changed_runs(b"hello", b"hello").is_empty());
+        assert_eq!(changed_runs(b"hello", b"jelly"), vec![0..1, 4..5]);
+        assert_eq!(changed_runs(b"\0\0\0\0", b"ab\0c"), vec![0..2, 3..4]);
+    }
+
+    #[test]
+    fn forked_mappings_sync_on_their_own() {
+        let mut mappings = WasiMappings::default();
+        let mapping = mapping(0x10000, 4);
+        *mapping.synced.lock().unwrap() = b"abcd".to_vec();
+        mappings.insert(mapping);
+
+        let forked = mappings.fork();
+        let ours = &mappings.overlapping(0x10000, 4)[0];
+        let theirs = &forked.overlapping(0x10000, 4)[0];
+        assert!(Arc::ptr_eq(&ours.file, &theirs.file));
+
+        theirs.synced.lock().unwrap()[0] = b'x';
+        assert_eq!(*ours.synced.lock().unwrap(), b"abcd");
+    }
+}
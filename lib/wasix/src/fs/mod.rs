@@ -8,12 +8,17 @@
 mod fd;
 mod fd_list;
 mod inode_guard;
+mod lock;
+mod mmap;
 mod notification;
+mod path_cache;
 pub(crate) mod relative_path_hack;
+mod signal_notification;
+mod watches;
 
 use std::{
     borrow::{Borrow, Cow},
-    collections::{HashMap, HashSet, VecDeque},
+    collections::{BTreeSet, HashMap, HashSet, VecDeque},
     ops::{Deref, DerefMut},
     path::{Component, Path, PathBuf},
     pin::Pin,
@@ -24,8 +29,10 @@ use std::{
     task::{Context, Poll},
 };
 
-use self::fd_list::FdList;
+pub use self::fd_list::FdList;
+use self::path_cache::PathCache;
 use crate::{
+    WasiThread,
     net::socket::InodeSocketKind,
     state::{Stderr, Stdin, Stdout},
 };
@@ -35,25 +42,29 @@ use serde_derive::{Deserialize, Serialize};
 use tokio::io::AsyncWriteExt;
 use tracing::{debug, trace};
 use virtual_fs::{
-    FileSystem, FsError, OpenOptions, UnionFileSystem, VirtualFile, copy_reference,
-    tmp_fs::TmpFileSystem,
+    EmptyFileSystem, FileSystem, FsError, OpenOptions, UnionFileSystem, VirtualFile,
+    copy_reference, tmp_fs::TmpFileSystem,
 };
 use wasmer_config::package::PackageId;
 use wasmer_wasix_types::{
     types::{__WASI_STDERR_FILENO, __WASI_STDIN_FILENO, __WASI_STDOUT_FILENO},
     wasi::{
         Errno, Fd as WasiFd, Fdflags, Fdflagsext, Fdstat, Filesize, Filestat, Filetype,
-        Preopentype, Prestat, PrestatEnum, Rights, Socktype,
+        Fswatchmask, Preopentype, Prestat, PrestatEnum, RLIM_INFINITY, Rights, Socktype,
     },
 };
 
-pub use self::fd::{Fd, FdInner, InodeVal, Kind};
+pub use self::fd::{Fd, FdInner, InodeVal, Kind, PassedFds};
 pub(crate) use self::inode_guard::{
     InodeValFilePollGuard, InodeValFilePollGuardJoin, InodeValFilePollGuardMode,
     InodeValFileReadGuard, InodeValFileWriteGuard, WasiStateFileGuard,
 };
+pub use self::lock::{FileDescription, FileLock};
+pub(crate) use self::mmap::{FileMapping, WasiMappings, read_region};
 pub use self::notification::NotificationInner;
 use self::relative_path_hack::RelativeOrAbsolutePathHack;
+pub use self::signal_notification::SignalNotificationInner;
+pub use self::watches::FsWatches;
 use crate::syscalls::map_io_err;
 use crate::{ALL_RIGHTS, bin_factory::BinaryPackage, state::PreopenedDir};
 
@@ -205,6 +216,10 @@ impl InodeGuard {
                 trace!(%ino, "closing pipe tx");
                 tx.close();
             }
+            Kind::Socket { socket } => {
+                trace!(%ino, "closing socket");
+                socket.close().ok();
+            }
             _ => (),
         }
     }
@@ -258,7 +273,9 @@ pub struct WasiInodes {
     protected: Arc<RwLock<WasiInodesProtected>>,
 }
 
-fn normalize_virtual_symlink_key(path: &Path) -> PathBuf {
+/// Collapses the `.` and `..` components of a path (without looking at
+/// the file system), `..` never goes above the root
+fn normalize_path(path: &Path) -> PathBuf {
     let mut normalized = PathBuf::new();
     for component in path.components() {
         match component {
@@ -418,6 +435,44 @@ pub enum WasiFsRoot {
     Backing(Arc<dyn FileSystem + Send + Sync>),
 }
 
+impl WasiFsRoot {
+    /// Changes the access and modification times of the file or directory
+    /// at `path`, the ones that are `None` are left as they are. A backing
+    /// file system only supports this for the directories that it can open.
+    pub(crate) fn set_times(
+        &self,
+        path: &Path,
+        atime: Option<u64>,
+        mtime: Option<u64>,
+    ) -> virtual_fs::Result<()> {
+        match self {
+            Self::Sandbox(fs) => fs.set_times(path, atime, mtime),
+            Self::Overlay(overlay) => match overlay.primary().set_times(path, atime, mtime) {
+                Err(FsError::EntryNotFound) => {
+                    Self::open_and_set_times(overlay, path, atime, mtime)
+                }
+                res => res,
+            },
+            Self::Backing(fs) => Self::open_and_set_times(fs, path, atime, mtime),
+        }
+    }
+
+    fn open_and_set_times(
+        fs: &dyn FileSystem,
+        path: &Path,
+        atime: Option<u64>,
+        mtime: Option<u64>,
+    ) -> virtual_fs::Result<()> {
+        // Opening the file can touch its access time so the times being
+        // left alone are kept
+        let metadata = fs.metadata(path)?;
+        let atime = atime.or(Some(metadata.accessed));
+        let mtime = mtime.or(Some(metadata.modified));
+        let mut handle = fs.new_open_options().read(true).open(path)?;
+        handle.set_times(atime, mtime)
+    }
+}
+
 impl FileSystem for WasiFsRoot {
     fn readlink(&self, path: &Path) -> virtual_fs::Result<PathBuf> {
         match self {
@@ -575,6 +630,21 @@ fn create_dir_all(fs: &dyn FileSystem, path: &Path) -> Result<(), virtual_fs::Fs
     Ok(())
 }
 
+/// What [`WasiFs::replace_fd_table`] does with a file that can't be opened
+/// again on the host, for instance because it was moved or deleted. This is
+/// how a process that is restored from a journal deals with the files that
+/// are gone, see [`WasiEnvBuilder::with_journal_reopen_failure`].
+///
+/// [`WasiEnvBuilder::with_journal_reopen_failure`]: crate::WasiEnvBuilder::with_journal_reopen_failure
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ReopenFailure {
+    /// The new table is rejected and the current one is kept
+    #[default]
+    Fail,
+    /// The descriptors of the file are left out of the new table
+    Skip,
+}
+
 /// Warning, modifying these fields directly may cause invariants to break and
 /// should be considered unsafe.  These fields may be made private in a future release
 #[cfg_attr(feature = "enable-serde", derive(Serialize, Deserialize))]
@@ -588,6 +658,14 @@ pub struct WasiFs {
     pub root_inode: InodeGuard,
     pub has_unioned: Mutex<HashSet<PackageId>>,
     ephemeral_symlinks: Arc<RwLock<HashMap<PathBuf, EphemeralSymlinkEntry>>>,
+    #[cfg_attr(feature = "enable-serde", serde(skip, default))]
+    path_cache: PathCache,
+    /// The watches that report changes to the file system to the guest
+    #[cfg_attr(feature = "enable-serde", serde(skip, default))]
+    pub(crate) watches: FsWatches,
+    /// The files that are mapped into the linear memory of the process
+    #[cfg_attr(feature = "enable-serde", serde(skip, default))]
+    pub(crate) mappings: Mutex<WasiMappings>,
 
     // TODO: remove
     // using an atomic is a hack to enable customization after construction,
@@ -595,6 +673,25 @@ pub struct WasiFs {
     // It should not be necessary at all.
     is_wasix: AtomicBool,
 
+    // One more than the highest descriptor that can be allocated, which is
+    // the `RLIMIT_NOFILE` soft limit of the process
+    fd_limit: AtomicU64,
+
+    // Set for instances that have no file system at all, every path is
+    // refused with `Errno::Notcapable` and only the stdio descriptors exist
+    #[cfg_attr(feature = "enable-serde", serde(default))]
+    pub(crate) no_filesystem: bool,
+
+    // Size of the buffer that writes to the files that are opened for writing
+    // are coalesced in (see `WasiEnvBuilder::write_buffer_size`)
+    #[cfg_attr(feature = "enable-serde", serde(default))]
+    pub(crate) write_buffer_size: Option<usize>,
+
+    // The paths (and everything below them) that the paths the guest
+    // resolves must be in, see `SandboxPolicy::allowed_paths`
+    #[cfg_attr(feature = "enable-serde", serde(default))]
+    pub(crate) allowed_paths: Option<BTreeSet<PathBuf>>,
+
     // The preopens when this was initialized
     pub(crate) init_preopens: Vec<PreopenedDir>,
     // The virtual file system preopens when this was initialized
@@ -612,6 +709,29 @@ impl WasiFs {
         self.is_wasix.store(is_wasix, Ordering::SeqCst);
     }
 
+    /// Puts the write buffer of the instance in front of a file that was
+    /// opened for writing, if it has one
+    pub(crate) fn buffer_writes(
+        &self,
+        file: Box<dyn VirtualFile + Send + Sync + 'static>,
+        write: bool,
+    ) -> Box<dyn VirtualFile + Send + Sync + 'static> {
+        match self.write_buffer_size {
+            Some(size) if write => Box::new(virtual_fs::BufferedWriteFile::new(file, size)),
+            _ => file,
+        }
+    }
+
+    /// Limits the descriptors that are allocated to the ones below `limit`,
+    /// descriptors that are already open are left alone
+    pub(crate) fn set_fd_limit(&self, limit: u64) {
+        self.fd_limit.store(limit, Ordering::SeqCst);
+    }
+
+    fn is_below_fd_limit(&self, fd: WasiFd) -> bool {
+        u64::from(fd) < self.fd_limit.load(Ordering::Acquire)
+    }
+
     pub(crate) fn register_ephemeral_symlink(
         &self,
         full_path: PathBuf,
@@ -619,12 +739,13 @@ impl WasiFs {
         path_to_symlink: PathBuf,
         relative_path: PathBuf,
     ) {
+        self.invalidate_path_cache();
         let mut guard = self.ephemeral_symlinks.write().unwrap();
         guard.insert(
-            normalize_virtual_symlink_key(&full_path),
+            normalize_path(&full_path),
             EphemeralSymlinkEntry {
                 base_po_dir,
-                path_to_symlink: normalize_virtual_symlink_key(&path_to_symlink),
+                path_to_symlink: normalize_path(&path_to_symlink),
                 relative_path,
             },
         );
@@ -635,7 +756,7 @@ impl WasiFs {
         full_path: &Path,
     ) -> Option<(WasiFd, PathBuf, PathBuf)> {
         let guard = self.ephemeral_symlinks.read().unwrap();
-        let entry = guard.get(&normalize_virtual_symlink_key(full_path))?;
+        let entry = guard.get(&normalize_path(full_path))?;
         Some((
             entry.base_po_dir,
             entry.path_to_symlink.clone(),
@@ -644,8 +765,17 @@ impl WasiFs {
     }
 
     pub(crate) fn unregister_ephemeral_symlink(&self, full_path: &Path) {
+        self.invalidate_path_cache();
         let mut guard = self.ephemeral_symlinks.write().unwrap();
-        guard.remove(&normalize_virtual_symlink_key(full_path));
+        guard.remove(&normalize_path(full_path));
+    }
+
+    /// Forgets the ephemeral symlinks in a directory and its subdirectories
+    pub(crate) fn unregister_ephemeral_symlinks_in(&self, dir: &Path) {
+        self.invalidate_path_cache();
+        let dir = normalize_path(dir);
+        let mut guard = self.ephemeral_symlinks.write().unwrap();
+        guard.retain(|path, _| !path.starts_with(&dir));
     }
 
     pub(crate) fn move_ephemeral_symlink(
@@ -656,32 +786,51 @@ impl WasiFs {
         path_to_symlink: PathBuf,
         relative_path: PathBuf,
     ) {
-        let old_key = normalize_virtual_symlink_key(old_full_path);
-        let new_key = normalize_virtual_symlink_key(new_full_path);
+        let old_key = normalize_path(old_full_path);
+        let new_key = normalize_path(new_full_path);
 
+        self.invalidate_path_cache();
         let mut guard = self.ephemeral_symlinks.write().unwrap();
         guard.remove(&old_key);
         guard.insert(
             new_key,
             EphemeralSymlinkEntry {
                 base_po_dir,
-                path_to_symlink: normalize_virtual_symlink_key(&path_to_symlink),
+                path_to_symlink: normalize_path(&path_to_symlink),
                 relative_path,
             },
         );
     }
 
+    /// Forgets all the paths that were resolved so far, this must be called
+    /// whenever an entry is added to or removed from a directory.
+    pub(crate) fn invalidate_path_cache(&self) {
+        self.path_cache.invalidate();
+    }
+
     /// Forking the WasiState is used when either fork or vfork is called
+    ///
+    /// The file descriptors of the fork refer to the same open files as the
+    /// ones of the parent (like POSIX the inode, handle and offset are shared
+    /// rather than duplicated), so appends from both processes all land at
+    /// the end of the file without overwriting each other.
     pub fn fork(&self) -> Self {
         Self {
             preopen_fds: RwLock::new(self.preopen_fds.read().unwrap().clone()),
             fd_map: RwLock::new(self.fd_map.read().unwrap().clone()),
             current_dir: Mutex::new(self.current_dir.lock().unwrap().clone()),
             is_wasix: AtomicBool::new(self.is_wasix.load(Ordering::Acquire)),
+            fd_limit: AtomicU64::new(self.fd_limit.load(Ordering::Acquire)),
             root_fs: self.root_fs.clone(),
             root_inode: self.root_inode.clone(),
             has_unioned: Mutex::new(self.has_unioned.lock().unwrap().clone()),
             ephemeral_symlinks: self.ephemeral_symlinks.clone(),
+            path_cache: self.path_cache.clone(),
+            watches: self.watches.clone(),
+            mappings: Mutex::new(self.mappings.lock().unwrap().fork()),
+            no_filesystem: self.no_filesystem,
+            write_buffer_size: self.write_buffer_size,
+            allowed_paths: self.allowed_paths.clone(),
             init_preopens: self.init_preopens.clone(),
             init_vfs_preopens: self.init_vfs_preopens.clone(),
         }
@@ -689,20 +838,36 @@ impl WasiFs {
 
     /// Closes all the file handles.
     pub async fn close_cloexec_fds(&self) {
+        self.close_fds_where(|fd, v| {
+            let close = v.inner.fd_flags.contains(Fdflagsext::CLOEXEC)
+                && !v.is_stdio
+                && !v.inode.is_preopened;
+            if close {
+                tracing::trace!(%fd, "Closing FD due to CLOEXEC flag");
+            }
+            close
+        })
+        .await;
+    }
+
+    /// Closes every file handle that `inherit` doesn't accept, the
+    /// preopened directories are always kept.
+    pub async fn close_uninherited_fds(&self, inherit: impl Fn(WasiFd) -> bool) {
+        self.close_fds_where(|fd, v| {
+            let close = !v.inode.is_preopened && !inherit(fd);
+            if close {
+                tracing::trace!(%fd, "Closing FD as it is not inherited");
+            }
+            close
+        })
+        .await;
+    }
+
+    async fn close_fds_where(&self, close: impl Fn(WasiFd, &Fd) -> bool) {
         let to_close = {
             if let Ok(map) = self.fd_map.read() {
                 map.iter()
-                    .filter_map(|(k, v)| {
-                        if v.inner.fd_flags.contains(Fdflagsext::CLOEXEC)
-                            && !v.is_stdio
-                            && !v.inode.is_preopened
-                        {
-                            tracing::trace!(fd = %k, "Closing FD due to CLOEXEC flag");
-                            Some(k)
-                        } else {
-                            None
-                        }
-                    })
+                    .filter_map(|(k, v)| close(k, v).then_some(k))
                     .collect::<HashSet<_>>()
             } else {
                 HashSet::new()
@@ -735,9 +900,11 @@ impl WasiFs {
         to_close.insert(__WASI_STDOUT_FILENO);
         to_close.insert(__WASI_STDERR_FILENO);
 
+        // Every descriptor is flushed before it is closed so that the last
+        // writes of the process aren't lost when the handles are dropped
         let _ = tokio::join!(async {
             for fd in to_close {
-                self.flush(fd).await.ok();
+                self.flush_before_close(fd).await.ok();
                 self.close_fd(fd).ok();
             }
         });
@@ -747,6 +914,94 @@ impl WasiFs {
         }
     }
 
+    /// Replaces the whole fd table at once, which is how the descriptors of
+    /// a process are restored from a snapshot or a journal. Files that lost
+    /// their handle (as they do when the table is deserialized, or when the
+    /// file couldn't be opened while the journal was played) are opened
+    /// again from their path first, `on_failure` decides what happens to the
+    /// ones that can't be opened anymore.
+    ///
+    /// The current table is left untouched when an error is returned,
+    /// otherwise it is handed back to the caller.
+    pub fn replace_fd_table(
+        &self,
+        mut table: FdList,
+        on_failure: ReopenFailure,
+    ) -> Result<FdList, Errno> {
+        // The descriptors of a file share its handle, which has to allow
+        // what any of them was opened for
+        let mut open_flags: HashMap<Inode, u16> = HashMap::new();
+        for (_, entry) in table.iter() {
+            *open_flags.entry(entry.inode.ino()).or_default() |= entry.open_flags;
+        }
+
+        let mut reopened: HashMap<Inode, (InodeGuard, Box<dyn VirtualFile + Send + Sync>)> =
+            HashMap::new();
+        let mut failed = HashSet::new();
+        for (fd, entry) in table.iter() {
+            let ino = entry.inode.ino();
+            if reopened.contains_key(&ino) || failed.contains(&ino) {
+                continue;
+            }
+            let guard = entry.inode.read();
+            let Kind::File {
+                handle: None,
+                path,
+                fd: None,
+            } = guard.deref()
+            else {
+                continue;
+            };
+            if path.as_os_str().is_empty() {
+                continue;
+            }
+
+            let flags = open_flags[&ino];
+            let append = flags & Fd::APPEND != 0;
+            let write = append || flags & Fd::WRITE != 0;
+            let read = flags & Fd::READ != 0 || !write;
+            match self
+                .root_fs
+                .new_open_options()
+                .read(read)
+                .write(write)
+                .append(append)
+                .open(path)
+            {
+                Ok(file) => {
+                    let file = self.buffer_writes(file, write);
+                    reopened.insert(ino, (entry.inode.clone(), file));
+                }
+                Err(err) if on_failure == ReopenFailure::Skip => {
+                    tracing::warn!(%fd, path = %path.display(), "skipping file descriptor that can't be reopened - {err}");
+                    failed.insert(ino);
+                }
+                Err(err) => {
+                    debug!(%fd, path = %path.display(), "failed to reopen file descriptor - {err}");
+                    return Err(fs_error_into_wasi_err(err));
+                }
+            }
+        }
+
+        let skipped = table
+            .iter()
+            .filter_map(|(fd, entry)| failed.contains(&entry.inode.ino()).then_some(fd))
+            .collect::<Vec<_>>();
+        for fd in skipped {
+            table.remove(fd);
+        }
+        for (inode, file) in reopened.into_values() {
+            if let Kind::File { handle, .. } = inode.write().deref_mut()
+                && handle.is_none()
+            {
+                *handle = Some(Arc::new(RwLock::new(file)));
+            }
+        }
+
+        let mut fd_map = self.fd_map.write().unwrap();
+        Ok(std::mem::replace(fd_map.deref_mut(), table))
+    }
+
     /// Will conditionally union the binary file system with this one
     /// if it has not already been unioned
     pub async fn conditional_union(
@@ -791,6 +1046,16 @@ impl WasiFs {
         Ok(wasi_fs)
     }
 
+    /// Created for the builder API. Like `new_with_preopen` for an instance
+    /// without a file system, only the stdio descriptors are opened and the
+    /// root is backed by an empty file system
+    pub(crate) fn new_without_filesystem(inodes: &WasiInodes) -> Self {
+        let root_fs = WasiFsRoot::Backing(Arc::new(EmptyFileSystem::default()));
+        let mut wasi_fs = Self::new_stdio(root_fs, inodes, FS_ROOT_INO);
+        wasi_fs.no_filesystem = true;
+        wasi_fs
+    }
+
     /// Converts a relative path into an absolute path
     pub(crate) fn relative_path_to_absolute(&self, path: String) -> String {
         if path.starts_with('/') {
@@ -808,6 +1073,15 @@ impl WasiFs {
         inodes: &WasiInodes,
         st_ino: Inode,
     ) -> Result<Self, String> {
+        let wasi_fs = Self::new_stdio(fs_backing, inodes, st_ino);
+        wasi_fs.create_rootfd()?;
+
+        Ok(wasi_fs)
+    }
+
+    /// Private helper function that inits the filesystem with only the stdio
+    /// descriptors opened
+    fn new_stdio(fs_backing: WasiFsRoot, inodes: &WasiInodes, st_ino: Inode) -> Self {
         debug!("Initializing WASI filesystem");
 
         let stat = Filestat {
@@ -823,6 +1097,7 @@ impl WasiFs {
             is_preopened: true,
             name: RwLock::new("/".into()),
             kind: RwLock::new(root_kind),
+            lock: Default::default(),
         });
 
         let wasi_fs = Self {
@@ -830,19 +1105,24 @@ impl WasiFs {
             fd_map: RwLock::new(FdList::new()),
             current_dir: Mutex::new("/".to_string()),
             is_wasix: AtomicBool::new(false),
+            fd_limit: AtomicU64::new(RLIM_INFINITY),
             root_fs: fs_backing,
             root_inode,
             has_unioned: Mutex::new(HashSet::new()),
             ephemeral_symlinks: Arc::new(RwLock::new(HashMap::new())),
+            path_cache: Default::default(),
+            watches: Default::default(),
+            mappings: Default::default(),
+            no_filesystem: false,
+            write_buffer_size: None,
+            allowed_paths: None,
             init_preopens: Default::default(),
             init_vfs_preopens: Default::default(),
         };
         wasi_fs.create_stdin(inodes);
         wasi_fs.create_stdout(inodes);
         wasi_fs.create_stderr(inodes);
-        wasi_fs.create_rootfd()?;
-
-        Ok(wasi_fs)
+        wasi_fs
     }
 
     /// This function is like create dir all, but it also opens it.
@@ -902,6 +1182,7 @@ impl WasiFs {
                         match guard.deref_mut() {
                             Kind::Dir { entries, .. } | Kind::Root { entries } => {
                                 entries.insert(segment_name, inode.clone());
+                                self.invalidate_path_cache();
                             }
                             _ => unreachable!("Dir or Root became not Dir or Root"),
                         }
@@ -969,6 +1250,7 @@ impl WasiFs {
                     match guard.deref_mut() {
                         Kind::Dir { entries, .. } | Kind::Root { entries } => {
                             entries.insert(name, inode.clone());
+                            self.invalidate_path_cache();
                         }
                         _ => unreachable!("Dir or Root became not Dir or Root"),
                     }
@@ -1084,8 +1366,29 @@ impl WasiFs {
 
     /// Changes the current directory
     pub fn set_current_dir(&self, path: &str) {
+        let path = normalize_path(Path::new(path));
         let mut guard = self.current_dir.lock().unwrap();
-        *guard = path.to_string();
+        *guard = path.to_string_lossy().into_owned();
+    }
+
+    /// Changes the current directory to a directory that must exist,
+    /// relative paths are resolved against the current directory
+    pub(crate) fn change_dir(&self, path: &str) -> Result<(), Errno> {
+        let path = self.relative_path_to_absolute(path.to_string());
+        let path = normalize_path(Path::new(&path));
+        self.check_path_allowed(&path)?;
+
+        let metadata = self
+            .root_fs
+            .metadata(&path)
+            .map_err(fs_error_into_wasi_err)?;
+        if !metadata.is_dir() {
+            return Err(Errno::Notdir);
+        }
+
+        let mut guard = self.current_dir.lock().unwrap();
+        *guard = path.to_string_lossy().into_owned();
+        Ok(())
     }
 
     /// Gets the current directory
@@ -1139,8 +1442,11 @@ impl WasiFs {
         mut symlink_count: u32,
         follow_symlinks: bool,
     ) -> Result<InodeGuard, Errno> {
+        if self.no_filesystem {
+            return Err(Errno::Notcapable);
+        }
         if symlink_count > MAX_SYMLINKS {
-            return Err(Errno::Mlink);
+            return Err(Errno::Loop);
         }
 
         // Absolute root paths should resolve to the mounted "/" inode when present.
@@ -1374,6 +1680,7 @@ impl WasiFs {
                     | Kind::PipeTx { .. }
                     | Kind::DuplexPipe { .. }
                     | Kind::EventNotifications { .. }
+                    | Kind::SignalNotifications { .. }
                     | Kind::Epoll { .. } => {
                         return Err(Errno::Notdir);
                     }
@@ -1519,19 +1826,120 @@ impl WasiFs {
     // even if it's false, it still follows symlinks, just not the last
     // symlink so
     // This will be resolved when we have tests asserting the correct behavior
+    //
+    // The path has to be in the `allowed_paths` of the sandbox, otherwise
+    // `Errno::Notcapable` is returned
     pub(crate) fn get_inode_at_path(
         &self,
         inodes: &WasiInodes,
         base: WasiFd,
         path: &str,
         follow_symlinks: bool,
+    ) -> Result<InodeGuard, Errno> {
+        let inode = self.resolve_inode_at_path(inodes, base, path, follow_symlinks)?;
+        self.check_inode_allowed(&inode)?;
+        Ok(inode)
+    }
+
+    /// Resolves a symlink that ends a path to the file or directory that it
+    /// points at (through a chain of symlinks), as `get_inode_at_path`
+    /// leaves a symlink that was already loaded at the end of the path as it
+    /// is even when it follows symlinks. Other inodes are returned as is.
+    pub(crate) fn resolve_last_symlink(
+        &self,
+        inodes: &WasiInodes,
+        mut inode: InodeGuard,
+    ) -> Result<InodeGuard, Errno> {
+        for _ in 0..MAX_SYMLINKS {
+            let target = {
+                let guard = inode.read();
+                match guard.deref() {
+                    Kind::Symlink { relative_path, .. } if relative_path.is_absolute() => {
+                        Some((VIRTUAL_ROOT_FD, relative_path.to_string_lossy().to_string()))
+                    }
+                    Kind::Symlink {
+                        base_po_dir,
+                        path_to_symlink,
+                        relative_path,
+                    } => {
+                        let mut path = path_to_symlink.clone();
+                        path.pop();
+                        path.push(relative_path);
+                        Some((*base_po_dir, path.to_string_lossy().to_string()))
+                    }
+                    _ => None,
+                }
+            };
+            match target {
+                Some((base, path)) => inode = self.get_inode_at_path(inodes, base, &path, true)?,
+                None => return Ok(inode),
+            }
+        }
+        Err(Errno::Loop)
+    }
+
+    /// Fails with `Errno::Notcapable` when the sandbox doesn't allow the
+    /// guest to access the file, directory or symlink of `inode`
+    fn check_inode_allowed(&self, inode: &InodeGuard) -> Result<(), Errno> {
+        if self.allowed_paths.is_none() {
+            return Ok(());
+        }
+        let path = {
+            let guard = inode.read();
+            match guard.deref() {
+                Kind::File { path, .. } | Kind::Dir { path, .. } => path.clone(),
+                Kind::Symlink {
+                    base_po_dir,
+                    path_to_symlink,
+                    ..
+                } => {
+                    let base = self.get_fd_inode(*base_po_dir)?;
+                    let guard = base.read();
+                    match guard.deref() {
+                        Kind::Dir { path, .. } => path.join(path_to_symlink),
+                        _ => path_to_symlink.clone(),
+                    }
+                }
+                _ => return Ok(()),
+            }
+        };
+        self.check_path_allowed(&path)
+    }
+
+    /// Fails with `Errno::Notcapable` when the sandbox doesn't allow the
+    /// guest to access `path`
+    fn check_path_allowed(&self, path: &Path) -> Result<(), Errno> {
+        match &self.allowed_paths {
+            Some(allowed) if !allowed.iter().any(|prefix| path.starts_with(prefix)) => {
+                Err(Errno::Notcapable)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn resolve_inode_at_path(
+        &self,
+        inodes: &WasiInodes,
+        base: WasiFd,
+        path: &str,
+        follow_symlinks: bool,
     ) -> Result<InodeGuard, Errno> {
         let base_inode = self.get_fd_inode(base)?;
-        self.get_inode_at_path_inner(inodes, base_inode, path, 0, follow_symlinks)
+        if let Some(inode) = self.path_cache.get(&base_inode, path, follow_symlinks) {
+            return Ok(inode);
+        }
+
+        let generation = self.path_cache.generation();
+        let inode =
+            self.get_inode_at_path_inner(inodes, base_inode.clone(), path, 0, follow_symlinks)?;
+        self.path_cache
+            .insert(generation, &base_inode, path, follow_symlinks, &inode);
+        Ok(inode)
     }
 
     /// Returns the parent Dir or Root that the file at a given path is in and the file name
-    /// stripped off
+    /// stripped off, the file (rather than its parent) has to be in the `allowed_paths` of
+    /// the sandbox
     pub(crate) fn get_parent_inode_at_path(
         &self,
         inodes: &WasiInodes,
@@ -1550,8 +1958,169 @@ impl WasiFs {
         for comp in components.rev() {
             parent_dir.push(comp);
         }
-        self.get_inode_at_path(inodes, base, &parent_dir.to_string_lossy(), follow_symlinks)
-            .map(|v| (v, new_entity_name))
+        let parent = self.resolve_inode_at_path(
+            inodes,
+            base,
+            &parent_dir.to_string_lossy(),
+            follow_symlinks,
+        )?;
+        {
+            let guard = parent.read();
+            match guard.deref() {
+                Kind::Dir { path, .. } => self.check_path_allowed(&path.join(&new_entity_name))?,
+                Kind::Root { .. } => self.check_path_allowed(Path::new(&new_entity_name))?,
+                _ => {}
+            }
+        }
+        Ok((parent, new_entity_name))
+    }
+
+    /// Removes the directory at `path` (relative to `base`) together with
+    /// everything that is in it, the equivalent of `rm -r`. The descriptor
+    /// `base` needs the rights to remove directories and to unlink files.
+    ///
+    /// Symlinks are never followed, they are removed themselves (which
+    /// includes the case where `path` is a symlink). When something can't
+    /// be removed the rest of the tree is still removed as far as possible
+    /// and the first error is returned. Entries that disappear while the
+    /// tree is removed are fine, entries that appear in the meantime make
+    /// the removal of their directory fail with `Errno::Notempty`.
+    ///
+    /// Every entry that is removed is reported to the watches and then to
+    /// `removed`, with its path on the file system and whether it was a
+    /// directory. Builtin commands use [`WasiEnv::remove_dir_all`] which
+    /// journals them.
+    ///
+    /// [`WasiEnv::remove_dir_all`]: crate::WasiEnv::remove_dir_all
+    pub(crate) fn remove_dir_all(
+        &self,
+        inodes: &WasiInodes,
+        base: WasiFd,
+        path: &str,
+        mut removed: impl FnMut(&Path, bool),
+    ) -> Result<(), Errno> {
+        let base_dir = self.get_fd(base)?;
+        if !base_dir
+            .inner
+            .rights
+            .contains(Rights::PATH_REMOVE_DIRECTORY | Rights::PATH_UNLINK_FILE)
+        {
+            return Err(Errno::Access);
+        }
+
+        let inode = self.get_inode_at_path(inodes, base, path, false)?;
+        let (parent_inode, name) =
+            self.get_parent_inode_at_path(inodes, base, Path::new(path), false)?;
+        let host_path = match parent_inode.read().deref() {
+            Kind::Dir { path, .. } => path.join(&name),
+            Kind::Root { .. } => return Err(Errno::Access),
+            _ => return Err(Errno::Notdir),
+        };
+
+        let ret = match inode.read().deref() {
+            Kind::Symlink { .. } => {
+                let ret = match self.root_fs.remove_file(&host_path) {
+                    Ok(()) => {
+                        removed(&host_path, false);
+                        Ok(())
+                    }
+                    Err(FsError::EntryNotFound)
+                        if self.ephemeral_symlink_at(&host_path).is_some() =>
+                    {
+                        Ok(())
+                    }
+                    Err(err) => Err(fs_error_into_wasi_err(err)),
+                };
+                self.unregister_ephemeral_symlink(&host_path);
+                if ret.is_ok() {
+                    self.watches.notify(&host_path, Fswatchmask::DELETE);
+                }
+                ret
+            }
+            Kind::Dir { path, .. } => {
+                self.unregister_ephemeral_symlinks_in(path);
+                self.remove_tree(path, &mut removed)
+            }
+            Kind::Root { .. } => return Err(Errno::Access),
+            _ => return Err(Errno::Notdir),
+        };
+
+        // The inodes of the tree are gone with its entry, whatever is left
+        // of it is loaded again from the file system when it is looked up
+        if let Kind::Dir { entries, .. } = parent_inode.write().deref_mut() {
+            entries.remove(&name);
+        }
+        self.invalidate_path_cache();
+        ret
+    }
+
+    /// Removes a directory of the file system and everything in it, see
+    /// [`WasiFs::remove_dir_all`]
+    fn remove_tree(&self, path: &Path, removed: &mut impl FnMut(&Path, bool)) -> Result<(), Errno> {
+        let mut first_err = None;
+
+        // The directories are visited twice, once to remove the entries in
+        // them and once more to remove the directory itself, after all the
+        // directories in it are gone
+        let mut stack = vec![(path.to_path_buf(), false)];
+        while let Some((dir, emptied)) = stack.pop() {
+            let mut rets = Vec::new();
+            if emptied {
+                rets.push(self.remove_tree_entry(&dir, true, removed));
+            } else {
+                match self.root_fs.read_dir(&dir) {
+                    Ok(entries) => {
+                        stack.push((dir, true));
+                        for entry in entries.filter_map(|entry| entry.ok()) {
+                            rets.push(match self.root_fs.symlink_metadata(&entry.path) {
+                                Ok(meta) if meta.is_dir() && !meta.file_type().is_symlink() => {
+                                    stack.push((entry.path, false));
+                                    Ok(())
+                                }
+                                Ok(_) => self.remove_tree_entry(&entry.path, false, removed),
+                                Err(err) => Err(fs_error_into_wasi_err(err)),
+                            });
+                        }
+                    }
+                    Err(FsError::EntryNotFound) => {}
+                    Err(err) => {
+                        // The directory is still removed, in case it is empty
+                        stack.push((dir, true));
+                        rets.push(Err(fs_error_into_wasi_err(err)));
+                    }
+                }
+            }
+
+            for ret in rets {
+                match ret {
+                    Ok(()) | Err(Errno::Noent) => {}
+                    Err(err) => {
+                        first_err.get_or_insert(err);
+                    }
+                }
+            }
+        }
+        first_err.map_or(Ok(()), Err)
+    }
+
+    /// Removes a single entry of a tree that is removed, see
+    /// [`WasiFs::remove_dir_all`]
+    fn remove_tree_entry(
+        &self,
+        path: &Path,
+        is_dir: bool,
+        removed: &mut impl FnMut(&Path, bool),
+    ) -> Result<(), Errno> {
+        let ret = if is_dir {
+            self.root_fs.remove_dir(path)
+        } else {
+            self.root_fs.remove_file(path)
+        };
+        ret.map_err(fs_error_into_wasi_err)?;
+
+        self.watches.notify(path, Fswatchmask::DELETE);
+        removed(path, is_dir);
+        Ok(())
     }
 
     pub fn get_fd(&self, fd: WasiFd) -> Result<Fd, Errno> {
@@ -1563,13 +2132,14 @@ impl WasiFs {
             .ok_or(Errno::Badf)
             .cloned();
 
-        if ret.is_err() && fd == VIRTUAL_ROOT_FD {
+        if ret.is_err() && fd == VIRTUAL_ROOT_FD && !self.no_filesystem {
             Ok(Fd {
                 inner: FdInner {
                     rights: ALL_RIGHTS,
                     rights_inheriting: ALL_RIGHTS,
                     flags: Fdflags::empty(),
                     offset: Arc::new(AtomicU64::new(0)),
+                    description: Default::default(),
                     fd_flags: Fdflagsext::empty(),
                 },
                 open_flags: 0,
@@ -1583,7 +2153,7 @@ impl WasiFs {
 
     pub fn get_fd_inode(&self, fd: WasiFd) -> Result<InodeGuard, Errno> {
         // see `VIRTUAL_ROOT_FD` for details as to why this exists
-        if fd == VIRTUAL_ROOT_FD {
+        if fd == VIRTUAL_ROOT_FD && !self.no_filesystem {
             return Ok(self.root_inode.clone());
         }
         self.fd_map
@@ -1626,7 +2196,7 @@ impl WasiFs {
                     fs_rights_inheriting: Rights::empty(),
                 });
             }
-            VIRTUAL_ROOT_FD => {
+            VIRTUAL_ROOT_FD if !self.no_filesystem => {
                 return Ok(Fdstat {
                     fs_filetype: Filetype::Directory,
                     fs_flags: Fdflags::empty(),
@@ -1722,22 +2292,83 @@ impl WasiFs {
                 };
                 drop(fd);
 
-                struct FlushPoller {
-                    file: Arc<RwLock<Box<dyn VirtualFile + Send + Sync>>>,
+                flush_file(file).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Flushes a file descriptor that is about to be closed, unlike
+    /// [`WasiFs::flush`] this doesn't need the `FD_DATASYNC` right and also
+    /// pushes out the buffers of sockets.
+    async fn flush_before_close(&self, fd: WasiFd) -> Result<(), Errno> {
+        if matches!(
+            fd,
+            __WASI_STDIN_FILENO | __WASI_STDOUT_FILENO | __WASI_STDERR_FILENO
+        ) {
+            return self.flush(fd).await;
+        }
+
+        let fd = self.get_fd(fd)?;
+        let file = {
+            let guard = fd.inode.read();
+            match guard.deref() {
+                Kind::File {
+                    handle: Some(file), ..
+                } => file.clone(),
+                Kind::Socket { socket } => return socket.try_flush(),
+                _ => return Ok(()),
+            }
+        };
+        drop(fd);
+
+        flush_file(file).await
+    }
+
+    /// Flushes the buffered output of every file descriptor that can be
+    /// written to (the standard streams and the files), all of them are
+    /// flushed even when one fails and the first error is then returned.
+    pub async fn flush_all(&self) -> Result<(), Errno> {
+        let to_flush = {
+            if let Ok(map) = self.fd_map.read() {
+                map.iter()
+                    .filter(|(_, v)| v.inner.rights.contains(Rights::FD_WRITE))
+                    .map(|(k, _)| k)
+                    .collect::<Vec<_>>()
+            } else {
+                Vec::new()
+            }
+        };
+
+        let mut ret = Ok(());
+        for fd in to_flush {
+            let res = match fd {
+                __WASI_STDIN_FILENO | __WASI_STDOUT_FILENO | __WASI_STDERR_FILENO => {
+                    self.flush(fd).await
                 }
-                impl Future for FlushPoller {
-                    type Output = Result<(), Errno>;
-                    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-                        let mut file = self.file.write().unwrap();
-                        Pin::new(file.as_mut())
-                            .poll_flush(cx)
-                            .map_err(|_| Errno::Io)
+                _ => {
+                    let file = match self.get_fd(fd) {
+                        Ok(fd) => match fd.inode.read().deref() {
+                            Kind::File {
+                                handle: Some(file), ..
+                            } => Some(file.clone()),
+                            _ => None,
+                        },
+                        // The descriptor was closed in the meantime
+                        Err(_) => None,
+                    };
+                    match file {
+                        Some(file) => flush_file(file).await,
+                        None => Ok(()),
                     }
                 }
-                FlushPoller { file }.await?;
+            };
+            if let Err(err) = res {
+                tracing::debug!(%fd, %err, "failed to flush the file descriptor");
+                ret = ret.and(Err(err));
             }
         }
-        Ok(())
+        ret
     }
 
     /// Creates an inode and inserts it given a Kind and some extra data
@@ -1819,6 +2450,7 @@ impl WasiFs {
             is_preopened,
             name: RwLock::new(name),
             kind: RwLock::new(kind),
+            lock: Default::default(),
         })
     }
 
@@ -1889,6 +2521,7 @@ impl WasiFs {
                 rights_inheriting,
                 flags: fs_flags,
                 offset: Arc::new(AtomicU64::new(0)),
+                description: Default::default(),
                 fd_flags,
             },
             open_flags,
@@ -1906,8 +2539,24 @@ impl WasiFs {
                     Err(Errno::Exist)
                 }
             }
-            None => Ok(guard.insert_first_free(fd)),
+            None => {
+                if !self.is_below_fd_limit(guard.next_free_fd()) {
+                    return Err(Errno::Mfile);
+                }
+                Ok(guard.insert_first_free(fd))
+            }
+        }
+    }
+
+    /// Installs a descriptor that shares its open file description with
+    /// another one (like a descriptor that was passed over a socket) at
+    /// the lowest free number
+    pub(crate) fn install_fd(&self, fd: Fd) -> Result<WasiFd, Errno> {
+        let mut guard = self.fd_map.write().unwrap();
+        if !self.is_below_fd_limit(guard.next_free_fd()) {
+            return Err(Errno::Mfile);
         }
+        Ok(guard.insert_first_free(fd))
     }
 
     pub fn clone_fd(&self, fd: WasiFd) -> Result<WasiFd, Errno> {
@@ -1921,13 +2570,15 @@ impl WasiFs {
         cloexec: Option<bool>,
     ) -> Result<WasiFd, Errno> {
         let fd = self.get_fd(fd)?;
-        Ok(self.fd_map.write().unwrap().insert_first_free_after(
+        let mut guard = self.fd_map.write().unwrap();
+        let new_fd = guard.insert_first_free_after(
             Fd {
                 inner: FdInner {
                     rights: fd.inner.rights,
                     rights_inheriting: fd.inner.rights_inheriting,
                     flags: fd.inner.flags,
                     offset: fd.inner.offset.clone(),
+                    description: fd.inner.description.clone(),
                     fd_flags: match cloexec {
                         None => fd.inner.fd_flags,
                         Some(cloexec) => {
@@ -1942,7 +2593,12 @@ impl WasiFs {
                 is_stdio: fd.is_stdio,
             },
             min_result_fd,
-        ))
+        );
+        if !self.is_below_fd_limit(new_fd) {
+            guard.remove(new_fd);
+            return Err(Errno::Mfile);
+        }
+        Ok(new_fd)
     }
 
     /// Low level function to remove an inode, that is it deletes the WASI FS's
@@ -1995,6 +2651,11 @@ impl WasiFs {
     }
 
     pub(crate) fn create_rootfd(&self) -> Result<(), String> {
+        // There is no root to open without a file system
+        if self.no_filesystem {
+            return Ok(());
+        }
+
         // create virtual root
         let all_rights = ALL_RIGHTS;
         // TODO: make this a list of positive rigths instead of negative ones
@@ -2076,6 +2737,7 @@ impl WasiFs {
                 let mut guard = self.root_inode.write();
                 if let Kind::Root { entries } = guard.deref_mut() {
                     let existing_entry = entries.insert(preopen_name.clone(), inode);
+                    self.invalidate_path_cache();
                     if existing_entry.is_some() && !ignore_duplicates {
                         return Err(format!("Found duplicate entry for alias `{preopen_name}`"));
                     }
@@ -2199,6 +2861,7 @@ impl WasiFs {
                         path.to_string_lossy().into_owned()
                     };
                     let existing_entry = entries.insert(key.clone(), inode);
+                    self.invalidate_path_cache();
                     if existing_entry.is_some() && !ignore_duplicates {
                         return Err(format!("Found duplicate entry for alias `{key}`"));
                     }
@@ -2237,6 +2900,7 @@ impl WasiFs {
                 is_preopened: true,
                 name: RwLock::new(name.to_string().into()),
                 kind: RwLock::new(kind),
+                lock: Default::default(),
             })
         };
         self.fd_map.write().unwrap().insert(
@@ -2248,6 +2912,7 @@ impl WasiFs {
                     rights_inheriting: Rights::empty(),
                     flags: fd_flags,
                     offset: Arc::new(AtomicU64::new(0)),
+                    description: Default::default(),
                     fd_flags: Fdflagsext::empty(),
                 },
                 // since we're not calling open on this, we don't need open flags
@@ -2327,10 +2992,54 @@ impl WasiFs {
         })
     }
 
+    /// Binds the signal descriptors (see `fd_signal`) of a forked file
+    /// system to the main thread of the forked process, so that they report
+    /// the signals of the child rather than the ones of the thread that
+    /// created them. The reported signals are blocked on that thread, as
+    /// `fd_signal` does when it creates a descriptor.
+    ///
+    /// Every descriptor that shared a signal descriptor inode still shares
+    /// the new inode that replaces it.
+    pub(crate) fn rebind_signal_fds(&self, inodes: &WasiInodes, thread: &WasiThread) {
+        let mut fd_map = self.fd_map.write().unwrap();
+        let signal_fds = fd_map
+            .iter()
+            .filter_map(|(idx, fd)| match &*fd.inode.read() {
+                Kind::SignalNotifications { inner } => Some((idx, fd.clone(), inner.mask())),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+
+        let mut rebound = HashMap::new();
+        for (idx, mut fd, mask) in signal_fds {
+            let inode = rebound
+                .entry(Arc::as_ptr(&fd.inode.inner))
+                .or_insert_with(|| {
+                    thread.set_signal_mask(thread.signal_mask() | mask);
+                    let kind = Kind::SignalNotifications {
+                        inner: Arc::new(SignalNotificationInner::new(thread.clone(), mask)),
+                    };
+                    self.create_inode_with_default_stat(
+                        inodes,
+                        kind,
+                        false,
+                        "signal".to_string().into(),
+                    )
+                })
+                .clone();
+            fd.inode = inode;
+            fd_map.insert(false, idx, fd);
+        }
+    }
+
     /// Closes an open FD, handling all details such as FD being preopen
     pub(crate) fn close_fd(&self, fd: WasiFd) -> Result<(), Errno> {
         let mut fd_map = self.fd_map.write().unwrap();
 
+        // Removing the FD drops its handle on the inode, when that was the
+        // last handle the host resources behind it are released as well (and
+        // the lock of its open file description once the description is gone,
+        // see `FileDescription`)
         let pfd = fd_map.remove(fd).ok_or(Errno::Badf);
         match pfd {
             Ok(fd_ref) => {
@@ -2341,12 +3050,13 @@ impl WasiFs {
                 } else {
                     trace!(%fd, %inode, %ref_cnt, "weakening file descriptor");
                 }
+                Ok(())
             }
             Err(err) => {
                 trace!(%fd, "closing file descriptor failed - {}", err);
+                Err(err)
             }
         }
-        Ok(())
     }
 }
 
@@ -2503,9 +3213,26 @@ pub fn fs_error_into_wasi_err(fs_error: FsError) -> Errno {
     }
 }
 
+async fn flush_file(file: Arc<RwLock<Box<dyn VirtualFile + Send + Sync>>>) -> Result<(), Errno> {
+    struct FlushPoller {
+        file: Arc<RwLock<Box<dyn VirtualFile + Send + Sync>>>,
+    }
+    impl Future for FlushPoller {
+        type Output = Result<(), Errno>;
+        fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+            let mut file = self.file.write().unwrap();
+            Pin::new(file.as_mut())
+                .poll_flush(cx)
+                .map_err(|_| Errno::Io)
+        }
+    }
+    FlushPoller { file }.await
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::bin_factory::FdInheritance;
 
     #[tokio::test]
     async fn test_relative_path_to_absolute() {
@@ -2592,4 +3319,528 @@ mod tests {
             "/home/user/file.txt"
         );
     }
+
+    #[tokio::test]
+    async fn test_replace_fd_table_reopens_files() {
+        let inodes = WasiInodes::new();
+        let tmp_fs = TmpFileSystem::new();
+        tmp_fs
+            .new_open_options()
+            .create(true)
+            .write(true)
+            .open("/foo.txt")
+            .unwrap();
+        let wasi_fs = WasiFs::new_init(WasiFsRoot::Sandbox(tmp_fs), &inodes, FS_ROOT_INO).unwrap();
+
+        // Restored inodes know the path of their file but not its handle
+        let restored_fd = |name: &str| {
+            let inode = wasi_fs.create_inode_with_default_stat(
+                &inodes,
+                Kind::File {
+                    handle: None,
+                    path: PathBuf::from(format!("/{name}")),
+                    fd: None,
+                },
+                false,
+                name.to_string().into(),
+            );
+            let fd = Fd {
+                inner: FdInner {
+                    rights: Rights::all(),
+                    rights_inheriting: Rights::all(),
+                    flags: Fdflags::empty(),
+                    offset: Arc::new(AtomicU64::new(0)),
+                    description: Default::default(),
+                    fd_flags: Fdflagsext::empty(),
+                },
+                open_flags: Fd::READ | Fd::WRITE,
+                inode: inode.clone(),
+                is_stdio: false,
+            };
+            (fd, inode)
+        };
+        let (foo, foo_inode) = restored_fd("foo.txt");
+        let (missing, missing_inode) = restored_fd("missing.txt");
+
+        let mut table = FdList::new();
+        table.insert(true, 5, foo.clone());
+        table.insert(true, 6, foo);
+        table.insert(true, 7, missing);
+
+        assert_eq!(
+            wasi_fs
+                .replace_fd_table(table.clone(), ReopenFailure::Fail)
+                .err(),
+            Some(Errno::Noent)
+        );
+        assert!(wasi_fs.get_fd(5).is_err());
+        assert!(matches!(
+            foo_inode.read().deref(),
+            Kind::File { handle: None, .. }
+        ));
+
+        wasi_fs
+            .replace_fd_table(table, ReopenFailure::Skip)
+            .unwrap();
+        assert!(wasi_fs.get_fd(5).is_ok());
+        assert!(wasi_fs.get_fd(6).is_ok());
+        assert!(wasi_fs.get_fd(7).is_err());
+        assert!(matches!(
+            foo_inode.read().deref(),
+            Kind::File {
+                handle: Some(_),
+                ..
+            }
+        ));
+        assert_eq!(foo_inode.handle_count(), 2);
+        assert_eq!(missing_inode.handle_count(), 0);
+    }
+
+    #[cfg(feature = "host-fs")]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_replace_fd_table_with_a_moved_host_file() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("kept.txt"), b"kept").unwrap();
+        std::fs::write(dir.path().join("moved.txt"), b"moved").unwrap();
+        let host_fs =
+            virtual_fs::host_fs::FileSystem::new(tokio::runtime::Handle::current(), dir.path())
+                .unwrap();
+
+        let inodes = WasiInodes::new();
+        let wasi_fs =
+            WasiFs::new_init(WasiFsRoot::Backing(Arc::new(host_fs)), &inodes, FS_ROOT_INO).unwrap();
+        let restored_fd = |name: &str, open_flags: u16| {
+            let inode = wasi_fs.create_inode_with_default_stat(
+                &inodes,
+                Kind::File {
+                    handle: None,
+                    path: PathBuf::from(format!("/{name}")),
+                    fd: None,
+                },
+                false,
+                name.to_string().into(),
+            );
+            Fd {
+                inner: FdInner {
+                    rights: Rights::all(),
+                    rights_inheriting: Rights::all(),
+                    flags: Fdflags::empty(),
+                    offset: Arc::new(AtomicU64::new(0)),
+                    description: Default::default(),
+                    fd_flags: Fdflagsext::empty(),
+                },
+                open_flags,
+                inode,
+                is_stdio: false,
+            }
+        };
+        let kept = restored_fd("kept.txt", Fd::READ | Fd::WRITE);
+        let moved = restored_fd("moved.txt", Fd::READ);
+
+        let mut table = FdList::new();
+        table.insert(true, 3, kept.clone());
+        table.insert(true, 4, moved.clone());
+
+        // The file moved on the host after the table was captured
+        std::fs::rename(
+            dir.path().join("moved.txt"),
+            dir.path().join("elsewhere.txt"),
+        )
+        .unwrap();
+
+        assert_eq!(
+            wasi_fs
+                .replace_fd_table(table.clone(), ReopenFailure::Fail)
+                .err(),
+            Some(Errno::Noent)
+        );
+        assert!(wasi_fs.get_fd(3).is_err());
+
+        wasi_fs
+            .replace_fd_table(table, ReopenFailure::Skip)
+            .unwrap();
+        assert!(wasi_fs.get_fd(4).is_err());
+        assert_eq!(moved.inode.handle_count(), 0);
+
+        // The file that stayed is opened again from its path
+        match kept.inode.read().deref() {
+            Kind::File {
+                handle: Some(handle),
+                ..
+            } => assert_eq!(handle.read().unwrap().size(), 4),
+            _ => panic!("the handle of kept.txt was not reopened"),
+        }
+        assert_eq!(kept.inode.handle_count(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_close_fd_twice_releases_the_file() {
+        let inodes = WasiInodes::new();
+        let tmp_fs = TmpFileSystem::new();
+        let file = tmp_fs
+            .new_open_options()
+            .create(true)
+            .write(true)
+            .open("/foo.txt")
+            .unwrap();
+        let handle = Arc::new(RwLock::new(file));
+        let weak_handle = Arc::downgrade(&handle);
+
+        let wasi_fs = WasiFs::new_init(WasiFsRoot::Sandbox(tmp_fs), &inodes, FS_ROOT_INO).unwrap();
+        let inode = wasi_fs.create_inode_with_default_stat(
+            &inodes,
+            Kind::File {
+                handle: Some(handle),
+                path: PathBuf::from("/foo.txt"),
+                fd: None,
+            },
+            false,
+            "foo.txt".into(),
+        );
+        let fd = wasi_fs
+            .create_fd(
+                Rights::all(),
+                Rights::all(),
+                Fdflags::empty(),
+                Fdflagsext::empty(),
+                0,
+                inode.clone(),
+            )
+            .unwrap();
+        assert_eq!(inode.handle_count(), 1);
+
+        assert_eq!(wasi_fs.close_fd(fd), Ok(()));
+        assert_eq!(wasi_fs.close_fd(fd), Err(Errno::Badf));
+
+        // The host file is released as soon as the last FD is closed, even
+        // though the inode itself is still alive
+        assert_eq!(inode.handle_count(), 0);
+        assert!(matches!(
+            inode.read().deref(),
+            Kind::File { handle: None, .. }
+        ));
+        assert!(weak_handle.upgrade().is_none());
+    }
+
+    #[cfg(feature = "host-fs")]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_close_all_flushes_host_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let host_fs =
+            virtual_fs::host_fs::FileSystem::new(tokio::runtime::Handle::current(), dir.path())
+                .unwrap();
+        let mut file = host_fs
+            .new_open_options()
+            .create(true)
+            .write(true)
+            .open("/out.txt")
+            .unwrap();
+        // Host files write in the background, nothing is guaranteed to be
+        // on disk until they are flushed
+        file.write_all(b"hello world").await.unwrap();
+
+        let inodes = WasiInodes::new();
+        let wasi_fs = WasiFs::new_init(
+            WasiFsRoot::Sandbox(TmpFileSystem::new()),
+            &inodes,
+            FS_ROOT_INO,
+        )
+        .unwrap();
+        let inode = wasi_fs.create_inode_with_default_stat(
+            &inodes,
+            Kind::File {
+                handle: Some(Arc::new(RwLock::new(file))),
+                path: PathBuf::from("/out.txt"),
+                fd: None,
+            },
+            false,
+            "out.txt".into(),
+        );
+        // The descriptor is not allowed to call `fd_datasync` itself
+        wasi_fs
+            .create_fd(
+                Rights::FD_WRITE,
+                Rights::empty(),
+                Fdflags::empty(),
+                Fdflagsext::empty(),
+                0,
+                inode.clone(),
+            )
+            .unwrap();
+
+        wasi_fs.close_all().await;
+
+        assert_eq!(inode.handle_count(), 0);
+        assert_eq!(
+            std::fs::read(dir.path().join("out.txt")).unwrap(),
+            b"hello world"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_path_cache_is_invalidated_when_entries_change() {
+        let inodes = WasiInodes::new();
+        let tmp_fs = TmpFileSystem::new();
+        tmp_fs.create_dir(Path::new("/dir")).unwrap();
+        tmp_fs
+            .new_open_options()
+            .create(true)
+            .write(true)
+            .open("/dir/a.txt")
+            .unwrap();
+        let wasi_fs = WasiFs::new_with_preopen(
+            &inodes,
+            &[],
+            &["/".to_string()],
+            WasiFsRoot::Sandbox(tmp_fs.clone()),
+        )
+        .unwrap();
+
+        // Repeated lookups are served from the cache
+        let first = wasi_fs
+            .get_inode_at_path(&inodes, VIRTUAL_ROOT_FD, "/dir/a.txt", true)
+            .unwrap();
+        let second = wasi_fs
+            .get_inode_at_path(&inodes, VIRTUAL_ROOT_FD, "/dir/a.txt", true)
+            .unwrap();
+        assert!(Arc::ptr_eq(&first.inner, &second.inner));
+
+        // A lookup that raced with a change is not remembered
+        let dir = wasi_fs
+            .get_inode_at_path(&inodes, VIRTUAL_ROOT_FD, "/dir", true)
+            .unwrap();
+        let generation = wasi_fs.path_cache.generation();
+        wasi_fs.invalidate_path_cache();
+        wasi_fs
+            .path_cache
+            .insert(generation, &dir, "stale", true, &first);
+        assert!(wasi_fs.path_cache.get(&dir, "stale", true).is_none());
+
+        // Unlinking the file (the way `path_unlink_file` does) is seen by
+        // the next lookup
+        tmp_fs.remove_file(Path::new("/dir/a.txt")).unwrap();
+        if let Kind::Dir { entries, .. } = dir.write().deref_mut() {
+            entries.remove("a.txt");
+        }
+        wasi_fs.invalidate_path_cache();
+        assert_eq!(
+            wasi_fs
+                .get_inode_at_path(&inodes, VIRTUAL_ROOT_FD, "/dir/a.txt", true)
+                .unwrap_err(),
+            Errno::Noent
+        );
+    }
+
+    #[tokio::test]
+    async fn test_remove_dir_all() {
+        let inodes = WasiInodes::new();
+        let tmp_fs = TmpFileSystem::new();
+        for dir in [
+            "/tree",
+            "/tree/a",
+            "/tree/a/b",
+            "/tree/c",
+            "/target",
+            "/links",
+        ] {
+            tmp_fs.create_dir(Path::new(dir)).unwrap();
+        }
+        for file in [
+            "/tree/x.txt",
+            "/tree/a/y.txt",
+            "/tree/a/b/z.txt",
+            "/target/keep.txt",
+        ] {
+            tmp_fs
+                .new_open_options()
+                .create(true)
+                .write(true)
+                .open(file)
+                .unwrap();
+        }
+        tmp_fs
+            .create_symlink(Path::new("/target"), Path::new("/tree/c/link"))
+            .unwrap();
+        tmp_fs
+            .create_symlink(Path::new("/target"), Path::new("/links/target"))
+            .unwrap();
+        let wasi_fs = WasiFs::new_with_preopen(
+            &inodes,
+            &[PreopenedDir {
+                path: "/".into(),
+                alias: None,
+                read: true,
+                write: true,
+                create: false,
+            }],
+            &["/".to_string()],
+            WasiFsRoot::Sandbox(tmp_fs.clone()),
+        )
+        .unwrap();
+        // The preopens of the `/` directory rather than the virtual root,
+        // the first one is read only
+        let preopens: Vec<_> = wasi_fs
+            .preopen_fds
+            .read()
+            .unwrap()
+            .iter()
+            .copied()
+            .filter(|fd| {
+                matches!(
+                    wasi_fs.get_fd_inode(*fd).unwrap().read().deref(),
+                    Kind::Dir { .. }
+                )
+            })
+            .collect();
+        let (read_only, root) = (preopens[0], preopens[1]);
+        let mut removed = Vec::new();
+        let mut remove_dir_all = |path: &str| {
+            wasi_fs.remove_dir_all(&inodes, root, path, |path, is_dir| {
+                removed.push((path.to_path_buf(), is_dir))
+            })
+        };
+
+        assert_eq!(
+            wasi_fs.remove_dir_all(&inodes, read_only, "tree", |_, _| {}),
+            Err(Errno::Access)
+        );
+
+        // Files are not directories
+        assert_eq!(remove_dir_all("tree/x.txt"), Err(Errno::Notdir));
+
+        // The inodes that were looked up before are forgotten as well, the
+        // watches hear about everything that is removed
+        wasi_fs
+            .get_inode_at_path(&inodes, root, "tree/a/b/z.txt", true)
+            .unwrap();
+        let mut events = wasi_fs
+            .watches
+            .add(Path::new("/tree/a"), Fswatchmask::DELETE);
+        remove_dir_all("tree").unwrap();
+        assert!(tmp_fs.metadata(Path::new("/tree")).is_err());
+        assert_eq!(
+            wasi_fs
+                .get_inode_at_path(&inodes, root, "tree/a/b/z.txt", true)
+                .unwrap_err(),
+            Errno::Noent
+        );
+        let mut buf = [0u8; 64];
+        let mut read = 0;
+        while let Some(amt @ 1..) = events.try_read(&mut buf[read..]) {
+            read += amt;
+        }
+        assert_eq!(read, 3 * 8 + "y.txt".len() + "b".len());
+
+        // The link in the tree was removed rather than what it points to
+        assert!(tmp_fs.metadata(Path::new("/target/keep.txt")).is_ok());
+
+        // A link to a directory is removed itself
+        remove_dir_all("links/target").unwrap();
+        assert!(tmp_fs.symlink_metadata(Path::new("/links/target")).is_err());
+        assert!(tmp_fs.metadata(Path::new("/target/keep.txt")).is_ok());
+
+        assert_eq!(remove_dir_all("tree"), Err(Errno::Noent));
+
+        // The directories are removed after what is in them
+        drop(remove_dir_all);
+        let dirs = ["/tree/a/b", "/tree/a", "/tree/c", "/tree"].map(Path::new);
+        let position = |path: &Path| removed.iter().position(|(p, _)| p == path).unwrap();
+        assert_eq!(removed.len(), 9);
+        for (path, is_dir) in &removed {
+            assert_eq!(*is_dir, dirs.contains(&path.as_path()));
+            if let Some(parent) = path.parent().filter(|parent| dirs.contains(parent)) {
+                assert!(position(path) < position(parent));
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_close_uninherited_fds_keeps_preopens() {
+        let inodes = WasiInodes::new();
+        let wasi_fs = WasiFs::new_with_preopen(
+            &inodes,
+            &[],
+            &["/".to_string()],
+            WasiFsRoot::Sandbox(TmpFileSystem::new()),
+        )
+        .unwrap();
+        let preopens = wasi_fs.preopen_fds.read().unwrap().clone();
+        assert!(!preopens.is_empty());
+
+        let open = |name: &str| {
+            let inode = wasi_fs.create_inode_with_default_stat(
+                &inodes,
+                Kind::Buffer { buffer: Vec::new() },
+                false,
+                name.to_string().into(),
+            );
+            wasi_fs
+                .create_fd(
+                    Rights::all(),
+                    Rights::empty(),
+                    Fdflags::empty(),
+                    Fdflagsext::empty(),
+                    0,
+                    inode,
+                )
+                .unwrap()
+        };
+        let kept = open("kept");
+        let closed = open("closed");
+
+        let inherit = FdInheritance::Only(vec![kept]);
+        wasi_fs
+            .close_uninherited_fds(|fd| inherit.inherits(fd))
+            .await;
+
+        assert!(wasi_fs.get_fd(kept).is_ok());
+        assert_eq!(wasi_fs.get_fd(closed).unwrap_err(), Errno::Badf);
+        for fd in preopens {
+            assert!(wasi_fs.get_fd(fd).is_ok());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_change_dir() {
+        let inodes = WasiInodes::new();
+        let tmp_fs = TmpFileSystem::new();
+        tmp_fs.create_dir(Path::new("/a")).unwrap();
+        tmp_fs.create_dir(Path::new("/a/b")).unwrap();
+        tmp_fs
+            .new_open_options()
+            .create(true)
+            .write(true)
+            .open("/a/file.txt")
+            .unwrap();
+        let wasi_fs = WasiFs::new_with_preopen(
+            &inodes,
+            &[],
+            &["/".to_string()],
+            WasiFsRoot::Sandbox(tmp_fs),
+        )
+        .unwrap();
+        let cwd = |wasi_fs: &WasiFs| wasi_fs.current_dir.lock().unwrap().clone();
+
+        // Into nested directories and back out again
+        wasi_fs.change_dir("/a/b").unwrap();
+        assert_eq!(cwd(&wasi_fs), "/a/b");
+        wasi_fs.change_dir("..").unwrap();
+        assert_eq!(cwd(&wasi_fs), "/a");
+        wasi_fs.change_dir("./b/../b/.").unwrap();
+        assert_eq!(cwd(&wasi_fs), "/a/b");
+        wasi_fs.change_dir("../../..").unwrap();
+        assert_eq!(cwd(&wasi_fs), "/");
+
+        // Relative paths resolve against the new directory
+        wasi_fs.change_dir("a/").unwrap();
+        assert_eq!(
+            wasi_fs.relative_path_to_absolute("file.txt".to_string()),
+            "/a/file.txt"
+        );
+
+        // Failures leave the current directory alone
+        assert_eq!(wasi_fs.change_dir("missing"), Err(Errno::Noent));
+        assert_eq!(wasi_fs.change_dir("file.txt"), Err(Errno::Notdir));
+        assert_eq!(cwd(&wasi_fs), "/a");
+    }
 }
@@ -0,0 +1,119 @@
+//! Remembers which inode a path resolved to, relative to the directory it
+//! was resolved from, so that guests that keep opening files in the same
+//! place don't have to walk every component of the path each time.
+//!
+//! The invalidation is deliberately coarse: any change to the entries of
+//! any directory (create, unlink, rename, link, symlink, ...) throws the
+//! whole cache away.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, Weak},
+};
+
+use super::{InodeGuard, InodeVal, InodeWeakGuard};
+
+/// Upper bound on the number of paths that are remembered, once it is
+/// reached the cache starts again from scratch
+const MAX_ENTRIES: usize = 1024;
+
+#[derive(Debug, PartialEq, Eq, Hash)]
+struct PathCacheKey {
+    /// Identity of the directory the path was resolved from
+    base: *const InodeVal,
+    path: String,
+    follow_symlinks: bool,
+}
+
+// The pointer is only used as an identity and never dereferenced
+unsafe impl Send for PathCacheKey {}
+unsafe impl Sync for PathCacheKey {}
+
+#[derive(Debug)]
+struct PathCacheEntry {
+    /// Keeps the identity of the base directory honest in case the
+    /// memory of a dropped inode gets reused for another one
+    base: Weak<InodeVal>,
+    inode: InodeWeakGuard,
+}
+
+#[derive(Debug, Default)]
+struct PathCacheState {
+    entries: HashMap<PathCacheKey, PathCacheEntry>,
+    /// Bumped on every invalidation so that a resolution that raced with
+    /// a change to the file system is not stored
+    generation: u64,
+}
+
+/// Cache of resolved paths, this is shared between all the forks of a
+/// process as they also share the inode tree.
+#[derive(Debug, Default, Clone)]
+pub(crate) struct PathCache {
+    state: Arc<Mutex<PathCacheState>>,
+}
+
+impl PathCache {
+    /// Returns the inode the path resolved to the last time, if it is
+    /// still known and alive.
+    pub(crate) fn get(
+        &self,
+        base: &InodeGuard,
+        path: &str,
+        follow_symlinks: bool,
+    ) -> Option<InodeGuard> {
+        let state = self.state.lock().unwrap();
+        let entry = state.entries.get(&PathCacheKey {
+            base: Arc::as_ptr(&base.inner),
+            path: path.to_string(),
+            follow_symlinks,
+        })?;
+        if !Weak::ptr_eq(&entry.base, &Arc::downgrade(&base.inner)) {
+            return None;
+        }
+        entry.inode.upgrade()
+    }
+
+    /// Returns the current generation of the cache, this must be read
+    /// before the path is resolved and passed to [`PathCache::insert`].
+    pub(crate) fn generation(&self) -> u64 {
+        self.state.lock().unwrap().generation
+    }
+
+    /// Remembers what a path resolved to, unless the cache was invalidated
+    /// since `generation` was read.
+    pub(crate) fn insert(
+        &self,
+        generation: u64,
+        base: &InodeGuard,
+        path: &str,
+        follow_symlinks: bool,
+        inode: &InodeGuard,
+    ) {
+        let mut state = self.state.lock().unwrap();
+        if state.generation != generation {
+            return;
+        }
+        if state.entries.len() >= MAX_ENTRIES {
+            state.entries.clear();
+        }
+        state.entries.insert(
+            PathCacheKey {
+                base: Arc::as_ptr(&base.inner),
+                path: path.to_string(),
+                follow_symlinks,
+            },
+            PathCacheEntry {
+                base: Arc::downgrade(&base.inner),
+                inode: inode.downgrade(),
+            },
+        );
+    }
+
+    /// Forgets every path, this must be called whenever the entries of a
+    /// directory change.
+    pub(crate) fn invalidate(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.entries.clear();
+        state.generation += 1;
+    }
+}
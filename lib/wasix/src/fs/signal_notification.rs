@@ -0,0 +1,56 @@
+use std::task::{Poll, Waker};
+
+use wasmer_wasix_types::wasi::Signal;
+
+use crate::WasiThread;
+
+/// A descriptor that reports the signals of a thread instead of having them
+/// delivered to its signal handler (the equivalent of a `signalfd`, see
+/// `fd_signal`).
+///
+/// The signals are blocked by the thread when the descriptor is created,
+/// otherwise they could be delivered before they are read from it.
+#[derive(Debug)]
+pub struct SignalNotificationInner {
+    /// The thread whose pending signals are read
+    thread: WasiThread,
+    /// The signals that are read (one bit per signal number)
+    mask: u64,
+}
+
+impl SignalNotificationInner {
+    pub fn new(thread: WasiThread, mask: u64) -> Self {
+        Self { thread, mask }
+    }
+
+    /// The signals that are read (one bit per signal number)
+    pub fn mask(&self) -> u64 {
+        self.mask
+    }
+
+    /// Returns the number of bytes that a read returns once one of the
+    /// signals is pending
+    pub fn poll(&self, waker: &Waker) -> Poll<usize> {
+        if self.thread.has_signal_in_or_subscribe(self.mask, waker) {
+            Poll::Ready(std::mem::size_of::<u32>())
+        } else {
+            Poll::Pending
+        }
+    }
+
+    /// Takes one of the pending signals
+    pub fn read(&self, waker: &Waker) -> Poll<Signal> {
+        loop {
+            if let Some(signal) = self.thread.take_signal_in(self.mask) {
+                return Poll::Ready(signal);
+            }
+            if !self.thread.has_signal_in_or_subscribe(self.mask, waker) {
+                return Poll::Pending;
+            }
+        }
+    }
+
+    pub fn try_read(&self) -> Option<Signal> {
+        self.thread.take_signal_in(self.mask)
+    }
+}
@@ -0,0 +1,171 @@
+//! Watches on the paths of the file system (the equivalent of `inotify`).
+//!
+//! Every watch writes its events into a pipe, the guest reads the other end
+//! of the pipe (and polls it) like any other pipe. A watch goes away once
+//! the guest closed the read end.
+//!
+//! The pipe of a watch is bounded, once it is full the events are dropped
+//! and a single [`Fswatchmask::OVERFLOW`] event tells the guest that it
+//! missed some (the equivalent of `IN_Q_OVERFLOW`).
+
+use std::{
+    io::Write,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use virtual_fs::{Pipe, PipeRx, PipeTx};
+use wasmer_wasix_types::wasi::{Fsevent, Fswatchmask};
+
+use super::normalize_path;
+
+/// How many bytes of events a watch holds before the guest reads them
+const WATCH_QUEUE_LIMIT: usize = 64 * 1024;
+
+/// Size of an `Fsevent` without a name, which is what the overflow event is
+const EVENT_LEN: usize = 8;
+
+#[derive(Debug)]
+struct FsWatch {
+    /// Normalized absolute path that is watched
+    path: PathBuf,
+    mask: Fswatchmask,
+    tx: PipeTx,
+    /// The queue was full and the overflow event was sent, the events are
+    /// dropped until the guest made room again
+    overflowed: bool,
+}
+
+/// The watches on a file system, this is shared between all the forks of a
+/// process.
+///
+/// The host can hand the same watches to the
+/// [builder](crate::WasiEnvBuilder::fs_watches) and keep a clone of them to
+/// [inject](FsWatches::notify) changes that the guest doesn't make itself,
+/// for instance when the host updates the files behind its back.
+#[derive(Debug, Default, Clone)]
+pub struct FsWatches {
+    watches: Arc<Mutex<Vec<FsWatch>>>,
+}
+
+impl FsWatches {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reports the events of `mask` on `path` and on the entries directly in
+    /// it (when it is a directory) to the returned end of the pipe.
+    pub(crate) fn add(&self, path: &Path, mask: Fswatchmask) -> PipeRx {
+        let (tx, rx) = Pipe::with_capacity(WATCH_QUEUE_LIMIT).split();
+        self.watches.lock().unwrap().push(FsWatch {
+            path: absolute_path(path),
+            mask,
+            tx,
+            overflowed: false,
+        });
+        rx
+    }
+
+    /// Reports a change to `path` to the watches on it and on its parent
+    /// directory, the watches that the guest closed are forgotten.
+    ///
+    /// The paths are the ones of the file system of the instance, paths
+    /// that are not absolute are relative to its root.
+    pub fn notify(&self, path: impl AsRef<Path>, mask: Fswatchmask) {
+        let mut watches = self.watches.lock().unwrap();
+        if watches.is_empty() {
+            return;
+        }
+
+        let path = absolute_path(path.as_ref());
+        watches.retain_mut(|watch| {
+            let mask = mask & watch.mask;
+            if mask.is_empty() {
+                return true;
+            }
+            let name = if path == watch.path {
+                ""
+            } else if path.parent() == Some(watch.path.as_path()) {
+                match path.file_name().and_then(|name| name.to_str()) {
+                    Some(name) => name,
+                    None => return true,
+                }
+            } else {
+                return true;
+            };
+
+            // There is always room left for the overflow event
+            let free = watch.tx.free_capacity().unwrap_or(usize::MAX);
+            let buf = if free >= EVENT_LEN * 2 + name.len() {
+                watch.overflowed = false;
+                encode_event(mask, name)
+            } else if !watch.overflowed {
+                watch.overflowed = true;
+                encode_event(Fswatchmask::OVERFLOW, "")
+            } else {
+                return true;
+            };
+
+            // The whole event is sent at once so events never interleave,
+            // the only error is the guest having closed the watch
+            watch.tx.write(&buf).is_ok()
+        });
+    }
+}
+
+fn encode_event(mask: Fswatchmask, name: &str) -> Vec<u8> {
+    let event = Fsevent {
+        mask: mask.bits(),
+        _padding: 0,
+        name_len: name.len() as u32,
+    };
+    let mut buf = Vec::with_capacity(EVENT_LEN + name.len());
+    buf.extend_from_slice(&event.mask.to_le_bytes());
+    buf.extend_from_slice(&event._padding.to_le_bytes());
+    buf.extend_from_slice(&event.name_len.to_le_bytes());
+    buf.extend_from_slice(name.as_bytes());
+    buf
+}
+
+fn absolute_path(path: &Path) -> PathBuf {
+    normalize_path(&Path::new("/").join(path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_full_watch_reports_a_single_overflow() {
+        let watches = FsWatches::new();
+        let mut rx = watches.add(Path::new("/data"), Fswatchmask::MODIFY);
+
+        let event_len = EVENT_LEN + "file".len();
+        let fits = (WATCH_QUEUE_LIMIT - EVENT_LEN) / event_len;
+        for _ in 0..fits * 2 {
+            watches.notify("/data/file", Fswatchmask::MODIFY);
+        }
+
+        let mut queued = vec![0u8; WATCH_QUEUE_LIMIT * 2];
+        let mut read = 0;
+        while let Some(amt) = rx.try_read(&mut queued[read..]) {
+            if amt == 0 {
+                break;
+            }
+            read += amt;
+        }
+        let overflow = encode_event(Fswatchmask::OVERFLOW, "");
+        assert!(read <= WATCH_QUEUE_LIMIT);
+        assert_eq!(read, fits * event_len + overflow.len());
+        assert_eq!(&queued[read - overflow.len()..read], overflow.as_slice());
+
+        // Once the guest read the events they are reported again
+        watches.notify("/data/file", Fswatchmask::MODIFY);
+        let mut event = [0u8; 12];
+        assert_eq!(rx.try_read(&mut event), Some(12));
+        assert_eq!(
+            event.as_slice(),
+            encode_event(Fswatchmask::MODIFY, "file").as_slice()
+        );
+    }
+}
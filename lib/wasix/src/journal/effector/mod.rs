@@ -26,6 +26,7 @@ mod syscalls {
     pub(super) use super::*;
     mod chdir;
     mod clock_time;
+    mod copy_file_range;
     mod epoll_create;
     mod epoll_ctl;
     mod fd_advise;
@@ -75,6 +76,7 @@ mod syscalls {
     mod sock_send_to;
     mod sock_set_opt_flag;
     mod sock_set_opt_size;
+    mod sock_set_opt_str;
     mod sock_set_opt_time;
     mod sock_shutdown;
     mod tty_set;
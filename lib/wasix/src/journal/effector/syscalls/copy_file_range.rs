@@ -0,0 +1,41 @@
+use super::*;
+
+impl JournalEffector {
+    /// A copy is saved as the writes of the copied bytes to `fd_out` (which
+    /// is how `fd_write` saves them) followed by the seeks that leave the
+    /// offsets of the descriptors where the copy left them, so that the
+    /// replay doesn't depend on what `fd_in` contains by then
+    pub fn save_copy_file_range<M: MemorySize>(
+        ctx: &mut FunctionEnvMut<'_, WasiEnv>,
+        fd_in: Fd,
+        moved_in: bool,
+        fd_out: Fd,
+        moved_out: bool,
+        mut offset: Filesize,
+        data: &[u8],
+    ) -> anyhow::Result<()> {
+        for chunk in data.chunks(64 * 1024) {
+            Self::save_event(
+                ctx,
+                JournalEntry::FileDescriptorWriteV1 {
+                    fd: fd_out,
+                    offset,
+                    data: Cow::Borrowed(chunk),
+                    is_64bit: M::is_64bit(),
+                },
+            )?;
+            offset += chunk.len() as Filesize;
+        }
+
+        // Replaying the writes moves the offset of `fd_out`, which the copy
+        // only does when it used that offset
+        let copied = data.len() as i64;
+        if !moved_out && copied > 0 {
+            Self::save_fd_seek(ctx, fd_out, -copied, Whence::Cur)?;
+        }
+        if moved_in && copied > 0 {
+            Self::save_fd_seek(ctx, fd_in, copied, Whence::Cur)?;
+        }
+        Ok(())
+    }
+}
@@ -1,3 +1,7 @@
+use std::{ops::Deref, path::PathBuf};
+
+use crate::fs::Kind;
+
 use super::*;
 
 impl JournalEffector {
@@ -65,4 +69,81 @@ impl JournalEffector {
         };
         Ok(())
     }
+
+    /// Restores the descriptor of a file that can't be opened while the
+    /// journal is played (it may have been moved on the host since then)
+    /// without a handle. The file is opened again once the whole journal was
+    /// played, see [`crate::fs::WasiFs::replace_fd_table`].
+    #[allow(clippy::too_many_arguments)]
+    pub fn apply_path_open_unresolved(
+        ctx: &mut FunctionEnvMut<'_, WasiEnv>,
+        fd: Fd,
+        dirfd: Fd,
+        path: &str,
+        fs_rights_base: Rights,
+        fs_rights_inheriting: Rights,
+        fs_flags: Fdflags,
+        fd_flags: Fdflagsext,
+    ) -> anyhow::Result<()> {
+        let state = &ctx.data().state;
+        let host_path = if path.starts_with('/') {
+            PathBuf::from(path)
+        } else {
+            let dir = state.fs.get_fd(dirfd).map_err(|err| {
+                anyhow::format_err!(
+                    "journal restore error: failed to open descriptor (fd={fd}, path={path}) - {err}"
+                )
+            })?;
+            match dir.inode.read().deref() {
+                Kind::Dir { path: dir_path, .. } => dir_path.join(path),
+                Kind::Root { .. } => PathBuf::from(path),
+                _ => bail!(
+                    "journal restore error: failed to open descriptor (fd={fd}, path={path}) - {}",
+                    Errno::Notdir
+                ),
+            }
+        };
+        let name = host_path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+
+        let mut open_flags = 0;
+        if fs_rights_base.contains(Rights::FD_READ) {
+            open_flags |= crate::fs::Fd::READ;
+        }
+        if fs_rights_base.contains(Rights::FD_WRITE) {
+            open_flags |= crate::fs::Fd::WRITE;
+        }
+        if fs_flags.contains(Fdflags::APPEND) {
+            open_flags |= crate::fs::Fd::APPEND;
+        }
+
+        let inode = state.fs.create_inode_with_default_stat(
+            &state.inodes,
+            Kind::File {
+                handle: None,
+                path: host_path,
+                fd: None,
+            },
+            false,
+            name.into(),
+        );
+        state
+            .fs
+            .with_fd(
+                fs_rights_base,
+                fs_rights_inheriting,
+                fs_flags,
+                fd_flags,
+                open_flags,
+                inode,
+                fd,
+            )
+            .map_err(|err| {
+                anyhow::format_err!(
+                    "journal restore error: failed to restore descriptor (fd={fd}, path={path}) - {err}"
+                )
+            })
+    }
 }
@@ -21,6 +21,27 @@ impl JournalEffector {
         )
     }
 
+    /// Saves an entry of a tree that [`WasiEnv::remove_dir_all`] removed,
+    /// its path is the one on the file system so it is replayed relative
+    /// to the root
+    pub fn save_remove_dir_all_entry(
+        env: &WasiEnv,
+        path: &Path,
+        is_dir: bool,
+    ) -> anyhow::Result<()> {
+        let fd = VIRTUAL_ROOT_FD;
+        let path = Cow::Owned(path.to_string_lossy().into_owned());
+        let event = if is_dir {
+            JournalEntry::RemoveDirectoryV1 { fd, path }
+        } else {
+            JournalEntry::UnlinkFileV1 { fd, path }
+        };
+        env.active_journal()?
+            .write(event)
+            .map_err(map_snapshot_err)?;
+        Ok(())
+    }
+
     pub fn apply_path_remove_directory(
         ctx: &mut FunctionEnvMut<'_, WasiEnv>,
         fd: Fd,
@@ -59,6 +59,8 @@ impl JournalEffector {
                     read_timeout: None,
                     accept_timeout: None,
                     connect_timeout: None,
+                    device: None,
+                    last_error: None,
                     handler: None,
                 },
             }),
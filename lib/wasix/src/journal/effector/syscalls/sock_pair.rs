@@ -1,25 +1,27 @@
+use wasmer_wasix_types::wasi::Socktype;
+
 use super::*;
 
 impl JournalEffector {
     // Note: since the current implementation uses a pipe, we don't store the
-    // socket properties (domain, address family, etc.) in the journal.
-    // Once the sock_pair syscall is fixed, we should create a SocketPairV2
-    // entry that stores the socket properties as well. This ensures
-    // forward-compatibility when that change is implemented.
+    // socket properties (domain, address family, etc.) in the journal, only
+    // the type that decides whether the pipe keeps the datagram boundaries.
     pub fn save_sock_pair(
         ctx: &mut FunctionEnvMut<'_, WasiEnv>,
         fd1: Fd,
         fd2: Fd,
+        ty: Socktype,
     ) -> anyhow::Result<()> {
-        Self::save_event(ctx, JournalEntry::SocketPairV1 { fd1, fd2 })
+        Self::save_event(ctx, JournalEntry::SocketPairV2 { fd1, fd2, ty })
     }
 
     pub fn apply_sock_pair(
         ctx: &mut FunctionEnvMut<'_, WasiEnv>,
         fd1: Fd,
         fd2: Fd,
+        ty: Socktype,
     ) -> anyhow::Result<()> {
-        crate::syscalls::sock_pair_internal(ctx, Some(fd1), Some(fd2)).map_err(|err| {
+        crate::syscalls::sock_pair_internal(ctx, ty, Some(fd1), Some(fd2)).map_err(|err| {
             anyhow::format_err!("journal restore error: failed to create socket pair - {err}")
         })?;
         Ok(())
@@ -0,0 +1,37 @@
+use wasmer_wasix_types::wasi::Sockoption;
+
+use super::*;
+
+impl JournalEffector {
+    pub fn save_sock_set_opt_str(
+        ctx: &mut FunctionEnvMut<'_, WasiEnv>,
+        fd: Fd,
+        opt: Sockoption,
+        value: String,
+    ) -> anyhow::Result<()> {
+        Self::save_event(
+            ctx,
+            JournalEntry::SocketSetOptStrV1 {
+                fd,
+                opt,
+                value: value.into(),
+            },
+        )
+    }
+
+    pub fn apply_sock_set_opt_str(
+        ctx: &mut FunctionEnvMut<'_, WasiEnv>,
+        fd: Fd,
+        opt: Sockoption,
+        value: &str,
+    ) -> anyhow::Result<()> {
+        crate::syscalls::sock_set_opt_str_internal(ctx, fd, opt, value)
+            .map(|r| r.map_err(|err| err.to_string()))
+            .unwrap_or_else(|err| Err(err.to_string()))
+            .map_err(|err| {
+                anyhow::format_err!(
+                    "journal restore error: failed to set socket option (fd={fd}, opt={opt:?}, value={value}) - {err}")
+            })?;
+        Ok(())
+    }
+}
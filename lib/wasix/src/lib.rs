@@ -76,12 +76,14 @@ pub use wasmer;
 pub use wasmer_wasix_types;
 
 use wasmer::{
-    AsStoreMut, Exports, FunctionEnv, Imports, Memory32, MemoryAccessError, MemorySize,
-    RuntimeError, imports, namespace,
+    AsStoreMut, Exports, Extern, Function, FunctionEnv, Imports, Memory32, MemoryAccessError,
+    MemorySize, RuntimeError, Type, Value, imports, namespace,
 };
 
 pub use virtual_fs;
-pub use virtual_fs::{DuplexPipe, FsError, Pipe, VirtualFile, WasiBidirectionalSharedPipePair};
+pub use virtual_fs::{
+    DuplexPipe, FsError, Pipe, StreamBackedFile, VirtualFile, WasiBidirectionalSharedPipePair,
+};
 pub use virtual_net;
 pub use virtual_net::{UnsupportedVirtualNetworking, VirtualNetworking};
 
@@ -95,25 +97,28 @@ pub use wasmer_napi::{NapiCtx, NapiCtxBuilder, NapiLimits, NapiRuntimeHooks, mod
 use wasmer_wasix_types::wasi::{Errno, ExitCode};
 
 pub use crate::{
-    fs::{Fd, VIRTUAL_ROOT_FD, WasiFs, WasiInodes, default_fs_backing},
+    fs::{Fd, FsWatches, VIRTUAL_ROOT_FD, WasiFs, WasiInodes, default_fs_backing},
     os::{
         WasiTtyState,
+        system_info::SystemInfo,
         task::{
             control_plane::WasiControlPlane,
-            process::{WasiProcess, WasiProcessId},
+            process::{DeepSleepStats, ExitReason, ForkMode, WasiProcess, WasiProcessId},
             thread::{WasiThread, WasiThreadError, WasiThreadHandle, WasiThreadId},
         },
     },
     rewind::*,
     runtime::{PluggableRuntime, Runtime, task_manager::VirtualTaskManager},
     state::{
-        ALL_RIGHTS, WasiEnv, WasiEnvBuilder, WasiEnvInit, WasiFunctionEnv,
+        ALL_RIGHTS, AbsentStdin, StreamMode, WasiEnv, WasiEnvBuilder, WasiEnvInit, WasiFunctionEnv,
         WasiModuleInstanceHandles, WasiModuleTreeHandles, WasiStateCreationError,
     },
     syscalls::{journal::wait_for_snapshot, rewind, rewind_ext, types, unwind},
     utils::is_wasix_module,
     utils::{
-        WasiVersion, get_wasi_version, get_wasi_versions, is_wasi_module,
+        WasiVersion,
+        core_dump::{CORE_DUMP_VERSION, CoreDump, CoreDumpFrame, capture_core_dump},
+        get_wasi_version, get_wasi_versions, is_wasi_module,
         store::{StoreSnapshot, capture_store_snapshot, restore_store_snapshot},
     },
 };
@@ -175,6 +180,9 @@ pub enum SpawnError {
     /// the binary name was not found.
     #[error("could not find binary '{binary}'")]
     BinaryNotFound { binary: String },
+    /// The interpreter named by the `#!` line of a script was not found.
+    #[error("could not find the interpreter '{interpreter}' of the script '{script}'")]
+    InterpreterNotFound { script: String, interpreter: String },
     #[error("could not find an entrypoint in the package '{package_id}'")]
     MissingEntrypoint {
         package_id: wasmer_config::package::PackageId,
@@ -199,6 +207,17 @@ pub enum SpawnError {
     /// Memory access violation
     #[error("memory access violation")]
     MemoryAccessViolation,
+    /// The module imports a memory that the runtime can't create, or that
+    /// is bigger than it allows
+    #[error("the module imports a {requested} memory that the runtime can't provide: {error}")]
+    UnsatisfiableMemoryImport {
+        requested: wasmer_types::MemoryType,
+        error: wasmer::MemoryError,
+    },
+    /// The module imports functions (or other externs) that neither WASIX
+    /// nor the runtime provide
+    #[error("the module imports externs that are not provided: {imports:?}")]
+    MissingImports { imports: Vec<wasmer::MissingImport> },
     /// Some other unhandled error. If you see this, it's probably a bug.
     #[error("unknown error found")]
     UnknownError,
@@ -256,7 +275,10 @@ impl SpawnError {
     pub fn is_not_found(&self) -> bool {
         matches!(
             self,
-            Self::NotFound { .. } | Self::MissingEntrypoint { .. } | Self::BinaryNotFound { .. }
+            Self::NotFound { .. }
+                | Self::MissingEntrypoint { .. }
+                | Self::BinaryNotFound { .. }
+                | Self::InterpreterNotFound { .. }
         )
     }
 }
@@ -379,9 +401,52 @@ pub fn generate_import_object_from_env(
 
     imports.extend(&imports_wasi_generic);
 
+    apply_sandbox_policy(store, ctx, &mut imports);
+    runtime::syscall_log::apply_syscall_log(store, ctx, &mut imports);
+
     imports
 }
 
+/// Replaces every import that is denied by the [`SandboxPolicy`] of the
+/// environment with a stub that fails with [`Errno::Notcapable`] without
+/// ever entering the syscall.
+///
+/// [`SandboxPolicy`]: crate::capabilities::SandboxPolicy
+fn apply_sandbox_policy(
+    store: &mut impl AsStoreMut,
+    env: &FunctionEnv<WasiEnv>,
+    imports: &mut Imports,
+) {
+    let policy = env.as_ref(store).sandbox_policy().clone();
+    if policy.denied_syscalls.is_empty() {
+        return;
+    }
+
+    let denied = imports
+        .iter()
+        .filter(|(_, name, _)| !policy.is_syscall_allowed(name))
+        .filter_map(|(namespace, name, ext)| match ext {
+            Extern::Function(f) => Some((namespace.to_string(), name.to_string(), f.ty(store))),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+
+    for (namespace, name, ty) in denied {
+        let returns_errno = ty.results() == [Type::I32];
+        let syscall = name.clone();
+        let stub = Function::new(store, &ty, move |_| {
+            if returns_errno {
+                Ok(vec![Value::I32(Errno::Notcapable as i32)])
+            } else {
+                Err(RuntimeError::new(format!(
+                    "syscall `{syscall}` is denied by the sandbox policy"
+                )))
+            }
+        });
+        imports.define(&namespace, &name, stub);
+    }
+}
+
 fn wasi_exports_generic(mut store: &mut impl AsStoreMut, env: &FunctionEnv<WasiEnv>) -> Exports {
     use syscalls::*;
     let namespace = namespace! {
@@ -512,6 +577,7 @@ fn wasix_exports_32(mut store: &mut impl AsStoreMut, env: &FunctionEnv<WasiEnv>)
         "clock_res_get" => Function::new_typed_with_env(&mut store, env, clock_res_get::<Memory32>),
         "clock_time_get" => Function::new_typed_with_env(&mut store, env, clock_time_get::<Memory32>),
         "clock_time_set" => Function::new_typed_with_env(&mut store, env, clock_time_set),
+        "clock_nanosleep" => Function::new_typed_with_env(&mut store, env, clock_nanosleep::<Memory32>),
         "closure_prepare" => Function::new_typed_with_env(&mut store, env, closure_prepare::<Memory32>),
         "closure_allocate" => Function::new_typed_with_env(&mut store, env, closure_allocate::<Memory32>),
         "closure_free" => Function::new_typed_with_env(&mut store, env, closure_free),
@@ -539,6 +605,7 @@ fn wasix_exports_32(mut store: &mut impl AsStoreMut, env: &FunctionEnv<WasiEnv>)
         "fd_renumber" => Function::new_typed_with_env(&mut store, env, fd_renumber),
         "fd_dup" => Function::new_typed_with_env(&mut store, env, fd_dup::<Memory32>),
         "fd_dup2" => Function::new_typed_with_env(&mut store, env, fd_dup2::<Memory32>),
+        "copy_file_range" => Function::new_typed_with_env(&mut store, env, copy_file_range::<Memory32>),
         "fd_fdflags_get" => Function::new_typed_with_env(&mut store, env, fd_fdflags_get::<Memory32>),
         "fd_fdflags_set" => Function::new_typed_with_env(&mut store, env, fd_fdflags_set),
         "fd_event" => Function::new_typed_with_env(&mut store, env, fd_event::<Memory32>),
@@ -547,25 +614,42 @@ fn wasix_exports_32(mut store: &mut impl AsStoreMut, env: &FunctionEnv<WasiEnv>)
         "fd_tell" => Function::new_typed_with_env(&mut store, env, fd_tell::<Memory32>),
         "fd_write" => Function::new_typed_with_env(&mut store, env, fd_write::<Memory32>),
         "fd_pipe" => Function::new_typed_with_env(&mut store, env, fd_pipe::<Memory32>),
+        "fd_read_deadline" => Function::new_typed_with_env(&mut store, env, fd_read_deadline::<Memory32>),
+        "fd_signal" => Function::new_typed_with_env(&mut store, env, fd_signal::<Memory32>),
+        "fd_ioctl" => Function::new_typed_with_env(&mut store, env, fd_ioctl::<Memory32>),
+        "fd_lock" => Function::new_typed_with_env(&mut store, env, fd_lock),
+        "fd_unlock" => Function::new_typed_with_env(&mut store, env, fd_unlock),
+        "fd_mmap" => Function::new_typed_with_env(&mut store, env, fd_mmap::<Memory32>),
+        "fd_msync" => Function::new_typed_with_env(&mut store, env, fd_msync::<Memory32>),
+        "fd_munmap" => Function::new_typed_with_env(&mut store, env, fd_munmap::<Memory32>),
+        "fs_watch_add" => Function::new_typed_with_env(&mut store, env, fs_watch_add::<Memory32>),
         "path_create_directory" => Function::new_typed_with_env(&mut store, env, path_create_directory::<Memory32>),
         "path_filestat_get" => Function::new_typed_with_env(&mut store, env, path_filestat_get::<Memory32>),
         "path_filestat_set_times" => Function::new_typed_with_env(&mut store, env, path_filestat_set_times::<Memory32>),
         "path_link" => Function::new_typed_with_env(&mut store, env, path_link::<Memory32>),
         "path_open" => Function::new_typed_with_env(&mut store, env, path_open::<Memory32>),
         "path_open2" => Function::new_typed_with_env(&mut store, env, path_open2::<Memory32>),
+        "path_open_parent" => Function::new_typed_with_env(&mut store, env, path_open_parent::<Memory32>),
         "path_readlink" => Function::new_typed_with_env(&mut store, env, path_readlink::<Memory32>),
         "path_remove_directory" => Function::new_typed_with_env(&mut store, env, path_remove_directory::<Memory32>),
         "path_rename" => Function::new_typed_with_env(&mut store, env, path_rename::<Memory32>),
         "path_symlink" => Function::new_typed_with_env(&mut store, env, path_symlink::<Memory32>),
         "path_unlink_file" => Function::new_typed_with_env(&mut store, env, path_unlink_file::<Memory32>),
         "poll_oneoff" => Function::new_typed_with_env(&mut store, env, poll_oneoff::<Memory32>),
+        "poll_oneoff_deadline" => Function::new_typed_with_env(&mut store, env, poll_oneoff_deadline::<Memory32>),
         "proc_exit" => Function::new_typed_with_env(&mut store, env, proc_exit::<Memory32>),
         "proc_fork" => Function::new_typed_with_env(&mut store, env, proc_fork::<Memory32>),
         "proc_fork_env" => Function::new_typed_with_env(&mut store, env, proc_fork_env::<Memory32>),
         "proc_join" => Function::new_typed_with_env(&mut store, env, proc_join::<Memory32>),
+        "proc_wait_any" => Function::new_typed_with_env(&mut store, env, proc_wait_any::<Memory32>),
         "proc_signal" => Function::new_typed_with_env(&mut store, env, proc_signal),
         "proc_signals_get" => Function::new_typed_with_env(&mut store, env, proc_signals_get::<Memory32>),
         "proc_signals_sizes_get" => Function::new_typed_with_env(&mut store, env, proc_signals_sizes_get::<Memory32>),
+        "proc_sigpending" => Function::new_typed_with_env(&mut store, env, proc_sigpending::<Memory32>),
+        "proc_sigrestart" => Function::new_typed_with_env(&mut store, env, proc_sigrestart),
+        "proc_get_title" => Function::new_typed_with_env(&mut store, env, proc_get_title::<Memory32>),
+        "proc_set_title" => Function::new_typed_with_env(&mut store, env, proc_set_title::<Memory32>),
+        "proc_flush" => Function::new_typed_with_env(&mut store, env, proc_flush),
         "proc_exec" => Function::new_typed_with_env(&mut store, env, proc_exec::<Memory32>),
         "proc_exec2" => Function::new_typed_with_env(&mut store, env, proc_exec2::<Memory32>),
         "proc_exec3" => Function::new_typed_with_env(&mut store, env, proc_exec3::<Memory32>),
@@ -576,11 +660,17 @@ fn wasix_exports_32(mut store: &mut impl AsStoreMut, env: &FunctionEnv<WasiEnv>)
         "proc_spawn" => Function::new_typed_with_env(&mut store, env, proc_spawn::<Memory32>),
         "proc_spawn2" => Function::new_typed_with_env(&mut store, env, proc_spawn2::<Memory32>),
         "proc_id" => Function::new_typed_with_env(&mut store, env, proc_id::<Memory32>),
+        "proc_uptime" => Function::new_typed_with_env(&mut store, env, proc_uptime::<Memory32>),
+        "perf_counter_read" => Function::new_typed_with_env(&mut store, env, perf_counter_read::<Memory32>),
+        "perf_counter_frequency" => Function::new_typed_with_env(&mut store, env, perf_counter_frequency::<Memory32>),
         "proc_parent" => Function::new_typed_with_env(&mut store, env, proc_parent::<Memory32>),
+        "proc_getrlimit" => Function::new_typed_with_env(&mut store, env, proc_getrlimit::<Memory32>),
+        "proc_setrlimit" => Function::new_typed_with_env(&mut store, env, proc_setrlimit::<Memory32>),
         "random_get" => Function::new_typed_with_env(&mut store, env, random_get::<Memory32>),
         "tty_get" => Function::new_typed_with_env(&mut store, env, tty_get::<Memory32>),
         "tty_set" => Function::new_typed_with_env(&mut store, env, tty_set::<Memory32>),
         "getcwd" => Function::new_typed_with_env(&mut store, env, getcwd::<Memory32>),
+        "memfd_create" => Function::new_typed_with_env(&mut store, env, memfd_create::<Memory32>),
         "chdir" => Function::new_typed_with_env(&mut store, env, chdir::<Memory32>),
         "dl_invalid_handle" => Function::new_typed_with_env(&mut store, env, dl_invalid_handle),
         "dlopen" => Function::new_typed_with_env(&mut store, env, dlopen::<Memory32>),
@@ -597,6 +687,9 @@ fn wasix_exports_32(mut store: &mut impl AsStoreMut, env: &FunctionEnv<WasiEnv>)
         "sched_yield" => Function::new_typed_with_env(&mut store, env, sched_yield::<Memory32>),
         "stack_checkpoint" => Function::new_typed_with_env(&mut store, env, stack_checkpoint::<Memory32>),
         "stack_restore" => Function::new_typed_with_env(&mut store, env, stack_restore::<Memory32>),
+        "sysinfo" => Function::new_typed_with_env(&mut store, env, sysinfo::<Memory32>),
+        "gethostname" => Function::new_typed_with_env(&mut store, env, gethostname::<Memory32>),
+        "sethostname" => Function::new_typed_with_env(&mut store, env, sethostname::<Memory32>),
         "context_create" => Function::new_typed_with_env(&mut store, env, context_create::<Memory32>),
         "context_switch" => if engine_supports_async { Function::new_typed_with_env_async(&mut store, env, context_switch) } else { Function::new_typed_with_env(&mut store, env, context_switch_not_supported) },
         "context_destroy" => Function::new_typed_with_env(&mut store, env, context_destroy),
@@ -616,7 +709,9 @@ fn wasix_exports_32(mut store: &mut impl AsStoreMut, env: &FunctionEnv<WasiEnv>)
         "port_route_remove" => Function::new_typed_with_env(&mut store, env, port_route_remove::<Memory32>),
         "port_route_clear" => Function::new_typed_with_env(&mut store, env, port_route_clear),
         "port_route_list" => Function::new_typed_with_env(&mut store, env, port_route_list::<Memory32>),
+        "net_interfaces" => Function::new_typed_with_env(&mut store, env, net_interfaces::<Memory32>),
         "sock_status" => Function::new_typed_with_env(&mut store, env, sock_status::<Memory32>),
+        "sock_stats" => Function::new_typed_with_env(&mut store, env, sock_stats::<Memory32>),
         "sock_addr_local" => Function::new_typed_with_env(&mut store, env, sock_addr_local::<Memory32>),
         "sock_addr_peer" => Function::new_typed_with_env(&mut store, env, sock_addr_peer::<Memory32>),
         "sock_open" => Function::new_typed_with_env(&mut store, env, sock_open::<Memory32>),
@@ -627,6 +722,7 @@ fn wasix_exports_32(mut store: &mut impl AsStoreMut, env: &FunctionEnv<WasiEnv>)
         "sock_get_opt_time" => Function::new_typed_with_env(&mut store, env, sock_get_opt_time::<Memory32>),
         "sock_set_opt_size" => Function::new_typed_with_env(&mut store, env, sock_set_opt_size),
         "sock_get_opt_size" => Function::new_typed_with_env(&mut store, env, sock_get_opt_size::<Memory32>),
+        "sock_set_opt_str" => Function::new_typed_with_env(&mut store, env, sock_set_opt_str::<Memory32>),
         "sock_join_multicast_v4" => Function::new_typed_with_env(&mut store, env, sock_join_multicast_v4::<Memory32>),
         "sock_leave_multicast_v4" => Function::new_typed_with_env(&mut store, env, sock_leave_multicast_v4::<Memory32>),
         "sock_join_multicast_v6" => Function::new_typed_with_env(&mut store, env, sock_join_multicast_v6::<Memory32>),
@@ -638,9 +734,12 @@ fn wasix_exports_32(mut store: &mut impl AsStoreMut, env: &FunctionEnv<WasiEnv>)
         "sock_connect" => Function::new_typed_with_env(&mut store, env, sock_connect::<Memory32>),
         "sock_recv" => Function::new_typed_with_env(&mut store, env, sock_recv::<Memory32>),
         "sock_recv_from" => Function::new_typed_with_env(&mut store, env, sock_recv_from::<Memory32>),
+        "sock_recv_from_ttl" => Function::new_typed_with_env(&mut store, env, sock_recv_from_ttl::<Memory32>),
+        "sock_recv_fds" => Function::new_typed_with_env(&mut store, env, sock_recv_fds::<Memory32>),
         "sock_send" => Function::new_typed_with_env(&mut store, env, sock_send::<Memory32>),
         "sock_send_to" => Function::new_typed_with_env(&mut store, env, sock_send_to::<Memory32>),
         "sock_send_file" => Function::new_typed_with_env(&mut store, env, sock_send_file::<Memory32>),
+        "sock_send_fds" => Function::new_typed_with_env(&mut store, env, sock_send_fds::<Memory32>),
         "sock_shutdown" => Function::new_typed_with_env(&mut store, env, sock_shutdown),
         "resolve" => Function::new_typed_with_env(&mut store, env, resolve::<Memory32>),
     };
@@ -659,6 +758,7 @@ fn wasix_exports_64(mut store: &mut impl AsStoreMut, env: &FunctionEnv<WasiEnv>)
         "clock_res_get" => Function::new_typed_with_env(&mut store, env, clock_res_get::<Memory64>),
         "clock_time_get" => Function::new_typed_with_env(&mut store, env, clock_time_get::<Memory64>),
         "clock_time_set" => Function::new_typed_with_env(&mut store, env, clock_time_set),
+        "clock_nanosleep" => Function::new_typed_with_env(&mut store, env, clock_nanosleep::<Memory64>),
         "closure_prepare" => Function::new_typed_with_env(&mut store, env, closure_prepare::<Memory64>),
         "closure_allocate" => Function::new_typed_with_env(&mut store, env, closure_allocate::<Memory64>),
         "closure_free" => Function::new_typed_with_env(&mut store, env, closure_free),
@@ -686,6 +786,7 @@ fn wasix_exports_64(mut store: &mut impl AsStoreMut, env: &FunctionEnv<WasiEnv>)
         "fd_renumber" => Function::new_typed_with_env(&mut store, env, fd_renumber),
         "fd_dup" => Function::new_typed_with_env(&mut store, env, fd_dup::<Memory64>),
         "fd_dup2" => Function::new_typed_with_env(&mut store, env, fd_dup2::<Memory64>),
+        "copy_file_range" => Function::new_typed_with_env(&mut store, env, copy_file_range::<Memory64>),
         "fd_fdflags_get" => Function::new_typed_with_env(&mut store, env, fd_fdflags_get::<Memory64>),
         "fd_fdflags_set" => Function::new_typed_with_env(&mut store, env, fd_fdflags_set),
         "fd_event" => Function::new_typed_with_env(&mut store, env, fd_event::<Memory64>),
@@ -694,25 +795,42 @@ fn wasix_exports_64(mut store: &mut impl AsStoreMut, env: &FunctionEnv<WasiEnv>)
         "fd_tell" => Function::new_typed_with_env(&mut store, env, fd_tell::<Memory64>),
         "fd_write" => Function::new_typed_with_env(&mut store, env, fd_write::<Memory64>),
         "fd_pipe" => Function::new_typed_with_env(&mut store, env, fd_pipe::<Memory64>),
+        "fd_read_deadline" => Function::new_typed_with_env(&mut store, env, fd_read_deadline::<Memory64>),
+        "fd_signal" => Function::new_typed_with_env(&mut store, env, fd_signal::<Memory64>),
+        "fd_ioctl" => Function::new_typed_with_env(&mut store, env, fd_ioctl::<Memory64>),
+        "fd_lock" => Function::new_typed_with_env(&mut store, env, fd_lock),
+        "fd_unlock" => Function::new_typed_with_env(&mut store, env, fd_unlock),
+        "fd_mmap" => Function::new_typed_with_env(&mut store, env, fd_mmap::<Memory64>),
+        "fd_msync" => Function::new_typed_with_env(&mut store, env, fd_msync::<Memory64>),
+        "fd_munmap" => Function::new_typed_with_env(&mut store, env, fd_munmap::<Memory64>),
+        "fs_watch_add" => Function::new_typed_with_env(&mut store, env, fs_watch_add::<Memory64>),
         "path_create_directory" => Function::new_typed_with_env(&mut store, env, path_create_directory::<Memory64>),
         "path_filestat_get" => Function::new_typed_with_env(&mut store, env, path_filestat_get::<Memory64>),
         "path_filestat_set_times" => Function::new_typed_with_env(&mut store, env, path_filestat_set_times::<Memory64>),
         "path_link" => Function::new_typed_with_env(&mut store, env, path_link::<Memory64>),
         "path_open" => Function::new_typed_with_env(&mut store, env, path_open::<Memory64>),
         "path_open2" => Function::new_typed_with_env(&mut store, env, path_open2::<Memory64>),
+        "path_open_parent" => Function::new_typed_with_env(&mut store, env, path_open_parent::<Memory64>),
         "path_readlink" => Function::new_typed_with_env(&mut store, env, path_readlink::<Memory64>),
         "path_remove_directory" => Function::new_typed_with_env(&mut store, env, path_remove_directory::<Memory64>),
         "path_rename" => Function::new_typed_with_env(&mut store, env, path_rename::<Memory64>),
         "path_symlink" => Function::new_typed_with_env(&mut store, env, path_symlink::<Memory64>),
         "path_unlink_file" => Function::new_typed_with_env(&mut store, env, path_unlink_file::<Memory64>),
         "poll_oneoff" => Function::new_typed_with_env(&mut store, env, poll_oneoff::<Memory64>),
+        "poll_oneoff_deadline" => Function::new_typed_with_env(&mut store, env, poll_oneoff_deadline::<Memory64>),
         "proc_exit" => Function::new_typed_with_env(&mut store, env, proc_exit::<Memory64>),
         "proc_fork" => Function::new_typed_with_env(&mut store, env, proc_fork::<Memory64>),
         "proc_fork_env" => Function::new_typed_with_env(&mut store, env, proc_fork_env::<Memory64>),
         "proc_join" => Function::new_typed_with_env(&mut store, env, proc_join::<Memory64>),
+        "proc_wait_any" => Function::new_typed_with_env(&mut store, env, proc_wait_any::<Memory64>),
         "proc_signal" => Function::new_typed_with_env(&mut store, env, proc_signal),
         "proc_signals_get" => Function::new_typed_with_env(&mut store, env, proc_signals_get::<Memory64>),
         "proc_signals_sizes_get" => Function::new_typed_with_env(&mut store, env, proc_signals_sizes_get::<Memory64>),
+        "proc_sigpending" => Function::new_typed_with_env(&mut store, env, proc_sigpending::<Memory64>),
+        "proc_sigrestart" => Function::new_typed_with_env(&mut store, env, proc_sigrestart),
+        "proc_get_title" => Function::new_typed_with_env(&mut store, env, proc_get_title::<Memory64>),
+        "proc_set_title" => Function::new_typed_with_env(&mut store, env, proc_set_title::<Memory64>),
+        "proc_flush" => Function::new_typed_with_env(&mut store, env, proc_flush),
         "proc_exec" => Function::new_typed_with_env(&mut store, env, proc_exec::<Memory64>),
         "proc_exec2" => Function::new_typed_with_env(&mut store, env, proc_exec2::<Memory64>),
         "proc_exec3" => Function::new_typed_with_env(&mut store, env, proc_exec3::<Memory64>),
@@ -723,11 +841,17 @@ fn wasix_exports_64(mut store: &mut impl AsStoreMut, env: &FunctionEnv<WasiEnv>)
         "proc_spawn" => Function::new_typed_with_env(&mut store, env, proc_spawn::<Memory64>),
         "proc_spawn2" => Function::new_typed_with_env(&mut store, env, proc_spawn2::<Memory64>),
         "proc_id" => Function::new_typed_with_env(&mut store, env, proc_id::<Memory64>),
+        "proc_uptime" => Function::new_typed_with_env(&mut store, env, proc_uptime::<Memory64>),
+        "perf_counter_read" => Function::new_typed_with_env(&mut store, env, perf_counter_read::<Memory64>),
+        "perf_counter_frequency" => Function::new_typed_with_env(&mut store, env, perf_counter_frequency::<Memory64>),
         "proc_parent" => Function::new_typed_with_env(&mut store, env, proc_parent::<Memory64>),
+        "proc_getrlimit" => Function::new_typed_with_env(&mut store, env, proc_getrlimit::<Memory64>),
+        "proc_setrlimit" => Function::new_typed_with_env(&mut store, env, proc_setrlimit::<Memory64>),
         "random_get" => Function::new_typed_with_env(&mut store, env, random_get::<Memory64>),
         "tty_get" => Function::new_typed_with_env(&mut store, env, tty_get::<Memory64>),
         "tty_set" => Function::new_typed_with_env(&mut store, env, tty_set::<Memory64>),
         "getcwd" => Function::new_typed_with_env(&mut store, env, getcwd::<Memory64>),
+        "memfd_create" => Function::new_typed_with_env(&mut store, env, memfd_create::<Memory64>),
         "chdir" => Function::new_typed_with_env(&mut store, env, chdir::<Memory64>),
         "dl_invalid_handle" => Function::new_typed_with_env(&mut store, env, dl_invalid_handle),
         "dlopen" => Function::new_typed_with_env(&mut store, env, dlopen::<Memory64>),
@@ -744,6 +868,9 @@ fn wasix_exports_64(mut store: &mut impl AsStoreMut, env: &FunctionEnv<WasiEnv>)
         "sched_yield" => Function::new_typed_with_env(&mut store, env, sched_yield::<Memory64>),
         "stack_checkpoint" => Function::new_typed_with_env(&mut store, env, stack_checkpoint::<Memory64>),
         "stack_restore" => Function::new_typed_with_env(&mut store, env, stack_restore::<Memory64>),
+        "sysinfo" => Function::new_typed_with_env(&mut store, env, sysinfo::<Memory64>),
+        "gethostname" => Function::new_typed_with_env(&mut store, env, gethostname::<Memory64>),
+        "sethostname" => Function::new_typed_with_env(&mut store, env, sethostname::<Memory64>),
         "context_create" => Function::new_typed_with_env(&mut store, env, context_create::<Memory64>),
         "context_switch" => if engine_supports_async { Function::new_typed_with_env_async(&mut store, env, context_switch) } else { Function::new_typed_with_env(&mut store, env, context_switch_not_supported) },
         "context_destroy" => Function::new_typed_with_env(&mut store, env, context_destroy),
@@ -763,7 +890,9 @@ fn wasix_exports_64(mut store: &mut impl AsStoreMut, env: &FunctionEnv<WasiEnv>)
         "port_route_remove" => Function::new_typed_with_env(&mut store, env, port_route_remove::<Memory64>),
         "port_route_clear" => Function::new_typed_with_env(&mut store, env, port_route_clear),
         "port_route_list" => Function::new_typed_with_env(&mut store, env, port_route_list::<Memory64>),
+        "net_interfaces" => Function::new_typed_with_env(&mut store, env, net_interfaces::<Memory64>),
         "sock_status" => Function::new_typed_with_env(&mut store, env, sock_status::<Memory64>),
+        "sock_stats" => Function::new_typed_with_env(&mut store, env, sock_stats::<Memory64>),
         "sock_addr_local" => Function::new_typed_with_env(&mut store, env, sock_addr_local::<Memory64>),
         "sock_addr_peer" => Function::new_typed_with_env(&mut store, env, sock_addr_peer::<Memory64>),
         "sock_open" => Function::new_typed_with_env(&mut store, env, sock_open::<Memory64>),
@@ -774,6 +903,7 @@ fn wasix_exports_64(mut store: &mut impl AsStoreMut, env: &FunctionEnv<WasiEnv>)
         "sock_get_opt_time" => Function::new_typed_with_env(&mut store, env, sock_get_opt_time::<Memory64>),
         "sock_set_opt_size" => Function::new_typed_with_env(&mut store, env, sock_set_opt_size),
         "sock_get_opt_size" => Function::new_typed_with_env(&mut store, env, sock_get_opt_size::<Memory64>),
+        "sock_set_opt_str" => Function::new_typed_with_env(&mut store, env, sock_set_opt_str::<Memory64>),
         "sock_join_multicast_v4" => Function::new_typed_with_env(&mut store, env, sock_join_multicast_v4::<Memory64>),
         "sock_leave_multicast_v4" => Function::new_typed_with_env(&mut store, env, sock_leave_multicast_v4::<Memory64>),
         "sock_join_multicast_v6" => Function::new_typed_with_env(&mut store, env, sock_join_multicast_v6::<Memory64>),
@@ -785,9 +915,12 @@ fn wasix_exports_64(mut store: &mut impl AsStoreMut, env: &FunctionEnv<WasiEnv>)
         "sock_connect" => Function::new_typed_with_env(&mut store, env, sock_connect::<Memory64>),
         "sock_recv" => Function::new_typed_with_env(&mut store, env, sock_recv::<Memory64>),
         "sock_recv_from" => Function::new_typed_with_env(&mut store, env, sock_recv_from::<Memory64>),
+        "sock_recv_from_ttl" => Function::new_typed_with_env(&mut store, env, sock_recv_from_ttl::<Memory64>),
+        "sock_recv_fds" => Function::new_typed_with_env(&mut store, env, sock_recv_fds::<Memory64>),
         "sock_send" => Function::new_typed_with_env(&mut store, env, sock_send::<Memory64>),
         "sock_send_to" => Function::new_typed_with_env(&mut store, env, sock_send_to::<Memory64>),
         "sock_send_file" => Function::new_typed_with_env(&mut store, env, sock_send_file::<Memory64>),
+        "sock_send_fds" => Function::new_typed_with_env(&mut store, env, sock_send_fds::<Memory64>),
         "sock_shutdown" => Function::new_typed_with_env(&mut store, env, sock_shutdown),
         "resolve" => Function::new_typed_with_env(&mut store, env, resolve::<Memory64>),
     };
@@ -817,6 +950,9 @@ fn import_object_for_all_wasi_versions(
         "wasix_64v1" => exports_wasix_64v1,
     };
 
+    apply_sandbox_policy(store, env, &mut imports);
+    runtime::syscall_log::apply_syscall_log(store, env, &mut imports);
+
     imports
 }
 
@@ -49,6 +49,21 @@ macro_rules! wasi_try_ok_ok {
 }
 
 /// Like `wasi_try` but converts a `MemoryAccessError` to a `wasi::Errno`.
+/// Processes the pending operations at the entry of a syscall (see
+/// `WasiEnv::do_pending_operations_or_sleep`), returns from the syscall right
+/// away when the thread deep sleeps before the syscall is processed
+macro_rules! wasi_pending_operations_or_sleep {
+    ($ctx:expr) => {{
+        match wasi_try_ok!($crate::WasiEnv::do_pending_operations_or_sleep($ctx)?) {
+            $crate::state::PendingOperations::Proceed => {}
+            // The errno is ignored as the stack unwinds
+            $crate::state::PendingOperations::Unwound => {
+                return Ok(crate::syscalls::types::wasi::Errno::Success);
+            }
+        }
+    }};
+}
+
 macro_rules! wasi_try_mem {
     ($expr:expr) => {{ wasi_try!($expr.map_err($crate::mem_error_to_wasi)) }};
 }
@@ -4,17 +4,21 @@ use std::{
     mem::MaybeUninit,
     net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
     pin::Pin,
-    sync::{Arc, RwLock, RwLockWriteGuard},
+    sync::{
+        Arc, RwLock, RwLockWriteGuard,
+        atomic::{AtomicU64, Ordering},
+    },
     task::{Context, Poll},
     time::Duration,
 };
 
 #[cfg(feature = "enable-serde")]
 use serde_derive::{Deserialize, Serialize};
-use virtual_mio::InterestHandler;
+use virtual_mio::{InterestHandler, InterestType};
 use virtual_net::{
-    NetworkError, VirtualIcmpSocket, VirtualNetworking, VirtualRawSocket, VirtualTcpListener,
-    VirtualTcpSocket, VirtualUdpSocket, net_error_into_io_err,
+    DynVirtualNetworking, NetworkError, VirtualIcmpSocket, VirtualNetworking, VirtualRawSocket,
+    VirtualTcpBinding, VirtualTcpListener, VirtualTcpSocket, VirtualUdpSocket,
+    net_error_into_io_err,
 };
 use wasmer_types::MemorySize;
 use wasmer_wasix_types::wasi::{Addressfamily, Errno, Rights, SockProto, Sockoption, Socktype};
@@ -49,6 +53,11 @@ pub struct SocketProperties {
     pub read_timeout: Option<Duration>,
     pub accept_timeout: Option<Duration>,
     pub connect_timeout: Option<Duration>,
+    /// Network interface that the socket is bound to (`SO_BINDTODEVICE`)
+    pub device: Option<String>,
+    /// Error of a non-blocking connect that failed, reported through
+    /// `SO_ERROR` (and cleared by reading it)
+    pub last_error: Option<Errno>,
     pub handler: Option<Box<dyn InterestHandler + Send + Sync>>,
 }
 
@@ -58,6 +67,9 @@ pub enum InodeSocketKind {
     PreSocket {
         props: SocketProperties,
         addr: Option<SocketAddr>,
+        /// Holds on to the ephemeral port of a stream socket that was bound
+        /// to port 0 until it listens (or connects)
+        binding: Option<Box<dyn VirtualTcpBinding + Sync>>,
     },
     Icmp(Box<dyn VirtualIcmpSocket + Sync>),
     Raw(Box<dyn VirtualRawSocket + Sync>),
@@ -73,6 +85,10 @@ pub enum InodeSocketKind {
     UdpSocket {
         socket: Box<dyn VirtualUdpSocket + Sync>,
         peer: Option<SocketAddr>,
+        write_timeout: Option<Duration>,
+        read_timeout: Option<Duration>,
+        /// Multicast groups that the socket joined
+        multicast_groups: Vec<MulticastGroup>,
     },
     RemoteSocket {
         props: SocketProperties,
@@ -84,6 +100,19 @@ pub enum InodeSocketKind {
     },
 }
 
+/// A multicast group together with the interface it was joined on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MulticastGroup {
+    V4 {
+        multiaddr: Ipv4Addr,
+        iface: Ipv4Addr,
+    },
+    V6 {
+        multiaddr: Ipv6Addr,
+        iface: u32,
+    },
+}
+
 pub enum WasiSocketOption {
     Noop,
     ReusePort,
@@ -112,6 +141,7 @@ pub enum WasiSocketOption {
     MulticastTtlV4,
     Type,
     Proto,
+    RecvTtl,
 }
 
 impl TryFrom<Sockoption> for WasiSocketOption {
@@ -147,6 +177,7 @@ impl TryFrom<Sockoption> for WasiSocketOption {
             Sockoption::MulticastTtlV4 => Ok(MulticastTtlV4),
             Sockoption::Type => Ok(Type),
             Sockoption::Proto => Ok(Proto),
+            Sockoption::RecvTtl => Ok(RecvTtl),
             _ => Err(Errno::Inval),
         }
     }
@@ -160,6 +191,25 @@ pub enum WasiSocketStatus {
     Failed,
 }
 
+/// Statistics of a socket, see [`InodeSocket::stats`]
+#[derive(Debug)]
+pub struct WasiSocketStats {
+    /// Number of bytes that were sent through the socket
+    pub bytes_sent: u64,
+    /// Number of bytes that were received from the socket
+    pub bytes_received: u64,
+    /// Number of bytes that were written but not sent to the peer yet
+    pub send_queued: Option<usize>,
+    /// Number of bytes that were received but not read yet
+    pub recv_queued: Option<usize>,
+    /// Number of connections that wait to be accepted (for listeners)
+    pub accept_queued: Option<usize>,
+    pub status: WasiSocketStatus,
+}
+
+/// The largest datagram that a socket can receive
+pub(crate) const MAX_DATAGRAM_SIZE: usize = u16::MAX as usize;
+
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 #[cfg_attr(feature = "enable-serde", derive(Serialize, Deserialize))]
 pub enum TimeType {
@@ -208,6 +258,10 @@ pub(crate) struct InodeSocketProtected {
 //#[cfg_attr(feature = "enable-serde", derive(Serialize, Deserialize))]
 pub(crate) struct InodeSocketInner {
     pub protected: RwLock<InodeSocketProtected>,
+    /// Number of bytes that were sent through the socket
+    pub bytes_sent: AtomicU64,
+    /// Number of bytes that were received from the socket
+    pub bytes_received: AtomicU64,
 }
 
 #[derive(Debug, Clone)]
@@ -222,6 +276,8 @@ impl InodeSocket {
         Self {
             inner: Arc::new(InodeSocketInner {
                 protected: RwLock::new(protected),
+                bytes_sent: AtomicU64::new(0),
+                bytes_received: AtomicU64::new(0),
             }),
         }
     }
@@ -277,7 +333,57 @@ impl InodeSocket {
             .flatten()
             .unwrap_or(Duration::from_secs(30));
         let inner = self.inner.protected.write().unwrap();
-        Self::bind_internal(tasks, net, set_addr, timeout, inner).await
+        let ret = Self::bind_internal(tasks, net, set_addr, timeout, inner).await?;
+        if set_addr.port() == 0 {
+            self.reserve_ephemeral_port(tasks, net, timeout).await?;
+        }
+        Ok(ret)
+    }
+
+    /// Assigns an ephemeral port to a stream socket that was bound to port 0,
+    /// so that it can be queried before the socket listens. The port is held
+    /// by a socket that is bound but doesn't listen yet, which then becomes
+    /// the listener on `listen` or the connection on `connect`. Networks that
+    /// can't bind without listening leave the port unassigned until then.
+    async fn reserve_ephemeral_port(
+        &self,
+        tasks: &dyn VirtualTaskManager,
+        net: &dyn VirtualNetworking,
+        timeout: Duration,
+    ) -> Result<(), Errno> {
+        let device_net = device_net(net, &self.inner.protected.read().unwrap().kind)?;
+        let net = device_net.as_deref().map_or(net, |net| net);
+        let binding = {
+            let inner = self.inner.protected.read().unwrap();
+            match &inner.kind {
+                InodeSocketKind::PreSocket {
+                    props,
+                    addr: Some(addr),
+                    ..
+                } if props.ty == Socktype::Stream => {
+                    net.bind_tcp(*addr, props.only_v6, props.reuse_port, props.reuse_addr)
+                }
+                _ => return Ok(()),
+            }
+        };
+
+        let binding = tokio::select! {
+            binding = binding => match binding {
+                Ok(binding) => binding,
+                Err(NetworkError::Unsupported) => return Ok(()),
+                Err(err) => return Err(net_error_into_wasi_err(err)),
+            },
+            _ = tasks.sleep_now(timeout) => return Err(Errno::Timedout)
+        };
+
+        let mut inner = self.inner.protected.write().unwrap();
+        if let InodeSocketKind::PreSocket {
+            binding: reserved, ..
+        } = &mut inner.kind
+        {
+            reserved.replace(binding);
+        }
+        Ok(())
     }
 
     // The lock is dropped before awaiting, but clippy doesn't realize it
@@ -289,9 +395,15 @@ impl InodeSocket {
         timeout: Duration,
         mut inner: RwLockWriteGuard<'_, InodeSocketProtected>,
     ) -> Result<Option<InodeSocket>, Errno> {
-        let socket = {
+        let device_net = device_net(net, &inner.kind)?;
+        let net = device_net.as_deref().map_or(net, |net| net);
+        let (socket, write_timeout, read_timeout) = {
             match &mut inner.kind {
-                InodeSocketKind::PreSocket { props, addr, .. } => {
+                InodeSocketKind::PreSocket {
+                    props,
+                    addr,
+                    binding,
+                } => {
                     match props.family {
                         Addressfamily::Inet4 => {
                             if !set_addr.is_ipv4() {
@@ -314,6 +426,7 @@ impl InodeSocket {
                         }
                     }
 
+                    binding.take();
                     addr.replace(set_addr);
                     let addr = (*addr).unwrap();
 
@@ -327,7 +440,11 @@ impl InodeSocket {
                             let reuse_port = props.reuse_port;
                             let reuse_addr = props.reuse_addr;
 
-                            net.bind_udp(addr, reuse_port, reuse_addr)
+                            (
+                                net.bind_udp(addr, reuse_port, reuse_addr),
+                                props.write_timeout,
+                                props.read_timeout,
+                            )
                         }
                         _ => return Err(Errno::Inval),
                     }
@@ -372,7 +489,11 @@ impl InodeSocket {
                             let reuse_port = props.reuse_port;
                             let reuse_addr = props.reuse_addr;
 
-                            net.bind_udp(addr, reuse_port, reuse_addr)
+                            (
+                                net.bind_udp(addr, reuse_port, reuse_addr),
+                                props.write_timeout,
+                                props.read_timeout,
+                            )
                         }
                         _ => return Err(Errno::Inval),
                     }
@@ -386,7 +507,13 @@ impl InodeSocket {
         tokio::select! {
             socket = socket => {
                 let socket = socket.map_err(net_error_into_wasi_err)?;
-                Ok(Some(InodeSocket::new(InodeSocketKind::UdpSocket { socket, peer: None })))
+                Ok(Some(InodeSocket::new(InodeSocketKind::UdpSocket {
+                    socket,
+                    peer: None,
+                    write_timeout,
+                    read_timeout,
+                    multicast_groups: Vec::new(),
+                })))
             },
             _ = tasks.sleep_now(timeout) => Err(Errno::Timedout)
         }
@@ -396,7 +523,7 @@ impl InodeSocket {
         &self,
         tasks: &dyn VirtualTaskManager,
         net: &dyn VirtualNetworking,
-        _backlog: usize,
+        backlog: usize,
     ) -> Result<Option<InodeSocket>, Errno> {
         let timeout = self
             .opt_time(TimeType::AcceptTimeout)
@@ -404,11 +531,26 @@ impl InodeSocket {
             .flatten()
             .unwrap_or(Duration::from_secs(30));
 
+        let device_net = device_net(net, &self.inner.protected.read().unwrap().kind)?;
+        let net = device_net.as_deref().map_or(net, |net| net);
         let socket = {
-            let inner = self.inner.protected.read().unwrap();
-            match &inner.kind {
-                InodeSocketKind::PreSocket { props, addr, .. } => match props.ty {
+            let mut inner = self.inner.protected.write().unwrap();
+            match &mut inner.kind {
+                InodeSocketKind::PreSocket {
+                    props,
+                    addr,
+                    binding,
+                } => match props.ty {
                     Socktype::Stream => {
+                        // The socket is already bound to its ephemeral port
+                        if let Some(binding) = binding.take() {
+                            let socket =
+                                binding.listen(backlog).map_err(net_error_into_wasi_err)?;
+                            return Ok(Some(InodeSocket::new(InodeSocketKind::TcpListener {
+                                socket,
+                                accept_timeout: Some(timeout),
+                            })));
+                        }
                         if addr.is_none() {
                             tracing::warn!("wasi[?]::sock_listen - failed - address not set");
                             return Err(Errno::Inval);
@@ -571,6 +713,18 @@ impl InodeSocket {
         Ok(())
     }
 
+    /// Tries to push out any data that is still sitting in the local buffers
+    /// of the socket, this does not wait for it to be sent.
+    pub fn try_flush(&self) -> Result<(), Errno> {
+        let mut inner = self.inner.protected.write().unwrap();
+        match &mut inner.kind {
+            InodeSocketKind::TcpStream { socket, .. } => socket.try_flush(),
+            InodeSocketKind::Raw(socket) => socket.try_flush(),
+            _ => Ok(()),
+        }
+        .map_err(net_error_into_wasi_err)
+    }
+
     pub async fn connect(
         &mut self,
         tasks: &dyn VirtualTaskManager,
@@ -584,16 +738,25 @@ impl InodeSocket {
 
         let timeout = timeout.unwrap_or(Duration::from_secs(30));
 
+        let device_net = device_net(net, &self.inner.protected.read().unwrap().kind)?;
+        let net = device_net.as_deref().map_or(net, |net| net);
         let handler;
         let connect = {
             let mut inner = self.inner.protected.write().unwrap();
             match &mut inner.kind {
-                InodeSocketKind::PreSocket { props, addr, .. } => {
+                InodeSocketKind::PreSocket {
+                    props,
+                    addr,
+                    binding,
+                } => {
                     handler = props.handler.take();
                     new_write_timeout = props.write_timeout;
                     new_read_timeout = props.read_timeout;
                     match props.ty {
                         Socktype::Stream => {
+                            // The connection keeps the ephemeral port that
+                            // the socket is bound to
+                            let binding = binding.take();
                             let no_delay = props.no_delay;
                             let keep_alive = props.keep_alive;
                             let dont_route = props.dont_route;
@@ -608,7 +771,10 @@ impl InodeSocket {
                                 }
                             };
                             Box::pin(async move {
-                                let mut ret = net.connect_tcp(addr, peer).await?;
+                                let mut ret = match binding {
+                                    Some(binding) => binding.connect(peer).await?,
+                                    None => net.connect_tcp(addr, peer).await?,
+                                };
                                 if let Some(no_delay) = no_delay {
                                     ret.set_nodelay(no_delay).ok();
                                 }
@@ -642,9 +808,27 @@ impl InodeSocket {
             }
         };
 
-        let mut socket = tokio::select! {
-            res = connect => res.map_err(net_error_into_wasi_err)?,
-            _ = tasks.sleep_now(timeout) => return Err(Errno::Timedout)
+        let res = tokio::select! {
+            res = connect => res.map_err(net_error_into_wasi_err),
+            _ = tasks.sleep_now(timeout) => Err(Errno::Timedout)
+        };
+        let mut socket = match res {
+            Ok(socket) => socket,
+            // A non-blocking connect that fails is reported as still in
+            // progress, the socket then becomes ready and the guest reads
+            // the actual error through `SO_ERROR`
+            Err(err) if nonblocking => {
+                let mut inner = self.inner.protected.write().unwrap();
+                if let InodeSocketKind::PreSocket { props, .. } = &mut inner.kind {
+                    props.last_error = Some(err);
+                    props.handler = handler;
+                    if let Some(handler) = props.handler.as_mut() {
+                        handler.push_interest(InterestType::Writable);
+                    }
+                }
+                return Err(Errno::Inprogress);
+            }
+            Err(err) => return Err(err),
         };
 
         if let Some(handler) = handler {
@@ -665,7 +849,10 @@ impl InodeSocket {
     pub fn status(&self) -> Result<WasiSocketStatus, Errno> {
         let inner = self.inner.protected.read().unwrap();
         Ok(match &inner.kind {
-            InodeSocketKind::PreSocket { .. } => WasiSocketStatus::Opening,
+            InodeSocketKind::PreSocket { props, .. } => match props.last_error {
+                Some(_) => WasiSocketStatus::Failed,
+                None => WasiSocketStatus::Opening,
+            },
             InodeSocketKind::TcpListener { .. } => WasiSocketStatus::Opened,
             InodeSocketKind::TcpStream { .. } => WasiSocketStatus::Opened,
             InodeSocketKind::UdpSocket { .. } => WasiSocketStatus::Opened,
@@ -677,11 +864,62 @@ impl InodeSocket {
         })
     }
 
+    /// Returns the statistics of the socket, the queues are only known when
+    /// the networking of the socket can tell
+    pub fn stats(&self) -> Result<WasiSocketStats, Errno> {
+        let status = self.status()?;
+        let inner = self.inner.protected.read().unwrap();
+        let (send_queued, recv_queued, accept_queued) = match &inner.kind {
+            InodeSocketKind::TcpStream { socket, .. } => {
+                (socket.send_queued().ok(), socket.recv_queued().ok(), None)
+            }
+            InodeSocketKind::UdpSocket { socket, .. } => {
+                (socket.send_queued().ok(), socket.recv_queued().ok(), None)
+            }
+            InodeSocketKind::Raw(socket) => {
+                (socket.send_queued().ok(), socket.recv_queued().ok(), None)
+            }
+            InodeSocketKind::Icmp(socket) => {
+                (socket.send_queued().ok(), socket.recv_queued().ok(), None)
+            }
+            InodeSocketKind::TcpListener { socket, .. } => {
+                (None, None, socket.accept_queued().ok())
+            }
+            InodeSocketKind::PreSocket { .. } | InodeSocketKind::RemoteSocket { .. } => {
+                (None, None, None)
+            }
+        };
+        Ok(WasiSocketStats {
+            bytes_sent: self.inner.bytes_sent.load(Ordering::Relaxed),
+            bytes_received: self.inner.bytes_received.load(Ordering::Relaxed),
+            send_queued,
+            recv_queued,
+            accept_queued,
+            status,
+        })
+    }
+
+    /// Returns true if the socket receives whole datagrams, a datagram that
+    /// does not fit in the buffer of a receive is truncated
+    pub fn is_datagram(&self) -> bool {
+        let inner = self.inner.protected.read().unwrap();
+        matches!(
+            &inner.kind,
+            InodeSocketKind::UdpSocket { .. } | InodeSocketKind::Icmp(..)
+        )
+    }
+
     pub fn addr_local(&self) -> Result<SocketAddr, Errno> {
         let inner = self.inner.protected.read().unwrap();
         Ok(match &inner.kind {
-            InodeSocketKind::PreSocket { props, addr, .. } => {
-                if let Some(addr) = addr {
+            InodeSocketKind::PreSocket {
+                props,
+                addr,
+                binding,
+            } => {
+                if let Some(binding) = binding {
+                    binding.addr_local().map_err(net_error_into_wasi_err)?
+                } else if let Some(addr) = addr {
                     *addr
                 } else {
                     SocketAddr::new(
@@ -792,6 +1030,9 @@ impl InodeSocket {
                 WasiSocketOption::MulticastLoopV6 => socket
                     .set_multicast_loop_v6(val)
                     .map_err(net_error_into_wasi_err)?,
+                WasiSocketOption::RecvTtl => {
+                    socket.set_recv_ttl(val).map_err(net_error_into_wasi_err)?
+                }
                 _ => return Err(Errno::Inval),
             },
             _ => return Err(Errno::Notsup),
@@ -837,6 +1078,7 @@ impl InodeSocket {
                 WasiSocketOption::MulticastLoopV6 => socket
                     .multicast_loop_v6()
                     .map_err(net_error_into_wasi_err)?,
+                WasiSocketOption::RecvTtl => socket.recv_ttl().map_err(net_error_into_wasi_err)?,
                 _ => return Err(Errno::Inval),
             },
             _ => return Err(Errno::Notsup),
@@ -874,6 +1116,23 @@ impl InodeSocket {
         }
     }
 
+    /// Binds the socket to the network interface `device`, which must happen
+    /// before the socket listens or connects
+    pub fn set_device(&mut self, net: &dyn VirtualNetworking, device: &str) -> Result<(), Errno> {
+        let mut inner = self.inner.protected.write().unwrap();
+        match &mut inner.kind {
+            InodeSocketKind::PreSocket { props, .. } => {
+                // Makes sure that the backend supports the interface
+                net.bind_to_device(device)
+                    .map_err(net_error_into_wasi_err)?;
+                props.device = Some(device.to_string());
+            }
+            // The socket is already connected or listening
+            _ => return Err(Errno::Inval),
+        }
+        Ok(())
+    }
+
     pub fn set_recv_buf_size(&mut self, size: usize) -> Result<(), Errno> {
         let mut inner = self.inner.protected.write().unwrap();
         match &mut inner.kind {
@@ -905,6 +1164,16 @@ impl InodeSocket {
         }
     }
 
+    /// Returns the error of a failed non-blocking connect and clears it
+    pub fn take_last_error(&self) -> Result<Option<Errno>, Errno> {
+        let mut inner = self.inner.protected.write().unwrap();
+        match &mut inner.kind {
+            InodeSocketKind::PreSocket { props, .. }
+            | InodeSocketKind::RemoteSocket { props, .. } => Ok(props.last_error.take()),
+            _ => Ok(None),
+        }
+    }
+
     pub fn set_linger(&mut self, linger: Option<std::time::Duration>) -> Result<(), Errno> {
         let mut inner = self.inner.protected.write().unwrap();
         match &mut inner.kind {
@@ -933,12 +1202,20 @@ impl InodeSocket {
         ty: TimeType,
         timeout: Option<std::time::Duration>,
     ) -> Result<(), Errno> {
+        // A zero timeout means that there is no timeout at all
+        let timeout = timeout.filter(|timeout| !timeout.is_zero());
+
         let mut inner = self.inner.protected.write().unwrap();
         match &mut inner.kind {
             InodeSocketKind::TcpStream {
                 write_timeout,
                 read_timeout,
                 ..
+            }
+            | InodeSocketKind::UdpSocket {
+                write_timeout,
+                read_timeout,
+                ..
             } => {
                 match ty {
                     TimeType::WriteTimeout => *write_timeout = timeout,
@@ -976,6 +1253,11 @@ impl InodeSocket {
                 read_timeout,
                 write_timeout,
                 ..
+            }
+            | InodeSocketKind::UdpSocket {
+                read_timeout,
+                write_timeout,
+                ..
             } => Ok(match ty {
                 TimeType::ReadTimeout => *read_timeout,
                 TimeType::WriteTimeout => *write_timeout,
@@ -1061,49 +1343,66 @@ impl InodeSocket {
     }
 
     pub fn join_multicast_v4(&self, multiaddr: Ipv4Addr, iface: Ipv4Addr) -> Result<(), Errno> {
-        let mut inner = self.inner.protected.write().unwrap();
-        match &mut inner.kind {
-            InodeSocketKind::UdpSocket { socket, .. } => socket
-                .join_multicast_v4(multiaddr, iface)
-                .map_err(net_error_into_wasi_err),
-            InodeSocketKind::RemoteSocket { .. } => Ok(()),
-            InodeSocketKind::PreSocket { .. } => Err(Errno::Io),
-            _ => Err(Errno::Notsup),
-        }
+        self.change_multicast_membership(MulticastGroup::V4 { multiaddr, iface }, true)
     }
 
     pub fn leave_multicast_v4(&self, multiaddr: Ipv4Addr, iface: Ipv4Addr) -> Result<(), Errno> {
-        let mut inner = self.inner.protected.write().unwrap();
-        match &mut inner.kind {
-            InodeSocketKind::UdpSocket { socket, .. } => socket
-                .leave_multicast_v4(multiaddr, iface)
-                .map_err(net_error_into_wasi_err),
-            InodeSocketKind::RemoteSocket { .. } => Ok(()),
-            InodeSocketKind::PreSocket { .. } => Err(Errno::Io),
-            _ => Err(Errno::Notsup),
-        }
+        self.change_multicast_membership(MulticastGroup::V4 { multiaddr, iface }, false)
     }
 
     pub fn join_multicast_v6(&self, multiaddr: Ipv6Addr, iface: u32) -> Result<(), Errno> {
-        let mut inner = self.inner.protected.write().unwrap();
-        match &mut inner.kind {
-            InodeSocketKind::UdpSocket { socket, .. } => socket
-                .join_multicast_v6(multiaddr, iface)
-                .map_err(net_error_into_wasi_err),
-            InodeSocketKind::RemoteSocket { .. } => Ok(()),
-            InodeSocketKind::PreSocket { .. } => Err(Errno::Io),
-            _ => Err(Errno::Notsup),
-        }
+        self.change_multicast_membership(MulticastGroup::V6 { multiaddr, iface }, true)
     }
 
     pub fn leave_multicast_v6(&mut self, multiaddr: Ipv6Addr, iface: u32) -> Result<(), Errno> {
+        self.change_multicast_membership(MulticastGroup::V6 { multiaddr, iface }, false)
+    }
+
+    /// Joins or leaves a multicast group. Like on Linux, joining a group
+    /// that the socket is already a member of fails with `Addrinuse` and
+    /// leaving a group that it isn't a member of fails with `Addrnotavail`,
+    /// whatever the network implementation does.
+    fn change_multicast_membership(&self, group: MulticastGroup, join: bool) -> Result<(), Errno> {
         let mut inner = self.inner.protected.write().unwrap();
         match &mut inner.kind {
-            InodeSocketKind::UdpSocket { socket, .. } => socket
-                .leave_multicast_v6(multiaddr, iface)
-                .map_err(net_error_into_wasi_err),
+            InodeSocketKind::UdpSocket {
+                socket,
+                multicast_groups,
+                ..
+            } => {
+                let member = multicast_groups.contains(&group);
+                if join && member {
+                    return Err(Errno::Addrinuse);
+                }
+                if !join && !member {
+                    return Err(Errno::Addrnotavail);
+                }
+                match (group, join) {
+                    (MulticastGroup::V4 { multiaddr, iface }, true) => {
+                        socket.join_multicast_v4(multiaddr, iface)
+                    }
+                    (MulticastGroup::V4 { multiaddr, iface }, false) => {
+                        socket.leave_multicast_v4(multiaddr, iface)
+                    }
+                    (MulticastGroup::V6 { multiaddr, iface }, true) => {
+                        socket.join_multicast_v6(multiaddr, iface)
+                    }
+                    (MulticastGroup::V6 { multiaddr, iface }, false) => {
+                        socket.leave_multicast_v6(multiaddr, iface)
+                    }
+                }
+                .map_err(net_error_into_wasi_err)?;
+                if join {
+                    multicast_groups.push(group);
+                } else {
+                    multicast_groups.retain(|g| *g != group);
+                }
+                Ok(())
+            }
             InodeSocketKind::RemoteSocket { .. } => Ok(()),
-            InodeSocketKind::PreSocket { .. } => Err(Errno::Io),
+            InodeSocketKind::PreSocket { props, .. } if props.ty == Socktype::Dgram => {
+                Err(Errno::Io)
+            }
             _ => Err(Errno::Notsup),
         }
     }
@@ -1140,7 +1439,7 @@ impl InodeSocket {
                     let res = match &mut inner.kind {
                         InodeSocketKind::Raw(socket) => socket.try_send(self.data),
                         InodeSocketKind::TcpStream { socket, .. } => socket.try_send(self.data),
-                        InodeSocketKind::UdpSocket { socket, peer } => {
+                        InodeSocketKind::UdpSocket { socket, peer, .. } => {
                             if let Some(peer) = peer {
                                 socket.try_send_to(self.data, *peer)
                             } else {
@@ -1184,14 +1483,20 @@ impl InodeSocket {
             nonblocking,
             handler_registered: false,
         };
-        if let Some(timeout) = timeout {
+        let res = if let Some(timeout) = timeout {
             tokio::select! {
                 res = poller => res,
                 _ = tasks.sleep_now(timeout) => Err(Errno::Timedout)
             }
         } else {
             poller.await
+        };
+        if let Ok(amt) = &res {
+            self.inner
+                .bytes_sent
+                .fetch_add(*amt as u64, Ordering::Relaxed);
         }
+        res
     }
 
     pub async fn send_to<M: MemorySize>(
@@ -1268,14 +1573,20 @@ impl InodeSocket {
             nonblocking,
             handler_registered: false,
         };
-        if let Some(timeout) = timeout {
+        let res = if let Some(timeout) = timeout {
             tokio::select! {
                 res = poller => res,
                 _ = tasks.sleep_now(timeout) => Err(Errno::Timedout)
             }
         } else {
             poller.await
+        };
+        if let Ok(amt) = &res {
+            self.inner
+                .bytes_sent
+                .fetch_add(*amt as u64, Ordering::Relaxed);
         }
+        res
     }
 
     pub async fn recv(
@@ -1315,7 +1626,7 @@ impl InodeSocket {
                         InodeSocketKind::TcpStream { socket, .. } => {
                             socket.try_recv(self.data, peek)
                         }
-                        InodeSocketKind::UdpSocket { socket, peer } => {
+                        InodeSocketKind::UdpSocket { socket, peer, .. } => {
                             if let Some(peer) = peer {
                                 match socket.try_recv_from(self.data, peek) {
                                     Ok((amt, addr)) if addr == *peer => Ok(amt),
@@ -1368,14 +1679,22 @@ impl InodeSocket {
             peek,
             handler_registered: false,
         };
-        if let Some(timeout) = timeout {
+        let res = if let Some(timeout) = timeout {
             tokio::select! {
                 res = poller => res,
                 _ = tasks.sleep_now(timeout) => Err(Errno::Timedout)
             }
         } else {
             poller.await
-        }
+        };
+        if let Ok(amt) = &res
+            && !peek
+        {
+            self.inner
+                .bytes_received
+                .fetch_add(*amt as u64, Ordering::Relaxed);
+        }
+        res
     }
 
     pub async fn recv_from(
@@ -1386,6 +1705,22 @@ impl InodeSocket {
         nonblocking: bool,
         peek: bool,
     ) -> Result<(usize, SocketAddr), Errno> {
+        self.recv_from_ttl(tasks, buf, timeout, nonblocking, peek)
+            .await
+            .map(|(amt, addr, _)| (amt, addr))
+    }
+
+    /// Receives a datagram like [`recv_from`](Self::recv_from) along with
+    /// the TTL that it arrived with, which is only known for UDP sockets
+    /// that have the `RecvTtl` option set
+    pub async fn recv_from_ttl(
+        &self,
+        tasks: &dyn VirtualTaskManager,
+        buf: &mut [MaybeUninit<u8>],
+        timeout: Option<Duration>,
+        nonblocking: bool,
+        peek: bool,
+    ) -> Result<(usize, SocketAddr, Option<u8>), Errno> {
         struct SocketReceiver<'a, 'b> {
             inner: &'a InodeSocketInner,
             data: &'b mut [MaybeUninit<u8>],
@@ -1402,7 +1737,7 @@ impl InodeSocket {
             }
         }
         impl Future for SocketReceiver<'_, '_> {
-            type Output = Result<(usize, SocketAddr), Errno>;
+            type Output = Result<(usize, SocketAddr, Option<u8>), Errno>;
             fn poll(
                 mut self: Pin<&mut Self>,
                 cx: &mut std::task::Context<'_>,
@@ -1411,15 +1746,17 @@ impl InodeSocket {
                 let mut inner = self.inner.protected.write().unwrap();
                 loop {
                     let res = match &mut inner.kind {
-                        InodeSocketKind::Icmp(socket) => socket.try_recv_from(self.data, peek),
+                        InodeSocketKind::Icmp(socket) => socket
+                            .try_recv_from(self.data, peek)
+                            .map(|(amt, addr)| (amt, addr, None)),
                         InodeSocketKind::UdpSocket { socket, .. } => {
-                            socket.try_recv_from(self.data, peek)
+                            socket.try_recv_from_ttl(self.data, peek)
                         }
                         InodeSocketKind::RemoteSocket {
                             is_dead, peer_addr, ..
                         } => {
                             return match is_dead {
-                                true => Poll::Ready(Ok((0, *peer_addr))),
+                                true => Poll::Ready(Ok((0, *peer_addr, None))),
                                 false => Poll::Pending,
                             };
                         }
@@ -1429,7 +1766,7 @@ impl InodeSocket {
                         _ => return Poll::Ready(Err(Errno::Notsup)),
                     };
                     return match res {
-                        Ok((amt, addr)) => Poll::Ready(Ok((amt, addr))),
+                        Ok(ret) => Poll::Ready(Ok(ret)),
                         Err(NetworkError::WouldBlock) if self.nonblocking => {
                             Poll::Ready(Err(Errno::Again))
                         }
@@ -1454,14 +1791,22 @@ impl InodeSocket {
             peek,
             handler_registered: false,
         };
-        if let Some(timeout) = timeout {
+        let res = if let Some(timeout) = timeout {
             tokio::select! {
                 res = poller => res,
                 _ = tasks.sleep_now(timeout) => Err(Errno::Timedout)
             }
         } else {
             poller.await
-        }
+        };
+        if let Ok((amt, ..)) = &res
+            && !peek
+        {
+            self.inner
+                .bytes_received
+                .fetch_add(*amt as u64, Ordering::Relaxed);
+        }
+        res
     }
 
     pub fn shutdown(&mut self, how: std::net::Shutdown) -> Result<(), Errno> {
@@ -1517,7 +1862,10 @@ impl InodeSocketProtected {
             InodeSocketKind::UdpSocket { socket, .. } => socket.poll_read_ready(cx),
             InodeSocketKind::Raw(socket) => socket.poll_read_ready(cx),
             InodeSocketKind::Icmp(socket) => socket.poll_read_ready(cx),
-            InodeSocketKind::PreSocket { .. } => Poll::Pending,
+            InodeSocketKind::PreSocket { props, .. } => match props.last_error {
+                Some(_) => Poll::Ready(Ok(0)),
+                None => Poll::Pending,
+            },
             InodeSocketKind::RemoteSocket { is_dead, .. } => match is_dead {
                 true => Poll::Ready(Ok(0)),
                 false => Poll::Pending,
@@ -1533,7 +1881,10 @@ impl InodeSocketProtected {
             InodeSocketKind::UdpSocket { socket, .. } => socket.poll_write_ready(cx),
             InodeSocketKind::Raw(socket) => socket.poll_write_ready(cx),
             InodeSocketKind::Icmp(socket) => socket.poll_write_ready(cx),
-            InodeSocketKind::PreSocket { .. } => Poll::Pending,
+            InodeSocketKind::PreSocket { props, .. } => match props.last_error {
+                Some(_) => Poll::Ready(Ok(0)),
+                None => Poll::Pending,
+            },
             InodeSocketKind::RemoteSocket { is_dead, .. } => match is_dead {
                 true => Poll::Ready(Ok(0)),
                 false => Poll::Pending,
@@ -1561,6 +1912,23 @@ impl InodeSocketProtected {
     }
 }
 
+/// The network that a socket that is bound to an interface with
+/// `SO_BINDTODEVICE` uses, `None` when it uses the whole network
+fn device_net(
+    net: &dyn VirtualNetworking,
+    kind: &InodeSocketKind,
+) -> Result<Option<DynVirtualNetworking>, Errno> {
+    match kind {
+        InodeSocketKind::PreSocket { props, .. } => props
+            .device
+            .as_deref()
+            .map(|device| net.bind_to_device(device))
+            .transpose()
+            .map_err(net_error_into_wasi_err),
+        _ => Ok(None),
+    }
+}
+
 // TODO: review allow...
 #[allow(dead_code)]
 pub(crate) fn all_socket_rights() -> Rights {
@@ -1590,7 +1958,7 @@ mod tests {
         net::{Ipv4Addr, Shutdown, SocketAddr},
         pin::Pin,
         sync::{
-            Arc,
+            Arc, Mutex,
             atomic::{AtomicUsize, Ordering},
         },
         task::{Context, Poll},
@@ -1598,8 +1966,9 @@ mod tests {
     };
     use virtual_mio::InterestHandler;
     use virtual_net::{
-        NetworkError, Result as NetResult, SocketStatus, VirtualConnectedSocket, VirtualIoSource,
-        VirtualSocket, VirtualTcpSocket,
+        DynVirtualNetworking, NetworkError, Result as NetResult, SocketStatus,
+        VirtualConnectedSocket, VirtualIoSource, VirtualNetworking, VirtualSocket,
+        VirtualTcpSocket,
     };
 
     #[derive(Debug)]
@@ -1748,4 +2117,233 @@ mod tests {
         assert_eq!(read_calls.load(Ordering::Relaxed), 0);
         assert_eq!(write_calls.load(Ordering::Relaxed), 1);
     }
+
+    #[cfg(feature = "sys-thread")]
+    fn pre_socket(
+        ty: wasmer_wasix_types::wasi::Socktype,
+        pt: wasmer_wasix_types::wasi::SockProto,
+    ) -> InodeSocket {
+        use wasmer_wasix_types::wasi::Addressfamily;
+
+        use super::SocketProperties;
+
+        InodeSocket::new(InodeSocketKind::PreSocket {
+            props: SocketProperties {
+                family: Addressfamily::Inet4,
+                ty,
+                pt,
+                only_v6: false,
+                reuse_port: false,
+                reuse_addr: false,
+                no_delay: None,
+                keep_alive: None,
+                dont_route: None,
+                send_buf_size: None,
+                recv_buf_size: None,
+                write_timeout: None,
+                read_timeout: None,
+                accept_timeout: None,
+                connect_timeout: None,
+                device: None,
+                last_error: None,
+                handler: None,
+            },
+            addr: None,
+            binding: None,
+        })
+    }
+
+    #[cfg(all(feature = "host-vnet", feature = "sys-thread"))]
+    async fn bound_udp_socket(
+        tasks: &dyn crate::VirtualTaskManager,
+        read_timeout: Option<Duration>,
+    ) -> InodeSocket {
+        use wasmer_wasix_types::wasi::{SockProto, Socktype};
+
+        use super::TimeType;
+
+        let socket = pre_socket(Socktype::Dgram, SockProto::Udp);
+        socket
+            .set_opt_time(TimeType::ReadTimeout, read_timeout)
+            .unwrap();
+
+        let net = virtual_net::host::LocalNetworking::default();
+        socket
+            .bind(tasks, &net, SocketAddr::from((Ipv4Addr::LOCALHOST, 0)))
+            .await
+            .unwrap()
+            .unwrap()
+    }
+
+    #[cfg(all(feature = "host-vnet", feature = "sys-thread"))]
+    #[tokio::test]
+    async fn udp_recv_times_out_on_idle_socket() {
+        use super::TimeType;
+        use crate::runtime::task_manager::tokio::TokioTaskManager;
+        use wasmer_wasix_types::wasi::Errno;
+
+        let tasks = TokioTaskManager::new(tokio::runtime::Handle::current());
+        let socket = bound_udp_socket(&tasks, Some(Duration::from_millis(50))).await;
+
+        // The timeout set before binding is carried over to the bound socket
+        let timeout = socket.opt_time(TimeType::ReadTimeout).unwrap();
+        assert_eq!(timeout, Some(Duration::from_millis(50)));
+
+        let mut buf = [MaybeUninit::uninit(); 16];
+        let res = socket.recv(&tasks, &mut buf, timeout, false, false).await;
+        assert_eq!(res, Err(Errno::Timedout));
+    }
+
+    #[cfg(all(feature = "host-vnet", feature = "sys-thread"))]
+    #[tokio::test]
+    async fn udp_recv_completes_before_timeout() {
+        use super::TimeType;
+        use crate::runtime::task_manager::tokio::TokioTaskManager;
+
+        let tasks = TokioTaskManager::new(tokio::runtime::Handle::current());
+        let socket = bound_udp_socket(&tasks, Some(Duration::from_secs(5))).await;
+
+        let sender = std::net::UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        sender
+            .send_to(b"hello", socket.addr_local().unwrap())
+            .unwrap();
+
+        let timeout = socket.opt_time(TimeType::ReadTimeout).unwrap();
+        let mut buf = [MaybeUninit::uninit(); 16];
+        let res = socket.recv(&tasks, &mut buf, timeout, false, false).await;
+        assert_eq!(res, Ok(5));
+    }
+
+    #[cfg(all(feature = "host-vnet", feature = "sys-thread"))]
+    #[tokio::test]
+    async fn tcp_bind_to_port_zero_assigns_an_ephemeral_port() {
+        use crate::runtime::task_manager::tokio::TokioTaskManager;
+        use wasmer_wasix_types::wasi::{SockProto, Socktype};
+
+        let tasks = TokioTaskManager::new(tokio::runtime::Handle::current());
+        let net = virtual_net::host::LocalNetworking::default();
+        let socket = pre_socket(Socktype::Stream, SockProto::Tcp);
+        let ret = socket
+            .bind(&tasks, &net, SocketAddr::from((Ipv4Addr::LOCALHOST, 0)))
+            .await
+            .unwrap();
+        assert!(ret.is_none());
+
+        // The port is assigned by the bind, before the socket listens, but
+        // nothing can connect to it yet
+        let addr = socket.addr_local().unwrap();
+        assert_eq!(addr.ip(), Ipv4Addr::LOCALHOST);
+        assert_ne!(addr.port(), 0);
+        std::net::TcpStream::connect(addr).unwrap_err();
+
+        // And the socket keeps that port once it listens
+        let listener = socket.listen(&tasks, &net, 16).await.unwrap().unwrap();
+        assert_eq!(listener.addr_local().unwrap(), addr);
+        std::net::TcpStream::connect(addr).unwrap();
+    }
+
+    #[cfg(all(feature = "host-vnet", feature = "sys-thread"))]
+    #[tokio::test]
+    async fn tcp_connect_keeps_the_ephemeral_port_of_the_bind() {
+        use crate::runtime::task_manager::tokio::TokioTaskManager;
+        use wasmer_wasix_types::wasi::{SockProto, Socktype};
+
+        let tasks = TokioTaskManager::new(tokio::runtime::Handle::current());
+        let net = virtual_net::host::LocalNetworking::default();
+        let server = std::net::TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+
+        let socket = pre_socket(Socktype::Stream, SockProto::Tcp);
+        socket
+            .bind(&tasks, &net, SocketAddr::from((Ipv4Addr::LOCALHOST, 0)))
+            .await
+            .unwrap();
+        let addr = socket.addr_local().unwrap();
+        socket
+            .connect(&tasks, &net, server.local_addr().unwrap(), None, false)
+            .await
+            .unwrap()
+            .unwrap();
+
+        let (_, peer) = server.accept().unwrap();
+        assert_eq!(peer, addr);
+    }
+
+    /// Network that records the interface of every connection
+    #[derive(Debug, Default)]
+    struct DeviceNetworking {
+        device: Option<String>,
+        connections: Arc<Mutex<Vec<(Option<String>, SocketAddr)>>>,
+    }
+
+    #[async_trait::async_trait]
+    impl VirtualNetworking for DeviceNetworking {
+        fn bind_to_device(&self, device: &str) -> NetResult<DynVirtualNetworking> {
+            Ok(Arc::new(DeviceNetworking {
+                device: Some(device.to_string()),
+                connections: self.connections.clone(),
+            }))
+        }
+
+        async fn connect_tcp(
+            &self,
+            _addr: SocketAddr,
+            peer: SocketAddr,
+        ) -> NetResult<Box<dyn VirtualTcpSocket + Sync>> {
+            self.connections
+                .lock()
+                .unwrap()
+                .push((self.device.clone(), peer));
+            Ok(Box::new(MockTcpSocket {
+                read_calls: Default::default(),
+                write_calls: Default::default(),
+            }))
+        }
+    }
+
+    #[cfg(feature = "sys-thread")]
+    #[tokio::test]
+    async fn socket_bound_to_device_connects_through_it() {
+        use crate::runtime::task_manager::tokio::TokioTaskManager;
+        use wasmer_wasix_types::wasi::{Errno, SockProto, Socktype};
+
+        let tasks = TokioTaskManager::new(tokio::runtime::Handle::current());
+        let peer = SocketAddr::from((Ipv4Addr::LOCALHOST, 80));
+
+        let mut socket = pre_socket(Socktype::Stream, SockProto::Tcp);
+        let unsupported = virtual_net::UnsupportedVirtualNetworking::default();
+        assert_eq!(socket.set_device(&unsupported, "eth1"), Err(Errno::Notsup));
+
+        let net = DeviceNetworking::default();
+        socket.set_device(&net, "eth1").unwrap();
+        let mut connected = socket
+            .connect(&tasks, &net, peer, None, false)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            *net.connections.lock().unwrap(),
+            [(Some("eth1".to_string()), peer)]
+        );
+
+        // The interface can't change once the socket is connected
+        assert_eq!(connected.set_device(&net, "eth2"), Err(Errno::Inval));
+    }
+
+    #[test]
+    fn zero_socket_timeout_means_no_timeout() {
+        use super::TimeType;
+
+        let socket = InodeSocket::new(InodeSocketKind::TcpStream {
+            socket: Box::new(MockTcpSocket {
+                read_calls: Default::default(),
+                write_calls: Default::default(),
+            }),
+            write_timeout: None,
+            read_timeout: None,
+        });
+        socket
+            .set_opt_time(TimeType::WriteTimeout, Some(Duration::ZERO))
+            .unwrap();
+        assert_eq!(socket.opt_time(TimeType::WriteTimeout), Ok(None));
+    }
 }
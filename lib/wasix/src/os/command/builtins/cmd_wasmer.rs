@@ -15,7 +15,7 @@ use wasmer_wasix_types::wasi::Errno;
 
 use crate::{
     Runtime, WasiEnv,
-    bin_factory::{BinaryPackage, spawn_exec},
+    bin_factory::{BinaryPackage, FdInheritance, spawn_exec},
     syscalls::stderr_write,
 };
 
@@ -142,11 +142,11 @@ impl CmdWasmer {
                     env.use_package_async(&binary).await.unwrap();
 
                     // Now run the module
-                    spawn_exec(*binary, name, env, &self.runtime).await
+                    spawn_exec(*binary, name, env, &self.runtime, FdInheritance::All).await
                 }
                 Executable::Wasm(bytes) => {
                     let data = HashedModuleData::new(bytes);
-                    spawn_exec_wasm(data, name, env, &self.runtime).await
+                    spawn_exec_wasm(data, name, env, &self.runtime, FdInheritance::All).await
                 }
             }
         } else {
@@ -277,7 +277,13 @@ impl Console {
 
         // Build the config
         // Run the binary
-        let process = block_on(spawn_exec(pkg, prog, env, &self.runtime))?;
+        let process = block_on(spawn_exec(
+            pkg,
+            prog,
+            env,
+            &self.runtime,
+            Default::default(),
+        ))?;
 
         // Return the process
         Ok((process, wasi_process))
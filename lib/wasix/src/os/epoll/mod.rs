@@ -144,8 +144,9 @@ impl Drop for EpollJoinGuard {
     fn drop(&mut self) {
         // Dropping a subscription must detach its interest handler from the source.
         match &self.fd_guard.mode {
-            InodeValFilePollGuardMode::File(_) => {
-                // Intentionally ignored, epoll doesn't work with files
+            InodeValFilePollGuardMode::File(_)
+            | InodeValFilePollGuardMode::SignalNotifications(_) => {
+                // Intentionally ignored, epoll doesn't work with files or signal descriptors
             }
             InodeValFilePollGuardMode::Socket { inner } => {
                 let mut inner = inner.protected.write().unwrap();
@@ -641,8 +642,8 @@ pub(crate) fn register_epoll_handler(
     let handler = EpollHandler::new(event.fd(), epoll_state.clone(), sub_state.clone());
 
     match &fd_guard.mode {
-        InodeValFilePollGuardMode::File(_) => {
-            // Intentionally ignored, epoll doesn't work with files
+        InodeValFilePollGuardMode::File(_) | InodeValFilePollGuardMode::SignalNotifications(_) => {
+            // Intentionally ignored, epoll doesn't work with files or signal descriptors
             return Ok(None);
         }
         InodeValFilePollGuardMode::Socket { inner, .. } => {
@@ -4,6 +4,7 @@ pub(crate) mod epoll;
 pub mod tty;
 
 pub mod command;
+pub mod system_info;
 pub mod task;
 
 pub use console::*;
@@ -0,0 +1,101 @@
+//! What the guests are told about the system they run on, see the
+//! `sysinfo` syscall.
+
+use std::time::Duration;
+
+use wasmer::WASM_PAGE_SIZE;
+
+/// The hostname of the guests when none is set
+pub const DEFAULT_HOSTNAME: &str = "localhost";
+
+/// Overrides the information about the system that is handed to the guests
+/// (the number of CPUs, the amount of memory, ...), every value that is not
+/// set is taken from the host.
+///
+/// This lets a sandbox report the resources it actually grants instead of
+/// the ones of the machine it happens to run on, so that guests size their
+/// thread pools and buffers accordingly.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SystemInfo {
+    /// Number of logical CPUs, by default the parallelism of the task manager
+    pub cpus: Option<u32>,
+    /// Size of a page of memory, by default the size of a WebAssembly page
+    pub page_size: Option<u32>,
+    /// Total amount of memory in bytes
+    pub total_memory: Option<u64>,
+    /// Amount of memory in bytes that is available
+    pub available_memory: Option<u64>,
+    /// The uptime of the system when the environment is built, it then
+    /// advances with the monotonic clock of the host (the clocks of the
+    /// guest are not affected)
+    pub uptime: Option<Duration>,
+}
+
+impl SystemInfo {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_cpus(mut self, cpus: u32) -> Self {
+        self.cpus = Some(cpus);
+        self
+    }
+
+    pub fn with_page_size(mut self, page_size: u32) -> Self {
+        self.page_size = Some(page_size);
+        self
+    }
+
+    pub fn with_total_memory(mut self, bytes: u64) -> Self {
+        self.total_memory = Some(bytes);
+        self
+    }
+
+    pub fn with_available_memory(mut self, bytes: u64) -> Self {
+        self.available_memory = Some(bytes);
+        self
+    }
+
+    pub fn with_uptime(mut self, uptime: Duration) -> Self {
+        self.uptime = Some(uptime);
+        self
+    }
+
+    pub(crate) fn page_size_or_default(&self) -> u32 {
+        self.page_size.unwrap_or(WASM_PAGE_SIZE as u32)
+    }
+
+    /// The total and available memory, the values that are not overridden
+    /// come from the host (or are zero when the host doesn't tell)
+    pub(crate) fn memory_or_default(&self) -> (u64, u64) {
+        let (host_total, host_available) = match (self.total_memory, self.available_memory) {
+            (Some(total), Some(available)) => (total, available),
+            _ => host_memory(),
+        };
+        let total = self.total_memory.unwrap_or(host_total);
+        let available = self.available_memory.unwrap_or(host_available).min(total);
+        (total, available)
+    }
+}
+
+/// The total and available memory of the host
+#[cfg(any(target_os = "linux", target_os = "android"))]
+#[allow(clippy::unnecessary_cast)] // the fields are 32 bits wide on some targets
+fn host_memory() -> (u64, u64) {
+    let mut info = std::mem::MaybeUninit::<libc::sysinfo>::zeroed();
+    if unsafe { libc::sysinfo(info.as_mut_ptr()) } != 0 {
+        return (0, 0);
+    }
+    let info = unsafe { info.assume_init() };
+    let unit = (info.mem_unit as u64).max(1);
+    (
+        (info.totalram as u64).saturating_mul(unit),
+        (info.freeram as u64).saturating_mul(unit),
+    )
+}
+
+/// The total and available memory of the host
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+fn host_memory() -> (u64, u64) {
+    (0, 0)
+}
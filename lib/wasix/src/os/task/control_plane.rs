@@ -1,5 +1,6 @@
 use std::{
-    collections::HashMap,
+    collections::{HashMap, VecDeque},
+    num::NonZeroU32,
     sync::{
         Arc, RwLock,
         atomic::{AtomicUsize, Ordering},
@@ -7,8 +8,12 @@ use std::{
     time::Duration,
 };
 
-use crate::{WasiProcess, WasiProcessId};
+use crate::{WasiProcess, WasiProcessId, syscalls::platform_clock_time_get};
 use wasmer_types::ModuleHash;
+use wasmer_wasix_types::wasi::Snapshot0Clockid;
+
+/// The longest that a fork is delayed by [`WasiControlPlane::register_fork`]
+const MAX_FORK_DELAY: Duration = Duration::from_secs(1);
 
 #[derive(Debug, Clone)]
 pub struct WasiControlPlane {
@@ -48,6 +53,13 @@ pub struct ControlPlaneConfig {
     /// time that it will pause the CPU)
     /// (default = off)
     pub enable_exponential_cpu_backoff: Option<Duration>,
+    /// Number of forks per second that a process can make before its
+    /// forks are delayed. Every fork beyond the limit waits twice as long
+    /// as the one before it (starting at the time between two forks at
+    /// the limit, up to a second), which dampens fork storms without
+    /// failing the forks.
+    /// (default = off)
+    pub max_forks_per_second: Option<NonZeroU32>,
 }
 
 impl ControlPlaneConfig {
@@ -56,6 +68,7 @@ impl ControlPlaneConfig {
             max_task_count: None,
             enable_asynchronous_threading: false,
             enable_exponential_cpu_backoff: None,
+            max_forks_per_second: None,
         }
     }
 }
@@ -83,9 +96,27 @@ struct MutableState {
     process_seed: u32,
     /// The processes running on this machine
     processes: HashMap<WasiProcessId, WasiProcess>,
+    /// How often the processes forked
+    forks: HashMap<WasiProcessId, ForkCounter>,
     // TODO: keep a queue of terminated process ids for id reuse.
 }
 
+/// How often a process forked, see [`WasiControlPlane::fork_stats`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ForkStats {
+    /// The number of forks since the process was created
+    pub total: u64,
+    /// The number of forks in the last second
+    pub last_second: usize,
+}
+
+#[derive(Debug, Default)]
+struct ForkCounter {
+    total: u64,
+    /// Monotonic times (in nanoseconds) of the forks in the last second
+    recent: VecDeque<u64>,
+}
+
 impl WasiControlPlane {
     pub fn new(config: ControlPlaneConfig) -> Self {
         Self {
@@ -95,6 +126,7 @@ impl WasiControlPlane {
                 mutable: RwLock::new(MutableState {
                     process_seed: 0,
                     processes: Default::default(),
+                    forks: Default::default(),
                 }),
             }),
         }
@@ -128,9 +160,8 @@ impl WasiControlPlane {
         Ok(TaskCountGuard(self.state.task_count.clone()))
     }
 
-    /// Creates a new process
-    // FIXME: De-register terminated processes!
-    // Currently they just accumulate.
+    /// Creates a new process, it stays registered until it terminates (see
+    /// [`WasiProcess::terminate`])
     pub fn new_process(&self, module_hash: ModuleHash) -> Result<WasiProcess, ControlPlaneError> {
         if let Some(max) = self.state.config.max_task_count
             && self.active_task_count() >= max
@@ -151,6 +182,14 @@ impl WasiControlPlane {
         Ok(proc)
     }
 
+    /// Removes a process that terminated along with the forks that were
+    /// counted for it
+    pub(crate) fn remove_process(&self, pid: WasiProcessId) {
+        let mut mutable = self.state.mutable.write().unwrap();
+        mutable.processes.remove(&pid);
+        mutable.forks.remove(&pid);
+    }
+
     /// Generates a new process ID
     pub fn generate_id(&self) -> Result<WasiProcessId, ControlPlaneError> {
         let mut mutable = self.state.mutable.write().unwrap();
@@ -167,6 +206,72 @@ impl WasiControlPlane {
             .get(&pid)
             .cloned()
     }
+
+    /// Lists the processes of this machine ordered by their ID
+    pub fn processes(&self) -> Vec<WasiProcess> {
+        let mut processes: Vec<_> = self
+            .state
+            .mutable
+            .read()
+            .unwrap()
+            .processes
+            .values()
+            .cloned()
+            .collect();
+        processes.sort_by_key(|process| process.pid());
+        processes
+    }
+
+    /// Counts a fork of the process and returns how long the fork has to be
+    /// delayed, see [`ControlPlaneConfig::max_forks_per_second`]
+    pub(crate) fn register_fork(&self, pid: WasiProcessId) -> Option<Duration> {
+        let now = platform_clock_time_get(Snapshot0Clockid::Monotonic, 1_000_000).unwrap() as u64;
+        self.register_fork_at(pid, now)
+    }
+
+    fn register_fork_at(&self, pid: WasiProcessId, now: u64) -> Option<Duration> {
+        let mut mutable = self.state.mutable.write().unwrap();
+        let counter = mutable.forks.entry(pid).or_default();
+        counter.total += 1;
+        counter.recent.push_back(now);
+        while let Some(first) = counter.recent.front()
+            && now.saturating_sub(*first) >= Duration::from_secs(1).as_nanos() as u64
+        {
+            counter.recent.pop_front();
+        }
+
+        let max = self.state.config.max_forks_per_second?.get();
+        let excess = (counter.recent.len() as u32).saturating_sub(max);
+        if excess == 0 {
+            return None;
+        }
+        let delay = 2u32
+            .checked_pow(excess - 1)
+            .map_or(MAX_FORK_DELAY, |factor| {
+                (Duration::from_secs(1) / max).saturating_mul(factor)
+            });
+        Some(delay.min(MAX_FORK_DELAY))
+    }
+
+    /// Returns how often the process forked
+    pub fn fork_stats(&self, pid: WasiProcessId) -> ForkStats {
+        let now = platform_clock_time_get(Snapshot0Clockid::Monotonic, 1_000_000).unwrap() as u64;
+        let mutable = self.state.mutable.read().unwrap();
+        mutable
+            .forks
+            .get(&pid)
+            .map(|counter| ForkStats {
+                total: counter.total,
+                last_second: counter
+                    .recent
+                    .iter()
+                    .filter(|at| {
+                        now.saturating_sub(**at) < Duration::from_secs(1).as_nanos() as u64
+                    })
+                    .count(),
+            })
+            .unwrap_or_default()
+    }
 }
 
 impl MutableState {
@@ -211,7 +316,7 @@ pub enum ControlPlaneError {
 
 #[cfg(test)]
 mod tests {
-    use wasmer_wasix_types::wasix::ThreadStartType;
+    use wasmer_wasix_types::{wasi::ExitCode, wasix::ThreadStartType};
 
     use crate::os::task::thread::WasiMemoryLayout;
 
@@ -224,6 +329,7 @@ mod tests {
             max_task_count: Some(2),
             enable_asynchronous_threading: false,
             enable_exponential_cpu_backoff: None,
+            max_forks_per_second: None,
         });
 
         let p1 = p.new_process(ModuleHash::random()).unwrap();
@@ -247,6 +353,7 @@ mod tests {
             max_task_count: Some(2),
             enable_asynchronous_threading: false,
             enable_exponential_cpu_backoff: None,
+            max_forks_per_second: None,
         });
 
         let p1 = p.new_process(ModuleHash::random()).unwrap();
@@ -269,4 +376,72 @@ mod tests {
             ControlPlaneError::TaskLimitReached { max: 2 }
         );
     }
+
+    #[test]
+    fn test_rapid_forks_are_progressively_delayed() {
+        const MS: u64 = 1_000_000;
+
+        let p = WasiControlPlane::new(ControlPlaneConfig {
+            max_forks_per_second: NonZeroU32::new(4),
+            ..ControlPlaneConfig::new()
+        });
+        let pid = p.new_process(ModuleHash::random()).unwrap().pid();
+
+        // Bursts up to the limit are not delayed
+        for n in 0..4 {
+            assert_eq!(p.register_fork_at(pid, n * MS), None);
+        }
+
+        // Every fork beyond it waits twice as long as the one before
+        let delays: Vec<_> = (4..8)
+            .map(|n| p.register_fork_at(pid, n * MS).unwrap())
+            .collect();
+        assert_eq!(
+            delays,
+            [250, 500, 1000, 1000].map(Duration::from_millis).to_vec()
+        );
+
+        // Other processes have a limit of their own
+        let other = p.new_process(ModuleHash::random()).unwrap().pid();
+        assert_eq!(p.register_fork_at(other, 8 * MS), None);
+
+        // Once the storm is over the forks are no longer delayed
+        assert_eq!(p.register_fork_at(pid, 2_000 * MS), None);
+        assert_eq!(p.fork_stats(pid).total, 9);
+    }
+
+    #[test]
+    fn test_terminated_processes_are_removed() {
+        let p = WasiControlPlane::new(ControlPlaneConfig::new());
+        let process = p.new_process(ModuleHash::random()).unwrap();
+        let pid = process.pid();
+        p.register_fork_at(pid, 0);
+        assert_eq!(p.fork_stats(pid).total, 1);
+
+        process.terminate(ExitCode::from(0u16));
+        assert!(p.get_process(pid).is_none());
+        assert!(!p.state.mutable.read().unwrap().forks.contains_key(&pid));
+    }
+
+    #[test]
+    fn test_control_plane_lists_processes_with_their_titles() {
+        let p = WasiControlPlane::new(ControlPlaneConfig::new());
+        let p1 = p.new_process(ModuleHash::random()).unwrap();
+        let p2 = p.new_process(ModuleHash::random()).unwrap();
+        p1.set_title("shell");
+        p2.set_title("daemon: idle");
+
+        let listing: Vec<_> = p
+            .processes()
+            .iter()
+            .map(|process| (process.pid(), process.title()))
+            .collect();
+        assert_eq!(
+            listing,
+            vec![
+                (p1.pid(), "shell".to_string()),
+                (p2.pid(), "daemon: idle".to_string())
+            ]
+        );
+    }
 }
@@ -3,6 +3,8 @@
 pub mod backoff;
 pub mod control_plane;
 pub mod process;
+pub mod rate_limit;
+pub mod rlimit;
 pub mod signal;
 mod task_join_handle;
 pub mod thread;
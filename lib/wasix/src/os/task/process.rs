@@ -7,20 +7,21 @@ use std::collections::HashSet;
 use std::{
     collections::HashMap,
     convert::TryInto,
+    future::Future,
     ops::Range,
     sync::{
         Arc, Condvar, Mutex, MutexGuard, RwLock, Weak,
-        atomic::{AtomicU32, Ordering},
+        atomic::{AtomicU32, AtomicU64, Ordering},
     },
-    task::Waker,
+    task::{Poll, Waker},
     time::Duration,
 };
-use tracing::trace;
+use tracing::{Span, debug_span, field, trace};
 use wasmer::FunctionEnvMut;
 use wasmer_types::ModuleHash;
 use wasmer_wasix_types::{
     types::Signal,
-    wasi::{Errno, ExitCode, Snapshot0Clockid},
+    wasi::{Errno, ExitCode, Rlimit, RlimitResource, Snapshot0Clockid},
     wasix::ThreadStartType,
 };
 
@@ -33,6 +34,7 @@ use super::{
     TaskStatus,
     backoff::WasiProcessCpuBackoff,
     control_plane::{ControlPlaneError, WasiControlPlaneHandle},
+    rlimit::WasiResourceLimits,
     signal::{SignalDeliveryError, SignalHandlerAbi},
     task_join_handle::OwnedTaskStatus,
     thread::WasiMemoryLayout,
@@ -84,6 +86,24 @@ impl std::fmt::Debug for WasiProcessId {
     }
 }
 
+/// Describes how a process terminated
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ExitReason {
+    /// The process exited normally with an exit code
+    Exited(ExitCode),
+    /// The process was terminated by a signal
+    Signaled(ExitCode, Signal),
+}
+
+impl ExitReason {
+    /// Returns the exit code the process finished with
+    pub fn exit_code(&self) -> ExitCode {
+        match self {
+            Self::Exited(exit_code) | Self::Signaled(exit_code, _) => *exit_code,
+        }
+    }
+}
+
 pub type LockableWasiProcessInner = Arc<(Mutex<WasiProcessInner>, Condvar)>;
 
 /// Represents a process running within the compute state
@@ -111,6 +131,41 @@ pub struct WasiProcess {
     /// the exponential backoff of CPU is halted (as in CPU
     /// is allowed to run freely)
     pub(crate) cpu_run_tokens: Arc<AtomicU32>,
+    /// Counts the deep sleeps of the threads of this process
+    pub(crate) deep_sleeps: Arc<DeepSleepCounters>,
+    /// When the process was created on the monotonic clock of the host, in
+    /// nanoseconds
+    pub(crate) started: u64,
+    /// Span that the threads of the process run in, it carries the pid and
+    /// the title of the process
+    pub(crate) span: Span,
+}
+
+/// How a process was created from its parent by `proc_fork` (or
+/// `proc_fork_env`), see [`WasiProcess::fork_mode`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ForkMode {
+    /// The memory of the parent was copied into the child
+    Copy,
+    /// The child runs on the memory of its parent, which is suspended
+    /// until the child calls `proc_exec` or exits
+    VFork,
+}
+
+/// Number of times the threads of a process went into a deep sleep and
+/// were resumed afterwards, see [`WasiProcess::deep_sleep_stats`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DeepSleepStats {
+    /// Number of times a thread unwound its stack to go into a deep sleep
+    pub sleeps: u64,
+    /// Number of times a thread was rewound after a deep sleep
+    pub resumes: u64,
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct DeepSleepCounters {
+    sleeps: AtomicU64,
+    resumes: AtomicU64,
 }
 
 /// Represents a freeze of all threads to perform some action
@@ -187,6 +242,26 @@ pub struct WasiProcessInner {
     /// which will be used to determine if the CPU should be
     /// throttled or not
     pub(super) backoff: WasiProcessCpuBackoff,
+    /// The signal that terminated this process (if any)
+    pub exit_signal: Option<Signal>,
+    /// Limits on the resources that the process can use
+    pub rlimits: WasiResourceLimits,
+    /// Title of the process as shown in process listings, which starts
+    /// out as the name it was spawned with
+    pub title: String,
+    /// If true then the threads of the process wait at their next syscall
+    /// until the process is resumed, see [`WasiProcess::pause`]
+    pub paused: bool,
+    /// Wakers of the threads that wait for the process to be resumed
+    pub(super) pause_wakers: Vec<Waker>,
+    /// How the process was forked from its parent (if it was)
+    pub fork_mode: Option<ForkMode>,
+    /// If true then the process is a vfork child that did not exec or exit
+    /// yet, which means its parent is still suspended
+    pub vfork_pending: bool,
+    /// Signals after which the blocking syscalls they interrupted are
+    /// restarted (one bit per signal number, like `SA_RESTART`)
+    pub restart_signals: u64,
 }
 
 pub enum MaybeCheckpointResult<'a> {
@@ -440,6 +515,14 @@ impl WasiProcess {
                 disable_journaling_after_checkpoint: false,
                 stop_running_after_checkpoint: false,
                 backoff: WasiProcessCpuBackoff::new(max_cpu_backoff_time, max_cpu_cool_off_time),
+                exit_signal: None,
+                rlimits: Default::default(),
+                title: String::new(),
+                paused: false,
+                pause_wakers: Vec::new(),
+                fork_mode: None,
+                restart_signals: 0,
+                vfork_pending: false,
             }),
             Condvar::new(),
         ));
@@ -469,11 +552,16 @@ impl WasiProcess {
             ),
             waiting,
             cpu_run_tokens: Arc::new(AtomicU32::new(0)),
+            deep_sleeps: Default::default(),
+            started: platform_clock_time_get(Snapshot0Clockid::Monotonic, 1).unwrap_or_default()
+                as u64,
+            span: debug_span!(parent: None, "process", %pid, title = field::Empty),
         }
     }
 
     pub(super) fn set_pid(&mut self, pid: WasiProcessId) {
         self.pid = pid;
+        self.span.record("pid", field::display(pid));
     }
 
     /// Gets the process ID of this process
@@ -534,6 +622,12 @@ impl WasiProcess {
 
         // The wait finished should be the process version if its the main thread
         let mut inner = self.inner.0.lock().unwrap();
+        let max_threads = inner.rlimits.nproc.rlim_cur;
+        if !is_main && u64::from(inner.thread_count) >= max_threads {
+            return Err(ControlPlaneError::TaskLimitReached {
+                max: usize::try_from(max_threads).unwrap_or(usize::MAX),
+            });
+        }
         let finished = if is_main {
             self.finished.clone()
         } else {
@@ -597,6 +691,137 @@ impl WasiProcess {
         signal_process_internal(&self.inner, signal);
     }
 
+    /// Returns the signals that were sent to the threads of this process but
+    /// were not delivered yet (for instance because they are blocked), in
+    /// the order of their signal numbers
+    pub fn pending_signals(&self) -> Vec<Signal> {
+        let inner = self.inner.0.lock().unwrap();
+        let mut ret: Vec<Signal> = inner
+            .threads
+            .values()
+            .flat_map(|thread| thread.pending_signals())
+            .collect();
+        ret.sort_by_key(|signal| *signal as u8);
+        ret.dedup();
+        ret
+    }
+
+    /// Returns the title of the process (the equivalent of what
+    /// `setproctitle` changes)
+    pub fn title(&self) -> String {
+        self.inner.0.lock().unwrap().title.clone()
+    }
+
+    /// Changes the title of the process
+    pub fn set_title(&self, title: impl Into<String>) {
+        let title = title.into();
+        self.span.record("title", title.as_str());
+        self.inner.0.lock().unwrap().title = title;
+    }
+
+    /// Returns the span that the threads of the process run in
+    pub fn span(&self) -> &Span {
+        &self.span
+    }
+
+    /// Pauses the process from the outside (which is independent of the
+    /// job control signals), every thread of the process goes into a deep
+    /// sleep at its next syscall until [`WasiProcess::resume`] is called. Threads that are
+    /// busy in the guest keep running until they make a syscall and threads
+    /// that are already blocked in a syscall only pause once it returns.
+    ///
+    /// A paused process can still be killed with `Sigkill` or terminated.
+    pub fn pause(&self) {
+        let pid = self.pid();
+        tracing::trace!(%pid, "pause");
+        self.inner.0.lock().unwrap().paused = true;
+    }
+
+    /// Resumes a process that was paused with [`WasiProcess::pause`]
+    pub fn resume(&self) {
+        let pid = self.pid();
+        tracing::trace!(%pid, "resume");
+        let mut guard = self.inner.0.lock().unwrap();
+        guard.paused = false;
+        for waker in guard.pause_wakers.drain(..) {
+            waker.wake();
+        }
+    }
+
+    /// Waits until the process is no longer paused (see
+    /// [`WasiProcess::pause`])
+    pub(crate) fn wait_for_resume(&self) -> impl Future<Output = ()> + Send + Sync + 'static {
+        let inner = self.inner.clone();
+        std::future::poll_fn(move |cx| {
+            let mut guard = inner.0.lock().unwrap();
+            if !guard.paused {
+                return Poll::Ready(());
+            }
+            if !guard.pause_wakers.iter().any(|w| w.will_wake(cx.waker())) {
+                guard.pause_wakers.push(cx.waker().clone());
+            }
+            Poll::Pending
+        })
+    }
+
+    /// Returns true if the process was paused with [`WasiProcess::pause`]
+    pub fn is_paused(&self) -> bool {
+        self.inner.0.lock().unwrap().paused
+    }
+
+    /// Returns how the process was forked from its parent, or `None` when
+    /// it was spawned in another way
+    pub fn fork_mode(&self) -> Option<ForkMode> {
+        self.inner.0.lock().unwrap().fork_mode
+    }
+
+    /// Returns true while the process is a vfork child that has neither
+    /// called `proc_exec` nor exited, its parent is suspended until then
+    pub fn is_vfork_pending(&self) -> bool {
+        self.inner.0.lock().unwrap().vfork_pending
+    }
+
+    /// Records that the process was just forked from its parent
+    pub(crate) fn set_forked(&self, mode: ForkMode) {
+        let mut inner = self.inner.0.lock().unwrap();
+        inner.fork_mode = Some(mode);
+        inner.vfork_pending = mode == ForkMode::VFork;
+    }
+
+    /// Records that the vfork child has called `proc_exec` or exited, which
+    /// gives the control back to its parent
+    pub(crate) fn finish_vfork(&self) {
+        self.inner.0.lock().unwrap().vfork_pending = false;
+    }
+
+    /// Returns how often the threads of the process went into a deep sleep
+    /// and were resumed, a process that does so at a high rate spends much
+    /// of its time unwinding and rewinding its stacks
+    pub fn deep_sleep_stats(&self) -> DeepSleepStats {
+        DeepSleepStats {
+            sleeps: self.deep_sleeps.sleeps.load(Ordering::Relaxed),
+            resumes: self.deep_sleeps.resumes.load(Ordering::Relaxed),
+        }
+    }
+
+    /// How long ago the process was created, on the monotonic clock so that
+    /// changes to the wall clock don't affect it
+    pub fn uptime(&self) -> Duration {
+        let now =
+            platform_clock_time_get(Snapshot0Clockid::Monotonic, 1).unwrap_or_default() as u64;
+        Duration::from_nanos(now.saturating_sub(self.started))
+    }
+
+    /// Records that a thread of the process went into a deep sleep
+    pub(crate) fn record_deep_sleep(&self) {
+        self.deep_sleeps.sleeps.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Records that a thread of the process was resumed after a deep sleep
+    pub(crate) fn record_deep_sleep_resume(&self) {
+        self.deep_sleeps.resumes.fetch_add(1, Ordering::Relaxed);
+    }
+
     /// Takes a snapshot of the process and disables journaling returning
     /// a future that can be waited on for the snapshot to complete
     ///
@@ -756,6 +981,47 @@ impl WasiProcess {
         );
     }
 
+    /// Returns true if the blocking syscalls that `signal` interrupts are
+    /// restarted once it was handled instead of failing with `Errno::Intr`
+    /// (`SIGKILL` and `SIGSTOP` never restart anything)
+    pub fn signal_restarts(&self, signal: Signal) -> bool {
+        if matches!(signal, Signal::Sigkill | Signal::Sigstop) {
+            return false;
+        }
+        let inner = self.inner.0.lock().unwrap();
+        inner
+            .restart_signals
+            .checked_shr(signal as u32)
+            .unwrap_or(0)
+            & 1
+            == 1
+    }
+
+    /// Changes whether the blocking syscalls that `signal` interrupts are
+    /// restarted once it was handled (the equivalent of installing its
+    /// handler with `SA_RESTART`)
+    pub fn set_signal_restarts(&self, signal: Signal, restart: bool) {
+        let mut inner = self.inner.0.lock().unwrap();
+        let bit = 1u64.checked_shl(signal as u32).unwrap_or(0);
+        if restart {
+            inner.restart_signals |= bit;
+        } else {
+            inner.restart_signals &= !bit;
+        }
+    }
+
+    /// Returns the soft and hard limit of a resource of this process
+    pub fn rlimit(&self, resource: RlimitResource) -> Result<Rlimit, Errno> {
+        let inner = self.inner.0.lock().unwrap();
+        inner.rlimits.get(resource)
+    }
+
+    /// Changes the limits of a resource of this process
+    pub fn set_rlimit(&self, resource: RlimitResource, rlimit: Rlimit) -> Result<(), Errno> {
+        let mut inner = self.inner.0.lock().unwrap();
+        inner.rlimits.set(resource, rlimit)
+    }
+
     /// Returns the number of active threads for this process
     pub fn active_threads(&self) -> u32 {
         let inner = self.inner.0.lock().unwrap();
@@ -773,6 +1039,23 @@ impl WasiProcess {
         self.finished.status().into_finished()
     }
 
+    /// Records the signal that is terminating this process, only the
+    /// first terminating signal is kept
+    pub fn record_exit_signal(&self, signal: Signal) {
+        let mut inner = self.inner.0.lock().unwrap();
+        inner.exit_signal.get_or_insert(signal);
+    }
+
+    /// Determines how the process terminated given the exit code it
+    /// finished with
+    pub fn exit_reason(&self, exit_code: ExitCode) -> ExitReason {
+        let inner = self.inner.0.lock().unwrap();
+        match inner.exit_signal {
+            Some(signal) => ExitReason::Signaled(exit_code, signal),
+            None => ExitReason::Exited(exit_code),
+        }
+    }
+
     /// Waits for all the children to be finished
     pub async fn join_children(&mut self) -> Option<Result<ExitCode, Arc<WasiRuntimeError>>> {
         let _guard = WasiProcessWait::new(self);
@@ -785,21 +1068,42 @@ impl WasiProcess {
         }
         let mut waits = Vec::new();
         for child in children {
-            if let Some(process) = self.compute.must_upgrade().get_process(child.pid) {
-                let inner = self.inner.clone();
-                waits.push(async move {
-                    let join = process.join().await;
-                    let mut inner = inner.0.lock().unwrap();
-                    inner.children.retain(|a| a.pid != child.pid);
-                    join
-                })
-            }
+            let inner = self.inner.clone();
+            waits.push(async move {
+                let join = child.join().await;
+                let mut inner = inner.0.lock().unwrap();
+                inner.children.retain(|a| a.pid != child.pid);
+                join
+            })
         }
         futures::future::join_all(waits).await.into_iter().next()
     }
 
+    /// Returns the first child that has already finished (if any) without
+    /// waiting, the child is reaped and will no longer be joinable
+    pub fn try_join_any_child(&mut self) -> Result<Option<(WasiProcessId, ExitReason)>, Errno> {
+        let mut inner = self.inner.0.lock().unwrap();
+        if inner.children.is_empty() {
+            return Err(Errno::Child);
+        }
+
+        let finished = inner
+            .children
+            .iter()
+            .find_map(|child| child.try_join().map(|res| (child.clone(), res)));
+        let Some((child, res)) = finished else {
+            return Ok(None);
+        };
+        inner.children.retain(|a| a.pid != child.pid);
+        drop(inner);
+
+        let code =
+            res.unwrap_or_else(|e| e.as_exit_code().unwrap_or_else(|| Errno::Canceled.into()));
+        Ok(Some((child.pid, child.exit_reason(code))))
+    }
+
     /// Waits for any of the children to finished
-    pub async fn join_any_child(&mut self) -> Result<Option<(WasiProcessId, ExitCode)>, Errno> {
+    pub async fn join_any_child(&mut self) -> Result<Option<(WasiProcessId, ExitReason)>, Errno> {
         let _guard = WasiProcessWait::new(self);
         let children: Vec<_> = {
             let inner = self.inner.0.lock().unwrap();
@@ -811,15 +1115,13 @@ impl WasiProcess {
 
         let mut waits = Vec::new();
         for child in children {
-            if let Some(process) = self.compute.must_upgrade().get_process(child.pid) {
-                let inner = self.inner.clone();
-                waits.push(async move {
-                    let join = process.join().await;
-                    let mut inner = inner.0.lock().unwrap();
-                    inner.children.retain(|a| a.pid != child.pid);
-                    (child, join)
-                })
-            }
+            let inner = self.inner.clone();
+            waits.push(async move {
+                let join = child.join().await;
+                let mut inner = inner.0.lock().unwrap();
+                inner.children.retain(|a| a.pid != child.pid);
+                (child, join)
+            })
         }
         let (child, res) = futures::future::select_all(waits.into_iter().map(Box::pin))
             .await
@@ -828,11 +1130,17 @@ impl WasiProcess {
         let code =
             res.unwrap_or_else(|e| e.as_exit_code().unwrap_or_else(|| Errno::Canceled.into()));
 
-        Ok(Some((child.pid, code)))
+        Ok(Some((child.pid, child.exit_reason(code))))
     }
 
     /// Terminate the process and all its threads
     pub fn terminate(&self, exit_code: ExitCode) {
+        // The pid is freed before the threads finish so that whoever waits
+        // on the process no longer finds it afterwards
+        if let Some(control_plane) = self.compute.upgrade() {
+            control_plane.remove_process(self.pid);
+        }
+
         // FIXME: this is wrong, threads might still be running!
         // Need special logic for the main thread.
         let guard = self.inner.0.lock().unwrap();
@@ -900,3 +1208,74 @@ impl SignalHandlerAbi for WasiProcess {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::os::task::control_plane::{ControlPlaneConfig, WasiControlPlane};
+
+    use super::*;
+
+    #[test]
+    fn test_pending_signals_are_the_undelivered_ones() {
+        let plane = WasiControlPlane::new(ControlPlaneConfig::new());
+        let process = plane.new_process(ModuleHash::random()).unwrap();
+        let main = process
+            .new_thread(WasiMemoryLayout::default(), ThreadStartType::MainThread)
+            .unwrap();
+        let other = process
+            .new_thread(
+                WasiMemoryLayout::default(),
+                ThreadStartType::ThreadSpawn { start_ptr: 0 },
+            )
+            .unwrap();
+        assert!(process.pending_signals().is_empty());
+
+        // Block the signals on one of the threads so they stay queued
+        let blocked = (1 << Signal::Sigusr1 as u8) | (1 << Signal::Sighup as u8);
+        main.set_signal_mask(blocked);
+        process.signal_process(Signal::Sigusr1);
+        process.signal_thread(&main.tid(), Signal::Sighup);
+        process.signal_thread(&other.tid(), Signal::Sigterm);
+
+        // Signals sent to several threads are only listed once
+        assert_eq!(
+            process.pending_signals(),
+            vec![Signal::Sighup, Signal::Sigusr1, Signal::Sigterm]
+        );
+
+        // Delivering the signals that are not blocked takes them off the list
+        assert_eq!(other.pop_signals(), vec![Signal::Sigusr1, Signal::Sigterm]);
+        assert!(main.pop_signals().is_empty());
+        assert_eq!(
+            process.pending_signals(),
+            vec![Signal::Sighup, Signal::Sigusr1]
+        );
+    }
+
+    #[test]
+    fn test_deep_sleeps_are_counted_per_process() {
+        let plane = WasiControlPlane::new(ControlPlaneConfig::new());
+        let process = plane.new_process(ModuleHash::random()).unwrap();
+        let other = plane.new_process(ModuleHash::random()).unwrap();
+        assert_eq!(process.deep_sleep_stats(), DeepSleepStats::default());
+
+        process.record_deep_sleep();
+        process.record_deep_sleep();
+        process.record_deep_sleep_resume();
+
+        // The counters are shared by the clones of the process
+        let listed = plane
+            .processes()
+            .into_iter()
+            .find(|p| p.pid() == process.pid())
+            .unwrap();
+        assert_eq!(
+            listed.deep_sleep_stats(),
+            DeepSleepStats {
+                sleeps: 2,
+                resumes: 1
+            }
+        );
+        assert_eq!(other.deep_sleep_stats(), DeepSleepStats::default());
+    }
+}
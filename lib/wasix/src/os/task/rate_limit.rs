@@ -0,0 +1,126 @@
+use std::{num::NonZeroU32, sync::Mutex, time::Duration};
+
+use wasmer_wasix_types::wasi::Snapshot0Clockid;
+
+use crate::syscalls::platform_clock_time_get;
+
+/// Token bucket that limits how many syscalls the threads of a process
+/// can make per second, see [`WasiEnvBuilder::max_syscalls_per_second`].
+///
+/// The bucket holds up to a second worth of tokens so short bursts run at
+/// full speed. Once it is empty every syscall borrows against the tokens
+/// that have yet to be refilled and has to wait until its token would
+/// have been available, which spreads the syscalls of all the threads out
+/// evenly at the configured rate. The debt is capped at a second worth of
+/// tokens as well, so that a burst of threads can not push the wait of the
+/// syscalls that follow out without bounds.
+///
+/// [`WasiEnvBuilder::max_syscalls_per_second`]: crate::WasiEnvBuilder::max_syscalls_per_second
+#[derive(Debug)]
+pub struct SyscallRateLimiter {
+    per_second: NonZeroU32,
+    bucket: Mutex<TokenBucket>,
+}
+
+#[derive(Debug)]
+struct TokenBucket {
+    /// Number of tokens left, negative when syscalls are waiting for
+    /// tokens that have not been refilled yet (never below minus the rate)
+    tokens: f64,
+    /// Monotonic time (in nanoseconds) of the last refill
+    refilled_at: u64,
+}
+
+impl SyscallRateLimiter {
+    pub fn new(per_second: NonZeroU32) -> Self {
+        Self::new_at(per_second, Self::now())
+    }
+
+    fn new_at(per_second: NonZeroU32, now: u64) -> Self {
+        Self {
+            per_second,
+            bucket: Mutex::new(TokenBucket {
+                tokens: per_second.get() as f64,
+                refilled_at: now,
+            }),
+        }
+    }
+
+    /// Maximum number of syscalls per second
+    pub fn per_second(&self) -> NonZeroU32 {
+        self.per_second
+    }
+
+    /// Takes a token for a syscall and returns how long the syscall has to
+    /// wait before it may be processed (if the bucket was empty)
+    pub fn acquire(&self) -> Option<Duration> {
+        self.acquire_at(Self::now())
+    }
+
+    fn acquire_at(&self, now: u64) -> Option<Duration> {
+        let rate = self.per_second.get() as f64;
+        let mut bucket = self.bucket.lock().unwrap();
+
+        let elapsed = now.saturating_sub(bucket.refilled_at);
+        bucket.refilled_at = bucket.refilled_at.max(now);
+        bucket.tokens = (bucket.tokens + elapsed as f64 * rate / 1_000_000_000.0).min(rate);
+
+        bucket.tokens = (bucket.tokens - 1.0).max(-rate);
+        if bucket.tokens >= 0.0 {
+            None
+        } else {
+            Some(Duration::from_secs_f64(-bucket.tokens / rate))
+        }
+    }
+
+    fn now() -> u64 {
+        platform_clock_time_get(Snapshot0Clockid::Monotonic, 1_000_000).unwrap() as u64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECOND: u64 = 1_000_000_000;
+
+    #[test]
+    fn bursts_are_allowed_up_to_the_rate() {
+        let limiter = SyscallRateLimiter::new_at(NonZeroU32::new(4).unwrap(), 0);
+        for _ in 0..4 {
+            assert_eq!(limiter.acquire_at(0), None);
+        }
+
+        // Every syscall beyond the burst waits for its own token
+        assert_eq!(limiter.acquire_at(0), Some(Duration::from_millis(250)));
+        assert_eq!(limiter.acquire_at(0), Some(Duration::from_millis(500)));
+    }
+
+    #[test]
+    fn debt_is_capped_at_a_second() {
+        let limiter = SyscallRateLimiter::new_at(NonZeroU32::new(4).unwrap(), 0);
+        for _ in 0..100 {
+            limiter.acquire_at(0);
+        }
+        assert_eq!(limiter.acquire_at(0), Some(Duration::from_secs(1)));
+
+        // Once the second has passed the bucket is back to empty
+        assert_eq!(limiter.acquire_at(SECOND), Some(Duration::from_millis(250)));
+    }
+
+    #[test]
+    fn tokens_are_refilled_over_time() {
+        let limiter = SyscallRateLimiter::new_at(NonZeroU32::new(10).unwrap(), 0);
+        for _ in 0..10 {
+            limiter.acquire_at(0);
+        }
+        assert!(limiter.acquire_at(SECOND / 20).is_some());
+
+        // The bucket never holds more than a second worth of tokens
+        let later = 10 * SECOND;
+        for _ in 0..10 {
+            assert_eq!(limiter.acquire_at(later), None);
+        }
+        assert!(limiter.acquire_at(later).is_some());
+    }
+}
@@ -0,0 +1,121 @@
+use wasmer_wasix_types::wasi::{Errno, RLIM_INFINITY, Rlimit, RlimitResource};
+
+/// The resource limits of a process, which are inherited by the processes
+/// that it forks or spawns and kept when it execs another program
+#[derive(Debug, Clone, Default)]
+pub struct WasiResourceLimits {
+    /// CPU time in seconds, this is not enforced so it is always unlimited
+    pub cpu: Rlimit,
+    /// Number of threads that the process can run at the same time
+    pub nproc: Rlimit,
+    /// One more than the highest file descriptor that can be opened
+    pub nofile: Rlimit,
+    /// Size of the linear memory in bytes, this is not enforced so it is
+    /// always unlimited
+    pub address_space: Rlimit,
+}
+
+impl WasiResourceLimits {
+    /// Returns the soft and hard limit of a resource
+    pub fn get(&self, resource: RlimitResource) -> Result<Rlimit, Errno> {
+        Ok(*self.limit(resource)?)
+    }
+
+    /// Changes the limits of a resource, the soft limit can not exceed
+    /// the hard limit and the hard limit can only ever be lowered
+    ///
+    /// The limits that are not enforced can't be set to anything other than
+    /// unlimited, rather than silently not being applied.
+    pub fn set(&mut self, resource: RlimitResource, new: Rlimit) -> Result<(), Errno> {
+        if new.rlim_cur > new.rlim_max {
+            return Err(Errno::Inval);
+        }
+        let limit = self.limit_mut(resource)?;
+        if matches!(resource, RlimitResource::Cpu | RlimitResource::As)
+            && (new.rlim_cur != RLIM_INFINITY || new.rlim_max != RLIM_INFINITY)
+        {
+            return Err(Errno::Notsup);
+        }
+        if new.rlim_max > limit.rlim_max {
+            return Err(Errno::Perm);
+        }
+        *limit = new;
+        Ok(())
+    }
+
+    fn limit(&self, resource: RlimitResource) -> Result<&Rlimit, Errno> {
+        Ok(match resource {
+            RlimitResource::Cpu => &self.cpu,
+            RlimitResource::Nproc => &self.nproc,
+            RlimitResource::Nofile => &self.nofile,
+            RlimitResource::As => &self.address_space,
+            RlimitResource::Unknown => return Err(Errno::Inval),
+        })
+    }
+
+    fn limit_mut(&mut self, resource: RlimitResource) -> Result<&mut Rlimit, Errno> {
+        Ok(match resource {
+            RlimitResource::Cpu => &mut self.cpu,
+            RlimitResource::Nproc => &mut self.nproc,
+            RlimitResource::Nofile => &mut self.nofile,
+            RlimitResource::As => &mut self.address_space,
+            RlimitResource::Unknown => return Err(Errno::Inval),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lowering_the_hard_limit_is_irreversible() {
+        let mut limits = WasiResourceLimits::default();
+        assert_eq!(
+            limits.get(RlimitResource::Nofile).unwrap().rlim_max,
+            RLIM_INFINITY
+        );
+
+        let lowered = Rlimit {
+            rlim_cur: 8,
+            rlim_max: 16,
+        };
+        limits.set(RlimitResource::Nofile, lowered).unwrap();
+        assert_eq!(limits.get(RlimitResource::Nofile).unwrap(), lowered);
+
+        let raised = Rlimit {
+            rlim_cur: 8,
+            rlim_max: 32,
+        };
+        assert_eq!(limits.set(RlimitResource::Nofile, raised), Err(Errno::Perm));
+
+        let above_hard = Rlimit {
+            rlim_cur: 17,
+            rlim_max: 16,
+        };
+        assert_eq!(
+            limits.set(RlimitResource::Nofile, above_hard),
+            Err(Errno::Inval)
+        );
+        assert_eq!(limits.get(RlimitResource::Nofile).unwrap(), lowered);
+        assert_eq!(limits.get(RlimitResource::Unknown), Err(Errno::Inval));
+    }
+
+    #[test]
+    fn test_limits_that_are_not_enforced_stay_unlimited() {
+        let mut limits = WasiResourceLimits::default();
+        let unlimited = Rlimit {
+            rlim_cur: RLIM_INFINITY,
+            rlim_max: RLIM_INFINITY,
+        };
+        let lowered = Rlimit {
+            rlim_cur: 60,
+            rlim_max: RLIM_INFINITY,
+        };
+        for resource in [RlimitResource::Cpu, RlimitResource::As] {
+            assert_eq!(limits.set(resource, lowered), Err(Errno::Notsup));
+            assert_eq!(limits.set(resource, unlimited), Ok(()));
+            assert_eq!(limits.get(resource).unwrap(), unlimited);
+        }
+    }
+}
@@ -10,7 +10,7 @@ use crate::{
 };
 use bytes::{Bytes, BytesMut};
 use serde::{Deserialize, Serialize};
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::{
     collections::HashMap,
     ops::{Deref, DerefMut},
@@ -124,6 +124,11 @@ impl WasiThread {
         self.start
     }
 
+    /// Returns true if a rewind of any type has been queued
+    pub(crate) fn has_rewind(&self) -> bool {
+        self.rewind.is_some()
+    }
+
     /// Returns true if a rewind of a particular type has been queued
     /// for processed by a rewind operation
     pub(crate) fn has_rewind_of_type(&self, type_: HandleRewindType) -> bool {
@@ -159,6 +164,32 @@ impl WasiThread {
         self.state.deep_sleeping.load(Ordering::SeqCst)
     }
 
+    /// Sets a flag that tells the rewound syscall that the thread deep
+    /// slept before the syscall was processed
+    pub(crate) fn set_pending_operations_sleep(&self, val: bool) {
+        self.state
+            .pending_operations_sleep
+            .store(val, Ordering::SeqCst);
+    }
+
+    /// Takes the flag set by [`WasiThread::set_pending_operations_sleep`]
+    pub(crate) fn take_pending_operations_sleep(&self) -> bool {
+        self.state
+            .pending_operations_sleep
+            .swap(false, Ordering::SeqCst)
+    }
+
+    /// Sets a flag that tells the rewound syscall that the thread deep
+    /// slept before retrying host IO that would have blocked
+    pub(crate) fn set_blocking_io_retry(&self, val: bool) {
+        self.state.blocking_io_retry.store(val, Ordering::SeqCst);
+    }
+
+    /// Takes the flag set by [`WasiThread::set_blocking_io_retry`]
+    pub(crate) fn take_blocking_io_retry(&self) -> bool {
+        self.state.blocking_io_retry.swap(false, Ordering::SeqCst)
+    }
+
     /// Sets a flag that tells others that this thread is currently
     /// check pointing itself
     #[cfg(feature = "journal")]
@@ -239,11 +270,27 @@ struct WasiThreadState {
     pid: WasiProcessId,
     id: WasiThreadId,
     signals: Mutex<(Vec<Signal>, Vec<Waker>)>,
+    /// Signals that are blocked from being delivered to this thread (one
+    /// bit per signal number), blocked signals remain pending
+    signal_mask: AtomicU64,
+    /// Signal mask to restore once a wait with a temporary mask is over
+    /// (see [`WasiThread::set_temporary_signal_mask`]), it is kept here so
+    /// that it survives a deep sleep
+    saved_signal_mask: Mutex<Option<u64>>,
     stack: Mutex<ThreadStack>,
     status: Arc<OwnedTaskStatus>,
     #[cfg(feature = "journal")]
     check_pointing: AtomicBool,
     deep_sleeping: AtomicBool,
+    /// Set while the thread deep sleeps before a syscall is processed (see
+    /// [`WasiEnv::do_pending_operations_or_sleep`]), the rewind then belongs
+    /// to that sleep rather than to the syscall itself
+    ///
+    /// [`WasiEnv::do_pending_operations_or_sleep`]: crate::WasiEnv::do_pending_operations_or_sleep
+    pending_operations_sleep: AtomicBool,
+    /// Set while the thread deep sleeps before it retries host IO that would
+    /// have blocked, the rewind then carries the state of the retries
+    blocking_io_retry: AtomicBool,
 
     // Registers the task termination with the ControlPlane on drop.
     // Never accessed, since it's a drop guard.
@@ -269,10 +316,14 @@ impl WasiThread {
                 id,
                 status,
                 signals: Mutex::new((Vec::new(), Vec::new())),
+                signal_mask: AtomicU64::new(0),
+                saved_signal_mask: Mutex::new(None),
                 stack: Mutex::new(ThreadStack::default()),
                 #[cfg(feature = "journal")]
                 check_pointing: AtomicBool::new(false),
                 deep_sleeping: AtomicBool::new(false),
+                pending_operations_sleep: AtomicBool::new(false),
+                blocking_io_retry: AtomicBool::new(false),
                 _task_count_guard: guard,
             }),
             layout,
@@ -365,6 +416,72 @@ impl WasiThread {
         false
     }
 
+    /// Returns the signals that were sent to this thread but were not
+    /// delivered yet (including the ones that are blocked)
+    pub fn pending_signals(&self) -> Vec<Signal> {
+        self.state.signals.lock().unwrap().0.clone()
+    }
+
+    /// Returns the mask of signals that are currently blocked
+    pub fn signal_mask(&self) -> u64 {
+        self.state.signal_mask.load(Ordering::Acquire)
+    }
+
+    /// Replaces the mask of blocked signals and returns the previous one
+    ///
+    /// Pending signals that are no longer blocked will wake up anyone
+    /// waiting on this thread so that they get processed.
+    pub fn set_signal_mask(&self, mask: u64) -> u64 {
+        let mut guard = self.state.signals.lock().unwrap();
+        let prev = self.state.signal_mask.swap(mask, Ordering::AcqRel);
+        if guard.0.iter().any(|sig| !Self::is_masked(mask, *sig)) {
+            guard.1.drain(..).for_each(|w| w.wake());
+        }
+        prev
+    }
+
+    /// Replaces the mask of blocked signals for the duration of a wait (the
+    /// way `ppoll` does), until [`WasiThread::restore_signal_mask`] puts the
+    /// mask that was there before back. Replacing it again before that
+    /// keeps the mask to restore as it is.
+    pub(crate) fn set_temporary_signal_mask(&self, mask: u64) {
+        let mut saved = self.state.saved_signal_mask.lock().unwrap();
+        let prev = self.set_signal_mask(mask);
+        saved.get_or_insert(prev);
+    }
+
+    /// Restores the mask that [`WasiThread::set_temporary_signal_mask`]
+    /// replaced, if any
+    pub(crate) fn restore_signal_mask(&self) {
+        let mut saved = self.state.saved_signal_mask.lock().unwrap();
+        if let Some(prev) = saved.take() {
+            self.set_signal_mask(prev);
+        }
+    }
+
+    /// Returns true if the signal is currently blocked from being delivered
+    pub fn is_signal_blocked(&self, signal: Signal) -> bool {
+        Self::is_masked(self.signal_mask(), signal)
+    }
+
+    fn is_masked(mask: u64, signal: Signal) -> bool {
+        // SIGKILL and SIGSTOP can never be blocked
+        if signal == Signal::Sigkill || signal == Signal::Sigstop {
+            return false;
+        }
+        mask.checked_shr(signal as u32).unwrap_or(0) & 1 == 1
+    }
+
+    /// Removes the signals that are not blocked from the pending list
+    fn take_deliverable(&self, pending: &mut Vec<Signal>) -> Vec<Signal> {
+        let mask = self.signal_mask();
+        let (ret, blocked) = pending
+            .drain(..)
+            .partition(|sig| !Self::is_masked(mask, *sig));
+        *pending = blocked;
+        ret
+    }
+
     /// Waits for a signal to arrive
     pub async fn wait_for_signal(&self) {
         // This poller will process any signals when the main working function is idle
@@ -389,8 +506,7 @@ impl WasiThread {
     /// Returns all the signals that are waiting to be processed
     pub fn pop_signals_or_subscribe(&self, waker: &Waker) -> Option<Vec<Signal>> {
         let mut guard = self.state.signals.lock().unwrap();
-        let mut ret = Vec::new();
-        std::mem::swap(&mut ret, &mut guard.0);
+        let ret = self.take_deliverable(&mut guard.0);
         match ret.is_empty() {
             true => {
                 if !guard.1.iter().any(|w| w.will_wake(waker)) {
@@ -412,7 +528,31 @@ impl WasiThread {
     /// Returns all the signals that are waiting to be processed
     pub fn has_signals_or_subscribe(&self, waker: &Waker) -> bool {
         let mut guard = self.state.signals.lock().unwrap();
-        let has_signals = !guard.0.is_empty();
+        let has_signals = guard.0.iter().any(|sig| !self.is_signal_blocked(*sig));
+        if !has_signals && !guard.1.iter().any(|w| w.will_wake(waker)) {
+            guard.1.push(waker.clone());
+        }
+        has_signals
+    }
+
+    /// Takes the pending signal with the lowest number among the ones in
+    /// `mask` (one bit per signal number), even when it is blocked
+    pub fn take_signal_in(&self, mask: u64) -> Option<Signal> {
+        let mut guard = self.state.signals.lock().unwrap();
+        let (idx, _) = guard
+            .0
+            .iter()
+            .enumerate()
+            .filter(|(_, sig)| Self::is_masked(mask, **sig))
+            .min_by_key(|(_, sig)| **sig as u8)?;
+        Some(guard.0.remove(idx))
+    }
+
+    /// Returns true if one of the signals in `mask` is pending, otherwise
+    /// the waker is woken up when the next signal arrives
+    pub fn has_signal_in_or_subscribe(&self, mask: u64, waker: &Waker) -> bool {
+        let mut guard = self.state.signals.lock().unwrap();
+        let has_signals = guard.0.iter().any(|sig| Self::is_masked(mask, *sig));
         if !has_signals && !guard.1.iter().any(|w| w.will_wake(waker)) {
             guard.1.push(waker.clone());
         }
@@ -422,9 +562,7 @@ impl WasiThread {
     /// Returns all the signals that are waiting to be processed
     pub fn pop_signals(&self) -> Vec<Signal> {
         let mut guard = self.state.signals.lock().unwrap();
-        let mut ret = Vec::new();
-        std::mem::swap(&mut ret, &mut guard.0);
-        ret
+        self.take_deliverable(&mut guard.0)
     }
 
     /// Adds a stack snapshot and removes dead ones
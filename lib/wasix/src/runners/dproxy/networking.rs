@@ -7,8 +7,8 @@ use std::{
 };
 
 use virtual_net::{
-    IpCidr, IpRoute, NetworkError, StreamSecurity, VirtualIcmpSocket, VirtualNetworking,
-    VirtualRawSocket, VirtualTcpListener, VirtualTcpSocket, VirtualUdpSocket,
+    IpCidr, IpRoute, NetworkError, NetworkInterface, StreamSecurity, VirtualIcmpSocket,
+    VirtualNetworking, VirtualRawSocket, VirtualTcpListener, VirtualTcpSocket, VirtualUdpSocket,
     host::LocalNetworking, loopback::LoopbackNetworking,
 };
 
@@ -112,6 +112,11 @@ impl VirtualNetworking for LocalWithLoopbackNetworking {
         self.inner_networking.mac().await
     }
 
+    /// Lists the network interfaces along with their addresses
+    async fn interfaces(&self) -> Result<Vec<NetworkInterface>, NetworkError> {
+        self.inner_networking.interfaces().await
+    }
+
     /// Adds a default gateway to the routing table
     async fn gateway_set(&self, ip: IpAddr) -> Result<(), NetworkError> {
         self.inner_networking.gateway_set(ip).await
@@ -1,21 +1,22 @@
 //! WebC container support for running WASI modules
 
-use std::{path::PathBuf, sync::Arc};
+use std::{num::NonZeroU32, path::PathBuf, sync::Arc};
 
 #[cfg(feature = "napi-v8")]
 use std::borrow::Cow;
 
 use anyhow::{Context, Error};
 use tracing::Instrument;
-use virtual_fs::{ArcBoxFile, FileSystem, TmpFileSystem, VirtualFile};
+use virtual_fs::{ArcBoxFile, FileSystem, MergedOutputFile, TmpFileSystem, VirtualFile};
 use wasmer::{Engine, Module};
 use wasmer_types::ModuleHash;
 use webc::metadata::{Command, annotations::Wasi};
 
 use crate::{
-    Runtime, WasiEnvBuilder, WasiError, WasiRuntimeError,
+    AbsentStdin, Runtime, StreamMode, WasiEnvBuilder, WasiError, WasiRuntimeError,
     bin_factory::BinaryPackage,
     capabilities::Capabilities,
+    fs::ReopenFailure,
     journal::{DynJournal, DynReadableJournal, SnapshotTrigger},
     runners::{MappedDirectory, MountedDirectory, wasi_common::CommonWasiOptions},
     runtime::task_manager::VirtualTaskManagerExt,
@@ -30,8 +31,25 @@ use wasmer_napi::NapiCtx;
 pub struct WasiRunner {
     wasi: CommonWasiOptions,
     stdin: Option<ArcBoxFile>,
+    absent_stdin: AbsentStdin,
+    stdin_mode: StreamMode,
     stdout: Option<ArcBoxFile>,
+    stdout_mode: StreamMode,
     stderr: Option<ArcBoxFile>,
+    stderr_mode: StreamMode,
+    stdout_lines: Option<LineCallback>,
+    stderr_lines: Option<LineCallback>,
+}
+
+/// Function that receives the output of the guest one line at a time.
+#[derive(Clone)]
+#[allow(clippy::type_complexity)]
+struct LineCallback(Arc<dyn Fn(&[u8]) + Send + Sync + 'static>);
+
+impl std::fmt::Debug for LineCallback {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LineCallback").finish_non_exhaustive()
+    }
 }
 
 pub enum PackageOrHash<'a> {
@@ -180,6 +198,20 @@ impl WasiRunner {
         self
     }
 
+    /// Coalesces the writes to files that the instance opens for writing in
+    /// a buffer of `size` bytes, see [`WasiEnvBuilder::write_buffer_size`].
+    pub fn with_write_buffer_size(&mut self, size: usize) -> &mut Self {
+        self.wasi.write_buffer_size = Some(size);
+        self
+    }
+
+    /// Limits the number of syscalls per second that every process of the
+    /// instance can make, see [`WasiEnvBuilder::max_syscalls_per_second`].
+    pub fn with_max_syscalls_per_second(&mut self, limit: NonZeroU32) -> &mut Self {
+        self.wasi.max_syscalls_per_second = Some(limit);
+        self
+    }
+
     #[cfg(feature = "napi-v8")]
     pub fn with_napi_ctx(
         &mut self,
@@ -251,11 +283,33 @@ impl WasiRunner {
         self
     }
 
+    /// Decides what happens to the file descriptors that are restored from
+    /// a journal when their file can't be opened anymore, see
+    /// [`WasiEnvBuilder::with_journal_reopen_failure`]
+    pub fn with_journal_reopen_failure(&mut self, on_failure: ReopenFailure) -> &mut Self {
+        self.wasi.journal_reopen_failure = on_failure;
+        self
+    }
+
     pub fn with_stdin(&mut self, stdin: Box<dyn VirtualFile + Send + Sync>) -> &mut Self {
         self.stdin = Some(ArcBoxFile::new(stdin));
         self
     }
 
+    /// What `stdin` is when no file was attached with
+    /// [`WasiRunner::with_stdin`], see [`AbsentStdin`].
+    pub fn with_absent_stdin(&mut self, absent_stdin: AbsentStdin) -> &mut Self {
+        self.absent_stdin = absent_stdin;
+        self
+    }
+
+    /// How the line endings that the guest reads from `stdin` are
+    /// translated, see [`WasiEnvBuilder::set_stdin_mode`].
+    pub fn with_stdin_mode(&mut self, mode: StreamMode) -> &mut Self {
+        self.stdin_mode = mode;
+        self
+    }
+
     pub fn with_stdout(&mut self, stdout: Box<dyn VirtualFile + Send + Sync>) -> &mut Self {
         self.stdout = Some(ArcBoxFile::new(stdout));
         self
@@ -266,6 +320,50 @@ impl WasiRunner {
         self
     }
 
+    /// Sends `stdout` and `stderr` to `sink` as a single stream, in the
+    /// order in which the guest wrote them, see
+    /// [`WasiEnvBuilder::set_combined_output`].
+    pub fn with_combined_output(&mut self, sink: Box<dyn VirtualFile + Send + Sync>) -> &mut Self {
+        let (stdout, stderr) = MergedOutputFile::pair(sink);
+        self.stdout = Some(ArcBoxFile::new(Box::new(stdout)));
+        self.stderr = Some(ArcBoxFile::new(Box::new(stderr)));
+        self
+    }
+
+    /// How the line endings that the guest writes to `stdout` are
+    /// translated, see [`WasiEnvBuilder::set_stdout_mode`].
+    pub fn with_stdout_mode(&mut self, mode: StreamMode) -> &mut Self {
+        self.stdout_mode = mode;
+        self
+    }
+
+    /// How the line endings that the guest writes to `stderr` are
+    /// translated, see [`WasiEnvBuilder::set_stderr_mode`].
+    pub fn with_stderr_mode(&mut self, mode: StreamMode) -> &mut Self {
+        self.stderr_mode = mode;
+        self
+    }
+
+    /// Calls `on_line` with every line the guest writes to `stdout`, see
+    /// [`WasiEnvBuilder::set_stdout_lines`].
+    pub fn with_stdout_lines(
+        &mut self,
+        on_line: impl Fn(&[u8]) + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.stdout_lines = Some(LineCallback(Arc::new(on_line)));
+        self
+    }
+
+    /// Calls `on_line` with every line the guest writes to `stderr`, see
+    /// [`WasiEnvBuilder::set_stderr_lines`].
+    pub fn with_stderr_lines(
+        &mut self,
+        on_line: impl Fn(&[u8]) + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.stderr_lines = Some(LineCallback(Arc::new(on_line)));
+        self
+    }
+
     fn ensure_tokio_runtime() -> Option<tokio::runtime::Runtime> {
         #[cfg(feature = "sys-thread")]
         {
@@ -367,6 +465,10 @@ impl WasiRunner {
         self.wasi
             .prepare_webc_env(&mut builder, container_fs, wasi, root_fs)?;
 
+        builder.set_absent_stdin(self.absent_stdin);
+        builder.set_stdin_mode(self.stdin_mode);
+        builder.set_stdout_mode(self.stdout_mode);
+        builder.set_stderr_mode(self.stderr_mode);
         if let Some(stdin) = &self.stdin {
             builder.set_stdin(Box::new(stdin.clone()));
         }
@@ -376,6 +478,12 @@ impl WasiRunner {
         if let Some(stderr) = &self.stderr {
             builder.set_stderr(Box::new(stderr.clone()));
         }
+        if let Some(LineCallback(on_line)) = self.stdout_lines.clone() {
+            builder.set_stdout_lines(move |line| on_line(line));
+        }
+        if let Some(LineCallback(on_line)) = self.stderr_lines.clone() {
+            builder.set_stderr_lines(move |line| on_line(line));
+        }
 
         Ok(builder)
     }
@@ -443,6 +551,7 @@ impl WasiRunner {
 
             builder.with_stop_running_after_snapshot(runner.wasi.stop_running_after_snapshot);
             builder.with_skip_stdio_during_bootstrap(runner.wasi.skip_stdio_during_bootstrap);
+            builder.with_journal_reopen_failure(runner.wasi.journal_reopen_failure);
         }
 
         let env = builder.build()?;
@@ -450,7 +559,8 @@ impl WasiRunner {
         let tasks = runtime.task_manager().clone();
 
         let mut task_handle =
-            crate::bin_factory::spawn_exec_module(module, env, &runtime).context("Spawn failed")?;
+            crate::bin_factory::spawn_exec_module(module, env, &runtime, Default::default())
+                .context("Spawn failed")?;
 
         #[cfg(feature = "ctrlc")]
         task_handle.install_ctrlc_handler();
@@ -561,10 +671,15 @@ impl WasiRunner {
         // See run_wasm above for a possible fix
         let exit_code = tasks.spawn_and_block_on(
             async move {
-                let mut task_handle =
-                    crate::bin_factory::spawn_exec(pkg, &command_name, env, &runtime)
-                        .await
-                        .context("Spawn failed")?;
+                let mut task_handle = crate::bin_factory::spawn_exec(
+                    pkg,
+                    &command_name,
+                    env,
+                    &runtime,
+                    Default::default(),
+                )
+                .await
+                .context("Spawn failed")?;
 
                 #[cfg(feature = "ctrlc")]
                 task_handle.install_ctrlc_handler();
@@ -1,5 +1,6 @@
 use std::{
     collections::HashMap,
+    num::NonZeroU32,
     path::{Path, PathBuf},
     sync::Arc,
 };
@@ -15,7 +16,7 @@ use crate::{
     WasiEnvBuilder,
     bin_factory::BinaryPackage,
     capabilities::Capabilities,
-    fs::{WasiFsRoot, relative_path_hack::RelativeOrAbsolutePathHack},
+    fs::{ReopenFailure, WasiFsRoot, relative_path_hack::RelativeOrAbsolutePathHack},
     journal::{DynJournal, DynReadableJournal, SnapshotTrigger},
 };
 
@@ -46,7 +47,10 @@ pub(crate) struct CommonWasiOptions {
     pub(crate) snapshot_interval: Option<std::time::Duration>,
     pub(crate) stop_running_after_snapshot: bool,
     pub(crate) skip_stdio_during_bootstrap: bool,
+    pub(crate) journal_reopen_failure: ReopenFailure,
     pub(crate) current_dir: Option<PathBuf>,
+    pub(crate) write_buffer_size: Option<usize>,
+    pub(crate) max_syscalls_per_second: Option<NonZeroU32>,
 }
 
 impl CommonWasiOptions {
@@ -97,6 +101,13 @@ impl CommonWasiOptions {
 
         *builder.capabilities_mut() = self.capabilities.clone();
 
+        if let Some(size) = self.write_buffer_size {
+            builder.set_write_buffer_size(size);
+        }
+        if let Some(limit) = self.max_syscalls_per_second {
+            builder.set_max_syscalls_per_second(limit);
+        }
+
         #[cfg(feature = "journal")]
         {
             for journal in &self.read_only_journals {
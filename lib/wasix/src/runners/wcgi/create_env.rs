@@ -35,6 +35,9 @@ pub(crate) async fn default_create_env(conf: CreateEnvConfig) -> anyhow::Result<
             insecure_allow_all: true,
             http_client: HttpClientCapabilityV1::new_allow_all(),
             threading: Default::default(),
+            sandbox: Default::default(),
+            allow_set_hostname: false,
+            allow_perf_counters: false,
         });
     let env = builder.build()?;
 
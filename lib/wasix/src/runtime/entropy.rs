@@ -0,0 +1,140 @@
+//! Sources of the random bytes that are handed out to guests.
+
+use std::{
+    fmt,
+    sync::{Arc, Mutex},
+};
+
+use rand::{Rng, SeedableRng, rngs::StdRng};
+
+/// The entropy source was unable to produce random bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+#[error("no entropy is available")]
+pub struct EntropyUnavailable;
+
+/// Produces the random bytes that are returned by `random_get` and read
+/// from the virtual `/dev/random` and `/dev/urandom`.
+pub trait EntropySource: fmt::Debug + Send + Sync {
+    /// Fills the whole buffer with random bytes.
+    fn fill(&self, buf: &mut [u8]) -> Result<(), EntropyUnavailable>;
+}
+
+pub type DynEntropySource = dyn EntropySource + Send + Sync;
+
+/// Entropy from the random number generator of the host, this is what is
+/// used unless the runtime is configured otherwise.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct HostEntropySource;
+
+impl EntropySource for HostEntropySource {
+    fn fill(&self, buf: &mut [u8]) -> Result<(), EntropyUnavailable> {
+        getrandom::fill(buf).map_err(|_| EntropyUnavailable)
+    }
+}
+
+/// Hands out at most a fixed number of bytes from another entropy source
+/// and then fails every request, which is useful to test how guests deal
+/// with running out of entropy.
+///
+/// A request that only partially fits in what is left also fails and uses
+/// up the remaining bytes, so the source fails in the same place every run.
+#[derive(Debug)]
+pub struct LimitedEntropySource {
+    inner: Arc<DynEntropySource>,
+    remaining: Mutex<u64>,
+}
+
+impl LimitedEntropySource {
+    /// Draws from the entropy of the host.
+    pub fn new(limit: u64) -> Self {
+        Self::with_source(Arc::new(HostEntropySource), limit)
+    }
+
+    pub fn with_source(inner: Arc<DynEntropySource>, limit: u64) -> Self {
+        Self {
+            inner,
+            remaining: Mutex::new(limit),
+        }
+    }
+
+    /// The number of bytes that can still be handed out.
+    pub fn remaining(&self) -> u64 {
+        *self.remaining.lock().unwrap()
+    }
+}
+
+impl EntropySource for LimitedEntropySource {
+    fn fill(&self, buf: &mut [u8]) -> Result<(), EntropyUnavailable> {
+        let mut remaining = self.remaining.lock().unwrap();
+        let len = buf.len() as u64;
+        if len > *remaining {
+            *remaining = 0;
+            return Err(EntropyUnavailable);
+        }
+        self.inner.fill(buf)?;
+        *remaining -= len;
+        Ok(())
+    }
+}
+
+/// Random bytes that are derived from a seed, so that a guest gets the
+/// same bytes every time it runs with the same seed.
+///
+/// This is what [`WasiEnvBuilder::deterministic`](crate::WasiEnvBuilder::deterministic)
+/// uses, it must never be used where the bytes need to be unpredictable.
+#[derive(Debug)]
+pub struct SeededEntropySource {
+    rng: Mutex<StdRng>,
+}
+
+impl SeededEntropySource {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            rng: Mutex::new(StdRng::seed_from_u64(seed)),
+        }
+    }
+}
+
+impl EntropySource for SeededEntropySource {
+    fn fill(&self, buf: &mut [u8]) -> Result<(), EntropyUnavailable> {
+        self.rng.lock().unwrap().fill_bytes(buf);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn limited_source_fails_once_the_limit_is_reached() {
+        let source = LimitedEntropySource::new(16);
+
+        let mut buf = [0u8; 8];
+        assert_eq!(source.fill(&mut buf), Ok(()));
+        assert_eq!(source.fill(&mut buf[..6]), Ok(()));
+        assert_eq!(source.remaining(), 2);
+
+        // A request that doesn't fit exhausts the source
+        assert_eq!(source.fill(&mut buf[..4]), Err(EntropyUnavailable));
+        assert_eq!(source.remaining(), 0);
+        assert_eq!(source.fill(&mut buf[..1]), Err(EntropyUnavailable));
+
+        // Asking for nothing always works
+        assert_eq!(source.fill(&mut []), Ok(()));
+    }
+
+    #[test]
+    fn seeded_source_repeats_for_the_same_seed() {
+        let fill = |seed| {
+            let source = SeededEntropySource::new(seed);
+            let mut buf = [0u8; 32];
+            source.fill(&mut buf[..12]).unwrap();
+            source.fill(&mut buf[12..]).unwrap();
+            buf
+        };
+
+        assert_eq!(fill(7), fill(7));
+        assert_ne!(fill(7), fill(8));
+    }
+}
@@ -0,0 +1,134 @@
+//! Eviction of threads that stay in a deep sleep for too long, see
+//! [`Runtime::idle_eviction`](super::Runtime::idle_eviction).
+
+use std::{
+    collections::HashMap,
+    fmt, io,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::Duration,
+};
+
+/// Keeps the linear memory of evicted threads until they are restored.
+///
+/// The memory is handed over once when the thread is evicted and handed
+/// back once when it is restored, a store that keeps it outside of the
+/// host memory (for instance on disk) frees up all the resources of the
+/// evicted thread.
+pub trait EvictionStore: fmt::Debug + Send + Sync {
+    /// Stores the memory of an evicted thread and returns the key that it
+    /// can be loaded with again, the thread is not evicted when this fails.
+    fn save(&self, memory: Vec<u8>) -> io::Result<u64>;
+
+    /// Loads (and forgets) the memory that was stored under `key`, the
+    /// thread is terminated when this fails.
+    fn load(&self, key: u64) -> io::Result<Vec<u8>>;
+
+    /// Forgets the memory that was stored under `key` as the thread was
+    /// terminated while it was evicted.
+    fn discard(&self, key: u64);
+}
+
+pub type DynEvictionStore = dyn EvictionStore + Send + Sync;
+
+/// Keeps the memory of the evicted threads in the memory of the host,
+/// which still releases their instances (and the address space that was
+/// reserved for their memories).
+#[derive(Debug, Default)]
+pub struct InMemoryEvictionStore {
+    next_key: AtomicU64,
+    memories: Mutex<HashMap<u64, Vec<u8>>>,
+}
+
+impl InMemoryEvictionStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The number of evicted threads whose memory is held in the store
+    pub fn len(&self) -> usize {
+        self.memories.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl EvictionStore for InMemoryEvictionStore {
+    fn save(&self, memory: Vec<u8>) -> io::Result<u64> {
+        let key = self.next_key.fetch_add(1, Ordering::SeqCst);
+        self.memories.lock().unwrap().insert(key, memory);
+        Ok(key)
+    }
+
+    fn load(&self, key: u64) -> io::Result<Vec<u8>> {
+        self.memories
+            .lock()
+            .unwrap()
+            .remove(&key)
+            .ok_or_else(|| io::ErrorKind::NotFound.into())
+    }
+
+    fn discard(&self, key: u64) {
+        self.memories.lock().unwrap().remove(&key);
+    }
+}
+
+/// Evicts processes that stay in a deep sleep (for instance in `poll_oneoff`
+/// or `thread_sleep`) for longer than `timeout`.
+///
+/// An evicted process has its instance dropped and its linear memory
+/// moved to the [`EvictionStore`], its globals and the unwound stack are
+/// kept along with the syscall that it is waiting on. Only processes with
+/// a single thread are evicted, as the memory of the others is still in
+/// use by their other threads.
+///
+/// While a process is evicted the events that it waits for queue up where
+/// they always do, data in the buffers of its pipes and sockets and signals
+/// on its thread. The process is restored (with a new instance that gets
+/// the memory back) as soon as the syscall it waits on completes or a
+/// signal or a checkpoint request arrives, and then carries on from where
+/// it went to sleep. A process that is terminated while it is evicted is
+/// never restored and its memory is discarded from the store.
+#[derive(Debug, Clone)]
+pub struct IdleEviction {
+    pub timeout: Duration,
+    pub store: Arc<DynEvictionStore>,
+}
+
+impl IdleEviction {
+    /// Keeps the memory of evicted processes in an [`InMemoryEvictionStore`]
+    pub fn new(timeout: Duration) -> Self {
+        Self::with_store(timeout, Arc::new(InMemoryEvictionStore::new()))
+    }
+
+    pub fn with_store(timeout: Duration, store: Arc<DynEvictionStore>) -> Self {
+        Self { timeout, store }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn in_memory_store_hands_back_the_memory_once() {
+        let store = InMemoryEvictionStore::new();
+        let first = store.save(vec![1, 2, 3]).unwrap();
+        let second = store.save(vec![4]).unwrap();
+        assert_ne!(first, second);
+        assert_eq!(store.len(), 2);
+
+        assert_eq!(store.load(first).unwrap(), vec![1, 2, 3]);
+        assert_eq!(
+            store.load(first).unwrap_err().kind(),
+            io::ErrorKind::NotFound
+        );
+
+        store.discard(second);
+        assert!(store.is_empty());
+    }
+}
@@ -1,10 +1,20 @@
+pub mod entropy;
+pub mod eviction;
 pub mod module_cache;
 pub mod package_loader;
 pub mod resolver;
+pub mod syscall_log;
 pub mod task_manager;
+pub mod template;
+pub mod timer_wheel;
 
+use self::entropy::{DynEntropySource, EntropySource, HostEntropySource};
+use self::eviction::IdleEviction;
 use self::module_cache::CacheError;
+use self::syscall_log::SyscallLog;
 pub use self::task_manager::{SpawnType, VirtualTaskManager};
+use self::template::ProcessTemplate;
+use self::timer_wheel::{DynTimerWheel, TimerWheel};
 use module_cache::HashedModuleData;
 use wasmer_types::{CompilationProgressCallback, ModuleHash};
 
@@ -24,10 +34,10 @@ use wasmer_wasix_types::wasi::ExitCode;
 #[cfg(feature = "journal")]
 use crate::journal::{DynJournal, DynReadableJournal};
 use crate::{
-    SpawnError, WasiTtyState,
+    SpawnError, WasiEnv, WasiProcessId, WasiThreadId, WasiTtyState,
     bin_factory::BinaryPackageCommand,
     http::{DynHttpClient, HttpClient},
-    os::TtyBridge,
+    os::{TtyBridge, task::TaskJoinHandle},
     runtime::{
         module_cache::{
             ModuleCache, ThreadLocalCache,
@@ -36,6 +46,7 @@ use crate::{
         package_loader::{PackageLoader, UnsupportedPackageLoader},
         resolver::{BackendSource, MultiSource, Source},
     },
+    utils::core_dump::CoreDump,
 };
 
 pub type MakeImportCallback = dyn Fn(&wasmer::Module, &mut wasmer::StoreMut) -> anyhow::Result<wasmer::Imports>
@@ -70,6 +81,48 @@ impl fmt::Debug for InstanceCallback {
     }
 }
 
+pub type ForkCallbackFn = dyn Fn(WasiProcessId, WasiProcessId) + Send + Sync + 'static;
+
+/// Invoked with the pid of the parent and the pid of the child after a
+/// process was forked (see [`Runtime::on_fork`])
+#[derive(Clone)]
+pub struct ForkCallback(pub Arc<ForkCallbackFn>);
+
+impl fmt::Debug for ForkCallback {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ForkCallback(..)")
+    }
+}
+
+pub type CallHookFn = dyn Fn(WasiProcessId, WasiThreadId) + Send + Sync + 'static;
+
+/// Invoked with the pid and tid of the thread right before it calls into
+/// the module and right after the call returns (see [`Runtime::call_hooks`])
+#[derive(Clone)]
+pub struct CallHooks {
+    pub on_enter: Arc<CallHookFn>,
+    pub on_exit: Arc<CallHookFn>,
+}
+
+impl fmt::Debug for CallHooks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("CallHooks(..)")
+    }
+}
+
+pub type CoreDumpCallbackFn = dyn Fn(&CoreDump) + Send + Sync + 'static;
+
+/// Receives a core dump of every thread that fails with a trap
+/// (see [`Runtime::on_core_dump`])
+#[derive(Clone)]
+pub struct CoreDumpCallback(pub Arc<CoreDumpCallbackFn>);
+
+impl fmt::Debug for CoreDumpCallback {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("CoreDumpCallback(..)")
+    }
+}
+
 #[derive(Clone)]
 pub enum TaintReason {
     UnknownWasiVersion,
@@ -331,6 +384,84 @@ where
     /// for multiple reasons however the most common is a panic within the process
     fn on_taint(&self, _reason: TaintReason) {}
 
+    /// The maximum size (in bytes) of the stack that may be captured when a
+    /// thread is unwound with asyncify (deep sleep, fork, checkpoints), when
+    /// the stack is larger than this the syscall fails with `Errno::Nomem`
+    fn snapshot_size_limit(&self) -> Option<u64> {
+        None
+    }
+
+    /// The source of the random bytes that are handed out to guests
+    fn entropy_source(&self) -> &DynEntropySource {
+        &HostEntropySource
+    }
+
+    /// Records the syscalls of the guests into a log, or replays them from
+    /// one (see [`SyscallLog`])
+    fn syscall_log(&self) -> Option<SyscallLog> {
+        None
+    }
+
+    /// The timer that the sleeps of the guests register their deadlines
+    /// with, when `None` every sleep gets a timer of its own from the task
+    /// manager (see [`TimerWheel`])
+    fn timer_wheel(&self) -> Option<Arc<DynTimerWheel>> {
+        None
+    }
+
+    /// Callback that is invoked after every successful `proc_fork`, before
+    /// the child starts running, so that the host can keep track of the
+    /// relationship between the processes. The fork only waits about 100ms
+    /// for the callback to return, a callback that takes longer still runs
+    /// to completion but the child may already be running by then.
+    fn on_fork(&self) -> Option<ForkCallback> {
+        None
+    }
+
+    /// Hooks that are invoked around every call into the module (running
+    /// the entrypoint of a process or a thread, resuming it after a fork or
+    /// a deep sleep), for instance to time them or to watch over them.
+    fn call_hooks(&self) -> Option<CallHooks> {
+        None
+    }
+
+    /// Callback that receives a core dump (linear memory, globals and call
+    /// stack) of every thread that fails with a trap, before the process
+    /// is cleaned up. Core dumps are only captured when this returns a
+    /// callback.
+    fn on_core_dump(&self) -> Option<CoreDumpCallback> {
+        None
+    }
+
+    /// Evicts processes that stay in a deep sleep for longer than a timeout
+    /// and restores them once they wake up again (see [`IdleEviction`]),
+    /// processes are never evicted when this returns `None`.
+    fn idle_eviction(&self) -> Option<IdleEviction> {
+        None
+    }
+
+    /// Instantiates the module in the environment and runs its initialization
+    /// once, the result is kept as a template that new processes are cloned
+    /// from (see [`ProcessTemplate`])
+    fn capture_process_template(
+        &self,
+        module: Module,
+        env: WasiEnv,
+    ) -> Result<ProcessTemplate, SpawnError> {
+        ProcessTemplate::capture(module, env)
+    }
+
+    /// Starts a new process that is cloned from a template that was captured
+    /// with [`Runtime::capture_process_template`]
+    fn spawn_from_template(
+        &self,
+        template: &ProcessTemplate,
+        args: Option<Vec<String>>,
+        envs: Option<Vec<(String, String)>>,
+    ) -> Result<TaskJoinHandle, SpawnError> {
+        template.spawn(args, envs)
+    }
+
     /// The list of all read-only journals which will be used to restore the state of the
     /// runtime at a particular point in time
     #[cfg(feature = "journal")]
@@ -466,6 +597,14 @@ pub struct PluggableRuntime {
     pub writable_journals: Vec<Arc<DynJournal>>,
     pub additional_imports: Vec<ImportCallback>,
     pub instance_callbacks: Vec<InstanceCallback>,
+    pub snapshot_size_limit: Option<u64>,
+    pub entropy_source: Option<Arc<DynEntropySource>>,
+    pub syscall_log: Option<SyscallLog>,
+    pub timer_wheel: Option<Arc<DynTimerWheel>>,
+    pub on_fork: Option<ForkCallback>,
+    pub call_hooks: Option<CallHooks>,
+    pub on_core_dump: Option<CoreDumpCallback>,
+    pub idle_eviction: Option<IdleEviction>,
 }
 
 impl PluggableRuntime {
@@ -506,6 +645,14 @@ impl PluggableRuntime {
             writable_journals: Vec::new(),
             additional_imports: Vec::new(),
             instance_callbacks: Vec::new(),
+            snapshot_size_limit: None,
+            entropy_source: None,
+            syscall_log: None,
+            timer_wheel: None,
+            on_fork: None,
+            call_hooks: None,
+            on_core_dump: None,
+            idle_eviction: None,
         }
     }
 
@@ -527,6 +674,75 @@ impl PluggableRuntime {
         self
     }
 
+    /// Limits the size of the stack snapshots taken when threads are
+    /// unwound (see [`Runtime::snapshot_size_limit`])
+    pub fn set_snapshot_size_limit(&mut self, limit: u64) -> &mut Self {
+        self.snapshot_size_limit = Some(limit);
+        self
+    }
+
+    /// Replaces the source of the random bytes handed out to guests
+    /// (see [`Runtime::entropy_source`])
+    pub fn set_entropy_source(&mut self, source: impl EntropySource + 'static) -> &mut Self {
+        self.entropy_source = Some(Arc::new(source));
+        self
+    }
+
+    /// Records the syscalls of the guests into a log, or replays them from
+    /// one (see [`Runtime::syscall_log`])
+    pub fn set_syscall_log(&mut self, log: SyscallLog) -> &mut Self {
+        self.syscall_log = Some(log);
+        self
+    }
+
+    /// Makes the sleeps of the guests share a timer
+    /// (see [`Runtime::timer_wheel`])
+    pub fn set_timer_wheel(&mut self, wheel: impl TimerWheel + 'static) -> &mut Self {
+        self.timer_wheel = Some(Arc::new(wheel));
+        self
+    }
+
+    /// Sets the callback that is invoked after every successful fork
+    /// (see [`Runtime::on_fork`])
+    pub fn set_on_fork(
+        &mut self,
+        callback: impl Fn(WasiProcessId, WasiProcessId) + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.on_fork = Some(ForkCallback(Arc::new(callback)));
+        self
+    }
+
+    /// Sets the hooks that are invoked around every call into the module
+    /// (see [`Runtime::call_hooks`])
+    pub fn set_call_hooks(
+        &mut self,
+        on_enter: impl Fn(WasiProcessId, WasiThreadId) + Send + Sync + 'static,
+        on_exit: impl Fn(WasiProcessId, WasiThreadId) + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.call_hooks = Some(CallHooks {
+            on_enter: Arc::new(on_enter),
+            on_exit: Arc::new(on_exit),
+        });
+        self
+    }
+
+    /// Sets the callback that receives a core dump of every thread that
+    /// traps (see [`Runtime::on_core_dump`])
+    pub fn set_on_core_dump(
+        &mut self,
+        callback: impl Fn(&CoreDump) + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.on_core_dump = Some(CoreDumpCallback(Arc::new(callback)));
+        self
+    }
+
+    /// Evicts processes that stay in a deep sleep for too long
+    /// (see [`Runtime::idle_eviction`])
+    pub fn set_idle_eviction(&mut self, eviction: IdleEviction) -> &mut Self {
+        self.idle_eviction = Some(eviction);
+        self
+    }
+
     pub fn set_module_cache(
         &mut self,
         module_cache: impl ModuleCache + Send + Sync + 'static,
@@ -635,6 +851,41 @@ impl Runtime for PluggableRuntime {
         self.module_cache.clone()
     }
 
+    fn snapshot_size_limit(&self) -> Option<u64> {
+        self.snapshot_size_limit
+    }
+
+    fn entropy_source(&self) -> &DynEntropySource {
+        match self.entropy_source.as_deref() {
+            Some(source) => source,
+            None => &HostEntropySource,
+        }
+    }
+
+    fn syscall_log(&self) -> Option<SyscallLog> {
+        self.syscall_log.clone()
+    }
+
+    fn timer_wheel(&self) -> Option<Arc<DynTimerWheel>> {
+        self.timer_wheel.clone()
+    }
+
+    fn on_fork(&self) -> Option<ForkCallback> {
+        self.on_fork.clone()
+    }
+
+    fn call_hooks(&self) -> Option<CallHooks> {
+        self.call_hooks.clone()
+    }
+
+    fn on_core_dump(&self) -> Option<CoreDumpCallback> {
+        self.on_core_dump.clone()
+    }
+
+    fn idle_eviction(&self) -> Option<IdleEviction> {
+        self.idle_eviction.clone()
+    }
+
     fn additional_imports(
         &self,
         module: &wasmer::Module,
@@ -691,6 +942,14 @@ pub struct OverriddenRuntime {
     tty: Option<Arc<dyn TtyBridge + Send + Sync>>,
     additional_imports: Vec<ImportCallback>,
     instance_callbacks: Vec<InstanceCallback>,
+    snapshot_size_limit: Option<u64>,
+    entropy_source: Option<Arc<DynEntropySource>>,
+    syscall_log: Option<SyscallLog>,
+    timer_wheel: Option<Arc<DynTimerWheel>>,
+    on_fork: Option<ForkCallback>,
+    call_hooks: Option<CallHooks>,
+    on_core_dump: Option<CoreDumpCallback>,
+    idle_eviction: Option<IdleEviction>,
     #[cfg(feature = "journal")]
     pub read_only_journals: Option<Vec<Arc<DynReadableJournal>>>,
     #[cfg(feature = "journal")]
@@ -711,6 +970,14 @@ impl OverriddenRuntime {
             tty: None,
             additional_imports: Vec::new(),
             instance_callbacks: Vec::new(),
+            snapshot_size_limit: None,
+            entropy_source: None,
+            syscall_log: None,
+            timer_wheel: None,
+            on_fork: None,
+            call_hooks: None,
+            on_core_dump: None,
+            idle_eviction: None,
             #[cfg(feature = "journal")]
             read_only_journals: None,
             #[cfg(feature = "journal")]
@@ -790,6 +1057,60 @@ impl OverriddenRuntime {
         self
     }
 
+    pub fn with_snapshot_size_limit(mut self, limit: u64) -> Self {
+        self.snapshot_size_limit.replace(limit);
+        self
+    }
+
+    pub fn with_entropy_source(mut self, source: Arc<DynEntropySource>) -> Self {
+        self.entropy_source.replace(source);
+        self
+    }
+
+    pub fn with_syscall_log(mut self, log: SyscallLog) -> Self {
+        self.syscall_log.replace(log);
+        self
+    }
+
+    pub fn with_timer_wheel(mut self, wheel: Arc<DynTimerWheel>) -> Self {
+        self.timer_wheel.replace(wheel);
+        self
+    }
+
+    pub fn with_on_fork(
+        mut self,
+        callback: impl Fn(WasiProcessId, WasiProcessId) + Send + Sync + 'static,
+    ) -> Self {
+        self.on_fork.replace(ForkCallback(Arc::new(callback)));
+        self
+    }
+
+    pub fn with_call_hooks(
+        mut self,
+        on_enter: impl Fn(WasiProcessId, WasiThreadId) + Send + Sync + 'static,
+        on_exit: impl Fn(WasiProcessId, WasiThreadId) + Send + Sync + 'static,
+    ) -> Self {
+        self.call_hooks.replace(CallHooks {
+            on_enter: Arc::new(on_enter),
+            on_exit: Arc::new(on_exit),
+        });
+        self
+    }
+
+    pub fn with_on_core_dump(
+        mut self,
+        callback: impl Fn(&CoreDump) + Send + Sync + 'static,
+    ) -> Self {
+        self.on_core_dump
+            .replace(CoreDumpCallback(Arc::new(callback)));
+        self
+    }
+
+    pub fn with_idle_eviction(mut self, eviction: IdleEviction) -> Self {
+        self.idle_eviction.replace(eviction);
+        self
+    }
+
     #[cfg(feature = "journal")]
     pub fn with_read_only_journals(mut self, journals: Vec<Arc<DynReadableJournal>>) -> Self {
         self.read_only_journals.replace(journals);
@@ -903,6 +1224,87 @@ impl Runtime for OverriddenRuntime {
         }
     }
 
+    fn snapshot_size_limit(&self) -> Option<u64> {
+        if let Some(limit) = self.snapshot_size_limit {
+            Some(limit)
+        } else {
+            self.inner.snapshot_size_limit()
+        }
+    }
+
+    fn entropy_source(&self) -> &DynEntropySource {
+        if let Some(source) = self.entropy_source.as_ref() {
+            source.deref()
+        } else {
+            self.inner.entropy_source()
+        }
+    }
+
+    fn syscall_log(&self) -> Option<SyscallLog> {
+        if let Some(log) = self.syscall_log.as_ref() {
+            Some(log.clone())
+        } else {
+            self.inner.syscall_log()
+        }
+    }
+
+    fn timer_wheel(&self) -> Option<Arc<DynTimerWheel>> {
+        if let Some(wheel) = self.timer_wheel.as_ref() {
+            Some(wheel.clone())
+        } else {
+            self.inner.timer_wheel()
+        }
+    }
+
+    fn on_fork(&self) -> Option<ForkCallback> {
+        if let Some(callback) = self.on_fork.as_ref() {
+            Some(callback.clone())
+        } else {
+            self.inner.on_fork()
+        }
+    }
+
+    fn call_hooks(&self) -> Option<CallHooks> {
+        if let Some(hooks) = self.call_hooks.as_ref() {
+            Some(hooks.clone())
+        } else {
+            self.inner.call_hooks()
+        }
+    }
+
+    fn on_core_dump(&self) -> Option<CoreDumpCallback> {
+        if let Some(callback) = self.on_core_dump.as_ref() {
+            Some(callback.clone())
+        } else {
+            self.inner.on_core_dump()
+        }
+    }
+
+    fn idle_eviction(&self) -> Option<IdleEviction> {
+        if let Some(eviction) = self.idle_eviction.as_ref() {
+            Some(eviction.clone())
+        } else {
+            self.inner.idle_eviction()
+        }
+    }
+
+    fn capture_process_template(
+        &self,
+        module: Module,
+        env: WasiEnv,
+    ) -> Result<ProcessTemplate, SpawnError> {
+        self.inner.capture_process_template(module, env)
+    }
+
+    fn spawn_from_template(
+        &self,
+        template: &ProcessTemplate,
+        args: Option<Vec<String>>,
+        envs: Option<Vec<(String, String)>>,
+    ) -> Result<TaskJoinHandle, SpawnError> {
+        self.inner.spawn_from_template(template, args, envs)
+    }
+
     #[cfg(feature = "journal")]
     fn read_only_journals<'a>(&'a self) -> Box<dyn Iterator<Item = Arc<DynReadableJournal>> + 'a> {
         if let Some(journals) = self.read_only_journals.as_ref() {
@@ -0,0 +1,594 @@
+//! Recording of the syscalls that a guest makes and deterministic replay of
+//! them, see [`SyscallLog`].
+//!
+//! # Format
+//!
+//! A log starts with the 8 bytes [`SYSCALL_LOG_MAGIC`] followed by the
+//! version of the format as a little endian `u32` (currently
+//! [`SYSCALL_LOG_VERSION`]). Then comes one entry per syscall, in the order
+//! the syscalls were made. Every entry is the length of the [`SyscallRecord`]
+//! as a little endian `u32` followed by the record encoded with `bincode`
+//! (standard configuration).
+//!
+//! Logs with another version are refused rather than misread, the version
+//! is bumped whenever the layout of [`SyscallRecord`] changes.
+
+use std::{
+    collections::VecDeque,
+    fmt,
+    io::{self, Read, Write},
+    sync::{Arc, Mutex},
+};
+
+use bincode::config;
+use serde::{Deserialize, Serialize};
+use wasmer::{
+    AsStoreMut, Extern, Function, FunctionEnv, FunctionEnvMut, Imports, RuntimeError, Value,
+};
+use wasmer_wasix_types::wasi::ExitCode;
+use xxhash_rust::xxh64::xxh64;
+
+use crate::{WasiEnv, WasiError};
+
+/// The bytes that every syscall log starts with.
+pub const SYSCALL_LOG_MAGIC: [u8; 8] = *b"WASIXSYS";
+
+/// The version of the format of the syscall logs that are written.
+pub const SYSCALL_LOG_VERSION: u32 = 1;
+
+/// The memory is hashed in blocks of this size before every syscall, the
+/// blocks whose hash changed by the time the syscall returns are recorded.
+const MEMORY_BLOCK_SIZE: usize = 256;
+
+/// The memory is read in chunks of this size while it is hashed.
+const MEMORY_CHUNK_SIZE: usize = 64 * 1024;
+
+/// The longest entry of a syscall log that is read, so that a corrupted
+/// log can't make the reader allocate more than this.
+const MAX_ENTRY_LEN: usize = 1 << 30;
+
+/// What the runtime does with the syscalls of the guests.
+///
+/// When recording, every syscall is passed on as usual and its parameters,
+/// the changes it made to the linear memory and its results are written to
+/// the log. When replaying, the syscalls never reach the real resources
+/// (files, sockets, clocks, ...), instead the recorded memory changes and
+/// results are handed back to the guest, which then runs exactly like it
+/// did when it was recorded. Blocking syscalls (`poll_oneoff`,
+/// `thread_sleep`, ...) return right away with the wakeups that were
+/// recorded.
+///
+/// The log is meant for a single-threaded process. The memory changes of
+/// a syscall are found by hashing the linear memory in blocks before and
+/// after it, only the blocks that changed are copied to the log. This
+/// still reads the whole memory twice per syscall, which makes recording
+/// slow for large memories, and changes made by other threads at the same
+/// time end up in the log as well.
+/// Syscalls that unwind the stack of the guest (`proc_fork`, deep sleep,
+/// ...) can't be replayed, nor can signal handlers that are invoked from
+/// within a syscall.
+#[derive(Debug, Clone)]
+pub enum SyscallLog {
+    Record(Arc<SyscallRecorder>),
+    Replay(Arc<SyscallReplayer>),
+}
+
+/// A value passed to or returned from a syscall.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RecordedValue {
+    I32(i32),
+    I64(i64),
+    /// The bits of an `f32`
+    F32(u32),
+    /// The bits of an `f64`
+    F64(u64),
+}
+
+impl RecordedValue {
+    fn from_value(value: &Value) -> Result<Self, RuntimeError> {
+        match value {
+            Value::I32(v) => Ok(Self::I32(*v)),
+            Value::I64(v) => Ok(Self::I64(*v)),
+            Value::F32(v) => Ok(Self::F32(v.to_bits())),
+            Value::F64(v) => Ok(Self::F64(v.to_bits())),
+            other => Err(RuntimeError::new(format!(
+                "the syscall log can't hold the value {other:?}"
+            ))),
+        }
+    }
+
+    fn to_value(self) -> Value {
+        match self {
+            Self::I32(v) => Value::I32(v),
+            Self::I64(v) => Value::I64(v),
+            Self::F32(v) => Value::F32(f32::from_bits(v)),
+            Self::F64(v) => Value::F64(f64::from_bits(v)),
+        }
+    }
+}
+
+/// Bytes that a syscall wrote to the linear memory.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MemoryWrite {
+    pub offset: u64,
+    pub data: Vec<u8>,
+}
+
+/// How a syscall ended.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SyscallOutcome {
+    /// The syscall returned these values
+    Returned(Vec<RecordedValue>),
+    /// The syscall exited the process (or thread) with this code
+    Exited(i32),
+    /// The syscall failed with a trap
+    Trapped(String),
+}
+
+/// One syscall as the guest made it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SyscallRecord {
+    pub namespace: String,
+    pub name: String,
+    pub params: Vec<RecordedValue>,
+    /// Size of the linear memory once the syscall is done
+    pub memory_size: u64,
+    pub memory_writes: Vec<MemoryWrite>,
+    pub outcome: SyscallOutcome,
+}
+
+/// The syscall log could not be read.
+#[derive(Debug, thiserror::Error)]
+pub enum SyscallLogError {
+    #[error("the syscall log could not be read")]
+    Io(#[from] io::Error),
+    #[error("this is not a syscall log")]
+    BadMagic,
+    #[error("version {0} of the syscall log is not supported")]
+    UnsupportedVersion(u32),
+    #[error("entry {index} of the syscall log is corrupted: {reason}")]
+    Corrupted { index: usize, reason: String },
+}
+
+/// Reads every record of a syscall log.
+pub fn read_syscall_log(mut reader: impl Read) -> Result<Vec<SyscallRecord>, SyscallLogError> {
+    let mut magic = [0u8; 8];
+    reader.read_exact(&mut magic)?;
+    if magic != SYSCALL_LOG_MAGIC {
+        return Err(SyscallLogError::BadMagic);
+    }
+    let mut version = [0u8; 4];
+    reader.read_exact(&mut version)?;
+    let version = u32::from_le_bytes(version);
+    if version != SYSCALL_LOG_VERSION {
+        return Err(SyscallLogError::UnsupportedVersion(version));
+    }
+
+    let mut records = Vec::new();
+    loop {
+        let mut len = [0u8; 4];
+        match reader.read_exact(&mut len) {
+            Ok(()) => {}
+            Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => break,
+            Err(err) => return Err(err.into()),
+        }
+        let len = u32::from_le_bytes(len) as usize;
+        if len > MAX_ENTRY_LEN {
+            return Err(SyscallLogError::Corrupted {
+                index: records.len(),
+                reason: format!("the entry is {len} bytes long"),
+            });
+        }
+        // The entry is read as far as the log goes rather than allocated
+        // upfront, in case the log was cut short
+        let mut entry = Vec::new();
+        (&mut reader).take(len as u64).read_to_end(&mut entry)?;
+        if entry.len() != len {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+        }
+        let config = config::standard().with_limit::<MAX_ENTRY_LEN>();
+        let (record, _) = bincode::serde::decode_from_slice(&entry, config).map_err(|err| {
+            SyscallLogError::Corrupted {
+                index: records.len(),
+                reason: err.to_string(),
+            }
+        })?;
+        records.push(record);
+    }
+    Ok(records)
+}
+
+/// Writes the syscalls of the guests to a log.
+pub struct SyscallRecorder {
+    writer: Mutex<Box<dyn Write + Send>>,
+}
+
+impl fmt::Debug for SyscallRecorder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SyscallRecorder").finish_non_exhaustive()
+    }
+}
+
+impl SyscallRecorder {
+    /// Starts a new log in `writer` by writing its header.
+    pub fn new(mut writer: impl Write + Send + 'static) -> io::Result<Self> {
+        writer.write_all(&SYSCALL_LOG_MAGIC)?;
+        writer.write_all(&SYSCALL_LOG_VERSION.to_le_bytes())?;
+        Ok(Self {
+            writer: Mutex::new(Box::new(writer)),
+        })
+    }
+
+    /// Appends a record to the log.
+    pub fn record(&self, record: &SyscallRecord) -> io::Result<()> {
+        let entry = bincode::serde::encode_to_vec(record, config::standard())
+            .map_err(|err| io::Error::other(err.to_string()))?;
+        let len = u32::try_from(entry.len()).map_err(io::Error::other)?;
+        let mut writer = self.writer.lock().unwrap();
+        writer.write_all(&len.to_le_bytes())?;
+        writer.write_all(&entry)
+    }
+
+    /// Flushes the log to the underlying writer.
+    pub fn flush(&self) -> io::Result<()> {
+        self.writer.lock().unwrap().flush()
+    }
+}
+
+/// Hands the recorded syscalls back to a guest, in order.
+#[derive(Debug)]
+pub struct SyscallReplayer {
+    records: Mutex<VecDeque<SyscallRecord>>,
+}
+
+impl SyscallReplayer {
+    pub fn new(records: Vec<SyscallRecord>) -> Self {
+        Self {
+            records: Mutex::new(records.into()),
+        }
+    }
+
+    /// Reads the log that is to be replayed.
+    pub fn from_reader(reader: impl Read) -> Result<Self, SyscallLogError> {
+        read_syscall_log(reader).map(Self::new)
+    }
+
+    /// The number of syscalls that were not replayed yet.
+    pub fn remaining(&self) -> usize {
+        self.records.lock().unwrap().len()
+    }
+
+    /// Takes the next record, which must be for the same syscall with the
+    /// same parameters, otherwise the guest took another path than the one
+    /// that was recorded.
+    fn next(
+        &self,
+        namespace: &str,
+        name: &str,
+        params: &[RecordedValue],
+    ) -> Result<SyscallRecord, String> {
+        let mut records = self.records.lock().unwrap();
+        let Some(record) = records.pop_front() else {
+            return Err(format!(
+                "the guest called `{namespace}::{name}` after the end of the syscall log"
+            ));
+        };
+        if record.namespace != namespace || record.name != name || record.params != params {
+            return Err(format!(
+                "the guest diverged from the syscall log, it called `{namespace}::{name}` with \
+                 {params:?} instead of `{}::{}` with {:?}",
+                record.namespace, record.name, record.params
+            ));
+        }
+        Ok(record)
+    }
+}
+
+/// Wraps every function of the imports so that it is recorded or replayed,
+/// depending on the [`SyscallLog`] of the runtime (if it has one).
+pub(crate) fn apply_syscall_log(
+    store: &mut impl AsStoreMut,
+    env: &FunctionEnv<WasiEnv>,
+    imports: &mut Imports,
+) {
+    let Some(log) = env.as_ref(store).runtime().syscall_log() else {
+        return;
+    };
+
+    let functions = imports
+        .iter()
+        .filter_map(|(namespace, name, ext)| match ext {
+            Extern::Function(f) => Some((namespace.to_string(), name.to_string(), f.clone())),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+
+    for (namespace, name, inner) in functions {
+        let ty = inner.ty(store);
+        let wrapper = match log.clone() {
+            SyscallLog::Record(recorder) => {
+                let (namespace, name) = (namespace.clone(), name.clone());
+                Function::new_with_env(store, env, &ty, move |mut ctx, params| {
+                    record_syscall(&mut ctx, &recorder, &namespace, &name, &inner, params)
+                })
+            }
+            SyscallLog::Replay(replayer) => {
+                let (namespace, name) = (namespace.clone(), name.clone());
+                Function::new_with_env(store, env, &ty, move |mut ctx, params| {
+                    replay_syscall(&mut ctx, &replayer, &namespace, &name, params)
+                })
+            }
+        };
+        imports.define(&namespace, &name, wrapper);
+    }
+}
+
+/// The hashes of the blocks of the memory of the guest (see
+/// [`hash_blocks`]).
+fn memory_hashes(ctx: &FunctionEnvMut<'_, WasiEnv>) -> Vec<u64> {
+    let Some(view) = ctx.data().try_memory_view(ctx) else {
+        return Vec::new();
+    };
+    hash_blocks(view.data_size(), |offset, buf| {
+        if view.read(offset, buf).is_err() {
+            buf.fill(0);
+        }
+    })
+}
+
+/// The size of the memory of the guest and the blocks of it that changed
+/// since `before` was hashed (see [`changed_blocks`]).
+fn memory_changes(ctx: &FunctionEnvMut<'_, WasiEnv>, before: &[u64]) -> (u64, Vec<MemoryWrite>) {
+    let Some(view) = ctx.data().try_memory_view(ctx) else {
+        return (0, Vec::new());
+    };
+    let size = view.data_size();
+    let writes = changed_blocks(size, before, |offset, buf| {
+        if view.read(offset, buf).is_err() {
+            buf.fill(0);
+        }
+    });
+    (size, writes)
+}
+
+fn record_syscall(
+    ctx: &mut FunctionEnvMut<'_, WasiEnv>,
+    recorder: &SyscallRecorder,
+    namespace: &str,
+    name: &str,
+    inner: &Function,
+    params: &[Value],
+) -> Result<Vec<Value>, RuntimeError> {
+    let recorded_params = params
+        .iter()
+        .map(RecordedValue::from_value)
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let before = memory_hashes(ctx);
+    let ret = inner.call(ctx, params);
+    let (memory_size, memory_writes) = memory_changes(ctx, &before);
+
+    let outcome = match &ret {
+        Ok(values) => SyscallOutcome::Returned(
+            values
+                .iter()
+                .map(RecordedValue::from_value)
+                .collect::<Result<Vec<_>, _>>()?,
+        ),
+        Err(err) => match err.downcast_ref::<WasiError>() {
+            Some(WasiError::Exit(code)) => SyscallOutcome::Exited(code.raw()),
+            _ => SyscallOutcome::Trapped(err.message()),
+        },
+    };
+    let record = SyscallRecord {
+        namespace: namespace.to_string(),
+        name: name.to_string(),
+        params: recorded_params,
+        memory_size,
+        memory_writes,
+        outcome,
+    };
+    if let Err(err) = recorder.record(&record) {
+        tracing::warn!("failed to record the syscall `{namespace}::{name}` - {err}");
+    }
+
+    ret.map(|values| values.into_vec())
+}
+
+fn replay_syscall(
+    ctx: &mut FunctionEnvMut<'_, WasiEnv>,
+    replayer: &SyscallReplayer,
+    namespace: &str,
+    name: &str,
+    params: &[Value],
+) -> Result<Vec<Value>, RuntimeError> {
+    let params = params
+        .iter()
+        .map(RecordedValue::from_value)
+        .collect::<Result<Vec<_>, _>>()?;
+    let record = replayer
+        .next(namespace, name, &params)
+        .map_err(RuntimeError::new)?;
+
+    if let Some(memory) = ctx.data().try_memory_clone() {
+        let size = memory.view(ctx).data_size();
+        if record.memory_size > size {
+            let page_size = wasmer::WASM_PAGE_SIZE as u64;
+            let delta = u32::try_from((record.memory_size - size).div_ceil(page_size))
+                .map_err(|_| RuntimeError::new("the syscall log grows the memory too much"))?;
+            memory
+                .grow(ctx, delta)
+                .map_err(|err| RuntimeError::new(err.to_string()))?;
+        }
+        let view = memory.view(ctx);
+        for write in record.memory_writes.iter() {
+            view.write(write.offset, &write.data)
+                .map_err(|err| RuntimeError::new(err.to_string()))?;
+        }
+    }
+
+    match record.outcome {
+        SyscallOutcome::Returned(values) => Ok(values.into_iter().map(|v| v.to_value()).collect()),
+        SyscallOutcome::Exited(code) => Err(RuntimeError::user(Box::new(WasiError::Exit(
+            ExitCode::from(code),
+        )))),
+        SyscallOutcome::Trapped(message) => Err(RuntimeError::new(message)),
+    }
+}
+
+/// Hashes the `len` bytes of memory that `read` reads, one hash per block
+/// of [`MEMORY_BLOCK_SIZE`] bytes.
+fn hash_blocks(len: u64, read: impl Fn(u64, &mut [u8])) -> Vec<u64> {
+    let mut hashes = Vec::with_capacity(len.div_ceil(MEMORY_BLOCK_SIZE as u64) as usize);
+    let mut chunk = vec![0u8; MEMORY_CHUNK_SIZE];
+    for offset in (0..len).step_by(MEMORY_CHUNK_SIZE) {
+        let chunk = &mut chunk[..(len - offset).min(MEMORY_CHUNK_SIZE as u64) as usize];
+        read(offset, chunk);
+        hashes.extend(chunk.chunks(MEMORY_BLOCK_SIZE).map(|block| xxh64(block, 0)));
+    }
+    hashes
+}
+
+/// Finds the blocks of the `len` bytes of memory that `read` reads whose
+/// hash is no longer the one in `before`, which are only copied then. The
+/// memory may have grown in between (new memory starts out as zeros).
+fn changed_blocks(len: u64, before: &[u64], read: impl Fn(u64, &mut [u8])) -> Vec<MemoryWrite> {
+    let zeros = [0u8; MEMORY_BLOCK_SIZE];
+    let mut writes: Vec<MemoryWrite> = Vec::new();
+    for (index, hash) in hash_blocks(len, &read).into_iter().enumerate() {
+        let offset = (index * MEMORY_BLOCK_SIZE) as u64;
+        let size = (len - offset).min(MEMORY_BLOCK_SIZE as u64) as usize;
+        let unchanged = match before.get(index) {
+            Some(before) => *before == hash,
+            None => xxh64(&zeros[..size], 0) == hash,
+        };
+        if unchanged {
+            continue;
+        }
+
+        let mut block = vec![0u8; size];
+        read(offset, &mut block);
+        // Blocks that follow each other are merged
+        match writes.last_mut() {
+            Some(last) if last.offset + last.data.len() as u64 == offset => {
+                last.data.extend_from_slice(&block)
+            }
+            _ => writes.push(MemoryWrite {
+                offset,
+                data: block,
+            }),
+        }
+    }
+    writes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Reads from `memory` like the memory of a guest is read
+    fn reader(memory: &[u8]) -> impl Fn(u64, &mut [u8]) + '_ {
+        |offset, buf| {
+            let offset = offset as usize;
+            buf.copy_from_slice(&memory[offset..offset + buf.len()]);
+        }
+    }
+
+    #[test]
+    fn changed_blocks_are_found_and_copied() {
+        const BLOCK: usize = MEMORY_BLOCK_SIZE;
+
+        let before = vec![0u8; 3 * BLOCK];
+        let hashes = hash_blocks(before.len() as u64, reader(&before));
+        let mut after = before.clone();
+        after[10] = 1;
+        after[BLOCK - 1] = 2;
+        after[BLOCK] = 3;
+        // The memory grew by a block that has a single byte set and one
+        // that is still empty
+        after.extend(std::iter::repeat_n(0, 2 * BLOCK));
+        after[4 * BLOCK - 1] = 4;
+
+        let writes = changed_blocks(after.len() as u64, &hashes, reader(&after));
+        assert_eq!(
+            writes,
+            vec![
+                MemoryWrite {
+                    offset: 0,
+                    data: after[..2 * BLOCK].to_vec(),
+                },
+                MemoryWrite {
+                    offset: 3 * BLOCK as u64,
+                    data: after[3 * BLOCK..4 * BLOCK].to_vec(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn corrupted_lengths_are_not_allocated() {
+        let mut log = SYSCALL_LOG_MAGIC.to_vec();
+        log.extend_from_slice(&SYSCALL_LOG_VERSION.to_le_bytes());
+        log.extend_from_slice(&u32::MAX.to_le_bytes());
+        assert!(matches!(
+            read_syscall_log(log.as_slice()),
+            Err(SyscallLogError::Corrupted { index: 0, .. })
+        ));
+
+        // An entry that is cut short
+        log.truncate(12);
+        log.extend_from_slice(&64u32.to_le_bytes());
+        log.extend_from_slice(&[0; 8]);
+        assert!(matches!(
+            read_syscall_log(log.as_slice()),
+            Err(SyscallLogError::Io(err)) if err.kind() == io::ErrorKind::UnexpectedEof
+        ));
+    }
+
+    #[test]
+    fn logs_round_trip_and_check_their_version() {
+        let record = SyscallRecord {
+            namespace: "wasi_snapshot_preview1".to_string(),
+            name: "clock_time_get".to_string(),
+            params: vec![RecordedValue::I32(1), RecordedValue::I64(1000)],
+            memory_size: 65536,
+            memory_writes: vec![MemoryWrite {
+                offset: 8,
+                data: vec![1, 2, 3, 4, 5, 6, 7, 8],
+            }],
+            outcome: SyscallOutcome::Returned(vec![RecordedValue::I32(0)]),
+        };
+
+        let log = Arc::new(Mutex::new(Vec::new()));
+        #[derive(Clone)]
+        struct SharedWriter(Arc<Mutex<Vec<u8>>>);
+        impl Write for SharedWriter {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                self.0.lock().unwrap().write(buf)
+            }
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+        let recorder = SyscallRecorder::new(SharedWriter(log.clone())).unwrap();
+        recorder.record(&record).unwrap();
+        recorder.record(&record).unwrap();
+
+        let bytes = log.lock().unwrap().clone();
+        assert_eq!(
+            read_syscall_log(bytes.as_slice()).unwrap(),
+            vec![record.clone(), record]
+        );
+
+        let mut newer = bytes.clone();
+        newer[8..12].copy_from_slice(&(SYSCALL_LOG_VERSION + 1).to_le_bytes());
+        assert!(matches!(
+            read_syscall_log(newer.as_slice()),
+            Err(SyscallLogError::UnsupportedVersion(v)) if v == SYSCALL_LOG_VERSION + 1
+        ));
+        assert!(matches!(
+            read_syscall_log(&b"not a log at all"[..]),
+            Err(SyscallLogError::BadMagic)
+        ));
+    }
+}
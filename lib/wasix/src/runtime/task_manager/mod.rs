@@ -1,8 +1,11 @@
 // TODO: should be behind a different , tokio specific feature flag.
 #[cfg(feature = "sys-thread")]
+mod scheduler;
+#[cfg(feature = "sys-thread")]
 pub mod tokio;
 
 use std::ops::Deref;
+use std::sync::Arc;
 use std::task::{Context, Poll};
 use std::{pin::Pin, time::Duration};
 
@@ -15,6 +18,7 @@ use wasmer::{
 };
 use wasmer_wasix_types::wasi::{Errno, ExitCode};
 
+use crate::runtime::eviction::IdleEviction;
 use crate::syscalls::AsyncifyFuture;
 use crate::{StoreSnapshot, WasiEnv, WasiFunctionEnv, WasiThread, capture_store_snapshot};
 use crate::{os::task::thread::WasiThreadError, state::Linker};
@@ -273,6 +277,13 @@ pub trait VirtualTaskManager: std::fmt::Debug + Send + Sync + 'static {
     /// Returns the amount of parallelism that is possible on this platform.
     fn thread_parallelism(&self) -> Result<usize, WasiThreadError>;
 
+    /// Returns true if all the WebAssembly tasks take turns on a single
+    /// thread, in which case a task should give up the thread (by going
+    /// into a deep sleep) as soon as it has to wait for something.
+    fn is_single_threaded(&self) -> bool {
+        false
+    }
+
     /// Schedule a blocking task to run on the threadpool, explicitly
     /// transferring a [`Module`] to the task.
     ///
@@ -341,6 +352,10 @@ where
         (**self).thread_parallelism()
     }
 
+    fn is_single_threaded(&self) -> bool {
+        (**self).is_single_threaded()
+    }
+
     fn spawn_with_module(
         &self,
         module: Module,
@@ -350,10 +365,37 @@ where
     }
 }
 
+// This poller will process any signals when the main working function is idle
+struct AsyncifyPollerOwned {
+    thread: WasiThread,
+    trigger: Pin<Box<AsyncifyFuture>>,
+}
+impl Future for AsyncifyPollerOwned {
+    type Output = Result<Bytes, ExitCode>;
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let work = self.trigger.as_mut();
+        Poll::Ready(if let Poll::Ready(res) = work.poll(cx) {
+            Ok(res)
+        } else if let Some(forced_exit) = self.thread.try_join() {
+            return Poll::Ready(Err(forced_exit.unwrap_or_else(|err| {
+                tracing::debug!("exit runtime error - {}", err);
+                Errno::Child.into()
+            })));
+        } else {
+            return Poll::Pending;
+        })
+    }
+}
+
+type ResumeTrigger = Pin<Box<dyn Future<Output = Result<Bytes, ExitCode>> + Send + Sync + 'static>>;
+
 impl dyn VirtualTaskManager {
     /// Starts an WebAssembly task will run on a dedicated thread
     /// pulled from the worker pool that has a stateful thread local variable
     /// After the poller has succeeded
+    ///
+    /// When the runtime evicts idle processes (see [`crate::Runtime::idle_eviction`])
+    /// the thread is evicted if it sleeps for longer than the timeout.
     #[doc(hidden)]
     pub unsafe fn resume_wasm_after_poller(
         &self,
@@ -362,28 +404,6 @@ impl dyn VirtualTaskManager {
         mut store: Store,
         trigger: Pin<Box<AsyncifyFuture>>,
     ) -> Result<(), WasiThreadError> {
-        // This poller will process any signals when the main working function is idle
-        struct AsyncifyPollerOwned {
-            thread: WasiThread,
-            trigger: Pin<Box<AsyncifyFuture>>,
-        }
-        impl Future for AsyncifyPollerOwned {
-            type Output = Result<Bytes, ExitCode>;
-            fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-                let work = self.trigger.as_mut();
-                Poll::Ready(if let Poll::Ready(res) = work.poll(cx) {
-                    Ok(res)
-                } else if let Some(forced_exit) = self.thread.try_join() {
-                    return Poll::Ready(Err(forced_exit.unwrap_or_else(|err| {
-                        tracing::debug!("exit runtime error - {}", err);
-                        Errno::Child.into()
-                    })));
-                } else {
-                    return Poll::Pending;
-                })
-            }
-        }
-
         let snapshot = capture_store_snapshot(&mut store.as_store_mut());
         let env = ctx.data(&store);
         let env_inner = env.inner();
@@ -392,52 +412,244 @@ impl dyn VirtualTaskManager {
             .ok_or(WasiThreadError::Unsupported)?;
         let module = handles.module_clone();
         let memory = handles.memory_clone();
-        let thread = env.thread.clone();
         let env = env.clone();
-
-        let thread_inner = thread.clone();
-        self.task_wasm(
-            TaskWasm::new(
-                Box::new(move |props| {
-                    let result = props
-                        .trigger_result
-                        .expect("If there is no result then its likely the trigger did not run");
-                    let result = match result {
-                        Ok(r) => r,
-                        Err(exit_code) => {
-                            thread.set_status_finished(Ok(exit_code));
-                            return;
-                        }
-                    };
-                    task(props.ctx, props.store, result)
-                }),
-                env.clone(),
-                module,
-                false,
-                false,
-            )
-            .with_memory(SpawnType::ShareMemory(memory, store.as_store_ref()))
-            .with_globals(snapshot)
-            .with_trigger(Box::new(move || {
+        let poller = AsyncifyPollerOwned {
+            thread: env.thread.clone(),
+            trigger,
+        };
+
+        // The memory of processes with other threads is still in use, so
+        // only single threaded processes are evicted
+        if let Some(eviction) = env.runtime().idle_eviction()
+            && env.process.active_threads() == 1
+        {
+            let tasks = env.tasks().clone();
+            return self.task_shared(Box::new(move || {
                 Box::pin(async move {
-                    let mut poller = AsyncifyPollerOwned {
-                        thread: thread_inner,
-                        trigger,
+                    let sleeper = IdleSleeper {
+                        tasks,
+                        eviction,
+                        task,
+                        env,
+                        module,
+                        snapshot,
                     };
-                    let res = Pin::new(&mut poller).await;
-                    let res = match res {
-                        Ok(res) => res,
-                        Err(exit_code) => {
-                            env.thread.set_status_finished(Ok(exit_code));
-                            return Err(exit_code);
-                        }
-                    };
-
-                    tracing::trace!("deep sleep woken - res.len={}", res.len());
-                    Ok(res)
+                    sleeper.run(ctx, store, memory, poller).await
                 })
-            })),
+            }));
+        }
+
+        resume_after_poller(
+            self,
+            task,
+            env,
+            module,
+            Some(SpawnType::ShareMemory(memory, store.as_store_ref())),
+            snapshot,
+            Box::pin(poller),
+            None,
+        )
+    }
+}
+
+/// Resumes a thread on a new instance once its trigger completes, the memory
+/// is either shared with the old instance or restored from `contents`
+#[allow(clippy::too_many_arguments)]
+fn resume_after_poller(
+    tasks: &dyn VirtualTaskManager,
+    task: Box<WasmResumeTask>,
+    env: WasiEnv,
+    module: Module,
+    spawn_type: Option<SpawnType>,
+    snapshot: StoreSnapshot,
+    trigger: ResumeTrigger,
+    contents: Option<Vec<u8>>,
+) -> Result<(), WasiThreadError> {
+    let thread = env.thread.clone();
+    tasks.task_wasm(
+        TaskWasm::new(
+            Box::new(move |props| {
+                let result = props
+                    .trigger_result
+                    .expect("If there is no result then its likely the trigger did not run");
+                let result = match result {
+                    Ok(r) => r,
+                    Err(exit_code) => {
+                        thread.set_status_finished(Ok(exit_code));
+                        return;
+                    }
+                };
+                let (ctx, mut store) = (props.ctx, props.store);
+                if let Some(contents) = contents
+                    && let Err(err) = restore_memory(&ctx, &mut store, &contents)
+                {
+                    tracing::error!("failed to restore the memory of an evicted thread - {err}");
+                    thread.set_status_finished(Ok(Errno::Io.into()));
+                    return;
+                }
+                task(ctx, store, result)
+            }),
+            env.clone(),
+            module,
+            false,
+            false,
         )
+        .with_optional_memory(spawn_type)
+        .with_globals(snapshot)
+        .with_trigger(Box::new(move || {
+            Box::pin(async move {
+                let res = trigger.await;
+                let res = match res {
+                    Ok(res) => res,
+                    Err(exit_code) => {
+                        env.thread.set_status_finished(Ok(exit_code));
+                        return Err(exit_code);
+                    }
+                };
+
+                tracing::trace!("deep sleep woken - res.len={}", res.len());
+                Ok(res)
+            })
+        })),
+    )
+}
+
+/// Writes the memory of an evicted thread into its new instance
+pub(crate) fn restore_memory(
+    ctx: &WasiFunctionEnv,
+    store: &mut Store,
+    contents: &[u8],
+) -> anyhow::Result<()> {
+    let memory = ctx
+        .data(store)
+        .inner()
+        .static_module_instance_handles()
+        .ok_or_else(|| anyhow::anyhow!("the instance has no memory"))?
+        .memory_clone();
+    let size = memory.view(store).data_size();
+    let missing = (contents.len() as u64).saturating_sub(size);
+    if missing > 0 {
+        let pages = missing.div_ceil(wasmer::WASM_PAGE_SIZE as u64);
+        memory.grow(store, wasmer::Pages(pages as u32))?;
+    }
+    memory.view(store).write(0, contents)?;
+    Ok(())
+}
+
+/// A thread in a deep sleep that is evicted when it sleeps for too long
+struct IdleSleeper {
+    tasks: Arc<dyn VirtualTaskManager>,
+    eviction: IdleEviction,
+    task: Box<WasmResumeTask>,
+    env: WasiEnv,
+    module: Module,
+    snapshot: StoreSnapshot,
+}
+
+impl IdleSleeper {
+    async fn run(
+        self,
+        ctx: WasiFunctionEnv,
+        store: Store,
+        memory: Memory,
+        mut poller: AsyncifyPollerOwned,
+    ) {
+        enum Woken {
+            Trigger(Result<Bytes, ExitCode>),
+            Event,
+            Idle,
+        }
+
+        // Anything that would need the instance while the thread sleeps
+        // wakes it up before the timeout
+        let woken = ::tokio::select! {
+            res = &mut poller => Woken::Trigger(res),
+            _ = self.env.thread.wait_for_signal() => Woken::Event,
+            _ = crate::wait_for_snapshot(&self.env) => Woken::Event,
+            _ = self.tasks.sleep_now(self.eviction.timeout) => Woken::Idle,
+        };
+        let trigger: ResumeTrigger = match woken {
+            Woken::Trigger(res) => Box::pin(futures::future::ready(res)),
+            Woken::Event => Box::pin(poller),
+            Woken::Idle => {
+                let saved = memory
+                    .view(&store)
+                    .copy_to_vec()
+                    .map_err(std::io::Error::other)
+                    .and_then(|contents| self.eviction.store.save(contents));
+                match saved {
+                    Ok(key) => {
+                        drop((ctx, memory, store));
+                        return self.evicted(key, poller).await;
+                    }
+                    Err(err) => {
+                        tracing::warn!("failed to evict an idle thread - {err}");
+                        Box::pin(poller)
+                    }
+                }
+            }
+        };
+
+        let spawn_type = SpawnType::ShareMemory(memory, store.as_store_ref());
+        if let Err(err) = resume_after_poller(
+            self.tasks.as_ref(),
+            self.task,
+            self.env,
+            self.module,
+            Some(spawn_type),
+            self.snapshot,
+            trigger,
+            None,
+        ) {
+            tracing::error!("failed to resume a thread after a deep sleep - {err}");
+        }
+    }
+
+    /// Waits for the thread to wake up while its instance is released and
+    /// its memory is held by the eviction store
+    async fn evicted(self, key: u64, mut poller: AsyncifyPollerOwned) {
+        let pid = self.env.pid();
+        let tid = self.env.tid();
+        tracing::debug!(%pid, %tid, "evicted an idle thread");
+
+        let woken = ::tokio::select! {
+            res = &mut poller => Some(res),
+            _ = self.env.thread.wait_for_signal() => None,
+            _ = crate::wait_for_snapshot(&self.env) => None,
+        };
+
+        // A thread that is terminated while it is evicted is never restored
+        if let Some(Err(exit_code)) = woken {
+            self.eviction.store.discard(key);
+            self.env.thread.set_status_finished(Ok(exit_code));
+            return;
+        }
+        let contents = match self.eviction.store.load(key) {
+            Ok(contents) => contents,
+            Err(err) => {
+                tracing::error!(%pid, %tid, "failed to load the memory of an evicted thread - {err}");
+                self.env.thread.set_status_finished(Ok(Errno::Io.into()));
+                return;
+            }
+        };
+        tracing::debug!(%pid, %tid, "restoring an evicted thread");
+
+        let trigger: ResumeTrigger = match woken {
+            Some(res) => Box::pin(futures::future::ready(res)),
+            None => Box::pin(poller),
+        };
+        if let Err(err) = resume_after_poller(
+            self.tasks.as_ref(),
+            self.task,
+            self.env,
+            self.module,
+            None,
+            self.snapshot,
+            trigger,
+            Some(contents),
+        ) {
+            tracing::error!(%pid, %tid, "failed to restore an evicted thread - {err}");
+        }
     }
 }
 
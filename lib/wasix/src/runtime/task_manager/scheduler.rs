@@ -0,0 +1,163 @@
+//! Deterministic scheduling of the WebAssembly tasks, see
+//! [`TokioTaskManager::new_deterministic`](super::tokio::TokioTaskManager::new_deterministic).
+
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Condvar, Mutex},
+    task::{Context, Poll, Wake, Waker},
+};
+
+use rand::{RngExt, SeedableRng, rngs::StdRng};
+use tokio::runtime::Handle;
+
+/// Work that runs on the WebAssembly thread
+pub(super) type WasmJob = Box<dyn FnOnce() + Send + 'static>;
+
+/// Trigger of a task that resumes once it resolves
+pub(super) type WasmJobTrigger = Pin<Box<dyn Future<Output = WasmJob> + Send + 'static>>;
+
+/// Runs all the WebAssembly tasks on one thread and uses a seeded random
+/// number generator to pick which of the runnable tasks goes next.
+///
+/// The triggers of the sleeping tasks are polled on the same thread (in the
+/// order in which the tasks went to sleep) whenever the running task gives
+/// up the thread, so a task that was woken by another one (for instance by
+/// a `futex_wake`) is always runnable by the time the next task is picked.
+#[derive(derive_more::Debug)]
+pub(super) struct DeterministicScheduler {
+    #[debug(ignore)]
+    state: Mutex<SchedulerState>,
+    #[debug(ignore)]
+    notify: Arc<Notify>,
+    #[debug(ignore)]
+    handle: Handle,
+}
+
+struct SchedulerState {
+    rng: StdRng,
+    runnable: Vec<WasmJob>,
+    waiting: Vec<WasmJobTrigger>,
+    /// Whether the thread that runs the tasks is alive
+    running: bool,
+}
+
+impl DeterministicScheduler {
+    pub fn new(handle: Handle, seed: u64) -> Self {
+        Self {
+            state: Mutex::new(SchedulerState {
+                rng: StdRng::seed_from_u64(seed),
+                runnable: Vec::new(),
+                waiting: Vec::new(),
+                running: false,
+            }),
+            notify: Default::default(),
+            handle,
+        }
+    }
+
+    /// Adds a task that can run straight away
+    pub fn run(self: &Arc<Self>, job: WasmJob) {
+        let mut state = self.state.lock().unwrap();
+        state.runnable.push(job);
+        self.start(&mut state);
+    }
+
+    /// Adds a task that becomes runnable once its trigger resolves
+    pub fn run_when_ready(self: &Arc<Self>, trigger: WasmJobTrigger) {
+        let mut state = self.state.lock().unwrap();
+        state.waiting.push(trigger);
+        self.start(&mut state);
+    }
+
+    fn start(self: &Arc<Self>, state: &mut SchedulerState) {
+        self.notify.wake_by_ref();
+        if state.running {
+            return;
+        }
+        state.running = true;
+
+        let this = self.clone();
+        std::thread::Builder::new()
+            .name("TokioTaskManager Deterministic Thread".to_string())
+            .spawn(move || this.run_tasks())
+            .expect("failed to spawn the thread of the deterministic scheduler");
+    }
+
+    /// Runs the tasks until there are none left
+    fn run_tasks(self: Arc<Self>) {
+        let _guard = self.handle.enter();
+        let waker = Waker::from(self.notify.clone());
+        let mut cx = Context::from_waker(&waker);
+
+        loop {
+            // The triggers are polled without holding the lock as they might
+            // add tasks of their own
+            self.notify.clear();
+            let mut waiting = std::mem::take(&mut self.state.lock().unwrap().waiting);
+            let mut ready = Vec::new();
+            waiting.retain_mut(|trigger| match trigger.as_mut().poll(&mut cx) {
+                Poll::Ready(job) => {
+                    ready.push(job);
+                    false
+                }
+                Poll::Pending => true,
+            });
+
+            let job = {
+                let mut state = self.state.lock().unwrap();
+                waiting.append(&mut state.waiting);
+                state.waiting = waiting;
+                state.runnable.extend(ready);
+
+                if state.runnable.is_empty() {
+                    if state.waiting.is_empty() {
+                        state.running = false;
+                        return;
+                    }
+                    None
+                } else {
+                    let len = state.runnable.len();
+                    let idx = state.rng.random_range(0..len);
+                    Some(state.runnable.remove(idx))
+                }
+            };
+
+            match job {
+                Some(job) => job(),
+                None => self.notify.wait(),
+            }
+        }
+    }
+}
+
+/// Wakes up the thread of the scheduler
+#[derive(Default)]
+struct Notify {
+    woken: Mutex<bool>,
+    condvar: Condvar,
+}
+
+impl Notify {
+    fn clear(&self) {
+        *self.woken.lock().unwrap() = false;
+    }
+
+    fn wait(&self) {
+        let mut woken = self.woken.lock().unwrap();
+        while !*woken {
+            woken = self.condvar.wait(woken).unwrap();
+        }
+    }
+}
+
+impl Wake for Notify {
+    fn wake(self: Arc<Self>) {
+        self.wake_by_ref();
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        *self.woken.lock().unwrap() = true;
+        self.condvar.notify_all();
+    }
+}
@@ -9,6 +9,7 @@ use wasmer::AsStoreMut;
 use crate::runtime::SpawnType;
 use crate::{WasiFunctionEnv, os::task::thread::WasiThreadError};
 
+use super::scheduler::{DeterministicScheduler, WasmJob};
 use super::{SpawnMemoryTypeOrStore, TaskWasm, TaskWasmRunProperties, VirtualTaskManager};
 
 #[derive(Debug, Clone)]
@@ -74,6 +75,12 @@ impl std::fmt::Debug for ThreadPool {
 pub struct TokioTaskManager {
     rt: RuntimeOrHandle,
     pool: Arc<ThreadPool>,
+    /// Pool with a single thread that runs all the WebAssembly tasks when
+    /// the task manager is single threaded
+    wasm_pool: Option<Arc<ThreadPool>>,
+    /// Runs all the WebAssembly tasks in a seeded order when the task
+    /// manager is deterministic
+    scheduler: Option<Arc<DeterministicScheduler>>,
 }
 
 impl TokioTaskManager {
@@ -95,6 +102,70 @@ impl TokioTaskManager {
                     .max_size(max_threads)
                     .build(),
             }),
+            wasm_pool: None,
+            scheduler: None,
+        }
+    }
+
+    /// Creates a task manager that runs all the WebAssembly code on one
+    /// thread, for hosts that can't hand out a thread per WASIX thread.
+    ///
+    /// Processes and threads take turns on that thread, a task only hands
+    /// it over to the next one when it goes into a deep sleep, which it
+    /// does straight away when it calls `sched_yield` or has to wait in a
+    /// syscall that supports deep sleeping. This has a few consequences:
+    ///
+    /// - there is no true parallelism, `thread_parallelism` reports a
+    ///   single core
+    /// - modules that were not compiled with asyncify can't go into a deep
+    ///   sleep and hold on to the thread when they block, so one that
+    ///   waits for another thread (or a forked child) never wakes up
+    /// - syscalls that block without deep sleeping (like most file I/O)
+    ///   hold up all the other tasks until they return
+    /// - a task that spins without making any syscalls starves all the
+    ///   other ones
+    pub fn new_single_threaded<I>(rt: I) -> Self
+    where
+        I: Into<RuntimeOrHandle>,
+    {
+        Self {
+            wasm_pool: Some(Arc::new(ThreadPool {
+                inner: rusty_pool::Builder::new()
+                    .name("TokioTaskManager WebAssembly Thread".to_string())
+                    .core_size(1)
+                    .max_size(1)
+                    .build(),
+            })),
+            ..Self::new(rt)
+        }
+    }
+
+    /// Creates a single threaded task manager (see
+    /// [`TokioTaskManager::new_single_threaded`]) that picks which task
+    /// gets the thread next with a random number generator seeded with
+    /// `seed`, this is meant for tests that need to reproduce a data race.
+    ///
+    /// Whenever the running task gives up the thread, the tasks that were
+    /// woken in the meantime (for instance by `futex_wake`, or straight
+    /// away after `sched_yield`) are added to the runnable ones and one of
+    /// them is picked at random. Spawned threads are runnable straight
+    /// away, so a guest that only synchronizes its threads with futexes
+    /// and yields interleaves them the same way for the same seed.
+    ///
+    /// Tasks that are woken by something outside of the guest, like timers
+    /// expiring or I/O becoming ready, still wake up whenever that happens
+    /// and thus make the interleaving depend on the timing of the host.
+    pub fn new_deterministic<I>(rt: I, seed: u64) -> Self
+    where
+        I: Into<RuntimeOrHandle>,
+    {
+        let tasks = Self::new(rt);
+        Self {
+            scheduler: Some(Arc::new(DeterministicScheduler::new(
+                tasks.runtime_handle(),
+                seed,
+            ))),
+            ..tasks
         }
     }
 
@@ -105,6 +176,19 @@ impl TokioTaskManager {
     pub fn pool_handle(&self) -> Arc<ThreadPool> {
         self.pool.clone()
     }
+
+    /// The pool that the WebAssembly tasks run on
+    fn wasm_pool(&self) -> &Arc<ThreadPool> {
+        self.wasm_pool.as_ref().unwrap_or(&self.pool)
+    }
+
+    /// Runs a WebAssembly task
+    fn execute_wasm(&self, job: WasmJob) {
+        match self.scheduler.as_ref() {
+            Some(scheduler) => scheduler.run(job),
+            None => self.wasm_pool().execute(job),
+        }
+    }
 }
 
 impl Default for TokioTaskManager {
@@ -220,8 +304,7 @@ impl VirtualTaskManager for TokioTaskManager {
             let (mut ctx, mut store) = ret?;
 
             let mut trigger = trigger();
-            let pool = self.pool.clone();
-            self.rt.handle().spawn(async move {
+            let resume = async move {
                 // We wait for either the trigger or for a snapshot to take place
                 let result = loop {
                     let env = ctx.data(&store);
@@ -263,7 +346,7 @@ impl VirtualTaskManager for TokioTaskManager {
                 }
 
                 // Build the task that will go on the callback
-                pool.execute(move || {
+                Box::new(move || {
                     // Invoke the callback
                     run(TaskWasmRunProperties {
                         ctx,
@@ -271,26 +354,29 @@ impl VirtualTaskManager for TokioTaskManager {
                         trigger_result: Some(result),
                         recycle,
                     });
-                });
-            });
+                }) as WasmJob
+            };
+
+            match self.scheduler.as_ref() {
+                Some(scheduler) => scheduler.run_when_ready(Box::pin(resume)),
+                None => {
+                    let pool = self.wasm_pool().clone();
+                    self.rt.handle().spawn(async move {
+                        pool.execute(resume.await);
+                    });
+                }
+            }
         } else {
             tracing::trace!("spawning task_wasm in blocking thread");
 
-            let (sx, rx) = std::sync::mpsc::channel();
+            // The instance was already created above, so failures are reported
+            // without waiting for the task to start (which, on a single threaded
+            // task manager, only happens once the calling task gives up the thread)
+            let (mut ctx, mut store) = ret?;
 
             // Run the callback on a dedicated thread
-            self.pool.execute(move || {
+            self.execute_wasm(Box::new(move || {
                 tracing::trace!("task_wasm started in blocking thread");
-                let (mut ctx, mut store) = match ret {
-                    Ok(x) => {
-                        sx.send(Ok(())).unwrap();
-                        x
-                    }
-                    Err(c) => {
-                        sx.send(Err(c)).unwrap();
-                        return;
-                    }
-                };
 
                 if let Some(pre_run) = pre_run {
                     block_on(pre_run(&mut ctx, &mut store));
@@ -303,10 +389,7 @@ impl VirtualTaskManager for TokioTaskManager {
                     trigger_result: None,
                     recycle,
                 });
-            });
-
-            rx.recv()
-                .map_err(|_| WasiThreadError::InvalidWasmContext)??;
+            }));
         }
         Ok(())
     }
@@ -324,10 +407,18 @@ impl VirtualTaskManager for TokioTaskManager {
 
     /// See [`VirtualTaskManager::thread_parallelism`].
     fn thread_parallelism(&self) -> Result<usize, WasiThreadError> {
+        if self.is_single_threaded() {
+            return Ok(1);
+        }
         Ok(std::thread::available_parallelism()
             .map(usize::from)
             .unwrap_or(8))
     }
+
+    /// See [`VirtualTaskManager::is_single_threaded`].
+    fn is_single_threaded(&self) -> bool {
+        self.wasm_pool.is_some() || self.scheduler.is_some()
+    }
 }
 
 // Used by [`VirtualTaskManager::sleep_now`] to abort a sleep task when drop.
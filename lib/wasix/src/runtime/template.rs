@@ -0,0 +1,153 @@
+//! Templates of initialized processes that new processes are cloned from,
+//! see [`ProcessTemplate`].
+
+use std::sync::Mutex;
+
+use wasmer::{AsStoreMut, AsStoreRef, Memory, Module, Store};
+use wasmer_wasix_types::wasi::{Errno, ExitCode};
+
+use crate::{
+    SpawnError, StoreSnapshot, WasiEnv, WasiFunctionEnv,
+    bin_factory::run_exec_initialized,
+    capture_store_snapshot,
+    os::task::TaskJoinHandle,
+    runtime::task_manager::{
+        SpawnMemoryTypeOrStore, SpawnType, TaskWasm, TaskWasmRunProperties, restore_memory,
+    },
+    syscalls::_prepare_wasi,
+};
+
+/// A process that ran its initialization (the `_initialize` function of the
+/// module) once and that new processes are then cloned from, without running
+/// the initialization again. This is how a warmed up interpreter can be
+/// launched many times over (like the zygote of Android).
+///
+/// The template keeps the instance that was initialized. Every clone is a
+/// new process that is forked from the environment of the template (so it
+/// gets a copy of its file descriptors, arguments, environment variables and
+/// signal dispositions) and starts in `_start` with a copy of the globals and
+/// of the linear memory of the template. An imported memory is copied with
+/// copy-on-write, an exported one is written into the memory of the new
+/// instance. The clones never share their memory with each other or with the
+/// template.
+///
+/// Every clone frees its pid when it exits, the pid of the template is freed
+/// when the template is dropped. Dynamically linked modules can't be used as
+/// a template.
+#[derive(Debug)]
+pub struct ProcessTemplate {
+    env: WasiEnv,
+    module: Module,
+    globals: StoreSnapshot,
+    memory: Memory,
+    imported_memory: bool,
+    store: Mutex<Store>,
+}
+
+impl ProcessTemplate {
+    /// Instantiates the module in the environment and runs its `_initialize`
+    /// function (on the calling thread), then keeps the result as a template
+    pub fn capture(module: Module, env: WasiEnv) -> Result<Self, SpawnError> {
+        let imported_memory = module.imports().memories().next().map(|a| *a.ty());
+        let spawn_type = match imported_memory {
+            Some(ty) => SpawnMemoryTypeOrStore::Type(ty),
+            None => SpawnMemoryTypeOrStore::New,
+        };
+        let (ctx, mut store) = WasiFunctionEnv::new_with_store(
+            module.clone(),
+            env,
+            None,
+            spawn_type,
+            true,
+            true,
+            None,
+        )
+        .map_err(|err| SpawnError::Other(Box::new(err)))?;
+
+        let env = ctx.data(&store);
+        let memory = env
+            .inner()
+            .static_module_instance_handles()
+            .ok_or(SpawnError::Unsupported)?
+            .memory_clone();
+        let env = env.clone();
+        let globals = capture_store_snapshot(&mut store.as_store_mut());
+
+        Ok(Self {
+            env,
+            module,
+            globals,
+            memory,
+            imported_memory: imported_memory.is_some(),
+            store: Mutex::new(store),
+        })
+    }
+
+    /// Starts a new process that is cloned from the template, the arguments
+    /// are replaced and the environment variables are merged into the ones
+    /// of the template (just like for the processes that `proc_spawn` starts)
+    pub fn spawn(
+        &self,
+        args: Option<Vec<String>>,
+        envs: Option<Vec<(String, String)>>,
+    ) -> Result<TaskJoinHandle, SpawnError> {
+        let (mut env, handle) = self
+            .env
+            .fork()
+            .map_err(|err| SpawnError::Other(Box::new(err)))?;
+        _prepare_wasi(&mut env, args, envs, None);
+        env.owned_handles.push(handle);
+
+        let join_handle = env.thread.join_handle();
+        let process = env.process.clone();
+        let tasks = env.tasks().clone();
+        let store = self.store.lock().unwrap();
+
+        // An exported memory is created by the new instance, the contents
+        // of the template are written into it before the clone starts
+        let contents = match self.imported_memory {
+            true => None,
+            false => Some(
+                self.memory
+                    .view(&*store)
+                    .copy_to_vec()
+                    .map_err(|_| SpawnError::MemoryAccessViolation)?,
+            ),
+        };
+        let run = move |mut props: TaskWasmRunProperties| {
+            if let Some(contents) = contents
+                && let Err(err) = restore_memory(&props.ctx, &mut props.store, &contents)
+            {
+                tracing::error!("failed to restore the memory of a cloned process - {err}");
+                let env = props.ctx.data(&props.store);
+                env.thread.set_status_finished(Ok(Errno::Io.into()));
+                env.blocking_on_exit(Some(Errno::Io.into()));
+                return;
+            }
+            run_exec_initialized(props)
+        };
+
+        let mut task = TaskWasm::new(Box::new(run), env, self.module.clone(), false, false)
+            .with_globals(self.globals.clone());
+        if self.imported_memory {
+            task = task.with_memory(SpawnType::CopyMemory(
+                self.memory.clone(),
+                store.as_store_ref(),
+            ));
+        }
+        tasks.task_wasm(task).map_err(|err| {
+            process.terminate(Errno::Noexec.into());
+            SpawnError::Other(Box::new(err))
+        })?;
+
+        Ok(join_handle)
+    }
+}
+
+impl Drop for ProcessTemplate {
+    fn drop(&mut self) {
+        // The template itself never runs, its process only goes away (and
+        // frees its pid) once the template does
+        self.env.process.terminate(ExitCode::from(0u16));
+    }
+}
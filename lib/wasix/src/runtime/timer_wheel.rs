@@ -0,0 +1,60 @@
+//! Timers that are shared by all the sleeping threads of the guests.
+
+use std::{
+    fmt,
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll, Waker},
+    time::Instant,
+};
+
+/// A timer that the host shares between all the sleeping guests, so that
+/// thousands of sleeps don't each need a timer of their own.
+///
+/// When the runtime has one (see [`Runtime::timer_wheel`]) the sleeps of
+/// the guests (`thread_sleep`, `clock_nanosleep`, ...) register their
+/// deadline with it instead of creating a timer future through
+/// [`VirtualTaskManager::sleep_now`]. The wheel is free to batch the
+/// wakeups, for instance by only waking up sleepers on every tick of a
+/// fixed resolution.
+///
+/// [`Runtime::timer_wheel`]: crate::Runtime::timer_wheel
+/// [`VirtualTaskManager::sleep_now`]: crate::VirtualTaskManager::sleep_now
+pub trait TimerWheel: fmt::Debug + Send + Sync {
+    /// Wakes up `waker` once `deadline` has passed, it may be woken up later
+    /// than that but never before.
+    ///
+    /// The same sleep registers itself again (with its current waker) every
+    /// time it is woken up before its deadline. A sleep that is interrupted
+    /// (for instance by a signal) does not unregister itself, its waker is
+    /// simply woken up for nothing once the deadline passes.
+    fn register(&self, deadline: Instant, waker: &Waker);
+}
+
+pub type DynTimerWheel = dyn TimerWheel + Send + Sync;
+
+/// Resolves once the deadline has passed, as reported by a [`TimerWheel`].
+#[derive(Debug)]
+pub struct TimerWheelSleep {
+    wheel: Arc<DynTimerWheel>,
+    deadline: Instant,
+}
+
+impl TimerWheelSleep {
+    pub fn new(wheel: Arc<DynTimerWheel>, deadline: Instant) -> Self {
+        Self { wheel, deadline }
+    }
+}
+
+impl Future for TimerWheelSleep {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if Instant::now() >= self.deadline {
+            return Poll::Ready(());
+        }
+        self.wheel.register(self.deadline, cx.waker());
+        Poll::Pending
+    }
+}
@@ -2,13 +2,18 @@
 
 use std::{
     collections::{HashMap, HashSet},
+    num::NonZeroU32,
     path::{Path, PathBuf},
-    sync::Arc,
+    sync::{Arc, RwLock},
 };
 
-use rand::RngExt;
+use rand::{RngExt, SeedableRng, rngs::StdRng};
 use thiserror::Error;
-use virtual_fs::{ArcFile, FileSystem, FsError, TmpFileSystem, VirtualFile};
+use virtual_fs::{
+    ArcFile, FileSystem, FsError, LineObserverFile, MergedOutputFile, NullFile, OverlayFileSystem,
+    PendingFile, TextModeFile, TmpFileSystem, UnionFileSystem, VirtualFile,
+    random_file::RandomSource,
+};
 use wasmer::{AsStoreMut, Engine, Instance, Module};
 use wasmer_config::package::PackageId;
 
@@ -18,13 +23,20 @@ use crate::{
     Runtime, WasiEnv, WasiFunctionEnv, WasiRuntimeError, WasiThreadError,
     bin_factory::{BinFactory, BinaryPackage},
     capabilities::Capabilities,
-    fs::{WasiFs, WasiFsRoot, WasiInodes},
-    os::task::control_plane::{ControlPlaneConfig, ControlPlaneError, WasiControlPlane},
+    fs::{
+        FsWatches, ReopenFailure, WasiFs, WasiFsRoot, WasiInodes,
+        relative_path_hack::RelativeOrAbsolutePathHack,
+    },
+    os::{
+        system_info::{DEFAULT_HOSTNAME, SystemInfo},
+        task::control_plane::{ControlPlaneConfig, ControlPlaneError, WasiControlPlane},
+    },
+    runtime::{OverriddenRuntime, entropy::SeededEntropySource},
     state::WasiState,
     syscalls::types::{__WASI_STDERR_FILENO, __WASI_STDIN_FILENO, __WASI_STDOUT_FILENO},
 };
-use wasmer_types::ModuleHash;
-use wasmer_wasix_types::wasi::SignalDisposition;
+use wasmer_types::{ModuleHash, Pages};
+use wasmer_wasix_types::wasi::{SignalDisposition, Snapshot0Clockid};
 
 use super::env::WasiEnvInit;
 
@@ -64,7 +76,28 @@ pub struct WasiEnvBuilder {
     pub(super) stdout: Option<Box<dyn VirtualFile + Send + Sync + 'static>>,
     pub(super) stderr: Option<Box<dyn VirtualFile + Send + Sync + 'static>>,
     pub(super) stdin: Option<Box<dyn VirtualFile + Send + Sync + 'static>>,
+    /// What `stdin` is when nothing was attached to it.
+    pub(super) absent_stdin: AbsentStdin,
+    /// How line endings are translated on each of the standard streams.
+    pub(super) stdin_mode: StreamMode,
+    pub(super) stdout_mode: StreamMode,
+    pub(super) stderr_mode: StreamMode,
     pub(super) fs: Option<WasiFsRoot>,
+    /// Whether `/dev/null`, `/dev/zero` and friends are added to the file system.
+    pub(super) dev_files: bool,
+    /// Whether the instance runs without any file system.
+    pub(super) no_filesystem: bool,
+    /// Size of the buffer that small writes to files are coalesced in.
+    pub(super) write_buffer_size: Option<usize>,
+    /// The rate that the syscalls of the instance are limited to, see
+    /// [`WasiEnvBuilder::max_syscalls_per_second`].
+    pub(super) max_syscalls_per_second: Option<NonZeroU32>,
+
+    /// Largest memory that a module may import, see
+    /// [`WasiEnvBuilder::max_memory_pages`].
+    pub(super) max_memory_pages: Option<Pages>,
+    /// The watches that report changes to the file system to the guest.
+    pub(super) fs_watches: Option<FsWatches>,
     pub(super) engine: Option<Engine>,
     pub(super) runtime: Option<Arc<dyn crate::Runtime + Send + Sync + 'static>>,
     pub(super) current_dir: Option<PathBuf>,
@@ -81,6 +114,16 @@ pub struct WasiEnvBuilder {
 
     pub(super) capabilites: Capabilities,
 
+    /// What the guest is told about the system by `sysinfo`.
+    pub(super) system_info: SystemInfo,
+
+    /// The hostname of the guest, see [`WasiEnvBuilder::hostname`].
+    pub(super) hostname: Option<String>,
+
+    /// The seed that all the nondeterminism is derived from, see
+    /// [`WasiEnvBuilder::deterministic`].
+    pub(super) deterministic_seed: Option<u64>,
+
     #[cfg(feature = "journal")]
     pub(super) snapshot_on: Vec<SnapshotTrigger>,
 
@@ -98,6 +141,8 @@ pub struct WasiEnvBuilder {
 
     pub(super) skip_stdio_during_bootstrap: bool,
 
+    pub(super) journal_reopen_failure: ReopenFailure,
+
     #[cfg(feature = "ctrlc")]
     pub(super) attach_ctrl_c: bool,
 }
@@ -122,6 +167,45 @@ impl std::fmt::Debug for WasiEnvBuilder {
     }
 }
 
+/// What the guest gets as `stdin` when no file was attached to it with
+/// [`WasiEnvBuilder::stdin`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AbsentStdin {
+    /// Reads report end-of-file right away, so programs that consume their
+    /// input run to completion instead of hanging in headless runs.
+    #[default]
+    Eof,
+    /// Nothing ever becomes readable, so interactive programs keep waiting
+    /// for input.
+    Pending,
+    /// The `stdin` of the host process is used.
+    Inherit,
+}
+
+/// How the line endings of a standard stream are translated, see
+/// [`WasiEnvBuilder::stdout_mode`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum StreamMode {
+    /// The bytes are passed through unchanged.
+    #[default]
+    Binary,
+    /// Line endings are translated like text mode streams do on Windows,
+    /// `\n` is written as `\r\n` and `\r\n` is read as `\n`.
+    Text,
+}
+
+impl StreamMode {
+    fn apply(
+        self,
+        file: Box<dyn VirtualFile + Send + Sync + 'static>,
+    ) -> Box<dyn VirtualFile + Send + Sync + 'static> {
+        match self {
+            StreamMode::Binary => file,
+            StreamMode::Text => Box::new(TextModeFile::new(file)),
+        }
+    }
+}
+
 /// Error type returned when bad data is given to [`WasiEnvBuilder`].
 #[derive(Error, Debug, Clone, PartialEq, Eq)]
 pub enum WasiStateCreationError {
@@ -149,6 +233,52 @@ pub enum WasiStateCreationError {
     ControlPlane(#[from] ControlPlaneError),
 }
 
+/// What is added to the monotonic clock of the host to get the uptime that
+/// `sysinfo` reports, so that it starts at the uptime that overrides the
+/// one of the host (see [`SystemInfo::uptime`]). A deterministic
+/// environment starts at zero when the uptime is not overridden. The
+/// clocks of the guest are left alone.
+fn uptime_offset(system_info: &SystemInfo, deterministic: bool) -> i64 {
+    let uptime = match system_info.uptime {
+        Some(uptime) => i64::try_from(uptime.as_nanos()).unwrap_or(i64::MAX),
+        None if deterministic => 0,
+        None => return 0,
+    };
+    let now = crate::syscalls::platform_clock_time_get(Snapshot0Clockid::Monotonic, 1)
+        .unwrap_or_default();
+    uptime.saturating_sub(now)
+}
+
+/// Where the clocks of the guest start when the environment is
+/// [deterministic](WasiEnvBuilder::deterministic), the realtime clock
+/// starts at a whole second in the years after 2000 and the monotonic clock
+/// at the overridden uptime (or zero).
+fn deterministic_clock_offset(
+    system_info: &SystemInfo,
+    rng: &mut StdRng,
+) -> HashMap<Snapshot0Clockid, i64> {
+    const YEAR_2000: i64 = 946_684_800;
+    const NANOS_PER_SECOND: i64 = 1_000_000_000;
+
+    let mut offsets = HashMap::new();
+    let realtime =
+        crate::syscalls::platform_clock_time_get(Snapshot0Clockid::Realtime, 1).unwrap_or_default();
+    let start = (YEAR_2000 + rng.random_range(0..(20 * 365 * 24 * 3600))) * NANOS_PER_SECOND;
+    offsets.insert(Snapshot0Clockid::Realtime, start - realtime);
+
+    let monotonic = crate::syscalls::platform_clock_time_get(Snapshot0Clockid::Monotonic, 1)
+        .unwrap_or_default();
+    let uptime = system_info
+        .uptime
+        .map(|uptime| i64::try_from(uptime.as_nanos()).unwrap_or(i64::MAX))
+        .unwrap_or_default();
+    offsets.insert(
+        Snapshot0Clockid::Monotonic,
+        uptime.saturating_sub(monotonic),
+    );
+    offsets
+}
+
 fn validate_mapped_dir_alias(alias: &str) -> Result<(), WasiStateCreationError> {
     if !alias.bytes().all(|b| b != b'\0') {
         return Err(WasiStateCreationError::MappedDirAliasFormattingError(
@@ -183,8 +313,8 @@ impl WasiEnvBuilder {
     /// Add an environment variable pair.
     ///
     /// Both the key and value of an environment variable must not
-    /// contain a nul byte (`0x0`), and the key must not contain the
-    /// `=` byte (`0x3d`).
+    /// contain a nul byte (`0x0`), and the key must not be empty or
+    /// contain the `=` byte (`0x3d`).
     pub fn env<Key, Value>(mut self, key: Key, value: Value) -> Self
     where
         Key: AsRef<[u8]>,
@@ -197,8 +327,8 @@ impl WasiEnvBuilder {
     /// Add an environment variable pair.
     ///
     /// Both the key and value of an environment variable must not
-    /// contain a nul byte (`0x0`), and the key must not contain the
-    /// `=` byte (`0x3d`).
+    /// contain a nul byte (`0x0`), and the key must not be empty or
+    /// contain the `=` byte (`0x3d`).
     pub fn add_env<Key, Value>(&mut self, key: Key, value: Value)
     where
         Key: AsRef<[u8]>,
@@ -679,6 +809,75 @@ impl WasiEnvBuilder {
         self.stderr = Some(new_file);
     }
 
+    /// Sends what the guest writes to `stdout` and `stderr` to `sink` as a
+    /// single stream, in the order in which the guest wrote it (see
+    /// [`MergedOutputFile`]). This replaces the `stdout` and `stderr` that
+    /// were set before.
+    pub fn combined_output(mut self, sink: Box<dyn VirtualFile + Send + Sync + 'static>) -> Self {
+        self.set_combined_output(sink);
+        self
+    }
+
+    /// Sends what the guest writes to `stdout` and `stderr` to `sink` as a
+    /// single stream, in the order in which the guest wrote it (see
+    /// [`MergedOutputFile`]). This replaces the `stdout` and `stderr` that
+    /// were set before.
+    pub fn set_combined_output(&mut self, sink: Box<dyn VirtualFile + Send + Sync + 'static>) {
+        let (stdout, stderr) = MergedOutputFile::pair(sink);
+        self.stdout = Some(Box::new(stdout));
+        self.stderr = Some(Box::new(stderr));
+    }
+
+    /// Delivers everything the guest writes to `stdout` to `on_line`, one
+    /// line at a time and without the trailing newline.
+    ///
+    /// The output is still passed on to the `stdout` that was set before this
+    /// call (or the default one). A partial line at the end of the output is
+    /// delivered when the process exits.
+    pub fn stdout_lines(mut self, on_line: impl FnMut(&[u8]) + Send + Sync + 'static) -> Self {
+        self.set_stdout_lines(on_line);
+        self
+    }
+
+    /// Delivers everything the guest writes to `stdout` to `on_line`, one
+    /// line at a time and without the trailing newline.
+    ///
+    /// The output is still passed on to the `stdout` that was set before this
+    /// call (or the default one). A partial line at the end of the output is
+    /// delivered when the process exits.
+    pub fn set_stdout_lines(&mut self, on_line: impl FnMut(&[u8]) + Send + Sync + 'static) {
+        let inner = self
+            .stdout
+            .take()
+            .unwrap_or_else(|| Box::<super::Stdout>::default());
+        self.stdout = Some(Box::new(LineObserverFile::new(inner, on_line)));
+    }
+
+    /// Delivers everything the guest writes to `stderr` to `on_line`, one
+    /// line at a time and without the trailing newline.
+    ///
+    /// The output is still passed on to the `stderr` that was set before this
+    /// call (or the default one). A partial line at the end of the output is
+    /// delivered when the process exits.
+    pub fn stderr_lines(mut self, on_line: impl FnMut(&[u8]) + Send + Sync + 'static) -> Self {
+        self.set_stderr_lines(on_line);
+        self
+    }
+
+    /// Delivers everything the guest writes to `stderr` to `on_line`, one
+    /// line at a time and without the trailing newline.
+    ///
+    /// The output is still passed on to the `stderr` that was set before this
+    /// call (or the default one). A partial line at the end of the output is
+    /// delivered when the process exits.
+    pub fn set_stderr_lines(&mut self, on_line: impl FnMut(&[u8]) + Send + Sync + 'static) {
+        let inner = self
+            .stderr
+            .take()
+            .unwrap_or_else(|| Box::<super::Stderr>::default());
+        self.stderr = Some(Box::new(LineObserverFile::new(inner, on_line)));
+    }
+
     /// Overwrite the default WASI `stdin`, if you want to hold on to the
     /// original `stdin` use [`WasiFs::swap_file`] after building.
     pub fn stdin(mut self, new_file: Box<dyn VirtualFile + Send + Sync + 'static>) -> Self {
@@ -693,6 +892,59 @@ impl WasiEnvBuilder {
         self.stdin = Some(new_file);
     }
 
+    /// Selects what `stdin` is when no file was attached to it, by default
+    /// reading it reports end-of-file.
+    pub fn absent_stdin(mut self, absent_stdin: AbsentStdin) -> Self {
+        self.absent_stdin = absent_stdin;
+
+        self
+    }
+
+    /// Selects what `stdin` is when no file was attached to it, by default
+    /// reading it reports end-of-file.
+    pub fn set_absent_stdin(&mut self, absent_stdin: AbsentStdin) {
+        self.absent_stdin = absent_stdin;
+    }
+
+    /// Selects how the line endings that the guest reads from `stdin` are
+    /// translated, by default the bytes are passed through unchanged.
+    pub fn stdin_mode(mut self, mode: StreamMode) -> Self {
+        self.set_stdin_mode(mode);
+        self
+    }
+
+    /// Selects how the line endings that the guest reads from `stdin` are
+    /// translated, by default the bytes are passed through unchanged.
+    pub fn set_stdin_mode(&mut self, mode: StreamMode) {
+        self.stdin_mode = mode;
+    }
+
+    /// Selects how the line endings that the guest writes to `stdout` are
+    /// translated, by default the bytes are passed through unchanged.
+    pub fn stdout_mode(mut self, mode: StreamMode) -> Self {
+        self.set_stdout_mode(mode);
+        self
+    }
+
+    /// Selects how the line endings that the guest writes to `stdout` are
+    /// translated, by default the bytes are passed through unchanged.
+    pub fn set_stdout_mode(&mut self, mode: StreamMode) {
+        self.stdout_mode = mode;
+    }
+
+    /// Selects how the line endings that the guest writes to `stderr` are
+    /// translated, by default the bytes are passed through unchanged.
+    pub fn stderr_mode(mut self, mode: StreamMode) -> Self {
+        self.set_stderr_mode(mode);
+        self
+    }
+
+    /// Selects how the line endings that the guest writes to `stderr` are
+    /// translated, by default the bytes are passed through unchanged.
+    pub fn set_stderr_mode(&mut self, mode: StreamMode) {
+        self.stderr_mode = mode;
+    }
+
     /// Sets the FileSystem to be used with this WASI instance.
     ///
     /// This is usually used in case a custom `virtual_fs::FileSystem` is needed.
@@ -709,6 +961,37 @@ impl WasiEnvBuilder {
         self.fs = Some(fs);
     }
 
+    /// Uses a writable `upper` file system on top of a read-only `lower` one
+    /// as the FileSystem of this WASI instance.
+    ///
+    /// Files are looked up in the upper layer first and fall through to the
+    /// lower layer. Writing to a file of the lower layer copies it up into
+    /// the upper layer first and removing one leaves a whiteout in the upper
+    /// layer, the lower layer itself is never modified. The packages used by
+    /// the instance are added to the lower layer.
+    pub fn overlay_fs(
+        mut self,
+        upper: TmpFileSystem,
+        lower: impl Into<Arc<dyn virtual_fs::FileSystem + Send + Sync>>,
+    ) -> Result<Self, WasiStateCreationError> {
+        self.set_overlay_fs(upper, lower)?;
+        Ok(self)
+    }
+
+    /// Uses a writable `upper` file system on top of a read-only `lower` one
+    /// as the FileSystem of this WASI instance, see [`WasiEnvBuilder::overlay_fs`].
+    pub fn set_overlay_fs(
+        &mut self,
+        upper: TmpFileSystem,
+        lower: impl Into<Arc<dyn virtual_fs::FileSystem + Send + Sync>>,
+    ) -> Result<(), WasiStateCreationError> {
+        let union = UnionFileSystem::new();
+        union.mount("lower".to_string(), Path::new("/"), Box::new(lower.into()))?;
+        let overlay = OverlayFileSystem::new(upper, [RelativeOrAbsolutePathHack(union)]);
+        self.fs = Some(WasiFsRoot::Overlay(Arc::new(overlay)));
+        Ok(())
+    }
+
     /// Sets a new sandbox FileSystem to be used with this WASI instance.
     ///
     /// This is usually used in case a custom `virtual_fs::FileSystem` is needed.
@@ -717,6 +1000,118 @@ impl WasiEnvBuilder {
         self
     }
 
+    /// Adds the virtual device files `/dev/null`, `/dev/zero`, `/dev/full`,
+    /// `/dev/random` and `/dev/urandom` to the file system of the instance,
+    /// see [`virtual_fs::insert_default_devices`].
+    ///
+    /// This is off by default, file systems that are built with
+    /// [`virtual_fs::RootFileSystemBuilder`] already contain them.
+    pub fn dev_files(mut self, enabled: bool) -> Self {
+        self.set_dev_files(enabled);
+        self
+    }
+
+    /// Adds the virtual device files `/dev/null`, `/dev/zero`, `/dev/full`,
+    /// `/dev/random` and `/dev/urandom` to the file system of the instance,
+    /// see [`virtual_fs::insert_default_devices`].
+    ///
+    /// This is off by default, file systems that are built with
+    /// [`virtual_fs::RootFileSystemBuilder`] already contain them.
+    pub fn set_dev_files(&mut self, enabled: bool) {
+        self.dev_files = enabled;
+    }
+
+    /// Runs the instance without any file system, for guests that only use
+    /// the network. Every path based syscall fails with `Errno::Notcapable`
+    /// and the only descriptors are the ones of stdio (and the sockets and
+    /// pipes that the guest creates), there is no root descriptor.
+    ///
+    /// This is stricter than leaving out the preopens, building fails when
+    /// a file system, preopens or a current directory are configured too.
+    pub fn no_filesystem(mut self) -> Self {
+        self.set_no_filesystem(true);
+        self
+    }
+
+    /// Runs the instance without any file system, see
+    /// [`WasiEnvBuilder::no_filesystem`].
+    pub fn set_no_filesystem(&mut self, enabled: bool) {
+        self.no_filesystem = enabled;
+    }
+
+    /// Coalesces the writes to files that the guest opens for writing in a
+    /// buffer of `size` bytes, which saves a host syscall for every small
+    /// write (see [`virtual_fs::BufferedWriteFile`]).
+    ///
+    /// The buffer of a file is written out when it is full, when the guest
+    /// syncs or closes the file and on `proc_flush`. Other descriptors of
+    /// the same file don't see the buffered data before that.
+    pub fn write_buffer_size(mut self, size: usize) -> Self {
+        self.set_write_buffer_size(size);
+        self
+    }
+
+    /// Coalesces the writes to files that the guest opens for writing, see
+    /// [`WasiEnvBuilder::write_buffer_size`].
+    pub fn set_write_buffer_size(&mut self, size: usize) {
+        self.write_buffer_size = Some(size).filter(|size| *size > 0);
+    }
+
+    /// Limits the number of syscalls per second that the threads of a
+    /// process can make together, to throttle untrusted guests. A thread
+    /// that exceeds the limit is put to sleep when it enters a syscall until
+    /// the rate drops below the limit again.
+    ///
+    /// Every process gets a budget of its own, the processes that it forks
+    /// or spawns are limited to the same rate. Cheap syscalls that only read
+    /// the state of the environment (like `args_get`, `clock_res_get` or
+    /// `thread_id`) are never limited.
+    pub fn max_syscalls_per_second(mut self, limit: NonZeroU32) -> Self {
+        self.set_max_syscalls_per_second(limit);
+        self
+    }
+
+    /// Limits the number of syscalls per second of the instance, see
+    /// [`WasiEnvBuilder::max_syscalls_per_second`].
+    pub fn set_max_syscalls_per_second(&mut self, limit: NonZeroU32) {
+        self.max_syscalls_per_second = Some(limit);
+    }
+
+    /// Limits the memory that a module may import to `max` pages, a module
+    /// whose imported memory can grow beyond it (or has no maximum) fails to
+    /// spawn with [`SpawnError::UnsatisfiableMemoryImport`].
+    ///
+    /// Without a limit the module may import any memory that the engine
+    /// (and its tunables) can create.
+    ///
+    /// [`SpawnError::UnsatisfiableMemoryImport`]: crate::SpawnError::UnsatisfiableMemoryImport
+    pub fn max_memory_pages(mut self, max: Pages) -> Self {
+        self.set_max_memory_pages(max);
+        self
+    }
+
+    /// Limits the memory that a module may import, see
+    /// [`WasiEnvBuilder::max_memory_pages`].
+    pub fn set_max_memory_pages(&mut self, max: Pages) {
+        self.max_memory_pages = Some(max);
+    }
+
+    /// Shares the watches on the file system of the instance with the host,
+    /// which keeps a clone of them to report changes to the guest that it
+    /// makes behind its back, see [`FsWatches::notify`].
+    ///
+    /// The guest adds the watches itself with `fs_watch_add`.
+    pub fn fs_watches(mut self, watches: FsWatches) -> Self {
+        self.set_fs_watches(watches);
+        self
+    }
+
+    /// Shares the watches on the file system of the instance with the host,
+    /// see [`WasiEnvBuilder::fs_watches`].
+    pub fn set_fs_watches(&mut self, watches: FsWatches) {
+        self.fs_watches = Some(watches);
+    }
+
     /// Configure the WASI filesystem before running.
     // TODO: improve ergonomics on this function
     pub fn setup_fs(mut self, setup_fs_fn: SetupFsFn) -> Self {
@@ -760,6 +1155,82 @@ impl WasiEnvBuilder {
         self.capabilites = capabilities;
     }
 
+    /// Overrides what the guest is told about the system (the number of
+    /// CPUs, the amount of memory, ...) by the `sysinfo` syscall, the values
+    /// that are not set come from the host.
+    pub fn system_info(mut self, system_info: SystemInfo) -> Self {
+        self.set_system_info(system_info);
+        self
+    }
+
+    /// Overrides what the guest is told about the system (the number of
+    /// CPUs, the amount of memory, ...) by the `sysinfo` syscall, the values
+    /// that are not set come from the host.
+    pub fn set_system_info(&mut self, system_info: SystemInfo) {
+        self.system_info = system_info;
+    }
+
+    /// Sets the hostname that the guest gets from `gethostname` instead of
+    /// the one of the host (defaults to `localhost`).
+    ///
+    /// The guest can only change it with `sethostname` when
+    /// [`Capabilities::allow_set_hostname`] is set.
+    pub fn hostname(mut self, hostname: impl Into<String>) -> Self {
+        self.set_hostname(hostname);
+        self
+    }
+
+    /// Sets the hostname that the guest gets from `gethostname` instead of
+    /// the one of the host (defaults to `localhost`).
+    ///
+    /// The guest can only change it with `sethostname` when
+    /// [`Capabilities::allow_set_hostname`] is set.
+    pub fn set_hostname(&mut self, hostname: impl Into<String>) {
+        self.hostname = Some(hostname.into());
+    }
+
+    /// Derives everything that would otherwise differ between runs from
+    /// `seed`, so that a guest that is run twice with the same seed (and
+    /// the same inputs) behaves the same way, which is what fuzzers need to
+    /// reproduce a crash.
+    ///
+    /// The seed covers:
+    /// - the bytes returned by `random_get` (see [`SeededEntropySource`]),
+    ///   and the ones read from `/dev/random` and `/dev/urandom` when they
+    ///   are the virtual device files
+    /// - the secret that protects the stack snapshots of the guest
+    /// - the rotation of the subscriptions in `poll_oneoff`
+    /// - where the clocks start, the realtime clock starts at a time that
+    ///   is derived from the seed and the monotonic clock starts at zero
+    ///   (or at the uptime of [`WasiEnvBuilder::system_info`])
+    ///
+    /// The order in which the threads of the guest run is only covered when
+    /// the [runtime](WasiEnvBuilder::runtime) uses a task manager that was
+    /// created with [`TokioTaskManager::new_deterministic`] (which needs
+    /// guests that were built with asyncify), ideally with the same seed.
+    ///
+    /// It does not cover threads that really run in parallel on the host,
+    /// or what is read from the file system and the network. Neither does
+    /// it cover anything that advances at the rate of the host:
+    /// - the clocks after they started (including the CPU time clocks)
+    /// - `proc_uptime`, which counts from when the process was created
+    /// - `perf_counter_read`, which reads the counter of the host
+    /// - how much of its timeout is left when a poll or sleep is restarted
+    ///   after a signal handler ran
+    ///
+    /// [`SeededEntropySource`]: crate::runtime::entropy::SeededEntropySource
+    /// [`TokioTaskManager::new_deterministic`]: crate::runtime::task_manager::tokio::TokioTaskManager::new_deterministic
+    pub fn deterministic(mut self, seed: u64) -> Self {
+        self.set_deterministic(seed);
+        self
+    }
+
+    /// Derives everything that would otherwise differ between runs from
+    /// `seed`, see [`WasiEnvBuilder::deterministic`].
+    pub fn set_deterministic(&mut self, seed: u64) {
+        self.deterministic_seed = Some(seed);
+    }
+
     #[cfg(feature = "journal")]
     pub fn add_snapshot_trigger(&mut self, on: SnapshotTrigger) {
         self.snapshot_on.push(on);
@@ -779,6 +1250,13 @@ impl WasiEnvBuilder {
         self.skip_stdio_during_bootstrap = skip;
     }
 
+    /// Decides what happens to the file descriptors that are restored from
+    /// a journal when their file can't be opened anymore (for instance
+    /// because it was moved on the host), see [`ReopenFailure`]
+    pub fn with_journal_reopen_failure(&mut self, on_failure: ReopenFailure) {
+        self.journal_reopen_failure = on_failure;
+    }
+
     /// Consumes the [`WasiEnvBuilder`] and produces a [`WasiEnvInit`], which
     /// can be used to construct a new [`WasiEnv`].
     ///
@@ -802,6 +1280,16 @@ impl WasiEnvBuilder {
         }
 
         for (env_key, env_value) in self.envs.iter() {
+            // Otherwise the guest would see a `=value` entry without a key
+            if env_key.is_empty() {
+                return Err(WasiStateCreationError::EnvironmentVariableFormatError(
+                    format!(
+                        "found empty env var key for value \"{}\" (key=value)",
+                        String::from_utf8_lossy(env_value),
+                    ),
+                ));
+            }
+
             match env_key.as_bytes().iter().find_map(|&ch| {
                 if ch == 0 {
                     Some(InvalidCharacter::Nul)
@@ -837,17 +1325,81 @@ impl WasiEnvBuilder {
         }
 
         // Determine the STDIN
-        let stdin: Box<dyn VirtualFile + Send + Sync + 'static> = self
-            .stdin
-            .take()
-            .unwrap_or_else(|| Box::new(ArcFile::new(Box::<super::Stdin>::default())));
+        let stdin: Box<dyn VirtualFile + Send + Sync + 'static> = match self.stdin.take() {
+            Some(stdin) => stdin,
+            None => match self.absent_stdin {
+                AbsentStdin::Eof => Box::<NullFile>::default(),
+                AbsentStdin::Pending => Box::<PendingFile>::default(),
+                AbsentStdin::Inherit => Box::new(ArcFile::new(Box::<super::Stdin>::default())),
+            },
+        };
+        let stdin = self.stdin_mode.apply(stdin);
 
-        let fs_backing = self
-            .fs
-            .take()
-            .unwrap_or_else(|| WasiFsRoot::Sandbox(TmpFileSystem::new()));
+        // The default streams are only replaced when they are translated
+        let stdout = match (self.stdout.take(), self.stdout_mode) {
+            (None, StreamMode::Binary) => None,
+            (stdout, mode) => {
+                Some(mode.apply(stdout.unwrap_or_else(|| Box::<super::Stdout>::default())))
+            }
+        };
+        let stderr = match (self.stderr.take(), self.stderr_mode) {
+            (None, StreamMode::Binary) => None,
+            (stderr, mode) => {
+                Some(mode.apply(stderr.unwrap_or_else(|| Box::<super::Stderr>::default())))
+            }
+        };
 
-        if let Some(dir) = &self.current_dir {
+        if self.no_filesystem
+            && (self.fs.is_some()
+                || !self.preopens.is_empty()
+                || !self.vfs_preopens.is_empty()
+                || self.current_dir.is_some())
+        {
+            return Err(WasiStateCreationError::WasiFsSetupError(
+                "a file system was configured for an instance without one".to_string(),
+            ));
+        }
+
+        // An instance without a file system does not get a sandbox either
+        let fs_backing = if self.no_filesystem {
+            None
+        } else {
+            Some(
+                self.fs
+                    .take()
+                    .unwrap_or_else(|| WasiFsRoot::Sandbox(TmpFileSystem::new())),
+            )
+        };
+
+        // The device files that are mounted on a backing file system, their
+        // random devices are bound to the runtime below
+        let mut mounted_devices = None;
+        if self.dev_files
+            && let Some(fs_backing) = &fs_backing
+        {
+            let dev = Path::new("/dev");
+            match fs_backing {
+                WasiFsRoot::Sandbox(fs) => virtual_fs::insert_default_devices(fs, dev),
+                WasiFsRoot::Overlay(overlay) => {
+                    virtual_fs::insert_default_devices(overlay.primary(), dev)
+                }
+                WasiFsRoot::Backing(fs) => {
+                    let devices = TmpFileSystem::new();
+                    virtual_fs::insert_default_devices(&devices, Path::new("/"));
+                    fs.mount("dev".to_string(), dev, Box::new(devices.clone()))
+                        .map_err(|err| {
+                            WasiStateCreationError::WasiFsSetupError(format!(
+                                "Could not mount the device files at '/dev': {err}"
+                            ))
+                        })?;
+                    mounted_devices = Some(devices);
+                }
+            }
+        }
+
+        if let Some(dir) = &self.current_dir
+            && let Some(fs_backing) = &fs_backing
+        {
             match fs_backing.read_dir(dir) {
                 Ok(_) => {
                     // All good
@@ -873,22 +1425,34 @@ impl WasiEnvBuilder {
         let inodes = crate::state::WasiInodes::new();
         let wasi_fs = {
             // self.preopens are checked in [`PreopenDirBuilder::build`]
-            let mut wasi_fs =
-                WasiFs::new_with_preopen(&inodes, &self.preopens, &self.vfs_preopens, fs_backing)
-                    .map_err(WasiStateCreationError::WasiFsCreationError)?;
+            let mut wasi_fs = match fs_backing {
+                Some(fs_backing) => WasiFs::new_with_preopen(
+                    &inodes,
+                    &self.preopens,
+                    &self.vfs_preopens,
+                    fs_backing,
+                )
+                .map_err(WasiStateCreationError::WasiFsCreationError)?,
+                None => WasiFs::new_without_filesystem(&inodes),
+            };
+            wasi_fs.allowed_paths = self.capabilites.sandbox.allowed_paths.clone();
+            wasi_fs.write_buffer_size = self.write_buffer_size;
+            if let Some(watches) = self.fs_watches.clone() {
+                wasi_fs.watches = watches;
+            }
 
             // set up the file system, overriding base files and calling the setup function
             wasi_fs
                 .swap_file(__WASI_STDIN_FILENO, stdin)
                 .map_err(WasiStateCreationError::FileSystemError)?;
 
-            if let Some(stdout_override) = self.stdout.take() {
+            if let Some(stdout_override) = stdout {
                 wasi_fs
                     .swap_file(__WASI_STDOUT_FILENO, stdout_override)
                     .map_err(WasiStateCreationError::FileSystemError)?;
             }
 
-            if let Some(stderr_override) = self.stderr.take() {
+            if let Some(stderr_override) = stderr {
                 wasi_fs
                     .swap_file(__WASI_STDERR_FILENO, stderr_override)
                     .map_err(WasiStateCreationError::FileSystemError)?;
@@ -914,14 +1478,35 @@ impl WasiEnvBuilder {
             wasi_fs.has_unioned.lock().unwrap().insert(id.clone());
         }
 
+        // Everything that is random is derived from the seed, one after the
+        // other, so that it is the same for every run
+        let mut seeded_rng = self.deterministic_seed.map(StdRng::seed_from_u64);
+        let secret = match seeded_rng.as_mut() {
+            Some(rng) => rng.random::<[u8; 32]>(),
+            None => rand::rng().random::<[u8; 32]>(),
+        };
+        let clock_offset = match seeded_rng.as_mut() {
+            Some(rng) => deterministic_clock_offset(&self.system_info, rng),
+            None => HashMap::new(),
+        };
+        let uptime_offset = uptime_offset(&self.system_info, seeded_rng.is_some());
+        let poll_seed = seeded_rng
+            .as_mut()
+            .map(|rng| rng.random::<u32>() as u64)
+            .unwrap_or(0);
+        // The entropy that the guest can read has its own seed, drawn after
+        // the secret, so that the secret can't be recovered from it
+        let entropy_seed = seeded_rng.as_mut().map(|rng| rng.random::<u64>());
+
         let state = WasiState {
             fs: wasi_fs,
-            secret: rand::rng().random::<[u8; 32]>(),
+            secret,
             inodes,
             args: std::sync::Mutex::new(self.args.clone()),
             preopen: self.vfs_preopens.clone(),
             futexs: Default::default(),
-            clock_offset: Default::default(),
+            clock_offset: std::sync::Mutex::new(clock_offset),
+            uptime_offset,
             envs: std::sync::Mutex::new(conv_env_vars(self.envs)),
             signals: std::sync::Mutex::new(self.signals.iter().map(|s| (s.sig, s.disp)).collect()),
         };
@@ -961,6 +1546,38 @@ impl WasiEnvBuilder {
             }
         });
 
+        let runtime = match entropy_seed {
+            Some(seed) => Arc::new(
+                OverriddenRuntime::new(runtime)
+                    .with_entropy_source(Arc::new(SeededEntropySource::new(seed))),
+            ),
+            None => runtime,
+        };
+
+        // `/dev/random` and `/dev/urandom` hand out the same entropy as
+        // `random_get` does
+        let random_source: RandomSource = {
+            let runtime = runtime.clone();
+            Arc::new(move |buf: &mut [u8]| {
+                runtime
+                    .entropy_source()
+                    .fill(buf)
+                    .map_err(std::io::Error::other)
+            })
+        };
+        match (&state.fs.root_fs, mounted_devices) {
+            (_, Some(devices)) => {
+                virtual_fs::bind_random_devices(&devices, Path::new("/"), random_source)
+            }
+            (WasiFsRoot::Sandbox(fs), None) => {
+                virtual_fs::bind_random_devices(fs, Path::new("/dev"), random_source)
+            }
+            (WasiFsRoot::Overlay(overlay), None) => {
+                virtual_fs::bind_random_devices(overlay.primary(), Path::new("/dev"), random_source)
+            }
+            (WasiFsRoot::Backing(_), None) => {}
+        }
+
         let uses = self.uses;
         let map_commands = self.map_commands;
 
@@ -972,6 +1589,7 @@ impl WasiEnvBuilder {
             max_task_count: capabilities.threading.max_threads,
             enable_asynchronous_threading: capabilities.threading.enable_asynchronous_threading,
             enable_exponential_cpu_backoff: capabilities.threading.enable_exponential_cpu_backoff,
+            max_forks_per_second: capabilities.threading.max_forks_per_second,
         };
         let control_plane = WasiControlPlane::new(plane_config);
 
@@ -998,6 +1616,15 @@ impl WasiEnvBuilder {
             #[cfg(feature = "journal")]
             stop_running_after_snapshot: self.stop_running_after_snapshot,
             skip_stdio_during_bootstrap: self.skip_stdio_during_bootstrap,
+            journal_reopen_failure: self.journal_reopen_failure,
+            max_syscalls_per_second: self.max_syscalls_per_second,
+            max_memory_pages: self.max_memory_pages,
+            system_info: self.system_info,
+            hostname: Arc::new(RwLock::new(
+                self.hostname
+                    .unwrap_or_else(|| DEFAULT_HOSTNAME.to_string()),
+            )),
+            poll_seed,
         };
 
         Ok(init)
@@ -1185,8 +1812,65 @@ impl PreopenDirBuilder {
 
 #[cfg(test)]
 mod test {
+    use virtual_fs::{AsyncReadExt, AsyncWriteExt};
+    use wasmer_wasix_types::wasi::Errno;
+
     use super::*;
 
+    #[tokio::test]
+    async fn overlay_fs_keeps_the_lower_layer_intact() {
+        let lower = TmpFileSystem::new();
+        lower.create_dir(Path::new("/data")).unwrap();
+        for name in ["/data/a.txt", "/data/b.txt"] {
+            let mut file = lower
+                .new_open_options()
+                .write(true)
+                .create(true)
+                .open(name)
+                .unwrap();
+            file.write_all(b"lower").await.unwrap();
+        }
+        let upper = TmpFileSystem::new();
+
+        let init = WasiEnv::builder("test_prog")
+            .engine(Engine::default())
+            .overlay_fs(upper.clone(), Arc::new(lower.clone()) as Arc<_>)
+            .unwrap()
+            .build_init()
+            .unwrap();
+        let fs = &init.state.fs.root_fs;
+
+        let read = |fs: &dyn FileSystem, path: &str| {
+            let mut file = fs.new_open_options().read(true).open(path).unwrap();
+            let mut contents = String::new();
+            virtual_mio::block_on(file.read_to_string(&mut contents)).unwrap();
+            contents
+        };
+
+        // Writing copies the file up into the upper layer
+        let mut file = fs
+            .new_open_options()
+            .write(true)
+            .append(true)
+            .open("/data/a.txt")
+            .unwrap();
+        file.write_all(b" upper").await.unwrap();
+        file.flush().await.unwrap();
+        drop(file);
+        assert_eq!(read(fs, "/data/a.txt"), "lower upper");
+        assert_eq!(read(&upper, "/data/a.txt"), "lower upper");
+        assert_eq!(read(&lower, "/data/a.txt"), "lower");
+
+        // Removing a file of the lower layer leaves a whiteout behind
+        fs.remove_file(Path::new("/data/b.txt")).unwrap();
+        assert_eq!(
+            fs.metadata(Path::new("/data/b.txt")).unwrap_err(),
+            FsError::EntryNotFound
+        );
+        assert!(upper.metadata(Path::new("/data/.wh.b.txt")).is_ok());
+        assert_eq!(read(&lower, "/data/b.txt"), "lower");
+    }
+
     #[test]
     fn env_var_errors() {
         #[cfg(not(target_arch = "wasm32"))]
@@ -1226,6 +1910,15 @@ mod test {
             "nul in value must be invalid"
         );
 
+        // An empty key is invalid.
+        assert!(
+            WasiEnvBuilder::new("test_prog")
+                .env("", "/home/home")
+                .build_init()
+                .is_err(),
+            "empty key must be invalid"
+        );
+
         // `=` in the value is valid.
         assert!(
             WasiEnvBuilder::new("test_prog")
@@ -1257,4 +1950,153 @@ mod test {
             WasiStateCreationError::ArgumentContainsNulByte(_)
         ));
     }
+
+    #[test]
+    fn build_reports_malformed_args_and_env_vars() {
+        // The errors surface before anything is set up for the guest
+        let err = WasiEnvBuilder::new("test_prog")
+            .arg("a\0b")
+            .build()
+            .expect_err("should fail");
+        assert!(matches!(
+            err,
+            WasiRuntimeError::Init(WasiStateCreationError::ArgumentContainsNulByte(_))
+        ));
+
+        let err = WasiEnvBuilder::new("test_prog")
+            .env("", "value")
+            .build()
+            .expect_err("should fail");
+        assert!(matches!(
+            err,
+            WasiRuntimeError::Init(WasiStateCreationError::EnvironmentVariableFormatError(_))
+        ));
+    }
+
+    #[test]
+    fn no_filesystem_conflicts_with_a_configured_one() {
+        let err = WasiEnvBuilder::new("test_prog")
+            .no_filesystem()
+            .current_dir("/app")
+            .build_init()
+            .expect_err("should fail");
+        assert!(matches!(err, WasiStateCreationError::WasiFsSetupError(_)));
+
+        let err = WasiEnvBuilder::new("test_prog")
+            .no_filesystem()
+            .fs(Arc::new(virtual_fs::mem_fs::FileSystem::default())
+                as Arc<dyn virtual_fs::FileSystem + Send + Sync>)
+            .build_init()
+            .expect_err("should fail");
+        assert!(matches!(err, WasiStateCreationError::WasiFsSetupError(_)));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn derived_children_get_their_own_args_envs_and_fds() {
+        let env = WasiEnvBuilder::new("test_prog")
+            .arg("--parent")
+            .env("KEEP", "1")
+            .env("OVERRIDE", "parent")
+            .engine(Engine::default())
+            .build()
+            .unwrap();
+
+        let (child, _handle) = env
+            .derive_child(
+                Some(vec!["child".to_string(), "--child".to_string()]),
+                Some(vec![
+                    ("OVERRIDE".to_string(), "child".to_string()),
+                    ("NEW".to_string(), "2".to_string()),
+                ]),
+                |child| child.state.fs.close_fd(1),
+            )
+            .unwrap()
+            .unwrap();
+
+        assert_ne!(child.pid(), env.pid());
+        assert_eq!(
+            *child.state.args.lock().unwrap(),
+            vec!["child".to_string(), "--child".to_string()]
+        );
+        assert_eq!(
+            *child.state.envs.lock().unwrap(),
+            vec![
+                b"KEEP=1".to_vec(),
+                b"OVERRIDE=child".to_vec(),
+                b"NEW=2".to_vec()
+            ]
+        );
+        assert!(child.state.fs.get_fd(1).is_err());
+
+        // The parent is left as it was
+        assert_eq!(
+            *env.state.args.lock().unwrap(),
+            vec!["test_prog".to_string(), "--parent".to_string()]
+        );
+        assert_eq!(
+            *env.state.envs.lock().unwrap(),
+            vec![b"KEEP=1".to_vec(), b"OVERRIDE=parent".to_vec()]
+        );
+        assert!(env.state.fs.get_fd(1).is_ok());
+
+        // A failing action is reported as such
+        let err = env
+            .derive_child(None, None, |_| Err(Errno::Badf))
+            .unwrap()
+            .err();
+        assert_eq!(err, Some(Errno::Badf));
+    }
+
+    #[tokio::test]
+    async fn dev_files() {
+        use virtual_fs::{AsyncReadExt, AsyncWriteExt};
+
+        async fn check_devices(fs: &WasiFsRoot) {
+            let mut zero = fs.new_open_options().read(true).open("/dev/zero").unwrap();
+            let mut buf = [1u8; 16];
+            zero.read_exact(&mut buf).await.unwrap();
+            assert_eq!(buf, [0u8; 16]);
+
+            let mut null = fs
+                .new_open_options()
+                .read(true)
+                .write(true)
+                .open("/dev/null")
+                .unwrap();
+            assert_eq!(null.write(b"hello").await.unwrap(), 5);
+            let mut buf = Vec::new();
+            null.read_to_end(&mut buf).await.unwrap();
+            assert!(buf.is_empty());
+        }
+
+        // The devices are only there when asked for
+        let init = WasiEnvBuilder::new("test_prog")
+            .engine(Engine::default())
+            .build_init()
+            .unwrap();
+        assert!(
+            init.state
+                .fs
+                .root_fs
+                .metadata(Path::new("/dev/null"))
+                .is_err()
+        );
+
+        let init = WasiEnvBuilder::new("test_prog")
+            .dev_files(true)
+            .engine(Engine::default())
+            .build_init()
+            .unwrap();
+        check_devices(&init.state.fs.root_fs).await;
+
+        // Custom file systems get them mounted at `/dev`
+        let init = WasiEnvBuilder::new("test_prog")
+            .fs(Arc::new(virtual_fs::mem_fs::FileSystem::default())
+                as Arc<dyn virtual_fs::FileSystem + Send + Sync>)
+            .dev_files(true)
+            .engine(Engine::default())
+            .build_init()
+            .unwrap();
+        check_devices(&init.state.fs.root_fs).await;
+    }
 }
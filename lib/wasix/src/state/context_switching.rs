@@ -105,8 +105,27 @@ impl ContextSwitchingEnvironment {
 
     /// Run the main context function in a context-switching environment
     ///
-    /// This call blocks until the entrypoint returns or traps
+    /// This call blocks until the entrypoint returns or traps, the call hooks
+    /// of the runtime (if any) are invoked around it
     pub(crate) fn run_main_context(
+        ctx: &WasiFunctionEnv,
+        store: Store,
+        entrypoint: wasmer::Function,
+        params: Vec<wasmer::Value>,
+    ) -> (Store, Result<Box<[wasmer::Value]>, RuntimeError>) {
+        let env = ctx.data(&store);
+        let Some(hooks) = env.runtime().call_hooks() else {
+            return Self::run_entrypoint(ctx, store, entrypoint, params);
+        };
+        let (pid, tid) = (env.pid(), env.tid());
+
+        (hooks.on_enter)(pid, tid);
+        let ret = Self::run_entrypoint(ctx, store, entrypoint, params);
+        (hooks.on_exit)(pid, tid);
+        ret
+    }
+
+    fn run_entrypoint(
         ctx: &WasiFunctionEnv,
         mut store: Store,
         entrypoint: wasmer::Function,
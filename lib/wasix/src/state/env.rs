@@ -4,38 +4,48 @@ use crate::{
     Runtime, VirtualTaskManager, WasiControlPlane, WasiEnvBuilder, WasiError, WasiFunctionEnv,
     WasiResult, WasiRuntimeError, WasiStateCreationError, WasiThreadError, WasiVFork,
     bin_factory::{BinFactory, BinaryPackage, BinaryPackageCommand},
-    capabilities::Capabilities,
-    fs::{WasiFsRoot, WasiInodes},
+    capabilities::{Capabilities, SandboxPolicy},
+    fs::{ReopenFailure, WasiFsRoot, WasiInodes},
     import_object_for_all_wasi_versions,
-    os::task::{
-        control_plane::ControlPlaneError,
-        process::{WasiProcess, WasiProcessId},
-        thread::{WasiMemoryLayout, WasiThread, WasiThreadHandle, WasiThreadId},
+    os::{
+        system_info::SystemInfo,
+        task::{
+            control_plane::ControlPlaneError,
+            process::{WasiProcess, WasiProcessId},
+            rate_limit::SyscallRateLimiter,
+            thread::{WasiMemoryLayout, WasiThread, WasiThreadHandle, WasiThreadId},
+        },
+    },
+    syscalls::{
+        __asyncify_with_deep_sleep, _prepare_wasi, AsyncifyAction, get_memory_stack_pointer,
+        handle_rewind, platform_clock_time_get, write_stack_overflow_message,
     },
-    syscalls::platform_clock_time_get,
 };
 use futures::future::BoxFuture;
 use rand::RngExt;
 use std::{
     collections::HashMap,
+    future::Future,
+    num::NonZeroU32,
     ops::Deref,
     path::{Path, PathBuf},
     str,
-    sync::Arc,
+    sync::{Arc, RwLock},
+    task::Poll,
     time::Duration,
 };
 use virtual_fs::{FileSystem, FsError, VirtualFile};
 use virtual_mio::block_on;
 use virtual_net::DynVirtualNetworking;
 use wasmer::{
-    AsStoreMut, AsStoreRef, ExportError, FunctionEnvMut, Instance, Memory, MemoryType, MemoryView,
-    Module,
+    AsStoreMut, AsStoreRef, ExportError, FunctionEnvMut, Instance, Memory, Memory32, Memory64,
+    MemorySize, MemoryType, MemoryView, Module, Value,
 };
 use wasmer_config::package::PackageSource;
-use wasmer_types::ModuleHash;
+use wasmer_types::{ModuleHash, Pages};
 use wasmer_wasix_types::{
     types::Signal,
-    wasi::{Errno, ExitCode, Snapshot0Clockid},
+    wasi::{Disposition, Errno, ExitCode, Snapshot0Clockid},
     wasix::ThreadStartType,
 };
 use webc::metadata::annotations::Wasi;
@@ -78,6 +88,28 @@ pub struct WasiEnvInit {
 
     /// Skip writes to stdout and stderr when bootstrapping from a journal
     pub skip_stdio_during_bootstrap: bool,
+
+    /// What happens to the restored descriptors of files that can't be
+    /// opened anymore when bootstrapping from a journal
+    pub journal_reopen_failure: ReopenFailure,
+
+    /// The rate that the syscalls of every process are limited to, see
+    /// [`WasiEnvBuilder::max_syscalls_per_second`]
+    pub max_syscalls_per_second: Option<NonZeroU32>,
+
+    /// Largest memory that a module may import, see
+    /// [`WasiEnvBuilder::max_memory_pages`]
+    pub max_memory_pages: Option<Pages>,
+
+    /// What the guest is told about the system by `sysinfo`
+    pub system_info: SystemInfo,
+
+    /// The hostname of the guest, shared by all the processes that are
+    /// spawned from this environment
+    pub hostname: Arc<RwLock<String>>,
+
+    /// Where the rotation of the subscriptions of `poll_oneoff` starts
+    pub poll_seed: u64,
 }
 
 impl WasiEnvInit {
@@ -98,6 +130,7 @@ impl WasiEnvInit {
                 clock_offset: std::sync::Mutex::new(
                     self.state.clock_offset.lock().unwrap().clone(),
                 ),
+                uptime_offset: self.state.uptime_offset,
                 args: std::sync::Mutex::new(self.state.args.lock().unwrap().clone()),
                 envs: std::sync::Mutex::new(self.state.envs.lock().unwrap().deref().clone()),
                 signals: std::sync::Mutex::new(self.state.signals.lock().unwrap().deref().clone()),
@@ -120,10 +153,27 @@ impl WasiEnvInit {
             #[cfg(feature = "journal")]
             stop_running_after_snapshot: self.stop_running_after_snapshot,
             skip_stdio_during_bootstrap: self.skip_stdio_during_bootstrap,
+            journal_reopen_failure: self.journal_reopen_failure,
+            max_syscalls_per_second: self.max_syscalls_per_second,
+            max_memory_pages: self.max_memory_pages,
+            system_info: self.system_info.clone(),
+            hostname: self.hostname.clone(),
+            poll_seed: self.poll_seed,
         }
     }
 }
 
+/// What a syscall does once [`WasiEnv::do_pending_operations_or_sleep`]
+/// processed the pending operations at its entry
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum PendingOperations {
+    /// The syscall is processed right away
+    Proceed,
+    /// The stack is unwinding for a deep sleep, the syscall has to return
+    /// right away and it is processed once the thread is rewound
+    Unwound,
+}
+
 /// The environment provided to the WASI imports.
 pub struct WasiEnv {
     pub control_plane: WasiControlPlane,
@@ -161,6 +211,23 @@ pub struct WasiEnv {
     /// time that it will pause the CPU)
     pub enable_exponential_cpu_backoff: Option<Duration>,
 
+    /// Limits the rate of the syscalls of the process (shared by all its
+    /// threads), see [`WasiEnvBuilder::max_syscalls_per_second`]
+    pub(crate) syscall_rate_limiter: Option<Arc<SyscallRateLimiter>>,
+
+    /// Set while `proc_fork` waits out the delay of a fork storm (see
+    /// [`ControlPlaneConfig::max_forks_per_second`]), so that it carries on
+    /// with the fork when it is rewound after a deep sleep
+    ///
+    /// [`ControlPlaneConfig::max_forks_per_second`]: crate::os::task::control_plane::ControlPlaneConfig::max_forks_per_second
+    pub(crate) fork_delayed: bool,
+
+    /// What the guest is told about the system by `sysinfo`
+    pub(crate) system_info: Arc<SystemInfo>,
+
+    /// The hostname of the guest, see `gethostname` and `sethostname`
+    pub(crate) hostname: Arc<RwLock<String>>,
+
     /// Flag that indicates if the environment is currently replaying the journal
     /// (and hence it should not record new events)
     pub replaying_journal: bool,
@@ -168,6 +235,13 @@ pub struct WasiEnv {
     /// Should stdio be skipped when bootstrapping this module from an existing journal?
     pub skip_stdio_during_bootstrap: bool,
 
+    /// What happens to the restored descriptors of files that can't be
+    /// opened anymore when bootstrapping this module from an existing journal?
+    pub journal_reopen_failure: ReopenFailure,
+
+    /// Largest memory that the modules that are spawned may import
+    pub(crate) max_memory_pages: Option<Pages>,
+
     /// Flag that indicates the cleanup of the environment is to be disabled
     /// (this is normally used so that the instance can be reused later on)
     pub(crate) disable_fs_cleanup: bool,
@@ -210,8 +284,14 @@ impl Clone for WasiEnv {
             enable_deep_sleep: self.enable_deep_sleep,
             enable_journal: self.enable_journal,
             enable_exponential_cpu_backoff: self.enable_exponential_cpu_backoff,
+            syscall_rate_limiter: self.syscall_rate_limiter.clone(),
+            fork_delayed: self.fork_delayed,
+            system_info: self.system_info.clone(),
+            hostname: self.hostname.clone(),
             replaying_journal: self.replaying_journal,
             skip_stdio_during_bootstrap: self.skip_stdio_during_bootstrap,
+            journal_reopen_failure: self.journal_reopen_failure,
+            max_memory_pages: self.max_memory_pages,
             disable_fs_cleanup: self.disable_fs_cleanup,
             context_switching_environment: None,
         }
@@ -229,10 +309,23 @@ impl WasiEnv {
         let process = self.control_plane.new_process(self.process.module_hash)?;
         let handle = process.new_thread(self.layout.clone(), ThreadStartType::MainThread)?;
 
+        // Resource limits and the title are inherited by the child, this also
+        // covers the processes that are spawned (see `derive_child`)
+        let (rlimits, title) = {
+            let inner = self.process.inner.0.lock().unwrap();
+            (inner.rlimits.clone(), inner.title.clone())
+        };
+        {
+            let mut inner = process.inner.0.lock().unwrap();
+            inner.rlimits = rlimits;
+            inner.title = title;
+        }
+
         let thread = handle.as_thread();
         thread.copy_stack_from(&self.thread);
 
         let state = Arc::new(self.state.fork());
+        state.fs.rebind_signal_fds(&state.inodes, &thread);
 
         let bin_factory = self.bin_factory.clone();
 
@@ -252,14 +345,55 @@ impl WasiEnv {
             enable_deep_sleep: self.enable_deep_sleep,
             enable_journal: self.enable_journal,
             enable_exponential_cpu_backoff: self.enable_exponential_cpu_backoff,
+            // The child gets a bucket of its own
+            syscall_rate_limiter: self
+                .syscall_rate_limiter
+                .as_ref()
+                .map(|limiter| Arc::new(SyscallRateLimiter::new(limiter.per_second()))),
+            fork_delayed: false,
+            system_info: self.system_info.clone(),
+            hostname: self.hostname.clone(),
             replaying_journal: false,
             skip_stdio_during_bootstrap: self.skip_stdio_during_bootstrap,
+            journal_reopen_failure: self.journal_reopen_failure,
+            max_memory_pages: self.max_memory_pages,
             disable_fs_cleanup: self.disable_fs_cleanup,
             context_switching_environment: None,
         };
         Ok((new_env, handle))
     }
 
+    /// Derives the environment of a new process that is spawned from this
+    /// one: the environment is forked, the arguments are replaced, the
+    /// environment variables are merged into the inherited ones and then
+    /// `fd_actions` runs on the child (to rearrange its file descriptors,
+    /// change its directory and the like) before it is handed back.
+    ///
+    /// The child shares with its parent the runtime, the control plane, the
+    /// binary factory, the capabilities and the file system (the root file
+    /// system, the inodes and the ephemeral symlinks). It gets its own copy
+    /// of the file descriptor table (the descriptors still point at the same
+    /// open files), the arguments, the environment variables, the signal
+    /// dispositions, the current directory, the resource limits, the title
+    /// and the stack of the calling thread. Its process, its module instance
+    /// and its syscall rate limiter bucket are new.
+    ///
+    /// The outer error is returned when the control plane refuses to create
+    /// the process and the inner one when one of the `fd_actions` fails.
+    pub(crate) fn derive_child(
+        &self,
+        args: Option<Vec<String>>,
+        envs: Option<Vec<(String, String)>>,
+        fd_actions: impl FnOnce(&mut WasiEnv) -> Result<(), Errno>,
+    ) -> Result<Result<(Self, WasiThreadHandle), Errno>, ControlPlaneError> {
+        let (mut child_env, handle) = self.fork()?;
+        _prepare_wasi(&mut child_env, args, envs, None);
+        if let Err(err) = fd_actions(&mut child_env) {
+            return Ok(Err(err));
+        }
+        Ok(Ok((child_env, handle)))
+    }
+
     pub fn pid(&self) -> WasiProcessId {
         self.process.pid()
     }
@@ -344,7 +478,9 @@ impl WasiEnv {
     }
 
     fn deep_sleep_capability_requested(&self) -> bool {
-        self.capabilities.threading.enable_deep_sleep
+        // Tasks that share a single thread can only take turns by going
+        // into a deep sleep, so it is always on for them
+        self.capabilities.threading.enable_deep_sleep || self.tasks().is_single_threaded()
     }
 
     fn deep_sleep_supported_by_module(&self) -> bool {
@@ -385,6 +521,15 @@ impl WasiEnv {
             guard.stop_running_after_checkpoint = init.stop_running_after_snapshot;
         }
 
+        // The title starts out as the name that the program was spawned with
+        if let Some(name) = init.state.args.lock().unwrap().first() {
+            process.set_title(name.as_str());
+        }
+
+        if init.capabilities.allow_perf_counters || init.capabilities.insecure_allow_all {
+            crate::utils::perf_counter::calibrate_ahead(init.runtime.task_manager().as_ref());
+        }
+
         let layout = WasiMemoryLayout::default();
         let thread = if let Some(t) = init.thread {
             t
@@ -398,7 +543,7 @@ impl WasiEnv {
             thread: thread.as_thread(),
             layout,
             vfork: None,
-            poll_seed: 0,
+            poll_seed: init.poll_seed,
             state: Arc::new(init.state),
             inner: Default::default(),
             owned_handles: Vec::new(),
@@ -408,11 +553,19 @@ impl WasiEnv {
             enable_journal: false,
             replaying_journal: false,
             skip_stdio_during_bootstrap: init.skip_stdio_during_bootstrap,
+            journal_reopen_failure: init.journal_reopen_failure,
+            max_memory_pages: init.max_memory_pages,
             enable_deep_sleep: false,
             enable_exponential_cpu_backoff: init
                 .capabilities
                 .threading
                 .enable_exponential_cpu_backoff,
+            syscall_rate_limiter: init
+                .max_syscalls_per_second
+                .map(|limit| Arc::new(SyscallRateLimiter::new(limit))),
+            fork_delayed: false,
+            system_info: Arc::new(init.system_info),
+            hostname: init.hostname,
             runtime: init.runtime,
             bin_factory: init.bin_factory,
             capabilities: init.capabilities,
@@ -637,6 +790,29 @@ impl WasiEnv {
         &self.state.fs.root_fs
     }
 
+    /// Removes the directory at `path` (relative to the descriptor `base`)
+    /// together with everything in it, for builtin commands such as `rm -r`.
+    ///
+    /// Symlinks are removed rather than followed. When something can't be
+    /// removed the rest of the tree is still removed and the first error is
+    /// returned. The entries that are removed are journaled.
+    pub fn remove_dir_all(&self, base: crate::syscalls::WasiFd, path: &str) -> Result<(), Errno> {
+        let state = &self.state;
+        state
+            .fs
+            .remove_dir_all(&state.inodes, base, path, |path, is_dir| {
+                #[cfg(feature = "journal")]
+                if self.should_journal()
+                    && self.has_active_journal()
+                    && let Err(err) = JournalEffector::save_remove_dir_all_entry(self, path, is_dir)
+                {
+                    tracing::warn!("failed to save the removal of {path:?} - {err}");
+                }
+                #[cfg(not(feature = "journal"))]
+                let _ = (path, is_dir);
+            })
+    }
+
     /// Overrides the runtime implementation for this environment
     pub fn set_runtime<R>(&mut self, runtime: R)
     where
@@ -654,10 +830,205 @@ impl WasiEnv {
     /// cross-cutting, such as signals, thread/process exit, DL operations, etc.
     pub fn do_pending_operations(ctx: &mut FunctionEnvMut<'_, Self>) -> Result<(), WasiError> {
         Self::do_pending_link_operations(ctx, true)?;
-        _ = Self::process_signals_and_exit(ctx)?;
+        Self::check_stack_guard(ctx)?;
+        if let Err(err) = Self::process_signals_and_exit(ctx)? {
+            return Err(WasiError::Exit(err.into()));
+        }
         Ok(())
     }
 
+    /// Processes the pending operations (see [`WasiEnv::do_pending_operations`])
+    /// at the entry of a syscall, putting the thread into a deep sleep first
+    /// while the process is paused or its syscalls are throttled. The syscall
+    /// has to return right away when this returns [`PendingOperations::Unwound`].
+    pub(crate) fn do_pending_operations_or_sleep(
+        ctx: &mut FunctionEnvMut<'_, Self>,
+    ) -> WasiResult<PendingOperations> {
+        Self::do_pending_link_operations(ctx, true)?;
+        Self::check_stack_guard(ctx)?;
+
+        // A syscall that is rewound after the thread slept here carries on
+        // from where the sleep left it, one that slept itself went through
+        // here before it did and the thread can not sleep again until its
+        // rewind is over
+        let throttled = match Self::handle_pending_operations_rewind(ctx) {
+            Some(throttled) => throttled,
+            None if ctx.data().thread.has_rewind() => {
+                wasi_try_ok_ok!(Self::process_signals_and_exit(ctx)?);
+                return Ok(Ok(PendingOperations::Proceed));
+            }
+            None => false,
+        };
+
+        if ctx.data().process.is_paused() {
+            let resumed = ctx.data().wait_while_paused();
+            let res = Self::sleep_before_syscall(ctx, async move {
+                resumed.await;
+                throttled
+            })?;
+            if wasi_try_ok_ok!(res) == PendingOperations::Unwound {
+                return Ok(Ok(PendingOperations::Unwound));
+            }
+        }
+
+        wasi_try_ok_ok!(Self::process_signals_and_exit(ctx)?);
+
+        if !throttled && let Some(wait) = ctx.data().throttle_syscall() {
+            let tasks = ctx.data().tasks().clone();
+            return Self::sleep_before_syscall(ctx, async move {
+                tasks.sleep_now(wait).await;
+                true
+            });
+        }
+        Ok(Ok(PendingOperations::Proceed))
+    }
+
+    /// [`WasiEnv::do_pending_operations_or_sleep`] for the syscalls that
+    /// don't return an errno. Returns `false` while the stack unwinds for a
+    /// sleep, the syscall then returns right away and is processed once it
+    /// is rewound. Any other errno fails the thread.
+    pub(crate) fn do_pending_operations_or_unwind(
+        ctx: &mut FunctionEnvMut<'_, Self>,
+    ) -> Result<bool, WasiError> {
+        match Self::do_pending_operations_or_sleep(ctx)? {
+            Ok(PendingOperations::Proceed) => Ok(true),
+            Ok(PendingOperations::Unwound) => Ok(false),
+            Err(err) => Err(WasiError::Exit(err.into())),
+        }
+    }
+
+    /// Fails the thread once its stack pointer has left its stack, which
+    /// is only known for threads that were spawned with a guard region below
+    /// their stack. A thread that runs in a context other than its main one
+    /// is on a stack of its own and is not checked.
+    fn check_stack_guard(ctx: &mut FunctionEnvMut<'_, Self>) -> Result<(), WasiError> {
+        let env = ctx.data();
+        if env.layout.guard_size == 0
+            || env
+                .context_switching_environment
+                .as_ref()
+                .is_some_and(|contexts| contexts.active_context_id() != contexts.main_context_id())
+        {
+            return Ok(());
+        }
+        let layout = env.layout.clone();
+        let Ok(stack_pointer) = (unsafe { get_memory_stack_pointer(ctx) }) else {
+            return Ok(());
+        };
+        if !layout.is_stack_overflow(stack_pointer) {
+            return Ok(());
+        }
+
+        let env = ctx.data();
+        tracing::warn!(pid=%env.pid(), tid=%env.tid(), stack_pointer, "thread overflowed its stack");
+        write_stack_overflow_message(env);
+        Err(WasiError::Exit(Errno::Fault.into()))
+    }
+
+    /// Waits for as long as the process is paused (see
+    /// [`WasiProcess::pause`]), or until it gets killed or terminated
+    fn wait_while_paused(&self) -> impl Future<Output = ()> + Send + Sync + 'static {
+        tracing::trace!(pid=%self.pid(), tid=%self.tid(), "thread paused");
+        let resumed = self.process.wait_for_resume();
+        let killed = {
+            let thread = self.thread.clone();
+            std::future::poll_fn(move |cx| {
+                thread.signals_subscribe(cx.waker());
+                match thread
+                    .signals()
+                    .lock()
+                    .unwrap()
+                    .0
+                    .contains(&Signal::Sigkill)
+                {
+                    true => Poll::Ready(()),
+                    false => Poll::Pending,
+                }
+            })
+        };
+        let thread = self.thread.clone();
+        let process = self.process.clone();
+        let (pid, tid) = (self.pid(), self.tid());
+        async move {
+            tokio::select! {
+                _ = resumed => {},
+                _ = killed => {},
+                _ = thread.join() => {},
+                _ = process.finished.await_termination() => {},
+            }
+            tracing::trace!(%pid, %tid, "thread resumed");
+        }
+    }
+
+    /// Takes a token for the syscall when the process makes more syscalls
+    /// than its [`WasiEnvBuilder::max_syscalls_per_second`] allow and returns
+    /// how long the thread has to sleep before the syscall is processed
+    fn throttle_syscall(&self) -> Option<Duration> {
+        let wait = self.syscall_rate_limiter.as_ref()?.acquire()?;
+        tracing::trace!(pid=%self.pid(), tid=%self.tid(), ?wait, "syscall rate limit exceeded");
+        Some(wait)
+    }
+
+    /// Puts the thread into a deep sleep before the syscall is processed,
+    /// the syscall returns right away when the stack unwinds and is
+    /// processed once the thread is rewound. The work returns whether the
+    /// syscall already took its token from the rate limiter.
+    fn sleep_before_syscall<Fut>(
+        ctx: &mut FunctionEnvMut<'_, Self>,
+        work: Fut,
+    ) -> WasiResult<PendingOperations>
+    where
+        Fut: Future<Output = bool> + Send + Sync + 'static,
+    {
+        if Self::is_memory64(ctx) {
+            Self::sleep_before_syscall_ext::<Memory64, _>(ctx, work)
+        } else {
+            Self::sleep_before_syscall_ext::<Memory32, _>(ctx, work)
+        }
+    }
+
+    fn sleep_before_syscall_ext<M: MemorySize, Fut>(
+        ctx: &mut FunctionEnvMut<'_, Self>,
+        work: Fut,
+    ) -> WasiResult<PendingOperations>
+    where
+        Fut: Future<Output = bool> + Send + Sync + 'static,
+    {
+        let thread = ctx.data().thread.clone();
+        thread.set_pending_operations_sleep(true);
+        let res = match __asyncify_with_deep_sleep::<M, _, _>(ctx.as_mut(), work)? {
+            AsyncifyAction::Finish(..) => Ok(PendingOperations::Proceed),
+            AsyncifyAction::Unwind => return Ok(Ok(PendingOperations::Unwound)),
+            AsyncifyAction::Abort(err) => Err(err),
+        };
+        thread.set_pending_operations_sleep(false);
+        Ok(res)
+    }
+
+    /// Finishes the rewind of a thread that deep slept before the syscall
+    /// was processed (see [`WasiEnv::sleep_before_syscall`]), returns whether
+    /// the syscall already took its token from the rate limiter
+    fn handle_pending_operations_rewind(ctx: &mut FunctionEnvMut<'_, Self>) -> Option<bool> {
+        if !ctx.data().thread.take_pending_operations_sleep() {
+            return None;
+        }
+        if Self::is_memory64(ctx) {
+            unsafe { handle_rewind::<Memory64, bool>(ctx) }
+        } else {
+            unsafe { handle_rewind::<Memory32, bool>(ctx) }
+        }
+    }
+
+    /// Tells if the main module uses a 64-bit memory, which is known from
+    /// the type of its stack pointer
+    fn is_memory64(ctx: &mut FunctionEnvMut<'_, Self>) -> bool {
+        let stack_pointer = ctx
+            .data()
+            .try_inner()
+            .and_then(|inner| inner.main_module_instance_handles().stack_pointer.clone());
+        stack_pointer.is_some_and(|stack_pointer| matches!(stack_pointer.get(ctx), Value::I64(_)))
+    }
+
     pub fn do_pending_link_operations(
         ctx: &mut FunctionEnvMut<'_, Self>,
         fast: bool,
@@ -690,6 +1061,7 @@ impl WasiEnv {
                         || sig == Signal::Sigabrt
                         || sig == Signal::Sigpipe
                     {
+                        env.process.record_exit_signal(sig);
                         let exit_code = env.thread.set_or_get_exit_code_for_signal(sig);
                         return Err(WasiError::Exit(exit_code));
                     } else {
@@ -708,6 +1080,120 @@ impl WasiEnv {
         Self::process_signals(ctx)
     }
 
+    /// Returns true if the disposition of `signal` for this process is to
+    /// ignore it (as inherited from its parent or set when it was built)
+    pub(crate) fn is_signal_ignored(&self, signal: Signal) -> bool {
+        matches!(
+            self.state.signals.lock().unwrap().get(&signal),
+            Some(Disposition::Ignore)
+        )
+    }
+
+    /// Returns true if the guest has installed its signal handler, which
+    /// all the signals then go to
+    pub(crate) fn has_signal_handler(&self) -> bool {
+        self.try_inner().is_some_and(|inner| {
+            let handles = inner.main_module_instance_handles();
+            handles.signal_set && handles.signal.is_some()
+        })
+    }
+
+    /// Returns true if `signal` interrupts a blocking read or write, which
+    /// is the case when it goes to the signal handler of the guest or when
+    /// it terminates the process because there is no handler
+    pub(crate) fn signal_interrupts_io(&self, signal: Signal, has_handler: bool) -> bool {
+        !self.is_signal_ignored(signal) && signal_interrupts_syscalls(signal, has_handler)
+    }
+
+    /// Processes the signals that interrupted a blocking read or write
+    /// (see `__asyncify_interruptible`) before the syscall returns, so that
+    /// the handlers of the guest run before it sees the result.
+    ///
+    /// Returns true if the syscall has to be restarted, which is the case
+    /// when it was interrupted before it transferred anything and all the
+    /// signals that interrupted it restart syscalls (see
+    /// [`WasiProcess::set_signal_restarts`]).
+    pub(crate) fn handle_interrupted_io<T>(
+        ctx: &mut FunctionEnvMut<'_, Self>,
+        res: &Result<T, Errno>,
+    ) -> Result<bool, WasiError> {
+        let restart = Self::handle_interrupted_wait(ctx)?;
+        Ok(restart && matches!(res, Err(Errno::Intr)))
+    }
+
+    /// Processes the signals that interrupted a wait (see
+    /// [`WasiEnv::wait_for_interrupt`]) so that the handlers of the guest
+    /// run while the syscall is still waiting.
+    ///
+    /// Returns true if the syscall has to carry on waiting, which is the
+    /// case when all the signals that interrupted it restart syscalls (see
+    /// [`WasiProcess::set_signal_restarts`]), otherwise it fails with
+    /// `Errno::Intr`.
+    pub(crate) fn handle_interrupted_wait(
+        ctx: &mut FunctionEnvMut<'_, Self>,
+    ) -> Result<bool, WasiError> {
+        let env = ctx.data();
+        let signals = env
+            .thread
+            .pending_signals()
+            .into_iter()
+            .filter(|sig| !env.thread.is_signal_blocked(*sig))
+            .collect::<Vec<_>>();
+        if signals.is_empty() {
+            return Ok(false);
+        }
+
+        let restart = signals.iter().all(|sig| env.process.signal_restarts(*sig));
+        if let Err(err) = Self::process_signals_and_exit(ctx)? {
+            return Err(WasiError::Exit(err.into()));
+        }
+        Ok(restart)
+    }
+
+    /// Waits until a signal arrives that interrupts the blocking syscalls
+    /// that wait in a deep sleep (such as `poll_oneoff` and `clock_nanosleep`),
+    /// which are the signals that are not blocked and interrupt a blocking
+    /// read or write as well (see [`WasiEnv::signal_interrupts_io`]). Once
+    /// their handlers ran (see [`WasiEnv::handle_interrupted_wait`]) the
+    /// syscall either fails with `Errno::Intr` or carries on waiting for the
+    /// time that is left.
+    pub(crate) fn wait_for_interrupt(&self) -> impl Future<Output = ()> + Send + Sync + 'static {
+        let thread = self.thread.clone();
+        let state = self.state.clone();
+        let has_handler = self.has_signal_handler();
+        std::future::poll_fn(move |cx| {
+            thread.signals_subscribe(cx.waker());
+            let signals = thread.signals().lock().unwrap().0.clone();
+            let dispositions = state.signals.lock().unwrap();
+            let interrupted = signals.iter().any(|sig| {
+                !thread.is_signal_blocked(*sig)
+                    && !matches!(dispositions.get(sig), Some(Disposition::Ignore))
+                    && signal_interrupts_syscalls(*sig, has_handler)
+            });
+            match interrupted {
+                true => Poll::Ready(()),
+                false => Poll::Pending,
+            }
+        })
+    }
+
+    /// Handles a write to a pipe or a socket whose other end is closed.
+    ///
+    /// Unless the process ignores `SIGPIPE` the signal is raised, which
+    /// terminates the process when it has no handler for it. When the
+    /// process survives (it ignores the signal or handles it) the write
+    /// fails with the returned `Errno::Pipe`.
+    pub(crate) fn broken_pipe(ctx: &mut FunctionEnvMut<'_, Self>) -> Result<Errno, WasiError> {
+        let env = ctx.data();
+        if !env.is_signal_ignored(Signal::Sigpipe) {
+            env.process.signal_process(Signal::Sigpipe);
+            if let Err(err) = Self::process_signals_and_exit(ctx)? {
+                return Ok(err);
+            }
+        }
+        Ok(Errno::Pipe)
+    }
+
     /// Porcesses any signals that are batched up
     pub(crate) fn process_signals(ctx: &mut FunctionEnvMut<'_, Self>) -> WasiResult<bool> {
         // If a signal handler has never been set then we need to handle signals
@@ -846,6 +1332,11 @@ impl WasiEnv {
         self.runtime.networking()
     }
 
+    /// Returns the sandbox policy that is consulted when syscalls are entered
+    pub fn sandbox_policy(&self) -> &SandboxPolicy {
+        &self.capabilities.sandbox
+    }
+
     /// Providers safe access to the initialized part of WasiEnv
     /// (it must be initialized before it can be used)
     pub(crate) fn inner(&self) -> WasiInstanceGuard<'_> {
@@ -1351,10 +1842,21 @@ impl WasiEnv {
             if let Some(env_vars) = env_vars {
                 let env_vars = env_vars
                     .into_iter()
-                    .map(|env_var| {
-                        let (k, v) = env_var.split_once('=').unwrap();
-
-                        (k.to_string(), v.as_bytes().to_vec())
+                    .filter_map(|env_var| {
+                        // Malformed entries (without a `=` or without a key)
+                        // are skipped rather than handed to the guest
+                        match env_var.split_once('=') {
+                            Some((k, v)) if !k.is_empty() => {
+                                Some((k.to_string(), v.as_bytes().to_vec()))
+                            }
+                            _ => {
+                                tracing::warn!(
+                                    %env_var,
+                                    "skipping a malformed env var of the package"
+                                );
+                                None
+                            }
+                        }
                     })
                     .collect::<Vec<_>>();
 
@@ -1380,3 +1882,18 @@ impl WasiEnv {
         }
     }
 }
+
+/// Returns true if `signal` interrupts a blocking syscall of a process that
+/// does not ignore it, which is the case when it goes to the signal handler
+/// of the guest or when it terminates the process because there is no
+/// handler. The host-only wakeup signal never interrupts anything.
+fn signal_interrupts_syscalls(signal: Signal, has_handler: bool) -> bool {
+    if signal == Signal::Sigwakeup {
+        return false;
+    }
+    has_handler
+        || matches!(
+            signal,
+            Signal::Sigint | Signal::Sigquit | Signal::Sigkill | Signal::Sigabrt | Signal::Sigpipe
+        )
+}
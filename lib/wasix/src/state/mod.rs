@@ -38,6 +38,7 @@ use wasmer_wasix_types::wasi::{
     Disposition, Errno, Fd as WasiFd, Rights, Signal, Snapshot0Clockid,
 };
 
+pub(crate) use self::env::PendingOperations;
 pub use self::{
     builder::*,
     env::{WasiEnv, WasiEnvInit, WasiModuleInstanceHandles, WasiModuleTreeHandles},
@@ -136,6 +137,9 @@ pub(crate) struct WasiState {
     pub inodes: WasiInodes,
     pub futexs: Mutex<WasiFutexState>,
     pub clock_offset: Mutex<HashMap<Snapshot0Clockid, i64>>,
+    /// Added to the monotonic clock of the host to get the uptime that
+    /// `sysinfo` reports
+    pub uptime_offset: i64,
     pub args: Mutex<Vec<String>>,
     pub envs: Mutex<Vec<Vec<u8>>>,
     pub signals: Mutex<HashMap<Signal, Disposition>>,
@@ -259,10 +263,21 @@ impl WasiState {
             inodes: self.inodes.clone(),
             futexs: Default::default(),
             clock_offset: Mutex::new(self.clock_offset.lock().unwrap().clone()),
+            uptime_offset: self.uptime_offset,
             args: Mutex::new(self.args.lock().unwrap().clone()),
             envs: Mutex::new(self.envs.lock().unwrap().clone()),
             signals: Mutex::new(self.signals.lock().unwrap().clone()),
             preopen: self.preopen.clone(),
         }
     }
+
+    /// Puts the signals back to their default disposition when a new image
+    /// is executed, except for the ones that are ignored (as POSIX `exec`
+    /// does). The handlers of the old image don't exist in the new one.
+    pub(crate) fn reset_signal_dispositions(&self) {
+        self.signals
+            .lock()
+            .unwrap()
+            .retain(|_, disp| matches!(disp, Disposition::Ignore));
+    }
 }
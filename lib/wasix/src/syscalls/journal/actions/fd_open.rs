@@ -15,7 +15,7 @@ impl<'a> JournalSyscallPlayer<'a, '_> {
         fd_flags: Fdflagsext,
     ) -> Result<(), WasiRuntimeError> {
         tracing::trace!(%fd, %dirfd, %dirflags,  "Replay journal - FdOpen {}", path);
-        JournalEffector::apply_path_open(
+        let res = JournalEffector::apply_path_open(
             &mut self.ctx,
             fd,
             dirfd,
@@ -26,8 +26,29 @@ impl<'a> JournalSyscallPlayer<'a, '_> {
             fs_rights_inheriting,
             fs_flags,
             fd_flags,
-        )
-        .map_err(anyhow_err_to_runtime_err)?;
+        );
+        match res {
+            Ok(()) => {}
+            // A file that existed when the journal was written may be gone
+            // from the host (or only come back with a later event), so its
+            // descriptor is kept aside until the whole journal was played
+            Err(err) if !o_flags.contains(Oflags::CREATE) => {
+                tracing::debug!(%fd, "Replay journal - FdOpen deferred - {err}");
+                JournalEffector::apply_path_open_unresolved(
+                    &mut self.ctx,
+                    fd,
+                    dirfd,
+                    &path,
+                    fs_rights_base,
+                    fs_rights_inheriting,
+                    fs_flags,
+                    fd_flags,
+                )
+                .map_err(anyhow_err_to_runtime_err)?;
+                self.unresolved_fds = true;
+            }
+            Err(err) => return Err(anyhow_err_to_runtime_err(err)),
+        }
         Ok(())
     }
 }
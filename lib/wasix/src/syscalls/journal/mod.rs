@@ -43,6 +43,10 @@ pub struct JournalSyscallPlayer<'a, 'c> {
     pub real_fd: HashSet<WasiFd>,
     pub connected_sockets_are_dead: bool,
 
+    // Files that couldn't be opened while replaying have descriptors
+    // without a handle until the end, where they are opened again
+    pub unresolved_fds: bool,
+
     // We delay the spawning of threads until the end as its
     // possible that the threads will be cancelled before all the
     // events finished the streaming process
@@ -69,6 +73,7 @@ impl<'c> JournalSyscallPlayer<'_, 'c> {
             journal_module_hash: None,
             rewind: None,
             connected_sockets_are_dead: true,
+            unresolved_fds: false,
             spawn_threads: Default::default(),
             staged_differ_memory: Default::default(),
             differ_memory: Default::default(),
@@ -522,8 +522,20 @@ impl<'a> JournalSyscallPlayer<'a, '_> {
                     tracing::trace!(%fd1, %fd2, "Differ(ether) journal - SocketPair");
                     differ_ethereal.push(JournalEntry::SocketPairV1 { fd1, fd2 });
                 } else {
-                    tracing::trace!(%fd1, %fd2, "Replay journal - SocketOpen");
-                    JournalEffector::apply_sock_pair(&mut self.ctx, fd1, fd2)
+                    tracing::trace!(%fd1, %fd2, "Replay journal - SocketPair");
+                    // The type was not recorded back then, the pairs were
+                    // always restored as stream pairs
+                    JournalEffector::apply_sock_pair(&mut self.ctx, fd1, fd2, Socktype::Stream)
+                        .map_err(anyhow_err_to_runtime_err)?
+                }
+            }
+            JournalEntry::SocketPairV2 { fd1, fd2, ty } => {
+                if let Some(differ_ethereal) = differ_ethereal {
+                    tracing::trace!(%fd1, %fd2, ?ty, "Differ(ether) journal - SocketPair");
+                    differ_ethereal.push(JournalEntry::SocketPairV2 { fd1, fd2, ty });
+                } else {
+                    tracing::trace!(%fd1, %fd2, ?ty, "Replay journal - SocketPair");
+                    JournalEffector::apply_sock_pair(&mut self.ctx, fd1, fd2, ty)
                         .map_err(anyhow_err_to_runtime_err)?
                 }
             }
@@ -813,6 +825,16 @@ impl<'a> JournalSyscallPlayer<'a, '_> {
                         .map_err(anyhow_err_to_runtime_err)?
                 }
             }
+            JournalEntry::SocketSetOptStrV1 { fd, opt, value } => {
+                if let Some(differ_ethereal) = differ_ethereal {
+                    tracing::trace!(%fd, ?opt, %value, "Differ(ether) journal - SocketSetOptStr");
+                    differ_ethereal.push(JournalEntry::SocketSetOptStrV1 { fd, opt, value });
+                } else {
+                    tracing::trace!(%fd, ?opt, %value, "Replay journal - SocketSetOptStr");
+                    JournalEffector::apply_sock_set_opt_str(&mut self.ctx, fd, opt, &value)
+                        .map_err(anyhow_err_to_runtime_err)?
+                }
+            }
             JournalEntry::SocketSetOptTimeV1 { fd, ty, time } => {
                 if let Some(differ_ethereal) = differ_ethereal {
                     tracing::trace!(%fd, ?ty, ?time, "Differ(ether) journal - SocketSetOptTime");
@@ -30,6 +30,22 @@ pub unsafe fn restore_snapshot(
         tracing::trace!("Orphaned ethereal events - {:?}", evt);
     }
 
+    // The descriptors of the files that couldn't be opened while replaying
+    // are opened again, the restored table is installed once they are
+    if runner.unresolved_fds {
+        tracing::trace!("reopening the restored file descriptors");
+        let env = runner.ctx.data();
+        let table = env.state.fs.fd_map.read().unwrap().clone();
+        env.state
+            .fs
+            .replace_fd_table(table, env.journal_reopen_failure)
+            .map_err(|err| {
+                anyhow_err_to_runtime_err(anyhow::format_err!(
+                    "journal restore error: failed to reopen the restored file descriptors - {err}"
+                ))
+            })?;
+    }
+
     // FIXME: if the stdout/stderr FDs were closed as a result of replaying the journal,
     // this breaks. A potential fix would be to only close those two FDs afterwards; so
     // a `JournalSyscallPlayer::should_close_stdout: bool` or similar.
@@ -68,7 +68,7 @@ pub fn poll_oneoff<M: MemorySize>(
     nsubscriptions: u32,
     nevents: WasmPtr<u32, Memory32>,
 ) -> Result<Errno, WasiError> {
-    WasiEnv::do_pending_operations(&mut ctx)?;
+    wasi_pending_operations_or_sleep!(&mut ctx);
 
     let env = ctx.data();
     let memory = unsafe { env.memory_view(&ctx) };
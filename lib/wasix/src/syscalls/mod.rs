@@ -54,7 +54,7 @@ pub(crate) use std::{
     pin::Pin,
     sync::{
         Arc, Condvar, Mutex,
-        atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering},
+        atomic::{AtomicBool, AtomicU32, AtomicU64, AtomicUsize, Ordering},
         mpsc,
     },
     task::{Context, Poll},
@@ -95,10 +95,12 @@ pub(crate) use self::types::{
     wasi::{
         Addressfamily, Advice, Clockid, Dircookie, Dirent, DlFlags, DlHandle, Errno, Event,
         EventFdReadwrite, Eventrwflags, Eventtype, ExitCode, Fd as WasiFd, Fdflags, Fdflagsext,
-        Fdstat, Filesize, Filestat, Filetype, Fstflags, Linkcount, Longsize, OptionFd, Pid,
-        Prestat, ProcSpawnFdOp, Rights, SignalDisposition, Snapshot0Clockid, Sockoption,
+        Fdstat, Filesize, Filestat, Filetype, Fstflags, Fswatchmask, Linkcount, Lockflags,
+        Longsize, Mmapflags, Netif, Netifflags, OptionFd, Pid, Prestat, ProcSpawnFdOp, Rights,
+        Rlimit, RlimitResource, SignalDisposition, Snapshot0Clockid, Sockoption, Sockstats,
         Sockstatus, Socktype, StackSnapshot, StdioMode as WasiStdioMode, Streamsecurity,
-        Subscription, SubscriptionFsReadwrite, Tid, Timestamp, TlKey, TlUser, TlVal, Tty, Whence,
+        Subscription, SubscriptionFsReadwrite, Sysinfo, Tid, Timestamp, TlKey, TlUser, TlVal, Tty,
+        Whence,
     },
     *,
 };
@@ -281,6 +283,9 @@ where
 
     let work = async move {
         tokio::select! {
+            // The work is polled first so that it still completes when it is
+            // ready by the time the timeout passes
+            biased;
             // The main work we are doing
             res = work => res,
             // Optional timeout
@@ -303,6 +308,101 @@ where
     block_on(work)
 }
 
+/// Initial delay before retrying host IO that would block on a blocking fd
+const BLOCKING_IO_RETRY_MIN: Duration = Duration::from_millis(1);
+/// Upper bound on the delay between retries of host IO on a blocking fd
+const BLOCKING_IO_RETRY_MAX: Duration = Duration::from_millis(100);
+
+/// Exponential backoff used to retry host IO that reported a transient
+/// "would block" condition on a file descriptor in blocking mode
+///
+/// Guests that opened a file as blocking generally don't handle `EAGAIN`,
+/// so rather than surfacing it the operation is retried until it either
+/// completes or fails with another error. The IO gives up the file handle
+/// with `Errno::Again` and the thread deep sleeps between the attempts, so
+/// others that share the handle are not held up by the retries.
+#[derive(Debug, Clone, Copy, serde::Serialize, serde::Deserialize)]
+pub(crate) struct BlockingIoRetry {
+    delay: Duration,
+}
+
+impl Default for BlockingIoRetry {
+    fn default() -> Self {
+        Self {
+            delay: BLOCKING_IO_RETRY_MIN,
+        }
+    }
+}
+
+impl BlockingIoRetry {
+    /// Picks up the retries from where they were when the thread deep
+    /// slept in [`BlockingIoRetry::backoff`], this has to be called when
+    /// the syscall is entered
+    pub(crate) fn rewound<M: MemorySize>(ctx: &mut FunctionEnvMut<'_, WasiEnv>) -> Self {
+        if !ctx.data().thread.take_blocking_io_retry() {
+            return Self::default();
+        }
+        unsafe { handle_rewind::<M, Self>(ctx) }.unwrap_or_default()
+    }
+
+    /// Returns true if the error should be retried rather than returned
+    pub(crate) fn should_retry(nonblocking: bool, err: &std::io::Error) -> bool {
+        !nonblocking && err.kind() == std::io::ErrorKind::WouldBlock
+    }
+
+    /// Returns true if the IO on the file descriptor gave up the handle to
+    /// be retried after a [`BlockingIoRetry::backoff`]
+    pub(crate) fn is_pending(fd_entry: &Fd, res: &Result<usize, Errno>) -> bool {
+        matches!(res, Err(Errno::Again))
+            && !fd_entry.inner.flags.contains(Fdflags::NONBLOCK)
+            && matches!(
+                fd_entry.inode.read().deref(),
+                Kind::File {
+                    handle: Some(_),
+                    ..
+                }
+            )
+    }
+
+    /// Deep sleeps before the next attempt, doubling the delay each time
+    ///
+    /// Returns `Errno::Success` when the stack unwinds for the sleep, the
+    /// syscall then has to return right away and makes the next attempt
+    /// once it is rewound.
+    pub(crate) fn backoff<M: MemorySize>(
+        self,
+        ctx: FunctionEnvMut<'_, WasiEnv>,
+    ) -> Result<Result<(FunctionEnvMut<'_, WasiEnv>, Self), Errno>, WasiError> {
+        tracing::trace!("retrying blocking host IO in {:?}", self.delay);
+        let tasks = ctx.data().tasks().clone();
+        let next = Self {
+            delay: (self.delay * 2).min(BLOCKING_IO_RETRY_MAX),
+        };
+        let delay = self.delay;
+        ctx.data().thread.set_blocking_io_retry(true);
+        let thread = ctx.data().thread.clone();
+        let res = __asyncify_with_deep_sleep::<M, _, _>(ctx, async move {
+            tasks.sleep_now(delay).await;
+            next
+        });
+        match res {
+            Ok(AsyncifyAction::Finish(ctx, next)) => {
+                ctx.data().thread.set_blocking_io_retry(false);
+                Ok(Ok((ctx, next)))
+            }
+            Ok(AsyncifyAction::Unwind) => Ok(Err(Errno::Success)),
+            Ok(AsyncifyAction::Abort(err)) => {
+                thread.set_blocking_io_retry(false);
+                Ok(Err(err))
+            }
+            Err(err) => {
+                thread.set_blocking_io_retry(false);
+                Err(err)
+            }
+        }
+    }
+}
+
 /// Asyncify takes the current thread and blocks on the async runtime associated with it
 /// thus allowed for asynchronous operations to execute. It has built in functionality
 /// to (optionally) timeout the IO, force exit the process, callback signals and pump
@@ -396,12 +496,14 @@ where
                 signals
                     .0
                     .iter()
+                    .filter(|sig| !env.thread.is_signal_blocked(**sig))
                     .filter_map(|sig| {
                         if *sig == Signal::Sigint
                             || *sig == Signal::Sigquit
                             || *sig == Signal::Sigkill
                             || *sig == Signal::Sigabrt
                         {
+                            env.process.record_exit_signal(*sig);
                             Some(env.thread.set_or_get_exit_code_for_signal(*sig))
                         } else {
                             None
@@ -435,6 +537,10 @@ pub enum AsyncifyAction<'a, R> {
     /// Indicates that asyncify should unwind by immediately exiting
     /// the current function
     Unwind,
+    /// Indicates that the thread could not be put into a deep sleep
+    /// (e.g. its stack is too large to snapshot) and the syscall
+    /// should fail with this error instead
+    Abort(Errno),
 }
 
 /// Exponentially increasing backoff of CPU usage
@@ -461,12 +567,10 @@ pub(crate) fn maybe_backoff<M: MemorySize>(
     // Determine if we need to do a backoff, if so lets do one
     if let Some(backoff) = env.process.acquire_cpu_backoff_token(env.tasks()) {
         tracing::trace!("exponential CPU backoff {:?}", backoff.backoff_time());
-        if let AsyncifyAction::Finish(mut ctx, _) =
-            __asyncify_with_deep_sleep::<M, _, _>(ctx, backoff)?
-        {
-            Ok(Ok(ctx))
-        } else {
-            Ok(Err(Errno::Success))
+        match __asyncify_with_deep_sleep::<M, _, _>(ctx, backoff)? {
+            AsyncifyAction::Finish(ctx, _) => Ok(Ok(ctx)),
+            AsyncifyAction::Unwind => Ok(Err(Errno::Success)),
+            AsyncifyAction::Abort(err) => Ok(Err(err)),
         }
     } else {
         Ok(Ok(ctx))
@@ -490,10 +594,14 @@ where
     T: serde::Serialize + serde::de::DeserializeOwned,
     Fut: Future<Output = T> + Send + Sync + 'static,
 {
-    // Determine the deep sleep time
-    let deep_sleep_time = match ctx.data().enable_journal {
-        true => Duration::from_micros(100),
-        false => Duration::from_millis(50),
+    // Determine the deep sleep time, when all the tasks share a single
+    // thread it is handed over to the others right away
+    let deep_sleep_time = if ctx.data().tasks().is_single_threaded() {
+        Duration::ZERO
+    } else if ctx.data().enable_journal {
+        Duration::from_micros(100)
+    } else {
+        Duration::from_millis(50)
     };
 
     // Box up the trigger
@@ -532,6 +640,12 @@ where
             },
             // Determines when and if we should go into a deep sleep
             _ = deep_sleep_wait => {
+                // Deep sleeping requires a snapshot of the stack, if its too
+                // large then we give up rather than capture it
+                if let Err(err) = check_snapshot_size(&mut ctx) {
+                    return Ok(AsyncifyAction::Abort(err));
+                }
+
                 let pid = ctx.data().pid();
                 let tid = ctx.data().tid();
 
@@ -580,6 +694,68 @@ where
     Ok(block_on(work))
 }
 
+/// Blocks on a read or a write the way [`__asyncify_light`] does, except
+/// that a signal arriving while it waits interrupts it with `Errno::Intr`.
+///
+/// Only the signals that would do something interrupt the work: the ones
+/// that go to the signal handler of the guest or that terminate it when it
+/// has none. The signals are left pending, the caller runs their handlers
+/// once the work (and its borrows of the memory) is gone, see
+/// [`WasiEnv::handle_interrupted_io`]. A work that transferred some bytes
+/// before it got interrupted is expected to return that count instead.
+///
+/// The work fails with `Errno::Timedout` once the timeout passes. A timeout
+/// of zero is how the callers ask for nonblocking IO, which the work takes
+/// care of itself, so it doesn't time out the work.
+pub(crate) fn __asyncify_interruptible<T, Fut>(
+    env: &WasiEnv,
+    timeout: Option<Duration>,
+    work: Fut,
+) -> WasiResult<T>
+where
+    T: 'static,
+    Fut: Future<Output = Result<T, Errno>>,
+{
+    struct InterruptiblePoller<'a, Fut> {
+        env: &'a WasiEnv,
+        has_handler: bool,
+        work: Pin<Box<Fut>>,
+    }
+    impl<T, Fut> Future for InterruptiblePoller<'_, Fut>
+    where
+        Fut: Future<Output = Result<T, Errno>>,
+    {
+        type Output = Result<T, Errno>;
+        fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+            if let Poll::Ready(res) = self.work.as_mut().poll(cx) {
+                return Poll::Ready(res);
+            }
+
+            let thread = &self.env.thread;
+            let mut guard = thread.signals().lock().unwrap();
+            let interrupted = guard.0.iter().any(|sig| {
+                !thread.is_signal_blocked(*sig)
+                    && self.env.signal_interrupts_io(*sig, self.has_handler)
+            });
+            if interrupted {
+                return Poll::Ready(Err(Errno::Intr));
+            }
+            if !guard.1.iter().any(|w| w.will_wake(cx.waker())) {
+                guard.1.push(cx.waker().clone());
+            }
+            Poll::Pending
+        }
+    }
+
+    let poller = InterruptiblePoller {
+        env,
+        has_handler: env.has_signal_handler(),
+        work: Box::pin(work),
+    };
+    let timeout = timeout.filter(|timeout| !timeout.is_zero());
+    block_on_with_timeout(env.tasks(), timeout, async move { Ok(poller.await) })
+}
+
 // This should be compiled away, it will simply wait forever however its never
 // used by itself, normally this is passed into asyncify which will still abort
 // the operating on timeouts, signals or other work due to a select! around the await
@@ -896,6 +1072,26 @@ pub(crate) unsafe fn get_memory_stack_offset(
     Ok(stack_upper - stack_pointer)
 }
 
+/// Makes sure that the stack currently in use is small enough to be
+/// captured in a snapshot (see [`Runtime::snapshot_size_limit`])
+pub(crate) fn check_snapshot_size(ctx: &mut FunctionEnvMut<'_, WasiEnv>) -> Result<(), Errno> {
+    let Some(limit) = ctx.data().runtime().snapshot_size_limit() else {
+        return Ok(());
+    };
+    let stack_size = match unsafe { get_memory_stack_offset(ctx) } {
+        Ok(a) => a,
+        Err(err) => {
+            warn!("unable to get the memory stack - {}", err);
+            return Err(Errno::Unknown);
+        }
+    };
+    if stack_size > limit {
+        warn!(%stack_size, %limit, "stack is too large to be captured in a snapshot");
+        return Err(Errno::Nomem);
+    }
+    Ok(())
+}
+
 pub(crate) fn set_memory_stack_offset(
     env: &WasiEnv,
     store: &mut impl AsStoreMut,
@@ -1090,6 +1286,7 @@ pub(crate) fn deep_sleep<M: MemorySize>(
         }
 
         // Schedule the process on the stack so that it can be resumed
+        ctx.data().process.record_deep_sleep();
         OnCalledAction::Trap(Box::new(WasiError::DeepSleep(DeepSleepWork {
             trigger,
             rewind: RewindState {
@@ -16,6 +16,7 @@ pub fn clock_res_get<M: MemorySize>(
     resolution: WasmPtr<Timestamp, M>,
 ) -> Errno {
     let env = ctx.data();
+    wasi_try!(env.sandbox_policy().check_clock(clock_id));
     let memory = unsafe { env.memory_view(&ctx) };
 
     let out_addr = resolution.deref(&memory);
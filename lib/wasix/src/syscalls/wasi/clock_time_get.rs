@@ -28,11 +28,12 @@ pub fn clock_time_get<M: MemorySize>(
     precision: Timestamp,
     time: WasmPtr<Timestamp, M>,
 ) -> Result<Errno, WasiError> {
-    WasiEnv::do_pending_operations(&mut ctx)?;
+    wasi_pending_operations_or_sleep!(&mut ctx);
 
     ctx = wasi_try_ok!(maybe_backoff::<M>(ctx)?);
 
     let env = ctx.data();
+    wasi_try_ok!(env.sandbox_policy().check_clock(clock_id));
     let memory = unsafe { env.memory_view(&ctx) };
 
     let mut t_out = wasi_try_ok!(platform_clock_time_get(clock_id, precision));
@@ -14,7 +14,8 @@ pub fn clock_time_set(
     clock_id: Snapshot0Clockid,
     time: Timestamp,
 ) -> Result<Errno, WasiError> {
-    WasiEnv::do_pending_operations(&mut ctx)?;
+    wasi_pending_operations_or_sleep!(&mut ctx);
+    wasi_try_ok!(ctx.data().sandbox_policy().check_clock(clock_id));
 
     let ret = clock_time_set_internal(&mut ctx, clock_id, time);
     let env = ctx.data();
@@ -20,7 +20,7 @@ pub fn fd_advise(
     len: Filesize,
     advice: Advice,
 ) -> Result<Errno, WasiError> {
-    WasiEnv::do_pending_operations(&mut ctx)?;
+    wasi_pending_operations_or_sleep!(&mut ctx);
 
     wasi_try_ok!(fd_advise_internal(&mut ctx, fd, offset, len, advice));
     let env = ctx.data();
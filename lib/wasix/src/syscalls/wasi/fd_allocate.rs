@@ -17,7 +17,7 @@ pub fn fd_allocate(
     offset: Filesize,
     len: Filesize,
 ) -> Result<Errno, WasiError> {
-    WasiEnv::do_pending_operations(&mut ctx)?;
+    wasi_pending_operations_or_sleep!(&mut ctx);
 
     wasi_try_ok!(fd_allocate_internal(&mut ctx, fd, offset, len));
     let env = ctx.data();
@@ -77,6 +77,7 @@ pub(crate) fn fd_allocate_internal(
             | Kind::DuplexPipe { .. }
             | Kind::Symlink { .. }
             | Kind::EventNotifications { .. }
+            | Kind::SignalNotifications { .. }
             | Kind::Epoll { .. } => return Err(Errno::Badf),
             Kind::Dir { .. } | Kind::Root { .. } => return Err(Errno::Isdir),
         }
@@ -31,7 +31,7 @@ impl Future for FlushPoller {
 ///     If `fd` is invalid or not open
 #[instrument(level = "trace", skip_all, fields(pid = ctx.data().process.pid().raw(), %fd), ret)]
 pub fn fd_close(mut ctx: FunctionEnvMut<'_, WasiEnv>, fd: WasiFd) -> Result<Errno, WasiError> {
-    WasiEnv::do_pending_operations(&mut ctx)?;
+    wasi_pending_operations_or_sleep!(&mut ctx);
 
     let env = ctx.data();
     let (_, mut state) = unsafe { env.get_memory_and_wasi_state(&ctx, 0) };
@@ -55,6 +55,7 @@ pub fn fd_close(mut ctx: FunctionEnvMut<'_, WasiEnv>, fd: WasiFd) -> Result<Errn
                 return Ok(e);
             }
         }
+        drop(fd_entry);
         wasi_try_ok!(state.fs.close_fd(fd));
     } else {
         // Capture the file handle before removing the fd, then close first.
@@ -70,6 +71,9 @@ pub fn fd_close(mut ctx: FunctionEnvMut<'_, WasiEnv>, fd: WasiFd) -> Result<Errn
             }
         });
 
+        // The copy of the descriptor goes first, so that closing it can tell
+        // if it was the last descriptor of its open file description
+        drop(fd_entry);
         wasi_try_ok!(state.fs.close_fd(fd));
 
         if let Some(file) = flush_target {
@@ -8,7 +8,7 @@ use crate::syscalls::*;
 ///     The file descriptor to sync
 #[instrument(level = "trace", skip_all, fields(%fd), ret)]
 pub fn fd_datasync(mut ctx: FunctionEnvMut<'_, WasiEnv>, fd: WasiFd) -> Result<Errno, WasiError> {
-    WasiEnv::do_pending_operations(&mut ctx)?;
+    wasi_pending_operations_or_sleep!(&mut ctx);
 
     let env = ctx.data();
     let state = env.state.clone();
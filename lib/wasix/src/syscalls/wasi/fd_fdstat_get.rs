@@ -15,7 +15,7 @@ pub fn fd_fdstat_get<M: MemorySize>(
     fd: WasiFd,
     buf_ptr: WasmPtr<Fdstat, M>,
 ) -> Result<Errno, WasiError> {
-    WasiEnv::do_pending_operations(&mut ctx)?;
+    wasi_pending_operations_or_sleep!(&mut ctx);
 
     let env = ctx.data();
     let (memory, mut state, inodes) = unsafe { env.get_memory_and_wasi_state_and_inodes(&ctx, 0) };
@@ -17,7 +17,7 @@ pub fn fd_fdstat_set_rights(
     fs_rights_base: Rights,
     fs_rights_inheriting: Rights,
 ) -> Result<Errno, WasiError> {
-    WasiEnv::do_pending_operations(&mut ctx)?;
+    wasi_pending_operations_or_sleep!(&mut ctx);
 
     wasi_try_ok!(fd_fdstat_set_rights_internal(
         &mut ctx,
@@ -18,7 +18,7 @@ pub fn fd_filestat_get<M: MemorySize>(
     fd: WasiFd,
     buf: WasmPtr<Filestat, M>,
 ) -> Result<Errno, WasiError> {
-    WasiEnv::do_pending_operations(&mut ctx)?;
+    wasi_pending_operations_or_sleep!(&mut ctx);
 
     let stat = wasi_try_ok!(fd_filestat_get_internal(&mut ctx, fd));
 
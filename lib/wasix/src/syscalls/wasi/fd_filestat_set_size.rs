@@ -14,10 +14,15 @@ pub fn fd_filestat_set_size(
     fd: WasiFd,
     st_size: Filesize,
 ) -> Result<Errno, WasiError> {
-    WasiEnv::do_pending_operations(&mut ctx)?;
+    wasi_pending_operations_or_sleep!(&mut ctx);
 
-    wasi_try_ok!(fd_filestat_set_size_internal(&mut ctx, fd, st_size));
     let env = ctx.data();
+    let fd_entry = wasi_try_ok!(env.state.fs.get_fd(fd));
+    if !fd_entry.inner.rights.contains(Rights::FD_FILESTAT_SET_SIZE) {
+        return Ok(Errno::Access);
+    }
+
+    wasi_try_ok!(fd_filestat_set_size_internal(env, fd, st_size));
 
     #[cfg(feature = "journal")]
     if env.enable_journal {
@@ -30,33 +35,31 @@ pub fn fd_filestat_set_size(
     Ok(Errno::Success)
 }
 
+/// Changes the size of the file behind the descriptor, the caller checks
+/// that it is allowed to
 pub(crate) fn fd_filestat_set_size_internal(
-    ctx: &mut FunctionEnvMut<'_, WasiEnv>,
+    env: &WasiEnv,
     fd: WasiFd,
     st_size: Filesize,
 ) -> Result<(), Errno> {
-    let env = ctx.data();
-    let (_, mut state) = unsafe { env.get_memory_and_wasi_state(&ctx, 0) };
-    let fd_entry = state.fs.get_fd(fd)?;
+    let fd_entry = env.state.fs.get_fd(fd)?;
     let inode = fd_entry.inode;
 
-    if !fd_entry.inner.rights.contains(Rights::FD_FILESTAT_SET_SIZE) {
-        return Err(Errno::Access);
-    }
-
-    {
+    let modified = {
         let mut guard = inode.write();
         match guard.deref_mut() {
-            Kind::File { handle, .. } => {
+            Kind::File { handle, path, .. } => {
                 if let Some(handle) = handle {
                     let mut handle = handle.write().unwrap();
                     handle.set_len(st_size).map_err(fs_error_into_wasi_err)?;
                 } else {
                     return Err(Errno::Badf);
                 }
+                Some(path.clone())
             }
             Kind::Buffer { buffer } => {
                 buffer.resize(st_size as usize, 0);
+                None
             }
             Kind::Socket { .. }
             | Kind::PipeRx { .. }
@@ -64,11 +67,15 @@ pub(crate) fn fd_filestat_set_size_internal(
             | Kind::DuplexPipe { .. }
             | Kind::Symlink { .. }
             | Kind::EventNotifications { .. }
+            | Kind::SignalNotifications { .. }
             | Kind::Epoll { .. } => return Err(Errno::Badf),
             Kind::Dir { .. } | Kind::Root { .. } => return Err(Errno::Isdir),
         }
-    }
+    };
     inode.stat.write().unwrap().st_size = st_size;
+    if let Some(path) = modified {
+        env.state.fs.watches.notify(path, Fswatchmask::MODIFY);
+    }
 
     Ok(())
 }
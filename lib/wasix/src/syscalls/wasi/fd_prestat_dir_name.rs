@@ -45,6 +45,7 @@ pub fn fd_prestat_dir_name<M: MemorySize>(
         | Kind::PipeTx { .. }
         | Kind::DuplexPipe { .. }
         | Kind::EventNotifications { .. }
+        | Kind::SignalNotifications { .. }
         | Kind::Epoll { .. } => Errno::Notdir,
     }
 }
@@ -1,12 +1,12 @@
-use std::{collections::VecDeque, task::Waker};
+use std::{collections::VecDeque, task::Waker, time::Instant};
 
 use virtual_fs::{AsyncReadExt, DeviceFile, ReadBuf};
 
 use super::*;
 use crate::{
-    fs::NotificationInner,
+    fs::{NotificationInner, SignalNotificationInner},
     journal::SnapshotTrigger,
-    net::socket::TimeType,
+    net::socket::{MAX_DATAGRAM_SIZE, TimeType},
     os::task::process::{MaybeCheckpointResult, WasiProcessCheckpoint, WasiProcessInner},
     syscalls::*,
 };
@@ -32,7 +32,8 @@ pub fn fd_read<M: MemorySize>(
     iovs_len: M::Offset,
     nread: WasmPtr<M::Offset, M>,
 ) -> Result<Errno, WasiError> {
-    WasiEnv::do_pending_operations(&mut ctx)?;
+    wasi_pending_operations_or_sleep!(&mut ctx);
+    let mut retry = BlockingIoRetry::rewound::<M>(&mut ctx);
 
     let pid = ctx.data().pid();
     let tid = ctx.data().tid();
@@ -49,7 +50,26 @@ pub fn fd_read<M: MemorySize>(
         ctx = wasi_try_ok!(maybe_snapshot_once::<M>(ctx, SnapshotTrigger::FirstStdin)?);
     }
 
-    let res = fd_read_internal::<M>(&mut ctx, fd, fd_entry, iovs, iovs_len, offset, nread, true)?;
+    let res = loop {
+        let res = fd_read_internal::<M>(
+            &mut ctx,
+            fd,
+            fd_entry.clone(),
+            iovs,
+            iovs_len,
+            offset,
+            nread,
+            true,
+            &ReadDeadline::default(),
+        )?;
+        if BlockingIoRetry::is_pending(&fd_entry, &res) {
+            (ctx, retry) = wasi_try_ok!(retry.backoff::<M>(ctx)?);
+            continue;
+        }
+        if !WasiEnv::handle_interrupted_io(&mut ctx, &res)? {
+            break res;
+        }
+    };
     fd_read_internal_handler(ctx, res, nread)
 }
 
@@ -77,6 +97,8 @@ pub fn fd_pread<M: MemorySize>(
     offset: Filesize,
     nread: WasmPtr<M::Offset, M>,
 ) -> Result<Errno, WasiError> {
+    let mut retry = BlockingIoRetry::rewound::<M>(&mut ctx);
+
     let pid = ctx.data().pid();
     let tid = ctx.data().tid();
 
@@ -90,16 +112,26 @@ pub fn fd_pread<M: MemorySize>(
         let state = env.state.clone();
         wasi_try_ok!(state.fs.get_fd(fd))
     };
-    let res = fd_read_internal::<M>(
-        &mut ctx,
-        fd,
-        fd_entry,
-        iovs,
-        iovs_len,
-        offset as usize,
-        nread,
-        false,
-    )?;
+    let res = loop {
+        let res = fd_read_internal::<M>(
+            &mut ctx,
+            fd,
+            fd_entry.clone(),
+            iovs,
+            iovs_len,
+            offset as usize,
+            nread,
+            false,
+            &ReadDeadline::default(),
+        )?;
+        if BlockingIoRetry::is_pending(&fd_entry, &res) {
+            (ctx, retry) = wasi_try_ok!(retry.backoff::<M>(ctx)?);
+            continue;
+        }
+        if !WasiEnv::handle_interrupted_io(&mut ctx, &res)? {
+            break res;
+        }
+    };
     fd_read_internal_handler::<M>(ctx, res, nread)
 }
 
@@ -131,6 +163,39 @@ pub(crate) fn fd_read_internal_handler<M: MemorySize>(
     Ok(ret)
 }
 
+/// When a blocking read gives up, see [`fd_read_deadline`]
+#[derive(Debug, Default)]
+pub(crate) struct ReadDeadline {
+    at: Option<Instant>,
+}
+
+impl ReadDeadline {
+    pub(crate) fn new(at: Option<Instant>) -> Self {
+        Self { at }
+    }
+
+    /// The timeout that asyncify gives a blocking read, once it passes the
+    /// read fails with `Errno::Timedout`. A read whose deadline has already
+    /// passed still gets a (tiny) timeout rather than none at all, so that
+    /// the data that is already there is read.
+    fn timeout(&self) -> Option<Duration> {
+        self.at.map(|at| {
+            at.saturating_duration_since(Instant::now())
+                .max(Duration::from_nanos(1))
+        })
+    }
+
+    /// Reads that time out for other reasons (like sockets with a receive
+    /// timeout) fail with `Errno::Again`, only the deadline passing makes
+    /// them fail with `Errno::Timedout`
+    fn map_err(&self, err: Errno) -> Errno {
+        match err {
+            Errno::Timedout if !self.at.is_some_and(|at| Instant::now() >= at) => Errno::Again,
+            err => err,
+        }
+    }
+}
+
 #[allow(clippy::await_holding_lock)]
 pub(crate) fn fd_read_internal<M: MemorySize>(
     ctx: &mut FunctionEnvMut<'_, WasiEnv>,
@@ -141,6 +206,7 @@ pub(crate) fn fd_read_internal<M: MemorySize>(
     offset: usize,
     nread: WasmPtr<M::Offset, M>,
     should_update_cursor: bool,
+    deadline: &ReadDeadline,
 ) -> WasiResult<usize> {
     let env = ctx.data();
     let memory = unsafe { env.memory_view(&ctx) };
@@ -165,15 +231,20 @@ pub(crate) fn fd_read_internal<M: MemorySize>(
                         return Ok(Err(Errno::Badf));
                     };
                     let handle = handle.clone();
+                    let nonblocking = fd_flags.contains(Fdflags::NONBLOCK);
 
                     drop(guard);
 
-                    let res = __asyncify_light(
+                    // What was read before a signal interrupted the read is
+                    // still returned
+                    let transferred = AtomicUsize::new(0);
+                    let transferred_ref = &transferred;
+                    let res = __asyncify_interruptible(
                         env,
-                        if fd_flags.contains(Fdflags::NONBLOCK) {
+                        if nonblocking {
                             Some(Duration::ZERO)
                         } else {
-                            None
+                            deadline.timeout()
                         },
                         async move {
                             let mut handle = match handle.write() {
@@ -198,7 +269,20 @@ pub(crate) fn fd_read_internal<M: MemorySize>(
                                     .map_err(mem_error_to_wasi)?
                                     .access()
                                     .map_err(mem_error_to_wasi)?;
-                                let r = handle.read(buf.as_mut()).await.map_err(|err| {
+                                // The handle is given up before the read is retried so
+                                // that others can use it in the meantime
+                                let r = match handle.read(buf.as_mut()).await {
+                                    Err(err)
+                                        if BlockingIoRetry::should_retry(nonblocking, &err) =>
+                                    {
+                                        if total_read > 0 {
+                                            break;
+                                        }
+                                        return Err(Errno::Again);
+                                    }
+                                    r => r,
+                                };
+                                let r = r.map_err(|err| {
                                     let err = From::<std::io::Error>::from(err);
                                     match err {
                                         Errno::Again => {
@@ -217,6 +301,7 @@ pub(crate) fn fd_read_internal<M: MemorySize>(
                                     Err(err) => return Err(err),
                                 };
                                 total_read += local_read;
+                                transferred_ref.store(total_read, Ordering::Relaxed);
                                 if local_read != buf.len() {
                                     break;
                                 }
@@ -224,9 +309,11 @@ pub(crate) fn fd_read_internal<M: MemorySize>(
                             Ok(total_read)
                         },
                     );
-                    let read = wasi_try_ok_ok!(res?.map_err(|err| match err {
-                        Errno::Timedout => Errno::Again,
-                        a => a,
+                    let read = wasi_try_ok_ok!(res?.or_else(|err| match err {
+                        Errno::Intr if transferred.load(Ordering::Relaxed) > 0 => {
+                            Ok(transferred.load(Ordering::Relaxed))
+                        }
+                        err => Err(deadline.map_err(err)),
                     }));
                     (read, true)
                 }
@@ -236,19 +323,15 @@ pub(crate) fn fd_read_internal<M: MemorySize>(
                     drop(guard);
 
                     let nonblocking = fd_flags.contains(Fdflags::NONBLOCK);
-                    let timeout = socket
-                        .opt_time(TimeType::ReadTimeout)
-                        .ok()
-                        .flatten()
-                        .unwrap_or(Duration::from_secs(30));
+                    let timeout = socket.opt_time(TimeType::ReadTimeout).ok().flatten();
 
                     let tasks = env.tasks().clone();
-                    let res = __asyncify_light(
+                    let res = __asyncify_interruptible(
                         env,
                         if fd_flags.contains(Fdflags::NONBLOCK) {
                             Some(Duration::ZERO)
                         } else {
-                            None
+                            deadline.timeout()
                         },
                         async move {
                             let mut total_read = 0usize;
@@ -263,27 +346,31 @@ pub(crate) fn fd_read_internal<M: MemorySize>(
                                     .access()
                                     .map_err(mem_error_to_wasi)?;
 
-                                let local_read = socket
+                                // Only the first buffer waits for data, the others
+                                // get whatever is left over
+                                let local_read = match socket
                                     .recv(
                                         tasks.deref(),
                                         buf.as_mut_uninit(),
-                                        Some(timeout),
-                                        nonblocking,
+                                        timeout,
+                                        nonblocking || total_read > 0,
                                         false,
                                     )
-                                    .await?;
+                                    .await
+                                {
+                                    Ok(s) => s,
+                                    Err(_) if total_read > 0 => break,
+                                    Err(err) => return Err(err),
+                                };
                                 total_read += local_read;
-                                if total_read != buf.len() {
+                                if local_read != buf.len() {
                                     break;
                                 }
                             }
                             Ok(total_read)
                         },
                     );
-                    let res = res?.map_err(|err| match err {
-                        Errno::Timedout => Errno::Again,
-                        a => a,
-                    });
+                    let res = res?.map_err(|err| deadline.map_err(err));
                     match res {
                         Err(Errno::Connaborted) | Err(Errno::Connreset) => (0, false),
                         res => {
@@ -299,12 +386,12 @@ pub(crate) fn fd_read_internal<M: MemorySize>(
 
                     let nonblocking = fd_flags.contains(Fdflags::NONBLOCK);
 
-                    let res = __asyncify_light(
+                    let res = __asyncify_interruptible(
                         env,
                         if fd_flags.contains(Fdflags::NONBLOCK) {
                             Some(Duration::ZERO)
                         } else {
-                            None
+                            deadline.timeout()
                         },
                         async move {
                             let mut total_read = 0usize;
@@ -319,9 +406,12 @@ pub(crate) fn fd_read_internal<M: MemorySize>(
                                     .access()
                                     .map_err(mem_error_to_wasi)?;
 
-                                let local_read = match nonblocking {
+                                // Only the first buffer waits for data, the others
+                                // get whatever is left over
+                                let local_read = match nonblocking || total_read > 0 {
                                     true => match rx.try_read(buf.as_mut()) {
                                         Some(amt) => amt,
+                                        None if total_read > 0 => break,
                                         None => {
                                             return Err(Errno::Again);
                                         }
@@ -340,31 +430,67 @@ pub(crate) fn fd_read_internal<M: MemorySize>(
                         },
                     );
 
-                    let bytes_read = wasi_try_ok_ok!(res?.map_err(|err| match err {
-                        Errno::Timedout => Errno::Again,
-                        a => a,
-                    }));
+                    let bytes_read = wasi_try_ok_ok!(res?.map_err(|err| deadline.map_err(err)));
 
                     (bytes_read, false)
                 }
-                Kind::DuplexPipe { pipe } => {
+                Kind::DuplexPipe { pipe, fds } => {
+                    // The descriptors that were passed with the messages that
+                    // were read before without `sock_recv_fds` are closed
+                    fds.discard(pipe.bytes_read());
                     let mut pipe = pipe.clone();
                     drop(guard);
 
                     let nonblocking = fd_flags.contains(Fdflags::NONBLOCK);
+                    let datagram = pipe.is_datagram();
 
-                    let res = __asyncify_light(
+                    let res = __asyncify_interruptible(
                         env,
                         if fd_flags.contains(Fdflags::NONBLOCK) {
                             Some(Duration::ZERO)
                         } else {
-                            None
+                            deadline.timeout()
                         },
                         async move {
                             let mut total_read = 0usize;
 
                             let iovs_arr =
                                 iovs.slice(&memory, iovs_len).map_err(mem_error_to_wasi)?;
+
+                            // A single datagram is read, spread over all the
+                            // buffers and truncated to their total length
+                            if datagram {
+                                let mut len = 0usize;
+                                for iov in iovs_arr.iter() {
+                                    let iov = iov.read().map_err(mem_error_to_wasi)?;
+                                    len = len.saturating_add(from_offset::<M>(iov.buf_len)?);
+                                }
+                                // The buffer is no bigger than the datagram that
+                                // is waiting, the guest only decides how much of
+                                // it is kept
+                                let pending = match nonblocking {
+                                    true => pipe.bytes_available(),
+                                    false => {
+                                        std::future::poll_fn(|cx| {
+                                            Pin::new(&mut pipe).poll_read_ready(cx)
+                                        })
+                                        .await?
+                                    }
+                                };
+                                let len = match pending {
+                                    0 => len.min(MAX_DATAGRAM_SIZE),
+                                    pending => len.min(pending),
+                                };
+                                let mut data = vec![0u8; len];
+                                let read = match nonblocking {
+                                    true => pipe.try_read(&mut data).ok_or(Errno::Again)?,
+                                    false => {
+                                        virtual_fs::AsyncReadExt::read(&mut pipe, &mut data).await?
+                                    }
+                                };
+                                return copy_from_slice::<M>(&data[..read], &memory, iovs_arr);
+                            }
+
                             let iovs_arr = iovs_arr.access().map_err(mem_error_to_wasi)?;
                             for iovs in iovs_arr.iter() {
                                 let mut buf = WasmPtr::<u8, M>::new(iovs.buf)
@@ -373,9 +499,12 @@ pub(crate) fn fd_read_internal<M: MemorySize>(
                                     .access()
                                     .map_err(mem_error_to_wasi)?;
 
-                                let local_read = match nonblocking {
+                                // Only the first buffer waits for data, the others
+                                // get whatever is left over
+                                let local_read = match nonblocking || total_read > 0 {
                                     true => match pipe.try_read(buf.as_mut()) {
                                         Some(amt) => amt,
+                                        None if total_read > 0 => break,
                                         None => {
                                             return Err(Errno::Again);
                                         }
@@ -394,10 +523,7 @@ pub(crate) fn fd_read_internal<M: MemorySize>(
                         },
                     );
 
-                    let bytes_read = wasi_try_ok_ok!(res?.map_err(|err| match err {
-                        Errno::Timedout => Errno::Again,
-                        a => a,
-                    }));
+                    let bytes_read = wasi_try_ok_ok!(res?.map_err(|err| deadline.map_err(err)));
 
                     (bytes_read, false)
                 }
@@ -434,10 +560,8 @@ pub(crate) fn fd_read_internal<M: MemorySize>(
                     // Yield until the notifications are triggered
                     let tasks_inner = env.tasks().clone();
 
-                    let res = __asyncify_light(env, None, poller)?.map_err(|err| match err {
-                        Errno::Timedout => Errno::Again,
-                        a => a,
-                    });
+                    let res = __asyncify_interruptible(env, deadline.timeout(), poller)?
+                        .map_err(|err| deadline.map_err(err));
                     let val = wasi_try_ok_ok!(res);
 
                     let mut memory = unsafe { env.memory_view(ctx) };
@@ -446,6 +570,50 @@ pub(crate) fn fd_read_internal<M: MemorySize>(
                     let ret = wasi_try_ok_ok!(read_bytes(&reader[..], &memory, iovs_arr));
                     (ret, false)
                 }
+                Kind::SignalNotifications { inner } => {
+                    struct SignalPoller {
+                        inner: Arc<SignalNotificationInner>,
+                        non_blocking: bool,
+                    }
+                    let poller = SignalPoller {
+                        inner: inner.clone(),
+                        non_blocking: fd_flags.contains(Fdflags::NONBLOCK),
+                    };
+
+                    drop(guard);
+
+                    // Every read takes a single signal, so the buffers have to
+                    // be able to hold it before it is taken
+                    let memory = unsafe { env.memory_view(ctx) };
+                    let iovs_arr = wasi_try_mem_ok_ok!(iovs.slice(&memory, iovs_len));
+                    let iovs_arr = wasi_try_mem_ok_ok!(iovs_arr.access());
+                    let buf_len = iovs_arr
+                        .iter()
+                        .fold(0u64, |len, iov| len.saturating_add(iov.buf_len.into()));
+                    drop(iovs_arr);
+                    if buf_len < std::mem::size_of::<u32>() as u64 {
+                        return Ok(Err(Errno::Inval));
+                    }
+
+                    impl Future for SignalPoller {
+                        type Output = Result<Signal, Errno>;
+                        fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+                            if self.non_blocking {
+                                Poll::Ready(self.inner.try_read().ok_or(Errno::Again))
+                            } else {
+                                self.inner.read(cx.waker()).map(Ok)
+                            }
+                        }
+                    }
+
+                    let res = __asyncify_interruptible(env, deadline.timeout(), poller)?;
+                    let signal = wasi_try_ok_ok!(res);
+
+                    let reader = (signal as u32).to_le_bytes();
+                    let iovs_arr = wasi_try_mem_ok_ok!(iovs.slice(&memory, iovs_len));
+                    let ret = wasi_try_ok_ok!(read_bytes(&reader[..], &memory, iovs_arr));
+                    (ret, false)
+                }
                 Kind::Symlink { .. } | Kind::Epoll { .. } => {
                     return Ok(Err(Errno::Notsup));
                 }
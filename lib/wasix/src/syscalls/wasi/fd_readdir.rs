@@ -25,7 +25,7 @@ pub fn fd_readdir<M: MemorySize>(
     cookie: Dircookie,
     bufused: WasmPtr<M::Offset, M>,
 ) -> Result<Errno, WasiError> {
-    WasiEnv::do_pending_operations(&mut ctx)?;
+    wasi_pending_operations_or_sleep!(&mut ctx);
 
     let env = ctx.data();
     let (memory, mut state) = unsafe { env.get_memory_and_wasi_state(&ctx, 0) };
@@ -113,6 +113,7 @@ pub fn fd_readdir<M: MemorySize>(
             | Kind::PipeTx { .. }
             | Kind::DuplexPipe { .. }
             | Kind::EventNotifications { .. }
+            | Kind::SignalNotifications { .. }
             | Kind::Epoll { .. } => return Ok(Errno::Notdir),
         }
     };
@@ -31,7 +31,7 @@ pub fn fd_renumber(
     from: WasiFd,
     to: WasiFd,
 ) -> Result<Errno, WasiError> {
-    WasiEnv::do_pending_operations(&mut ctx)?;
+    wasi_pending_operations_or_sleep!(&mut ctx);
 
     let ret = fd_renumber_internal(&mut ctx, from, to)?;
     let env = ctx.data();
@@ -21,7 +21,7 @@ pub fn fd_seek<M: MemorySize>(
     whence: Whence,
     newoffset: WasmPtr<Filesize, M>,
 ) -> Result<Errno, WasiError> {
-    WasiEnv::do_pending_operations(&mut ctx)?;
+    wasi_pending_operations_or_sleep!(&mut ctx);
 
     let new_offset = wasi_try_ok!(fd_seek_internal(&mut ctx, fd, offset, whence)?);
     let env = ctx.data();
@@ -65,24 +65,16 @@ pub(crate) fn fd_seek_internal(
     // TODO: handle case if fd is a dir?
     let new_offset = match whence {
         Whence::Cur => {
-            let fd_offset = &fd_entry.inner.offset;
-
-            #[allow(clippy::comparison_chain)]
-            if offset > 0 {
-                let offset = offset as u64;
-                fd_offset.fetch_add(offset, Ordering::AcqRel) + offset
-            } else if offset < 0 {
-                let offset = offset.unsigned_abs();
-
-                wasi_try_ok_ok!(
-                    fd_offset
-                        .fetch_sub(offset, Ordering::AcqRel)
-                        .checked_sub(offset)
-                        .ok_or(Errno::Inval)
-                )
-            } else {
-                fd_offset.load(Ordering::Acquire)
-            }
+            let mut new_offset = Ok(0);
+            let _ = fd_entry.inner.offset.fetch_update(
+                Ordering::AcqRel,
+                Ordering::Acquire,
+                |current| {
+                    new_offset = seek_position(current, offset);
+                    new_offset.ok()
+                },
+            );
+            wasi_try_ok_ok!(new_offset)
         }
         Whence::End => {
             use std::io::SeekFrom;
@@ -99,8 +91,9 @@ pub(crate) fn fd_seek_internal(
 
                         wasi_try_ok_ok!(__asyncify(ctx, None, async move {
                             let mut handle = handle.write().unwrap();
+                            let end = seek_position(handle.size(), offset)?;
                             let end = handle
-                                .seek(SeekFrom::End(offset))
+                                .seek(SeekFrom::Start(end))
                                 .await
                                 .map_err(map_io_err)?;
 
@@ -123,6 +116,7 @@ pub(crate) fn fd_seek_internal(
                 | Kind::PipeTx { .. }
                 | Kind::DuplexPipe { .. }
                 | Kind::EventNotifications { .. }
+                | Kind::SignalNotifications { .. }
                 | Kind::Epoll { .. } => {
                     // TODO: check this
                     return Ok(Err(Errno::Inval));
@@ -146,3 +140,15 @@ pub(crate) fn fd_seek_internal(
 
     Ok(Ok(new_offset))
 }
+
+/// The position that is `offset` bytes away from `base`, positions before
+/// the start of the file are invalid and positions that don't fit in a
+/// (signed) file offset overflow
+fn seek_position(base: Filesize, offset: FileDelta) -> Result<Filesize, Errno> {
+    match base.checked_add_signed(offset) {
+        Some(position) if position <= i64::MAX as u64 => Ok(position),
+        Some(_) => Err(Errno::Overflow),
+        None if offset < 0 => Err(Errno::Inval),
+        None => Err(Errno::Overflow),
+    }
+}
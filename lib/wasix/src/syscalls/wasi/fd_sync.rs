@@ -12,7 +12,7 @@ use crate::syscalls::*;
 /// - `Errno::Notcapable`
 #[instrument(level = "trace", skip_all, fields(%fd), ret)]
 pub fn fd_sync(mut ctx: FunctionEnvMut<'_, WasiEnv>, fd: WasiFd) -> Result<Errno, WasiError> {
-    WasiEnv::do_pending_operations(&mut ctx)?;
+    wasi_pending_operations_or_sleep!(&mut ctx);
 
     let env = ctx.data();
     let (_, mut state) = unsafe { env.get_memory_and_wasi_state(&ctx, 0) };
@@ -61,6 +61,7 @@ pub fn fd_sync(mut ctx: FunctionEnvMut<'_, WasiEnv>, fd: WasiFd) -> Result<Errno
             | Kind::PipeRx { .. }
             | Kind::DuplexPipe { .. }
             | Kind::EventNotifications { .. }
+            | Kind::SignalNotifications { .. }
             | Kind::Epoll { .. } => return Ok(Errno::Inval),
         }
     }
@@ -15,7 +15,7 @@ pub fn fd_tell<M: MemorySize>(
     fd: WasiFd,
     offset: WasmPtr<Filesize, M>,
 ) -> Result<Errno, WasiError> {
-    WasiEnv::do_pending_operations(&mut ctx)?;
+    wasi_pending_operations_or_sleep!(&mut ctx);
 
     let env = ctx.data();
     let (memory, mut state) = unsafe { env.get_memory_and_wasi_state(&ctx, 0) };
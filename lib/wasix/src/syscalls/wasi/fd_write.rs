@@ -30,7 +30,8 @@ pub fn fd_write<M: MemorySize>(
     iovs_len: M::Offset,
     nwritten: WasmPtr<M::Offset, M>,
 ) -> Result<Errno, WasiError> {
-    WasiEnv::do_pending_operations(&mut ctx)?;
+    wasi_pending_operations_or_sleep!(&mut ctx);
+    let mut retry = BlockingIoRetry::rewound::<M>(&mut ctx);
 
     let env = ctx.data();
     let enable_journal = env.enable_journal;
@@ -40,15 +41,24 @@ pub fn fd_write<M: MemorySize>(
     };
     let offset = fd_entry.inner.offset.load(Ordering::Acquire) as usize;
 
-    let bytes_written = wasi_try_ok!(fd_write_internal::<M>(
-        &mut ctx,
-        fd,
-        fd_entry,
-        FdWriteSource::Iovs { iovs, iovs_len },
-        offset as u64,
-        true,
-        enable_journal,
-    )?);
+    let bytes_written = wasi_try_ok!(loop {
+        let res = fd_write_internal::<M>(
+            &mut ctx,
+            fd,
+            fd_entry.clone(),
+            FdWriteSource::Iovs { iovs, iovs_len },
+            offset as u64,
+            true,
+            enable_journal,
+        )?;
+        if BlockingIoRetry::is_pending(&fd_entry, &res) {
+            (ctx, retry) = wasi_try_ok!(retry.backoff::<M>(ctx)?);
+            continue;
+        }
+        if !WasiEnv::handle_interrupted_io(&mut ctx, &res)? {
+            break res;
+        }
+    });
 
     Span::current().record("nwritten", bytes_written);
 
@@ -85,7 +95,8 @@ pub fn fd_pwrite<M: MemorySize>(
     offset: Filesize,
     nwritten: WasmPtr<M::Offset, M>,
 ) -> Result<Errno, WasiError> {
-    WasiEnv::do_pending_operations(&mut ctx)?;
+    wasi_pending_operations_or_sleep!(&mut ctx);
+    let mut retry = BlockingIoRetry::rewound::<M>(&mut ctx);
 
     let enable_snapshot_capture = ctx.data().enable_journal;
 
@@ -94,15 +105,24 @@ pub fn fd_pwrite<M: MemorySize>(
         let state = env.state.clone();
         wasi_try_ok!(state.fs.get_fd(fd))
     };
-    let bytes_written = wasi_try_ok!(fd_write_internal::<M>(
-        &mut ctx,
-        fd,
-        fd_entry,
-        FdWriteSource::Iovs { iovs, iovs_len },
-        offset,
-        false,
-        enable_snapshot_capture,
-    )?);
+    let bytes_written = wasi_try_ok!(loop {
+        let res = fd_write_internal::<M>(
+            &mut ctx,
+            fd,
+            fd_entry.clone(),
+            FdWriteSource::Iovs { iovs, iovs_len },
+            offset,
+            false,
+            enable_snapshot_capture,
+        )?;
+        if BlockingIoRetry::is_pending(&fd_entry, &res) {
+            (ctx, retry) = wasi_try_ok!(retry.backoff::<M>(ctx)?);
+            continue;
+        }
+        if !WasiEnv::handle_interrupted_io(&mut ctx, &res)? {
+            break res;
+        }
+    });
 
     Span::current().record("nwritten", bytes_written);
 
@@ -145,6 +165,7 @@ pub(crate) fn fd_write_internal<M: MemorySize>(
         }
 
         let fd_flags = fd_entry.inner.flags;
+        let is_append = !is_stdio && fd_flags.contains(Fdflags::APPEND);
         let mut memory = unsafe { env.memory_view(&ctx) };
 
         let (bytes_written, is_file, can_snapshot) = {
@@ -154,72 +175,119 @@ pub(crate) fn fd_write_internal<M: MemorySize>(
                 Kind::File { handle, .. } => {
                     if let Some(handle) = handle {
                         let handle = handle.clone();
+                        let nonblocking = fd_entry.inner.flags.contains(Fdflags::NONBLOCK);
                         drop(guard);
 
-                        let res = __asyncify_light(
-                            env,
-                            if fd_entry.inner.flags.contains(Fdflags::NONBLOCK) {
-                                Some(Duration::ZERO)
-                            } else {
-                                None
-                            },
-                            async {
-                                let mut handle = handle.write().unwrap();
-                                if !is_stdio {
-                                    if fd_entry.inner.flags.contains(Fdflags::APPEND) {
-                                        // `fdflags::append` means we need to seek to the end before writing.
-                                        offset = fd_entry.inode.stat.read().unwrap().st_size;
-                                        fd_entry.inner.offset.store(offset, Ordering::Release);
-                                    }
-
-                                    handle
-                                        .seek(std::io::SeekFrom::Start(offset))
-                                        .await
-                                        .map_err(map_io_err)?;
+                        // What was written before a signal interrupted the
+                        // write is still returned
+                        let transferred = AtomicUsize::new(0);
+                        let timeout = if nonblocking {
+                            Some(Duration::ZERO)
+                        } else {
+                            None
+                        };
+                        let work = async {
+                            let mut handle = handle.write().unwrap();
+                            if !is_stdio {
+                                if is_append {
+                                    // `fdflags::append` means we need to seek to the end before writing.
+                                    // The end is computed while holding the handle lock so that
+                                    // concurrent appenders never start from a stale size.
+                                    let st_size = fd_entry.inode.stat.read().unwrap().st_size;
+                                    offset = st_size.max(handle.size());
                                 }
 
-                                let mut written = 0usize;
+                                handle
+                                    .seek(std::io::SeekFrom::Start(offset))
+                                    .await
+                                    .map_err(map_io_err)?;
+                            }
+
+                            let mut written = 0usize;
+
+                            // Appends publish the new end of file before the handle lock
+                            // is released so the next appender writes after this data. It
+                            // is published after every buffer as a signal may interrupt
+                            // the write before it gets to the end.
+                            let publish_end = |end: u64| {
+                                let mut stat = fd_entry.inode.stat.write().unwrap();
+                                stat.st_size = stat.st_size.max(end);
+                                fd_entry.inner.offset.store(end, Ordering::Release);
+                            };
 
-                                match &data {
-                                    FdWriteSource::Iovs { iovs, iovs_len } => {
-                                        let iovs_arr = iovs
-                                            .slice(&memory, *iovs_len)
+                            match &data {
+                                FdWriteSource::Iovs { iovs, iovs_len } => {
+                                    let iovs_arr = iovs
+                                        .slice(&memory, *iovs_len)
+                                        .map_err(mem_error_to_wasi)?;
+                                    let iovs_arr = iovs_arr.access().map_err(mem_error_to_wasi)?;
+                                    for iovs in iovs_arr.iter() {
+                                        let buf = WasmPtr::<u8, M>::new(iovs.buf)
+                                            .slice(&memory, iovs.buf_len)
+                                            .map_err(mem_error_to_wasi)?
+                                            .access()
                                             .map_err(mem_error_to_wasi)?;
-                                        let iovs_arr =
-                                            iovs_arr.access().map_err(mem_error_to_wasi)?;
-                                        for iovs in iovs_arr.iter() {
-                                            let buf = WasmPtr::<u8, M>::new(iovs.buf)
-                                                .slice(&memory, iovs.buf_len)
-                                                .map_err(mem_error_to_wasi)?
-                                                .access()
-                                                .map_err(mem_error_to_wasi)?;
-                                            let local_written =
-                                                match handle.write(buf.as_ref()).await {
-                                                    Ok(s) => s,
-                                                    Err(_) if written > 0 => break,
-                                                    Err(err) => return Err(map_io_err(err)),
-                                                };
-                                            written += local_written;
-                                            if local_written != buf.len() {
-                                                break;
+                                        // The handle is given up before the write is
+                                        // retried so that others can use it in the meantime
+                                        let res = match handle.write(buf.as_ref()).await {
+                                            Err(err)
+                                                if BlockingIoRetry::should_retry(
+                                                    nonblocking,
+                                                    &err,
+                                                ) =>
+                                            {
+                                                if written > 0 {
+                                                    break;
+                                                }
+                                                return Err(Errno::Again);
                                             }
+                                            res => res,
+                                        };
+                                        let local_written = match res {
+                                            Ok(s) => s,
+                                            Err(_) if written > 0 => break,
+                                            Err(err) => return Err(map_io_err(err)),
+                                        };
+                                        written += local_written;
+                                        transferred.store(written, Ordering::Relaxed);
+                                        if is_append {
+                                            publish_end(offset + written as u64);
+                                        }
+                                        if local_written != buf.len() {
+                                            break;
                                         }
-                                    }
-                                    FdWriteSource::Buffer(data) => {
-                                        handle.write_all(data).await?;
-                                        written += data.len();
                                     }
                                 }
-
-                                if is_stdio {
-                                    handle.flush().await.map_err(map_io_err)?;
+                                FdWriteSource::Buffer(data) => {
+                                    handle.write_all(data).await?;
+                                    written += data.len();
                                 }
-                                Ok(written)
-                            },
-                        );
-                        let written = wasi_try_ok_ok!(res?.map_err(|err| match err {
-                            Errno::Timedout => Errno::Again,
-                            a => a,
+                            }
+
+                            if is_stdio {
+                                handle.flush().await.map_err(map_io_err)?;
+                            }
+
+                            if is_append {
+                                publish_end(offset + written as u64);
+                            }
+                            Ok(written)
+                        };
+                        // The buffers that are written on behalf of the runtime
+                        // (for instance when replaying a journal) are not
+                        // interrupted
+                        let res = match &data {
+                            FdWriteSource::Iovs { .. } => {
+                                __asyncify_interruptible(env, timeout, work)
+                            }
+                            FdWriteSource::Buffer(_) => __asyncify_light(env, timeout, work),
+                        };
+                        let written = wasi_try_ok_ok!(res?.or_else(|err| match err {
+                            Errno::Intr if transferred.load(Ordering::Relaxed) > 0 => {
+                                Ok(transferred.load(Ordering::Relaxed))
+                            }
+                            Errno::Timedout => Err(Errno::Again),
+                            a => Err(a),
                         }));
 
                         (written, true, true)
@@ -232,11 +300,7 @@ pub(crate) fn fd_write_internal<M: MemorySize>(
                     drop(guard);
 
                     let nonblocking = fd_flags.contains(Fdflags::NONBLOCK);
-                    let timeout = socket
-                        .opt_time(TimeType::WriteTimeout)
-                        .ok()
-                        .flatten()
-                        .unwrap_or(Duration::from_secs(30));
+                    let timeout = socket.opt_time(TimeType::WriteTimeout).ok().flatten();
 
                     let tasks = env.tasks().clone();
 
@@ -254,14 +318,14 @@ pub(crate) fn fd_write_internal<M: MemorySize>(
                                         .map_err(mem_error_to_wasi)?
                                         .access()
                                         .map_err(mem_error_to_wasi)?;
-                                    let local_sent = socket
-                                        .send(
-                                            tasks.deref(),
-                                            buf.as_ref(),
-                                            Some(timeout),
-                                            nonblocking,
-                                        )
-                                        .await?;
+                                    let local_sent = match socket
+                                        .send(tasks.deref(), buf.as_ref(), timeout, nonblocking)
+                                        .await
+                                    {
+                                        Ok(s) => s,
+                                        Err(_) if sent > 0 => break,
+                                        Err(err) => return Err(err),
+                                    };
                                     sent += local_sent;
                                     if local_sent != buf.len() {
                                         break;
@@ -270,13 +334,17 @@ pub(crate) fn fd_write_internal<M: MemorySize>(
                             }
                             FdWriteSource::Buffer(data) => {
                                 sent += socket
-                                    .send(tasks.deref(), data.as_ref(), Some(timeout), nonblocking)
+                                    .send(tasks.deref(), data.as_ref(), timeout, nonblocking)
                                     .await?;
                             }
                         }
                         Ok(sent)
                     });
-                    let written = wasi_try_ok_ok!(res?);
+                    let written = match res? {
+                        Ok(written) => written,
+                        Err(Errno::Pipe) => return Ok(Err(WasiEnv::broken_pipe(ctx)?)),
+                        Err(err) => return Ok(Err(err)),
+                    };
                     (written, false, false)
                 }
                 Kind::PipeRx { .. } => {
@@ -303,6 +371,9 @@ pub(crate) fn fd_write_internal<M: MemorySize>(
                                 let write_result = std::io::Write::write(tx, buf.as_ref());
                                 let local_written = match write_result {
                                     Ok(w) => w,
+                                    // Report the bytes that made it before the failure,
+                                    // the error will surface on the next write
+                                    Err(_) if written > 0 => break,
                                     Err(e) if e.kind() == std::io::ErrorKind::BrokenPipe => {
                                         // Need to do this to avoid double borrow on ctx with iovs_arr
                                         raise_sigpipe = true;
@@ -320,18 +391,14 @@ pub(crate) fn fd_write_internal<M: MemorySize>(
                             drop(iovs_arr);
 
                             if raise_sigpipe {
-                                env.process.signal_process(Signal::Sigpipe);
-                                wasi_try_ok_ok!(WasiEnv::process_signals_and_exit(ctx)?);
-                                return Ok(Err(Errno::Pipe));
+                                return Ok(Err(WasiEnv::broken_pipe(ctx)?));
                             }
                         }
                         FdWriteSource::Buffer(data) => {
                             match std::io::Write::write_all(tx, data) {
                                 Ok(()) => (),
                                 Err(e) if e.kind() == std::io::ErrorKind::BrokenPipe => {
-                                    env.process.signal_process(Signal::Sigpipe);
-                                    wasi_try_ok_ok!(WasiEnv::process_signals_and_exit(ctx)?);
-                                    return Ok(Err(Errno::Pipe));
+                                    return Ok(Err(WasiEnv::broken_pipe(ctx)?));
                                 }
                                 Err(e) => return Ok(Err(map_io_err(e))),
                             };
@@ -341,7 +408,7 @@ pub(crate) fn fd_write_internal<M: MemorySize>(
 
                     (written, false, true)
                 }
-                Kind::DuplexPipe { pipe } => {
+                Kind::DuplexPipe { pipe, .. } => {
                     let mut written = 0usize;
 
                     match &data {
@@ -362,6 +429,7 @@ pub(crate) fn fd_write_internal<M: MemorySize>(
                                 let write_result = std::io::Write::write(pipe, buf.as_ref());
                                 let local_written = match write_result {
                                     Ok(w) => w,
+                                    Err(_) if written > 0 => break,
                                     Err(e) if e.kind() == std::io::ErrorKind::BrokenPipe => {
                                         // Need to do this to avoid double borrow on ctx with iovs_arr
                                         raise_sigpipe = true;
@@ -379,18 +447,14 @@ pub(crate) fn fd_write_internal<M: MemorySize>(
                             drop(iovs_arr);
 
                             if raise_sigpipe {
-                                env.process.signal_process(Signal::Sigpipe);
-                                wasi_try_ok_ok!(WasiEnv::process_signals_and_exit(ctx)?);
-                                return Ok(Err(Errno::Pipe));
+                                return Ok(Err(WasiEnv::broken_pipe(ctx)?));
                             }
                         }
                         FdWriteSource::Buffer(data) => {
                             match std::io::Write::write_all(pipe, data) {
                                 Ok(()) => (),
                                 Err(e) if e.kind() == std::io::ErrorKind::BrokenPipe => {
-                                    env.process.signal_process(Signal::Sigpipe);
-                                    wasi_try_ok_ok!(WasiEnv::process_signals_and_exit(ctx)?);
-                                    return Ok(Err(Errno::Pipe));
+                                    return Ok(Err(WasiEnv::broken_pipe(ctx)?));
                                 }
                                 Err(e) => return Ok(Err(map_io_err(e))),
                             };
@@ -404,6 +468,9 @@ pub(crate) fn fd_write_internal<M: MemorySize>(
                     // TODO: verify
                     return Ok(Err(Errno::Isdir));
                 }
+                Kind::SignalNotifications { .. } => {
+                    return Ok(Err(Errno::Inval));
+                }
                 Kind::EventNotifications { inner } => {
                     let mut written = 0usize;
 
@@ -477,9 +544,12 @@ pub(crate) fn fd_write_internal<M: MemorySize>(
                                         .map_err(mem_error_to_wasi)
                                 );
                                 let buf = wasi_try_ok_ok!(buf.access().map_err(mem_error_to_wasi));
-                                let local_written = wasi_try_ok_ok!(
-                                    std::io::Write::write(buffer, buf.as_ref()).map_err(map_io_err)
-                                );
+                                let local_written =
+                                    match std::io::Write::write(buffer, buf.as_ref()) {
+                                        Ok(w) => w,
+                                        Err(_) if written > 0 => break,
+                                        Err(e) => return Ok(Err(map_io_err(e))),
+                                    };
                                 written += local_written;
                                 if local_written != buf.len() {
                                     break;
@@ -517,7 +587,9 @@ pub(crate) fn fd_write_internal<M: MemorySize>(
 
         // reborrow and update the size
         if !is_stdio {
-            let curr_offset = if is_file && should_update_cursor {
+            // Appends already moved the cursor to the end of the written data
+            // while holding the handle lock
+            let curr_offset = if is_file && should_update_cursor && !is_append {
                 let bytes_written = bytes_written as u64;
                 fd_entry
                     .inner
@@ -546,6 +618,13 @@ pub(crate) fn fd_write_internal<M: MemorySize>(
                     // and get the max value between it and the current size.
                     stat.st_size = stat.st_size.max(offset + bytes_written as u64);
                 }
+                drop(stat);
+
+                if bytes_written > 0
+                    && let Kind::File { path, .. } = fd_entry.inode.read().deref()
+                {
+                    state.fs.watches.notify(path, Fswatchmask::MODIFY);
+                }
             } else {
                 // Cast is valid because we don't support 128 bit systems...
                 fd_entry.inode.stat.write().unwrap().st_size += bytes_written as u64;
@@ -26,7 +26,7 @@ pub fn path_create_directory<M: MemorySize>(
     path: WasmPtr<u8, M>,
     path_len: M::Offset,
 ) -> Result<Errno, WasiError> {
-    WasiEnv::do_pending_operations(&mut ctx)?;
+    wasi_pending_operations_or_sleep!(&mut ctx);
 
     let env = ctx.data();
     let (memory, state, inodes) = unsafe { env.get_memory_and_wasi_state_and_inodes(&ctx, 0) };
@@ -102,7 +102,7 @@ pub(crate) fn path_create_directory_internal(
 
             let kind = Kind::Dir {
                 parent: parent_inode.downgrade(),
-                path: new_dir_path,
+                path: new_dir_path.clone(),
                 entries: Default::default(),
             };
             let new_inode = state
@@ -117,6 +117,8 @@ pub(crate) fn path_create_directory_internal(
                 };
 
                 entries.insert(dir_name, new_inode.clone());
+                state.fs.invalidate_path_cache();
+                state.fs.watches.notify(&new_dir_path, Fswatchmask::CREATE);
             }
         }
         Kind::Root { .. } => {
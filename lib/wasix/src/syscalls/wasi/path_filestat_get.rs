@@ -80,6 +80,9 @@ pub(crate) fn path_filestat_get_internal(
     )?;
 
     let st_ino = file_inode.ino().as_u64();
+    // The link count is only tracked on the inode, the backing file system
+    // doesn't know about the hard links that were made
+    let st_nlink = file_inode.stat.read().unwrap().st_nlink;
     let mut stat = if file_inode.is_preopened {
         *file_inode.stat.read().unwrap().deref()
     } else {
@@ -87,6 +90,7 @@ pub(crate) fn path_filestat_get_internal(
         state.fs.get_stat_for_kind(guard.deref())?
     };
     stat.st_ino = st_ino;
+    stat.st_nlink = st_nlink;
     Ok(stat)
 }
 
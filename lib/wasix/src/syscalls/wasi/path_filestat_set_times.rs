@@ -29,7 +29,7 @@ pub fn path_filestat_set_times<M: MemorySize>(
     st_mtim: Timestamp,
     fst_flags: Fstflags,
 ) -> Result<Errno, WasiError> {
-    WasiEnv::do_pending_operations(&mut ctx)?;
+    wasi_pending_operations_or_sleep!(&mut ctx);
 
     let env = ctx.data();
     let (memory, mut state, inodes) = unsafe { env.get_memory_and_wasi_state_and_inodes(&ctx, 0) };
@@ -94,14 +94,16 @@ pub(crate) fn path_filestat_set_times_internal(
         return Err(Errno::Inval);
     }
 
-    let file_inode =
-        state
-            .fs
-            .get_inode_at_path(inodes, fd, path, flags & __WASI_LOOKUP_SYMLINK_FOLLOW != 0)?;
-    let stat = {
-        let guard = file_inode.read();
-        state.fs.get_stat_for_kind(guard.deref())?
-    };
+    let follow_symlinks = flags & __WASI_LOOKUP_SYMLINK_FOLLOW != 0;
+    let mut file_inode = state
+        .fs
+        .get_inode_at_path(inodes, fd, path, follow_symlinks)?;
+    if follow_symlinks {
+        file_inode = state.fs.resolve_last_symlink(inodes, file_inode)?;
+    }
+
+    let mut atime = None;
+    let mut mtime = None;
 
     if fst_flags.contains(Fstflags::SET_ATIM) || fst_flags.contains(Fstflags::SET_ATIM_NOW) {
         let time_to_set = if fst_flags.contains(Fstflags::SET_ATIM) {
@@ -109,7 +111,8 @@ pub(crate) fn path_filestat_set_times_internal(
         } else {
             get_current_time_in_nanos()?
         };
-        fd_inode.stat.write().unwrap().st_atim = time_to_set;
+        file_inode.stat.write().unwrap().st_atim = time_to_set;
+        atime = Some(time_to_set);
     }
     if fst_flags.contains(Fstflags::SET_MTIM) || fst_flags.contains(Fstflags::SET_MTIM_NOW) {
         let time_to_set = if fst_flags.contains(Fstflags::SET_MTIM) {
@@ -117,7 +120,34 @@ pub(crate) fn path_filestat_set_times_internal(
         } else {
             get_current_time_in_nanos()?
         };
-        fd_inode.stat.write().unwrap().st_mtim = time_to_set;
+        file_inode.stat.write().unwrap().st_mtim = time_to_set;
+        mtime = Some(time_to_set);
+    }
+
+    let guard = file_inode.read();
+    match guard.deref() {
+        Kind::File {
+            handle: Some(handle),
+            ..
+        } => {
+            let mut handle = handle.write().unwrap();
+            handle
+                .set_times(atime, mtime)
+                .map_err(fs_error_into_wasi_err)?;
+        }
+        Kind::File {
+            handle: None, path, ..
+        }
+        | Kind::Dir { path, .. } => {
+            // Like POSIX, the times of a file can be changed without write
+            // access to it as the sandbox owns every file it can see
+            state
+                .fs
+                .root_fs
+                .set_times(path, atime, mtime)
+                .map_err(fs_error_into_wasi_err)?;
+        }
+        _ => {}
     }
 
     Ok(())
@@ -29,7 +29,7 @@ pub fn path_link<M: MemorySize>(
     new_path: WasmPtr<u8, M>,
     new_path_len: M::Offset,
 ) -> Result<Errno, WasiError> {
-    WasiEnv::do_pending_operations(&mut ctx)?;
+    wasi_pending_operations_or_sleep!(&mut ctx);
 
     if old_flags & __WASI_LOOKUP_SYMLINK_FOLLOW != 0 {
         Span::current().record("follow_symlinks", true);
@@ -110,11 +110,14 @@ pub(crate) fn path_link_internal(
     {
         let mut guard = target_parent_inode.write();
         match guard.deref_mut() {
-            Kind::Dir { entries, .. } => {
+            Kind::Dir { entries, path, .. } => {
                 if entries.contains_key(&new_entry_name) {
                     return Err(Errno::Exist);
                 }
+                let new_path = path.join(&new_entry_name);
                 entries.insert(new_entry_name, source_inode.clone());
+                state.fs.invalidate_path_cache();
+                state.fs.watches.notify(new_path, Fswatchmask::CREATE);
             }
             Kind::Root { .. } => return Err(Errno::Inval),
             Kind::File { .. }
@@ -125,6 +128,7 @@ pub(crate) fn path_link_internal(
             | Kind::PipeRx { .. }
             | Kind::DuplexPipe { .. }
             | Kind::EventNotifications { .. }
+            | Kind::SignalNotifications { .. }
             | Kind::Epoll { .. } => return Err(Errno::Notdir),
         }
     }
@@ -15,9 +15,11 @@ use crate::syscalls::*;
 /// - `Oflags o_flags`
 ///     How the file will be opened
 /// - `Rights fs_rights_base`
-///     The rights of the created file descriptor
+///     The rights of the created file descriptor, limited to the inheriting
+///     rights of `dirfd` (`Errno::Notcapable` if `FD_WRITE` is not among them)
 /// - `Rights fs_rightsinheriting`
-///     The rights of file descriptors derived from the created file descriptor
+///     The rights of file descriptors derived from the created file descriptor,
+///     also limited to the inheriting rights of `dirfd`
 /// - `Fdflags fs_flags`
 ///     The flags of the file descriptor
 /// Output:
@@ -38,7 +40,7 @@ pub fn path_open<M: MemorySize>(
     fs_flags: Fdflags,
     fd: WasmPtr<WasiFd, M>,
 ) -> Result<Errno, WasiError> {
-    WasiEnv::do_pending_operations(&mut ctx)?;
+    wasi_pending_operations_or_sleep!(&mut ctx);
 
     if dirflags & __WASI_LOOKUP_SYMLINK_FOLLOW != 0 {
         Span::current().record("follow_symlinks", true);
@@ -11,7 +11,7 @@ pub fn path_remove_directory<M: MemorySize>(
     path: WasmPtr<u8, M>,
     path_len: M::Offset,
 ) -> Result<Errno, WasiError> {
-    WasiEnv::do_pending_operations(&mut ctx)?;
+    wasi_pending_operations_or_sleep!(&mut ctx);
 
     // TODO check if fd is a dir, ensure it's within sandbox, etc.
     let env = ctx.data();
@@ -57,6 +57,7 @@ pub(crate) fn path_remove_directory_internal(
     match guard.deref_mut() {
         Kind::Dir {
             entries: parent_entries,
+            path: parent_path,
             ..
         } => {
             let Some(child_inode) = parent_entries.get(&dir_name) else {
@@ -86,6 +87,11 @@ pub(crate) fn path_remove_directory_internal(
             parent_entries.remove(&dir_name).expect(
                 "Entry should exist since we checked before and have an exclusive write lock",
             );
+            state.fs.invalidate_path_cache();
+            state
+                .fs
+                .watches
+                .notify(parent_path.join(&dir_name), Fswatchmask::DELETE);
 
             Ok(())
         }
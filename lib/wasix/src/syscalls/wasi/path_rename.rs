@@ -30,7 +30,7 @@ pub fn path_rename<M: MemorySize>(
     new_path: WasmPtr<u8, M>,
     new_path_len: M::Offset,
 ) -> Result<Errno, WasiError> {
-    WasiEnv::do_pending_operations(&mut ctx)?;
+    wasi_pending_operations_or_sleep!(&mut ctx);
 
     let env = ctx.data();
     let (memory, mut state, inodes) = unsafe { env.get_memory_and_wasi_state_and_inodes(&ctx, 0) };
@@ -108,6 +108,7 @@ pub fn path_rename_internal(
             | Kind::PipeRx { .. }
             | Kind::DuplexPipe { .. }
             | Kind::EventNotifications { .. }
+            | Kind::SignalNotifications { .. }
             | Kind::Epoll { .. } => return Ok(Errno::Inval),
             Kind::Symlink { .. } | Kind::File { .. } | Kind::Buffer { .. } => {
                 debug!("fatal internal logic error: parent of inode is not a directory");
@@ -131,6 +132,7 @@ pub fn path_rename_internal(
             | Kind::PipeRx { .. }
             | Kind::DuplexPipe { .. }
             | Kind::EventNotifications { .. }
+            | Kind::SignalNotifications { .. }
             | Kind::Epoll { .. } => return Ok(Errno::Inval),
             Kind::Symlink { .. } | Kind::File { .. } | Kind::Buffer { .. } => {
                 debug!("fatal internal logic error: parent of inode is not a directory");
@@ -143,7 +145,10 @@ pub fn path_rename_internal(
         let mut guard = source_parent_inode.write();
         match guard.deref_mut() {
             Kind::Dir { entries, .. } => {
-                wasi_try_ok!(entries.remove(&source_entry_name).ok_or(Errno::Noent))
+                let source_entry =
+                    wasi_try_ok!(entries.remove(&source_entry_name).ok_or(Errno::Noent));
+                state.fs.invalidate_path_cache();
+                source_entry
             }
             Kind::Root { .. } => return Ok(Errno::Notcapable),
             Kind::Socket { .. }
@@ -151,6 +156,7 @@ pub fn path_rename_internal(
             | Kind::PipeTx { .. }
             | Kind::DuplexPipe { .. }
             | Kind::EventNotifications { .. }
+            | Kind::SignalNotifications { .. }
             | Kind::Epoll { .. } => {
                 return Ok(Errno::Inval);
             }
@@ -181,6 +187,7 @@ pub fn path_rename_internal(
                     let mut guard = source_parent_inode.write();
                     if let Kind::Dir { entries, .. } = guard.deref_mut() {
                         entries.insert(source_entry_name, source_entry);
+                        state.fs.invalidate_path_cache();
                         return Ok(e);
                     }
                 } else {
@@ -233,6 +240,7 @@ pub fn path_rename_internal(
                         let mut guard = source_parent_inode.write();
                         if let Kind::Dir { entries, .. } = guard.deref_mut() {
                             entries.insert(source_entry_name, source_entry.clone());
+                            state.fs.invalidate_path_cache();
                             return Ok(e);
                         }
                     }
@@ -260,7 +268,8 @@ pub fn path_rename_internal(
             | Kind::PipeRx { .. }
             | Kind::DuplexPipe { .. }
             | Kind::Epoll { .. }
-            | Kind::EventNotifications { .. } => {}
+            | Kind::EventNotifications { .. }
+            | Kind::SignalNotifications { .. } => {}
             Kind::Root { .. } => unreachable!("The root can not be moved"),
         }
     }
@@ -271,6 +280,7 @@ pub fn path_rename_internal(
         let mut guard = target_parent_inode.write();
         if let Kind::Dir { entries, .. } = guard.deref_mut() {
             let result = entries.insert(target_entry_name.clone(), source_entry);
+            state.fs.invalidate_path_cache();
             assert!(
                 result.is_none(),
                 "fatal error: race condition on filesystem detected or internal logic error"
@@ -292,6 +302,16 @@ pub fn path_rename_internal(
         .fs
         .unregister_ephemeral_symlink(host_adjusted_target_path.as_path());
 
+    // Watches see a rename as the source going away and the target appearing
+    state
+        .fs
+        .watches
+        .notify(&host_adjusted_source_path, Fswatchmask::DELETE);
+    state
+        .fs
+        .watches
+        .notify(&host_adjusted_target_path, Fswatchmask::CREATE);
+
     Ok(Errno::Success)
 }
 
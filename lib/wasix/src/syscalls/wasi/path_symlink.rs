@@ -25,7 +25,7 @@ pub fn path_symlink<M: MemorySize>(
     new_path: WasmPtr<u8, M>,
     new_path_len: M::Offset,
 ) -> Result<Errno, WasiError> {
-    WasiEnv::do_pending_operations(&mut ctx)?;
+    wasi_pending_operations_or_sleep!(&mut ctx);
 
     let env = ctx.data();
     let (memory, mut state, inodes) = unsafe { env.get_memory_and_wasi_state_and_inodes(&ctx, 0) };
@@ -114,6 +114,7 @@ pub fn path_symlink_internal(
             | Kind::PipeTx { .. }
             | Kind::DuplexPipe { .. }
             | Kind::EventNotifications { .. }
+            | Kind::SignalNotifications { .. }
             | Kind::Epoll { .. } => return Err(Errno::Inval),
             Kind::File { .. } | Kind::Symlink { .. } | Kind::Buffer { .. } => {
                 unreachable!("get_parent_inode_at_path returned something other than a Dir or Root")
@@ -149,8 +150,10 @@ pub fn path_symlink_internal(
         let mut guard = target_parent_inode.write();
         if let Kind::Dir { entries, .. } = guard.deref_mut() {
             entries.insert(entry_name, new_inode);
+            state.fs.invalidate_path_cache();
         }
     }
+    state.fs.watches.notify(&symlink_path, Fswatchmask::CREATE);
 
     // Keep transient map in sync with the backing outcome.
     if needs_ephemeral_fallback {
@@ -17,7 +17,7 @@ pub fn path_unlink_file<M: MemorySize>(
     path: WasmPtr<u8, M>,
     path_len: M::Offset,
 ) -> Result<Errno, WasiError> {
-    WasiEnv::do_pending_operations(&mut ctx)?;
+    wasi_pending_operations_or_sleep!(&mut ctx);
 
     let env = ctx.data();
     let (memory, mut state, inodes) = unsafe { env.get_memory_and_wasi_state_and_inodes(&ctx, 0) };
@@ -78,6 +78,7 @@ pub(crate) fn path_unlink_file_internal(
         match guard.deref_mut() {
             Kind::Dir { entries, .. } => {
                 let removed_inode = wasi_try_ok!(entries.remove(&childs_name).ok_or(Errno::Inval));
+                state.fs.invalidate_path_cache();
                 // TODO: make this a debug assert in the future
                 assert!(inode.ino() == removed_inode.ino());
                 debug_assert!(inode.stat.read().unwrap().st_nlink > 0);
@@ -131,6 +132,10 @@ pub(crate) fn path_unlink_file_internal(
             }
         }
     }
+    state
+        .fs
+        .watches
+        .notify(&host_adjusted_path, Fswatchmask::DELETE);
 
     Ok(Errno::Success)
 }
@@ -1,3 +1,4 @@
+use futures::{StreamExt, stream::FuturesUnordered};
 use serde::{Deserialize, Serialize};
 use wasmer_wasix_types::wasi::{Subclockflags, SubscriptionClock, Userdata};
 
@@ -42,6 +43,16 @@ impl EventResult {
     }
 }
 
+/// How a poll that waited for its events ended
+#[derive(Serialize, Deserialize)]
+enum PollOutcome {
+    /// The events that were triggered, or why the poll failed
+    Finished(Result<Vec<EventResult>, Errno>),
+    /// A signal interrupted the poll after it waited for this many
+    /// nanoseconds
+    Interrupted { waited: u64 },
+}
+
 /// ### `poll_oneoff()`
 /// Concurrently poll for a set of events
 ///
@@ -64,7 +75,7 @@ pub fn poll_oneoff<M: MemorySize + 'static>(
     nsubscriptions: M::Offset,
     nevents: WasmPtr<M::Offset, M>,
 ) -> Result<Errno, WasiError> {
-    WasiEnv::do_pending_operations(&mut ctx)?;
+    wasi_pending_operations_or_sleep!(&mut ctx);
 
     // An empty subscription list would otherwise block forever in the poll loop.
     if nsubscriptions == M::ZERO {
@@ -112,21 +123,24 @@ pub fn poll_oneoff<M: MemorySize + 'static>(
     poll_oneoff_internal::<M, _>(ctx, subscriptions, process_events)
 }
 
+/// Waits for the first of the file descriptors to be ready.
+///
+/// The joins live in a [`FuturesUnordered`] so that a wakeup only polls the
+/// joins that were woken up, instead of every one of them, which matters
+/// for guests that poll hundreds of file descriptors at once.
 struct PollBatch {
     pid: WasiProcessId,
     tid: WasiThreadId,
-    evts: Vec<Event>,
-    joins: Vec<InodeValFilePollGuardJoin>,
+    joins: FuturesUnordered<PollBatchJoin>,
 }
 impl PollBatch {
     fn new(pid: WasiProcessId, tid: WasiThreadId, fds: Vec<InodeValFilePollGuard>) -> Self {
         Self {
             pid,
             tid,
-            evts: Vec::new(),
             joins: fds
                 .into_iter()
-                .map(InodeValFilePollGuardJoin::new)
+                .map(|fd| PollBatchJoin(InodeValFilePollGuardJoin::new(fd)))
                 .collect(),
         }
     }
@@ -134,30 +148,20 @@ impl PollBatch {
 impl Future for PollBatch {
     type Output = Result<Vec<EventResult>, Errno>;
     fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-        let pid = self.pid;
-        let tid = self.tid;
-        let mut done = false;
-
+        // Collect the events of every join that is ready, a join that
+        // isn't is left alone until it wakes up again
         let mut evts = Vec::new();
-        for mut join in self.joins.iter_mut() {
-            let fd = join.fd();
-            let peb = join.peb();
-            let mut guard = Pin::new(join);
-            match guard.poll(cx) {
-                Poll::Pending => {}
-                Poll::Ready(e) => {
-                    for (evt, readiness) in e {
-                        tracing::trace!(
-                            fd,
-                            readiness = ?readiness,
-                            userdata = evt.userdata,
-                            ty = evt.type_ as u8,
-                            peb,
-                            "triggered"
-                        );
-                        evts.push(evt);
-                    }
-                }
+        while let Poll::Ready(Some((fd, peb, e))) = self.joins.poll_next_unpin(cx) {
+            for (evt, readiness) in e {
+                tracing::trace!(
+                    fd,
+                    readiness = ?readiness,
+                    userdata = evt.userdata,
+                    ty = evt.type_ as u8,
+                    peb,
+                    "triggered"
+                );
+                evts.push(evt);
             }
         }
 
@@ -169,6 +173,22 @@ impl Future for PollBatch {
     }
 }
 
+/// A join of a [`PollBatch`], which also tells which file descriptor it
+/// was for once it is ready
+struct PollBatchJoin(InodeValFilePollGuardJoin);
+impl Future for PollBatchJoin {
+    type Output = (
+        WasiFd,
+        PollEventSet,
+        <InodeValFilePollGuardJoin as Future>::Output,
+    );
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let fd = self.0.fd();
+        let peb = self.0.peb();
+        Pin::new(&mut self.0).poll(cx).map(|e| (fd, peb, e))
+    }
+}
+
 pub(crate) fn poll_fd_guard(
     state: &Arc<WasiState>,
     peb: PollEventSet,
@@ -233,255 +253,308 @@ pub(crate) fn poll_oneoff_internal<'a, M: MemorySize, After>(
 where
     After: FnOnce(&FunctionEnvMut<'a, WasiEnv>, Vec<Event>) -> Errno,
 {
-    wasi_try_ok!(WasiEnv::process_signals_and_exit(&mut ctx)?);
+    // Signals that restart the poll go around again rather than recursing
+    loop {
+        wasi_try_ok!(WasiEnv::process_signals_and_exit(&mut ctx)?);
 
-    let pid = ctx.data().pid();
-    let tid = ctx.data().tid();
-    let subs_len = subs.len();
+        let pid = ctx.data().pid();
+        let tid = ctx.data().tid();
+        let subs_len = subs.len();
 
-    // Determine if we are in silent polling mode
-    let mut env = ctx.data();
-    let state = ctx.data().state.deref();
-    let memory = unsafe { env.memory_view(&ctx) };
-
-    // These are used when we capture what clocks (timeouts) are being
-    // subscribed too
-    let clock_cnt = subs
-        .iter()
-        .filter(|a| a.2.type_ == Eventtype::Clock)
-        .count();
-    let mut clock_subs: Vec<(SubscriptionClock, u64)> = Vec::with_capacity(subs.len());
-    let mut time_to_sleep = Duration::MAX;
-
-    // First we extract all the subscriptions into an array so that they
-    // can be processed
-    let mut env = ctx.data();
-    let state = ctx.data().state.deref();
-    let mut memory = unsafe { env.memory_view(&ctx) };
-    for (fd, peb, s) in subs.iter_mut() {
-        let fd = match s.type_ {
-            Eventtype::FdRead => {
-                let file_descriptor = unsafe { s.data.fd_readwrite.file_descriptor };
-                *fd = Some(file_descriptor);
-                *peb |= (PollEvent::PollIn as PollEventSet);
-                file_descriptor
-            }
-            Eventtype::FdWrite => {
-                let file_descriptor = unsafe { s.data.fd_readwrite.file_descriptor };
-                *fd = Some(file_descriptor);
-                *peb |= (PollEvent::PollOut as PollEventSet);
-                file_descriptor
-            }
-            Eventtype::Clock => {
-                let clock_info = unsafe { s.data.clock };
-                if clock_info.clock_id == Clockid::Realtime
-                    || clock_info.clock_id == Clockid::Monotonic
-                {
-                    // Ignore duplicates
-                    if clock_subs
-                        .iter()
-                        .any(|c| c.0.clock_id == clock_info.clock_id && c.1 == s.userdata)
+        // Determine if we are in silent polling mode
+        let mut env = ctx.data();
+        let state = ctx.data().state.deref();
+        let memory = unsafe { env.memory_view(&ctx) };
+
+        // These are used when we capture what clocks (timeouts) are being
+        // subscribed too
+        let clock_cnt = subs
+            .iter()
+            .filter(|a| a.2.type_ == Eventtype::Clock)
+            .count();
+        let mut clock_subs: Vec<(SubscriptionClock, u64)> = Vec::with_capacity(subs.len());
+        let mut time_to_sleep = Duration::MAX;
+
+        // First we extract all the subscriptions into an array so that they
+        // can be processed
+        let mut env = ctx.data();
+        let state = ctx.data().state.deref();
+        let mut memory = unsafe { env.memory_view(&ctx) };
+        for (fd, peb, s) in subs.iter_mut() {
+            let fd = match s.type_ {
+                Eventtype::FdRead => {
+                    let file_descriptor = unsafe { s.data.fd_readwrite.file_descriptor };
+                    *fd = Some(file_descriptor);
+                    *peb |= (PollEvent::PollIn as PollEventSet);
+                    file_descriptor
+                }
+                Eventtype::FdWrite => {
+                    let file_descriptor = unsafe { s.data.fd_readwrite.file_descriptor };
+                    *fd = Some(file_descriptor);
+                    *peb |= (PollEvent::PollOut as PollEventSet);
+                    file_descriptor
+                }
+                Eventtype::Clock => {
+                    let clock_info = unsafe { s.data.clock };
+                    wasi_try_ok!(
+                        env.sandbox_policy()
+                            .check_clock(Snapshot0Clockid::from(clock_info.clock_id))
+                    );
+                    if clock_info.clock_id == Clockid::Realtime
+                        || clock_info.clock_id == Clockid::Monotonic
                     {
-                        continue;
-                    }
-
-                    // If the timeout duration is zero then this is an immediate check rather than
-                    // a sleep itself
-                    if clock_info.timeout == 0 {
-                        time_to_sleep = Duration::MAX;
-                    } else if clock_info.timeout == 1 {
-                        time_to_sleep = Duration::ZERO;
-                        clock_subs.push((clock_info, s.userdata));
-                    } else {
-                        // if the timeout is specified as an absolute time in the future,
-                        // we should calculate the duration we need to sleep
-                        time_to_sleep = if clock_info
-                            .flags
-                            .contains(Subclockflags::SUBSCRIPTION_CLOCK_ABSTIME)
+                        // Ignore duplicates
+                        if clock_subs
+                            .iter()
+                            .any(|c| c.0.clock_id == clock_info.clock_id && c.1 == s.userdata)
                         {
-                            let now = wasi_try_ok!(platform_clock_time_get(
-                                Snapshot0Clockid::Monotonic,
-                                1
-                            )) as u64;
-
-                            if clock_info.timeout <= now {
-                                Duration::ZERO
-                            } else {
-                                Duration::from_nanos(clock_info.timeout) - Duration::from_nanos(now)
-                            }
+                            continue;
+                        }
+
+                        // If the timeout duration is zero then this is an immediate check rather than
+                        // a sleep itself. When there are multiple clocks the earliest one wins.
+                        if clock_info.timeout == 0 {
+                            continue;
+                        } else if clock_info.timeout == 1 {
+                            time_to_sleep = Duration::ZERO;
+                            clock_subs.push((clock_info, s.userdata));
                         } else {
-                            // if the timeout is not absolute, just use it as duration
-                            Duration::from_nanos(clock_info.timeout)
-                        };
+                            // if the timeout is specified as an absolute time in the future,
+                            // we should calculate the duration we need to sleep
+                            let clock_sleep = if clock_info
+                                .flags
+                                .contains(Subclockflags::SUBSCRIPTION_CLOCK_ABSTIME)
+                            {
+                                let now = wasi_try_ok!(platform_clock_time_get(
+                                    Snapshot0Clockid::Monotonic,
+                                    1
+                                )) as u64;
+
+                                if clock_info.timeout <= now {
+                                    Duration::ZERO
+                                } else {
+                                    Duration::from_nanos(clock_info.timeout)
+                                        - Duration::from_nanos(now)
+                                }
+                            } else {
+                                // if the timeout is not absolute, just use it as duration
+                                Duration::from_nanos(clock_info.timeout)
+                            };
+                            time_to_sleep = time_to_sleep.min(clock_sleep);
 
-                        clock_subs.push((clock_info, s.userdata));
+                            clock_subs.push((clock_info, s.userdata));
+                        }
+                        continue;
+                    } else {
+                        error!("polling not implemented for these clocks yet");
+                        return Ok(Errno::Inval);
                     }
+                }
+                _ => {
                     continue;
+                }
+            };
+        }
+
+        let mut events_seen: u32 = 0;
+
+        // Kept to carry on polling after the handler of a signal ran
+        let restart_subs = subs.clone();
+
+        let batch = {
+            // Build the batch of things we are going to poll
+            let state = ctx.data().state.clone();
+            let tasks = ctx.data().tasks().clone();
+            let mut guards = {
+                // We start by building a list of files we are going to poll
+                // and open a read lock on them all
+                let mut fd_guards = Vec::with_capacity(subs.len());
+
+                #[allow(clippy::significant_drop_in_scrutinee)]
+                for (fd, peb, s) in subs {
+                    if let Some(fd) = fd {
+                        let wasi_file_ref = wasi_try_ok!(poll_fd_guard(&state, peb, fd, s));
+                        fd_guards.push(wasi_file_ref);
+                    }
+                }
+
+                if fd_guards.len() > 10 {
+                    let small_list: Vec<_> = fd_guards.iter().take(10).collect();
+                    tracing::Span::current().record("fd_guards", format!("{small_list:?}..."));
                 } else {
-                    error!("polling not implemented for these clocks yet");
-                    return Ok(Errno::Inval);
+                    tracing::Span::current().record("fd_guards", format!("{fd_guards:?}"));
                 }
+
+                fd_guards
+            };
+
+            // Block polling the file descriptors
+            PollBatch::new(pid, tid, guards)
+        };
+
+        // If the time is infinite then we omit the time_to_sleep parameter
+        let timeout = match time_to_sleep {
+            Duration::ZERO => {
+                Span::current().record("timeout_ns", "nonblocking");
+                Some(Duration::ZERO)
             }
-            _ => {
-                continue;
+            Duration::MAX => {
+                Span::current().record("timeout_ns", "infinite");
+                None
+            }
+            time => {
+                Span::current().record("timeout_ns", time.as_millis());
+                Some(time)
             }
         };
-    }
 
-    let mut events_seen: u32 = 0;
-
-    let batch = {
-        // Build the batch of things we are going to poll
-        let state = ctx.data().state.clone();
-        let tasks = ctx.data().tasks().clone();
-        let mut guards = {
-            // We start by building a list of files we are going to poll
-            // and open a read lock on them all
-            let mut fd_guards = Vec::with_capacity(subs.len());
-
-            #[allow(clippy::significant_drop_in_scrutinee)]
-            for (fd, peb, s) in subs {
-                if let Some(fd) = fd {
-                    let wasi_file_ref = wasi_try_ok!(poll_fd_guard(&state, peb, fd, s));
-                    fd_guards.push(wasi_file_ref);
+        // Function to process a timeout
+        let process_timeout = {
+            let clock_subs = clock_subs.clone();
+            |ctx: &FunctionEnvMut<'a, WasiEnv>| {
+                // The timeout has triggered so lets add that event
+                if clock_subs.is_empty() {
+                    tracing::warn!("triggered_timeout (without any clock subscriptions)");
                 }
+                let mut evts = Vec::new();
+                for (clock_info, userdata) in clock_subs {
+                    let evt = Event {
+                        userdata,
+                        error: Errno::Success,
+                        type_: Eventtype::Clock,
+                        u: EventUnion { clock: 0 },
+                    };
+                    Span::current().record(
+                        "seen",
+                        format!(
+                            "clock(id={},userdata={})",
+                            clock_info.clock_id as u32, evt.userdata
+                        ),
+                    );
+                    evts.push(evt);
+                }
+                evts
             }
-
-            if fd_guards.len() > 10 {
-                let small_list: Vec<_> = fd_guards.iter().take(10).collect();
-                tracing::Span::current().record("fd_guards", format!("{small_list:?}..."));
-            } else {
-                tracing::Span::current().record("fd_guards", format!("{fd_guards:?}"));
-            }
-
-            fd_guards
         };
 
-        // Block polling the file descriptors
-        PollBatch::new(pid, tid, guards)
-    };
-
-    // If the time is infinite then we omit the time_to_sleep parameter
-    let timeout = match time_to_sleep {
-        Duration::ZERO => {
-            Span::current().record("timeout_ns", "nonblocking");
-            Some(Duration::ZERO)
-        }
-        Duration::MAX => {
-            Span::current().record("timeout_ns", "infinite");
-            None
-        }
-        time => {
-            Span::current().record("timeout_ns", time.as_millis());
-            Some(time)
+        #[cfg(feature = "sys")]
+        if env.capabilities.threading.enable_blocking_sleep && subs_len == 1 {
+            // Here, `poll_oneoff` is merely in a sleeping state
+            // due to a single relative timer event. This particular scenario was
+            // added following experimental findings indicating that std::thread::sleep
+            // yields more consistent sleep durations, allowing wasmer to meet
+            // real-time demands with greater precision.
+            if let Some(timeout) = timeout {
+                std::thread::sleep(timeout);
+                process_events(&ctx, process_timeout(&ctx));
+                return Ok(Errno::Success);
+            }
         }
-    };
 
-    // Function to process a timeout
-    let process_timeout = {
-        let clock_subs = clock_subs.clone();
-        |ctx: &FunctionEnvMut<'a, WasiEnv>| {
-            // The timeout has triggered so lets add that event
-            if clock_subs.is_empty() {
-                tracing::warn!("triggered_timeout (without any clock subscriptions)");
-            }
-            let mut evts = Vec::new();
-            for (clock_info, userdata) in clock_subs {
-                let evt = Event {
-                    userdata,
-                    error: Errno::Success,
-                    type_: Eventtype::Clock,
-                    u: EventUnion { clock: 0 },
-                };
-                Span::current().record(
-                    "seen",
-                    format!(
-                        "clock(id={},userdata={})",
-                        clock_info.clock_id as u32, evt.userdata
-                    ),
-                );
-                evts.push(evt);
+        let tasks = env.tasks().clone();
+        let timeout = async move {
+            if let Some(timeout) = timeout {
+                tasks.sleep_now(timeout).await;
+            } else {
+                InfiniteSleep::default().await
             }
-            evts
-        }
-    };
+        };
 
-    #[cfg(feature = "sys")]
-    if env.capabilities.threading.enable_blocking_sleep && subs_len == 1 {
-        // Here, `poll_oneoff` is merely in a sleeping state
-        // due to a single relative timer event. This particular scenario was
-        // added following experimental findings indicating that std::thread::sleep
-        // yields more consistent sleep durations, allowing wasmer to meet
-        // real-time demands with greater precision.
-        if let Some(timeout) = timeout {
-            std::thread::sleep(timeout);
-            process_events(&ctx, process_timeout(&ctx));
-            return Ok(Errno::Success);
-        }
-    }
+        // Signals interrupt the poll so that their handlers run right away
+        let interrupted = env.wait_for_interrupt();
+        let started = Instant::now();
+
+        // Build the trigger using the timeout
+        let trigger = async move {
+            tokio::select! {
+                res = batch => PollOutcome::Finished(res),
+                _ = timeout => PollOutcome::Finished(Err(Errno::Timedout)),
+                _ = interrupted => PollOutcome::Interrupted {
+                    waited: started.elapsed().as_nanos() as u64,
+                },
+            }
+        };
 
-    let tasks = env.tasks().clone();
-    let timeout = async move {
-        if let Some(timeout) = timeout {
-            tasks.sleep_now(timeout).await;
+        // Either we are rewound and the poll already happened, or we wait
+        // for it in a deep sleep
+        let outcome = if let Some(outcome) = unsafe { handle_rewind::<M, PollOutcome>(&mut ctx) } {
+            outcome
         } else {
-            InfiniteSleep::default().await
-        }
-    };
+            match __asyncify_with_deep_sleep::<M, PollOutcome, _>(ctx, Box::pin(trigger))? {
+                AsyncifyAction::Finish(finished_ctx, outcome) => {
+                    ctx = finished_ctx;
+                    outcome
+                }
+                AsyncifyAction::Unwind => return Ok(Errno::Success),
+                AsyncifyAction::Abort(err) => return Ok(err),
+            }
+        };
+        let events = match outcome {
+            PollOutcome::Finished(events) => events,
+            PollOutcome::Interrupted { waited } => {
+                // Signals that restart syscalls let the poll carry on for the
+                // time that is left once their handlers ran
+                if !WasiEnv::handle_interrupted_wait(&mut ctx)? {
+                    return Ok(Errno::Intr);
+                }
+                subs = restart_subs
+                    .into_iter()
+                    .map(|(fd, peb, sub)| (fd, peb, shorten_clock_subscription(sub, waited)))
+                    .collect();
+                continue;
+            }
+        };
 
-    // Build the trigger using the timeout
-    let trigger = async move {
-        tokio::select! {
-            res = batch => res,
-            _ = timeout => Err(Errno::Timedout)
-        }
-    };
+        // We replace the process events callback with another callback
+        // which will interpret the error codes
+        let process_events = {
+            let clock_subs = clock_subs.clone();
+            |ctx: &FunctionEnvMut<'a, WasiEnv>, events: Result<Vec<Event>, Errno>| {
+                // Process the result
+                match events {
+                    Ok(evts) => {
+                        // If its a timeout then return an event for it
+                        if evts.len() == 1 {
+                            Span::current().record("seen", format!("{:?}", evts.first().unwrap()));
+                        } else {
+                            Span::current().record("seen", format!("trigger_cnt=({})", evts.len()));
+                        }
 
-    // We replace the process events callback with another callback
-    // which will interpret the error codes
-    let process_events = {
-        let clock_subs = clock_subs.clone();
-        |ctx: &FunctionEnvMut<'a, WasiEnv>, events: Result<Vec<Event>, Errno>| {
-            // Process the result
-            match events {
-                Ok(evts) => {
-                    // If its a timeout then return an event for it
-                    if evts.len() == 1 {
-                        Span::current().record("seen", format!("{:?}", evts.first().unwrap()));
-                    } else {
-                        Span::current().record("seen", format!("trigger_cnt=({})", evts.len()));
+                        // Process the events
+                        process_events(ctx, evts)
+                    }
+                    Err(Errno::Timedout) => process_events(ctx, process_timeout(ctx)),
+                    // If nonblocking the Errno::Again needs to be turned into an empty list
+                    Err(Errno::Again) => process_events(ctx, Default::default()),
+                    // Otherwise process the error
+                    Err(err) => {
+                        tracing::warn!("failed to poll during deep sleep - {}", err);
+                        err
                     }
-
-                    // Process the events
-                    process_events(ctx, evts)
-                }
-                Err(Errno::Timedout) => process_events(ctx, process_timeout(ctx)),
-                // If nonblocking the Errno::Again needs to be turned into an empty list
-                Err(Errno::Again) => process_events(ctx, Default::default()),
-                // Otherwise process the error
-                Err(err) => {
-                    tracing::warn!("failed to poll during deep sleep - {}", err);
-                    err
                 }
             }
-        }
-    };
+        };
 
-    // If we are rewound then its time to process them
-    if let Some(events) = unsafe { handle_rewind::<M, Result<Vec<EventResult>, Errno>>(&mut ctx) } {
         let events = events.map(|events| events.into_iter().map(EventResult::into_event).collect());
         process_events(&ctx, events);
         return Ok(Errno::Success);
     }
+}
 
-    // We use asyncify with a deep sleep to wait on new IO events
-    let res = __asyncify_with_deep_sleep::<M, Result<Vec<EventResult>, Errno>, _>(
-        ctx,
-        Box::pin(trigger),
-    )?;
-    if let AsyncifyAction::Finish(mut ctx, events) = res {
-        let events = events.map(|events| events.into_iter().map(EventResult::into_event).collect());
-        process_events(&ctx, events);
+/// Takes the time that a poll already waited off the relative timeout of a
+/// clock subscription, absolute timeouts stay where they are
+fn shorten_clock_subscription(mut sub: Subscription, waited: u64) -> Subscription {
+    if sub.type_ == Eventtype::Clock {
+        let clock = unsafe { &mut sub.data.clock };
+        if clock.timeout > 1
+            && !clock
+                .flags
+                .contains(Subclockflags::SUBSCRIPTION_CLOCK_ABSTIME)
+        {
+            // A timeout of 0 only checks the clock, so 1 is what triggers
+            // right away
+            clock.timeout = clock.timeout.saturating_sub(waited).max(1);
+        }
     }
-    Ok(Errno::Success)
+    sub
 }
@@ -13,6 +13,12 @@ pub fn proc_exit<M: MemorySize>(
     mut ctx: FunctionEnvMut<'_, WasiEnv>,
     code: ExitCode,
 ) -> Result<(), WasiError> {
+    // Returning early here is not a return from `proc_exit`, the stack
+    // unwinds for a sleep and the exit is processed once it is rewound
+    if !WasiEnv::do_pending_operations_or_unwind(&mut ctx)? {
+        return Ok(());
+    }
+
     let in_asyncify_based_vfork = ctx
         .data()
         .vfork
@@ -20,7 +26,7 @@ pub fn proc_exit<M: MemorySize>(
         .map(|v| v.asyncify.is_some())
         .unwrap_or(false);
 
-    proc_exit2::<M>(ctx, code)?;
+    proc_exit2_internal::<M>(ctx, code)?;
 
     // proc_exit2 returns in two cases:
     // 1. We are in an asyncify-based vfork, in which case on_called is set and magic will happen
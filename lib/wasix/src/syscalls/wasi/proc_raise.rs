@@ -12,7 +12,7 @@ pub fn proc_raise(mut ctx: FunctionEnvMut<'_, WasiEnv>, sig: Signal) -> Result<E
     let env = ctx.data();
     env.process.signal_process(sig);
 
-    WasiEnv::do_pending_operations(&mut ctx)?;
+    wasi_pending_operations_or_sleep!(&mut ctx);
 
     Ok(Errno::Success)
 }
@@ -37,7 +37,7 @@ pub fn proc_raise_interval(
     let repeat = matches!(repeat, Bool::True);
     env.process.signal_interval(sig, interval, repeat);
 
-    WasiEnv::do_pending_operations(&mut ctx)?;
+    wasi_pending_operations_or_sleep!(&mut ctx);
 
     Ok(Errno::Success)
 }
@@ -3,6 +3,9 @@ use crate::syscalls::*;
 
 /// ### `random_get()`
 /// Fill buffer with high-quality random data.  This function may be slow and block
+///
+/// The bytes come from the entropy source of the runtime, when it is unable
+/// to produce them `Errno::Io` is returned
 /// Inputs:
 /// - `void *buf`
 ///     A pointer to a buffer where the random bytes will be written
@@ -18,7 +21,7 @@ pub fn random_get<M: MemorySize>(
     let memory = unsafe { env.memory_view(&ctx) };
     let buf_len64: u64 = buf_len.into();
     let mut u8_buffer = vec![0; buf_len64 as usize];
-    let res = getrandom::fill(&mut u8_buffer);
+    let res = env.runtime().entropy_source().fill(&mut u8_buffer);
     match res {
         Ok(()) => {
             let buf = wasi_try_mem!(buf.slice(&memory, buf_len));
@@ -2,14 +2,18 @@ use super::*;
 use crate::syscalls::*;
 
 /// ### `chdir()`
-/// Sets the current working directory
-#[instrument(level = "trace", skip_all, fields(name = field::Empty), ret)]
+/// Sets the current working directory, relative paths are resolved against
+/// the current one and the `.` and `..` components are collapsed
+///
+/// Returns `Errno::Noent` if the directory doesn't exist and `Errno::Notdir`
+/// if the path is not a directory
+#[instrument(level = "trace", skip_all, fields(path = field::Empty), ret)]
 pub fn chdir<M: MemorySize>(
     mut ctx: FunctionEnvMut<'_, WasiEnv>,
     path: WasmPtr<u8, M>,
     path_len: M::Offset,
 ) -> Result<Errno, WasiError> {
-    WasiEnv::do_pending_operations(&mut ctx)?;
+    wasi_pending_operations_or_sleep!(&mut ctx);
 
     let env = ctx.data();
     let (memory, mut state) = unsafe { env.get_memory_and_wasi_state(&ctx, 0) };
@@ -31,13 +35,5 @@ pub fn chdir<M: MemorySize>(
 }
 
 pub fn chdir_internal(env: &WasiEnv, path: &str) -> Result<(), Errno> {
-    let state = &env.state;
-
-    // Check if the directory exists
-    if state.fs.root_fs.read_dir(Path::new(path)).is_err() {
-        return Err(Errno::Noent);
-    }
-
-    state.fs.set_current_dir(path);
-    Ok(())
+    env.state.fs.change_dir(path)
 }
@@ -0,0 +1,153 @@
+use std::{future::Future, pin::Pin, task::Poll};
+
+use wasmer_wasix_types::wasi::Subclockflags;
+
+use super::*;
+use crate::{runtime::timer_wheel::TimerWheelSleep, syscalls::*};
+
+/// ### `clock_nanosleep()`
+/// Sends the current thread to sleep for a period of time, or until a
+/// point in time, measured against a particular clock
+///
+/// ## Parameters
+///
+/// * `clock_id` - Clock to measure the time with (`Monotonic` or `Realtime`)
+/// * `flags` - If `SUBSCRIPTION_CLOCK_ABSTIME` is set then `request` is an
+///   absolute time on the clock, otherwise it is relative to the current time
+/// * `request` - Amount of time to sleep for (or the time to sleep until)
+/// * `remain` - When a relative sleep is interrupted by a signal this receives
+///   the time that was left, if null then it is not written
+///
+/// ## Return
+///
+/// Returns `Errno::Intr` if a signal interrupted the sleep
+#[instrument(level = "trace", skip_all, fields(?clock_id, ?flags, %request), ret)]
+pub fn clock_nanosleep<M: MemorySize + 'static>(
+    mut ctx: FunctionEnvMut<'_, WasiEnv>,
+    clock_id: Snapshot0Clockid,
+    flags: Subclockflags,
+    request: Timestamp,
+    remain: WasmPtr<Timestamp, M>,
+) -> Result<Errno, WasiError> {
+    wasi_pending_operations_or_sleep!(&mut ctx);
+
+    let remain = if !remain.is_null() {
+        Some(remain)
+    } else {
+        None
+    };
+    clock_nanosleep_internal::<M>(ctx, clock_id, flags, request, remain, false)
+}
+
+/// Sleeps as [`clock_nanosleep`] does, except that a sleep with `restart`
+/// set carries on after every signal instead of only after the ones that
+/// restart syscalls, and never returns `Errno::Intr`
+pub(crate) fn clock_nanosleep_internal<M: MemorySize + 'static>(
+    mut ctx: FunctionEnvMut<'_, WasiEnv>,
+    clock_id: Snapshot0Clockid,
+    flags: Subclockflags,
+    mut request: Timestamp,
+    remain: Option<WasmPtr<Timestamp, M>>,
+    restart: bool,
+) -> Result<Errno, WasiError> {
+    let is_absolute = flags.contains(Subclockflags::SUBSCRIPTION_CLOCK_ABSTIME);
+
+    // Signals that restart the sleep go around again rather than recursing
+    loop {
+        // If we were just restored then the sleep has already happened
+        let remaining = if let Some(remaining) =
+            unsafe { handle_rewind::<M, Option<Timestamp>>(&mut ctx) }
+        {
+            remaining
+        } else {
+            wasi_try_ok!(ctx.data().sandbox_policy().check_clock(clock_id));
+
+            ctx = wasi_try_ok!(maybe_backoff::<M>(ctx)?);
+            ctx = wasi_try_ok!(maybe_snapshot::<M>(ctx)?);
+
+            let env = ctx.data();
+
+            // Work out how long we need to sleep for
+            let duration = match clock_id {
+                Snapshot0Clockid::Monotonic | Snapshot0Clockid::Realtime if is_absolute => {
+                    let mut now = wasi_try_ok!(platform_clock_time_get(clock_id, 1)) as Timestamp;
+                    {
+                        let guard = env.state.clock_offset.lock().unwrap();
+                        if let Some(offset) = guard.get(&clock_id) {
+                            now = now.saturating_add_signed(*offset);
+                        }
+                    }
+                    request.saturating_sub(now)
+                }
+                Snapshot0Clockid::Monotonic | Snapshot0Clockid::Realtime => request,
+                _ => return Ok(Errno::Inval),
+            };
+
+            #[cfg(feature = "sys-thread")]
+            if duration == 0 {
+                std::thread::yield_now();
+            }
+
+            if duration == 0 {
+                return Ok(Errno::Success);
+            }
+
+            // The remaining time is always measured on the monotonic clock so
+            // that changes to the realtime clock don't affect it
+            let deadline = (wasi_try_ok!(platform_clock_time_get(Snapshot0Clockid::Monotonic, 1))
+                as Timestamp)
+                .saturating_add(duration);
+            let duration = Duration::from_nanos(duration);
+            let tasks = env.tasks().clone();
+            let timer_wheel = env.runtime().timer_wheel();
+            let interrupted = env.wait_for_interrupt();
+            let res = __asyncify_with_deep_sleep::<M, _, _>(ctx, async move {
+                // Sleeps either share the timer wheel of the host or get a
+                // timer of their own (as do the ones that end too far away for
+                // an `Instant`)
+                let sleep: Pin<Box<dyn Future<Output = ()> + Send + Sync>> =
+                    match (timer_wheel, Instant::now().checked_add(duration)) {
+                        (Some(wheel), Some(at)) => Box::pin(TimerWheelSleep::new(wheel, at)),
+                        _ => tasks.sleep_now(duration),
+                    };
+
+                tokio::select! {
+                    _ = sleep => None,
+                    _ = interrupted => {
+                        let now = platform_clock_time_get(Snapshot0Clockid::Monotonic, 1)
+                            .unwrap_or_default() as Timestamp;
+                        Some(deadline.saturating_sub(now))
+                    }
+                }
+            })?;
+            match res {
+                AsyncifyAction::Finish(finished_ctx, remaining) => {
+                    ctx = finished_ctx;
+                    remaining
+                }
+                AsyncifyAction::Unwind => return Ok(Errno::Success),
+                AsyncifyAction::Abort(err) => return Ok(err),
+            }
+        };
+
+        let Some(remaining) = remaining else {
+            return Ok(Errno::Success);
+        };
+
+        // The signal handlers run before the sleep carries on or we return to
+        // the caller
+        if WasiEnv::handle_interrupted_wait(&mut ctx)? || restart {
+            if !is_absolute {
+                request = remaining;
+            }
+            continue;
+        }
+
+        if !is_absolute && let Some(remain) = remain {
+            let env = ctx.data();
+            let memory = unsafe { env.memory_view(&ctx) };
+            wasi_try_mem_ok!(remain.write(&memory, remaining));
+        }
+        return Ok(Errno::Intr);
+    }
+}
@@ -10,7 +10,7 @@ pub fn closure_allocate<M: MemorySize>(
     mut ctx: FunctionEnvMut<'_, WasiEnv>,
     closure: WasmPtr<u32, M>,
 ) -> Result<Errno, WasiError> {
-    WasiEnv::do_pending_operations(&mut ctx)?;
+    wasi_pending_operations_or_sleep!(&mut ctx);
 
     let (env, mut store) = ctx.data_and_store_mut();
     let Some(linker) = env.inner().linker().cloned() else {
@@ -8,7 +8,7 @@ pub fn closure_free(
     mut ctx: FunctionEnvMut<'_, WasiEnv>,
     closure: u32,
 ) -> Result<Errno, WasiError> {
-    WasiEnv::do_pending_operations(&mut ctx)?;
+    wasi_pending_operations_or_sleep!(&mut ctx);
 
     let (env, mut store) = ctx.data_and_store_mut();
 
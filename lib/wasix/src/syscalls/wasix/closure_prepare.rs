@@ -323,7 +323,7 @@ pub fn closure_prepare<M: MemorySize>(
     result_types_length: u32,
     environment: WasmPtr<u8, M>,
 ) -> Result<Errno, WasiError> {
-    WasiEnv::do_pending_operations(&mut ctx)?;
+    wasi_pending_operations_or_sleep!(&mut ctx);
 
     let (env, mut store) = ctx.data_and_store_mut();
     let memory = unsafe { env.memory_view(&store) };
@@ -60,7 +60,7 @@ pub fn context_create<M: MemorySize>(
     new_context_ptr: WasmPtr<u64, M>,
     entrypoint: u32,
 ) -> Result<Errno, WasiError> {
-    WasiEnv::do_pending_operations(&mut ctx)?;
+    wasi_pending_operations_or_sleep!(&mut ctx);
 
     // Verify that we are in an async context
     // We need to do this first, before we borrow the store mutably
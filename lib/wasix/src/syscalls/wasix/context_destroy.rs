@@ -17,7 +17,7 @@ pub fn context_destroy(
     mut ctx: FunctionEnvMut<'_, WasiEnv>,
     target_context_id: u64,
 ) -> Result<Errno, WasiError> {
-    WasiEnv::do_pending_operations(&mut ctx)?;
+    wasi_pending_operations_or_sleep!(&mut ctx);
 
     let env = ctx.data();
     let memory: MemoryView<'_> = unsafe { env.memory_view(&ctx) };
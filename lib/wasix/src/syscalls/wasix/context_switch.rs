@@ -84,7 +84,7 @@ pub fn context_switch_not_supported(
     mut ctx: FunctionEnvMut<'_, WasiEnv>,
     _target_context_id: u64,
 ) -> Result<Errno, WasiError> {
-    WasiEnv::do_pending_operations(&mut ctx)?;
+    wasi_pending_operations_or_sleep!(&mut ctx);
 
     tracing::warn!(
         "The WASIX context-switching API is only available in engines supporting async execution"
@@ -0,0 +1,276 @@
+use std::sync::RwLock;
+
+use virtual_fs::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt, FsError, VirtualFile};
+
+use super::*;
+#[cfg(feature = "journal")]
+use crate::journal::JournalEffector;
+use crate::syscalls::*;
+
+/// ### `copy_file_range()`
+/// Copies bytes from one file to another without them passing through the
+/// memory of the guest
+/// Note: This is similar to `copy_file_range` in POSIX
+///
+/// The host copies the bytes itself when both files live on the host,
+/// otherwise they are read and written in chunks.
+///
+/// ## Parameters
+///
+/// * `fd_in` - File that the bytes are copied from
+/// * `off_in` - Offset in `fd_in` to copy from, which is moved past the
+///   copied bytes. When it is null the offset of `fd_in` is used (and moved)
+/// * `fd_out` - File that the bytes are copied to
+/// * `off_out` - Offset in `fd_out` to copy to, which is moved past the
+///   copied bytes. When it is null the offset of `fd_out` is used (and moved)
+/// * `len` - Number of bytes to copy
+///
+/// ## Return
+///
+/// Number of bytes copied, which is less than `len` when the end of `fd_in`
+/// is reached first (or when a range would end past `i64::MAX`). Fails with
+/// `Errno::Inval` when the two ranges overlap in the same file or an offset
+/// is past `i64::MAX`, and with `Errno::Badf` when `fd_out` appends.
+#[instrument(level = "trace", skip_all, fields(%fd_in, %fd_out, %len, ncopied = field::Empty), ret)]
+pub fn copy_file_range<M: MemorySize>(
+    mut ctx: FunctionEnvMut<'_, WasiEnv>,
+    fd_in: WasiFd,
+    off_in: WasmPtr<Filesize, M>,
+    fd_out: WasiFd,
+    off_out: WasmPtr<Filesize, M>,
+    len: Filesize,
+    ret_copied: WasmPtr<Filesize, M>,
+) -> Result<Errno, WasiError> {
+    wasi_pending_operations_or_sleep!(&mut ctx);
+
+    let env = ctx.data();
+    let memory = unsafe { env.memory_view(&ctx) };
+    let src_offset = match off_in.is_null() {
+        true => None,
+        false => Some(wasi_try_mem_ok!(off_in.read(&memory))),
+    };
+    let dst_offset = match off_out.is_null() {
+        true => None,
+        false => Some(wasi_try_mem_ok!(off_out.read(&memory))),
+    };
+
+    let copied = wasi_try_ok!(copy_file_range_internal::<M>(
+        &mut ctx, fd_in, src_offset, fd_out, dst_offset, len
+    )?);
+    Span::current().record("ncopied", copied);
+
+    let env = ctx.data();
+    let memory = unsafe { env.memory_view(&ctx) };
+    if let Some(offset) = src_offset {
+        let offset = wasi_try_ok!(offset.checked_add(copied).ok_or(Errno::Overflow));
+        wasi_try_mem_ok!(off_in.write(&memory, offset));
+    }
+    if let Some(offset) = dst_offset {
+        let offset = wasi_try_ok!(offset.checked_add(copied).ok_or(Errno::Overflow));
+        wasi_try_mem_ok!(off_out.write(&memory, offset));
+    }
+    wasi_try_mem_ok!(ret_copied.write(&memory, copied));
+
+    Ok(Errno::Success)
+}
+
+/// Size of the chunks that are copied when the host can't copy the files
+const COPY_CHUNK_SIZE: Filesize = 64 * 1024;
+
+type FileHandle = Arc<RwLock<Box<dyn VirtualFile + Send + Sync + 'static>>>;
+
+#[allow(clippy::await_holding_lock)]
+pub(crate) fn copy_file_range_internal<M: MemorySize>(
+    ctx: &mut FunctionEnvMut<'_, WasiEnv>,
+    fd_in: WasiFd,
+    off_in: Option<Filesize>,
+    fd_out: WasiFd,
+    off_out: Option<Filesize>,
+    len: Filesize,
+) -> Result<Result<Filesize, Errno>, WasiError> {
+    let env = ctx.data();
+    let src = wasi_try_ok_ok!(env.state.fs.get_fd(fd_in));
+    let dst = wasi_try_ok_ok!(env.state.fs.get_fd(fd_out));
+    if !src.inner.rights.contains(Rights::FD_READ) || !dst.inner.rights.contains(Rights::FD_WRITE) {
+        return Ok(Err(Errno::Access));
+    }
+    if dst.inner.flags.contains(Fdflags::APPEND) {
+        return Ok(Err(Errno::Badf));
+    }
+    let src_handle = wasi_try_ok_ok!(file_handle(&src));
+    let dst_handle = wasi_try_ok_ok!(file_handle(&dst));
+
+    let src_offset = off_in.unwrap_or_else(|| src.inner.offset.load(Ordering::Acquire));
+    let dst_offset = off_out.unwrap_or_else(|| dst.inner.offset.load(Ordering::Acquire));
+    // Files can't grow past `i64::MAX`, the copy stops there
+    const MAX_OFFSET: Filesize = i64::MAX as Filesize;
+    if src_offset > MAX_OFFSET || dst_offset > MAX_OFFSET {
+        return Ok(Err(Errno::Inval));
+    }
+    let len = len.min(MAX_OFFSET - src_offset.max(dst_offset));
+    let same_file = Arc::ptr_eq(&src_handle, &dst_handle);
+    if same_file
+        && src_offset < dst_offset.saturating_add(len)
+        && dst_offset < src_offset.saturating_add(len)
+    {
+        return Ok(Err(Errno::Inval));
+    }
+
+    // The copied bytes are read back for the journal, which needs them to
+    // replay the copy
+    #[cfg(feature = "journal")]
+    let journal = ctx.data().enable_journal;
+    #[cfg(not(feature = "journal"))]
+    let journal = false;
+
+    let (copied, data) = wasi_try_ok_ok!(__asyncify(ctx, None, async move {
+        if same_file {
+            let mut handle = src_handle.write().unwrap();
+            let copied =
+                copy_through_buffer(handle.as_mut(), src_offset, None, dst_offset, len).await?;
+            let data = read_back(handle.as_mut(), journal, dst_offset, copied).await?;
+            return Ok((copied, data));
+        }
+
+        // The handles are always locked in the same order so that two copies
+        // in opposite directions can't deadlock
+        let (mut src, mut dst) = if Arc::as_ptr(&src_handle) < Arc::as_ptr(&dst_handle) {
+            let src = src_handle.write().unwrap();
+            (src, dst_handle.write().unwrap())
+        } else {
+            let dst = dst_handle.write().unwrap();
+            (src_handle.write().unwrap(), dst)
+        };
+
+        // Data that was written but is still buffered is copied as well
+        src.flush().await.map_err(map_io_err)?;
+        dst.flush().await.map_err(map_io_err)?;
+        let copied = match src.copy_range_to(src_offset, dst.as_mut(), dst_offset, len) {
+            Err(FsError::Unsupported) => {
+                copy_through_buffer(
+                    src.as_mut(),
+                    src_offset,
+                    Some(dst.as_mut()),
+                    dst_offset,
+                    len,
+                )
+                .await
+            }
+            res => res.map_err(fs_error_into_wasi_err),
+        }?;
+        let data = read_back(dst.as_mut(), journal, dst_offset, copied).await?;
+        Ok((copied, data))
+    })?);
+
+    #[cfg(feature = "journal")]
+    if let Some(data) = data {
+        JournalEffector::save_copy_file_range::<M>(
+            ctx,
+            fd_in,
+            off_in.is_none(),
+            fd_out,
+            off_out.is_none(),
+            dst_offset,
+            &data,
+        )
+        .map_err(|err| {
+            tracing::error!("failed to save copy_file_range event - {}", err);
+            WasiError::Exit(ExitCode::from(Errno::Fault))
+        })?;
+    }
+    #[cfg(not(feature = "journal"))]
+    let _ = data;
+
+    if off_in.is_none() {
+        src.inner.offset.fetch_add(copied, Ordering::AcqRel);
+    }
+    if off_out.is_none() {
+        dst.inner.offset.fetch_add(copied, Ordering::AcqRel);
+    }
+    let mut stat = dst.inode.stat.write().unwrap();
+    stat.st_size = stat.st_size.max(dst_offset.saturating_add(copied));
+    drop(stat);
+
+    if copied > 0
+        && let Kind::File { path, .. } = dst.inode.read().deref()
+    {
+        ctx.data()
+            .state
+            .fs
+            .watches
+            .notify(path, Fswatchmask::MODIFY);
+    }
+
+    Ok(Ok(copied))
+}
+
+/// Reads the bytes that were just copied to `offset` back, when they are
+/// needed for the journal
+async fn read_back(
+    file: &mut (dyn VirtualFile + Send + Sync + 'static),
+    needed: bool,
+    offset: Filesize,
+    len: Filesize,
+) -> Result<Option<Vec<u8>>, Errno> {
+    if !needed || len == 0 {
+        return Ok(None);
+    }
+    let mut data = vec![0u8; len as usize];
+    file.seek(std::io::SeekFrom::Start(offset))
+        .await
+        .map_err(map_io_err)?;
+    file.read_exact(&mut data).await.map_err(map_io_err)?;
+    Ok(Some(data))
+}
+
+/// The handle of a regular file
+fn file_handle(fd: &Fd) -> Result<FileHandle, Errno> {
+    if fd.is_stdio {
+        return Err(Errno::Inval);
+    }
+    match fd.inode.read().deref() {
+        Kind::File {
+            handle: Some(handle),
+            ..
+        } => Ok(handle.clone()),
+        Kind::Dir { .. } | Kind::Root { .. } => Err(Errno::Isdir),
+        _ => Err(Errno::Inval),
+    }
+}
+
+/// Copies the range by reading it into a buffer and writing it out again,
+/// `dst` is `None` when the range is copied within `src`
+async fn copy_through_buffer(
+    src: &mut (dyn VirtualFile + Send + Sync + 'static),
+    mut src_offset: Filesize,
+    mut dst: Option<&mut (dyn VirtualFile + Send + Sync + 'static)>,
+    mut dst_offset: Filesize,
+    len: Filesize,
+) -> Result<Filesize, Errno> {
+    let mut buf = vec![0u8; len.min(COPY_CHUNK_SIZE) as usize];
+    let mut copied: Filesize = 0;
+    while copied < len {
+        let chunk = (len - copied).min(COPY_CHUNK_SIZE) as usize;
+        src.seek(std::io::SeekFrom::Start(src_offset))
+            .await
+            .map_err(map_io_err)?;
+        let amt = src.read(&mut buf[..chunk]).await.map_err(map_io_err)?;
+        if amt == 0 {
+            break;
+        }
+
+        let dst = match dst.as_deref_mut() {
+            Some(dst) => dst,
+            None => &mut *src,
+        };
+        dst.seek(std::io::SeekFrom::Start(dst_offset))
+            .await
+            .map_err(map_io_err)?;
+        dst.write_all(&buf[..amt]).await.map_err(map_io_err)?;
+
+        copied += amt as Filesize;
+        src_offset += amt as Filesize;
+        dst_offset += amt as Filesize;
+    }
+    Ok(copied)
+}
@@ -8,7 +8,7 @@ pub fn dl_invalid_handle(
     mut ctx: FunctionEnvMut<'_, WasiEnv>,
     handle: DlHandle,
 ) -> Result<Errno, WasiError> {
-    WasiEnv::do_pending_operations(&mut ctx)?;
+    wasi_pending_operations_or_sleep!(&mut ctx);
 
     let (env, mut store) = ctx.data_and_store_mut();
     let memory = unsafe { env.memory_view(&store) };
@@ -14,7 +14,7 @@ pub fn dlopen<M: MemorySize>(
     ld_library_path_len: M::Offset,
     out_handle: WasmPtr<DlHandle, M>,
 ) -> Result<Errno, WasiError> {
-    WasiEnv::do_pending_operations(&mut ctx)?;
+    wasi_pending_operations_or_sleep!(&mut ctx);
 
     let (env, mut store) = ctx.data_and_store_mut();
     let memory = unsafe { env.memory_view(&store) };
@@ -22,7 +22,7 @@ pub fn epoll_ctl<M: MemorySize + 'static>(
     fd: WasiFd,
     event_ref: WasmPtr<EpollEvent<M>, M>,
 ) -> Result<Errno, WasiError> {
-    WasiEnv::do_pending_operations(&mut ctx)?;
+    wasi_pending_operations_or_sleep!(&mut ctx);
 
     let env = ctx.data();
 
@@ -22,7 +22,7 @@ pub fn epoll_wait<M: MemorySize + 'static>(
     timeout: Timestamp,
     ret_nevents: WasmPtr<M::Offset, M>,
 ) -> Result<Errno, WasiError> {
-    WasiEnv::do_pending_operations(&mut ctx)?;
+    wasi_pending_operations_or_sleep!(&mut ctx);
     if maxevents <= 0 {
         return Ok(Errno::Inval);
     }
@@ -148,9 +148,9 @@ pub fn epoll_wait<M: MemorySize + 'static>(
         ctx,
         Box::pin(trigger),
     )?;
-    if let AsyncifyAction::Finish(mut ctx, events) = res {
-        Ok(process_events(&ctx, events))
-    } else {
-        Ok(Errno::Success)
+    match res {
+        AsyncifyAction::Finish(mut ctx, events) => Ok(process_events(&ctx, events)),
+        AsyncifyAction::Unwind => Ok(Errno::Success),
+        AsyncifyAction::Abort(err) => Ok(err),
     }
 }
@@ -17,7 +17,7 @@ pub fn fd_dup2<M: MemorySize>(
     cloexec: Bool,
     ret_fd: WasmPtr<WasiFd, M>,
 ) -> Result<Errno, WasiError> {
-    WasiEnv::do_pending_operations(&mut ctx)?;
+    wasi_pending_operations_or_sleep!(&mut ctx);
 
     let copied_fd = wasi_try_ok!(fd_dup_internal(
         &mut ctx,
@@ -14,7 +14,7 @@ pub fn fd_fdflags_set(
     fd: WasiFd,
     flags: Fdflagsext,
 ) -> Result<Errno, WasiError> {
-    WasiEnv::do_pending_operations(&mut ctx)?;
+    wasi_pending_operations_or_sleep!(&mut ctx);
 
     let ret = fd_fdflags_set_internal(&mut ctx, fd, flags)?;
 
@@ -0,0 +1,124 @@
+use std::task::{Context, Poll};
+
+use super::*;
+use crate::syscalls::*;
+
+/// ### `fd_ioctl()`
+/// Performs a control operation on a file descriptor, only a small set of
+/// the requests that are known from `ioctl` are supported
+/// Inputs:
+/// - `Fd fd`
+///     The file descriptor to operate on
+/// - `IoctlRequest request`
+///     Which operation to perform
+///     - `IOCTL_FIONBIO` turns non-blocking mode on or off
+///     - `IOCTL_FIONREAD` returns the number of bytes that can be read
+///     - `IOCTL_TIOCGWINSZ` returns the size of the terminal
+/// - `void *arg`
+///     The argument of the request, its type depends on the request
+/// Possible Errors:
+/// - `Errno::Badf`, `Errno::Access`, `Errno::Fault` and `Errno::Notty` for
+///   requests that are unknown or that don't apply to the descriptor
+#[instrument(level = "trace", skip_all, fields(%fd, request = format!("{request:#x}")), ret)]
+pub fn fd_ioctl<M: MemorySize>(
+    mut ctx: FunctionEnvMut<'_, WasiEnv>,
+    fd: WasiFd,
+    request: IoctlRequest,
+    arg: WasmPtr<u8, M>,
+) -> Result<Errno, WasiError> {
+    wasi_pending_operations_or_sleep!(&mut ctx);
+
+    match request {
+        IOCTL_FIONBIO => {
+            let env = ctx.data();
+            let memory = unsafe { env.memory_view(&ctx) };
+            let enable = wasi_try_mem_ok!(arg.cast::<i32>().read(&memory)) != 0;
+
+            let mut flags = wasi_try_ok!(env.state.fs.get_fd(fd)).inner.flags;
+            flags.set(Fdflags::NONBLOCK, enable);
+            let ret = fd_fdstat_set_flags_internal(&mut ctx, fd, flags)?;
+
+            #[cfg(feature = "journal")]
+            if ret == Errno::Success && ctx.data().enable_journal {
+                JournalEffector::save_fd_set_flags(&mut ctx, fd, flags).map_err(|err| {
+                    tracing::error!("failed to save file set flags event - {}", err);
+                    WasiError::Exit(ExitCode::from(Errno::Fault))
+                })?;
+            }
+
+            Ok(ret)
+        }
+        IOCTL_FIONREAD => {
+            let env = ctx.data();
+            let available = wasi_try_ok!(fd_bytes_available(env, fd));
+            let available = i32::try_from(available).unwrap_or(i32::MAX);
+
+            let memory = unsafe { env.memory_view(&ctx) };
+            wasi_try_mem_ok!(arg.cast::<i32>().write(&memory, available));
+            Ok(Errno::Success)
+        }
+        IOCTL_TIOCGWINSZ => {
+            let env = ctx.data();
+            wasi_try_ok!(env.state.fs.get_fd(fd));
+            let Some(tty) = env.runtime.tty() else {
+                return Ok(Errno::Notty);
+            };
+            let state = tty.tty_get();
+            let is_tty = match fd {
+                __WASI_STDIN_FILENO => state.stdin_tty,
+                __WASI_STDOUT_FILENO => state.stdout_tty,
+                __WASI_STDERR_FILENO => state.stderr_tty,
+                _ => false,
+            };
+            if !is_tty {
+                return Ok(Errno::Notty);
+            }
+
+            // `struct winsize` is `ws_row`, `ws_col`, `ws_xpixel` and `ws_ypixel`
+            let mut winsize = [0u8; 8];
+            for (field, value) in
+                winsize
+                    .chunks_exact_mut(2)
+                    .zip([state.rows, state.cols, state.width, state.height])
+            {
+                let value = u16::try_from(value).unwrap_or(u16::MAX);
+                field.copy_from_slice(&value.to_le_bytes());
+            }
+
+            let memory = unsafe { env.memory_view(&ctx) };
+            let arg = wasi_try_mem_ok!(arg.slice(&memory, 8u32.into()));
+            wasi_try_mem_ok!(arg.write_slice(&winsize));
+            Ok(Errno::Success)
+        }
+        _ => Ok(Errno::Notty),
+    }
+}
+
+/// The number of bytes that can be read from the descriptor right now
+fn fd_bytes_available(env: &WasiEnv, fd: WasiFd) -> Result<u64, Errno> {
+    let fd_entry = env.state.fs.get_fd(fd)?;
+    if !fd_entry.inner.rights.contains(Rights::FD_READ) {
+        return Err(Errno::Access);
+    }
+    let offset = fd_entry.inner.offset.load(Ordering::Acquire);
+
+    let waker = futures::task::noop_waker();
+    let mut cx = Context::from_waker(&waker);
+
+    let mut guard = fd_entry.inode.write();
+    let available = match guard.deref_mut() {
+        Kind::File {
+            handle: Some(handle),
+            ..
+        } => handle.read().unwrap().size().saturating_sub(offset),
+        Kind::Buffer { buffer } => (buffer.len() as u64).saturating_sub(offset),
+        Kind::PipeRx { rx } => rx.bytes_available() as u64,
+        Kind::DuplexPipe { pipe, .. } => pipe.bytes_available() as u64,
+        Kind::Socket { socket } => match Pin::new(socket).poll_read_ready(&mut cx) {
+            Poll::Ready(Ok(available)) => available as u64,
+            Poll::Ready(Err(_)) | Poll::Pending => 0,
+        },
+        _ => return Err(Errno::Notty),
+    };
+    Ok(available)
+}
@@ -0,0 +1,47 @@
+use super::*;
+use crate::syscalls::*;
+
+/// ### `fd_lock()`
+/// Takes an advisory lock on a file (the equivalent of `flock`), the lock
+/// is held by the open file description so the duplicates of the
+/// descriptor and the ones inherited by forked processes share it. Taking
+/// a lock that is already held by the description converts it.
+///
+/// The lock is released by `fd_unlock` or when the last descriptor of the
+/// open file description is closed.
+/// Inputs:
+/// - `Fd fd`
+///     The file to lock
+/// - `Lockflags flags`
+///     If the lock is exclusive (otherwise it is shared) and if the call
+///     fails rather than waits when someone else holds the lock
+/// Possible Errors:
+/// - `Errno::Badf` if the descriptor is not open
+/// - `Errno::Again` if the lock is held by someone else in a way that
+///     conflicts and `Lockflags::NONBLOCK` is set
+/// - `Errno::Intr` if a signal interrupted the wait
+#[instrument(level = "trace", skip_all, fields(%fd, ?flags), ret)]
+pub fn fd_lock(
+    mut ctx: FunctionEnvMut<'_, WasiEnv>,
+    fd: WasiFd,
+    flags: Lockflags,
+) -> Result<Errno, WasiError> {
+    wasi_pending_operations_or_sleep!(&mut ctx);
+
+    let env = ctx.data();
+    let fd_entry = wasi_try_ok!(env.state.fs.get_fd(fd));
+    let exclusive = flags.contains(Lockflags::EXCLUSIVE);
+    let (inode, owner) = (fd_entry.inode, fd_entry.inner.description);
+    if inode.lock.try_lock(&owner, exclusive) {
+        return Ok(Errno::Success);
+    }
+    if flags.contains(Lockflags::NONBLOCK) {
+        return Ok(Errno::Again);
+    }
+
+    wasi_try_ok!(__asyncify(&mut ctx, None, async move {
+        inode.lock.lock(&owner, exclusive).await;
+        Ok(())
+    })?);
+    Ok(Errno::Success)
+}
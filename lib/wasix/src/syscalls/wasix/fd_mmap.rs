@@ -0,0 +1,176 @@
+use super::*;
+use crate::fs::{FileMapping, read_region};
+use crate::syscalls::*;
+
+/// ### `fd_mmap()`
+/// Maps a region of a file into linear memory (a restricted form of `mmap`)
+///
+/// The mapping is not demand paged: a fresh range of linear memory is
+/// allocated for it (by growing the memory a number of whole pages) and the
+/// region of the file is copied into it right away.
+///
+/// Linear memory can't alias the memory of another process, so a mapping
+/// that is asked to be shared (`Mmapflags::SHARED`) is a synced copy of the
+/// region rather than a shared segment of memory. Changes that are made to
+/// the file afterwards are only seen by it once it is synced with
+/// `fd_msync`, and when it is writable it is only copied back to the file by
+/// `fd_msync` and `fd_munmap`, whatever is still mapped when the process
+/// exits or execs is discarded. Two private mappings of the same region are
+/// independent copies of it, but a synced mapping of a region that the
+/// process already has a synced mapping of is refused, as the two copies
+/// would never see each other's writes.
+///
+/// The mappings are kept by a forked process and they stay attached to the
+/// same files. What a parent and its child share is the file (e.g. one from
+/// `memfd_create`), they communicate through their mappings of it by
+/// syncing them.
+///
+/// The part of a mapping that is beyond the end of the file reads as zeros
+/// and it is never written back, a mapping never changes the size of the
+/// file.
+/// Inputs:
+/// - `Fd fd`
+///     The file that is mapped, it must be readable (and writable for a
+///     writable synced mapping)
+/// - `Filesize offset`
+///     Where the mapped region starts in the file
+/// - `u32 len`
+///     The length of the mapping
+/// - `Mmapflags flags`
+///     If the mapping is writable and if it is synced with the file
+/// Output:
+/// - `u32 *ret_addr`
+///     Where the mapping starts in linear memory
+/// Possible Errors:
+/// - `Errno::Access` if the file can't be read (or written for a writable
+///   synced mapping)
+/// - `Errno::Nodev` if the descriptor is not a regular file
+/// - `Errno::Notsup` if the mapping is synced and the process already has
+///   a synced mapping of part of the same region of the file
+/// - `Errno::Nomem` if the linear memory can't be grown any further
+#[instrument(level = "trace", skip_all, fields(%fd, %offset, len = field::Empty, ?flags, ret_addr = field::Empty), ret)]
+pub fn fd_mmap<M: MemorySize>(
+    mut ctx: FunctionEnvMut<'_, WasiEnv>,
+    fd: WasiFd,
+    offset: Filesize,
+    len: M::Offset,
+    flags: Mmapflags,
+    ret_addr: WasmPtr<M::Offset, M>,
+) -> Result<Errno, WasiError> {
+    wasi_pending_operations_or_sleep!(&mut ctx);
+
+    let len: u64 = len.into();
+    Span::current().record("len", len);
+    if len == 0 {
+        return Ok(Errno::Inval);
+    }
+
+    let addr = wasi_try_ok!(fd_mmap_internal(&mut ctx, fd, offset, len, flags)?);
+    Span::current().record("ret_addr", addr);
+
+    let env = ctx.data();
+    let memory = unsafe { env.memory_view(&ctx) };
+    wasi_try_mem_ok!(ret_addr.write(&memory, wasi_try_ok!(to_offset::<M>(addr as usize))));
+
+    Ok(Errno::Success)
+}
+
+fn fd_mmap_internal(
+    ctx: &mut FunctionEnvMut<'_, WasiEnv>,
+    fd: WasiFd,
+    offset: Filesize,
+    len: u64,
+    flags: Mmapflags,
+) -> WasiResult<u64> {
+    let env = ctx.data();
+    let fd_entry = wasi_try_ok_ok!(env.state.fs.get_fd(fd));
+    let shared = flags.contains(Mmapflags::WRITE | Mmapflags::SHARED);
+    if !fd_entry.inner.rights.contains(Rights::FD_READ)
+        || (shared && !fd_entry.inner.rights.contains(Rights::FD_WRITE))
+    {
+        return Ok(Err(Errno::Access));
+    }
+    let file = {
+        let guard = fd_entry.inode.read();
+        match guard.deref() {
+            Kind::File {
+                handle: Some(handle),
+                ..
+            } => handle.clone(),
+            Kind::File { handle: None, .. } => return Ok(Err(Errno::Badf)),
+            _ => return Ok(Err(Errno::Nodev)),
+        }
+    };
+
+    if flags.contains(Mmapflags::SHARED)
+        && env
+            .state
+            .fs
+            .mappings
+            .lock()
+            .unwrap()
+            .maps_synced(&file, offset, len)
+    {
+        return Ok(Err(Errno::Notsup));
+    }
+
+    let data = wasi_try_ok_ok!(__asyncify_light(
+        env,
+        None,
+        read_region(file.clone(), offset, len)
+    )?);
+
+    // The mapping gets whole pages, either the ones that were released by
+    // a previous mapping or new ones at the end of the memory
+    let page_size = wasmer::WASM_PAGE_SIZE as u64;
+    let Some(reserved) = len.checked_next_multiple_of(page_size) else {
+        return Ok(Err(Errno::Nomem));
+    };
+    let reused = env.state.fs.mappings.lock().unwrap().take_free(reserved);
+    let addr = match reused {
+        Some(addr) => addr,
+        None => {
+            let Ok(pages) = u32::try_from(reserved / page_size) else {
+                return Ok(Err(Errno::Nomem));
+            };
+            let memory = env.inner().memory_clone();
+            match memory.grow(ctx, wasmer::Pages(pages)) {
+                Ok(previous) => previous.bytes().0 as u64,
+                Err(err) => {
+                    tracing::debug!("failed to grow the memory for a mapping - {}", err);
+                    return Ok(Err(Errno::Nomem));
+                }
+            }
+        }
+    };
+
+    let env = ctx.data();
+    let memory = unsafe { env.memory_view(&ctx) };
+    wasi_try_ok_ok!(memory.write(addr, &data).map_err(mem_error_to_wasi));
+    if reused.is_some() {
+        // Released pages still hold whatever the previous mapping left there,
+        // like `mmap` the rest of the last page reads as zeros
+        let zeros = vec![0u8; (reserved - data.len() as u64) as usize];
+        wasi_try_ok_ok!(
+            memory
+                .write(addr + data.len() as u64, &zeros)
+                .map_err(mem_error_to_wasi)
+        );
+    }
+
+    env.state.fs.mappings.lock().unwrap().insert(FileMapping {
+        addr,
+        len,
+        reserved,
+        offset,
+        file_len: data.len() as u64,
+        flags,
+        file,
+        synced: Arc::new(Mutex::new(if flags.contains(Mmapflags::SHARED) {
+            data
+        } else {
+            Vec::new()
+        })),
+    });
+    Ok(Ok(addr))
+}
@@ -0,0 +1,55 @@
+use super::*;
+use crate::syscalls::*;
+
+/// ### `fd_msync()`
+/// Syncs the mappings (see `fd_mmap`) in a range of linear memory
+/// with their files, the equivalent of `msync` with `MS_SYNC | MS_INVALIDATE`:
+/// the bytes of writable mappings that changed since the last sync are
+/// written back and then the mappings are updated with the changes that
+/// other mappings of the files (like the ones of a forked process) wrote
+/// back in the meantime
+///
+/// Only the part of a mapping that was within the file when it was mapped
+/// is synced, private mappings are left as they are. When mappings of the
+/// same region of a file overlap the mapping at the highest address is
+/// synced last.
+/// Inputs:
+/// - `u32 addr`
+///     Where the range starts in linear memory
+/// - `u32 len`
+///     The length of the range
+/// Possible Errors:
+/// - `Errno::Nomem` if no mapping overlaps the range
+#[instrument(level = "trace", skip_all, fields(addr = field::Empty, len = field::Empty), ret)]
+pub fn fd_msync<M: MemorySize>(
+    mut ctx: FunctionEnvMut<'_, WasiEnv>,
+    addr: M::Offset,
+    len: M::Offset,
+) -> Result<Errno, WasiError> {
+    wasi_pending_operations_or_sleep!(&mut ctx);
+
+    let addr: u64 = addr.into();
+    let len: u64 = len.into();
+    Span::current().record("addr", addr).record("len", len);
+    if len == 0 {
+        return Ok(Errno::Inval);
+    }
+
+    let env = ctx.data();
+    let mappings = env.state.fs.mappings.lock().unwrap().overlapping(addr, len);
+    if mappings.is_empty() {
+        return Ok(Errno::Nomem);
+    }
+
+    let memory = unsafe { env.memory_view(&ctx) };
+    let end = addr.saturating_add(len);
+    let res = __asyncify_light(env, None, async {
+        for mapping in mappings.iter() {
+            mapping.sync(&memory, addr, end, true).await?;
+        }
+        Ok(())
+    })?;
+    wasi_try_ok!(res);
+
+    Ok(Errno::Success)
+}
@@ -0,0 +1,49 @@
+use super::*;
+use crate::syscalls::*;
+
+/// ### `fd_munmap()`
+/// Removes the mappings (see `fd_mmap`) in a range of linear memory, the
+/// changes to writable synced mappings are written back to their files
+/// first
+///
+/// A mapping can only be removed as a whole. The memory of the removed
+/// mappings stays part of linear memory and it is used again for the next
+/// mappings.
+/// Inputs:
+/// - `u32 addr`
+///     Where the range starts in linear memory
+/// - `u32 len`
+///     The length of the range
+/// Possible Errors:
+/// - `Errno::Inval` if the range only covers part of a mapping
+#[instrument(level = "trace", skip_all, fields(addr = field::Empty, len = field::Empty), ret)]
+pub fn fd_munmap<M: MemorySize>(
+    mut ctx: FunctionEnvMut<'_, WasiEnv>,
+    addr: M::Offset,
+    len: M::Offset,
+) -> Result<Errno, WasiError> {
+    wasi_pending_operations_or_sleep!(&mut ctx);
+
+    let addr: u64 = addr.into();
+    let len: u64 = len.into();
+    Span::current().record("addr", addr).record("len", len);
+    if len == 0 {
+        return Ok(Errno::Inval);
+    }
+
+    let env = ctx.data();
+    let mappings = wasi_try_ok!(env.state.fs.mappings.lock().unwrap().remove(addr, len));
+
+    let memory = unsafe { env.memory_view(&ctx) };
+    let res = __asyncify_light(env, None, async {
+        for mapping in mappings.iter() {
+            mapping
+                .sync(&memory, mapping.addr, mapping.addr + mapping.len, false)
+                .await?;
+        }
+        Ok(())
+    })?;
+    wasi_try_ok!(res);
+
+    Ok(Errno::Success)
+}
@@ -0,0 +1,97 @@
+use std::time::Instant;
+
+use super::*;
+use crate::syscalls::*;
+
+/// ### `fd_read_deadline()`
+/// Read data from file descriptor, giving up when no data arrived before a
+/// deadline. Unlike a receive timeout on a socket the deadline only applies
+/// to this one read
+///
+/// Inputs:
+/// - `Fd fd`
+///     File descriptor from which data will be read
+/// - `const __wasi_iovec_t *iovs`
+///     Vectors where data will be stored
+/// - `u32 iovs_len`
+///     Length of data in `iovs`
+/// - `__wasi_option_timestamp_t *deadline`
+///     Absolute time on the monotonic clock after which the read fails
+///     with `Errno::Timedout`, if none (or zero) then it blocks like `fd_read`
+/// Output:
+/// - `u32 *nread`
+///     Number of bytes read
+#[instrument(level = "trace", skip_all, fields(%fd, nread = field::Empty), ret)]
+pub fn fd_read_deadline<M: MemorySize>(
+    mut ctx: FunctionEnvMut<'_, WasiEnv>,
+    fd: WasiFd,
+    iovs: WasmPtr<__wasi_iovec_t<M>, M>,
+    iovs_len: M::Offset,
+    deadline: WasmPtr<OptionTimestamp, M>,
+    nread: WasmPtr<M::Offset, M>,
+) -> Result<Errno, WasiError> {
+    wasi_pending_operations_or_sleep!(&mut ctx);
+    let mut retry = BlockingIoRetry::rewound::<M>(&mut ctx);
+
+    let env = ctx.data();
+    let memory = unsafe { env.memory_view(&ctx) };
+    let deadline = wasi_try_mem_ok!(deadline.read(&memory));
+    let deadline = match deadline.tag {
+        OptionTag::None => None,
+        OptionTag::Some if deadline.u == 0 => None,
+        OptionTag::Some => Some(deadline.u),
+        _ => return Ok(Errno::Inval),
+    };
+
+    // The deadline is on the monotonic clock of the guest, which is shifted
+    // from the one of the host. Deadlines that are too far away for the
+    // host are treated as no deadline at all.
+    let deadline = match deadline {
+        Some(deadline) => {
+            let mut now = wasi_try_ok!(platform_clock_time_get(Snapshot0Clockid::Monotonic, 1));
+            if let Some(offset) = env
+                .state
+                .clock_offset
+                .lock()
+                .unwrap()
+                .get(&Snapshot0Clockid::Monotonic)
+            {
+                now += *offset;
+            }
+            let remaining = i64::try_from(deadline)
+                .unwrap_or(i64::MAX)
+                .saturating_sub(now)
+                .max(0);
+            Instant::now().checked_add(Duration::from_nanos(remaining as u64))
+        }
+        None => None,
+    };
+    let deadline = ReadDeadline::new(deadline);
+
+    let fd_entry = wasi_try_ok!(env.state.fs.get_fd(fd));
+    let offset = fd_entry.inner.offset.load(Ordering::Acquire) as usize;
+
+    ctx = wasi_try_ok!(maybe_backoff::<M>(ctx)?);
+
+    let res = loop {
+        let res = fd_read_internal::<M>(
+            &mut ctx,
+            fd,
+            fd_entry.clone(),
+            iovs,
+            iovs_len,
+            offset,
+            nread,
+            true,
+            &deadline,
+        )?;
+        if BlockingIoRetry::is_pending(&fd_entry, &res) {
+            (ctx, retry) = wasi_try_ok!(retry.backoff::<M>(ctx)?);
+            continue;
+        }
+        if !WasiEnv::handle_interrupted_io(&mut ctx, &res)? {
+            break res;
+        }
+    };
+    fd_read_internal_handler(ctx, res, nread)
+}
@@ -0,0 +1,60 @@
+use super::*;
+use crate::{fs::SignalNotificationInner, syscalls::*};
+
+/// ### `fd_signal()`
+/// Creates a file handle that reports the signals of the current thread
+/// instead of having them delivered to its signal handler (the equivalent
+/// of `signalfd`)
+///
+/// The signals in `mask` are blocked on the current thread (and stay blocked
+/// once the handle is closed) so that they remain pending until they are read,
+/// like after a `sigprocmask` that precedes a `signalfd`. The handle becomes
+/// readable when one of them is pending and every read takes one of them off
+/// the thread, writing its number as a `u32` (a buffer smaller than that fails
+/// with `Errno::Inval`).
+///
+/// A forked process inherits the handle, but there it reports the signals
+/// of the main thread of the child (which blocks them as well) rather than
+/// the ones of the thread that created it.
+///
+/// Inputs:
+/// - `u64 mask`
+///     The signals that are reported (one bit per signal number, like the
+///     mask of `poll_oneoff_deadline`)
+/// Output:
+/// - `Fd *ret_fd`
+///     The new file handle
+#[instrument(level = "trace", skip_all, fields(mask = format!("{mask:#x}"), ret_fd = field::Empty), ret)]
+pub fn fd_signal<M: MemorySize>(
+    mut ctx: FunctionEnvMut<'_, WasiEnv>,
+    mask: u64,
+    ret_fd: WasmPtr<WasiFd, M>,
+) -> Result<Errno, WasiError> {
+    wasi_pending_operations_or_sleep!(&mut ctx);
+
+    let env = ctx.data();
+    env.thread.set_signal_mask(env.thread.signal_mask() | mask);
+    let (memory, state, inodes) = unsafe { env.get_memory_and_wasi_state_and_inodes(&ctx, 0) };
+
+    let kind = Kind::SignalNotifications {
+        inner: Arc::new(SignalNotificationInner::new(env.thread.clone(), mask)),
+    };
+    let inode =
+        state
+            .fs
+            .create_inode_with_default_stat(inodes, kind, false, "signal".to_string().into());
+    let rights = Rights::FD_READ | Rights::POLL_FD_READWRITE | Rights::FD_FDSTAT_SET_FLAGS;
+    let fd = wasi_try_ok!(state.fs.create_fd(
+        rights,
+        rights,
+        Fdflags::empty(),
+        Fdflagsext::empty(),
+        0,
+        inode,
+    ));
+
+    Span::current().record("ret_fd", fd);
+    wasi_try_mem_ok!(ret_fd.write(&memory, fd));
+
+    Ok(Errno::Success)
+}
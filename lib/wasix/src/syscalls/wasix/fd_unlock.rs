@@ -0,0 +1,19 @@
+use super::*;
+use crate::syscalls::*;
+
+/// ### `fd_unlock()`
+/// Releases the advisory lock (see `fd_lock`) that the open file
+/// description of a descriptor holds on its file, if it holds one
+/// Inputs:
+/// - `Fd fd`
+///     The file to unlock
+/// Possible Errors:
+/// - `Errno::Badf` if the descriptor is not open
+#[instrument(level = "trace", skip_all, fields(%fd), ret)]
+pub fn fd_unlock(mut ctx: FunctionEnvMut<'_, WasiEnv>, fd: WasiFd) -> Result<Errno, WasiError> {
+    wasi_pending_operations_or_sleep!(&mut ctx);
+
+    let fd_entry = wasi_try_ok!(ctx.data().state.fs.get_fd(fd));
+    fd_entry.inode.lock.unlock(&fd_entry.inner.description);
+    Ok(Errno::Success)
+}
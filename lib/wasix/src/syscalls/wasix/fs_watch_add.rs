@@ -0,0 +1,91 @@
+use std::{path::PathBuf, sync::atomic::AtomicUsize};
+
+use super::*;
+use crate::syscalls::*;
+
+static WATCH_NUMBER: AtomicUsize = AtomicUsize::new(0);
+
+/// ### `fs_watch_add()`
+/// Watches a path for changes (the equivalent of `inotify_add_watch`), the
+/// changes are read from the returned file descriptor as `Fsevent`s that are
+/// each followed by the name of the entry that changed. The descriptor is
+/// readable (for `poll_oneoff`) whenever there are events to read. When the
+/// guest doesn't keep up the events are dropped and a single `OVERFLOW`
+/// event is read instead.
+///
+/// When the path is a directory the changes to the entries directly in it
+/// are reported too. The changes that the host reports are read the same way
+/// as the ones that the guest makes.
+///
+/// Inputs:
+/// - `Fd fd`
+///     The directory that `path` is relative to
+/// - `const char *path`
+///     String containing the path to watch
+/// - `u32 path_len`
+///     The length of the `path` string
+/// - `Fswatchmask mask`
+///     The changes that are reported
+/// Output:
+/// - `Fd *ret_fd`
+///     The file descriptor the events are read from
+#[instrument(level = "trace", skip_all, fields(%fd, path = field::Empty, ?mask, ret_fd = field::Empty), ret)]
+pub fn fs_watch_add<M: MemorySize>(
+    mut ctx: FunctionEnvMut<'_, WasiEnv>,
+    fd: WasiFd,
+    path: WasmPtr<u8, M>,
+    path_len: M::Offset,
+    mask: Fswatchmask,
+    ret_fd: WasmPtr<WasiFd, M>,
+) -> Result<Errno, WasiError> {
+    wasi_pending_operations_or_sleep!(&mut ctx);
+
+    let env = ctx.data();
+    let (memory, state, inodes) = unsafe { env.get_memory_and_wasi_state_and_inodes(&ctx, 0) };
+    let path_string = unsafe { get_input_str_ok!(&memory, path, path_len) };
+    Span::current().record("path", path_string.as_str());
+
+    if mask.is_empty() {
+        return Ok(Errno::Inval);
+    }
+
+    let base_dir = wasi_try_ok!(state.fs.get_fd(fd));
+    if !base_dir.inner.rights.contains(Rights::PATH_FILESTAT_GET) {
+        return Ok(Errno::Access);
+    }
+    let inode = wasi_try_ok!(state.fs.get_inode_at_path(inodes, fd, &path_string, true));
+    let watched = {
+        let guard = inode.read();
+        match guard.deref() {
+            Kind::Dir { path, .. } | Kind::File { path, .. } => path.clone(),
+            Kind::Root { .. } => PathBuf::from("/"),
+            _ => return Ok(Errno::Inval),
+        }
+    };
+
+    let rx = state.fs.watches.add(&watched, mask);
+    let watch_no = WATCH_NUMBER.fetch_add(1, Ordering::SeqCst);
+    let rx_inode = state.fs.create_inode_with_default_stat(
+        inodes,
+        Kind::PipeRx { rx },
+        false,
+        format!("watch{watch_no}").into(),
+    );
+    let rights = Rights::FD_READ
+        | Rights::POLL_FD_READWRITE
+        | Rights::FD_FDSTAT_SET_FLAGS
+        | Rights::FD_FILESTAT_GET;
+    let watch_fd = wasi_try_ok!(state.fs.create_fd(
+        rights,
+        rights,
+        Fdflags::empty(),
+        Fdflagsext::empty(),
+        0,
+        rx_inode,
+    ));
+    Span::current().record("ret_fd", watch_fd);
+
+    wasi_try_mem_ok!(ret_fd.write(&memory, watch_fd));
+
+    Ok(Errno::Success)
+}
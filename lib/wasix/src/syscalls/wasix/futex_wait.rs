@@ -78,7 +78,7 @@ pub fn futex_wait<M: MemorySize + 'static>(
     timeout: WasmPtr<OptionTimestamp, M>,
     ret_woken: WasmPtr<Bool, M>,
 ) -> Result<Errno, WasiError> {
-    WasiEnv::do_pending_operations(&mut ctx)?;
+    wasi_pending_operations_or_sleep!(&mut ctx);
 
     futex_wait_internal(ctx, futex_ptr, expected, timeout, ret_woken)
 }
@@ -163,14 +163,18 @@ pub(super) fn futex_wait_internal<M: MemorySize + 'static>(
     // We use asyncify on the poller and potentially go into deep sleep
     tracing::trace!("wait on {futex_idx}");
     let res = __asyncify_with_deep_sleep::<M, _, _>(ctx, Box::pin(poller))?;
-    if let AsyncifyAction::Finish(ctx, res) = res {
-        let mut env = ctx.data();
-        let memory = unsafe { env.memory_view(&ctx) };
-        if res {
-            wasi_try_mem_ok!(ret_woken.write(&memory, Bool::True));
-        } else {
-            wasi_try_mem_ok!(ret_woken.write(&memory, Bool::False));
+    match res {
+        AsyncifyAction::Finish(ctx, res) => {
+            let mut env = ctx.data();
+            let memory = unsafe { env.memory_view(&ctx) };
+            if res {
+                wasi_try_mem_ok!(ret_woken.write(&memory, Bool::True));
+            } else {
+                wasi_try_mem_ok!(ret_woken.write(&memory, Bool::False));
+            }
         }
+        AsyncifyAction::Unwind => {}
+        AsyncifyAction::Abort(err) => return Ok(err),
     }
     Ok(Errno::Success)
 }
@@ -12,7 +12,7 @@ pub fn futex_wake_all<M: MemorySize>(
     futex_ptr: WasmPtr<u32, M>,
     ret_woken: WasmPtr<Bool, M>,
 ) -> Result<Errno, WasiError> {
-    WasiEnv::do_pending_operations(&mut ctx)?;
+    wasi_pending_operations_or_sleep!(&mut ctx);
 
     let env = ctx.data();
     let memory = unsafe { env.memory_view(&ctx) };
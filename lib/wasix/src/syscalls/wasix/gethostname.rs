@@ -0,0 +1,39 @@
+use super::*;
+use crate::syscalls::*;
+
+/// ### `gethostname()`
+/// Returns the hostname of the environment (which is virtual, the guest
+/// never sees the hostname of the host)
+///
+/// Inputs:
+/// - `char *name`
+///     Buffer that receives the hostname (which is not NUL terminated)
+/// - `size_t *name_len`
+///     The size of the buffer, the length of the hostname is written back
+///     into it
+///
+/// If the hostname exceeds the size of the buffer then this function
+/// will return ERANGE
+#[instrument(level = "trace", skip_all, fields(hostname = field::Empty), ret)]
+pub fn gethostname<M: MemorySize>(
+    ctx: FunctionEnvMut<'_, WasiEnv>,
+    name: WasmPtr<u8, M>,
+    name_len: WasmPtr<M::Offset, M>,
+) -> Errno {
+    let env = ctx.data();
+    let memory = unsafe { env.memory_view(&ctx) };
+
+    let hostname = env.hostname.read().unwrap().clone();
+    Span::current().record("hostname", hostname.as_str());
+
+    let max_name_len = wasi_try_mem!(name_len.read(&memory));
+    let max_name_len64: u64 = max_name_len.into();
+    wasi_try_mem!(name_len.write(&memory, wasi_try!(to_offset::<M>(hostname.len()))));
+    if hostname.len() as u64 > max_name_len64 {
+        return Errno::Range;
+    }
+
+    let name = wasi_try_mem!(name.slice(&memory, wasi_try!(to_offset::<M>(hostname.len()))));
+    wasi_try_mem!(name.write_slice(hostname.as_bytes()));
+    Errno::Success
+}
@@ -0,0 +1,181 @@
+use std::{
+    path::PathBuf,
+    sync::{RwLock, atomic::AtomicUsize},
+};
+
+use virtual_fs::BufferFile;
+
+use super::*;
+use crate::{fs::WasiFs, syscalls::*};
+
+// Used to make the names of the memory files unique, see `PIPE_NUMBER`
+static MEMFD_NUMBER: AtomicUsize = AtomicUsize::new(0);
+
+/// The longest name that can be given to a memory file (same as Linux)
+const MEMFD_NAME_MAX: u64 = 249;
+
+/// ### `memfd_create()`
+/// Creates an anonymous file that lives in memory. The file starts out
+/// empty and can be resized with `fd_allocate` or `fd_filestat_set_size`.
+///
+/// The memory is shared by every descriptor that refers to it, including
+/// the ones inherited by forked processes, and is released once the last
+/// of them is closed.
+/// Inputs:
+/// - `char *name`
+///     Name of the file, which is only used for debugging purposes
+/// - `u32 name_len`
+///     The length of the `name` string
+/// - `Fdflagsext fd_flags`
+///     The flags of the file descriptor
+/// Output:
+/// - `Fd* ret_fd`
+///     The new file descriptor
+#[instrument(level = "trace", skip_all, fields(name = field::Empty, ret_fd = field::Empty), ret)]
+pub fn memfd_create<M: MemorySize>(
+    mut ctx: FunctionEnvMut<'_, WasiEnv>,
+    name: WasmPtr<u8, M>,
+    name_len: M::Offset,
+    fd_flags: Fdflagsext,
+    ret_fd: WasmPtr<WasiFd, M>,
+) -> Result<Errno, WasiError> {
+    wasi_pending_operations_or_sleep!(&mut ctx);
+
+    let env = ctx.data();
+    let memory = unsafe { env.memory_view(&ctx) };
+    let name_len64: u64 = name_len.into();
+    if name_len64 > MEMFD_NAME_MAX {
+        return Ok(Errno::Inval);
+    }
+    let name = unsafe { get_input_str_ok!(&memory, name, name_len) };
+    Span::current().record("name", name.as_str());
+
+    let fd = wasi_try_ok!(memfd_create_internal(&mut ctx, &name, fd_flags));
+
+    let env = ctx.data();
+    let memory = unsafe { env.memory_view(&ctx) };
+    Span::current().record("ret_fd", fd);
+    wasi_try_mem_ok!(ret_fd.write(&memory, fd));
+
+    Ok(Errno::Success)
+}
+
+pub(crate) fn memfd_create_internal(
+    ctx: &mut FunctionEnvMut<'_, WasiEnv>,
+    name: &str,
+    fd_flags: Fdflagsext,
+) -> Result<WasiFd, Errno> {
+    let env = ctx.data();
+    let (_, state, inodes) = unsafe { env.get_memory_and_wasi_state_and_inodes(&ctx, 0) };
+    create_memfd(&state.fs, inodes, name, fd_flags)
+}
+
+/// Creates the inode of a memory file and opens a descriptor for it
+fn create_memfd(
+    fs: &WasiFs,
+    inodes: &WasiInodes,
+    name: &str,
+    fd_flags: Fdflagsext,
+) -> Result<WasiFd, Errno> {
+    let memfd_no = MEMFD_NUMBER.fetch_add(1, Ordering::SeqCst);
+    let kind = Kind::File {
+        handle: Some(Arc::new(RwLock::new(Box::<BufferFile>::default()))),
+        path: PathBuf::new(),
+        fd: None,
+    };
+    let stat = Filestat {
+        st_filetype: Filetype::RegularFile,
+        ..Filestat::default()
+    };
+    let inode = fs.create_inode_with_stat(
+        inodes,
+        kind,
+        false,
+        format!("memfd:{name}-{memfd_no}").into(),
+        stat,
+    );
+
+    let rights = Rights::FD_READ
+        | Rights::FD_WRITE
+        | Rights::FD_SEEK
+        | Rights::FD_TELL
+        | Rights::FD_SYNC
+        | Rights::FD_DATASYNC
+        | Rights::FD_ADVISE
+        | Rights::FD_ALLOCATE
+        | Rights::FD_FDSTAT_SET_FLAGS
+        | Rights::FD_FILESTAT_GET
+        | Rights::FD_FILESTAT_SET_SIZE
+        | Rights::FD_FILESTAT_SET_TIMES
+        | Rights::POLL_FD_READWRITE;
+
+    fs.create_fd(rights, rights, Fdflags::empty(), fd_flags, 0, inode)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Weak;
+
+    use virtual_fs::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt, TmpFileSystem, VirtualFile};
+
+    use super::*;
+    use crate::fs::{FS_ROOT_INO, WasiFsRoot};
+
+    type Handle = Arc<RwLock<Box<dyn VirtualFile + Send + Sync + 'static>>>;
+
+    fn new_fs(inodes: &WasiInodes) -> WasiFs {
+        let fs_backing = WasiFsRoot::Sandbox(TmpFileSystem::new());
+        WasiFs::new_init(fs_backing, inodes, FS_ROOT_INO).unwrap()
+    }
+
+    fn memfd_handle(fs: &WasiFs, fd: WasiFd) -> Handle {
+        let fd = fs.get_fd(fd).unwrap();
+        let guard = fd.inode.read();
+        match guard.deref() {
+            Kind::File {
+                handle: Some(handle),
+                ..
+            } => handle.clone(),
+            _ => panic!("not a memory file"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_memfd_is_shared_with_forks() {
+        let inodes = WasiInodes::new();
+        let parent = new_fs(&inodes);
+        let fd = create_memfd(&parent, &inodes, "shared", Fdflagsext::empty()).unwrap();
+        let child = parent.fork();
+
+        // What the child writes is visible to the parent
+        {
+            let handle = memfd_handle(&child, fd);
+            let mut file = handle.write().unwrap();
+            file.write_all(b"from the child").await.unwrap();
+        }
+        let handle = memfd_handle(&parent, fd);
+        let mut file = handle.write().unwrap();
+        assert_eq!(file.size(), 14);
+        file.seek(std::io::SeekFrom::Start(0)).await.unwrap();
+        let mut data = String::new();
+        file.read_to_string(&mut data).await.unwrap();
+        assert_eq!(data, "from the child");
+    }
+
+    #[tokio::test]
+    async fn test_memfd_is_freed_on_last_close() {
+        let inodes = WasiInodes::new();
+        let parent = new_fs(&inodes);
+        let fd = create_memfd(&parent, &inodes, "freed", Fdflagsext::empty()).unwrap();
+        let child = parent.fork();
+        let memory: Weak<_> = Arc::downgrade(&memfd_handle(&parent, fd));
+
+        // The memory outlives the descriptor of the parent...
+        parent.close_fd(fd).unwrap();
+        assert!(memory.upgrade().is_some());
+
+        // ...and is released with the last one
+        child.close_fd(fd).unwrap();
+        assert!(memory.upgrade().is_none());
+    }
+}
@@ -1,12 +1,14 @@
 mod call_dynamic;
 mod callback_signal;
 mod chdir;
+mod clock_nanosleep;
 mod closure_allocate;
 mod closure_free;
 mod closure_prepare;
 mod context_create;
 mod context_destroy;
 mod context_switch;
+mod copy_file_range;
 mod dl_invalid_handle;
 mod dlopen;
 mod dlsym;
@@ -16,12 +18,28 @@ mod epoll_wait;
 mod fd_dup2;
 mod fd_fdflags_get;
 mod fd_fdflags_set;
+mod fd_ioctl;
+mod fd_lock;
+mod fd_mmap;
+mod fd_msync;
+mod fd_munmap;
 mod fd_pipe;
+mod fd_read_deadline;
+mod fd_signal;
+mod fd_unlock;
+mod fs_watch_add;
 mod futex_wait;
 mod futex_wake;
 mod futex_wake_all;
 mod getcwd;
+mod gethostname;
+mod memfd_create;
+mod net_interfaces;
 mod path_open2;
+mod path_open_parent;
+mod perf_counter_frequency;
+mod perf_counter_read;
+mod poll_oneoff_deadline;
 mod port_addr_add;
 mod port_addr_clear;
 mod port_addr_list;
@@ -39,20 +57,30 @@ mod proc_exec;
 mod proc_exec2;
 mod proc_exec3;
 mod proc_exit2;
+mod proc_flush;
 mod proc_fork;
 mod proc_fork_env;
+mod proc_get_title;
+mod proc_getrlimit;
 mod proc_id;
 mod proc_join;
 mod proc_parent;
+mod proc_set_title;
+mod proc_setrlimit;
 mod proc_signal;
 mod proc_signals_get;
 mod proc_signals_sizes_get;
+mod proc_sigpending;
+mod proc_sigrestart;
 mod proc_snapshot;
 mod proc_spawn;
 mod proc_spawn2;
+mod proc_uptime;
+mod proc_wait_any;
 mod reflect_signature;
 mod resolve;
 mod sched_yield;
+mod sethostname;
 mod sock_accept;
 mod sock_addr_local;
 mod sock_addr_peer;
@@ -69,17 +97,23 @@ mod sock_listen;
 mod sock_open;
 mod sock_pair;
 mod sock_recv;
+mod sock_recv_fds;
 mod sock_recv_from;
+mod sock_recv_from_ttl;
 mod sock_send;
+mod sock_send_fds;
 mod sock_send_file;
 mod sock_send_to;
 mod sock_set_opt_flag;
 mod sock_set_opt_size;
+mod sock_set_opt_str;
 mod sock_set_opt_time;
 mod sock_shutdown;
+mod sock_stats;
 mod sock_status;
 mod stack_checkpoint;
 mod stack_restore;
+mod sysinfo;
 mod thread_exit;
 mod thread_id;
 mod thread_join;
@@ -93,12 +127,14 @@ mod tty_set;
 pub use call_dynamic::*;
 pub use callback_signal::*;
 pub use chdir::*;
+pub use clock_nanosleep::*;
 pub use closure_allocate::*;
 pub use closure_free::*;
 pub use closure_prepare::*;
 pub use context_create::*;
 pub use context_destroy::*;
 pub use context_switch::*;
+pub use copy_file_range::*;
 pub use dl_invalid_handle::*;
 pub use dlopen::*;
 pub use dlsym::*;
@@ -108,12 +144,28 @@ pub use epoll_wait::*;
 pub use fd_dup2::*;
 pub use fd_fdflags_get::*;
 pub use fd_fdflags_set::*;
+pub use fd_ioctl::*;
+pub use fd_lock::*;
+pub use fd_mmap::*;
+pub use fd_msync::*;
+pub use fd_munmap::*;
 pub use fd_pipe::*;
+pub use fd_read_deadline::*;
+pub use fd_signal::*;
+pub use fd_unlock::*;
+pub use fs_watch_add::*;
 pub use futex_wait::*;
 pub use futex_wake::*;
 pub use futex_wake_all::*;
 pub use getcwd::*;
+pub use gethostname::*;
+pub use memfd_create::*;
+pub use net_interfaces::*;
+pub use path_open_parent::*;
 pub use path_open2::*;
+pub use perf_counter_frequency::*;
+pub use perf_counter_read::*;
+pub use poll_oneoff_deadline::*;
 pub use port_addr_add::*;
 pub use port_addr_clear::*;
 pub use port_addr_list::*;
@@ -131,20 +183,30 @@ pub use proc_exec::*;
 pub use proc_exec2::*;
 pub use proc_exec3::*;
 pub use proc_exit2::*;
+pub use proc_flush::*;
 pub use proc_fork::*;
 pub use proc_fork_env::*;
+pub use proc_get_title::*;
+pub use proc_getrlimit::*;
 pub use proc_id::*;
 pub use proc_join::*;
 pub use proc_parent::*;
+pub use proc_set_title::*;
+pub use proc_setrlimit::*;
 pub use proc_signal::*;
 pub use proc_signals_get::*;
 pub use proc_signals_sizes_get::*;
+pub use proc_sigpending::*;
+pub use proc_sigrestart::*;
 pub use proc_snapshot::*;
 pub use proc_spawn::*;
 pub use proc_spawn2::*;
+pub use proc_uptime::*;
+pub use proc_wait_any::*;
 pub use reflect_signature::*;
 pub use resolve::*;
 pub use sched_yield::*;
+pub use sethostname::*;
 pub use sock_accept::*;
 pub use sock_addr_local::*;
 pub use sock_addr_peer::*;
@@ -161,17 +223,23 @@ pub use sock_listen::*;
 pub use sock_open::*;
 pub use sock_pair::*;
 pub use sock_recv::*;
+pub use sock_recv_fds::*;
 pub use sock_recv_from::*;
+pub use sock_recv_from_ttl::*;
 pub use sock_send::*;
+pub use sock_send_fds::*;
 pub use sock_send_file::*;
 pub use sock_send_to::*;
 pub use sock_set_opt_flag::*;
 pub use sock_set_opt_size::*;
+pub use sock_set_opt_str::*;
 pub use sock_set_opt_time::*;
 pub use sock_shutdown::*;
+pub use sock_stats::*;
 pub use sock_status::*;
 pub use stack_checkpoint::*;
 pub use stack_restore::*;
+pub use sysinfo::*;
 pub use thread_exit::*;
 pub use thread_id::*;
 pub use thread_join::*;
@@ -0,0 +1,82 @@
+use super::*;
+use crate::syscalls::*;
+
+/// ### `net_interfaces()`
+/// Lists the network interfaces along with their addresses and flags
+/// This function fills the output buffers as much as possible.
+/// If either buffer is not big enough then `nifaces` and `naddrs` are
+/// filled with the sizes needed and EOVERFLOW is returned
+///
+/// ## Parameters
+///
+/// * `ifaces` - The buffer where the interfaces will be stored
+/// * `nifaces` - The number of interfaces that fit in `ifaces`
+/// * `addrs` - The buffer where the addresses of all the interfaces will
+///   be stored, each interface refers to a range of them
+/// * `naddrs` - The number of addresses that fit in `addrs`
+///
+/// ## Return
+///
+/// The number of interfaces and addresses returned.
+#[instrument(level = "trace", skip_all, fields(nifaces = field::Empty, naddrs = field::Empty), ret)]
+pub fn net_interfaces<M: MemorySize>(
+    mut ctx: FunctionEnvMut<'_, WasiEnv>,
+    ifaces_ptr: WasmPtr<Netif, M>,
+    nifaces_ptr: WasmPtr<M::Offset, M>,
+    addrs_ptr: WasmPtr<__wasi_cidr_t, M>,
+    naddrs_ptr: WasmPtr<M::Offset, M>,
+) -> Result<Errno, WasiError> {
+    wasi_pending_operations_or_sleep!(&mut ctx);
+
+    let env = ctx.data();
+    let memory = unsafe { env.memory_view(&ctx) };
+    let max_ifaces: u64 = wasi_try_mem_ok!(nifaces_ptr.read(&memory)).into();
+    let max_addrs: u64 = wasi_try_mem_ok!(naddrs_ptr.read(&memory)).into();
+
+    let net = env.net().clone();
+    let ifaces = wasi_try_ok!(__asyncify(&mut ctx, None, async {
+        net.interfaces().await.map_err(net_error_into_wasi_err)
+    })?);
+    let env = ctx.data();
+    let memory = unsafe { env.memory_view(&ctx) };
+    let total_addrs: usize = ifaces.iter().map(|iface| iface.addrs.len()).sum();
+    Span::current().record("nifaces", ifaces.len());
+    Span::current().record("naddrs", total_addrs);
+
+    let ifaces_len: M::Offset = wasi_try_ok!(ifaces.len().try_into().map_err(|_| Errno::Overflow));
+    let addrs_len: M::Offset = wasi_try_ok!(total_addrs.try_into().map_err(|_| Errno::Overflow));
+    wasi_try_mem_ok!(nifaces_ptr.write(&memory, ifaces_len));
+    wasi_try_mem_ok!(naddrs_ptr.write(&memory, addrs_len));
+    if ifaces.len() as u64 > max_ifaces || total_addrs as u64 > max_addrs {
+        return Ok(Errno::Overflow);
+    }
+
+    let ref_ifaces = wasi_try_mem_ok!(ifaces_ptr.slice(&memory, ifaces_len));
+    let ref_addrs = wasi_try_mem_ok!(addrs_ptr.slice(&memory, addrs_len));
+    let mut addrs_offset = 0u32;
+    for (n, iface) in ifaces.iter().enumerate() {
+        let mut name = [0u8; 16];
+        let name_len = iface.name.len().min(name.len() - 1);
+        name[..name_len].copy_from_slice(&iface.name.as_bytes()[..name_len]);
+
+        let mut flags = Netifflags::empty();
+        flags.set(Netifflags::UP, iface.up);
+        flags.set(Netifflags::LOOPBACK, iface.loopback);
+        flags.set(Netifflags::MULTICAST, iface.multicast);
+
+        wasi_try_mem_ok!(ref_ifaces.index(n as u64).write(Netif {
+            name,
+            flags: flags.bits(),
+            _padding: 0,
+            addrs_offset,
+            naddrs: iface.addrs.len() as u32,
+        }));
+        for addr in iface.addrs.iter() {
+            let naddr = ref_addrs.index(addrs_offset as u64);
+            wasi_try_ok!(crate::net::write_cidr(&memory, naddr.as_ptr::<M>(), *addr));
+            addrs_offset += 1;
+        }
+    }
+
+    Ok(Errno::Success)
+}
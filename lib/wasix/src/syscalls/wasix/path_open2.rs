@@ -16,11 +16,19 @@ use crate::syscalls::*;
 /// - `Oflags o_flags`
 ///     How the file will be opened
 /// - `Rights fs_rights_base`
-///     The rights of the created file descriptor
+///     The rights of the created file descriptor, limited to the inheriting
+///     rights of `dirfd` (`Errno::Notcapable` if `FD_WRITE` is not among them)
 /// - `Rights fs_rightsinheriting`
-///     The rights of file descriptors derived from the created file descriptor
+///     The rights of file descriptors derived from the created file descriptor,
+///     also limited to the inheriting rights of `dirfd`
 /// - `Fdflags fs_flags`
 ///     The flags of the file descriptor
+/// - `Fdflagsext fd_flags`
+///     The extended flags of the file descriptor, with `EXCLUSIVE` the file
+///     is locked exclusively while it is open (`Errno::Busy` if someone
+///     else, including the other processes of the host, holds a lock on it).
+///     Combined with `Oflags::TRUNC` the file is only truncated once the lock
+///     is taken, so a file that someone else holds is left alone.
 /// Output:
 /// - `Fd* fd`
 ///     The new file descriptor
@@ -40,7 +48,7 @@ pub fn path_open2<M: MemorySize>(
     fd_flags: Fdflagsext,
     fd: WasmPtr<WasiFd, M>,
 ) -> Result<Errno, WasiError> {
-    WasiEnv::do_pending_operations(&mut ctx)?;
+    wasi_pending_operations_or_sleep!(&mut ctx);
 
     if dirflags & __WASI_LOOKUP_SYMLINK_FOLLOW != 0 {
         Span::current().record("follow_symlinks", true);
@@ -99,6 +107,18 @@ pub fn path_open2<M: MemorySize>(
             tracing::error!("failed to save unlink event - {}", err);
             WasiError::Exit(ExitCode::from(Errno::Fault))
         })?;
+
+        // A file that is truncated once it is locked is not truncated by
+        // the open itself
+        if fd_flags.contains(Fdflagsext::EXCLUSIVE)
+            && o_flags.contains(Oflags::TRUNC)
+            && truncates_once_locked(ctx.data(), out_fd)
+        {
+            JournalEffector::save_fd_set_size(&mut ctx, out_fd, 0).map_err(|err| {
+                tracing::error!("failed to save file set size event - {}", err);
+                WasiError::Exit(ExitCode::from(Errno::Fault))
+            })?;
+        }
     }
 
     let env = ctx.data();
@@ -125,6 +145,16 @@ pub(crate) fn path_open_internal(
     fd_flags: Fdflagsext,
     with_fd: Option<WasiFd>,
 ) -> Result<Result<WasiFd, Errno>, WasiError> {
+    // The lock of an exclusive open is taken once the file is open, so the
+    // file is truncated after that rather than when it is opened
+    let truncate_once_locked =
+        fd_flags.contains(Fdflagsext::EXCLUSIVE) && o_flags.contains(Oflags::TRUNC);
+    let o_flags = if truncate_once_locked {
+        o_flags & !Oflags::TRUNC
+    } else {
+        o_flags
+    };
+
     let state = env.state.deref();
     let inodes = &state.inodes;
     let follow_symlinks = dirflags & __WASI_LOOKUP_SYMLINK_FOLLOW != 0;
@@ -149,13 +179,14 @@ pub(crate) fn path_open_internal(
     }
 
     let mut open_flags = 0;
-    // TODO: traverse rights of dirs properly
-    // COMMENTED OUT: WASI isn't giving appropriate rights here when opening
-    //              TODO: look into this; file a bug report if this is a bug
-    //
-    // Maximum rights: should be the working dir rights
-    // Minimum rights: whatever rights are provided
-    let adjusted_rights = /*fs_rights_base &*/ working_dir_rights_inheriting;
+    // The new file descriptor can't have more rights than what the directory
+    // passes on to the files that are opened through it, asking to write to
+    // a file in a directory that doesn't pass on the right to write fails
+    let adjusted_rights = fs_rights_base & working_dir_rights_inheriting;
+    let adjusted_rights_inheriting = fs_rights_inheriting & working_dir_rights_inheriting;
+    if fs_rights_base.contains(Rights::FD_WRITE) && !adjusted_rights.contains(Rights::FD_WRITE) {
+        return Ok(Err(Errno::Notcapable));
+    }
     let mut open_options = state.fs_new_open_options();
 
     let target_rights = match maybe_inode {
@@ -224,14 +255,14 @@ pub(crate) fn path_open_internal(
             Kind::File {
                 handle, path, fd, ..
             } => {
+                if o_flags.contains(Oflags::DIRECTORY) || orig_path.ends_with('/') {
+                    return Ok(Err(Errno::Notdir));
+                }
                 if let Some(special_fd) = fd {
                     // short circuit if we're dealing with a special file
                     assert!(handle.is_some());
                     return Ok(Ok(*special_fd));
                 }
-                if o_flags.contains(Oflags::DIRECTORY) || orig_path.ends_with('/') {
-                    return Ok(Err(Errno::Notdir));
-                }
 
                 let open_options = open_options
                     .write(minimum_rights.write)
@@ -255,14 +286,23 @@ pub(crate) fn path_open_internal(
                 // when this open requires stronger rights than the existing handle may have.
                 let requires_stronger_handle =
                     minimum_rights.write || minimum_rights.truncate || minimum_rights.create;
+                let mut open = || {
+                    open_options
+                        .open(&path)
+                        .map(|file| state.fs.buffer_writes(file, minimum_rights.write))
+                        .map_err(fs_error_into_wasi_err)
+                };
                 if handle.is_none() {
-                    *handle = Some(Arc::new(std::sync::RwLock::new(wasi_try_ok_ok!(
-                        open_options.open(&path).map_err(fs_error_into_wasi_err)
-                    ))));
+                    *handle = Some(Arc::new(std::sync::RwLock::new(wasi_try_ok_ok!(open()))));
                 } else if requires_stronger_handle {
                     let mut file = handle.as_ref().unwrap().write().unwrap();
-                    *file =
-                        wasi_try_ok_ok!(open_options.open(&path).map_err(fs_error_into_wasi_err));
+                    // The writes buffered by the old handle have to reach the file
+                    // before it is reopened (and maybe truncated), or they get lost
+                    wasi_try_ok_ok!(block_on(file.flush()).map_err(map_io_err));
+                    *file = wasi_try_ok_ok!(open());
+                }
+                if minimum_rights.truncate {
+                    state.fs.watches.notify(&*path, Fswatchmask::MODIFY);
                 }
 
                 if let Some(handle) = handle {
@@ -282,12 +322,14 @@ pub(crate) fn path_open_internal(
                 }
             }
             Kind::Buffer { .. } => unimplemented!("wasi::path_open for Buffer type files"),
+            // Directories can be opened with or without O_DIRECTORY, but
+            // never with write access.
             Kind::Root { .. } => {
-                if !o_flags.contains(Oflags::DIRECTORY) {
+                if fs_rights_base.contains(Rights::FD_WRITE) {
                     return Ok(Err(Errno::Isdir));
                 }
             }
-            Kind::Dir { .. } => {
+            Kind::Dir { path, .. } => {
                 if fs_rights_base.contains(Rights::FD_WRITE) {
                     return Ok(Err(Errno::Isdir));
                 }
@@ -297,7 +339,12 @@ pub(crate) fn path_open_internal(
             | Kind::PipeRx { .. }
             | Kind::DuplexPipe { .. }
             | Kind::EventNotifications { .. }
-            | Kind::Epoll { .. } => {}
+            | Kind::SignalNotifications { .. }
+            | Kind::Epoll { .. } => {
+                if o_flags.contains(Oflags::DIRECTORY) {
+                    return Ok(Err(Errno::Notdir));
+                }
+            }
             Kind::Symlink {
                 base_po_dir,
                 path_to_symlink,
@@ -390,7 +437,7 @@ pub(crate) fn path_open_internal(
                 }
 
                 match open_options.open(&new_file_host_path) {
-                    Ok(handle) => Some(handle),
+                    Ok(handle) => Some(state.fs.buffer_writes(handle, minimum_rights.write)),
                     Err(err) => {
                         // Even though the file does not exist, it still failed to create with
                         // `AlreadyExists` error.  This can happen if the path resolves to a
@@ -407,7 +454,7 @@ pub(crate) fn path_open_internal(
             let new_inode = {
                 let kind = Kind::File {
                     handle: handle.map(|a| Arc::new(std::sync::RwLock::new(a))),
-                    path: new_file_host_path,
+                    path: new_file_host_path.clone(),
                     fd: None,
                 };
                 wasi_try_ok_ok!(
@@ -421,8 +468,15 @@ pub(crate) fn path_open_internal(
                 let mut guard = parent_inode.write();
                 if let Kind::Dir { entries, .. } = guard.deref_mut() {
                     entries.insert(new_entity_name, new_inode.clone());
+                    state.fs.invalidate_path_cache();
                 }
             }
+            // Files created right under the root are not kept in its
+            // entries, but they are created all the same
+            state
+                .fs
+                .watches
+                .notify(&new_file_host_path, Fswatchmask::CREATE);
 
             new_inode
         } else {
@@ -437,7 +491,7 @@ pub(crate) fn path_open_internal(
             .fs
             .with_fd(
                 adjusted_rights,
-                fs_rights_inheriting,
+                adjusted_rights_inheriting,
                 fs_flags,
                 fd_flags,
                 open_flags,
@@ -448,7 +502,7 @@ pub(crate) fn path_open_internal(
     } else {
         state.fs.create_fd(
             adjusted_rights,
-            fs_rights_inheriting,
+            adjusted_rights_inheriting,
             fs_flags,
             fd_flags,
             open_flags,
@@ -456,5 +510,64 @@ pub(crate) fn path_open_internal(
         )
     });
 
+    if fd_flags.contains(Fdflagsext::EXCLUSIVE)
+        && let Err(err) = lock_exclusively(state, out_fd).and_then(|()| {
+            if truncate_once_locked {
+                truncate_locked(env, out_fd)
+            } else {
+                Ok(())
+            }
+        })
+    {
+        _ = state.fs.close_fd(out_fd);
+        return Ok(Err(err));
+    }
+
     Ok(Ok(out_fd))
 }
+
+/// Truncates a file that is opened with `Oflags::TRUNC` and
+/// `Fdflagsext::EXCLUSIVE` once it is locked, like `Oflags::TRUNC` on its
+/// own this only happens when the file is opened for writing
+fn truncate_locked(env: &WasiEnv, fd: WasiFd) -> Result<(), Errno> {
+    if !truncates_once_locked(env, fd) {
+        return Ok(());
+    }
+    fd_filestat_set_size_internal(env, fd, 0)
+}
+
+/// Returns true if the descriptor was opened for writing, which is when
+/// [`truncate_locked`] truncates the file
+fn truncates_once_locked(env: &WasiEnv, fd: WasiFd) -> bool {
+    env.state
+        .fs
+        .get_fd(fd)
+        .is_ok_and(|fd| fd.open_flags & Fd::WRITE != 0)
+}
+
+/// Takes the lock of a file that is opened with `Fdflagsext::EXCLUSIVE`,
+/// which fails with `Errno::Busy` if someone else holds a lock on it
+fn lock_exclusively(state: &WasiState, fd: WasiFd) -> Result<(), Errno> {
+    let fd = state.fs.get_fd(fd)?;
+
+    // The host lock is taken with a handle of its own, as the handle of the
+    // inode is shared by all of its descriptors and may be reopened. It is
+    // opened the way the descriptor was, so that a file that can only be
+    // written to can still be locked.
+    let write = fd.open_flags & Fd::WRITE != 0;
+    let read = fd.open_flags & Fd::READ != 0 || !write;
+    let host_file = match fd.inode.read().deref() {
+        Kind::File { path, .. } => Some(
+            state
+                .fs_new_open_options()
+                .read(read)
+                .write(write)
+                .open(path)
+                .map_err(fs_error_into_wasi_err)?,
+        ),
+        _ => None,
+    };
+    fd.inode
+        .lock
+        .try_lock_exclusive_with_host(&fd.inner.description, host_file)
+}
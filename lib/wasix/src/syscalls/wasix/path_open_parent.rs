@@ -0,0 +1,191 @@
+use std::path::{Component, Path};
+
+use super::*;
+use crate::VIRTUAL_ROOT_FD;
+use crate::syscalls::*;
+
+/// ### `path_open_parent()`
+/// Opens the directory that holds the last component of a path and returns
+/// the name of that component, so that it can be used with the other `path_*`
+/// syscalls relative to the returned descriptor (for instance to create a
+/// temporary file with `path_open` and `path_rename` it into place).
+///
+/// The directory is resolved once and the descriptor keeps referring to it,
+/// so renaming or replacing the directories along the path afterwards does
+/// not change where the name is looked up. A path without a last component
+/// (like `/` or `.`) opens the directory it resolves to and returns `.` as
+/// the name.
+///
+/// Symbolic links along the path to the directory are always followed, the
+/// last component itself is never looked at.
+/// Inputs:
+/// - `Fd dirfd`
+///     The fd corresponding to the directory that the path is relative to
+/// - `char *path`
+///     The path whose parent directory will be opened
+/// - `u32 path_len`
+///     The length of the `path` string
+/// - `Rights fs_rights_base`
+///     The rights of the created file descriptor
+/// - `Rights fs_rights_inheriting`
+///     The rights of file descriptors derived from the created file descriptor
+/// - `Fdflagsext fd_flags`
+///     The flags of the file descriptor
+/// - `char *name`
+///     Buffer that receives the name of the last component
+/// - `u32 name_len`
+///     The length of the `name` buffer
+/// Output:
+/// - `u32 *ret_name_len`
+///     The length of the name, which is also written when the buffer is too
+///     small (in which case `Errno::Range` is returned and nothing is opened)
+/// - `Fd *ret_fd`
+///     The file descriptor of the parent directory
+#[instrument(level = "trace", skip_all, fields(%dirfd, path = field::Empty, name = field::Empty, ret_fd = field::Empty), ret)]
+pub fn path_open_parent<M: MemorySize>(
+    mut ctx: FunctionEnvMut<'_, WasiEnv>,
+    dirfd: WasiFd,
+    path: WasmPtr<u8, M>,
+    path_len: M::Offset,
+    fs_rights_base: Rights,
+    fs_rights_inheriting: Rights,
+    fd_flags: Fdflagsext,
+    name: WasmPtr<u8, M>,
+    name_len: M::Offset,
+    ret_name_len: WasmPtr<M::Offset, M>,
+    ret_fd: WasmPtr<WasiFd, M>,
+) -> Result<Errno, WasiError> {
+    wasi_pending_operations_or_sleep!(&mut ctx);
+
+    let env = ctx.data();
+    let memory = unsafe { env.memory_view(&ctx) };
+    let path_len64: u64 = path_len.into();
+    if path_len64 > 1024u64 * 1024u64 {
+        return Ok(Errno::Nametoolong);
+    }
+    if path_len64 == 0 {
+        return Ok(Errno::Noent);
+    }
+    let path_string = unsafe { get_input_str_ok!(&memory, path, path_len) };
+    Span::current().record("path", path_string.as_str());
+
+    let (parent_path, entity_name) = split_parent(&path_string);
+    Span::current().record("name", entity_name.as_str());
+
+    // The descriptor is only opened when the name fits in the buffer
+    let name_len64: u64 = name_len.into();
+    wasi_try_mem_ok!(ret_name_len.write(&memory, wasi_try_ok!(to_offset::<M>(entity_name.len()))));
+    if entity_name.len() as u64 > name_len64 {
+        return Ok(Errno::Range);
+    }
+
+    let fd = wasi_try_ok!(path_open_parent_internal(
+        ctx.data(),
+        dirfd,
+        &parent_path,
+        fs_rights_base,
+        fs_rights_inheriting,
+        fd_flags,
+    ));
+
+    // Opening the parent has the same effect as opening the directory it
+    // resolved to, which is what gets replayed
+    #[cfg(feature = "journal")]
+    if ctx.data().enable_journal {
+        JournalEffector::save_path_open(
+            &mut ctx,
+            fd,
+            dirfd,
+            __WASI_LOOKUP_SYMLINK_FOLLOW,
+            parent_path,
+            Oflags::DIRECTORY,
+            fs_rights_base,
+            fs_rights_inheriting,
+            Fdflags::empty(),
+            fd_flags,
+        )
+        .map_err(|err| {
+            tracing::error!("failed to save open parent event - {}", err);
+            WasiError::Exit(ExitCode::from(Errno::Fault))
+        })?;
+    }
+
+    let env = ctx.data();
+    let memory = unsafe { env.memory_view(&ctx) };
+    Span::current().record("ret_fd", fd);
+    let name_slice =
+        wasi_try_mem_ok!(name.slice(&memory, wasi_try_ok!(to_offset::<M>(entity_name.len()))));
+    wasi_try_mem_ok!(name_slice.write_slice(entity_name.as_bytes()));
+    wasi_try_mem_ok!(ret_fd.write(&memory, fd));
+
+    Ok(Errno::Success)
+}
+
+/// Splits a path into the path of the directory that holds its last
+/// component and the name of that component
+fn split_parent(path: &str) -> (String, String) {
+    let path = Path::new(path);
+    match path.components().next_back() {
+        Some(Component::Normal(name)) => {
+            let mut parent = path.components();
+            parent.next_back();
+            let parent = parent.as_path();
+            let parent = if parent.as_os_str().is_empty() {
+                Path::new(".")
+            } else {
+                parent
+            };
+            (
+                parent.to_string_lossy().into_owned(),
+                name.to_string_lossy().into_owned(),
+            )
+        }
+        _ => (path.to_string_lossy().into_owned(), ".".to_string()),
+    }
+}
+
+fn path_open_parent_internal(
+    env: &WasiEnv,
+    dirfd: WasiFd,
+    parent_path: &str,
+    fs_rights_base: Rights,
+    fs_rights_inheriting: Rights,
+    fd_flags: Fdflagsext,
+) -> Result<WasiFd, Errno> {
+    let state = env.state.deref();
+    let effective_dirfd = if parent_path.starts_with('/') {
+        VIRTUAL_ROOT_FD
+    } else {
+        dirfd
+    };
+
+    let working_dir = state.fs.get_fd(effective_dirfd)?;
+    if !working_dir.inner.rights.contains(Rights::PATH_OPEN) {
+        return Err(Errno::Access);
+    }
+
+    let inode = state
+        .fs
+        .get_inode_at_path(&state.inodes, effective_dirfd, parent_path, true)?;
+    {
+        let guard = inode.read();
+        match guard.deref() {
+            Kind::Root { .. } | Kind::Dir { .. } => {}
+            _ => return Err(Errno::Notdir),
+        }
+    }
+
+    // Directories are never opened with write access, and neither the
+    // directory nor what is opened through it gets more rights than the
+    // directory it was resolved from passes on
+    let rights = fs_rights_base & working_dir.inner.rights_inheriting & !Rights::FD_WRITE;
+    let rights_inheriting = fs_rights_inheriting & working_dir.inner.rights_inheriting;
+    state.fs.create_fd(
+        rights,
+        rights_inheriting,
+        Fdflags::empty(),
+        fd_flags,
+        0,
+        inode,
+    )
+}
@@ -0,0 +1,27 @@
+use super::*;
+use crate::{syscalls::*, utils::perf_counter};
+
+/// ### `perf_counter_frequency()`
+/// Returns the number of times per second that the counter read by
+/// `perf_counter_read` increases, it doesn't change while the process runs.
+/// Output:
+/// - `u64 *ret_frequency`
+///     The frequency of the counter in hertz
+/// Possible Errors:
+/// - `Errno::Perm` unless the environment allows the guest to read the
+///   counter (see [`Capabilities::allow_perf_counters`])
+/// - `Errno::Notcapable` if the sandbox doesn't allow the monotonic clock
+///
+/// [`Capabilities::allow_perf_counters`]: crate::capabilities::Capabilities::allow_perf_counters
+#[instrument(level = "trace", skip_all, ret)]
+pub fn perf_counter_frequency<M: MemorySize>(
+    ctx: FunctionEnvMut<'_, WasiEnv>,
+    ret_frequency: WasmPtr<u64, M>,
+) -> Errno {
+    let env = ctx.data();
+    wasi_try!(check_perf_counters(env));
+    let memory = unsafe { env.memory_view(&ctx) };
+
+    wasi_try_mem!(ret_frequency.write(&memory, perf_counter::frequency()));
+    Errno::Success
+}
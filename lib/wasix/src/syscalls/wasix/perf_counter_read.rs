@@ -0,0 +1,37 @@
+use super::*;
+use crate::{syscalls::*, utils::perf_counter};
+
+/// ### `perf_counter_read()`
+/// Reads the high resolution performance counter of the host, which is
+/// meant for benchmarking code inside the guest. The counter only ever
+/// increases, `perf_counter_frequency` returns how fast.
+/// Output:
+/// - `u64 *ret_counter`
+///     The current value of the counter
+/// Possible Errors:
+/// - `Errno::Perm` unless the environment allows the guest to read the
+///   counter (see [`Capabilities::allow_perf_counters`])
+/// - `Errno::Notcapable` if the sandbox doesn't allow the monotonic clock
+///
+/// [`Capabilities::allow_perf_counters`]: crate::capabilities::Capabilities::allow_perf_counters
+#[instrument(level = "trace", skip_all, ret)]
+pub fn perf_counter_read<M: MemorySize>(
+    ctx: FunctionEnvMut<'_, WasiEnv>,
+    ret_counter: WasmPtr<u64, M>,
+) -> Errno {
+    let env = ctx.data();
+    wasi_try!(check_perf_counters(env));
+    let memory = unsafe { env.memory_view(&ctx) };
+
+    wasi_try_mem!(ret_counter.write(&memory, perf_counter::read()));
+    Errno::Success
+}
+
+/// Checks that the guest may read the performance counter
+pub(crate) fn check_perf_counters(env: &WasiEnv) -> Result<(), Errno> {
+    if !env.capabilities.allow_perf_counters && !env.capabilities.insecure_allow_all {
+        return Err(Errno::Perm);
+    }
+    env.sandbox_policy()
+        .check_clock(Snapshot0Clockid::Monotonic)
+}
@@ -0,0 +1,145 @@
+use wasmer_wasix_types::wasi::{Subclockflags, SubscriptionClock, SubscriptionUnion, Userdata};
+
+use super::*;
+use crate::{state::PollEventSet, syscalls::*};
+
+/// ### `poll_oneoff_deadline()`
+/// Concurrently poll for a set of events until an absolute deadline,
+/// optionally with a signal mask applied for the duration of the wait
+/// (this is the equivalent of `ppoll`)
+///
+/// Inputs:
+/// - `const __wasi_subscription_t *in`
+///   The events to subscribe to
+/// - `__wasi_event_t *out`
+///   The events that have occured
+/// - `u32 nsubscriptions`
+///   The number of subscriptions and the number of events
+/// - `__wasi_option_timestamp_t *deadline`
+///   Absolute time on the monotonic clock after which the poll gives up,
+///   if none then the poll only finishes when an event triggers
+/// - `u64 *sigmask`
+///   Signals to block while waiting, the previous mask is restored before
+///   returning. If null then the signal mask is left untouched
+///
+/// Output:
+/// - `u32 nevents`
+///   The number of events seen, zero if the deadline passed
+#[instrument(level = "trace", skip_all, fields(timeout_ms = field::Empty, fd_guards = field::Empty, seen = field::Empty), ret)]
+pub fn poll_oneoff_deadline<M: MemorySize + 'static>(
+    mut ctx: FunctionEnvMut<'_, WasiEnv>,
+    in_: WasmPtr<Subscription, M>,
+    out_: WasmPtr<Event, M>,
+    nsubscriptions: M::Offset,
+    deadline: WasmPtr<OptionTimestamp, M>,
+    sigmask: WasmPtr<u64, M>,
+    nevents: WasmPtr<M::Offset, M>,
+) -> Result<Errno, WasiError> {
+    wasi_pending_operations_or_sleep!(&mut ctx);
+
+    ctx = wasi_try_ok!(maybe_backoff::<M>(ctx)?);
+    ctx = wasi_try_ok!(maybe_snapshot::<M>(ctx)?);
+
+    ctx.data_mut().poll_seed += 1;
+    let env = ctx.data();
+    let memory = unsafe { env.memory_view(&ctx) };
+
+    let deadline = wasi_try_mem_ok!(deadline.read(&memory));
+    let deadline = match deadline.tag {
+        OptionTag::None => None,
+        OptionTag::Some => Some(deadline.u),
+        _ => return Ok(Errno::Inval),
+    };
+    let sigmask = if !sigmask.is_null() {
+        Some(wasi_try_mem_ok!(sigmask.read(&memory)))
+    } else {
+        None
+    };
+
+    // Without a deadline or any subscriptions this would block forever
+    if nsubscriptions == M::ZERO && deadline.is_none() {
+        return Ok(Errno::Inval);
+    }
+
+    let subscription_array = wasi_try_mem_ok!(in_.slice(&memory, nsubscriptions));
+    let mut subscriptions = Vec::with_capacity(subscription_array.len() as usize + 1);
+    for n in 0..subscription_array.len() {
+        let n = (n + env.poll_seed) % subscription_array.len();
+        let sub = subscription_array.index(n);
+        let s = wasi_try_mem_ok!(sub.read());
+        subscriptions.push((None, PollEventSet::default(), s));
+    }
+
+    // The deadline is added as an extra clock subscription, its userdata is
+    // picked so that it doesn't collide with the ones of the caller and the
+    // event it triggers is not reported back
+    let mut deadline_userdata: Userdata = Userdata::MAX;
+    while subscriptions
+        .iter()
+        .any(|s| s.2.userdata == deadline_userdata)
+    {
+        deadline_userdata -= 1;
+    }
+    if let Some(deadline) = deadline {
+        subscriptions.push((
+            None,
+            PollEventSet::default(),
+            Subscription {
+                userdata: deadline_userdata,
+                type_: Eventtype::Clock,
+                data: SubscriptionUnion {
+                    clock: SubscriptionClock {
+                        clock_id: Clockid::Monotonic,
+                        // A zero timeout means no timeout at all so the earliest
+                        // possible deadline is used instead
+                        timeout: deadline.max(1),
+                        precision: 0,
+                        flags: Subclockflags::SUBSCRIPTION_CLOCK_ABSTIME,
+                    },
+                },
+            },
+        ));
+    }
+
+    // We clear the number of events
+    wasi_try_mem_ok!(nevents.write(&memory, M::ZERO));
+
+    // Function to invoke once the poll is finished
+    let process_events = |ctx: &FunctionEnvMut<'_, WasiEnv>, triggered_events: Vec<Event>| {
+        let env = ctx.data();
+        let memory = unsafe { env.memory_view(&ctx) };
+
+        // Process all the events that were triggered (except for the deadline)
+        let mut events_seen: u32 = 0;
+        let event_array = wasi_try_mem!(out_.slice(&memory, nsubscriptions));
+        for event in triggered_events {
+            if event.type_ == Eventtype::Clock && event.userdata == deadline_userdata {
+                continue;
+            }
+            wasi_try_mem!(event_array.index(events_seen as u64).write(event));
+            events_seen += 1;
+        }
+        let events_seen: M::Offset = events_seen.into();
+        let out_ptr = nevents.deref(&memory);
+        wasi_try_mem!(out_ptr.write(events_seen));
+        Errno::Success
+    };
+
+    // The mask is swapped in before any signals are checked so that a
+    // signal can't slip in between unblocking it and starting the wait
+    let thread = ctx.data().thread.clone();
+    if let Some(mask) = sigmask {
+        thread.set_temporary_signal_mask(mask);
+    }
+
+    // Poll and receive all the events that triggered
+    let ret = poll_oneoff_internal::<M, _>(ctx, subscriptions, process_events);
+
+    // A poll that went into a deep sleep keeps the mask until it is rewound
+    // and finishes. Restoring the mask will also wake the thread if any
+    // signals that were blocked during the wait are still pending.
+    if !thread.is_deep_sleeping() {
+        thread.restore_signal_mask();
+    }
+    ret
+}
@@ -20,7 +20,7 @@ pub fn port_addr_list<M: MemorySize>(
     addrs_ptr: WasmPtr<__wasi_cidr_t, M>,
     naddrs_ptr: WasmPtr<M::Offset, M>,
 ) -> Result<Errno, WasiError> {
-    WasiEnv::do_pending_operations(&mut ctx)?;
+    wasi_pending_operations_or_sleep!(&mut ctx);
 
     let mut env = ctx.data();
     let mut memory = unsafe { env.memory_view(&ctx) };
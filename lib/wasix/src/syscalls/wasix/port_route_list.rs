@@ -16,7 +16,7 @@ pub fn port_route_list<M: MemorySize>(
     routes_ptr: WasmPtr<Route, M>,
     nroutes_ptr: WasmPtr<M::Offset, M>,
 ) -> Result<Errno, WasiError> {
-    WasiEnv::do_pending_operations(&mut ctx)?;
+    wasi_pending_operations_or_sleep!(&mut ctx);
 
     let mut env = ctx.data();
     let mut memory = unsafe { env.memory_view(&ctx) };
@@ -5,7 +5,7 @@ use crate::syscalls::*;
 /// Disconnects from a remote network
 #[instrument(level = "trace", skip_all, ret)]
 pub fn port_unbridge(mut ctx: FunctionEnvMut<'_, WasiEnv>) -> Result<Errno, WasiError> {
-    WasiEnv::do_pending_operations(&mut ctx)?;
+    wasi_pending_operations_or_sleep!(&mut ctx);
 
     wasi_try_ok!(port_unbridge_internal(&mut ctx)?);
 
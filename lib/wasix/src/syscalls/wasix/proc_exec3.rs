@@ -32,7 +32,7 @@ pub fn proc_exec3<M: MemorySize>(
     path: WasmPtr<u8, M>,
     path_len: M::Offset,
 ) -> Result<Errno, WasiError> {
-    WasiEnv::do_pending_operations(&mut ctx)?;
+    wasi_pending_operations_or_sleep!(&mut ctx);
 
     // If we were just restored the stack then we were woken after a deep sleep
     if let Some(exit_code) = unsafe { handle_rewind::<M, i32>(&mut ctx) } {
@@ -124,6 +124,28 @@ pub fn proc_exec3<M: MemorySize>(
         }
     };
 
+    // The new image is compiled and checked before anything of the caller
+    // is given up for it, so that exec-ing a binary that can't run fails
+    // with the caller left as it was (built in commands are run directly)
+    let executable = if ctx.data().bin_factory.commands.exists(name.as_str()) {
+        None
+    } else {
+        let env = ctx.data();
+        let res = __asyncify_light(env, None, async {
+            env.bin_factory
+                .load(name.as_str(), env)
+                .await
+                .map_err(|err| {
+                    warn!(
+                        "failed to execve as the binary could not be loaded - {}",
+                        err
+                    );
+                    exec_err_to_errno(&err)
+                })
+        })?;
+        Some(wasi_try_ok!(res))
+    };
+
     let new_store = ctx.data().runtime.new_store();
 
     // If we are in a vfork we need to first spawn a subprocess of this type
@@ -148,6 +170,7 @@ pub fn proc_exec3<M: MemorySize>(
         let mut wasi_env = *vfork_env;
         wasi_env.owned_handles.push(vfork.handle.clone());
         _prepare_wasi(&mut wasi_env, Some(args), envs, None);
+        wasi_env.state.reset_signal_dispositions();
 
         // Record the stack offsets before we give up ownership of the wasi_env
         let stack_lower = wasi_env.layout.stack_lower;
@@ -171,9 +194,11 @@ pub fn proc_exec3<M: MemorySize>(
 
                     let env = config.take().unwrap();
 
-                    let name_inner = name.clone();
                     __asyncify_light(ctx.data(), None, async {
-                        let ret = bin_factory.spawn(name_inner, env).await;
+                        let Some(executable) = executable else {
+                            return Err(exec_err_to_errno(&err));
+                        };
+                        let ret = bin_factory.spawn_loaded(executable, env).await;
                         match ret {
                             Ok(ret) => {
                                 trace!(%child_pid, "spawned sub-process");
@@ -184,7 +209,7 @@ pub fn proc_exec3<M: MemorySize>(
 
                                 debug!(%child_pid, "process failed with (err={})", err_exit_code);
 
-                                Err(Errno::Noexec)
+                                Err(exec_err_to_errno(&err))
                             }
                         }
                     })
@@ -204,6 +229,9 @@ pub fn proc_exec3<M: MemorySize>(
                 return Ok(e);
             }
             Ok(()) => {
+                // The child now runs the new program on its own memory
+                child_env.process.finish_vfork();
+
                 // We spawned a new process - put the parent env back
                 ctx.data_mut().swap_inner(&mut vfork.env);
                 std::mem::swap(ctx.data_mut(), &mut vfork.env);
@@ -248,8 +276,11 @@ pub fn proc_exec3<M: MemorySize>(
     // on the new module
     else {
         // Prepare the environment
+        // (the signal callback belongs to the instance of the old image, the
+        // new one starts without it and only keeps the ignored signals)
         let mut wasi_env = ctx.data().clone();
         _prepare_wasi(&mut wasi_env, Some(args), envs, None);
+        wasi_env.state.reset_signal_dispositions();
 
         // Get a reference to the runtime
         let bin_factory = ctx.data().bin_factory.clone();
@@ -270,7 +301,10 @@ pub fn proc_exec3<M: MemorySize>(
                 let env = builder.take().unwrap();
 
                 // Spawn a new process with this current execution environment
-                block_on(bin_factory.spawn(name, env))
+                match executable {
+                    Some(executable) => block_on(bin_factory.spawn_loaded(executable, env)),
+                    None => Err(err),
+                }
             }
         };
 
@@ -298,6 +332,7 @@ pub fn proc_exec3<M: MemorySize>(
                         Err(WasiError::Exit(Errno::Unknown.into()))
                     }
                     AsyncifyAction::Unwind => Ok(Errno::Success),
+                    AsyncifyAction::Abort(err) => Ok(err),
                 }
             }
             Err(err) => {
@@ -305,12 +340,21 @@ pub fn proc_exec3<M: MemorySize>(
                     "failed to execve as the process could not be spawned (fork)[0] - {}",
                     err
                 );
-                Ok(Errno::Noexec)
+                Ok(exec_err_to_errno(&err))
             }
         }
     }
 }
 
+/// A script whose interpreter is missing doesn't exist as far as the caller
+/// is concerned, anything else that can't be spawned is not executable
+fn exec_err_to_errno(err: &SpawnError) -> Errno {
+    match err {
+        SpawnError::InterpreterNotFound { .. } => Errno::Noent,
+        _ => Errno::Noexec,
+    }
+}
+
 pub(crate) enum FindExecutableResult {
     Found(String),
     AccessError,
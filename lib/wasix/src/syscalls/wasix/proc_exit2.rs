@@ -14,8 +14,17 @@ pub fn proc_exit2<M: MemorySize>(
     mut ctx: FunctionEnvMut<'_, WasiEnv>,
     code: ExitCode,
 ) -> Result<(), WasiError> {
-    WasiEnv::do_pending_operations(&mut ctx)?;
+    if !WasiEnv::do_pending_operations_or_unwind(&mut ctx)? {
+        return Ok(());
+    }
+
+    proc_exit2_internal::<M>(ctx, code)
+}
 
+pub(crate) fn proc_exit2_internal<M: MemorySize>(
+    mut ctx: FunctionEnvMut<'_, WasiEnv>,
+    code: ExitCode,
+) -> Result<(), WasiError> {
     let Some(mut vfork) = ctx.data_mut().vfork.take() else {
         // Not in a vfork, just exit normally
         return Err(WasiError::Exit(code));
@@ -41,6 +50,7 @@ pub fn proc_exit2<M: MemorySize>(
     let mut child_env = std::mem::replace(ctx.data_mut(), *parent_env);
 
     // Terminate the child process
+    child_env.process.finish_vfork();
     child_env.owned_handles.push(vfork.handle);
     child_env.process.terminate(code);
 
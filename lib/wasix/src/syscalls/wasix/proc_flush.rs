@@ -0,0 +1,22 @@
+use super::*;
+use crate::syscalls::*;
+
+/// ### `proc_flush()`
+/// Flushes the buffered output of every file descriptor of the current
+/// process that can be written to, which makes all the output written so
+/// far reach the backing store (for instance before a checkpoint is taken).
+///
+/// Every file descriptor is flushed even when one of them fails.
+/// Possible Errors:
+/// - The error of the first file descriptor that failed to be flushed
+#[instrument(level = "trace", skip_all, ret)]
+pub fn proc_flush(mut ctx: FunctionEnvMut<'_, WasiEnv>) -> Result<Errno, WasiError> {
+    wasi_pending_operations_or_sleep!(&mut ctx);
+
+    let env = ctx.data();
+    let state = env.state.clone();
+
+    Ok(wasi_try_ok!(__asyncify(&mut ctx, None, async move {
+        state.fs.flush_all().await.map(|_| Errno::Success)
+    })?))
+}
@@ -1,7 +1,7 @@
 use super::*;
 use crate::{
     WasiThreadHandle, WasiVForkAsyncify, capture_store_snapshot,
-    os::task::OwnedTaskStatus,
+    os::task::{OwnedTaskStatus, process::ForkMode},
     runtime::task_manager::{TaskWasm, TaskWasmRunProperties},
     state::context_switching::ContextSwitchingEnvironment,
     syscalls::*,
@@ -9,6 +9,11 @@ use crate::{
 use serde::{Deserialize, Serialize};
 use wasmer::Memory;
 
+/// How long a fork waits for the [`Runtime::on_fork`] callback of the host
+/// to return before it carries on without it, the callback is meant to do
+/// little more than update a table
+const ON_FORK_TIMEOUT: Duration = Duration::from_millis(100);
+
 #[derive(Serialize, Deserialize)]
 pub(crate) struct ForkResult {
     pub pid: Pid,
@@ -25,7 +30,7 @@ pub fn proc_fork<M: MemorySize>(
     mut copy_memory: Bool,
     pid_ptr: WasmPtr<Pid, M>,
 ) -> Result<Errno, WasiError> {
-    WasiEnv::do_pending_operations(&mut ctx)?;
+    wasi_pending_operations_or_sleep!(&mut ctx);
 
     wasi_try_ok!(ctx.data().ensure_static_module().map_err(|_| {
         warn!("process forking not supported for dynamically linked modules");
@@ -42,8 +47,13 @@ pub fn proc_fork<M: MemorySize>(
         return Ok(Errno::Notsup);
     }
 
+    // A fork that was delayed (see below) carries on once it is rewound
+    let delayed = std::mem::take(&mut ctx.data_mut().fork_delayed)
+        && unsafe { handle_rewind_ext::<M, ()>(&mut ctx, HandleRewindType::ResultDriven) }
+            .is_some();
+
     // If we were just restored then we need to return the value instead
-    if let Some(result) = unsafe { handle_rewind::<M, ForkResult>(&mut ctx) } {
+    if !delayed && let Some(result) = unsafe { handle_rewind::<M, ForkResult>(&mut ctx) } {
         if result.pid == 0 {
             trace!("handle_rewind - i am child (ret={})", result.ret);
         } else {
@@ -55,6 +65,23 @@ pub fn proc_fork<M: MemorySize>(
         let memory = unsafe { ctx.data().memory_view(&ctx) };
         wasi_try_mem_ok!(pid_ptr.write(&memory, result.pid));
         return Ok(result.ret);
+    } else if !delayed && let Some(delay) = ctx.data().control_plane.register_fork(ctx.data().pid())
+    {
+        // Processes that fork too often are slowed down to dampen fork storms
+        trace!("delaying the fork by {delay:?}");
+        let tasks = ctx.data().tasks().clone();
+        ctx.data_mut().fork_delayed = true;
+        match __asyncify_with_deep_sleep::<M, _, _>(
+            ctx,
+            async move { tasks.sleep_now(delay).await },
+        )? {
+            AsyncifyAction::Finish(finished, ()) => {
+                ctx = finished;
+                ctx.data_mut().fork_delayed = false;
+            }
+            AsyncifyAction::Unwind => return Ok(Errno::Success),
+            AsyncifyAction::Abort(err) => return Ok(err),
+        }
     }
     trace!(%copy_memory, "capturing");
 
@@ -63,6 +90,9 @@ pub fn proc_fork<M: MemorySize>(
         return Ok(Errno::Notsup);
     }
 
+    // The stack is captured for the child so it must fit in a snapshot
+    wasi_try_ok!(check_snapshot_size(&mut ctx));
+
     // Fork the environment which will copy all the open file handlers
     // and associate a new context but otherwise shares things like the
     // file system interface. The handle to the forked process is stored
@@ -77,6 +107,11 @@ pub fn proc_fork<M: MemorySize>(
     };
     let child_pid = child_env.process.pid();
     let child_finished = child_env.process.finished.clone();
+    child_env.process.set_forked(if copy_memory == Bool::False {
+        ForkMode::VFork
+    } else {
+        ForkMode::Copy
+    });
 
     // We write a zero to the PID before we capture the stack
     // so that this is what will be returned to the child
@@ -84,6 +119,7 @@ pub fn proc_fork<M: MemorySize>(
         let mut inner = ctx.data().process.lock();
         inner.children.push(child_env.process.clone());
     }
+    notify_fork(ctx.data(), child_pid)?;
     let env = ctx.data();
     let memory = unsafe { env.memory_view(&ctx) };
 
@@ -320,7 +356,8 @@ fn run<M: MemorySize>(
                 let respawn = {
                     let tasks = tasks.clone();
                     let rewind_state = deep.rewind;
-                    move |ctx, store, rewind_result| {
+                    move |ctx: WasiFunctionEnv, store: Store, rewind_result| {
+                        ctx.data(&store).process.record_deep_sleep_resume();
                         run::<M>(
                             ctx,
                             store,
@@ -351,3 +388,38 @@ fn run<M: MemorySize>(
     drop(child_handle);
     ret
 }
+
+/// Tells the host about the new process, the callback runs on its own task
+/// so that a callback that never returns can't hang the fork, which only
+/// waits for it on the runtime for a short while
+fn notify_fork(env: &WasiEnv, child_pid: WasiProcessId) -> Result<(), WasiError> {
+    let Some(callback) = env.runtime().on_fork() else {
+        return Ok(());
+    };
+    let parent_pid = env.pid();
+
+    let (tx, rx) = tokio::sync::oneshot::channel();
+    let res = env.tasks().task_dedicated(Box::new(move || {
+        (callback.0)(parent_pid, child_pid);
+        tx.send(()).ok();
+    }));
+    if let Err(err) = res {
+        warn!("failed to invoke the fork callback - {}", err);
+        return Ok(());
+    }
+    let tasks = env.tasks().clone();
+    let returned = __asyncify_light(env, None, async move {
+        tokio::select! {
+            res = rx => Ok(res.is_ok()),
+            _ = tasks.sleep_now(ON_FORK_TIMEOUT) => Ok(false),
+        }
+    })?;
+    if !matches!(returned, Ok(true)) {
+        warn!(
+            parent = parent_pid.raw(),
+            child = child_pid.raw(),
+            "the fork callback did not return in time"
+        );
+    }
+    Ok(())
+}
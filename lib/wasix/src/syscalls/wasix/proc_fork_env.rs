@@ -1,4 +1,4 @@
-use crate::{WasiEnv, WasiError, WasiVFork};
+use crate::{WasiEnv, WasiError, WasiVFork, os::task::process::ForkMode};
 use wasmer::{FunctionEnvMut, Memory, MemorySize, WasmPtr};
 use wasmer_wasix_types::wasi::{Errno, Pid};
 
@@ -30,7 +30,7 @@ pub fn proc_fork_env<M: MemorySize>(
     mut ctx: FunctionEnvMut<'_, WasiEnv>,
     child_pid_ptr: WasmPtr<Pid, M>,
 ) -> Result<Errno, WasiError> {
-    WasiEnv::do_pending_operations(&mut ctx)?;
+    wasi_pending_operations_or_sleep!(&mut ctx);
 
     let env = ctx.data();
 
@@ -52,6 +52,8 @@ pub fn proc_fork_env<M: MemorySize>(
         }
     };
 
+    child_env.process.set_forked(ForkMode::VFork);
+
     // Write the child's PID to the provided pointer
     let memory = unsafe { env.memory_view(&ctx) };
     wasi_try_mem_ok!(child_pid_ptr.write(&memory, child_env.pid().raw()));
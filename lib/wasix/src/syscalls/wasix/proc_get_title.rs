@@ -0,0 +1,40 @@
+use super::*;
+use crate::syscalls::*;
+
+/// ### `proc_get_title()`
+/// Gets the title of the current process (see `proc_set_title`)
+/// Inputs:
+/// - `char *title`
+///     Buffer that receives the title
+/// - `u32 title_len`
+///     The length of the `title` buffer
+/// Output:
+/// - `u32 *ret_title_len`
+///     The length of the title, which is also written when the buffer is
+///     too small (in which case `Errno::Range` is returned)
+#[instrument(level = "trace", skip_all, fields(title = field::Empty), ret)]
+pub fn proc_get_title<M: MemorySize>(
+    mut ctx: FunctionEnvMut<'_, WasiEnv>,
+    title: WasmPtr<u8, M>,
+    title_len: M::Offset,
+    ret_title_len: WasmPtr<M::Offset, M>,
+) -> Result<Errno, WasiError> {
+    wasi_pending_operations_or_sleep!(&mut ctx);
+
+    let env = ctx.data();
+    let memory = unsafe { env.memory_view(&ctx) };
+    let current = env.process.title();
+    Span::current().record("title", current.as_str());
+
+    let title_len64: u64 = title_len.into();
+    wasi_try_mem_ok!(ret_title_len.write(&memory, wasi_try_ok!(to_offset::<M>(current.len()))));
+    if current.len() as u64 > title_len64 {
+        return Ok(Errno::Range);
+    }
+
+    let title_slice =
+        wasi_try_mem_ok!(title.slice(&memory, wasi_try_ok!(to_offset::<M>(current.len()))));
+    wasi_try_mem_ok!(title_slice.write_slice(current.as_bytes()));
+
+    Ok(Errno::Success)
+}
@@ -0,0 +1,28 @@
+use super::*;
+use crate::syscalls::*;
+
+/// ### `proc_getrlimit()`
+/// Returns the soft and hard limit of a resource of the current process
+/// Inputs:
+/// - `RlimitResource resource`
+///     The resource whose limits are returned
+/// Output:
+/// - `Rlimit *ret_rlimit`
+///     The soft and hard limit, `RLIM_INFINITY` means there is no limit
+/// Possible Errors:
+/// - `Errno::Inval` if the resource is not known
+#[instrument(level = "trace", skip_all, fields(?resource), ret)]
+pub fn proc_getrlimit<M: MemorySize>(
+    mut ctx: FunctionEnvMut<'_, WasiEnv>,
+    resource: RlimitResource,
+    ret_rlimit: WasmPtr<Rlimit, M>,
+) -> Result<Errno, WasiError> {
+    wasi_pending_operations_or_sleep!(&mut ctx);
+
+    let env = ctx.data();
+    let rlimit = wasi_try_ok!(env.process.rlimit(resource));
+
+    let memory = unsafe { env.memory_view(&ctx) };
+    wasi_try_mem_ok!(ret_rlimit.write(&memory, rlimit));
+    Ok(Errno::Success)
+}
@@ -2,15 +2,17 @@ use std::task::Waker;
 
 use serde::{Deserialize, Serialize};
 use wasmer::FromToNativeWasmType;
-use wasmer_wasix_types::wasi::{JoinFlags, JoinStatus, JoinStatusType, JoinStatusUnion, OptionPid};
+use wasmer_wasix_types::wasi::{
+    ErrnoSignal, JoinFlags, JoinStatus, JoinStatusType, JoinStatusUnion, OptionPid,
+};
 
 use super::*;
-use crate::{WasiProcess, syscalls::*};
+use crate::{ExitReason, WasiProcess, syscalls::*};
 
 #[derive(Serialize, Deserialize)]
 enum JoinStatusResult {
     Nothing,
-    ExitNormal(WasiProcessId, ExitCode),
+    Exited(WasiProcessId, ExitReason),
     Err(Errno),
 }
 
@@ -19,7 +21,15 @@ enum JoinStatusResult {
 ///
 /// ## Parameters
 ///
-/// * `pid` - Handle of the child process to wait on
+/// * `pid` - Handle of the child process to wait on, or none to wait on
+///   any of the children
+/// * `flags` - `NON_BLOCKING` returns immediately with a `Nothing` status
+///   if the child has not yet finished (like `WNOHANG`)
+/// * `status` - Receives how the child terminated, either normally with an
+///   exit code or by a signal
+///
+/// Returns `Errno::Child` if there is no such child to join (for instance
+/// because it was already joined)
 //#[instrument(level = "trace", skip_all, fields(pid = ctx.data().process.pid().raw()), ret)]
 pub fn proc_join<M: MemorySize + 'static>(
     mut ctx: FunctionEnvMut<'_, WasiEnv>,
@@ -27,7 +37,7 @@ pub fn proc_join<M: MemorySize + 'static>(
     flags: JoinFlags,
     status_ptr: WasmPtr<JoinStatus, M>,
 ) -> Result<Errno, WasiError> {
-    WasiEnv::do_pending_operations(&mut ctx)?;
+    wasi_pending_operations_or_sleep!(&mut ctx);
 
     proc_join_internal(ctx, pid_ptr, flags, status_ptr)
 }
@@ -52,17 +62,28 @@ pub(super) fn proc_join_internal<M: MemorySize + 'static>(
                     tag: JoinStatusType::Nothing,
                     u: JoinStatusUnion { nothing: 0 },
                 },
-                JoinStatusResult::ExitNormal(pid, exit_code) => {
+                JoinStatusResult::Exited(pid, reason) => {
                     let option_pid = OptionPid {
                         tag: OptionTag::Some,
                         pid: pid.raw() as Pid,
                     };
                     pid_ptr.write(&view, option_pid).ok();
 
-                    JoinStatus {
-                        tag: JoinStatusType::ExitNormal,
-                        u: JoinStatusUnion {
-                            exit_normal: exit_code.into(),
+                    match reason {
+                        ExitReason::Exited(exit_code) => JoinStatus {
+                            tag: JoinStatusType::ExitNormal,
+                            u: JoinStatusUnion {
+                                exit_normal: exit_code.into(),
+                            },
+                        },
+                        ExitReason::Signaled(exit_code, signal) => JoinStatus {
+                            tag: JoinStatusType::ExitSignal,
+                            u: JoinStatusUnion {
+                                exit_signal: ErrnoSignal {
+                                    exit_code: exit_code.into(),
+                                    signal,
+                                },
+                            },
                         },
                     }
                 }
@@ -115,6 +136,18 @@ pub(super) fn proc_join_internal<M: MemorySize + 'static>(
 
     // If the ID is maximum then it means wait for any of the children
     let pid = match option_pid {
+        None if flags.contains(JoinFlags::NON_BLOCKING) => {
+            let mut process = ctx.data().process.clone();
+            let status = match process.try_join_any_child() {
+                Ok(Some((pid, reason))) => {
+                    trace!(ret_id = pid.raw(), exit_code = reason.exit_code().raw());
+                    JoinStatusResult::Exited(pid, reason)
+                }
+                Ok(None) => JoinStatusResult::Nothing,
+                Err(err) => JoinStatusResult::Err(err),
+            };
+            return ret_result(ctx, status);
+        }
         None => {
             let mut process = ctx.data_mut().process.clone();
 
@@ -123,10 +156,10 @@ pub(super) fn proc_join_internal<M: MemorySize + 'static>(
             let res = __asyncify_with_deep_sleep::<M, _, _>(ctx, async move {
                 let child_exit = process.join_any_child().await;
                 match child_exit {
-                    Ok(Some((pid, exit_code))) => {
-                        tracing::trace!(%pid, %exit_code, "triggered child join");
-                        trace!(ret_id = pid.raw(), exit_code = exit_code.raw());
-                        JoinStatusResult::ExitNormal(pid, exit_code)
+                    Ok(Some((pid, reason))) => {
+                        tracing::trace!(%pid, ?reason, "triggered child join");
+                        trace!(ret_id = pid.raw(), exit_code = reason.exit_code().raw());
+                        JoinStatusResult::Exited(pid, reason)
                     }
                     Ok(None) => {
                         tracing::trace!("triggered child join (no child)");
@@ -141,6 +174,7 @@ pub(super) fn proc_join_internal<M: MemorySize + 'static>(
             return match res {
                 AsyncifyAction::Finish(ctx, result) => ret_result(ctx, result),
                 AsyncifyAction::Unwind => Ok(Errno::Success),
+                AsyncifyAction::Abort(err) => Ok(err),
             };
         }
         Some(pid) => pid,
@@ -151,25 +185,30 @@ pub(super) fn proc_join_internal<M: MemorySize + 'static>(
 
     // Waiting for a process that is an explicit child will join it
     // meaning it will no longer be a sub-process of the main process
-    let mut process = {
-        let mut inner = ctx.data().process.lock();
-        let process = inner
-            .children
-            .iter()
-            .filter(|c| c.pid == pid)
-            .map(Clone::clone)
-            .next();
-        inner.children.retain(|c| c.pid != pid);
-        process
+    let child = {
+        let inner = ctx.data().process.lock();
+        inner.children.iter().find(|c| c.pid == pid).cloned()
     };
+    let is_child = child.is_some();
 
     // Otherwise it could be the case that we are waiting for a process
-    // that is not a child of this process but may still be running
-    if process.is_none() {
-        process = ctx.data().control_plane.get_process(pid);
-    }
+    // that is not a child of this process but may still be running, a
+    // process that already finished (or was already joined) has nothing
+    // left to wait for
+    let process = child.or_else(|| {
+        ctx.data()
+            .control_plane
+            .get_process(pid)
+            .filter(|process| process.try_join().is_none())
+    });
 
     if let Some(process) = process {
+        let reap = |ctx: &FunctionEnvMut<'_, WasiEnv>| {
+            if is_child {
+                ctx.data().process.lock().children.retain(|c| c.pid != pid);
+            }
+        };
+
         // We can already set the process ID
         wasi_try_mem_ok!(pid_ptr.write(
             &memory,
@@ -181,26 +220,29 @@ pub(super) fn proc_join_internal<M: MemorySize + 'static>(
 
         if flags.contains(JoinFlags::NON_BLOCKING) {
             if let Some(status) = process.try_join() {
+                reap(&ctx);
                 let exit_code = status.unwrap_or_else(|_| Errno::Child.into());
-                ret_result(ctx, JoinStatusResult::ExitNormal(pid, exit_code))
+                let reason = process.exit_reason(exit_code);
+                ret_result(ctx, JoinStatusResult::Exited(pid, reason))
             } else {
                 ret_result(ctx, JoinStatusResult::Nothing)
             }
         } else {
             // Wait for the process to finish
-            let process2 = process.clone();
+            reap(&ctx);
             let res = __asyncify_with_deep_sleep::<M, _, _>(ctx, async move {
                 let exit_code = process.join().await.unwrap_or_else(|_| Errno::Child.into());
                 tracing::trace!(%exit_code, "triggered child join");
-                JoinStatusResult::ExitNormal(pid, exit_code)
+                JoinStatusResult::Exited(pid, process.exit_reason(exit_code))
             })?;
             match res {
                 AsyncifyAction::Finish(ctx, result) => ret_result(ctx, result),
                 AsyncifyAction::Unwind => Ok(Errno::Success),
+                AsyncifyAction::Abort(err) => Ok(err),
             }
         }
     } else {
-        trace!(ret_id = pid.raw(), "status=nothing");
-        ret_result(ctx, JoinStatusResult::Nothing)
+        trace!(ret_id = pid.raw(), "status=child");
+        ret_result(ctx, JoinStatusResult::Err(Errno::Child))
     }
 }
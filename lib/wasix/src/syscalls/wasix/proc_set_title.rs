@@ -0,0 +1,42 @@
+use super::*;
+use crate::syscalls::*;
+
+/// Longest title that a process can give itself
+const MAX_TITLE_LEN: u64 = 4096;
+
+/// ### `proc_set_title()`
+/// Changes the title of the current process, which is how the process is
+/// shown in process listings (this is the equivalent of `setproctitle`).
+///
+/// The title starts out as the name that the process was spawned with and
+/// it is inherited by the processes that it forks.
+/// Inputs:
+/// - `const char *title`
+///     The new title of the process
+/// - `u32 title_len`
+///     The length of the `title` string
+/// Possible Errors:
+/// - `Errno::Nametoolong` if the title is longer than 4096 bytes
+/// - `Errno::Inval` if the title is not valid UTF-8
+#[instrument(level = "trace", skip_all, fields(title = field::Empty), ret)]
+pub fn proc_set_title<M: MemorySize>(
+    mut ctx: FunctionEnvMut<'_, WasiEnv>,
+    title: WasmPtr<u8, M>,
+    title_len: M::Offset,
+) -> Result<Errno, WasiError> {
+    wasi_pending_operations_or_sleep!(&mut ctx);
+
+    let env = ctx.data();
+    let memory = unsafe { env.memory_view(&ctx) };
+    let title_len64: u64 = title_len.into();
+    if title_len64 > MAX_TITLE_LEN {
+        return Ok(Errno::Nametoolong);
+    }
+    let title = unsafe { get_input_str_ok!(&memory, title, title_len) };
+    Span::current().record("title", title.as_str());
+
+    tracing::debug!(pid = %env.pid(), %title, "process title changed");
+    env.process.set_title(title);
+
+    Ok(Errno::Success)
+}
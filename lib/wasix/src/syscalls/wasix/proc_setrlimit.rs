@@ -0,0 +1,40 @@
+use super::*;
+use crate::syscalls::*;
+
+/// ### `proc_setrlimit()`
+/// Changes the soft and hard limit of a resource of the current process,
+/// the limits are inherited by the processes that it forks or spawns and
+/// they are kept when it execs another program
+///
+/// The soft limit is the one that is enforced and it can be raised up to
+/// the hard limit, while the hard limit can only ever be lowered.
+/// Inputs:
+/// - `RlimitResource resource`
+///     The resource whose limits are changed
+/// - `const Rlimit *rlimit`
+///     The new soft and hard limit, `RLIM_INFINITY` means there is no limit
+/// Possible Errors:
+/// - `Errno::Inval` if the resource is not known or the soft limit is above
+///   the hard limit
+/// - `Errno::Perm` if the hard limit would be raised
+/// - `Errno::Notsup` if the resource is `RLIMIT_CPU` or `RLIMIT_AS`, which
+///   are not enforced and thus can only be unlimited
+#[instrument(level = "trace", skip_all, fields(?resource, rlimit = field::Empty), ret)]
+pub fn proc_setrlimit<M: MemorySize>(
+    mut ctx: FunctionEnvMut<'_, WasiEnv>,
+    resource: RlimitResource,
+    rlimit: WasmPtr<Rlimit, M>,
+) -> Result<Errno, WasiError> {
+    wasi_pending_operations_or_sleep!(&mut ctx);
+
+    let env = ctx.data();
+    let memory = unsafe { env.memory_view(&ctx) };
+    let rlimit = wasi_try_mem_ok!(rlimit.read(&memory));
+    Span::current().record("rlimit", format!("{rlimit:?}"));
+
+    wasi_try_ok!(env.process.set_rlimit(resource, rlimit));
+    if resource == RlimitResource::Nofile {
+        env.state.fs.set_fd_limit(rlimit.rlim_cur);
+    }
+    Ok(Errno::Success)
+}
@@ -22,7 +22,7 @@ pub fn proc_signal(
         process.signal_process(sig);
     }
 
-    WasiEnv::do_pending_operations(&mut ctx)?;
+    wasi_pending_operations_or_sleep!(&mut ctx);
 
     Ok(Errno::Success)
 }
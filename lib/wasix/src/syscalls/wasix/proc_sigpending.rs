@@ -0,0 +1,32 @@
+use super::*;
+use crate::syscalls::*;
+
+/// ### `proc_sigpending()`
+/// Gets the signals that were sent to this process but were not delivered
+/// yet, which are the ones that are currently blocked (this is the
+/// equivalent of `sigpending`)
+///
+/// Output:
+/// - `u64 *ret_mask`
+///     The pending signals (one bit per signal number, like the mask
+///     of `poll_oneoff_deadline`)
+#[instrument(level = "trace", skip_all, fields(ret_mask = field::Empty), ret)]
+pub fn proc_sigpending<M: MemorySize>(
+    mut ctx: FunctionEnvMut<'_, WasiEnv>,
+    ret_mask: WasmPtr<u64, M>,
+) -> Result<Errno, WasiError> {
+    wasi_pending_operations_or_sleep!(&mut ctx);
+
+    let env = ctx.data();
+    let memory = unsafe { env.memory_view(&ctx) };
+
+    let mask = env
+        .process
+        .pending_signals()
+        .into_iter()
+        .fold(0u64, |mask, signal| mask | 1u64 << (signal as u8));
+    Span::current().record("ret_mask", format!("{mask:#x}"));
+    wasi_try_mem_ok!(ret_mask.write(&memory, mask));
+
+    Ok(Errno::Success)
+}
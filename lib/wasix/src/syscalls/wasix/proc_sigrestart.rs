@@ -0,0 +1,37 @@
+use super::*;
+use crate::syscalls::*;
+
+/// ### `proc_sigrestart()`
+/// Changes whether the blocking syscalls (such as `fd_read` and `fd_write`)
+/// that a signal interrupts before they transferred anything are restarted
+/// once the signal was handled, instead of failing with `Errno::Intr` (this
+/// is the equivalent of installing the handler with `SA_RESTART`). Waits
+/// such as `poll_oneoff` and `clock_nanosleep` run the handler as soon as
+/// the signal arrives and then carry on waiting for the time that is left.
+///
+/// `SIGKILL` and `SIGSTOP` can't be caught, so they can't restart syscalls
+/// either and changing them fails with `Errno::Inval`.
+///
+/// Inputs:
+/// - `Signal sig`
+///     The signal whose behavior changes
+/// - `Bool restart`
+///     True if the interrupted syscalls are restarted
+#[instrument(level = "trace", skip_all, fields(?sig, ?restart), ret)]
+pub fn proc_sigrestart(
+    mut ctx: FunctionEnvMut<'_, WasiEnv>,
+    sig: Signal,
+    restart: Bool,
+) -> Result<Errno, WasiError> {
+    wasi_pending_operations_or_sleep!(&mut ctx);
+
+    if matches!(sig, Signal::Sigkill | Signal::Sigstop) {
+        return Ok(Errno::Inval);
+    }
+
+    ctx.data()
+        .process
+        .set_signal_restarts(sig, matches!(restart, Bool::True));
+
+    Ok(Errno::Success)
+}
@@ -40,7 +40,7 @@ pub fn proc_spawn<M: MemorySize>(
     working_dir_len: M::Offset,
     ret_handles: WasmPtr<ProcessHandles, M>,
 ) -> Result<Errno, WasiError> {
-    WasiEnv::do_pending_operations(&mut ctx)?;
+    wasi_pending_operations_or_sleep!(&mut ctx);
 
     let env = ctx.data();
     let control_plane = &env.control_plane;
@@ -103,27 +103,6 @@ pub fn proc_spawn_internal(
     stdout: WasiStdioMode,
     stderr: WasiStdioMode,
 ) -> WasiResult<(ProcessHandles, FunctionEnvMut<'_, WasiEnv>)> {
-    let env = ctx.data();
-
-    // Fork the current environment and set the new arguments
-    let (mut child_env, handle) = match ctx.data().fork() {
-        Ok(x) => x,
-        Err(err) => {
-            // TODO: evaluate the appropriate error code, document it in the spec.
-            return Ok(Err(Errno::Access));
-        }
-    };
-    let child_process = child_env.process.clone();
-    if let Some(args) = args {
-        let mut child_state = env.state.fork();
-        child_state.args = std::sync::Mutex::new(args);
-        child_env.state = Arc::new(child_state);
-    }
-
-    // Take ownership of this child
-    ctx.data_mut().owned_handles.push(handle);
-    let env = ctx.data();
-
     // Preopen
     if let Some(preopen) = preopen
         && !preopen.is_empty()
@@ -137,10 +116,25 @@ pub fn proc_spawn_internal(
         return Ok(Err(Errno::Notsup));
     }
 
-    // Change the current directory
-    if let Some(working_dir) = working_dir {
-        child_env.state.fs.set_current_dir(working_dir.as_str());
-    }
+    // Derive the child environment with the new arguments and directory
+    let (mut child_env, handle) = match ctx.data().derive_child(args, None, |child_env| {
+        if let Some(working_dir) = working_dir {
+            child_env.state.fs.set_current_dir(working_dir.as_str());
+        }
+        Ok(())
+    }) {
+        Ok(Ok(x)) => x,
+        Ok(Err(err)) => return Ok(Err(err)),
+        Err(err) => {
+            // TODO: evaluate the appropriate error code, document it in the spec.
+            return Ok(Err(Errno::Access));
+        }
+    };
+    let child_process = child_env.process.clone();
+
+    // Take ownership of this child
+    ctx.data_mut().owned_handles.push(handle);
+    let env = ctx.data();
 
     // Replace the STDIO
     let (stdin, stdout, stderr) = {
@@ -202,7 +196,11 @@ pub fn proc_spawn_internal(
                     fd: u32::MAX,
                 }),
                 _ => {
-                    child_state.fs.close_fd(fd);
+                    // The only failure is a descriptor that is already
+                    // closed, which leaves the child the way it is meant to be
+                    if let Err(err) = child_state.fs.close_fd(fd) {
+                        trace!(%fd, "stdio descriptor of the child already closed - {err}");
+                    }
                     Ok(OptionFd {
                         tag: OptionTag::None,
                         fd: u32::MAX,
@@ -42,7 +42,7 @@ pub fn proc_spawn2<M: MemorySize>(
     path_len: M::Offset,
     ret: WasmPtr<Pid, M>,
 ) -> Result<Errno, WasiError> {
-    WasiEnv::do_pending_operations(&mut ctx)?;
+    wasi_pending_operations_or_sleep!(&mut ctx);
 
     let env = ctx.data();
     let memory = unsafe { ctx.data().memory_view(&ctx) };
@@ -119,18 +119,26 @@ pub fn proc_spawn2<M: MemorySize>(
 
     Span::current().record("full_path", &name);
 
-    // Fork the environment which will copy all the open file handlers
-    // and associate a new context but otherwise shares things like the
-    // file system interface. The handle to the forked process is stored
-    // in the parent process context
-    let (mut child_env, mut child_handle) = match ctx.data().fork() {
-        Ok(p) => p,
-        Err(err) => {
-            debug!("could not fork process: {err}");
-            // TODO: evaluate the appropriate error code, document it in the spec.
-            return Ok(Errno::Perm);
-        }
-    };
+    // Derive the environment of the child which copies all the open file
+    // handlers and associates a new context but otherwise shares things
+    // like the file system interface. The handle to the child process is
+    // stored in the parent process context
+    let (child_env, mut child_handle) =
+        match ctx.data().derive_child(Some(args), envs, |child_env| {
+            _prepare_wasi(child_env, None, None, signals);
+            for fd_op in fd_ops {
+                apply_fd_op(child_env, &memory, &fd_op)?;
+            }
+            Ok(())
+        }) {
+            Ok(Ok(p)) => p,
+            Ok(Err(err)) => return Ok(err),
+            Err(err) => {
+                debug!("could not fork process: {err}");
+                // TODO: evaluate the appropriate error code, document it in the spec.
+                return Ok(Errno::Perm);
+            }
+        };
 
     {
         let mut inner = ctx.data().process.lock();
@@ -145,12 +153,6 @@ pub fn proc_spawn2<M: MemorySize>(
         .record("pid", pid.raw())
         .record("tid", tid.raw());
 
-    _prepare_wasi(&mut child_env, Some(args), envs, signals);
-
-    for fd_op in fd_ops {
-        wasi_try_ok!(apply_fd_op(&mut child_env, &memory, &fd_op));
-    }
-
     // Create the process and drop the context
     let bin_factory = Box::new(child_env.bin_factory.clone());
 
@@ -200,7 +202,11 @@ fn apply_fd_op<M: MemorySize>(
                 trace!("Skipping close FD action for pre-opened FD ({})", op.fd);
                 return Ok(());
             }
-            env.state.fs.close_fd(op.fd)
+            // Closing an FD that isn't open is not an error for spawn actions
+            match env.state.fs.close_fd(op.fd) {
+                Err(Errno::Badf) => Ok(()),
+                res => res,
+            }
         }
         ProcSpawnFdOpName::Dup2 => {
             let target_fd = env.state.fs.get_fd(op.fd).ok();
@@ -0,0 +1,28 @@
+use super::*;
+use crate::syscalls::*;
+
+/// ### `proc_uptime()`
+/// Returns how long ago the current process was created, on the monotonic
+/// clock so that changes to the wall clock don't affect it. A forked
+/// process counts from the fork, `proc_exec` doesn't reset it.
+/// Output:
+/// - `Timestamp *ret_uptime`
+///     The time since the process was created in nanoseconds
+/// Possible Errors:
+/// - `Errno::Notcapable` if the sandbox doesn't allow the monotonic clock
+#[instrument(level = "trace", skip_all, ret)]
+pub fn proc_uptime<M: MemorySize>(
+    ctx: FunctionEnvMut<'_, WasiEnv>,
+    ret_uptime: WasmPtr<Timestamp, M>,
+) -> Errno {
+    let env = ctx.data();
+    wasi_try!(
+        env.sandbox_policy()
+            .check_clock(Snapshot0Clockid::Monotonic)
+    );
+    let memory = unsafe { env.memory_view(&ctx) };
+
+    let uptime = env.process.uptime().as_nanos();
+    wasi_try_mem!(ret_uptime.write(&memory, uptime.try_into().unwrap_or(Timestamp::MAX)));
+    Errno::Success
+}
@@ -0,0 +1,42 @@
+use wasmer_wasix_types::wasi::{JoinFlags, JoinStatus, OptionPid};
+
+use super::*;
+use crate::syscalls::*;
+
+/// ### `proc_wait_any()`
+/// Waits for any of the children of this process to finish and reaps it,
+/// so a supervisor can reap all of its children without waiting on each
+/// of them in turn
+///
+/// ## Parameters
+///
+/// * `flags` - `NON_BLOCKING` returns immediately with a `Nothing` status
+///   if none of the children has finished yet (like `WNOHANG`)
+/// * `pid` - Receives the handle of the child that was reaped, or none if
+///   nothing was reaped
+/// * `status` - Receives how the child terminated, either normally with an
+///   exit code or by a signal
+///
+/// Returns `Errno::Child` if this process has no children left to wait on
+pub fn proc_wait_any<M: MemorySize + 'static>(
+    mut ctx: FunctionEnvMut<'_, WasiEnv>,
+    flags: JoinFlags,
+    pid_ptr: WasmPtr<OptionPid, M>,
+    status_ptr: WasmPtr<JoinStatus, M>,
+) -> Result<Errno, WasiError> {
+    wasi_pending_operations_or_sleep!(&mut ctx);
+
+    // Unlike `proc_join` the pid is only an output, so it starts out as
+    // none which waits on any of the children
+    let env = ctx.data();
+    let memory = unsafe { env.memory_view(&ctx) };
+    wasi_try_mem_ok!(pid_ptr.write(
+        &memory,
+        OptionPid {
+            tag: OptionTag::None,
+            pid: 0,
+        }
+    ));
+
+    proc_join_internal(ctx, pid_ptr, flags, status_ptr)
+}
@@ -3,14 +3,41 @@ use crate::syscalls::*;
 
 /// ### `sched_yield()`
 /// Yields execution of the thread
+///
+/// When all the tasks share a single thread (see
+/// [`VirtualTaskManager::is_single_threaded`]) then the thread goes into a
+/// deep sleep so that the other tasks get a turn before it continues.
 #[instrument(level = "trace", skip_all, ret)]
 pub fn sched_yield<M: MemorySize + 'static>(
     mut ctx: FunctionEnvMut<'_, WasiEnv>,
 ) -> Result<Errno, WasiError> {
-    WasiEnv::do_pending_operations(&mut ctx)?;
+    wasi_pending_operations_or_sleep!(&mut ctx);
+
+    // If we were just restored then the other tasks already had their turn
+    if unsafe { handle_rewind::<M, ()>(&mut ctx) }.is_some() {
+        return Ok(Errno::Success);
+    }
 
     ctx = wasi_try_ok!(maybe_backoff::<M>(ctx)?);
 
+    let env = ctx.data();
+    if env.tasks().is_single_threaded() && env.enable_deep_sleep {
+        let thread = env.thread.clone();
+        thread.set_deep_sleeping(true);
+        env.process.inner.1.notify_one();
+
+        deep_sleep::<M>(
+            ctx,
+            Box::pin(async move {
+                thread.set_deep_sleeping(false);
+                bincode::serde::encode_to_vec((), config::legacy())
+                    .unwrap()
+                    .into()
+            }),
+        )?;
+        return Ok(Errno::Success);
+    }
+
     //trace!("wasi[{}:{}]::sched_yield", ctx.data().pid(), ctx.data().tid());
     thread_sleep_internal::<M>(ctx, 0)
 }
@@ -0,0 +1,42 @@
+use super::*;
+use crate::syscalls::*;
+
+/// The longest hostname that a guest can set (`HOST_NAME_MAX`)
+const MAX_HOSTNAME_LEN: u64 = 64;
+
+/// ### `sethostname()`
+/// Changes the hostname of the environment, for every process that shares
+/// it. This only changes the virtual hostname, never the one of the host.
+///
+/// Inputs:
+/// - `const char *name`
+///     The new hostname
+/// - `size_t name_len`
+///     The length of the new hostname
+///
+/// Returns `Errno::Perm` unless the environment allows the guest to set its
+/// hostname (see [`Capabilities::allow_set_hostname`]) and `Errno::Inval` if
+/// the hostname is longer than 64 bytes.
+///
+/// [`Capabilities::allow_set_hostname`]: crate::capabilities::Capabilities::allow_set_hostname
+#[instrument(level = "trace", skip_all, fields(hostname = field::Empty), ret)]
+pub fn sethostname<M: MemorySize>(
+    ctx: FunctionEnvMut<'_, WasiEnv>,
+    name: WasmPtr<u8, M>,
+    name_len: M::Offset,
+) -> Errno {
+    let env = ctx.data();
+    if !env.capabilities.allow_set_hostname && !env.capabilities.insecure_allow_all {
+        return Errno::Perm;
+    }
+    if name_len.into() > MAX_HOSTNAME_LEN {
+        return Errno::Inval;
+    }
+
+    let memory = unsafe { env.memory_view(&ctx) };
+    let hostname = unsafe { get_input_str!(&memory, name, name_len) };
+    Span::current().record("hostname", hostname.as_str());
+
+    *env.hostname.write().unwrap() = hostname;
+    Errno::Success
+}
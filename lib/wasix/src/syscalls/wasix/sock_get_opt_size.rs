@@ -5,6 +5,9 @@ use crate::syscalls::*;
 /// Retrieve the size of particular option for this socket
 /// Note: This is similar to `getsockopt` in POSIX for SO_RCVBUF
 ///
+/// For `Sockoption::LastError` (SO_ERROR) the error of a non-blocking
+/// connect that failed is returned and cleared, zero means no error
+///
 /// ## Parameters
 ///
 /// * `fd` - Socket descriptor
@@ -27,6 +30,9 @@ pub fn sock_get_opt_size<M: MemorySize>(
             Sockoption::MulticastTtlV4 => {
                 socket.multicast_ttl_v4().map(|a| a as Filesize)
             }
+            Sockoption::LastError => socket
+                .take_last_error()
+                .map(|err| err.map_or(0, |err| err as Filesize)),
             _ => Err(Errno::Inval),
         }
     ));
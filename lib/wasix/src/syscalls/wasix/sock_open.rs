@@ -28,7 +28,8 @@ pub fn sock_open<M: MemorySize>(
     pt: SockProto,
     ro_sock: WasmPtr<WasiFd, M>,
 ) -> Result<Errno, WasiError> {
-    WasiEnv::do_pending_operations(&mut ctx)?;
+    wasi_pending_operations_or_sleep!(&mut ctx);
+    wasi_try_ok!(ctx.data().sandbox_policy().check_network());
 
     // only certain combinations are supported
     match pt {
@@ -87,9 +88,12 @@ pub(crate) fn sock_open_internal(
                     read_timeout: None,
                     accept_timeout: None,
                     connect_timeout: None,
+                    device: None,
+                    last_error: None,
                     handler: None,
                 },
                 addr: None,
+                binding: None,
             }),
         },
         _ => return Ok(Err(Errno::Notsup)),
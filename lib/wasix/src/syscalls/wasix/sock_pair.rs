@@ -2,6 +2,7 @@ use virtual_fs::Pipe;
 
 use super::*;
 use crate::{
+    fs::PassedFds,
     net::socket::{self, SocketProperties},
     syscalls::*,
 };
@@ -19,6 +20,11 @@ use crate::{
 /// is sufficient for anything that doesn't do socket-specific stuff, such
 /// as sending out-of-band packets.
 ///
+/// Whatever is written to one end can be read from the other one, a datagram
+/// pair keeps the boundaries between the writes. Closing one end makes the
+/// other one hang up. Both ends are shared with forked processes, just like
+/// any other descriptor.
+///
 /// Note: This is (supposed to be) similar to `socketpair` in POSIX using PF_INET
 ///
 /// Note 2: This requires hacks in `sock_send` and `sock_recv` as well.
@@ -41,7 +47,7 @@ pub fn sock_pair<M: MemorySize>(
     ro_sock1: WasmPtr<WasiFd, M>,
     ro_sock2: WasmPtr<WasiFd, M>,
 ) -> Result<Errno, WasiError> {
-    WasiEnv::do_pending_operations(&mut ctx)?;
+    wasi_pending_operations_or_sleep!(&mut ctx);
 
     // only certain combinations are supported
     match pt {
@@ -54,13 +60,13 @@ pub fn sock_pair<M: MemorySize>(
         _ => {}
     }
 
-    // FIXME: currently, socket properties are ignored outright, since they
-    // make no sense for the underlying pipe
-    let (fd1, fd2) = wasi_try_ok!(sock_pair_internal(&mut ctx, None, None));
+    // FIXME: currently, socket properties other than the type are ignored
+    // outright, since they make no sense for the underlying pipe
+    let (fd1, fd2) = wasi_try_ok!(sock_pair_internal(&mut ctx, ty, None, None));
 
     #[cfg(feature = "journal")]
     if ctx.data().enable_journal {
-        JournalEffector::save_sock_pair(&mut ctx, fd1, fd2).map_err(|err| {
+        JournalEffector::save_sock_pair(&mut ctx, fd1, fd2, ty).map_err(|err| {
             tracing::error!("failed to save sock_pair event - {}", err);
             WasiError::Exit(ExitCode::from(Errno::Fault))
         })?;
@@ -76,22 +82,33 @@ pub fn sock_pair<M: MemorySize>(
 
 pub(crate) fn sock_pair_internal(
     ctx: &mut FunctionEnvMut<'_, WasiEnv>,
+    ty: Socktype,
     with_fd1: Option<WasiFd>,
     with_fd2: Option<WasiFd>,
 ) -> Result<(WasiFd, WasiFd), Errno> {
     let env = ctx.data();
     let (memory, state, inodes) = unsafe { env.get_memory_and_wasi_state_and_inodes(&ctx, 0) };
-    let (end1, end2) = Pipe::channel();
+    let (end1, end2) = match ty {
+        Socktype::Dgram => Pipe::datagram_channel(),
+        _ => Pipe::channel(),
+    };
+    let (fds1, fds2) = PassedFds::channel();
 
     let inode1 = state.fs.create_inode_with_default_stat(
         inodes,
-        Kind::DuplexPipe { pipe: end1 },
+        Kind::DuplexPipe {
+            pipe: end1,
+            fds: fds1,
+        },
         false,
         "socketpair".into(),
     );
     let inode2 = state.fs.create_inode_with_default_stat(
         inodes,
-        Kind::DuplexPipe { pipe: end2 },
+        Kind::DuplexPipe {
+            pipe: end2,
+            fds: fds2,
+        },
         false,
         "socketpair".into(),
     );
@@ -143,8 +160,8 @@ pub(crate) fn sock_pair_internal(
             inode2,
         )?
     };
-    Span::current().record("end1", fd1);
-    Span::current().record("end2", fd2);
+    Span::current().record("sock1", fd1);
+    Span::current().record("sock2", fd2);
 
     Ok((fd1, fd2))
 }
@@ -1,7 +1,10 @@
 use std::{mem::MaybeUninit, task::Waker};
 
 use super::*;
-use crate::{net::socket::TimeType, syscalls::*};
+use crate::{
+    net::socket::{MAX_DATAGRAM_SIZE, TimeType},
+    syscalls::*,
+};
 
 /// ### `sock_recv()`
 /// Receive a message from a socket.
@@ -15,7 +18,9 @@ use crate::{net::socket::TimeType, syscalls::*};
 ///
 /// ## Return
 ///
-/// Number of bytes stored in ri_data and message flags.
+/// Number of bytes stored in ri_data and message flags. When a datagram is
+/// longer than the buffers the rest of it is discarded and
+/// `__WASI_SOCK_RECV_OUTPUT_DATA_TRUNCATED` is set in the flags.
 #[instrument(level = "trace", skip_all, fields(%sock, nread = field::Empty), ret)]
 pub fn sock_recv<M: MemorySize>(
     mut ctx: FunctionEnvMut<'_, WasiEnv>,
@@ -26,7 +31,7 @@ pub fn sock_recv<M: MemorySize>(
     ro_data_len: WasmPtr<M::Offset, M>,
     ro_flags: WasmPtr<RoFlags, M>,
 ) -> Result<Errno, WasiError> {
-    WasiEnv::do_pending_operations(&mut ctx)?;
+    wasi_pending_operations_or_sleep!(&mut ctx);
 
     let env = ctx.data();
     let fd_entry = wasi_try_ok!(env.state.fs.get_fd(sock));
@@ -56,17 +61,18 @@ pub fn sock_recv<M: MemorySize>(
 
 pub(super) fn sock_recv_internal_handler<M: MemorySize>(
     mut ctx: FunctionEnvMut<'_, WasiEnv>,
-    res: Result<usize, Errno>,
+    res: Result<(usize, RoFlags), Errno>,
     ro_data_len: WasmPtr<M::Offset, M>,
     ro_flags: WasmPtr<RoFlags, M>,
 ) -> Result<Errno, WasiError> {
     let mut ret = Errno::Success;
-    let bytes_read = match res {
-        Ok(bytes_read) => {
+    let (bytes_read, flags) = match res {
+        Ok((bytes_read, flags)) => {
             trace!(
                 %bytes_read,
+                %flags,
             );
-            bytes_read
+            (bytes_read, flags)
         }
         Err(err) => {
             let socket_err = err.name();
@@ -74,7 +80,7 @@ pub(super) fn sock_recv_internal_handler<M: MemorySize>(
                 %socket_err,
             );
             ret = err;
-            0
+            (0, 0)
         }
     };
     Span::current().record("nread", bytes_read);
@@ -83,7 +89,7 @@ pub(super) fn sock_recv_internal_handler<M: MemorySize>(
     let memory = unsafe { env.memory_view(&ctx) };
 
     let bytes_read: M::Offset = wasi_try_ok!(bytes_read.try_into().map_err(|_| Errno::Overflow));
-    wasi_try_mem_ok!(ro_flags.write(&memory, 0));
+    wasi_try_mem_ok!(ro_flags.write(&memory, flags));
     wasi_try_mem_ok!(ro_data_len.write(&memory, bytes_read));
 
     Ok(ret)
@@ -110,7 +116,7 @@ pub(super) fn sock_recv_internal<M: MemorySize>(
     ri_flags: RiFlags,
     ro_data_len: WasmPtr<M::Offset, M>,
     ro_flags: WasmPtr<RoFlags, M>,
-) -> WasiResult<usize> {
+) -> WasiResult<(usize, RoFlags)> {
     let mut env = ctx.data();
     let memory = unsafe { env.memory_view(ctx) };
 
@@ -124,8 +130,44 @@ pub(super) fn sock_recv_internal<M: MemorySize>(
             let iovs_arr = ri_data
                 .slice(&memory, ri_data_len)
                 .map_err(mem_error_to_wasi)?;
-            let iovs_arr = iovs_arr.access().map_err(mem_error_to_wasi)?;
+            let nonblocking = nonblocking_flag || fd.inner.flags.contains(Fdflags::NONBLOCK);
+            let timeout = socket.opt_time(TimeType::ReadTimeout).ok().flatten();
+
+            // A datagram is received as a whole, so it is read into a buffer
+            // that is one byte longer than the guest buffers to find out if
+            // the guest buffers were too small for it
+            if socket.is_datagram() {
+                let mut max_size = 0usize;
+                for iovs in iovs_arr.iter() {
+                    let iovs = iovs.read().map_err(mem_error_to_wasi)?;
+                    max_size = max_size.saturating_add(from_offset::<M>(iovs.buf_len)?);
+                }
+
+                let mut buf = Vec::with_capacity(max_size.min(MAX_DATAGRAM_SIZE) + 1);
+                let amt = socket
+                    .recv(
+                        env.tasks().deref(),
+                        buf.spare_capacity_mut(),
+                        timeout,
+                        nonblocking,
+                        peek,
+                    )
+                    .await?;
+                unsafe {
+                    buf.set_len(amt);
+                }
+
+                let flags = if amt > max_size {
+                    buf.truncate(max_size);
+                    __WASI_SOCK_RECV_OUTPUT_DATA_TRUNCATED
+                } else {
+                    0
+                };
+                let amt = copy_from_slice(&buf, &memory, iovs_arr)?;
+                return Ok((amt, flags));
+            }
 
+            let iovs_arr = iovs_arr.access().map_err(mem_error_to_wasi)?;
             let mut total_read = 0;
             for iovs in iovs_arr.iter() {
                 let mut buf = WasmPtr::<u8, M>::new(iovs.buf)
@@ -134,18 +176,11 @@ pub(super) fn sock_recv_internal<M: MemorySize>(
                     .access()
                     .map_err(mem_error_to_wasi)?;
 
-                let nonblocking = nonblocking_flag || fd.inner.flags.contains(Fdflags::NONBLOCK);
-                let timeout = socket
-                    .opt_time(TimeType::ReadTimeout)
-                    .ok()
-                    .flatten()
-                    .unwrap_or(Duration::from_secs(30));
-
                 let local_read = match socket
                     .recv(
                         env.tasks().deref(),
                         buf.as_mut_uninit(),
-                        Some(timeout),
+                        timeout,
                         nonblocking,
                         peek,
                     )
@@ -160,7 +195,7 @@ pub(super) fn sock_recv_internal<M: MemorySize>(
                     break;
                 }
             }
-            Ok(total_read)
+            Ok((total_read, 0))
         }
     ));
     Ok(Ok(data))
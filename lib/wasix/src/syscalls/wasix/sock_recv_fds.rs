@@ -0,0 +1,140 @@
+use super::*;
+use crate::syscalls::*;
+
+/// ### `sock_recv_fds()`
+/// Receive a message from a socket along with the file descriptors that
+/// were passed with it.
+/// Note: This is similar to `recvmsg` in POSIX with an `SCM_RIGHTS` control
+/// message. Only the sockets that are created with `sock_pair` support it.
+///
+/// When more descriptors arrive than `ro_fds` can hold, the ones that don't
+/// fit are closed and `__WASI_SOCK_RECV_OUTPUT_FDS_TRUNCATED` is set in the
+/// output flags.
+///
+/// The descriptors come with the message they were sent with, the ones of a
+/// message that is read in any other way are closed.
+///
+/// ## Parameters
+///
+/// * `ri_data` - List of scatter/gather vectors to which to store data.
+/// * `ri_flags` - Message flags.
+/// * `ro_fds` - Buffer that receives the new file descriptors
+///
+/// ## Return
+///
+/// Number of bytes stored in ri_data, number of file descriptors stored in
+/// ro_fds and message flags.
+#[instrument(level = "trace", skip_all, fields(%sock, nfds = field::Empty), ret)]
+#[allow(clippy::too_many_arguments)]
+pub fn sock_recv_fds<M: MemorySize>(
+    mut ctx: FunctionEnvMut<'_, WasiEnv>,
+    sock: WasiFd,
+    ri_data: WasmPtr<__wasi_iovec_t<M>, M>,
+    ri_data_len: M::Offset,
+    ri_flags: RiFlags,
+    ro_fds: WasmPtr<WasiFd, M>,
+    ro_fds_len: M::Offset,
+    ro_nfds: WasmPtr<M::Offset, M>,
+    ro_data_len: WasmPtr<M::Offset, M>,
+    ro_flags: WasmPtr<RoFlags, M>,
+) -> Result<Errno, WasiError> {
+    wasi_pending_operations_or_sleep!(&mut ctx);
+
+    let env = ctx.data();
+    let fd_entry = wasi_try_ok!(env.state.fs.get_fd(sock));
+    if !fd_entry.inner.rights.contains(Rights::SOCK_RECV) {
+        return Ok(Errno::Access);
+    }
+    let (pipe, passed_fds) = match fd_entry.inode.read().deref() {
+        Kind::DuplexPipe { pipe, fds } => (pipe.clone(), fds.clone()),
+        _ => return Ok(Errno::Notsup),
+    };
+
+    let ret = sock_recv(
+        ctx.as_mut(),
+        sock,
+        ri_data,
+        ri_data_len,
+        ri_flags,
+        ro_data_len,
+        ro_flags,
+    )?;
+    if ret != Errno::Success {
+        return Ok(ret);
+    }
+
+    let env = ctx.data();
+    let memory = unsafe { env.memory_view(&ctx) };
+
+    // The descriptors arrive together with the messages they were sent with,
+    // the ones whose start was just read
+    let fds = passed_fds.recv(pipe.bytes_read());
+    let capacity: u64 = ro_fds_len.into();
+    let mut truncated = fds.len() as u64 > capacity;
+
+    let ro_fds = wasi_try_mem_ok!(ro_fds.slice(&memory, ro_fds_len));
+    let mut installed = Vec::new();
+    for passed in fds.into_iter().take(capacity as usize) {
+        // Close-on-exec belongs to the sending descriptor only
+        let mut fd = passed.fd();
+        fd.inner.fd_flags = Fdflagsext::empty();
+        fd.is_stdio = false;
+        let description = fd.inner.description.id();
+        // Like `recvmsg`, the descriptors that don't fit under the limit of
+        // the process are closed and the message is flagged as truncated
+        let Ok(fd) = env.state.fs.install_fd(fd) else {
+            truncated = true;
+            break;
+        };
+        wasi_try_mem_ok!(ro_fds.write(installed.len() as u64, fd));
+        installed.push((fd, description));
+    }
+    let nfds = installed.len() as u64;
+    Span::current().record("nfds", nfds);
+
+    let nfds: M::Offset = wasi_try_ok!(nfds.try_into().map_err(|_| Errno::Overflow));
+    wasi_try_mem_ok!(ro_nfds.write(&memory, nfds));
+    let flags = if truncated {
+        __WASI_SOCK_RECV_OUTPUT_FDS_TRUNCATED
+    } else {
+        0
+    };
+    wasi_try_mem_ok!(ro_flags.write(&memory, flags));
+
+    #[cfg(feature = "journal")]
+    if ctx.data().enable_journal {
+        // A received descriptor is recorded as a duplicate of a descriptor of
+        // the same open file description, which the process only has when
+        // the descriptor was passed between its own sockets
+        for (fd, description) in installed {
+            let original = ctx
+                .data()
+                .state
+                .fs
+                .fd_map
+                .read()
+                .unwrap()
+                .iter()
+                .find(|(other, entry)| *other != fd && entry.inner.description.id() == description)
+                .map(|(other, _)| other);
+            match original {
+                Some(original) => {
+                    JournalEffector::save_fd_duplicate(&mut ctx, original, fd, false).map_err(
+                        |err| {
+                            tracing::error!(
+                                "failed to save file descriptor duplicate event - {}",
+                                err
+                            );
+                            WasiError::Exit(ExitCode::from(Errno::Fault))
+                        },
+                    )?;
+                }
+                None => {
+                    tracing::warn!(%fd, "received file descriptor can not be journaled");
+                }
+            }
+        }
+    }
+
+    Ok(Errno::Success)
+}
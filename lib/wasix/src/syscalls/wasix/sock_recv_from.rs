@@ -1,7 +1,10 @@
 use std::{mem::MaybeUninit, task::Waker};
 
 use super::*;
-use crate::{net::socket::TimeType, syscalls::*};
+use crate::{
+    net::socket::{MAX_DATAGRAM_SIZE, TimeType},
+    syscalls::*,
+};
 
 /// ### `sock_recv_from()`
 /// Receive a message and its peer address from a socket.
@@ -15,7 +18,9 @@ use crate::{net::socket::TimeType, syscalls::*};
 ///
 /// ## Return
 ///
-/// Number of bytes stored in ri_data and message flags.
+/// Number of bytes stored in ri_data and message flags. When a datagram is
+/// longer than the buffers the rest of it is discarded and
+/// `__WASI_SOCK_RECV_OUTPUT_DATA_TRUNCATED` is set in the flags.
 #[instrument(level = "trace", skip_all, fields(%sock, nread = field::Empty, peer = field::Empty), ret)]
 pub fn sock_recv_from<M: MemorySize>(
     mut ctx: FunctionEnvMut<'_, WasiEnv>,
@@ -27,7 +32,7 @@ pub fn sock_recv_from<M: MemorySize>(
     ro_flags: WasmPtr<RoFlags, M>,
     ro_addr: WasmPtr<__wasi_addr_port_t, M>,
 ) -> Result<Errno, WasiError> {
-    WasiEnv::do_pending_operations(&mut ctx)?;
+    wasi_pending_operations_or_sleep!(&mut ctx);
 
     sock_recv_from_internal(
         ctx,
@@ -38,6 +43,7 @@ pub fn sock_recv_from<M: MemorySize>(
         ro_data_len,
         ro_flags,
         ro_addr,
+        None,
     )
 }
 
@@ -50,6 +56,7 @@ pub(super) fn sock_recv_from_internal<M: MemorySize>(
     ro_data_len: WasmPtr<M::Offset, M>,
     ro_flags: WasmPtr<RoFlags, M>,
     ro_addr: WasmPtr<__wasi_addr_port_t, M>,
+    ro_ttl: Option<WasmPtr<u32, M>>,
 ) -> Result<Errno, WasiError> {
     let mut env = ctx.data();
     let memory = unsafe { env.memory_view(&ctx) };
@@ -68,83 +75,73 @@ pub(super) fn sock_recv_from_internal<M: MemorySize>(
         max_size
     };
 
-    let (bytes_read, peer) = {
-        if max_size <= 10240 {
+    // Datagrams are received into a buffer that is one byte longer than
+    // the guest buffers to find out if they were too small for them
+    let (bytes_read, peer, ttl, flags) = {
+        if max_size < 10240 {
             let mut buf: [MaybeUninit<u8>; 10240] = unsafe { MaybeUninit::uninit().assume_init() };
-            let writer = &mut buf[..max_size];
-            let (amt, peer) = wasi_try_ok!(__sock_asyncify(
+            let writer = &mut buf[..max_size + 1];
+            let (amt, peer, ttl) = wasi_try_ok!(__sock_asyncify(
                 env,
                 sock,
                 Rights::SOCK_RECV,
                 |socket, fd| async move {
                     let nonblocking =
                         nonblocking_flag || fd.inner.flags.contains(Fdflags::NONBLOCK);
-                    let timeout = socket
-                        .opt_time(TimeType::ReadTimeout)
-                        .ok()
-                        .flatten()
-                        .unwrap_or(Duration::from_secs(30));
+                    let timeout = socket.opt_time(TimeType::ReadTimeout).ok().flatten();
                     socket
-                        .recv_from(
-                            env.tasks().deref(),
-                            writer,
-                            Some(timeout),
-                            nonblocking,
-                            peek,
-                        )
+                        .recv_from_ttl(env.tasks().deref(), writer, timeout, nonblocking, peek)
                         .await
                 },
             ));
 
+            let flags = truncated_flags(amt, max_size);
+            let amt = amt.min(max_size);
             if amt > 0 {
                 let buf: &[MaybeUninit<u8>] = &buf[..amt];
                 let buf: &[u8] = unsafe { std::mem::transmute(buf) };
-                wasi_try_ok!(copy_from_slice(buf, &memory, iovs_arr).map(|_| (amt, peer)))
+                wasi_try_ok!(
+                    copy_from_slice(buf, &memory, iovs_arr).map(|_| (amt, peer, ttl, flags))
+                )
             } else {
-                (amt, peer)
+                (amt, peer, ttl, flags)
             }
         } else {
-            let (data, peer) = wasi_try_ok!(__sock_asyncify(
+            let (data, peer, ttl) = wasi_try_ok!(__sock_asyncify(
                 env,
                 sock,
                 Rights::SOCK_RECV_FROM,
                 |socket, fd| async move {
                     let nonblocking = fd.inner.flags.contains(Fdflags::NONBLOCK);
-                    let timeout = socket
-                        .opt_time(TimeType::ReadTimeout)
-                        .ok()
-                        .flatten()
-                        .unwrap_or(Duration::from_secs(30));
+                    let timeout = socket.opt_time(TimeType::ReadTimeout).ok().flatten();
 
-                    let mut buf = Vec::with_capacity(max_size);
+                    let len = max_size.min(MAX_DATAGRAM_SIZE) + 1;
+                    let mut buf = Vec::with_capacity(len);
                     unsafe {
-                        buf.set_len(max_size);
+                        buf.set_len(len);
                     }
                     socket
-                        .recv_from(
-                            env.tasks().deref(),
-                            &mut buf,
-                            Some(timeout),
-                            nonblocking,
-                            peek,
-                        )
+                        .recv_from_ttl(env.tasks().deref(), &mut buf, timeout, nonblocking, peek)
                         .await
-                        .map(|(amt, addr)| {
+                        .map(|(amt, addr, ttl)| {
                             unsafe {
                                 buf.set_len(amt);
                             }
                             let buf: Vec<u8> = unsafe { std::mem::transmute(buf) };
-                            (buf, addr)
+                            (buf, addr, ttl)
                         })
                 }
             ));
 
-            let data_len = data.len();
+            let flags = truncated_flags(data.len(), max_size);
+            let data_len = data.len().min(max_size);
             if data_len > 0 {
-                let mut reader = &data[..];
-                wasi_try_ok!(read_bytes(reader, &memory, iovs_arr).map(|_| (data_len, peer)))
+                let mut reader = &data[..data_len];
+                wasi_try_ok!(
+                    read_bytes(reader, &memory, iovs_arr).map(|_| (data_len, peer, ttl, flags))
+                )
             } else {
-                (0, peer)
+                (0, peer, ttl, flags)
             }
         }
     };
@@ -155,8 +152,21 @@ pub(super) fn sock_recv_from_internal<M: MemorySize>(
     wasi_try_ok!(write_ip_port(&memory, ro_addr, peer.ip(), peer.port()));
 
     let bytes_read: M::Offset = wasi_try_ok!(bytes_read.try_into().map_err(|_| Errno::Overflow));
-    wasi_try_mem_ok!(ro_flags.write(&memory, 0));
+    wasi_try_mem_ok!(ro_flags.write(&memory, flags));
     wasi_try_mem_ok!(ro_data_len.write(&memory, bytes_read));
+    if let Some(ro_ttl) = ro_ttl {
+        wasi_try_mem_ok!(ro_ttl.write(&memory, ttl.unwrap_or(0) as u32));
+    }
 
     Ok(Errno::Success)
 }
+
+/// Returns the flags that tell the guest whether a datagram of `len` bytes
+/// was truncated to fit in its buffers of `max_size` bytes
+fn truncated_flags(len: usize, max_size: usize) -> RoFlags {
+    if len > max_size {
+        __WASI_SOCK_RECV_OUTPUT_DATA_TRUNCATED
+    } else {
+        0
+    }
+}
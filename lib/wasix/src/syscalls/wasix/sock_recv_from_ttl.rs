@@ -0,0 +1,46 @@
+use super::*;
+use crate::syscalls::*;
+
+/// ### `sock_recv_from_ttl()`
+/// Receive a message, its peer address and the TTL that it arrived with
+/// from a socket.
+/// Note: This is similar to `recvmsg` in POSIX with the `IP_TTL` (or
+/// `IPV6_HOPLIMIT`) control message that `IP_RECVTTL` enables
+///
+/// ## Parameters
+///
+/// * `ri_data` - List of scatter/gather vectors to which to store data.
+/// * `ri_flags` - Message flags.
+///
+/// ## Return
+///
+/// Number of bytes stored in ri_data, message flags, the peer address and
+/// the TTL (or the hop limit for IPv6) of the datagram. The TTL is zero
+/// when it is not known, which is the case unless the `RecvTtl` option was
+/// set on the UDP socket.
+#[instrument(level = "trace", skip_all, fields(%sock, nread = field::Empty, peer = field::Empty), ret)]
+pub fn sock_recv_from_ttl<M: MemorySize>(
+    mut ctx: FunctionEnvMut<'_, WasiEnv>,
+    sock: WasiFd,
+    ri_data: WasmPtr<__wasi_iovec_t<M>, M>,
+    ri_data_len: M::Offset,
+    ri_flags: RiFlags,
+    ro_data_len: WasmPtr<M::Offset, M>,
+    ro_flags: WasmPtr<RoFlags, M>,
+    ro_addr: WasmPtr<__wasi_addr_port_t, M>,
+    ro_ttl: WasmPtr<u32, M>,
+) -> Result<Errno, WasiError> {
+    wasi_pending_operations_or_sleep!(&mut ctx);
+
+    sock_recv_from_internal(
+        ctx,
+        sock,
+        ri_data,
+        ri_data_len,
+        ri_flags,
+        ro_data_len,
+        ro_flags,
+        ro_addr,
+        Some(ro_ttl),
+    )
+}
@@ -15,7 +15,11 @@ use crate::{net::socket::TimeType, syscalls::*};
 ///
 /// ## Return
 ///
-/// Number of bytes transmitted.
+/// Number of bytes transmitted. When the send buffer of a non-blocking socket
+/// only has room for part of the data that part is sent and its length is
+/// returned, `Errno::Again` is only returned when nothing could be sent.
+/// Sending on a connection whose other end is closed raises `SIGPIPE`
+/// (unless the process ignores it) and fails with `Errno::Pipe`.
 #[instrument(level = "trace", skip_all, fields(%fd, nsent = field::Empty), ret)]
 pub fn sock_send<M: MemorySize>(
     mut ctx: FunctionEnvMut<'_, WasiEnv>,
@@ -25,7 +29,7 @@ pub fn sock_send<M: MemorySize>(
     si_flags: SiFlags,
     ret_data_len: WasmPtr<M::Offset, M>,
 ) -> Result<Errno, WasiError> {
-    WasiEnv::do_pending_operations(&mut ctx)?;
+    wasi_pending_operations_or_sleep!(&mut ctx);
 
     let env = ctx.data();
     let fd_entry = wasi_try_ok!(env.state.fs.get_fd(fd));
@@ -51,15 +55,19 @@ pub fn sock_send<M: MemorySize>(
             enable_journal
         )?)
     } else {
-        wasi_try_ok!(sock_send_internal::<M>(
+        match sock_send_internal::<M>(
             &ctx,
             fd,
             FdWriteSource::Iovs {
                 iovs: si_data,
-                iovs_len: si_data_len
+                iovs_len: si_data_len,
             },
             si_flags,
-        )?)
+        )? {
+            Ok(sent) => sent,
+            Err(Errno::Pipe) => return WasiEnv::broken_pipe(&mut ctx),
+            Err(err) => return Ok(err),
+        }
     };
 
     #[cfg(feature = "journal")]
@@ -100,11 +108,7 @@ pub(crate) fn sock_send_internal<M: MemorySize>(
         Rights::SOCK_SEND,
         |socket, fd| async move {
             let nonblocking = nonblocking_flag || fd.inner.flags.contains(Fdflags::NONBLOCK);
-            let timeout = socket
-                .opt_time(TimeType::WriteTimeout)
-                .ok()
-                .flatten()
-                .unwrap_or(Duration::from_secs(30));
+            let timeout = socket.opt_time(TimeType::WriteTimeout).ok().flatten();
 
             match si_data {
                 FdWriteSource::Iovs { iovs, iovs_len } => {
@@ -119,12 +123,7 @@ pub(crate) fn sock_send_internal<M: MemorySize>(
                             .access()
                             .map_err(mem_error_to_wasi)?;
                         let local_sent = match socket
-                            .send(
-                                env.tasks().deref(),
-                                buf.as_ref(),
-                                Some(timeout),
-                                nonblocking,
-                            )
+                            .send(env.tasks().deref(), buf.as_ref(), timeout, nonblocking)
                             .await
                         {
                             Ok(s) => s,
@@ -140,12 +139,7 @@ pub(crate) fn sock_send_internal<M: MemorySize>(
                 }
                 FdWriteSource::Buffer(data) => {
                     socket
-                        .send(
-                            env.tasks().deref(),
-                            data.as_ref(),
-                            Some(timeout),
-                            nonblocking,
-                        )
+                        .send(env.tasks().deref(), data.as_ref(), timeout, nonblocking)
                         .await
                 }
             }
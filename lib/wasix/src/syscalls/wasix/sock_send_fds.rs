@@ -0,0 +1,66 @@
+use super::*;
+use crate::syscalls::*;
+
+/// ### `sock_send_fds()`
+/// Send a message on a socket along with a list of file descriptors.
+/// Note: This is similar to `sendmsg` in POSIX with an `SCM_RIGHTS` control
+/// message. Only the sockets that are created with `sock_pair` support it.
+///
+/// The receiver gets its own descriptors that refer to the same open files,
+/// just as if they were created with `fd_dup`.
+///
+/// ## Parameters
+///
+/// * `si_data` - List of scatter/gather vectors to which to retrieve data
+/// * `si_flags` - Message flags.
+/// * `fds` - The file descriptors to pass along with the data
+///
+/// ## Return
+///
+/// Number of bytes transmitted.
+#[instrument(level = "trace", skip_all, fields(%fd, nfds = field::Empty), ret)]
+#[allow(clippy::too_many_arguments)]
+pub fn sock_send_fds<M: MemorySize>(
+    mut ctx: FunctionEnvMut<'_, WasiEnv>,
+    fd: WasiFd,
+    si_data: WasmPtr<__wasi_ciovec_t<M>, M>,
+    si_data_len: M::Offset,
+    si_flags: SiFlags,
+    fds: WasmPtr<WasiFd, M>,
+    fds_len: M::Offset,
+    ret_data_len: WasmPtr<M::Offset, M>,
+) -> Result<Errno, WasiError> {
+    wasi_pending_operations_or_sleep!(&mut ctx);
+
+    let env = ctx.data();
+    let memory = unsafe { env.memory_view(&ctx) };
+    let fd_entry = wasi_try_ok!(env.state.fs.get_fd(fd));
+    if !fd_entry.inner.rights.contains(Rights::SOCK_SEND) {
+        return Ok(Errno::Access);
+    }
+    let (pipe, passed_fds) = match fd_entry.inode.read().deref() {
+        Kind::DuplexPipe { pipe, fds } => (pipe.clone(), fds.clone()),
+        _ => return Ok(Errno::Notsup),
+    };
+
+    // Every descriptor must be open when it is sent
+    let fds = wasi_try_mem_ok!(fds.slice(&memory, fds_len));
+    let fds = wasi_try_mem_ok!(fds.read_to_vec());
+    Span::current().record("nfds", fds.len());
+    let fds = wasi_try_ok!(
+        fds.into_iter()
+            .map(|fd| env.state.fs.get_fd(fd))
+            .collect::<Result<Vec<_>, _>>()
+    );
+
+    // The descriptors go first so the receiver finds them with the data,
+    // they belong to the message that starts where the stream is now
+    let batch = (!fds.is_empty()).then(|| passed_fds.send(pipe.bytes_written(), fds));
+    let ret = sock_send(ctx, fd, si_data, si_data_len, si_flags, ret_data_len);
+    if let Some(batch) = batch
+        && !matches!(ret, Ok(Errno::Success))
+    {
+        passed_fds.unsend(batch);
+    }
+    ret
+}
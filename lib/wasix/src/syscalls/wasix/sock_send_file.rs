@@ -14,7 +14,9 @@ use crate::{WasiInodes, net::socket::TimeType, syscalls::*};
 ///
 /// ## Return
 ///
-/// Number of bytes transmitted.
+/// Number of bytes transmitted, which is less than `count` when the end of
+/// the file is reached first. Fails with `Errno::Notsock` (before anything
+/// is read from the file) when `sock` is not a socket.
 #[instrument(level = "trace", skip_all, fields(%sock, %in_fd, %offset, %count, nsent = field::Empty), ret)]
 pub fn sock_send_file<M: MemorySize>(
     mut ctx: FunctionEnvMut<'_, WasiEnv>,
@@ -24,7 +26,7 @@ pub fn sock_send_file<M: MemorySize>(
     count: Filesize,
     ret_sent: WasmPtr<Filesize, M>,
 ) -> Result<Errno, WasiError> {
-    WasiEnv::do_pending_operations(&mut ctx)?;
+    wasi_pending_operations_or_sleep!(&mut ctx);
 
     let total_written = wasi_try_ok!(sock_send_file_internal(
         &mut ctx, sock, in_fd, offset, count
@@ -48,6 +50,9 @@ pub fn sock_send_file<M: MemorySize>(
     Ok(Errno::Success)
 }
 
+/// Size of the chunks that the file is read in and sent down the socket
+const SEND_FILE_CHUNK_SIZE: Filesize = 64 * 1024;
+
 #[allow(clippy::await_holding_lock)]
 pub(crate) fn sock_send_file_internal(
     ctx: &mut FunctionEnvMut<'_, WasiEnv>,
@@ -62,14 +67,17 @@ pub(crate) fn sock_send_file_internal(
     let state = env.state.clone();
     let fd_entry = wasi_try_ok_ok!(state.fs.get_fd(in_fd));
 
+    // Nothing is read from the file unless it can be sent
+    wasi_try_ok_ok!(__sock_actor(ctx, sock, Rights::SOCK_SEND, |_, _| Ok(())));
+    env = ctx.data();
+
     // Set the offset of the file
     fd_entry.inner.offset.store(offset, Ordering::Release);
 
     // Enter a loop that will process all the data
     let mut total_written: Filesize = 0;
     while (count > 0) {
-        let sub_count = count.min(4096);
-        count -= sub_count;
+        let sub_count = count.min(SEND_FILE_CHUNK_SIZE);
 
         let fd_flags = fd_entry.inner.flags;
 
@@ -174,7 +182,7 @@ pub(crate) fn sock_send_file_internal(
                                 env = ctx.data();
                                 data
                             }
-                            Kind::DuplexPipe { pipe } => {
+                            Kind::DuplexPipe { pipe, .. } => {
                                 let data = wasi_try_ok_ok!(__asyncify(ctx, None, async move {
                                     // TODO: optimize with MaybeUninit
                                     let mut buf = vec![0u8; sub_count as usize];
@@ -189,7 +197,8 @@ pub(crate) fn sock_send_file_internal(
                             }
                             Kind::PipeTx { .. }
                             | Kind::Epoll { .. }
-                            | Kind::EventNotifications { .. } => {
+                            | Kind::EventNotifications { .. }
+                            | Kind::SignalNotifications { .. } => {
                                 return Ok(Err(Errno::Inval));
                             }
                             Kind::Dir { .. } | Kind::Root { .. } => {
@@ -200,7 +209,7 @@ pub(crate) fn sock_send_file_internal(
                                 // TODO: optimize with MaybeUninit
                                 let mut buf = vec![0u8; sub_count as usize];
 
-                                let mut buf_read = &buffer[offset..];
+                                let mut buf_read = buffer.get(offset..).unwrap_or_default();
                                 let amt = wasi_try_ok_ok!(
                                     std::io::Read::read(&mut buf_read, &mut buf[..])
                                         .map_err(map_io_err)
@@ -221,26 +230,39 @@ pub(crate) fn sock_send_file_internal(
             }
         };
 
-        // Write it down to the socket
-        let tasks = ctx.data().tasks().clone();
-        let bytes_written = wasi_try_ok_ok!(__sock_asyncify_mut(
-            ctx,
-            sock,
-            Rights::SOCK_SEND,
-            |socket, fd| async move {
+        // The file ended before `count` bytes were read
+        if data.is_empty() {
+            break;
+        }
+        count -= data.len() as Filesize;
+
+        // Write it down to the socket, which might take more than one send
+        let mut data = &data[..];
+        while !data.is_empty() {
+            let tasks = ctx.data().tasks().clone();
+            let res = __sock_asyncify_mut(ctx, sock, Rights::SOCK_SEND, |socket, fd| async move {
+                let nonblocking = fd.inner.flags.contains(Fdflags::NONBLOCK);
                 let write_timeout = socket
-                    .opt_time(TimeType::ReadTimeout)
+                    .opt_time(TimeType::WriteTimeout)
                     .ok()
                     .flatten()
                     .unwrap_or(Duration::from_secs(30));
                 socket
-                    .send(tasks.deref(), &data, Some(write_timeout), true)
+                    .send(tasks.deref(), data, Some(write_timeout), nonblocking)
                     .await
-            },
-        ));
-        env = ctx.data();
+            });
+            env = ctx.data();
 
-        total_written += bytes_written as u64;
+            let bytes_written = match res {
+                Ok(0) => return Ok(Ok(total_written)),
+                Ok(amt) => amt,
+                // Whatever was already sent is reported like a short write
+                Err(_) if total_written > 0 => return Ok(Ok(total_written)),
+                Err(err) => return Ok(Err(err)),
+            };
+            data = &data[bytes_written..];
+            total_written += bytes_written as u64;
+        }
     }
 
     Ok(Ok(total_written))
@@ -27,7 +27,7 @@ pub fn sock_send_to<M: MemorySize>(
     addr: WasmPtr<__wasi_addr_port_t, M>,
     ret_data_len: WasmPtr<M::Offset, M>,
 ) -> Result<Errno, WasiError> {
-    WasiEnv::do_pending_operations(&mut ctx)?;
+    wasi_pending_operations_or_sleep!(&mut ctx);
 
     let env = ctx.data();
     let memory = unsafe { env.memory_view(&ctx) };
@@ -40,16 +40,20 @@ pub fn sock_send_to<M: MemorySize>(
     let addr = SocketAddr::new(addr_ip, addr_port);
     Span::current().record("addr", format!("{addr:?}"));
 
-    let bytes_written = wasi_try_ok!(sock_send_to_internal(
+    let bytes_written = match sock_send_to_internal(
         &mut ctx,
         sock,
         FdWriteSource::Iovs {
             iovs: si_data,
-            iovs_len: si_data_len
+            iovs_len: si_data_len,
         },
         si_flags,
         addr,
-    )?);
+    )? {
+        Ok(sent) => sent,
+        Err(Errno::Pipe) => return WasiEnv::broken_pipe(&mut ctx),
+        Err(err) => return Ok(err),
+    };
 
     #[cfg(feature = "journal")]
     if ctx.data().enable_journal {
@@ -110,11 +114,7 @@ pub(crate) fn sock_send_to_internal<M: MemorySize>(
             Rights::SOCK_SEND_TO,
             |socket, fd| async move {
                 let nonblocking = nonblocking_flag || fd.inner.flags.contains(Fdflags::NONBLOCK);
-                let timeout = socket
-                    .opt_time(TimeType::WriteTimeout)
-                    .ok()
-                    .flatten()
-                    .unwrap_or(Duration::from_secs(30));
+                let timeout = socket.opt_time(TimeType::WriteTimeout).ok().flatten();
 
                 match si_data {
                     FdWriteSource::Iovs { iovs, iovs_len } => {
@@ -133,7 +133,7 @@ pub(crate) fn sock_send_to_internal<M: MemorySize>(
                                     env.tasks().deref(),
                                     buf.as_ref(),
                                     addr,
-                                    Some(timeout),
+                                    timeout,
                                     nonblocking,
                                 )
                                 .await
@@ -155,7 +155,7 @@ pub(crate) fn sock_send_to_internal<M: MemorySize>(
                                 env.tasks().deref(),
                                 data.as_ref(),
                                 addr,
-                                Some(timeout),
+                                timeout,
                                 nonblocking,
                             )
                             .await
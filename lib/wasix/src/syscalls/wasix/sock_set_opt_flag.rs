@@ -17,7 +17,7 @@ pub fn sock_set_opt_flag(
     opt: Sockoption,
     flag: Bool,
 ) -> Result<Errno, WasiError> {
-    WasiEnv::do_pending_operations(&mut ctx)?;
+    wasi_pending_operations_or_sleep!(&mut ctx);
 
     let flag = match flag {
         Bool::False => false,
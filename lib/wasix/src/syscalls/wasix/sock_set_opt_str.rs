@@ -0,0 +1,65 @@
+use super::*;
+use crate::syscalls::*;
+
+/// ### `sock_set_opt_str()`
+/// Sets a string option for this socket
+/// Note: This is similar to `setsockopt` in POSIX for SO_BINDTODEVICE
+///
+/// `Sockoption::BindToDevice` binds the socket to the network interface
+/// with the given name, it must be set before the socket is connected or
+/// listens (otherwise `Errno::Inval` is returned). When the network does
+/// not support binding sockets to interfaces `Errno::Notsup` is returned.
+///
+/// ## Parameters
+///
+/// * `fd` - Socket descriptor
+/// * `opt` - Socket option to be set
+/// * `value` - Value of the option
+/// * `value_len` - Length of the value
+#[instrument(level = "trace", skip_all, fields(%sock, %opt, value = field::Empty), ret)]
+pub fn sock_set_opt_str<M: MemorySize>(
+    mut ctx: FunctionEnvMut<'_, WasiEnv>,
+    sock: WasiFd,
+    opt: Sockoption,
+    value: WasmPtr<u8, M>,
+    value_len: M::Offset,
+) -> Result<Errno, WasiError> {
+    wasi_pending_operations_or_sleep!(&mut ctx);
+
+    let env = ctx.data();
+    let memory = unsafe { env.memory_view(&ctx) };
+    let value = unsafe { get_input_str_ok!(&memory, value, value_len) };
+    Span::current().record("value", value.as_str());
+
+    wasi_try_ok!(sock_set_opt_str_internal(&mut ctx, sock, opt, &value)?);
+
+    #[cfg(feature = "journal")]
+    if ctx.data().enable_journal {
+        JournalEffector::save_sock_set_opt_str(&mut ctx, sock, opt, value).map_err(|err| {
+            tracing::error!("failed to save sock_set_opt_str event - {}", err);
+            WasiError::Exit(ExitCode::from(Errno::Fault))
+        })?;
+    }
+
+    Ok(Errno::Success)
+}
+
+pub(crate) fn sock_set_opt_str_internal(
+    ctx: &mut FunctionEnvMut<'_, WasiEnv>,
+    sock: WasiFd,
+    opt: Sockoption,
+    value: &str,
+) -> Result<Result<(), Errno>, WasiError> {
+    if opt != Sockoption::BindToDevice {
+        return Ok(Err(Errno::Inval));
+    }
+
+    let net = ctx.data().net().clone();
+    wasi_try_ok_ok!(__sock_actor_mut(
+        ctx,
+        sock,
+        Rights::empty(),
+        |mut socket, _| socket.set_device(net.deref(), value)
+    ));
+    Ok(Ok(()))
+}
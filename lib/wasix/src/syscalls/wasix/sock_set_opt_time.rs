@@ -16,7 +16,7 @@ pub fn sock_set_opt_time<M: MemorySize>(
     opt: Sockoption,
     time: WasmPtr<OptionTimestamp, M>,
 ) -> Result<Errno, WasiError> {
-    WasiEnv::do_pending_operations(&mut ctx)?;
+    wasi_pending_operations_or_sleep!(&mut ctx);
 
     let env = ctx.data();
     let memory = unsafe { env.memory_view(&ctx) };
@@ -16,7 +16,7 @@ pub fn sock_shutdown(
     sock: WasiFd,
     how: SdFlags,
 ) -> Result<Errno, WasiError> {
-    WasiEnv::do_pending_operations(&mut ctx)?;
+    wasi_pending_operations_or_sleep!(&mut ctx);
 
     let both = __WASI_SHUT_RD | __WASI_SHUT_WR;
     let shutdown = match how {
@@ -0,0 +1,49 @@
+use super::*;
+use crate::syscalls::*;
+
+/// ### `sock_stats()`
+/// Returns the statistics of a socket: the bytes that went through it, how
+/// much is queued to be sent and to be read, and for listening sockets the
+/// number of connections that wait to be accepted
+///
+/// ## Parameters
+///
+/// * `fd` - Socket descriptor
+/// * `ret_stats` - Where the statistics are written
+#[instrument(level = "trace", skip_all, fields(%sock), ret)]
+pub fn sock_stats<M: MemorySize>(
+    mut ctx: FunctionEnvMut<'_, WasiEnv>,
+    sock: WasiFd,
+    ret_stats: WasmPtr<Sockstats, M>,
+) -> Errno {
+    let stats = wasi_try!(__sock_actor(
+        &mut ctx,
+        sock,
+        Rights::empty(),
+        |socket, _| socket.stats()
+    ));
+
+    use crate::net::socket::WasiSocketStatus;
+    let status = match stats.status {
+        WasiSocketStatus::Opening => Sockstatus::Opening,
+        WasiSocketStatus::Opened => Sockstatus::Opened,
+        WasiSocketStatus::Closed => Sockstatus::Closed,
+        WasiSocketStatus::Failed => Sockstatus::Failed,
+    };
+
+    let env = ctx.data();
+    let memory = unsafe { env.memory_view(&ctx) };
+    wasi_try_mem!(ret_stats.write(
+        &memory,
+        Sockstats {
+            bytes_sent: stats.bytes_sent,
+            bytes_received: stats.bytes_received,
+            send_queued: stats.send_queued.unwrap_or_default() as u64,
+            recv_queued: stats.recv_queued.unwrap_or_default() as u64,
+            accept_queued: stats.accept_queued.unwrap_or_default() as u64,
+            status: status as u8,
+            _padding: [0; 7],
+        }
+    ));
+    Errno::Success
+}
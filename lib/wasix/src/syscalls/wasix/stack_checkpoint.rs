@@ -20,7 +20,8 @@ pub fn stack_checkpoint<M: MemorySize>(
     }
     trace!("capturing");
 
-    WasiEnv::do_pending_operations(&mut ctx)?;
+    wasi_pending_operations_or_sleep!(&mut ctx);
+    wasi_try_ok!(check_snapshot_size(&mut ctx));
 
     // Set the return value that we will give back to
     // indicate we are a normal function call that has not yet
@@ -16,7 +16,9 @@ pub fn stack_restore<M: MemorySize>(
     snapshot_ptr: WasmPtr<StackSnapshot, M>,
     mut val: Longsize,
 ) -> Result<(), WasiError> {
-    WasiEnv::do_pending_operations(&mut ctx)?;
+    if !WasiEnv::do_pending_operations_or_unwind(&mut ctx)? {
+        return Ok(());
+    }
 
     // Read the snapshot from the stack
     let env = ctx.data();
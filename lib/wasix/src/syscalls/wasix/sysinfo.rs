@@ -0,0 +1,44 @@
+use super::*;
+use crate::syscalls::*;
+
+/// ### `sysinfo()`
+/// Returns information about the system, the values can be overridden
+/// on the builder and otherwise come from the host
+/// Output:
+/// - `Sysinfo *ret_info`
+///     The number of logical CPUs, the page size, the total and available
+///     memory in bytes and the uptime in nanoseconds
+#[instrument(level = "trace", skip_all, ret)]
+pub fn sysinfo<M: MemorySize>(
+    ctx: FunctionEnvMut<'_, WasiEnv>,
+    ret_info: WasmPtr<Sysinfo, M>,
+) -> Errno {
+    let env = ctx.data();
+    let system_info = &env.system_info;
+
+    let nprocs = match system_info.cpus {
+        Some(cpus) => cpus,
+        None => {
+            let parallelism = wasi_try!(env.tasks().thread_parallelism().map_err(Errno::from));
+            wasi_try!(parallelism.try_into().map_err(|_| Errno::Overflow))
+        }
+    };
+    let (total_memory, available_memory) = system_info.memory_or_default();
+
+    // The uptime advances with the monotonic clock of the host, from where
+    // it was overridden
+    let uptime = wasi_try!(platform_clock_time_get(Snapshot0Clockid::Monotonic, 1))
+        .saturating_add(env.state.uptime_offset);
+
+    let info = Sysinfo {
+        nprocs,
+        page_size: system_info.page_size_or_default(),
+        total_memory,
+        available_memory,
+        uptime: uptime.max(0) as u64,
+    };
+
+    let memory = unsafe { env.memory_view(&ctx) };
+    wasi_try_mem!(ret_info.write(&memory, info));
+    Errno::Success
+}
@@ -15,7 +15,7 @@ pub fn thread_join<M: MemorySize + 'static>(
     mut ctx: FunctionEnvMut<'_, WasiEnv>,
     join_tid: Tid,
 ) -> Result<Errno, WasiError> {
-    WasiEnv::do_pending_operations(&mut ctx)?;
+    wasi_pending_operations_or_sleep!(&mut ctx);
 
     thread_join_internal::<M>(ctx, join_tid)
 }
@@ -42,6 +42,9 @@ pub(super) fn thread_join_internal<M: MemorySize + 'static>(
                 .unwrap_or_else(|a| a)
                 .raw()
         })?;
+        if let AsyncifyAction::Abort(err) = res {
+            return Ok(err);
+        }
         Ok(Errno::Success)
     } else {
         Ok(Errno::Success)
@@ -20,7 +20,7 @@ pub fn thread_signal(
 
     let env = ctx.data();
 
-    WasiEnv::do_pending_operations(&mut ctx)?;
+    wasi_pending_operations_or_sleep!(&mut ctx);
 
     Ok(Errno::Success)
 }
@@ -1,4 +1,4 @@
-use std::task::Waker;
+use wasmer_wasix_types::wasi::Subclockflags;
 
 use super::*;
 use crate::syscalls::*;
@@ -14,35 +14,23 @@ pub fn thread_sleep<M: MemorySize + 'static>(
     mut ctx: FunctionEnvMut<'_, WasiEnv>,
     duration: Timestamp,
 ) -> Result<Errno, WasiError> {
-    WasiEnv::do_pending_operations(&mut ctx)?;
+    wasi_pending_operations_or_sleep!(&mut ctx);
 
     thread_sleep_internal::<M>(ctx, duration)
 }
 
 pub(crate) fn thread_sleep_internal<M: MemorySize + 'static>(
-    mut ctx: FunctionEnvMut<'_, WasiEnv>,
+    ctx: FunctionEnvMut<'_, WasiEnv>,
     duration: Timestamp,
 ) -> Result<Errno, WasiError> {
-    if let Some(()) = unsafe { handle_rewind::<M, _>(&mut ctx) } {
-        return Ok(Errno::Success);
-    }
-
-    ctx = wasi_try_ok!(maybe_backoff::<M>(ctx)?);
-    ctx = wasi_try_ok!(maybe_snapshot::<M>(ctx)?);
-
-    let env = ctx.data();
-
-    #[cfg(feature = "sys-thread")]
-    if duration == 0 {
-        std::thread::yield_now();
-    }
-
-    if duration > 0 {
-        let duration = Duration::from_nanos(duration);
-        let tasks = env.tasks().clone();
-        let res = __asyncify_with_deep_sleep::<M, _, _>(ctx, async move {
-            tasks.sleep_now(duration).await;
-        })?;
-    }
-    Ok(Errno::Success)
+    // The sleep goes on for the whole duration after the handlers of the
+    // signals that arrive in the middle of it have run
+    clock_nanosleep_internal::<M>(
+        ctx,
+        Snapshot0Clockid::Monotonic,
+        Subclockflags::empty(),
+        duration,
+        None,
+        true,
+    )
 }
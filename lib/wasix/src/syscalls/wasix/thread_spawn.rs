@@ -5,18 +5,23 @@ use super::*;
 use crate::journal::JournalEffector;
 use crate::{
     WasiThreadHandle,
-    os::task::thread::WasiMemoryLayout,
+    os::task::{control_plane::ControlPlaneError, thread::WasiMemoryLayout},
     runtime::{
         TaintReason,
         task_manager::{TaskWasm, TaskWasmRunProperties},
     },
     state::context_switching::ContextSwitchingEnvironment,
     syscalls::*,
+    utils::core_dump::write_core_dump,
 };
 
 use wasmer::Memory;
+use wasmer_types::TrapCode;
 use wasmer_wasix_types::wasi::ThreadStart;
 
+/// Size of the guard region that is kept below the stack of a thread
+const THREAD_STACK_GUARD_SIZE: u64 = 4096;
+
 /// ### `thread_spawn()`
 /// Creates a new thread by spawning that shares the same
 /// memory address space, file handles and main event loops.
@@ -36,7 +41,7 @@ pub fn thread_spawn_v2<M: MemorySize>(
     start_ptr: WasmPtr<ThreadStart<M>, M>,
     ret_tid: WasmPtr<Tid, M>,
 ) -> Result<Errno, WasiError> {
-    WasiEnv::do_pending_operations(&mut ctx)?;
+    wasi_pending_operations_or_sleep!(&mut ctx);
 
     // Create the thread
     let tid = wasi_try_ok!(thread_spawn_internal_from_wasi(&mut ctx, start_ptr));
@@ -69,10 +74,18 @@ pub fn thread_spawn_internal_from_wasi<M: MemorySize>(
     let layout = {
         let start: ThreadStart<M> = start_ptr.read(&memory).map_err(mem_error_to_wasi)?;
         let stack_upper: u64 = start.stack_upper.into();
-        let stack_size: u64 = start.stack_size.into();
-        let guard_size: u64 = start.guard_size.into();
+        let mut stack_size: u64 = start.stack_size.into();
+        let mut guard_size: u64 = start.guard_size.into();
         let tls_base: u64 = start.tls_base.into();
-        let stack_lower = stack_upper - stack_size;
+        let mut stack_lower = stack_upper - stack_size;
+
+        // When the guest did not leave a guard region below the stack then
+        // the bottom page of the stack itself is given up for it
+        if guard_size == 0 && stack_size >= 2 * THREAD_STACK_GUARD_SIZE {
+            stack_lower += THREAD_STACK_GUARD_SIZE;
+            stack_size -= THREAD_STACK_GUARD_SIZE;
+            guard_size = THREAD_STACK_GUARD_SIZE;
+        }
 
         WasiMemoryLayout {
             stack_upper,
@@ -94,6 +107,10 @@ pub fn thread_spawn_internal_from_wasi<M: MemorySize>(
     };
     let mut thread_handle = match env.process.new_thread(layout.clone(), thread_start) {
         Ok(h) => Arc::new(h),
+        Err(ControlPlaneError::TaskLimitReached { max }) => {
+            debug!(max, "too many threads to create another one");
+            return Err(Errno::Again);
+        }
         Err(err) => {
             error!(
                 stack_base = layout.stack_lower,
@@ -270,8 +287,17 @@ fn handle_thread_result(
                 .on_taint(TaintReason::DlSymbolResolutionFailed(symbol.clone()));
             Ok(Some(ExitCode::from(129)))
         }
+        Err(err) if is_stack_overflow(env, store, &err) => {
+            warn!(%tid, %pid, "thread overflowed its stack - {err}");
+            write_core_dump(env, store, &err);
+            let env = env.data(&store);
+            write_stack_overflow_message(env);
+            env.runtime.on_taint(TaintReason::RuntimeError(err));
+            Ok(Some(ExitCode::from(Errno::Fault)))
+        }
         Err(err) => {
             eprintln!("Thread {tid} of process {pid} failed with runtime error: {err}");
+            write_core_dump(env, store, &err);
             env.data(&store)
                 .runtime
                 .on_taint(TaintReason::RuntimeError(err));
@@ -280,6 +306,31 @@ fn handle_thread_result(
     }
 }
 
+/// Tells the guest on its stderr that one of its threads failed because
+/// it ran out of stack
+pub(crate) fn write_stack_overflow_message(env: &WasiEnv) {
+    if let Ok(mut stderr) = WasiInodes::stderr_mut(&env.state.fs.fd_map) {
+        let msg = format!(
+            "Thread {} of process {} failed: stack overflow\n",
+            env.tid(),
+            env.pid()
+        );
+        block_on(stderr.write_all(msg.as_bytes())).ok();
+    }
+}
+
+/// Checks if a thread failed because it ran out of stack, which is either
+/// the native call stack or the stack it keeps in its linear memory
+fn is_stack_overflow(env: &WasiFunctionEnv, store: &mut Store, err: &RuntimeError) -> bool {
+    if err.clone().to_trap() == Some(TrapCode::StackOverflow) {
+        return true;
+    }
+    let mut ctx = env.env.clone().into_mut(store);
+    let layout = ctx.data().layout.clone();
+    unsafe { get_memory_stack_pointer(&mut ctx) }
+        .is_ok_and(|stack_pointer| layout.is_stack_overflow(stack_pointer))
+}
+
 /// Calls the module
 fn call_module<M: MemorySize>(
     mut ctx: WasiFunctionEnv,
@@ -304,6 +355,12 @@ fn call_module<M: MemorySize>(
         if res != Errno::Success {
             return;
         }
+    } else {
+        // A new thread starts at the top of its stack, most guests set this
+        // up themselves but the stack guard must not trip for those that don't
+        let mut ctx = ctx.env.clone().into_mut(&mut store);
+        let (env, mut store) = ctx.data_and_store_mut();
+        set_memory_stack_offset(env, &mut store, 0).ok();
     }
 
     // Now invoke the module
@@ -315,7 +372,9 @@ fn call_module<M: MemorySize>(
         let rewind = deep.rewind;
         let respawn = {
             let tasks = tasks.clone();
-            move |ctx, store, trigger_res| {
+            move |ctx: WasiFunctionEnv, store: Store, trigger_res| {
+                ctx.data(&store).process.record_deep_sleep_resume();
+
                 // Call the thread
                 call_module::<M>(
                     ctx,
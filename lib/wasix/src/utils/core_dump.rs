@@ -0,0 +1,117 @@
+use bincode::config;
+use wasmer::{RuntimeError, Store};
+
+use super::store::{StoreSnapshot, capture_store_snapshot};
+use crate::{WasiFunctionEnv, syscalls::get_memory_stack_pointer};
+
+/// Version of the core dump format, bumped whenever [`CoreDump`] changes
+pub const CORE_DUMP_VERSION: u32 = 1;
+
+/// Post-mortem state of a process that trapped, which is handed to the
+/// callback of [`Runtime::on_core_dump`](crate::Runtime::on_core_dump).
+///
+/// The core dump is encoded with `bincode` (legacy configuration), the
+/// same encoding that is used for a [`StoreSnapshot`]. An inspector loads
+/// it again with [`CoreDump::deserialize`] after checking the `version`.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
+pub struct CoreDump {
+    /// Version of the format (see [`CORE_DUMP_VERSION`])
+    pub version: u32,
+    /// Process that trapped
+    pub pid: u32,
+    /// Thread that trapped
+    pub tid: u32,
+    /// Description of the trap
+    pub message: String,
+    /// Call stack of the thread at the time of the trap, innermost frame first
+    pub frames: Vec<CoreDumpFrame>,
+    /// Values of the globals of the instance
+    pub store: StoreSnapshot,
+    /// Stack pointer of the thread in the linear memory, if it could be read
+    pub stack_pointer: Option<u64>,
+    /// Lowest address of the stack of the thread in the linear memory
+    pub stack_lower: u64,
+    /// Highest address of the stack of the thread in the linear memory
+    pub stack_upper: u64,
+    /// Contents of the linear memory
+    pub memory: Vec<u8>,
+}
+
+/// A frame of the call stack of a [`CoreDump`]
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct CoreDumpFrame {
+    pub module_name: String,
+    pub func_index: u32,
+    pub func_name: Option<String>,
+    /// Offset of the instruction within the function
+    pub func_offset: u64,
+    /// Offset of the instruction within the module
+    pub module_offset: u64,
+}
+
+impl CoreDump {
+    pub fn serialize(&self) -> Result<Vec<u8>, bincode::error::EncodeError> {
+        bincode::serde::encode_to_vec(self, config::legacy())
+    }
+
+    pub fn deserialize(data: &[u8]) -> Result<Self, bincode::error::DecodeError> {
+        bincode::serde::decode_from_slice(data, config::legacy()).map(|(ret, _)| ret)
+    }
+}
+
+/// Captures the state of a thread that failed with `err`
+pub fn capture_core_dump(env: &WasiFunctionEnv, store: &mut Store, err: &RuntimeError) -> CoreDump {
+    let frames = err
+        .trace()
+        .iter()
+        .map(|frame| CoreDumpFrame {
+            module_name: frame.module_name().to_string(),
+            func_index: frame.func_index(),
+            func_name: frame.function_name().map(|name| name.to_string()),
+            func_offset: frame.func_offset() as u64,
+            module_offset: frame.module_offset() as u64,
+        })
+        .collect();
+
+    let (pid, tid, layout) = {
+        let env = env.data(store);
+        (env.pid().raw(), env.tid().raw(), env.layout.clone())
+    };
+    let stack_pointer = {
+        let mut ctx = env.env.clone().into_mut(store);
+        unsafe { get_memory_stack_pointer(&mut ctx) }.ok()
+    };
+    let memory = env
+        .data(store)
+        .try_memory_view(store)
+        .and_then(|view| view.copy_to_vec().ok())
+        .unwrap_or_default();
+
+    CoreDump {
+        version: CORE_DUMP_VERSION,
+        pid,
+        tid,
+        message: err.message(),
+        frames,
+        store: capture_store_snapshot(store),
+        stack_pointer,
+        stack_lower: layout.stack_lower,
+        stack_upper: layout.stack_upper,
+        memory,
+    }
+}
+
+/// Hands a core dump of the failed thread to the runtime, if it asked for them
+pub(crate) fn write_core_dump(env: &WasiFunctionEnv, store: &mut Store, err: &RuntimeError) {
+    let Some(callback) = env.data(store).runtime.on_core_dump() else {
+        return;
+    };
+    let core_dump = capture_core_dump(env, store, err);
+    tracing::debug!(
+        pid = core_dump.pid,
+        tid = core_dump.tid,
+        memory = core_dump.memory.len(),
+        "writing a core dump"
+    );
+    (callback.0)(&core_dump);
+}
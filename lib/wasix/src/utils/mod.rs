@@ -1,5 +1,7 @@
+pub mod core_dump;
 mod dummy_waker;
 mod owned_mutex_guard;
+pub(crate) mod perf_counter;
 pub mod store;
 pub mod thread_local_executor;
 mod thread_parker;
@@ -0,0 +1,99 @@
+//! High resolution performance counter that backs `perf_counter_read`.
+//!
+//! On x86_64 hosts with an invariant TSC the counter is the time stamp
+//! counter of the CPU, its frequency is measured against the monotonic
+//! clock once (ahead of time for the environments that may read the counter,
+//! see [`calibrate_ahead`]). Everywhere else the counter is the monotonic
+//! clock in nanoseconds.
+
+use std::sync::{
+    OnceLock,
+    atomic::{AtomicU64, Ordering},
+};
+
+use wasmer_wasix_types::wasi::Snapshot0Clockid;
+
+use crate::{VirtualTaskManager, syscalls::platform_clock_time_get};
+
+const NANOS_PER_SECOND: u64 = 1_000_000_000;
+
+/// The frequency of the time stamp counter, `None` when it can't be used
+static TSC_FREQUENCY: OnceLock<Option<u64>> = OnceLock::new();
+
+/// The highest value that was handed out, the counter never goes back even
+/// when it is read on different CPUs
+static LAST: AtomicU64 = AtomicU64::new(0);
+
+/// Reads the counter, it increases [`frequency`] times per second
+pub(crate) fn read() -> u64 {
+    let now = match tsc_frequency() {
+        Some(_) => read_tsc(),
+        None => monotonic_nanos(),
+    };
+    LAST.fetch_max(now, Ordering::AcqRel).max(now)
+}
+
+/// Number of ticks of the counter per second
+pub(crate) fn frequency() -> u64 {
+    tsc_frequency().unwrap_or(NANOS_PER_SECOND)
+}
+
+/// Measures the frequency of the time stamp counter on a dedicated task,
+/// as that takes a few milliseconds which the first `perf_counter_read` or
+/// `perf_counter_frequency` would otherwise spend on the syscall thread (it
+/// only waits for what is left when the measurement is still running)
+pub(crate) fn calibrate_ahead(tasks: &dyn VirtualTaskManager) {
+    if TSC_FREQUENCY.get().is_some() {
+        return;
+    }
+    if let Err(err) = tasks.task_dedicated(Box::new(|| {
+        tsc_frequency();
+    })) {
+        tracing::debug!("failed to calibrate the performance counter ahead of time - {err}");
+    }
+}
+
+fn monotonic_nanos() -> u64 {
+    platform_clock_time_get(Snapshot0Clockid::Monotonic, 1).unwrap_or_default() as u64
+}
+
+fn tsc_frequency() -> Option<u64> {
+    *TSC_FREQUENCY.get_or_init(calibrate_tsc)
+}
+
+#[cfg(target_arch = "x86_64")]
+fn read_tsc() -> u64 {
+    unsafe { core::arch::x86_64::_rdtsc() }
+}
+
+#[cfg(not(target_arch = "x86_64"))]
+fn read_tsc() -> u64 {
+    unreachable!("the time stamp counter is only used on x86_64")
+}
+
+#[cfg(target_arch = "x86_64")]
+fn calibrate_tsc() -> Option<u64> {
+    use core::arch::x86_64::__cpuid;
+
+    // Only a counter that ticks at a constant rate (and keeps ticking in
+    // the deeper sleep states) is of any use
+    let max_leaf = unsafe { __cpuid(0x8000_0000) }.eax;
+    if max_leaf < 0x8000_0007 || unsafe { __cpuid(0x8000_0007) }.edx & (1 << 8) == 0 {
+        return None;
+    }
+
+    let start_nanos = monotonic_nanos();
+    let start_ticks = read_tsc();
+    std::thread::sleep(std::time::Duration::from_millis(10));
+    let nanos = monotonic_nanos().saturating_sub(start_nanos);
+    let ticks = read_tsc().saturating_sub(start_ticks);
+    if nanos == 0 || ticks == 0 {
+        return None;
+    }
+    Some((ticks as u128 * NANOS_PER_SECOND as u128 / nanos as u128) as u64)
+}
+
+#[cfg(not(target_arch = "x86_64"))]
+fn calibrate_tsc() -> Option<u64> {
+    None
+}
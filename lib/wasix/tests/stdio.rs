@@ -1,9 +1,16 @@
-use virtual_fs::{AsyncReadExt, AsyncWriteExt};
+use std::{
+    io,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+};
+
+use virtual_fs::{AsyncRead, AsyncReadExt, AsyncSeek, AsyncWrite, AsyncWriteExt, VirtualFile};
 use virtual_mio::block_on;
 use wasmer::Module;
 use wasmer_types::ModuleHash;
 use wasmer_wasix::{
-    Pipe,
+    AbsentStdin, Pipe, StreamMode,
     runners::wasi::{RuntimeOrEngine, WasiRunner},
 };
 
@@ -13,15 +20,50 @@ mod sys {
         super::test_stdout();
     }
 
+    #[test]
+    fn test_stdout_lines() {
+        super::test_stdout_lines();
+    }
+
+    #[test]
+    fn test_combined_output() {
+        super::test_combined_output();
+    }
+
     #[test]
     fn test_stdin() {
         super::test_stdin();
     }
 
+    #[test]
+    fn test_absent_stdin_eof() {
+        super::test_absent_stdin_eof();
+    }
+
+    #[test]
+    fn test_absent_stdin_pending() {
+        super::test_absent_stdin_pending();
+    }
+
+    #[test]
+    fn test_stdio_text_mode() {
+        super::test_stdio_text_mode();
+    }
+
     #[test]
     fn test_env() {
         super::test_env();
     }
+
+    #[test]
+    fn test_stdout_partial_writev() {
+        super::test_stdout_partial_writev();
+    }
+
+    #[test]
+    fn test_stdout_backpressure() {
+        super::test_stdout_backpressure();
+    }
 }
 
 // #[cfg(feature = "js")]
@@ -111,6 +153,294 @@ fn test_stdout() {
     assert_eq!(stdout_as_str, "hello world");
 }
 
+fn test_stdout_lines() {
+    #[cfg(not(target_arch = "wasm32"))]
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .unwrap();
+    #[cfg(not(target_arch = "wasm32"))]
+    let handle = runtime.handle().clone();
+    #[cfg(not(target_arch = "wasm32"))]
+    let _guard = handle.enter();
+
+    let engine = wasmer::Engine::default();
+    let module = Module::new(
+        &engine,
+        br#"
+    (module
+        (import "wasi_unstable" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        ;; The last line has no newline, it is only delivered when the process exits
+        (data (i32.const 16) "first\nsec")
+        (data (i32.const 32) "ond\nthird")
+
+        (func $main (export "_start")
+            ;; Two writes that split the second line in half
+            (i32.store (i32.const 0) (i32.const 16))
+            (i32.store (i32.const 4) (i32.const 9))
+            (i32.store (i32.const 8) (i32.const 32))
+            (i32.store (i32.const 12) (i32.const 9))
+
+            (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 48))
+            drop
+            (call $fd_write (i32.const 1) (i32.const 8) (i32.const 1) (i32.const 48))
+            drop
+        )
+    )
+    "#,
+    )
+    .unwrap();
+
+    let (stdout_tx, mut stdout_rx) = Pipe::channel();
+    let lines = Arc::new(Mutex::new(Vec::new()));
+
+    {
+        let lines = lines.clone();
+        let mut runner = WasiRunner::new();
+        runner
+            .with_stdout(Box::new(stdout_tx))
+            .with_stdout_lines(move |line| {
+                lines
+                    .lock()
+                    .unwrap()
+                    .push(String::from_utf8_lossy(line).into_owned())
+            });
+
+        runner
+            .run_wasm(
+                RuntimeOrEngine::Engine(engine),
+                "command-name",
+                module,
+                ModuleHash::random(),
+            )
+            .unwrap();
+    }
+
+    assert_eq!(*lines.lock().unwrap(), vec!["first", "second", "third"]);
+
+    // The raw output is still passed through untouched
+    let mut stdout_str = String::new();
+    block_on(stdout_rx.read_to_string(&mut stdout_str)).unwrap();
+    assert_eq!(stdout_str, "first\nsecond\nthird");
+}
+
+fn test_combined_output() {
+    #[cfg(not(target_arch = "wasm32"))]
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .unwrap();
+    #[cfg(not(target_arch = "wasm32"))]
+    let handle = runtime.handle().clone();
+    #[cfg(not(target_arch = "wasm32"))]
+    let _guard = handle.enter();
+
+    let engine = wasmer::Engine::default();
+    let module = Module::new(
+        &engine,
+        br#"
+    (module
+        (import "wasi_unstable" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        (data (i32.const 16) "out 1\nerr 1\nout 2\nerr 2\nout 3\n")
+
+        ;; Writes the 6 bytes at `ptr` to `fd`
+        (func $write (param $fd i32) (param $ptr i32)
+            (i32.store (i32.const 0) (local.get $ptr))
+            (i32.store (i32.const 4) (i32.const 6))
+            (call $fd_write (local.get $fd) (i32.const 0) (i32.const 1) (i32.const 8))
+            drop
+        )
+
+        (func $main (export "_start")
+            (call $write (i32.const 1) (i32.const 16))
+            (call $write (i32.const 2) (i32.const 22))
+            (call $write (i32.const 1) (i32.const 28))
+            (call $write (i32.const 2) (i32.const 34))
+            (call $write (i32.const 1) (i32.const 40))
+        )
+    )
+    "#,
+    )
+    .unwrap();
+
+    let (output_tx, mut output_rx) = Pipe::channel();
+
+    {
+        let mut runner = WasiRunner::new();
+        runner.with_combined_output(Box::new(output_tx));
+
+        runner
+            .run_wasm(
+                RuntimeOrEngine::Engine(engine),
+                "command-name",
+                module,
+                ModuleHash::random(),
+            )
+            .unwrap();
+    }
+
+    let mut output = String::new();
+    block_on(output_rx.read_to_string(&mut output)).unwrap();
+    assert_eq!(output, "out 1\nerr 1\nout 2\nerr 2\nout 3\n");
+}
+
+/// Stdout that only has room for a fixed number of bytes, after which
+/// writes fail as if the disk was full
+#[derive(Debug, Clone)]
+struct LimitedFile {
+    data: Arc<Mutex<Vec<u8>>>,
+    capacity: usize,
+}
+
+impl AsyncSeek for LimitedFile {
+    fn start_seek(self: Pin<&mut Self>, _position: io::SeekFrom) -> io::Result<()> {
+        Ok(())
+    }
+    fn poll_complete(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<u64>> {
+        Poll::Ready(Ok(0))
+    }
+}
+
+impl AsyncWrite for LimitedFile {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let mut data = self.data.lock().unwrap();
+        let remaining = self.capacity - data.len();
+        if remaining == 0 {
+            return Poll::Ready(Err(io::ErrorKind::StorageFull.into()));
+        }
+        let len = buf.len().min(remaining);
+        data.extend_from_slice(&buf[..len]);
+        Poll::Ready(Ok(len))
+    }
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+    fn poll_shutdown(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl AsyncRead for LimitedFile {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        _buf: &mut virtual_fs::ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl VirtualFile for LimitedFile {
+    fn last_accessed(&self) -> u64 {
+        0
+    }
+    fn last_modified(&self) -> u64 {
+        0
+    }
+    fn created_time(&self) -> u64 {
+        0
+    }
+    fn size(&self) -> u64 {
+        self.data.lock().unwrap().len() as u64
+    }
+    fn set_len(&mut self, _new_size: u64) -> virtual_fs::Result<()> {
+        Ok(())
+    }
+    fn unlink(&mut self) -> virtual_fs::Result<()> {
+        Ok(())
+    }
+    fn poll_read_ready(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<usize>> {
+        Poll::Ready(Ok(0))
+    }
+    fn poll_write_ready(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<usize>> {
+        Poll::Ready(Ok(self.capacity - self.data.lock().unwrap().len()))
+    }
+}
+
+fn test_stdout_partial_writev() {
+    #[cfg(not(target_arch = "wasm32"))]
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .unwrap();
+    #[cfg(not(target_arch = "wasm32"))]
+    let handle = runtime.handle().clone();
+    #[cfg(not(target_arch = "wasm32"))]
+    let _guard = handle.enter();
+
+    let engine = wasmer::Engine::default();
+    let module = Module::new(
+        &engine,
+        br#"
+    (module
+        (import "wasi_unstable" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        (data (i32.const 16) "hello ")
+        (data (i32.const 32) "world")
+
+        (func $main (export "_start")
+            ;; Two io vectors, the disk fills up after the first one
+            (i32.store (i32.const 0) (i32.const 16))
+            (i32.store (i32.const 4) (i32.const 6))
+            (i32.store (i32.const 8) (i32.const 32))
+            (i32.store (i32.const 12) (i32.const 5))
+
+            ;; The partial write must succeed...
+            (if (i32.ne
+                    (call $fd_write (i32.const 1) (i32.const 0) (i32.const 2) (i32.const 48))
+                    (i32.const 0))
+                (then unreachable))
+            ;; ...and report the bytes that made it
+            (if (i32.ne (i32.load (i32.const 48)) (i32.const 6))
+                (then unreachable))
+
+            ;; Once nothing fits the error is reported
+            (if (i32.eqz
+                    (call $fd_write (i32.const 1) (i32.const 0) (i32.const 2) (i32.const 48)))
+                (then unreachable))
+        )
+    )
+    "#,
+    )
+    .unwrap();
+
+    let stdout = LimitedFile {
+        data: Default::default(),
+        capacity: 6,
+    };
+
+    {
+        let mut runner = WasiRunner::new();
+        runner.with_stdout(Box::new(stdout.clone()));
+
+        runner
+            .run_wasm(
+                RuntimeOrEngine::Engine(engine),
+                "command-name",
+                module,
+                ModuleHash::random(),
+            )
+            .unwrap();
+    }
+
+    assert_eq!(stdout.data.lock().unwrap().as_slice(), b"hello ");
+}
+
 fn test_env() {
     #[cfg(not(target_arch = "wasm32"))]
     let runtime = tokio::runtime::Builder::new_multi_thread()
@@ -202,3 +532,287 @@ fn test_stdin() {
     // pipe.read_to_end(&mut buf).await.unwrap();
     // assert_eq!(buf.len(), 0);
 }
+
+/// Copies what it reads from `stdin` to `stdout` and writes `done\n` to
+/// `stderr`.
+const ECHO_STDIN: &str = r#"
+(module
+    (import "wasi_snapshot_preview1" "fd_read" (func $fd_read (param i32 i32 i32 i32) (result i32)))
+    (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+
+    (memory 1)
+    (export "memory" (memory 0))
+
+    (data (i32.const 100) "done\n")
+
+    (func $check (param i32)
+        (if (local.get 0) (then unreachable))
+    )
+
+    (func $main (export "_start")
+        (i32.store (i32.const 0) (i32.const 400))
+        (i32.store (i32.const 4) (i32.const 64))
+        (call $check (call $fd_read (i32.const 0) (i32.const 0) (i32.const 1) (i32.const 8)))
+        (i32.store (i32.const 4) (i32.load (i32.const 8)))
+        (call $check (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 8)))
+
+        (i32.store (i32.const 0) (i32.const 100))
+        (i32.store (i32.const 4) (i32.const 5))
+        (call $check (call $fd_write (i32.const 2) (i32.const 0) (i32.const 1) (i32.const 8)))
+    )
+)
+"#;
+
+fn test_stdio_text_mode() {
+    #[cfg(not(target_arch = "wasm32"))]
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .unwrap();
+    #[cfg(not(target_arch = "wasm32"))]
+    let handle = runtime.handle().clone();
+    #[cfg(not(target_arch = "wasm32"))]
+    let _guard = handle.enter();
+
+    let engine = wasmer::Engine::default();
+    let module = Module::new(&engine, ECHO_STDIN).unwrap();
+
+    let (mut stdin_tx, stdin_rx) = Pipe::channel();
+    let (stdout_tx, mut stdout_rx) = Pipe::channel();
+    let (stderr_tx, mut stderr_rx) = Pipe::channel();
+    block_on(stdin_tx.write_all(b"one\r\ntwo\r\n")).unwrap();
+    drop(stdin_tx);
+
+    // `stdout` stays in binary mode, so it shows what the guest read
+    {
+        let mut runner = WasiRunner::new();
+        runner
+            .with_stdin(Box::new(stdin_rx))
+            .with_stdin_mode(StreamMode::Text)
+            .with_stdout(Box::new(stdout_tx))
+            .with_stderr(Box::new(stderr_tx))
+            .with_stderr_mode(StreamMode::Text);
+
+        runner
+            .run_wasm(
+                RuntimeOrEngine::Engine(engine),
+                "command-name",
+                module,
+                ModuleHash::random(),
+            )
+            .unwrap();
+    }
+
+    let mut stdout = Vec::new();
+    block_on(stdout_rx.read_to_end(&mut stdout)).unwrap();
+    assert_eq!(stdout, b"one\ntwo\n");
+
+    let mut stderr = Vec::new();
+    block_on(stderr_rx.read_to_end(&mut stderr)).unwrap();
+    assert_eq!(stderr, b"done\r\n");
+}
+
+/// Polls `stdin` for reading alongside a 100ms timeout. It writes the number
+/// of events, the user data of the first one (`1` for `stdin` and `2` for
+/// the timeout) and, if `stdin` was readable, the number of bytes read from
+/// it to stdout.
+const POLL_STDIN: &str = r#"
+(module
+    (import "wasi_snapshot_preview1" "poll_oneoff" (func $poll_oneoff (param i32 i32 i32 i32) (result i32)))
+    (import "wasi_snapshot_preview1" "fd_read" (func $fd_read (param i32 i32 i32 i32) (result i32)))
+    (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+
+    (memory 1)
+    (export "memory" (memory 0))
+
+    (data (i32.const 308) "\ff\ff\ff\ff")
+
+    (func $check (param i32)
+        (if (local.get 0) (then unreachable))
+    )
+
+    (func $main (export "_start")
+        ;; Wait for stdin to become readable
+        (i64.store (i32.const 1000) (i64.const 1))
+        (i32.store8 (i32.const 1008) (i32.const 1))
+        (i32.store (i32.const 1016) (i32.const 0))
+
+        ;; Or for 100ms to pass on the monotonic clock
+        (i64.store (i32.const 1048) (i64.const 2))
+        (i32.store8 (i32.const 1056) (i32.const 0))
+        (i32.store (i32.const 1064) (i32.const 1))
+        (i64.store (i32.const 1072) (i64.const 100000000))
+
+        (call $check (call $poll_oneoff (i32.const 1000) (i32.const 2000) (i32.const 2) (i32.const 300)))
+        (i32.store (i32.const 304) (i32.wrap_i64 (i64.load (i32.const 2000))))
+
+        (if (i32.eq (i32.load (i32.const 304)) (i32.const 1))
+            (then
+                (i32.store (i32.const 0) (i32.const 400))
+                (i32.store (i32.const 4) (i32.const 16))
+                (call $check (call $fd_read (i32.const 0) (i32.const 0) (i32.const 1) (i32.const 308)))
+            )
+        )
+
+        (i32.store (i32.const 0) (i32.const 300))
+        (i32.store (i32.const 4) (i32.const 12))
+        (call $check (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 8)))
+    )
+)
+"#;
+
+fn poll_absent_stdin(absent_stdin: Option<AbsentStdin>) -> Vec<u32> {
+    #[cfg(not(target_arch = "wasm32"))]
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .unwrap();
+    #[cfg(not(target_arch = "wasm32"))]
+    let handle = runtime.handle().clone();
+    #[cfg(not(target_arch = "wasm32"))]
+    let _guard = handle.enter();
+
+    let engine = wasmer::Engine::default();
+    let module = Module::new(&engine, POLL_STDIN).unwrap();
+
+    let (stdout_tx, mut stdout_rx) = Pipe::channel();
+
+    {
+        let mut runner = WasiRunner::new();
+        runner.with_stdout(Box::new(stdout_tx));
+        if let Some(absent_stdin) = absent_stdin {
+            runner.with_absent_stdin(absent_stdin);
+        }
+
+        runner
+            .run_wasm(
+                RuntimeOrEngine::Engine(engine),
+                "command-name",
+                module,
+                ModuleHash::random(),
+            )
+            .unwrap();
+    }
+
+    let mut stdout = Vec::new();
+    block_on(stdout_rx.read_to_end(&mut stdout)).unwrap();
+    stdout
+        .chunks(4)
+        .map(|value| u32::from_le_bytes(value.try_into().unwrap()))
+        .collect()
+}
+
+fn test_absent_stdin_eof() {
+    // Without a stdin the default is to report end-of-file right away
+    assert_eq!(poll_absent_stdin(None), vec![1, 1, 0]);
+    assert_eq!(poll_absent_stdin(Some(AbsentStdin::Eof)), vec![1, 1, 0]);
+}
+
+fn test_absent_stdin_pending() {
+    assert_eq!(
+        poll_absent_stdin(Some(AbsentStdin::Pending)),
+        vec![1, 2, u32::MAX]
+    );
+}
+
+/// Fills stdout, then polls it for writability with a timeout of 100ms and
+/// writes a `.` to stderr. Then polls stdout again without a timeout and
+/// writes the userdata of the two events to stdout (a byte each).
+const POLL_STDOUT: &str = r#"
+(module
+    (import "wasi_snapshot_preview1" "poll_oneoff" (func $poll_oneoff (param i32 i32 i32 i32) (result i32)))
+    (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+
+    (memory 1)
+    (export "memory" (memory 0))
+
+    (data (i32.const 100) "abcd")
+    (data (i32.const 104) ".")
+
+    (func $check (param i32)
+        (if (local.get 0) (then unreachable))
+    )
+
+    (func $write (param $fd i32) (param $ptr i32) (param $len i32)
+        (i32.store (i32.const 0) (local.get $ptr))
+        (i32.store (i32.const 4) (local.get $len))
+        (call $check (call $fd_write (local.get $fd) (i32.const 0) (i32.const 1) (i32.const 8)))
+    )
+
+    (func $main (export "_start")
+        (call $write (i32.const 1) (i32.const 100) (i32.const 4))
+
+        ;; Wait for stdout to become writable
+        (i64.store (i32.const 1000) (i64.const 1))
+        (i32.store8 (i32.const 1008) (i32.const 2))
+        (i32.store (i32.const 1016) (i32.const 1))
+
+        ;; Or for 100ms to pass on the monotonic clock
+        (i64.store (i32.const 1048) (i64.const 2))
+        (i32.store8 (i32.const 1056) (i32.const 0))
+        (i32.store (i32.const 1064) (i32.const 1))
+        (i64.store (i32.const 1072) (i64.const 100000000))
+
+        (call $check (call $poll_oneoff (i32.const 1000) (i32.const 2000) (i32.const 2) (i32.const 200)))
+        (i32.store8 (i32.const 300) (i32.load8_u (i32.const 2000)))
+        (call $write (i32.const 2) (i32.const 104) (i32.const 1))
+
+        (call $check (call $poll_oneoff (i32.const 1000) (i32.const 2000) (i32.const 1) (i32.const 200)))
+        (i32.store8 (i32.const 301) (i32.load8_u (i32.const 2000)))
+        (call $write (i32.const 1) (i32.const 300) (i32.const 2))
+    )
+)
+"#;
+
+fn test_stdout_backpressure() {
+    #[cfg(not(target_arch = "wasm32"))]
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .unwrap();
+    #[cfg(not(target_arch = "wasm32"))]
+    let handle = runtime.handle().clone();
+    #[cfg(not(target_arch = "wasm32"))]
+    let _guard = handle.enter();
+
+    let engine = wasmer::Engine::default();
+    let module = Module::new(&engine, POLL_STDOUT).unwrap();
+
+    // The sink of stdout only holds 4 bytes
+    let (stdout_tx, mut stdout_rx) = Pipe::channel_with_capacity(4);
+    let (stderr_tx, mut stderr_rx) = Pipe::channel();
+
+    let guest = std::thread::spawn(move || {
+        #[cfg(not(target_arch = "wasm32"))]
+        let _guard = handle.enter();
+
+        let mut runner = WasiRunner::new();
+        runner
+            .with_stdout(Box::new(stdout_tx))
+            .with_stderr(Box::new(stderr_tx));
+        runner
+            .run_wasm(
+                RuntimeOrEngine::Engine(engine),
+                "command-name",
+                module,
+                ModuleHash::random(),
+            )
+            .unwrap();
+    });
+
+    // Stdout is not writable while it is full, so the clock fires first
+    let mut signal = [0u8; 1];
+    block_on(stderr_rx.read_exact(&mut signal)).unwrap();
+    assert_eq!(&signal, b".");
+
+    // Reading from the sink makes room for the guest
+    let mut filled = [0u8; 4];
+    block_on(stdout_rx.read_exact(&mut filled)).unwrap();
+    assert_eq!(&filled, b"abcd");
+
+    guest.join().unwrap();
+
+    let mut stdout = Vec::new();
+    block_on(stdout_rx.read_to_end(&mut stdout)).unwrap();
+    assert_eq!(stdout, vec![2, 1]);
+}
@@ -0,0 +1,153 @@
+use std::{
+    io,
+    pin::Pin,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicUsize, Ordering},
+    },
+    task::{Context, Poll},
+};
+
+use tokio::io::{AsyncRead, AsyncSeek, AsyncWrite, ReadBuf};
+use virtual_fs::VirtualFile;
+
+use super::run_wat_with;
+
+/// Stdio that reports "would block" for its first few reads and writes
+#[derive(Debug)]
+struct WouldBlockFile {
+    would_block: usize,
+    attempts: Arc<AtomicUsize>,
+    input: &'static [u8],
+    output: Arc<Mutex<Vec<u8>>>,
+}
+
+impl WouldBlockFile {
+    fn new(would_block: usize, input: &'static [u8]) -> Self {
+        Self {
+            would_block,
+            attempts: Default::default(),
+            input,
+            output: Default::default(),
+        }
+    }
+
+    fn attempt(&mut self) -> io::Result<()> {
+        if self.attempts.fetch_add(1, Ordering::SeqCst) < self.would_block {
+            return Err(io::ErrorKind::WouldBlock.into());
+        }
+        Ok(())
+    }
+}
+
+impl AsyncRead for WouldBlockFile {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        self.attempt()?;
+        let len = buf.remaining().min(self.input.len());
+        buf.put_slice(&self.input[..len]);
+        self.input = &self.input[len..];
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl AsyncWrite for WouldBlockFile {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        self.attempt()?;
+        self.output.lock().unwrap().extend_from_slice(buf);
+        Poll::Ready(Ok(buf.len()))
+    }
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+    fn poll_shutdown(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl AsyncSeek for WouldBlockFile {
+    fn start_seek(self: Pin<&mut Self>, _position: io::SeekFrom) -> io::Result<()> {
+        Ok(())
+    }
+    fn poll_complete(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<u64>> {
+        Poll::Ready(Ok(0))
+    }
+}
+
+impl VirtualFile for WouldBlockFile {
+    fn last_accessed(&self) -> u64 {
+        0
+    }
+    fn last_modified(&self) -> u64 {
+        0
+    }
+    fn created_time(&self) -> u64 {
+        0
+    }
+    fn size(&self) -> u64 {
+        0
+    }
+    fn set_len(&mut self, _new_size: u64) -> virtual_fs::Result<()> {
+        Ok(())
+    }
+    fn unlink(&mut self) -> virtual_fs::Result<()> {
+        Ok(())
+    }
+    fn poll_read_ready(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<usize>> {
+        Poll::Ready(Ok(self.input.len()))
+    }
+    fn poll_write_ready(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<usize>> {
+        Poll::Ready(Ok(8192))
+    }
+}
+
+#[test]
+fn test_blocking_stdio_retries_would_block() {
+    let stdin = WouldBlockFile::new(3, b"ping");
+    let stdout = WouldBlockFile::new(2, b"");
+    let (stdin_attempts, stdout_attempts) = (stdin.attempts.clone(), stdout.attempts.clone());
+    let output = stdout.output.clone();
+
+    run_wat_with(
+        br#"
+    (module
+        (import "wasi_snapshot_preview1" "fd_read" (func $fd_read (param i32 i32 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        (func $check (param i32)
+            (if (local.get 0) (then unreachable))
+        )
+
+        (func $main (export "_start")
+            ;; Both calls succeed although the host reports that they would
+            ;; block, stdio is in blocking mode
+            (i32.store (i32.const 0) (i32.const 1024))
+            (i32.store (i32.const 4) (i32.const 64))
+            (call $check (call $fd_read (i32.const 0) (i32.const 0) (i32.const 1) (i32.const 8)))
+            (i32.store (i32.const 4) (i32.load (i32.const 8)))
+            (call $check (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 8)))
+            (call $check (i32.ne (i32.load (i32.const 8)) (i32.const 4)))
+        )
+    )
+    "#,
+        |runner| {
+            runner
+                .with_stdin(Box::new(stdin))
+                .with_stdout(Box::new(stdout));
+        },
+    );
+
+    assert_eq!(&output.lock().unwrap()[..], b"ping");
+    assert_eq!(stdin_attempts.load(Ordering::SeqCst), 4);
+    assert_eq!(stdout_attempts.load(Ordering::SeqCst), 3);
+}
@@ -0,0 +1,42 @@
+use std::sync::{Arc, Mutex};
+
+use wasmer_wasix::{WasiProcessId, WasiThreadId};
+
+use super::TestRuntime;
+
+type Calls = Arc<Mutex<Vec<(&'static str, WasiProcessId, WasiThreadId)>>>;
+
+#[test]
+fn test_call_hooks_fire_once_per_call() {
+    let calls: Calls = Default::default();
+
+    let mut runtime = TestRuntime::new();
+    {
+        let enters = calls.clone();
+        let exits = calls.clone();
+        runtime.rt.set_call_hooks(
+            move |pid, tid| enters.lock().unwrap().push(("enter", pid, tid)),
+            move |pid, tid| exits.lock().unwrap().push(("exit", pid, tid)),
+        );
+    }
+    let (result, _) = runtime.run_wat(
+        br#"
+    (module
+        (import "wasi_snapshot_preview1" "sched_yield" (func $sched_yield (result i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        (func $main (export "_start")
+            (drop (call $sched_yield))
+        )
+    )
+    "#,
+    );
+    result.unwrap();
+
+    let calls = calls.lock().unwrap();
+    assert_eq!(calls.len(), 2, "{calls:?}");
+    let (_, pid, tid) = calls[0];
+    assert_eq!(*calls, vec![("enter", pid, tid), ("exit", pid, tid)]);
+}
@@ -0,0 +1,73 @@
+use wasmer_wasix::capabilities::SandboxPolicy;
+use wasmer_wasix_types::wasi::{Errno, Snapshot0Clockid};
+
+use super::run_wat_with;
+
+/// Reads and sleeps on the realtime and the monotonic clock
+const PROGRAM: &[u8] = br#"
+(module
+    (import "wasi_snapshot_preview1" "clock_time_get" (func $clock_time_get (param i32 i64 i32) (result i32)))
+    (import "wasi_snapshot_preview1" "clock_res_get" (func $clock_res_get (param i32 i32) (result i32)))
+    (import "wasi_snapshot_preview1" "poll_oneoff" (func $poll_oneoff (param i32 i32 i32 i32) (result i32)))
+    (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+    (import "wasix_32v1" "clock_nanosleep" (func $clock_nanosleep (param i32 i32 i64 i32) (result i32)))
+
+    (memory 1)
+    (export "memory" (memory 0))
+
+    (func $check (param i32)
+        (if (local.get 0) (then unreachable))
+    )
+
+    ;; Waits for 1ms on `clock` with poll_oneoff
+    (func $poll (param $clock i32) (result i32)
+        (i64.store (i32.const 400) (i64.const 0))
+        (i32.store8 (i32.const 408) (i32.const 0))
+        (i32.store (i32.const 416) (local.get $clock))
+        (i64.store (i32.const 424) (i64.const 1000000))
+        (i64.store (i32.const 432) (i64.const 0))
+        (i32.store16 (i32.const 440) (i32.const 0))
+        (call $poll_oneoff (i32.const 400) (i32.const 512) (i32.const 1) (i32.const 600))
+    )
+
+    (func $main (export "_start")
+        (i32.store (i32.const 1024) (call $clock_time_get (i32.const 0) (i64.const 1) (i32.const 200)))
+        (i32.store (i32.const 1028) (call $clock_res_get (i32.const 0) (i32.const 200)))
+        (i32.store (i32.const 1032) (call $clock_nanosleep (i32.const 0) (i32.const 0) (i64.const 1000000) (i32.const 0)))
+        (i32.store (i32.const 1036) (call $poll (i32.const 0)))
+
+        (i32.store (i32.const 1040) (call $clock_time_get (i32.const 1) (i64.const 1) (i32.const 200)))
+        (i32.store (i32.const 1044) (call $clock_res_get (i32.const 1) (i32.const 200)))
+        (i32.store (i32.const 1048) (call $clock_nanosleep (i32.const 1) (i32.const 0) (i64.const 1000000) (i32.const 0)))
+        (i32.store (i32.const 1052) (call $poll (i32.const 1)))
+
+        ;; Send the results to stdout
+        (i32.store (i32.const 0) (i32.const 1024))
+        (i32.store (i32.const 4) (i32.const 32))
+        (call $check (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 8)))
+    )
+)
+"#;
+
+#[test]
+fn test_forbidden_clocks_are_not_capable() {
+    let stdout = run_wat_with(PROGRAM, |runner| {
+        runner
+            .capabilities_mut()
+            .sandbox
+            .update(SandboxPolicy::new().allow_clock(Snapshot0Clockid::Monotonic));
+    });
+    let results = stdout
+        .chunks(4)
+        .map(|value| u32::from_le_bytes(value.try_into().unwrap()))
+        .collect::<Vec<_>>();
+
+    let denied = Errno::Notcapable as u32;
+    let success = Errno::Success as u32;
+    assert_eq!(
+        results,
+        vec![
+            denied, denied, denied, denied, success, success, success, success
+        ]
+    );
+}
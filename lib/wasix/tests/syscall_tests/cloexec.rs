@@ -0,0 +1,104 @@
+use std::sync::Arc;
+
+use virtual_fs::{AsyncWriteExt, FileSystem, TmpFileSystem};
+use virtual_mio::block_on;
+
+use super::run_wat_with;
+
+/// The first image opens `tmp/a.txt` as close-on-exec, `tmp/b.txt` without
+/// it and `tmp/c.txt` without it but marks it as close-on-exec afterwards.
+/// It saves the three descriptor numbers into `tmp/b.txt` and then execs
+/// itself. The second image reads the numbers back and writes the result
+/// of `fd_fdflags_get` on each of them to stdout.
+const PROGRAM: &str = r#"
+(module
+    (import "wasi_snapshot_preview1" "args_sizes_get" (func $args_sizes_get (param i32 i32) (result i32)))
+    (import "wasi_snapshot_preview1" "path_open" (func $path_open (param i32 i32 i32 i32 i32 i64 i64 i32 i32) (result i32)))
+    (import "wasi_snapshot_preview1" "fd_read" (func $fd_read (param i32 i32 i32 i32) (result i32)))
+    (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+    (import "wasi_snapshot_preview1" "fd_close" (func $fd_close (param i32) (result i32)))
+    (import "wasix_32v1" "path_open2" (func $path_open2 (param i32 i32 i32 i32 i32 i64 i64 i32 i32 i32) (result i32)))
+    (import "wasix_32v1" "fd_fdflags_get" (func $fd_fdflags_get (param i32 i32) (result i32)))
+    (import "wasix_32v1" "fd_fdflags_set" (func $fd_fdflags_set (param i32 i32) (result i32)))
+    (import "wasix_32v1" "proc_exec3" (func $proc_exec3 (param i32 i32 i32 i32 i32 i32 i32 i32 i32) (result i32)))
+
+    (memory 1)
+    (export "memory" (memory 0))
+
+    (data (i32.const 100) "tmp/a.txt")
+    (data (i32.const 120) "tmp/b.txt")
+    (data (i32.const 140) "/prog/main.wasm")
+    (data (i32.const 160) "main\nexeced")
+    (data (i32.const 180) "tmp/c.txt")
+
+    (func $check (param i32)
+        (if (local.get 0) (then unreachable))
+    )
+
+    (func $main (export "_start")
+        (call $check (call $args_sizes_get (i32.const 16) (i32.const 20)))
+        (if (i32.eq (i32.load (i32.const 16)) (i32.const 1))
+            (then
+                (call $check (call $path_open2 (i32.const 3) (i32.const 0) (i32.const 100) (i32.const 9)
+                    (i32.const 1) (i64.const -1) (i64.const -1) (i32.const 0) (i32.const 1) (i32.const 200)))
+                (call $check (call $path_open2 (i32.const 3) (i32.const 0) (i32.const 120) (i32.const 9)
+                    (i32.const 1) (i64.const -1) (i64.const -1) (i32.const 0) (i32.const 0) (i32.const 204)))
+                (call $check (call $path_open2 (i32.const 3) (i32.const 0) (i32.const 180) (i32.const 9)
+                    (i32.const 1) (i64.const -1) (i64.const -1) (i32.const 0) (i32.const 0) (i32.const 208)))
+                (call $check (call $fd_fdflags_set (i32.load (i32.const 208)) (i32.const 1)))
+
+                (i32.store (i32.const 0) (i32.const 200))
+                (i32.store (i32.const 4) (i32.const 12))
+                (call $check (call $fd_write (i32.load (i32.const 204)) (i32.const 0) (i32.const 1) (i32.const 8)))
+
+                (call $check (call $proc_exec3 (i32.const 140) (i32.const 15) (i32.const 160) (i32.const 11)
+                    (i32.const 0) (i32.const 0) (i32.const 0) (i32.const 0) (i32.const 0)))
+                unreachable
+            )
+        )
+
+        (call $check (call $path_open (i32.const 3) (i32.const 0) (i32.const 120) (i32.const 9)
+            (i32.const 0) (i64.const -1) (i64.const -1) (i32.const 0) (i32.const 212)))
+        (i32.store (i32.const 0) (i32.const 200))
+        (i32.store (i32.const 4) (i32.const 12))
+        (call $check (call $fd_read (i32.load (i32.const 212)) (i32.const 0) (i32.const 1) (i32.const 8)))
+        ;; Otherwise the reader could be sitting in a slot that was freed
+        (call $check (call $fd_close (i32.load (i32.const 212))))
+
+        (i32.store (i32.const 300) (call $fd_fdflags_get (i32.load (i32.const 200)) (i32.const 400)))
+        (i32.store (i32.const 304) (call $fd_fdflags_get (i32.load (i32.const 204)) (i32.const 400)))
+        (i32.store (i32.const 308) (call $fd_fdflags_get (i32.load (i32.const 208)) (i32.const 400)))
+
+        (i32.store (i32.const 0) (i32.const 300))
+        (i32.store (i32.const 4) (i32.const 12))
+        (call $check (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 8)))
+    )
+)
+"#;
+
+#[test]
+fn test_cloexec_fds_are_closed_by_exec() {
+    let wasm = wasmer::wat2wasm(PROGRAM.as_bytes()).unwrap();
+
+    // The program execs itself from the file system
+    let prog = TmpFileSystem::new();
+    let mut file = prog
+        .new_open_options()
+        .create(true)
+        .write(true)
+        .open("/main.wasm")
+        .unwrap();
+    block_on(file.write_all(&wasm)).unwrap();
+
+    let stdout = run_wat_with(&wasm, |runner| {
+        runner.with_mount("/prog".to_string(), Arc::new(prog));
+    });
+    let errnos: Vec<_> = stdout
+        .chunks(4)
+        .map(|errno| u32::from_le_bytes(errno.try_into().unwrap()))
+        .collect();
+
+    // The close-on-exec descriptors are gone (`Errno::Badf`) while the
+    // other one survived the exec
+    assert_eq!(errnos, vec![8, 0, 8]);
+}
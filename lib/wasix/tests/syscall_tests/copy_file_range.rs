@@ -0,0 +1,193 @@
+use std::{path::Path, sync::Arc};
+
+use virtual_fs::{AsyncReadExt, AsyncWriteExt, FileSystem};
+use virtual_mio::block_on;
+use wasmer_wasix::WasiEnv;
+use wasmer_wasix_types::wasi::Errno;
+
+use super::TestRuntime;
+
+/// Copies `world` from `/src.txt` (which holds `hello world`) to the start
+/// of `/dst.txt` by asking for more than the file has, then tries to copy
+/// within `/dst.txt` to a range that overlaps and finally copies `wo` to
+/// the end of `/dst.txt`. The offsets, the number of bytes copied and the
+/// results are written to stdout.
+const PROGRAM: &str = r#"
+(module
+    (import "wasi_snapshot_preview1" "path_open" (func $path_open (param i32 i32 i32 i32 i32 i64 i64 i32 i32) (result i32)))
+    (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+    (import "wasix_32v1" "copy_file_range" (func $copy_file_range (param i32 i32 i32 i32 i64 i32) (result i32)))
+
+    (memory 1)
+    (export "memory" (memory 0))
+
+    (data (i32.const 100) "/src.txt")
+    (data (i32.const 120) "/dst.txt")
+
+    (func $check (param i32)
+        (if (local.get 0) (then unreachable))
+    )
+
+    (func $main (export "_start")
+        (call $check (call $path_open (i32.const 3) (i32.const 0) (i32.const 100) (i32.const 8)
+            (i32.const 0) (i64.const -1) (i64.const -1) (i32.const 0) (i32.const 200)))
+        (call $check (call $path_open (i32.const 3) (i32.const 0) (i32.const 120) (i32.const 8)
+            (i32.const 1) (i64.const -1) (i64.const -1) (i32.const 0) (i32.const 204)))
+
+        ;; From offset 6 of the source to the offset of the destination
+        (i64.store (i32.const 208) (i64.const 6))
+        (i32.store (i32.const 248)
+            (call $copy_file_range (i32.load (i32.const 200)) (i32.const 208)
+                (i32.load (i32.const 204)) (i32.const 0) (i64.const 100) (i32.const 216)))
+
+        ;; From offset 0 to offset 2 of the destination, which overlap
+        (i64.store (i32.const 232) (i64.const 2))
+        (i32.store (i32.const 252)
+            (call $copy_file_range (i32.load (i32.const 204)) (i32.const 224)
+                (i32.load (i32.const 204)) (i32.const 232) (i64.const 3) (i32.const 240)))
+
+        ;; From offset 0 to the offset of the destination
+        (i32.store (i32.const 256)
+            (call $copy_file_range (i32.load (i32.const 204)) (i32.const 224)
+                (i32.load (i32.const 204)) (i32.const 0) (i64.const 2) (i32.const 240)))
+
+        (i32.store (i32.const 0) (i32.const 208))
+        (i32.store (i32.const 4) (i32.const 52))
+        (call $check (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 8)))
+    )
+)
+"#;
+
+/// What the guest wrote to stdout
+#[derive(Debug, PartialEq, Eq)]
+struct Copies {
+    src_offset: u64,
+    first_copied: u64,
+    dst_offsets: [u64; 2],
+    last_copied: u64,
+    errnos: [u32; 3],
+}
+
+fn run_copies(
+    fs: impl FnOnce(tokio::runtime::Handle) -> Arc<dyn FileSystem + Send + Sync>,
+) -> (Copies, Vec<u8>) {
+    let runtime = TestRuntime::new();
+    let _guard = runtime.enter();
+
+    let fs = fs(runtime.handle().clone());
+    let mut src = fs
+        .new_open_options()
+        .write(true)
+        .create(true)
+        .open(Path::new("/src.txt"))
+        .unwrap();
+    block_on(src.write_all(b"hello world")).unwrap();
+    block_on(src.flush()).unwrap();
+    drop(src);
+
+    let builder = WasiEnv::builder("main")
+        .fs(fs.clone())
+        .preopen_dir("/")
+        .unwrap();
+    let (exit_code, stdout) = runtime.spawn_wat(PROGRAM, builder);
+    assert!(exit_code.is_success());
+
+    let dword = |at: usize| u64::from_le_bytes(stdout[at..at + 8].try_into().unwrap());
+    let word = |at: usize| u32::from_le_bytes(stdout[at..at + 4].try_into().unwrap());
+    let copies = Copies {
+        src_offset: dword(0),
+        first_copied: dword(8),
+        dst_offsets: [dword(16), dword(24)],
+        last_copied: dword(32),
+        errnos: [word(40), word(44), word(48)],
+    };
+
+    let mut dst = Vec::new();
+    let mut file = fs
+        .new_open_options()
+        .read(true)
+        .open(Path::new("/dst.txt"))
+        .unwrap();
+    block_on(file.read_to_end(&mut dst)).unwrap();
+    (copies, dst)
+}
+
+fn expected_copies() -> Copies {
+    Copies {
+        src_offset: 11,
+        first_copied: 5,
+        dst_offsets: [2, 2],
+        last_copied: 2,
+        errnos: [
+            Errno::Success as u32,
+            Errno::Inval as u32,
+            Errno::Success as u32,
+        ],
+    }
+}
+
+#[test]
+fn test_copy_file_range_between_host_files() {
+    let temp = tempfile::TempDir::new().unwrap();
+    let (copies, dst) = run_copies(|handle| {
+        Arc::new(virtual_fs::host_fs::FileSystem::new(handle, temp.path()).unwrap())
+    });
+    assert_eq!(copies, expected_copies());
+    assert_eq!(dst, b"worldwo");
+}
+
+#[test]
+fn test_copy_file_range_between_memory_files() {
+    let (copies, dst) = run_copies(|_| Arc::new(virtual_fs::mem_fs::FileSystem::default()));
+    assert_eq!(copies, expected_copies());
+    assert_eq!(dst, b"worldwo");
+}
+
+/// Copies from offset `u64::MAX` of `/a.txt` to `/b.txt` and writes the
+/// result to stdout
+const OVERFLOW_PROGRAM: &str = r#"
+(module
+    (import "wasi_snapshot_preview1" "path_open" (func $path_open (param i32 i32 i32 i32 i32 i64 i64 i32 i32) (result i32)))
+    (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+    (import "wasix_32v1" "copy_file_range" (func $copy_file_range (param i32 i32 i32 i32 i64 i32) (result i32)))
+
+    (memory 1)
+    (export "memory" (memory 0))
+
+    (data (i32.const 100) "/a.txt")
+    (data (i32.const 120) "/b.txt")
+
+    (func $check (param i32)
+        (if (local.get 0) (then unreachable))
+    )
+
+    (func $main (export "_start")
+        (call $check (call $path_open (i32.const 3) (i32.const 0) (i32.const 100) (i32.const 6)
+            (i32.const 1) (i64.const -1) (i64.const -1) (i32.const 0) (i32.const 200)))
+        (call $check (call $path_open (i32.const 3) (i32.const 0) (i32.const 120) (i32.const 6)
+            (i32.const 1) (i64.const -1) (i64.const -1) (i32.const 0) (i32.const 204)))
+
+        (i64.store (i32.const 208) (i64.const -1))
+        (i32.store (i32.const 224)
+            (call $copy_file_range (i32.load (i32.const 200)) (i32.const 208)
+                (i32.load (i32.const 204)) (i32.const 0) (i64.const 1) (i32.const 216)))
+
+        (i32.store (i32.const 0) (i32.const 224))
+        (i32.store (i32.const 4) (i32.const 4))
+        (call $check (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 8)))
+    )
+)
+"#;
+
+#[test]
+fn test_copy_file_range_rejects_offsets_past_i64_max() {
+    let runtime = TestRuntime::new();
+    let builder = WasiEnv::builder("main")
+        .fs(Arc::new(virtual_fs::mem_fs::FileSystem::default())
+            as Arc<dyn FileSystem + Send + Sync>)
+        .preopen_dir("/")
+        .unwrap();
+    let (exit_code, stdout) = runtime.spawn_wat(OVERFLOW_PROGRAM, builder);
+    assert!(exit_code.is_success());
+    assert_eq!(stdout, (Errno::Inval as u32).to_le_bytes());
+}
@@ -0,0 +1,50 @@
+use std::sync::{Arc, Mutex};
+
+use wasmer_wasix::{CORE_DUMP_VERSION, CoreDump};
+
+use super::TestRuntime;
+
+#[test]
+fn test_core_dump_on_trap() {
+    let dumps: Arc<Mutex<Vec<Vec<u8>>>> = Default::default();
+
+    let mut runtime = TestRuntime::new();
+    {
+        let dumps = dumps.clone();
+        runtime
+            .rt
+            .set_on_core_dump(move |dump| dumps.lock().unwrap().push(dump.serialize().unwrap()));
+    }
+    let (result, _) = runtime.run_wat(
+        br#"
+    (module
+        (memory 1)
+        (export "memory" (memory 0))
+        (global $counter (mut i32) (i32.const 0))
+
+        (func $crash
+            unreachable
+        )
+
+        (func $main (export "_start")
+            (i32.store (i32.const 100) (i32.const 0xdeadbeef))
+            (global.set $counter (i32.const 42))
+            (call $crash)
+        )
+    )
+    "#,
+    );
+    assert!(result.is_err());
+
+    let dumps = dumps.lock().unwrap();
+    assert_eq!(dumps.len(), 1);
+    let dump = CoreDump::deserialize(&dumps[0]).unwrap();
+
+    assert_eq!(dump.version, CORE_DUMP_VERSION);
+    assert!(dump.message.contains("unreachable"), "{}", dump.message);
+    assert_eq!(dump.memory.len(), 65536);
+    assert_eq!(&dump.memory[100..104], &0xdeadbeef_u32.to_le_bytes());
+    assert!(dump.store.globals.contains(&42));
+    let funcs: Vec<_> = dump.frames.iter().map(|frame| frame.func_index).collect();
+    assert_eq!(funcs, vec![0, 1], "the frame of $crash comes first");
+}
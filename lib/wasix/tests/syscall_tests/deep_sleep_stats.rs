@@ -0,0 +1,32 @@
+use virtual_fs::AsyncReadExt;
+use virtual_mio::block_on;
+use wasmer_wasix::{DeepSleepStats, WasiEnv};
+
+use super::{TestRuntime, idle_eviction::SLEEPER};
+
+#[test]
+fn test_deep_sleeps_of_a_process_are_counted() {
+    let runtime = TestRuntime::new();
+    let mut builder = WasiEnv::builder("sleeper");
+    builder.capabilities_mut().threading.enable_deep_sleep = true;
+    let (env, mut stdout_rx) = runtime.build_env(builder);
+    let process = env.process.clone();
+    assert_eq!(process.deep_sleep_stats(), DeepSleepStats::default());
+
+    let mut task = runtime.start(runtime.module(SLEEPER), env).unwrap();
+    let exit_code = block_on(task.wait_finished()).unwrap();
+    assert!(exit_code.is_success());
+
+    let mut stdout = Vec::new();
+    block_on(stdout_rx.read_to_end(&mut stdout)).unwrap();
+    assert_eq!(&stdout[..3], b"OK\n");
+
+    // The thread unwound once for the sleep and was rewound once it ended
+    assert_eq!(
+        process.deep_sleep_stats(),
+        DeepSleepStats {
+            sleeps: 1,
+            resumes: 1
+        }
+    );
+}
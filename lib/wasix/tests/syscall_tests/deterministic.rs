@@ -0,0 +1,86 @@
+use wasmer_wasix::{
+    WasiEnv,
+    runtime::entropy::{EntropySource, SeededEntropySource},
+};
+
+use super::TestRuntime;
+
+/// Writes 16 random bytes, the realtime and monotonic clocks in seconds and
+/// the order in which `poll_oneoff` reports two clock subscriptions that
+/// are both due right away to stdout
+const PROGRAM: &str = r#"
+(module
+    (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+    (import "wasi_snapshot_preview1" "random_get" (func $random_get (param i32 i32) (result i32)))
+    (import "wasi_snapshot_preview1" "clock_time_get" (func $clock_time_get (param i32 i64 i32) (result i32)))
+    (import "wasi_snapshot_preview1" "poll_oneoff" (func $poll_oneoff (param i32 i32 i32 i32) (result i32)))
+
+    (memory 1)
+    (export "memory" (memory 0))
+
+    (func $check (param i32)
+        (if (local.get 0) (then unreachable))
+    )
+
+    (func $main (export "_start")
+        (call $check (call $random_get (i32.const 296) (i32.const 16)))
+
+        (call $check (call $clock_time_get (i32.const 0) (i64.const 1) (i32.const 312)))
+        (i64.store (i32.const 312) (i64.div_u (i64.load (i32.const 312)) (i64.const 1000000000)))
+        (call $check (call $clock_time_get (i32.const 1) (i64.const 1) (i32.const 320)))
+        (i64.store (i32.const 320) (i64.div_u (i64.load (i32.const 320)) (i64.const 1000000000)))
+
+        ;; Two subscriptions to the monotonic clock that time out right away
+        (i64.store (i32.const 400) (i64.const 1))
+        (i32.store (i32.const 416) (i32.const 1))
+        (i64.store (i32.const 424) (i64.const 1))
+        (i64.store (i32.const 448) (i64.const 2))
+        (i32.store (i32.const 464) (i32.const 1))
+        (i64.store (i32.const 472) (i64.const 1))
+        (call $check (call $poll_oneoff (i32.const 400) (i32.const 512) (i32.const 2) (i32.const 328)))
+        (i64.store (i32.const 336) (i64.load (i32.const 512)))
+        (i64.store (i32.const 344) (i64.load (i32.const 544)))
+
+        (i32.store (i32.const 0) (i32.const 296))
+        (i32.store (i32.const 4) (i32.const 56))
+        (call $check (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 8)))
+    )
+)
+"#;
+
+fn run_with_seed(seed: u64) -> Vec<u8> {
+    let builder = WasiEnv::builder("main").deterministic(seed);
+    let (exit_code, stdout) = TestRuntime::new().spawn_wat(PROGRAM, builder);
+    assert!(exit_code.is_success());
+    assert_eq!(stdout.len(), 56);
+    stdout
+}
+
+#[test]
+fn test_deterministic_runs_with_the_same_seed_match() {
+    let first = run_with_seed(42);
+    let second = run_with_seed(42);
+    assert_eq!(first, second);
+
+    // The monotonic clock starts at zero and both subscriptions fired
+    let dword = |at: usize| u64::from_le_bytes(first[at..at + 8].try_into().unwrap());
+    assert_eq!(dword(24), 0);
+    assert_eq!(u32::from_le_bytes(first[32..36].try_into().unwrap()), 2);
+    let mut fired = [dword(40), dword(48)];
+    fired.sort();
+    assert_eq!(fired, [1, 2]);
+}
+
+#[test]
+fn test_deterministic_runs_with_other_seeds_differ() {
+    assert_ne!(run_with_seed(1)[..24], run_with_seed(2)[..24]);
+}
+
+#[test]
+fn test_deterministic_entropy_is_not_seeded_with_the_seed_itself() {
+    // The secret of the stack snapshots comes from the seed as well, so the
+    // guest must not be handed the bytes that the seed itself produces
+    let mut direct = [0u8; 16];
+    SeededEntropySource::new(42).fill(&mut direct).unwrap();
+    assert_ne!(run_with_seed(42)[..16], direct);
+}
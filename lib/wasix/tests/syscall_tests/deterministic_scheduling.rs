@@ -0,0 +1,130 @@
+use std::{collections::HashSet, sync::Arc};
+
+use wasmer_wasix::{VirtualTaskManager, runtime::task_manager::tokio::TokioTaskManager};
+
+use super::TestRuntime;
+
+/// Spawns three threads that each append their thread ID to a log five times,
+/// yielding in between, and prints the log once they are all done
+const INTERLEAVING: &[u8] = br#"
+(module
+    (import "env" "memory" (memory 2 2 shared))
+    (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+    (import "wasix_32v1" "thread_spawn_v2" (func $thread_spawn (param i32 i32) (result i32)))
+    (import "wasix_32v1" "sched_yield" (func $sched_yield (result i32)))
+
+    (global $__stack_low (export "__stack_low") i32 (i32.const 16384))
+    (global $__stack_pointer (export "__stack_pointer") (mut i32) (i32.const 32768))
+
+    ;; The functions that are unwound keep all their state in memory, so
+    ;; asyncify just needs to track its state
+    (global $asyncify_state (mut i32) (i32.const 0))
+    (func (export "asyncify_start_unwind") (param i32)
+        (global.set $asyncify_state (i32.const 1))
+    )
+    (func (export "asyncify_stop_unwind")
+        (global.set $asyncify_state (i32.const 0))
+    )
+    (func (export "asyncify_start_rewind") (param i32)
+        (global.set $asyncify_state (i32.const 2))
+    )
+    (func (export "asyncify_stop_rewind")
+        (global.set $asyncify_state (i32.const 0))
+    )
+    (func (export "asyncify_get_state") (result i32)
+        (global.get $asyncify_state)
+    )
+
+    ;; 4000: number of threads that are done
+    ;; 4004: length of the log
+    ;; 4096: number of entries of each thread in the log, indexed by thread ID
+    ;; 8192: the log
+    (func (export "wasi_thread_start") (param $tid i32) (param $args i32)
+        (local $count i32)
+        (local.set $count (i32.add (i32.const 4096) (i32.shl (local.get $tid) (i32.const 2))))
+        (loop $again
+            ;; Nothing is logged when the thread is rewound into `sched_yield`
+            (if (i32.eqz (global.get $asyncify_state))
+                (then
+                    (i32.store8
+                        (i32.add (i32.const 8192) (i32.load (i32.const 4004)))
+                        (i32.add (i32.const 48) (local.get $tid)))
+                    (i32.store (i32.const 4004) (i32.add (i32.load (i32.const 4004)) (i32.const 1)))
+                    (i32.store (local.get $count) (i32.add (i32.load (local.get $count)) (i32.const 1)))
+                    (if (i32.eq (i32.load (local.get $count)) (i32.const 5))
+                        (then
+                            (i32.store (i32.const 4000) (i32.add (i32.load (i32.const 4000)) (i32.const 1)))
+                            (return)
+                        )
+                    )
+                )
+            )
+            (drop (call $sched_yield))
+            (if (i32.eq (global.get $asyncify_state) (i32.const 1)) (then return))
+            (br $again)
+        )
+    )
+
+    (func $main (export "_start")
+        ;; The threads are only spawned the first time around, not when
+        ;; `_start` is rewound
+        (if (i32.eqz (global.get $asyncify_state))
+            (then
+                (i32.store (i32.const 1024) (i32.const 65536))
+                (i32.store (i32.const 1080) (i32.const 16384))
+                (if (call $thread_spawn (i32.const 1024) (i32.const 1100)) (then unreachable))
+                (if (call $thread_spawn (i32.const 1024) (i32.const 1100)) (then unreachable))
+                (if (call $thread_spawn (i32.const 1024) (i32.const 1100)) (then unreachable))
+            )
+        )
+
+        (block $done
+            (loop $wait
+                (if (i32.eqz (global.get $asyncify_state))
+                    (then (br_if $done (i32.eq (i32.load (i32.const 4000)) (i32.const 3))))
+                )
+                (drop (call $sched_yield))
+                (if (i32.eq (global.get $asyncify_state) (i32.const 1)) (then return))
+                (br $wait)
+            )
+        )
+
+        (i32.store (i32.const 0) (i32.const 8192))
+        (i32.store (i32.const 4) (i32.load (i32.const 4004)))
+        (drop (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 8)))
+    )
+)
+"#;
+
+/// Runs the module on a deterministic task manager and returns the log
+fn interleaving(seed: u64) -> String {
+    let runtime = TestRuntime::with_task_manager(|handle| {
+        let tasks = TokioTaskManager::new_deterministic(handle.clone(), seed);
+        assert!(tasks.is_single_threaded());
+        Arc::new(tasks)
+    });
+
+    let (result, stdout) = runtime.run_wat(INTERLEAVING);
+    result.unwrap();
+    String::from_utf8(stdout).unwrap()
+}
+
+#[test]
+fn test_deterministic_task_manager() {
+    let log = interleaving(7);
+    assert_eq!(log.len(), 15);
+    let threads: HashSet<char> = log.chars().collect();
+    assert_eq!(threads.len(), 3);
+    for thread in threads {
+        assert_eq!(log.matches(thread).count(), 5);
+    }
+
+    // The same seed always gives the same interleaving
+    for _ in 0..3 {
+        assert_eq!(interleaving(7), log);
+    }
+
+    // While other seeds interleave the threads differently
+    let logs: HashSet<String> = (0..8).map(interleaving).collect();
+    assert!(logs.len() > 1, "{logs:?}");
+}
@@ -0,0 +1,55 @@
+use wasmer_wasix::{WasiEnv, runtime::entropy::LimitedEntropySource};
+
+use super::TestRuntime;
+
+/// Draws 16 random bytes and then one more with `random_get`, then reads a
+/// byte from `/dev/urandom`, and writes the three errnos to stdout
+const PROGRAM: &str = r#"
+(module
+    (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+    (import "wasi_snapshot_preview1" "fd_read" (func $fd_read (param i32 i32 i32 i32) (result i32)))
+    (import "wasi_snapshot_preview1" "path_open" (func $path_open (param i32 i32 i32 i32 i32 i64 i64 i32 i32) (result i32)))
+    (import "wasi_snapshot_preview1" "random_get" (func $random_get (param i32 i32) (result i32)))
+
+    (memory 1)
+    (export "memory" (memory 0))
+
+    (data (i32.const 200) "dev/urandom")
+
+    (func $check (param i32)
+        (if (local.get 0) (then unreachable))
+    )
+
+    (func $main (export "_start")
+        (i32.store8 (i32.const 0) (call $random_get (i32.const 100) (i32.const 16)))
+        (i32.store8 (i32.const 1) (call $random_get (i32.const 100) (i32.const 1)))
+
+        (call $check (call $path_open (i32.const 3) (i32.const 0) (i32.const 200) (i32.const 11)
+            (i32.const 0) (i64.const -1) (i64.const -1) (i32.const 0) (i32.const 300)))
+        (i32.store (i32.const 400) (i32.const 100))
+        (i32.store (i32.const 404) (i32.const 1))
+        (i32.store8 (i32.const 2) (call $fd_read (i32.load (i32.const 300)) (i32.const 400) (i32.const 1) (i32.const 408)))
+
+        (i32.store (i32.const 400) (i32.const 0))
+        (i32.store (i32.const 404) (i32.const 3))
+        (call $check (call $fd_write (i32.const 1) (i32.const 400) (i32.const 1) (i32.const 408)))
+    )
+)
+"#;
+
+/// `Errno::Io`
+const EIO: u8 = 29;
+
+#[test]
+fn test_random_devices_fail_with_the_entropy_source() {
+    let mut runtime = TestRuntime::new();
+    runtime.rt.set_entropy_source(LimitedEntropySource::new(16));
+
+    let builder = WasiEnv::builder("main")
+        .dev_files(true)
+        .preopen_dir("/")
+        .unwrap();
+    let (exit_code, stdout) = runtime.spawn_wat(PROGRAM, builder);
+    assert!(exit_code.is_success());
+    assert_eq!(stdout, [0, EIO, EIO]);
+}
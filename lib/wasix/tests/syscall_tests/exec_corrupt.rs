@@ -0,0 +1,92 @@
+use std::sync::Arc;
+
+use virtual_fs::{AsyncWriteExt, FileSystem, TmpFileSystem};
+use virtual_mio::block_on;
+use wasmer_wasix_types::wasi::Errno;
+
+use super::run_wat_with;
+
+/// Counts its arguments and environment variables, tries to exec a binary
+/// that can't run with other arguments and variables, then counts them again
+/// and writes the counts and the result of the exec to stdout.
+const PROGRAM: &str = r#"
+(module
+    (import "wasi_snapshot_preview1" "args_sizes_get" (func $args_sizes_get (param i32 i32) (result i32)))
+    (import "wasi_snapshot_preview1" "environ_sizes_get" (func $environ_sizes_get (param i32 i32) (result i32)))
+    (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+    (import "wasix_32v1" "proc_exec3" (func $proc_exec3 (param i32 i32 i32 i32 i32 i32 i32 i32 i32) (result i32)))
+
+    (memory 1)
+    (export "memory" (memory 0))
+
+    (data (i32.const 100) "/prog/exec.wasm")
+    (data (i32.const 140) "corrupt\nx\ny")
+    (data (i32.const 160) "FOO=bar")
+
+    (func $check (param i32)
+        (if (local.get 0) (then unreachable))
+    )
+
+    (func $main (export "_start")
+        (call $check (call $args_sizes_get (i32.const 304) (i32.const 400)))
+        (call $check (call $environ_sizes_get (i32.const 308) (i32.const 400)))
+
+        (i32.store (i32.const 300) (call $proc_exec3 (i32.const 100) (i32.const 15) (i32.const 140) (i32.const 11)
+            (i32.const 160) (i32.const 7) (i32.const 0) (i32.const 0) (i32.const 0)))
+
+        (call $check (call $args_sizes_get (i32.const 312) (i32.const 400)))
+        (call $check (call $environ_sizes_get (i32.const 316) (i32.const 400)))
+
+        (i32.store (i32.const 0) (i32.const 300))
+        (i32.store (i32.const 4) (i32.const 20))
+        (call $check (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 8)))
+    )
+)
+"#;
+
+/// Runs [`PROGRAM`] with `binary` as the binary that it tries to exec and
+/// checks that the caller was left alone
+fn assert_exec_fails(binary: &[u8]) {
+    let prog = TmpFileSystem::new();
+    let mut file = prog
+        .new_open_options()
+        .create(true)
+        .write(true)
+        .open("/exec.wasm")
+        .unwrap();
+    block_on(file.write_all(binary)).unwrap();
+    drop(file);
+
+    let stdout = run_wat_with(PROGRAM, |runner| {
+        runner.with_mount("/prog".to_string(), Arc::new(prog));
+    });
+    let word = |at: usize| u32::from_le_bytes(stdout[at..at + 4].try_into().unwrap());
+
+    assert_eq!(word(0), Errno::Noexec as u32);
+
+    // The arguments and the variables of the caller were not swapped for
+    // the ones of the new image
+    assert_eq!(word(4), 1);
+    assert_eq!(word(12), word(4));
+    assert_eq!(word(16), word(8));
+}
+
+#[test]
+fn test_exec_of_a_corrupt_binary_leaves_the_caller_alone() {
+    // A wasm header followed by garbage
+    assert_exec_fails(b"\0asm\x01\0\0\0not a module");
+}
+
+#[test]
+fn test_exec_of_a_binary_with_a_missing_import_leaves_the_caller_alone() {
+    // Compiles fine, but nothing provides the import
+    assert_exec_fails(
+        br#"
+        (module
+            (import "wasix_32v1" "no_such_syscall" (func (param i32) (result i32)))
+            (memory (export "memory") 1)
+            (func (export "_start"))
+        )
+        "#,
+    );
+}
@@ -0,0 +1,103 @@
+use std::sync::Arc;
+
+use virtual_fs::{AsyncWriteExt, FileSystem, TmpFileSystem};
+use virtual_mio::block_on;
+use wasmer_wasix::WasiEnv;
+use wasmer_wasix_types::wasi::{Disposition, Signal, SignalDisposition};
+
+use super::TestRuntime;
+
+/// The first image installs a handler for the signals and raises `SIGINT`,
+/// the handler writes `h` to stdout. It then execs itself. The second image
+/// writes the number of signals with a disposition and the first of them to
+/// stdout and raises `SIGINT` again, which must now terminate it.
+const PROGRAM: &str = r#"
+(module
+    (import "wasi_snapshot_preview1" "args_sizes_get" (func $args_sizes_get (param i32 i32) (result i32)))
+    (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+    (import "wasi_snapshot_preview1" "proc_raise" (func $proc_raise (param i32) (result i32)))
+    (import "wasix_32v1" "callback_signal" (func $callback_signal (param i32 i32)))
+    (import "wasix_32v1" "proc_signals_sizes_get" (func $proc_signals_sizes_get (param i32) (result i32)))
+    (import "wasix_32v1" "proc_signals_get" (func $proc_signals_get (param i32) (result i32)))
+    (import "wasix_32v1" "proc_exec3" (func $proc_exec3 (param i32 i32 i32 i32 i32 i32 i32 i32 i32) (result i32)))
+
+    (memory 1)
+    (export "memory" (memory 0))
+
+    (data (i32.const 100) "handler")
+    (data (i32.const 120) "h")
+    (data (i32.const 140) "/prog/main.wasm")
+    (data (i32.const 160) "main\nexeced")
+    (data (i32.const 180) "survived")
+
+    (func $check (param i32)
+        (if (local.get 0) (then unreachable))
+    )
+
+    (func $write (param $ptr i32) (param $len i32)
+        (i32.store (i32.const 0) (local.get $ptr))
+        (i32.store (i32.const 4) (local.get $len))
+        (call $check (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 8)))
+    )
+
+    (func $handler (export "handler") (param i32)
+        (call $write (i32.const 120) (i32.const 1))
+    )
+
+    (func $main (export "_start")
+        (call $check (call $args_sizes_get (i32.const 16) (i32.const 20)))
+        (if (i32.eq (i32.load (i32.const 16)) (i32.const 1))
+            (then
+                (call $callback_signal (i32.const 100) (i32.const 7))
+                (call $check (call $proc_raise (i32.const 2)))
+
+                (call $check (call $proc_exec3 (i32.const 140) (i32.const 15) (i32.const 160) (i32.const 11)
+                    (i32.const 0) (i32.const 0) (i32.const 0) (i32.const 0) (i32.const 0)))
+                unreachable
+            )
+        )
+
+        (call $check (call $proc_signals_sizes_get (i32.const 300)))
+        (call $check (call $proc_signals_get (i32.const 304)))
+        (call $write (i32.const 300) (i32.const 5))
+
+        (call $check (call $proc_raise (i32.const 2)))
+        (call $write (i32.const 180) (i32.const 8))
+    )
+)
+"#;
+
+#[test]
+fn test_exec_resets_handled_signals_to_their_default() {
+    let runtime = TestRuntime::new();
+
+    let wasm = wasmer::wat2wasm(PROGRAM.as_bytes()).unwrap();
+
+    // The program execs itself from the file system
+    let fs = TmpFileSystem::new();
+    fs.create_dir(std::path::Path::new("/prog")).unwrap();
+    let mut file = fs
+        .new_open_options()
+        .create(true)
+        .write(true)
+        .open("/prog/main.wasm")
+        .unwrap();
+    block_on(file.write_all(&wasm)).unwrap();
+
+    let builder = WasiEnv::builder("main")
+        .fs(Arc::new(fs) as Arc<dyn FileSystem + Send + Sync>)
+        .signal(SignalDisposition {
+            sig: Signal::Sigpipe,
+            disp: Disposition::Ignore,
+        })
+        .signal(SignalDisposition {
+            sig: Signal::Sigusr1,
+            disp: Disposition::Default,
+        });
+    let (exit_code, stdout) = runtime.spawn_wat(&wasm, builder);
+
+    // The handler ran in the first image only, the second one was
+    // terminated by the signal and kept nothing but the ignored `SIGPIPE`
+    assert!(!exit_code.is_success());
+    assert_eq!(stdout, [b'h', 1, 0, 0, 0, Signal::Sigpipe as u8]);
+}
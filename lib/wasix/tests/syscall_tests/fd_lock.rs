@@ -0,0 +1,130 @@
+use wasmer_wasix_types::wasi::Errno;
+
+use super::run_wat;
+
+/// The parent takes an exclusive lock on `tmp/lock` and forks. The child
+/// closes the descriptor it inherited (which shares the lock), opens the
+/// file again and tries to take the lock without waiting, it sends the
+/// result to the parent through a pipe and then waits for the lock. Once
+/// the parent got the result it writes it and `p` to stdout and releases
+/// the lock by closing the file (with `$release`), which lets the child
+/// write `c`.
+const LOCK_PROGRAM: &str = r#"
+(module
+    (import "wasi_snapshot_preview1" "path_open" (func $path_open (param i32 i32 i32 i32 i32 i64 i64 i32 i32) (result i32)))
+    (import "wasi_snapshot_preview1" "fd_close" (func $fd_close (param i32) (result i32)))
+    (import "wasi_snapshot_preview1" "fd_renumber" (func $fd_renumber (param i32 i32) (result i32)))
+    (import "wasi_snapshot_preview1" "fd_read" (func $fd_read (param i32 i32 i32 i32) (result i32)))
+    (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+    (import "wasix_32v1" "fd_pipe" (func $fd_pipe (param i32 i32) (result i32)))
+    (import "wasix_32v1" "fd_lock" (func $fd_lock (param i32 i32) (result i32)))
+    (import "wasix_32v1" "proc_fork" (func $proc_fork (param i32 i32) (result i32)))
+    (import "wasix_32v1" "proc_join" (func $proc_join (param i32 i32 i32) (result i32)))
+
+    ;; Only a process with a shared memory can be forked
+    (import "env" "memory" (memory 1 16 shared))
+
+    (global $__stack_low (export "__stack_low") i32 (i32.const 16384))
+    (global $__stack_pointer (export "__stack_pointer") (mut i32) (i32.const 32768))
+
+    (data (i32.const 100) "tmp/lock")
+    (data (i32.const 110) "p")
+    (data (i32.const 111) "c")
+
+    ;; `_start` is the only function that is ever unwound and it keeps
+    ;; nothing on the stack, so asyncify just needs to track its state
+    (global $asyncify_state (mut i32) (i32.const 0))
+    (func (export "asyncify_start_unwind") (param i32)
+        (global.set $asyncify_state (i32.const 1))
+    )
+    (func (export "asyncify_stop_unwind")
+        (global.set $asyncify_state (i32.const 0))
+    )
+    (func (export "asyncify_start_rewind") (param i32)
+        (global.set $asyncify_state (i32.const 2))
+    )
+    (func (export "asyncify_stop_rewind")
+        (global.set $asyncify_state (i32.const 0))
+    )
+    (func (export "asyncify_get_state") (result i32)
+        (global.get $asyncify_state)
+    )
+
+    (func $check (param i32)
+        (if (local.get 0) (then unreachable))
+    )
+
+    (func $open (param $ret i32)
+        (call $check (call $path_open (i32.const 3) (i32.const 0) (i32.const 100) (i32.const 8)
+            (i32.const 1) (i64.const -1) (i64.const -1) (i32.const 0) (local.get $ret)))
+    )
+
+    (func $write (param $fd i32) (param $ptr i32)
+        (i32.store (i32.const 0) (local.get $ptr))
+        (i32.store (i32.const 4) (i32.const 1))
+        (call $check (call $fd_write (local.get $fd) (i32.const 0) (i32.const 1) (i32.const 8)))
+    )
+
+    (func $main (export "_start")
+        ;; The lock is only taken the first time around, not when `_start`
+        ;; is rewound
+        (if (i32.eqz (global.get $asyncify_state))
+            (then
+                (call $open (i32.const 200))
+                (call $check (call $fd_lock (i32.load (i32.const 200)) (i32.const 1)))
+                (call $check (call $fd_pipe (i32.const 204) (i32.const 208)))
+            )
+        )
+
+        (call $check (call $proc_fork (i32.const 1) (i32.const 212)))
+        (if (i32.eq (global.get $asyncify_state) (i32.const 1)) (then return))
+
+        ;; The child
+        (if (i32.eqz (i32.load (i32.const 212)))
+            (then
+                (call $check (call $fd_close (i32.load (i32.const 200))))
+                (call $open (i32.const 216))
+                (i32.store8 (i32.const 300) (call $fd_lock (i32.load (i32.const 216)) (i32.const 3)))
+                (call $write (i32.load (i32.const 208)) (i32.const 300))
+                (call $check (call $fd_lock (i32.load (i32.const 216)) (i32.const 1)))
+                (call $write (i32.const 1) (i32.const 111))
+                (return)
+            )
+        )
+
+        ;; The parent
+        (i32.store (i32.const 16) (i32.const 301))
+        (i32.store (i32.const 20) (i32.const 1))
+        (call $check (call $fd_read (i32.load (i32.const 204)) (i32.const 16) (i32.const 1) (i32.const 24)))
+        (call $write (i32.const 1) (i32.const 301))
+        (call $write (i32.const 1) (i32.const 110))
+        $release
+
+        (i32.store8 (i32.const 400) (i32.const 1))
+        (i32.store (i32.const 404) (i32.load (i32.const 212)))
+        (call $check (call $proc_join (i32.const 400) (i32.const 0) (i32.const 420)))
+    )
+)
+"#;
+
+#[test]
+fn test_fd_lock_between_processes() {
+    let program = LOCK_PROGRAM.replace(
+        "$release",
+        "(call $check (call $fd_close (i32.load (i32.const 200))))",
+    );
+    let stdout = run_wat(&program);
+    assert_eq!(stdout, [Errno::Again as u8, b'p', b'c']);
+}
+
+#[test]
+fn test_fd_lock_is_released_when_renumbered_over() {
+    // Renumbering the write end of the pipe over the locked descriptor
+    // closes it without going through `fd_close`
+    let program = LOCK_PROGRAM.replace(
+        "$release",
+        "(call $check (call $fd_renumber (i32.load (i32.const 208)) (i32.load (i32.const 200))))",
+    );
+    let stdout = run_wat(&program);
+    assert_eq!(stdout, [Errno::Again as u8, b'p', b'c']);
+}
@@ -0,0 +1,67 @@
+use super::run_wat;
+
+#[test]
+fn test_fd_read_fills_iovecs_in_order() {
+    let stdout = run_wat(br#"
+    (module
+        (import "wasi_snapshot_preview1" "fd_read" (func $fd_read (param i32 i32 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+        (import "wasix_32v1" "fd_pipe" (func $fd_pipe (param i32 i32) (result i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        (data (i32.const 100) "0123456789")
+        (data (i32.const 120) "ABCDEFGH")
+
+        (func $check (param i32)
+            (if (local.get 0) (then unreachable))
+        )
+
+        ;; Writes `len` bytes at `ptr` to the pipe
+        (func $write (param $ptr i32) (param $len i32)
+            (i32.store (i32.const 0) (local.get $ptr))
+            (i32.store (i32.const 4) (local.get $len))
+            (call $check (call $fd_write (i32.load (i32.const 204)) (i32.const 0) (i32.const 1) (i32.const 8)))
+        )
+
+        ;; Reads from the pipe into three iovecs of 4 bytes at `ptr`, `ptr + 8`
+        ;; and `ptr + 16`, the bytes in between must never be touched
+        (func $read (param $ptr i32) (param $nread i32)
+            (memory.fill (local.get $ptr) (i32.const 46) (i32.const 20))
+            (i32.store (i32.const 16) (local.get $ptr))
+            (i32.store (i32.const 20) (i32.const 4))
+            (i32.store (i32.const 24) (i32.add (local.get $ptr) (i32.const 8)))
+            (i32.store (i32.const 28) (i32.const 4))
+            (i32.store (i32.const 32) (i32.add (local.get $ptr) (i32.const 16)))
+            (i32.store (i32.const 36) (i32.const 4))
+            (call $check (call $fd_read (i32.load (i32.const 200)) (i32.const 16) (i32.const 3) (local.get $nread)))
+        )
+
+        (func $main (export "_start")
+            (call $check (call $fd_pipe (i32.const 200) (i32.const 204)))
+
+            ;; A short read ends in the last iovec
+            (call $write (i32.const 100) (i32.const 10))
+            (call $read (i32.const 400) (i32.const 300))
+
+            ;; A short read that ends right at the boundary of an iovec
+            ;; returns without waiting for more data
+            (call $write (i32.const 120) (i32.const 8))
+            (call $read (i32.const 440) (i32.const 304))
+
+            ;; Send the results to stdout
+            (i32.store (i32.const 0) (i32.const 300))
+            (i32.store (i32.const 4) (i32.const 160))
+            (call $check (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 8)))
+        )
+    )
+    "#);
+    let value = |offset: usize| u32::from_le_bytes(stdout[offset..offset + 4].try_into().unwrap());
+
+    assert_eq!(value(0), 10);
+    assert_eq!(&stdout[100..120], b"0123....4567....89..");
+
+    assert_eq!(value(4), 8);
+    assert_eq!(&stdout[140..160], b"ABCD....EFGH........");
+}
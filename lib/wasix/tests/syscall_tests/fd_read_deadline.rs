@@ -0,0 +1,128 @@
+use std::time::Duration;
+
+use virtual_fs::{AsyncReadExt, AsyncWriteExt};
+use virtual_mio::block_on;
+use wasmer_wasix::{Pipe, WasiEnv};
+use wasmer_wasix_types::wasi::Errno;
+
+use super::{TestRuntime, run_wat};
+
+/// Reads from an idle pipe with a deadline 50ms away, then writes `hi` to
+/// the pipe and reads it with the deadline that has passed by now
+const PROGRAM: &[u8] = br#"
+(module
+    (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+    (import "wasi_snapshot_preview1" "clock_time_get" (func $clock_time_get (param i32 i64 i32) (result i32)))
+    (import "wasix_32v1" "fd_pipe" (func $fd_pipe (param i32 i32) (result i32)))
+    (import "wasix_32v1" "fd_read_deadline" (func $fd_read_deadline (param i32 i32 i32 i32 i32) (result i32)))
+
+    (memory 1)
+    (export "memory" (memory 0))
+
+    (data (i32.const 400) "hi")
+
+    (func $check (param i32)
+        (if (local.get 0) (then unreachable))
+    )
+
+    (func $main (export "_start")
+        (call $check (call $fd_pipe (i32.const 100) (i32.const 104)))
+
+        ;; The deadline is 50ms after the first reading of the clock
+        (call $check (call $clock_time_get (i32.const 1) (i64.const 1) (i32.const 304)))
+        (i32.store8 (i32.const 128) (i32.const 1))
+        (i64.store (i32.const 136) (i64.add (i64.load (i32.const 304)) (i64.const 50000000)))
+
+        (i32.store (i32.const 160) (i32.const 320))
+        (i32.store (i32.const 164) (i32.const 16))
+        (i32.store (i32.const 288)
+            (call $fd_read_deadline (i32.load (i32.const 100)) (i32.const 160) (i32.const 1)
+                (i32.const 128) (i32.const 296)))
+        (call $check (call $clock_time_get (i32.const 1) (i64.const 1) (i32.const 312)))
+
+        (i32.store (i32.const 176) (i32.const 400))
+        (i32.store (i32.const 180) (i32.const 2))
+        (call $check (call $fd_write (i32.load (i32.const 104)) (i32.const 176) (i32.const 1) (i32.const 184)))
+        (i32.store (i32.const 292)
+            (call $fd_read_deadline (i32.load (i32.const 100)) (i32.const 160) (i32.const 1)
+                (i32.const 128) (i32.const 296)))
+
+        ;; Send the results, the number of bytes read, the clocks and the
+        ;; data to stdout
+        (i32.store (i32.const 0) (i32.const 288))
+        (i32.store (i32.const 4) (i32.const 34))
+        (call $check (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 8)))
+    )
+)
+"#;
+
+#[test]
+fn test_fd_read_deadline_times_out_on_an_idle_pipe() {
+    let stdout = run_wat(PROGRAM);
+    let dword = |at: usize| u64::from_le_bytes(stdout[at..at + 8].try_into().unwrap());
+    let word = |at: usize| u32::from_le_bytes(stdout[at..at + 4].try_into().unwrap());
+
+    assert_eq!(word(0), Errno::Timedout as u32);
+    let waited = Duration::from_nanos(dword(24) - dword(16));
+    assert!(waited >= Duration::from_millis(45), "{waited:?}");
+
+    // Data that is there is read even when the deadline has passed
+    assert_eq!(word(4), Errno::Success as u32);
+    assert_eq!(word(8), 2);
+    assert_eq!(&stdout[32..34], b"hi");
+}
+
+/// Reads from stdin with the deadline `u64::MAX` and writes the result, the
+/// number of bytes read and the data to stdout
+const NO_DEADLINE_PROGRAM: &[u8] = br#"
+(module
+    (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+    (import "wasix_32v1" "fd_read_deadline" (func $fd_read_deadline (param i32 i32 i32 i32 i32) (result i32)))
+
+    (memory 1)
+    (export "memory" (memory 0))
+
+    (func $check (param i32)
+        (if (local.get 0) (then unreachable))
+    )
+
+    (func $main (export "_start")
+        (i32.store8 (i32.const 128) (i32.const 1))
+        (i64.store (i32.const 136) (i64.const -1))
+
+        (i32.store (i32.const 160) (i32.const 296))
+        (i32.store (i32.const 164) (i32.const 2))
+        (i32.store (i32.const 288)
+            (call $fd_read_deadline (i32.const 0) (i32.const 160) (i32.const 1)
+                (i32.const 128) (i32.const 292)))
+
+        (i32.store (i32.const 0) (i32.const 288))
+        (i32.store (i32.const 4) (i32.const 10))
+        (call $check (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 8)))
+    )
+)
+"#;
+
+#[test]
+fn test_fd_read_deadline_of_u64_max_never_times_out() {
+    let runtime = TestRuntime::new();
+    let (mut stdin_tx, stdin_rx) = Pipe::channel();
+    let builder = WasiEnv::builder("main").stdin(Box::new(stdin_rx));
+    let (env, mut stdout_rx) = runtime.build_env(builder);
+    let mut task = runtime
+        .start(runtime.module(NO_DEADLINE_PROGRAM), env)
+        .unwrap();
+
+    // The read is still waiting by the time the data shows up
+    std::thread::sleep(Duration::from_millis(50));
+    block_on(stdin_tx.write_all(b"hi")).unwrap();
+
+    let mut stdout = [0u8; 10];
+    block_on(stdout_rx.read_exact(&mut stdout)).unwrap();
+    let exit_code = block_on(task.wait_finished()).unwrap();
+    assert!(exit_code.is_success());
+    let word = |at: usize| u32::from_le_bytes(stdout[at..at + 4].try_into().unwrap());
+    assert_eq!(word(0), Errno::Success as u32);
+    assert_eq!(word(4), 2);
+    assert_eq!(&stdout[8..10], b"hi");
+}
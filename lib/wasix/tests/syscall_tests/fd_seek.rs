@@ -0,0 +1,90 @@
+use wasmer_wasix_types::wasi::Errno;
+
+use super::run_wat;
+
+#[test]
+fn test_fd_seek_out_of_range() {
+    let stdout = run_wat(br#"
+    (module
+        (import "wasi_snapshot_preview1" "path_open" (func $path_open (param i32 i32 i32 i32 i32 i64 i64 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "fd_seek" (func $fd_seek (param i32 i64 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        (data (i32.const 100) "tmp/seek.txt")
+        (data (i32.const 120) "hello")
+
+        (func $check (param i32)
+            (if (local.get 0) (then unreachable))
+        )
+
+        ;; Seeks the file and stores the errno at $result, and the new
+        ;; offset at $result + 16 when the seek succeeds
+        (func $seek (param $offset i64) (param $whence i32) (param $result i32)
+            (i32.store (local.get $result)
+                (call $fd_seek (i32.load (i32.const 200)) (local.get $offset) (local.get $whence)
+                    (i32.add (local.get $result) (i32.const 16))))
+        )
+
+        (func $main (export "_start")
+            ;; Create tmp/seek.txt and write 5 bytes into it
+            (call $check (call $path_open (i32.const 3) (i32.const 0) (i32.const 100) (i32.const 12)
+                (i32.const 1) (i64.const -1) (i64.const -1) (i32.const 0) (i32.const 200)))
+            (i32.store (i32.const 0) (i32.const 120))
+            (i32.store (i32.const 4) (i32.const 5))
+            (call $check (call $fd_write (i32.load (i32.const 200)) (i32.const 0) (i32.const 1) (i32.const 8)))
+
+            ;; Going past the largest offset overflows and leaves the offset alone
+            (call $seek (i64.const 0x7fffffffffffffff) (i32.const 0) (i32.const 1024))
+            (call $seek (i64.const 1) (i32.const 1) (i32.const 1056))
+            (call $seek (i64.const 0) (i32.const 1) (i32.const 1088))
+            (call $seek (i64.const 0x7fffffffffffffff) (i32.const 2) (i32.const 1120))
+
+            ;; Going before the start is invalid and leaves the offset alone
+            (call $seek (i64.const 2) (i32.const 0) (i32.const 1152))
+            (call $seek (i64.const -3) (i32.const 1) (i32.const 1184))
+            (call $seek (i64.const -6) (i32.const 2) (i32.const 1216))
+            (call $seek (i64.const 0) (i32.const 1) (i32.const 1248))
+
+            ;; Landing exactly on the start is fine
+            (call $seek (i64.const -2) (i32.const 1) (i32.const 1280))
+            (call $seek (i64.const -5) (i32.const 2) (i32.const 1312))
+
+            ;; Send the results to stdout
+            (i32.store (i32.const 0) (i32.const 1024))
+            (i32.store (i32.const 4) (i32.const 320))
+            (call $check (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 8)))
+        )
+    )
+    "#);
+    let results = stdout
+        .chunks(32)
+        .map(|result| {
+            let errno = u32::from_le_bytes(result[0..4].try_into().unwrap());
+            let offset = u64::from_le_bytes(result[16..24].try_into().unwrap());
+            if errno == Errno::Success as u32 {
+                Ok(offset)
+            } else {
+                Err(errno)
+            }
+        })
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        results,
+        vec![
+            Ok(i64::MAX as u64),
+            Err(Errno::Overflow as u32),
+            Ok(i64::MAX as u64),
+            Err(Errno::Overflow as u32),
+            Ok(2),
+            Err(Errno::Inval as u32),
+            Err(Errno::Inval as u32),
+            Ok(2),
+            Ok(0),
+            Ok(0),
+        ]
+    );
+}
@@ -0,0 +1,217 @@
+use wasmer_wasix_types::wasi::Errno;
+
+use super::run_wat;
+
+/// The parts of a `Filestat` that identify the file
+#[derive(Debug, PartialEq, Eq)]
+struct FileIdentity {
+    ino: u64,
+    nlink: u64,
+}
+
+impl FileIdentity {
+    fn parse(stat: &[u8]) -> Self {
+        Self {
+            ino: u64::from_le_bytes(stat[8..16].try_into().unwrap()),
+            nlink: u64::from_le_bytes(stat[24..32].try_into().unwrap()),
+        }
+    }
+}
+
+#[test]
+fn test_hard_link_filestat() {
+    let stdout = run_wat(br#"
+    (module
+        (import "wasi_snapshot_preview1" "path_open" (func $path_open (param i32 i32 i32 i32 i32 i64 i64 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "path_link" (func $path_link (param i32 i32 i32 i32 i32 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "path_unlink_file" (func $path_unlink_file (param i32 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "path_filestat_get" (func $path_filestat_get (param i32 i32 i32 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "fd_filestat_get" (func $fd_filestat_get (param i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        (data (i32.const 100) "tmp/a.txt")
+        (data (i32.const 120) "tmp/b.txt")
+
+        (func $check (param i32)
+            (if (local.get 0) (then unreachable))
+        )
+
+        (func $main (export "_start")
+            ;; Create tmp/a.txt and hard link it as tmp/b.txt
+            (call $check (call $path_open (i32.const 3) (i32.const 0) (i32.const 100) (i32.const 9)
+                (i32.const 1) (i64.const -1) (i64.const -1) (i32.const 0) (i32.const 200)))
+            (call $check (call $path_link (i32.const 3) (i32.const 0) (i32.const 100) (i32.const 9)
+                (i32.const 3) (i32.const 120) (i32.const 9)))
+
+            ;; Both names, and the open descriptor, are the same file
+            (call $check (call $path_filestat_get (i32.const 3) (i32.const 0) (i32.const 100) (i32.const 9) (i32.const 1024)))
+            (call $check (call $path_filestat_get (i32.const 3) (i32.const 0) (i32.const 120) (i32.const 9) (i32.const 1088)))
+            (call $check (call $fd_filestat_get (i32.load (i32.const 200)) (i32.const 1152)))
+
+            ;; Removing one of the names leaves a single link behind
+            (call $check (call $path_unlink_file (i32.const 3) (i32.const 100) (i32.const 9)))
+            (call $check (call $path_filestat_get (i32.const 3) (i32.const 0) (i32.const 120) (i32.const 9) (i32.const 1216)))
+
+            ;; Send the four stats to stdout
+            (i32.store (i32.const 0) (i32.const 1024))
+            (i32.store (i32.const 4) (i32.const 256))
+            (call $check (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 8)))
+        )
+    )
+    "#);
+    let stats: Vec<_> = stdout.chunks(64).map(FileIdentity::parse).collect();
+    assert_eq!(stats.len(), 4);
+
+    let linked = &stats[0];
+    assert_ne!(linked.ino, 0);
+    assert_eq!(linked.nlink, 2);
+    assert_eq!(stats[1], *linked);
+    assert_eq!(stats[2], *linked);
+    assert_eq!(
+        stats[3],
+        FileIdentity {
+            ino: linked.ino,
+            nlink: 1,
+        }
+    );
+}
+
+/// The access and modification times of a `Filestat`
+fn file_times(stat: &[u8]) -> (u64, u64) {
+    (
+        u64::from_le_bytes(stat[40..48].try_into().unwrap()),
+        u64::from_le_bytes(stat[48..56].try_into().unwrap()),
+    )
+}
+
+#[test]
+fn test_path_filestat_set_times_symlink() {
+    let stdout = run_wat(br#"
+    (module
+        (import "wasi_snapshot_preview1" "path_open" (func $path_open (param i32 i32 i32 i32 i32 i64 i64 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "path_symlink" (func $path_symlink (param i32 i32 i32 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "path_filestat_get" (func $path_filestat_get (param i32 i32 i32 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "path_filestat_set_times" (func $path_filestat_set_times (param i32 i32 i32 i32 i64 i64 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "fd_close" (func $fd_close (param i32) (result i32)))
+        (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        (data (i32.const 100) "tmp/a.txt")
+        (data (i32.const 120) "tmp/link")
+        (data (i32.const 140) "a.txt")
+
+        (func $check (param i32)
+            (if (local.get 0) (then unreachable))
+        )
+
+        (func $main (export "_start")
+            ;; Create tmp/a.txt and a symlink to it at tmp/link
+            (call $check (call $path_open (i32.const 3) (i32.const 0) (i32.const 100) (i32.const 9)
+                (i32.const 1) (i64.const -1) (i64.const -1) (i32.const 0) (i32.const 200)))
+            (call $check (call $fd_close (i32.load (i32.const 200))))
+            (call $check (call $path_symlink (i32.const 140) (i32.const 5) (i32.const 3) (i32.const 120) (i32.const 8)))
+
+            ;; Without following the symlink the file is left alone
+            (call $check (call $path_filestat_set_times (i32.const 3) (i32.const 0) (i32.const 120) (i32.const 8)
+                (i64.const 1000) (i64.const 2000) (i32.const 5)))
+            (call $check (call $path_filestat_get (i32.const 3) (i32.const 0) (i32.const 100) (i32.const 9) (i32.const 1024)))
+
+            ;; Following it sets the times of the file
+            (call $check (call $path_filestat_set_times (i32.const 3) (i32.const 1) (i32.const 120) (i32.const 8)
+                (i64.const 3000) (i64.const 4000) (i32.const 5)))
+            (call $check (call $path_filestat_get (i32.const 3) (i32.const 0) (i32.const 100) (i32.const 9) (i32.const 1088)))
+
+            ;; Only touch the modification time, leaving the access time as is
+            (call $check (call $path_filestat_set_times (i32.const 3) (i32.const 1) (i32.const 120) (i32.const 8)
+                (i64.const 0) (i64.const 0) (i32.const 8)))
+            (call $check (call $path_filestat_get (i32.const 3) (i32.const 0) (i32.const 100) (i32.const 9) (i32.const 1152)))
+
+            ;; Send the three stats to stdout
+            (i32.store (i32.const 0) (i32.const 1024))
+            (i32.store (i32.const 4) (i32.const 192))
+            (call $check (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 8)))
+        )
+    )
+    "#);
+    let times: Vec<_> = stdout.chunks(64).map(file_times).collect();
+    assert_eq!(times.len(), 3);
+
+    assert_ne!(times[0], (1000, 2000));
+    assert_eq!(times[1], (3000, 4000));
+    assert_eq!(times[2].0, 3000);
+    assert!(times[2].1 > 4000);
+}
+
+#[test]
+fn test_path_filestat_set_times_directory() {
+    let stdout = run_wat(br#"
+    (module
+        (import "wasi_snapshot_preview1" "path_create_directory" (func $path_create_directory (param i32 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "path_filestat_get" (func $path_filestat_get (param i32 i32 i32 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "path_filestat_set_times" (func $path_filestat_set_times (param i32 i32 i32 i32 i64 i64 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        (data (i32.const 100) "tmp/dir")
+
+        (func $check (param i32)
+            (if (local.get 0) (then unreachable))
+        )
+
+        (func $main (export "_start")
+            (call $check (call $path_create_directory (i32.const 3) (i32.const 100) (i32.const 7)))
+
+            (call $check (call $path_filestat_set_times (i32.const 3) (i32.const 0) (i32.const 100) (i32.const 7)
+                (i64.const 5000) (i64.const 6000) (i32.const 5)))
+            (call $check (call $path_filestat_get (i32.const 3) (i32.const 0) (i32.const 100) (i32.const 7) (i32.const 1024)))
+
+            ;; Send the stat to stdout
+            (i32.store (i32.const 0) (i32.const 1024))
+            (i32.store (i32.const 4) (i32.const 64))
+            (call $check (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 8)))
+        )
+    )
+    "#);
+    assert_eq!(file_times(&stdout), (5000, 6000));
+}
+
+#[test]
+fn test_path_filestat_get_symlink_loop() {
+    let stdout = run_wat(br#"
+    (module
+        (import "wasi_snapshot_preview1" "path_symlink" (func $path_symlink (param i32 i32 i32 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "path_filestat_get" (func $path_filestat_get (param i32 i32 i32 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        (data (i32.const 100) "tmp/loop")
+        (data (i32.const 120) "loop")
+
+        (func $check (param i32)
+            (if (local.get 0) (then unreachable))
+        )
+
+        (func $main (export "_start")
+            ;; tmp/loop is a symlink to itself
+            (call $check (call $path_symlink (i32.const 120) (i32.const 4) (i32.const 3) (i32.const 100) (i32.const 8)))
+            (i32.store8 (i32.const 1024)
+                (call $path_filestat_get (i32.const 3) (i32.const 1) (i32.const 100) (i32.const 8) (i32.const 1088)))
+
+            ;; Send the result to stdout
+            (i32.store (i32.const 0) (i32.const 1024))
+            (i32.store (i32.const 4) (i32.const 1))
+            (call $check (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 8)))
+        )
+    )
+    "#);
+    assert_eq!(stdout, [Errno::Loop as u8]);
+}
@@ -0,0 +1,112 @@
+use std::sync::{Arc, Mutex};
+
+use virtual_fs::{AsyncReadExt, AsyncWriteExt};
+use virtual_mio::block_on;
+use wasmer_wasix::{ForkMode, Pipe, WasiEnv, WasiProcessId};
+
+use super::TestRuntime;
+
+#[test]
+fn test_on_fork_callback() {
+    let mut runtime = TestRuntime::new();
+
+    let forks: Arc<Mutex<Vec<(WasiProcessId, WasiProcessId)>>> = Default::default();
+
+    {
+        let forks = forks.clone();
+        runtime
+            .rt
+            .set_on_fork(move |parent, child| forks.lock().unwrap().push((parent, child)));
+    }
+
+    // The program forks once to write into a pipe from the child, only the
+    // fork matters here and not how the program itself exits
+    let _ = runtime.run_wat(include_bytes!("../example-pipe.wasm"));
+
+    let forks = forks.lock().unwrap();
+    assert_eq!(forks.len(), 1);
+    let (parent, child) = forks[0];
+    assert_ne!(parent, child);
+}
+
+/// Forks with `proc_fork_env`, then the child writes `c`, waits on stdin and
+/// exits with code 3 without calling `proc_exec`. The parent writes `p` once
+/// it is back in control.
+const VFORK_EXIT_PROGRAM: &str = r#"
+(module
+    (import "wasi_snapshot_preview1" "fd_read" (func $fd_read (param i32 i32 i32 i32) (result i32)))
+    (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+    (import "wasix_32v1" "proc_fork_env" (func $proc_fork_env (param i32) (result i32)))
+    (import "wasix_32v1" "proc_exit2" (func $proc_exit2 (param i32)))
+
+    (memory 1)
+    (export "memory" (memory 0))
+
+    (data (i32.const 100) "c")
+    (data (i32.const 101) "p")
+
+    (func $check (param i32)
+        (if (local.get 0) (then unreachable))
+    )
+
+    (func $write (param $ptr i32)
+        (i32.store (i32.const 0) (local.get $ptr))
+        (i32.store (i32.const 4) (i32.const 1))
+        (call $check (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 8)))
+    )
+
+    (func $main (export "_start")
+        (call $check (call $proc_fork_env (i32.const 200)))
+
+        ;; The child
+        (call $write (i32.const 100))
+        (i32.store (i32.const 16) (i32.const 300))
+        (i32.store (i32.const 20) (i32.const 1))
+        (call $check (call $fd_read (i32.const 0) (i32.const 16) (i32.const 1) (i32.const 24)))
+        (call $proc_exit2 (i32.const 3))
+
+        ;; The parent
+        (call $write (i32.const 101))
+    )
+)
+"#;
+
+#[test]
+fn test_vfork_child_exits_without_exec() {
+    let runtime = TestRuntime::new();
+
+    let (mut stdin_tx, stdin_rx) = Pipe::channel();
+    let builder = WasiEnv::builder("main").stdin(Box::new(stdin_rx));
+    let (env, mut stdout_rx) = runtime.build_env(builder);
+    let control_plane = env.control_plane.clone();
+    let parent = env.process.clone();
+
+    let mut task = runtime
+        .start(runtime.module(VFORK_EXIT_PROGRAM), env)
+        .unwrap();
+    let mut read_byte = || {
+        let mut buf = [0u8; 1];
+        block_on(stdout_rx.read_exact(&mut buf)).unwrap();
+        buf[0]
+    };
+
+    // The child runs while the parent is suspended
+    assert_eq!(read_byte(), b'c');
+    let child = control_plane
+        .processes()
+        .into_iter()
+        .find(|process| process.pid() != parent.pid())
+        .unwrap();
+    assert_eq!(child.fork_mode(), Some(ForkMode::VFork));
+    assert!(child.is_vfork_pending());
+    assert_eq!(parent.fork_mode(), None);
+
+    // Once the child exits the parent carries on
+    block_on(stdin_tx.write_all(b"x")).unwrap();
+    assert_eq!(read_byte(), b'p');
+    assert!(!child.is_vfork_pending());
+    assert_eq!(child.try_join().unwrap().unwrap().raw(), 3);
+
+    let exit_code = block_on(task.wait_finished()).unwrap();
+    assert!(exit_code.is_success());
+}
@@ -0,0 +1,158 @@
+use std::{path::Path, sync::Arc};
+
+use virtual_fs::{AsyncReadExt, AsyncWriteExt, FileSystem};
+use virtual_mio::block_on;
+use wasmer_wasix::WasiEnv;
+
+use super::TestRuntime;
+
+/// Number of records that the parent and the child each append
+const RECORDS: usize = 200;
+
+/// The parent opens `log` (which already holds `head`) to append to it and
+/// forks, then the parent appends `pppp` and the child appends `cccc` to the
+/// same descriptor (200 times each) and the parent waits for the child.
+const PROGRAM: &str = r#"
+(module
+    (import "wasi_snapshot_preview1" "path_open" (func $path_open (param i32 i32 i32 i32 i32 i64 i64 i32 i32) (result i32)))
+    (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+    (import "wasix_32v1" "proc_fork" (func $proc_fork (param i32 i32) (result i32)))
+    (import "wasix_32v1" "proc_join" (func $proc_join (param i32 i32 i32) (result i32)))
+
+    ;; Only a process with a shared memory can be forked
+    (import "env" "memory" (memory 1 16 shared))
+
+    (global $__stack_low (export "__stack_low") i32 (i32.const 16384))
+    (global $__stack_pointer (export "__stack_pointer") (mut i32) (i32.const 32768))
+
+    (data (i32.const 100) "log")
+    (data (i32.const 110) "pppp")
+    (data (i32.const 120) "cccc")
+
+    ;; `_start` is the only function that is ever unwound and it keeps
+    ;; nothing on the stack, so asyncify just needs to track its state
+    (global $asyncify_state (mut i32) (i32.const 0))
+    (func (export "asyncify_start_unwind") (param i32)
+        (global.set $asyncify_state (i32.const 1))
+    )
+    (func (export "asyncify_stop_unwind")
+        (global.set $asyncify_state (i32.const 0))
+    )
+    (func (export "asyncify_start_rewind") (param i32)
+        (global.set $asyncify_state (i32.const 2))
+    )
+    (func (export "asyncify_stop_rewind")
+        (global.set $asyncify_state (i32.const 0))
+    )
+    (func (export "asyncify_get_state") (result i32)
+        (global.get $asyncify_state)
+    )
+
+    (func $check (param i32)
+        (if (local.get 0) (then unreachable))
+    )
+
+    (func $append (param $record i32)
+        (local $i i32)
+        (loop $again
+            (i32.store (i32.const 0) (local.get $record))
+            (i32.store (i32.const 4) (i32.const 4))
+            (call $check (call $fd_write (i32.load (i32.const 200)) (i32.const 0) (i32.const 1) (i32.const 8)))
+            (local.set $i (i32.add (local.get $i) (i32.const 1)))
+            (br_if $again (i32.lt_u (local.get $i) (i32.const 200)))
+        )
+    )
+
+    (func $main (export "_start")
+        ;; The file is only opened the first time around, not when `_start`
+        ;; is rewound
+        (if (i32.eqz (global.get $asyncify_state))
+            (then
+                (call $check (call $path_open (i32.const 3) (i32.const 0) (i32.const 100) (i32.const 3)
+                    (i32.const 0) (i64.const -1) (i64.const -1) (i32.const 1) (i32.const 200)))
+            )
+        )
+
+        (call $check (call $proc_fork (i32.const 1) (i32.const 212)))
+        (if (i32.eq (global.get $asyncify_state) (i32.const 1)) (then return))
+
+        ;; The child
+        (if (i32.eqz (i32.load (i32.const 212)))
+            (then
+                (call $append (i32.const 120))
+                (return)
+            )
+        )
+
+        ;; The parent
+        (call $append (i32.const 110))
+        (i32.store8 (i32.const 400) (i32.const 1))
+        (i32.store (i32.const 404) (i32.load (i32.const 212)))
+        (call $check (call $proc_join (i32.const 400) (i32.const 0) (i32.const 420)))
+    )
+)
+"#;
+
+fn run_appends(
+    fs: impl FnOnce(tokio::runtime::Handle) -> Arc<dyn FileSystem + Send + Sync>,
+) -> Vec<u8> {
+    let runtime = TestRuntime::new();
+    let _guard = runtime.enter();
+
+    let fs = fs(runtime.handle().clone());
+    let mut log = fs
+        .new_open_options()
+        .write(true)
+        .create(true)
+        .open(Path::new("/log"))
+        .unwrap();
+    block_on(log.write_all(b"head")).unwrap();
+    block_on(log.flush()).unwrap();
+    drop(log);
+
+    let builder = WasiEnv::builder("main")
+        .fs(fs.clone())
+        .preopen_dir("/")
+        .unwrap();
+    let (exit_code, _) = runtime.spawn_wat(PROGRAM, builder);
+    assert!(exit_code.is_success());
+
+    let mut log = Vec::new();
+    let mut file = fs
+        .new_open_options()
+        .read(true)
+        .open(Path::new("/log"))
+        .unwrap();
+    block_on(file.read_to_end(&mut log)).unwrap();
+    log
+}
+
+/// Every record that was appended is there in one piece after what the
+/// file started with
+fn assert_no_overwrites(log: &[u8]) {
+    assert_eq!(log.len(), 4 + 2 * RECORDS * 4);
+    assert_eq!(&log[..4], b"head");
+    let records: Vec<&[u8]> = log[4..].chunks(4).collect();
+    assert!(
+        records.iter().all(|r| *r == b"pppp" || *r == b"cccc"),
+        "{}",
+        String::from_utf8_lossy(log)
+    );
+    assert_eq!(records.iter().filter(|r| **r == b"pppp").count(), RECORDS);
+    assert_eq!(records.iter().filter(|r| **r == b"cccc").count(), RECORDS);
+}
+
+#[test]
+fn test_fork_shares_append_fds_on_the_host() {
+    let temp = tempfile::TempDir::new().unwrap();
+    let log = run_appends(|handle| {
+        Arc::new(virtual_fs::host_fs::FileSystem::new(handle, temp.path()).unwrap())
+    });
+    assert_no_overwrites(&log);
+}
+
+#[test]
+fn test_fork_shares_append_fds_in_memory() {
+    let log = run_appends(|_| Arc::new(virtual_fs::mem_fs::FileSystem::default()));
+    assert_no_overwrites(&log);
+}
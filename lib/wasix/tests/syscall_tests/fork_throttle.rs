@@ -0,0 +1,82 @@
+use std::{num::NonZeroU32, time::Duration};
+
+use super::run_wat_with;
+
+/// Forks 7 times in a row, the children exit right away. Once it is done
+/// the parent writes the number of successful forks to stdout.
+const FORK_STORM_PROGRAM: &str = r#"
+(module
+    (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+    (import "wasix_32v1" "proc_fork" (func $proc_fork (param i32 i32) (result i32)))
+
+    ;; Only a process with a shared memory can be forked
+    (import "env" "memory" (memory 1 16 shared))
+
+    (global $__stack_low (export "__stack_low") i32 (i32.const 16384))
+    (global $__stack_pointer (export "__stack_pointer") (mut i32) (i32.const 32768))
+
+    ;; `_start` is the only function that is ever unwound and it keeps
+    ;; nothing on the stack, so asyncify just needs to track its state
+    (global $asyncify_state (mut i32) (i32.const 0))
+    (func (export "asyncify_start_unwind") (param i32)
+        (global.set $asyncify_state (i32.const 1))
+    )
+    (func (export "asyncify_stop_unwind")
+        (global.set $asyncify_state (i32.const 0))
+    )
+    (func (export "asyncify_start_rewind") (param i32)
+        (global.set $asyncify_state (i32.const 2))
+    )
+    (func (export "asyncify_stop_rewind")
+        (global.set $asyncify_state (i32.const 0))
+    )
+    (func (export "asyncify_get_state") (result i32)
+        (global.get $asyncify_state)
+    )
+
+    (func $check (param i32)
+        (if (local.get 0) (then unreachable))
+    )
+
+    (func $main (export "_start")
+        (block $done
+            (loop $next
+                ;; When `_start` is rewound it goes straight to the fork
+                (if (i32.eqz (global.get $asyncify_state))
+                    (then
+                        (br_if $done (i32.ge_u (i32.load (i32.const 300)) (i32.const 7)))
+                    )
+                )
+
+                (call $check (call $proc_fork (i32.const 1) (i32.const 212)))
+                (if (i32.eq (global.get $asyncify_state) (i32.const 1)) (then return))
+
+                ;; The child
+                (if (i32.eqz (i32.load (i32.const 212))) (then return))
+
+                ;; The parent
+                (i32.store (i32.const 300) (i32.add (i32.load (i32.const 300)) (i32.const 1)))
+                (br $next)
+            )
+        )
+
+        (i32.store (i32.const 0) (i32.const 300))
+        (i32.store (i32.const 4) (i32.const 1))
+        (call $check (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 8)))
+    )
+)
+"#;
+
+#[test]
+fn test_fork_storm_is_delayed() {
+    let started = std::time::Instant::now();
+    let stdout = run_wat_with(FORK_STORM_PROGRAM, |runner| {
+        let threading = &mut runner.capabilities_mut().threading;
+        threading.enable_deep_sleep = true;
+        threading.max_forks_per_second = NonZeroU32::new(4);
+    });
+
+    // The last three forks wait 250ms, 500ms and 1s
+    assert!(started.elapsed() >= Duration::from_millis(1750));
+    assert_eq!(stdout, [7]);
+}
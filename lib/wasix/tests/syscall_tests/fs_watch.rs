@@ -0,0 +1,220 @@
+use std::{path::Path, sync::Arc};
+
+use virtual_fs::{AsyncReadExt, FileSystem};
+use virtual_mio::block_on;
+use wasmer_wasix::{FsWatches, WasiEnv, types::wasi::Fswatchmask};
+
+use super::TestRuntime;
+
+/// Watches the preopened directory, creates `new` in it, writes to it and
+/// removes it, then polls the watch and reads the three events. Once they
+/// are sent to stdout it reads the event that the host injects.
+const PROGRAM: &str = r#"
+(module
+    (import "wasi_snapshot_preview1" "path_open" (func $path_open (param i32 i32 i32 i32 i32 i64 i64 i32 i32) (result i32)))
+    (import "wasi_snapshot_preview1" "path_unlink_file" (func $path_unlink_file (param i32 i32 i32) (result i32)))
+    (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+    (import "wasi_snapshot_preview1" "fd_read" (func $fd_read (param i32 i32 i32 i32) (result i32)))
+    (import "wasi_snapshot_preview1" "fd_close" (func $fd_close (param i32) (result i32)))
+    (import "wasi_snapshot_preview1" "poll_oneoff" (func $poll_oneoff (param i32 i32 i32 i32) (result i32)))
+    (import "wasix_32v1" "fs_watch_add" (func $fs_watch_add (param i32 i32 i32 i32 i32) (result i32)))
+
+    (memory 1)
+    (export "memory" (memory 0))
+
+    (data (i32.const 100) ".")
+    (data (i32.const 104) "new")
+    (data (i32.const 110) "x")
+
+    (func $check (param i32)
+        (if (local.get 0) (then unreachable))
+    )
+
+    ;; Reads from the watch into `buf` until there are at least `len` bytes
+    (func $read_events (param $buf i32) (param $len i32)
+        (local $total i32)
+        (loop $more
+            (i32.store (i32.const 240) (i32.add (local.get $buf) (local.get $total)))
+            (i32.store (i32.const 244) (i32.sub (i32.const 256) (local.get $total)))
+            (call $check (call $fd_read (i32.load (i32.const 200)) (i32.const 240) (i32.const 1) (i32.const 248)))
+            (local.set $total (i32.add (local.get $total) (i32.load (i32.const 248))))
+            (br_if $more (i32.lt_u (local.get $total) (local.get $len)))
+        )
+    )
+
+    (func $stdout (param $buf i32) (param $len i32)
+        (i32.store (i32.const 240) (local.get $buf))
+        (i32.store (i32.const 244) (local.get $len))
+        (call $check (call $fd_write (i32.const 1) (i32.const 240) (i32.const 1) (i32.const 248)))
+    )
+
+    (func $main (export "_start")
+        ;; CREATE | MODIFY | DELETE
+        (call $check (call $fs_watch_add (i32.const 4) (i32.const 100) (i32.const 1) (i32.const 7) (i32.const 200)))
+
+        (call $check (call $path_open (i32.const 4) (i32.const 0) (i32.const 104) (i32.const 3)
+            (i32.const 1) (i64.const -1) (i64.const -1) (i32.const 0) (i32.const 204)))
+        (i32.store (i32.const 240) (i32.const 110))
+        (i32.store (i32.const 244) (i32.const 1))
+        (call $check (call $fd_write (i32.load (i32.const 204)) (i32.const 240) (i32.const 1) (i32.const 248)))
+        (call $check (call $fd_close (i32.load (i32.const 204))))
+        (call $check (call $path_unlink_file (i32.const 4) (i32.const 104) (i32.const 3)))
+
+        ;; The watch is readable, a subscription to read from it fires
+        (i64.store (i32.const 256) (i64.const 42))
+        (i32.store8 (i32.const 264) (i32.const 1))
+        (i32.store (i32.const 272) (i32.load (i32.const 200)))
+        (call $check (call $poll_oneoff (i32.const 256) (i32.const 320) (i32.const 1) (i32.const 352)))
+        (if (i32.ne (i32.load (i32.const 352)) (i32.const 1)) (then unreachable))
+        (if (i64.ne (i64.load (i32.const 320)) (i64.const 42)) (then unreachable))
+        (if (i32.load16_u (i32.const 328)) (then unreachable))
+        (if (i32.ne (i32.load8_u (i32.const 330)) (i32.const 1)) (then unreachable))
+
+        ;; Three events for `new`, each is 8 bytes followed by the name
+        (call $read_events (i32.const 600) (i32.const 33))
+        (call $stdout (i32.const 600) (i32.const 33))
+
+        ;; The event of `host`
+        (call $read_events (i32.const 700) (i32.const 12))
+        (call $stdout (i32.const 700) (i32.const 12))
+    )
+)
+"#;
+
+fn event(mask: Fswatchmask, name: &str) -> Vec<u8> {
+    let mut event = Vec::new();
+    event.extend_from_slice(&mask.bits().to_le_bytes());
+    event.extend_from_slice(&0u16.to_le_bytes());
+    event.extend_from_slice(&(name.len() as u32).to_le_bytes());
+    event.extend_from_slice(name.as_bytes());
+    event
+}
+
+#[test]
+fn test_fs_watch_reports_changes_in_a_directory() {
+    let runtime = TestRuntime::new();
+
+    let fs = virtual_fs::mem_fs::FileSystem::default();
+    fs.create_dir(Path::new("/data")).unwrap();
+
+    let watches = FsWatches::new();
+    let builder = WasiEnv::builder("main")
+        .fs(Arc::new(fs) as Arc<dyn FileSystem + Send + Sync>)
+        .preopen_dir("/data")
+        .unwrap()
+        .fs_watches(watches.clone());
+    let (env, mut stdout_rx) = runtime.build_env(builder);
+    let mut task = runtime.start(runtime.module(PROGRAM), env).unwrap();
+
+    let mut events = [0u8; 33];
+    block_on(stdout_rx.read_exact(&mut events)).unwrap();
+    let expected = [
+        event(Fswatchmask::CREATE, "new"),
+        event(Fswatchmask::MODIFY, "new"),
+        event(Fswatchmask::DELETE, "new"),
+    ]
+    .concat();
+    assert_eq!(events.as_slice(), expected.as_slice());
+
+    // Changes that the host makes behind the back of the guest are
+    // reported the same way, but only to the watches that asked for them
+    watches.notify("/data/host", Fswatchmask::MODIFY);
+    let mut event_from_host = [0u8; 12];
+    block_on(stdout_rx.read_exact(&mut event_from_host)).unwrap();
+    assert_eq!(
+        event_from_host.as_slice(),
+        event(Fswatchmask::MODIFY, "host").as_slice()
+    );
+
+    let exit_code = block_on(task.wait_finished()).unwrap();
+    assert!(exit_code.is_success());
+}
+
+/// Watches the preopened directory, creates `new` and truncates it, renames
+/// it to `moved`, opens `moved` again with `O_TRUNC` and links to it with
+/// the symlink `link`, then sends the six events to stdout
+const TRUNCATE_AND_RENAME_PROGRAM: &str = r#"
+(module
+    (import "wasi_snapshot_preview1" "path_open" (func $path_open (param i32 i32 i32 i32 i32 i64 i64 i32 i32) (result i32)))
+    (import "wasi_snapshot_preview1" "path_rename" (func $path_rename (param i32 i32 i32 i32 i32 i32) (result i32)))
+    (import "wasi_snapshot_preview1" "path_symlink" (func $path_symlink (param i32 i32 i32 i32 i32) (result i32)))
+    (import "wasi_snapshot_preview1" "fd_filestat_set_size" (func $fd_filestat_set_size (param i32 i64) (result i32)))
+    (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+    (import "wasi_snapshot_preview1" "fd_read" (func $fd_read (param i32 i32 i32 i32) (result i32)))
+    (import "wasi_snapshot_preview1" "fd_close" (func $fd_close (param i32) (result i32)))
+    (import "wasix_32v1" "fs_watch_add" (func $fs_watch_add (param i32 i32 i32 i32 i32) (result i32)))
+
+    (memory 1)
+    (export "memory" (memory 0))
+
+    (data (i32.const 100) ".")
+    (data (i32.const 104) "new")
+    (data (i32.const 110) "moved")
+    (data (i32.const 120) "link")
+
+    (func $check (param i32)
+        (if (local.get 0) (then unreachable))
+    )
+
+    (func $main (export "_start")
+        (local $total i32)
+
+        ;; CREATE | MODIFY | DELETE
+        (call $check (call $fs_watch_add (i32.const 4) (i32.const 100) (i32.const 1) (i32.const 7) (i32.const 200)))
+
+        (call $check (call $path_open (i32.const 4) (i32.const 0) (i32.const 104) (i32.const 3)
+            (i32.const 1) (i64.const -1) (i64.const -1) (i32.const 0) (i32.const 204)))
+        (call $check (call $fd_filestat_set_size (i32.load (i32.const 204)) (i64.const 4)))
+        (call $check (call $fd_close (i32.load (i32.const 204))))
+
+        (call $check (call $path_rename (i32.const 4) (i32.const 104) (i32.const 3)
+            (i32.const 4) (i32.const 110) (i32.const 5)))
+
+        (call $check (call $path_open (i32.const 4) (i32.const 0) (i32.const 110) (i32.const 5)
+            (i32.const 8) (i64.const -1) (i64.const -1) (i32.const 0) (i32.const 208)))
+        (call $check (call $fd_close (i32.load (i32.const 208))))
+
+        (call $check (call $path_symlink (i32.const 110) (i32.const 5) (i32.const 4) (i32.const 120) (i32.const 4)))
+
+        ;; The six events are 8 bytes each followed by the name
+        (loop $more
+            (i32.store (i32.const 240) (i32.add (i32.const 600) (local.get $total)))
+            (i32.store (i32.const 244) (i32.sub (i32.const 256) (local.get $total)))
+            (call $check (call $fd_read (i32.load (i32.const 200)) (i32.const 240) (i32.const 1) (i32.const 248)))
+            (local.set $total (i32.add (local.get $total) (i32.load (i32.const 248))))
+            (br_if $more (i32.lt_u (local.get $total) (i32.const 71)))
+        )
+
+        (i32.store (i32.const 240) (i32.const 600))
+        (i32.store (i32.const 244) (local.get $total))
+        (call $check (call $fd_write (i32.const 1) (i32.const 240) (i32.const 1) (i32.const 248)))
+    )
+)
+"#;
+
+#[test]
+fn test_fs_watch_reports_truncations_and_renames() {
+    let runtime = TestRuntime::new();
+
+    let fs = virtual_fs::mem_fs::FileSystem::default();
+    fs.create_dir(Path::new("/data")).unwrap();
+
+    let builder = WasiEnv::builder("main")
+        .fs(Arc::new(fs) as Arc<dyn FileSystem + Send + Sync>)
+        .preopen_dir("/data")
+        .unwrap()
+        .fs_watches(FsWatches::new());
+    let (exit_code, stdout) = runtime.spawn_wat(TRUNCATE_AND_RENAME_PROGRAM, builder);
+    assert!(exit_code.is_success());
+
+    let expected = [
+        event(Fswatchmask::CREATE, "new"),
+        event(Fswatchmask::MODIFY, "new"),
+        event(Fswatchmask::DELETE, "new"),
+        event(Fswatchmask::CREATE, "moved"),
+        event(Fswatchmask::MODIFY, "moved"),
+        event(Fswatchmask::CREATE, "link"),
+    ]
+    .concat();
+    assert_eq!(stdout, expected);
+}
@@ -0,0 +1,83 @@
+use wasmer_wasix_types::wasi::Errno;
+
+use super::run_wat_with;
+
+/// Reads the hostname, sets it to `guest-box` and reads it again
+const HOSTNAME_PROGRAM: &[u8] = br#"
+(module
+    (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+    (import "wasix_32v1" "gethostname" (func $gethostname (param i32 i32) (result i32)))
+    (import "wasix_32v1" "sethostname" (func $sethostname (param i32 i32) (result i32)))
+
+    (memory 1)
+    (export "memory" (memory 0))
+
+    (data (i32.const 100) "guest-box")
+
+    (func $check (param i32)
+        (if (local.get 0) (then unreachable))
+    )
+
+    (func $main (export "_start")
+        (i32.store (i32.const 1000) (i32.const 64))
+        (i32.store (i32.const 1008) (call $gethostname (i32.const 1024) (i32.const 1000)))
+        (i32.store (i32.const 1012) (call $sethostname (i32.const 100) (i32.const 9)))
+        (i32.store (i32.const 1004) (i32.const 64))
+        (i32.store (i32.const 1016) (call $gethostname (i32.const 1088) (i32.const 1004)))
+
+        ;; Send the lengths, the results and the names to stdout
+        (i32.store (i32.const 0) (i32.const 1000))
+        (i32.store (i32.const 4) (i32.const 152))
+        (call $check (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 8)))
+    )
+)
+"#;
+
+/// The results of the syscalls and the hostnames that were read
+#[derive(Debug, PartialEq, Eq)]
+struct Hostnames {
+    errnos: [u32; 3],
+    before: String,
+    after: String,
+}
+
+fn run_hostname_program(allow_set_hostname: bool) -> Hostnames {
+    let stdout = run_wat_with(HOSTNAME_PROGRAM, |runner| {
+        runner.capabilities_mut().allow_set_hostname = allow_set_hostname;
+    });
+    let word = |at: usize| u32::from_le_bytes(stdout[at..at + 4].try_into().unwrap());
+    let name = |at: usize, len: u32| String::from_utf8(stdout[at..][..len as usize].to_vec());
+    Hostnames {
+        errnos: [word(8), word(12), word(16)],
+        before: name(24, word(0)).unwrap(),
+        after: name(88, word(4)).unwrap(),
+    }
+}
+
+#[test]
+fn test_set_and_get_hostname() {
+    assert_eq!(
+        run_hostname_program(true),
+        Hostnames {
+            errnos: [Errno::Success as u32; 3],
+            before: "localhost".to_string(),
+            after: "guest-box".to_string(),
+        }
+    );
+}
+
+#[test]
+fn test_set_hostname_needs_the_capability() {
+    assert_eq!(
+        run_hostname_program(false),
+        Hostnames {
+            errnos: [
+                Errno::Success as u32,
+                Errno::Perm as u32,
+                Errno::Success as u32
+            ],
+            before: "localhost".to_string(),
+            after: "localhost".to_string(),
+        }
+    );
+}
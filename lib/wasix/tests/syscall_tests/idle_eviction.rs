@@ -0,0 +1,119 @@
+use std::{
+    io,
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::Duration,
+};
+
+use wasmer_wasix::runtime::eviction::{EvictionStore, IdleEviction, InMemoryEvictionStore};
+
+use super::TestRuntime;
+
+/// Writes a message and a global before sleeping for 500ms and prints both
+/// once it wakes up again
+pub(crate) const SLEEPER: &[u8] = br#"
+(module
+    (import "wasi_snapshot_preview1" "poll_oneoff" (func $poll_oneoff (param i32 i32 i32 i32) (result i32)))
+    (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+
+    (memory 1)
+    (export "memory" (memory 0))
+
+    (global $__stack_low (export "__stack_low") i32 (i32.const 16384))
+    (global $__stack_pointer (export "__stack_pointer") (mut i32) (i32.const 32768))
+    (global $answer (mut i32) (i32.const 0))
+
+    ;; The functions that are unwound keep all their state in memory, so
+    ;; asyncify just needs to track its state
+    (global $asyncify_state (mut i32) (i32.const 0))
+    (func (export "asyncify_start_unwind") (param i32)
+        (global.set $asyncify_state (i32.const 1))
+    )
+    (func (export "asyncify_stop_unwind")
+        (global.set $asyncify_state (i32.const 0))
+    )
+    (func (export "asyncify_start_rewind") (param i32)
+        (global.set $asyncify_state (i32.const 2))
+    )
+    (func (export "asyncify_stop_rewind")
+        (global.set $asyncify_state (i32.const 0))
+    )
+    (func (export "asyncify_get_state") (result i32)
+        (global.get $asyncify_state)
+    )
+
+    (func $main (export "_start")
+        ;; The state is only set up the first time around, not when `_start`
+        ;; is rewound
+        (if (i32.eqz (global.get $asyncify_state))
+            (then
+                (i32.store8 (i32.const 2000) (i32.const 79))
+                (i32.store8 (i32.const 2001) (i32.const 75))
+                (i32.store8 (i32.const 2002) (i32.const 10))
+                (global.set $answer (i32.const 42))
+
+                ;; A subscription on the monotonic clock for 500ms
+                (i32.store8 (i32.const 108) (i32.const 0))
+                (i32.store (i32.const 116) (i32.const 1))
+                (i64.store (i32.const 124) (i64.const 500000000))
+            )
+        )
+        (if (call $poll_oneoff (i32.const 100) (i32.const 200) (i32.const 1) (i32.const 300)) (then unreachable))
+        (if (i32.eq (global.get $asyncify_state) (i32.const 1)) (then return))
+
+        (i32.store (i32.const 2004) (global.get $answer))
+        (i32.store (i32.const 0) (i32.const 2000))
+        (i32.store (i32.const 4) (i32.const 8))
+        (drop (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 8)))
+    )
+)
+"#;
+
+/// Counts how often the memory of an evicted thread is saved and loaded
+#[derive(Debug, Default)]
+struct CountingStore {
+    inner: InMemoryEvictionStore,
+    saved: AtomicUsize,
+    loaded: AtomicUsize,
+}
+
+impl EvictionStore for CountingStore {
+    fn save(&self, memory: Vec<u8>) -> io::Result<u64> {
+        self.saved.fetch_add(1, Ordering::SeqCst);
+        self.inner.save(memory)
+    }
+
+    fn load(&self, key: u64) -> io::Result<Vec<u8>> {
+        self.loaded.fetch_add(1, Ordering::SeqCst);
+        self.inner.load(key)
+    }
+
+    fn discard(&self, key: u64) {
+        self.inner.discard(key)
+    }
+}
+
+#[test]
+fn test_idle_process_is_evicted_and_restored() {
+    let store = Arc::new(CountingStore::default());
+    let mut runtime = TestRuntime::new();
+    runtime.rt.set_idle_eviction(IdleEviction::with_store(
+        Duration::from_millis(50),
+        store.clone(),
+    ));
+
+    let (result, stdout) = runtime.run_wat_with(SLEEPER, |runner| {
+        runner.capabilities_mut().threading.enable_deep_sleep = true;
+    });
+    result.unwrap();
+
+    // The memory and the globals survived the eviction
+    assert_eq!(&stdout[..3], b"OK\n");
+    assert_eq!(u32::from_le_bytes(stdout[4..8].try_into().unwrap()), 42);
+
+    assert_eq!(store.saved.load(Ordering::SeqCst), 1);
+    assert_eq!(store.loaded.load(Ordering::SeqCst), 1);
+    assert!(store.inner.is_empty());
+}
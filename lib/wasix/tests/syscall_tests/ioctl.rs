@@ -0,0 +1,56 @@
+use super::run_wat;
+
+#[test]
+fn test_fionread_on_pipe() {
+    let stdout = run_wat(br#"
+    (module
+        (import "wasi_snapshot_preview1" "fd_read" (func $fd_read (param i32 i32 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+        (import "wasix_32v1" "fd_pipe" (func $fd_pipe (param i32 i32) (result i32)))
+        (import "wasix_32v1" "fd_ioctl" (func $fd_ioctl (param i32 i32 i32) (result i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        (data (i32.const 100) "hello")
+
+        (func $check (param i32)
+            (if (local.get 0) (then unreachable))
+        )
+
+        (func $main (export "_start")
+            (call $check (call $fd_pipe (i32.const 200) (i32.const 204)))
+
+            ;; Nothing has been written yet
+            (call $check (call $fd_ioctl (i32.load (i32.const 200)) (i32.const 0x541B) (i32.const 300)))
+
+            ;; Two separate writes are both counted
+            (i32.store (i32.const 0) (i32.const 100))
+            (i32.store (i32.const 4) (i32.const 5))
+            (call $check (call $fd_write (i32.load (i32.const 204)) (i32.const 0) (i32.const 1) (i32.const 8)))
+            (call $check (call $fd_write (i32.load (i32.const 204)) (i32.const 0) (i32.const 1) (i32.const 8)))
+            (call $check (call $fd_ioctl (i32.load (i32.const 200)) (i32.const 0x541B) (i32.const 304)))
+
+            ;; Reading consumes some of the data
+            (i32.store (i32.const 0) (i32.const 400))
+            (i32.store (i32.const 4) (i32.const 3))
+            (call $check (call $fd_read (i32.load (i32.const 200)) (i32.const 0) (i32.const 1) (i32.const 8)))
+            (call $check (call $fd_ioctl (i32.load (i32.const 200)) (i32.const 0x541B) (i32.const 308)))
+
+            ;; Requests that are not known are rejected
+            (i32.store (i32.const 312) (call $fd_ioctl (i32.load (i32.const 200)) (i32.const 0x1234) (i32.const 500)))
+
+            (i32.store (i32.const 0) (i32.const 300))
+            (i32.store (i32.const 4) (i32.const 16))
+            (call $check (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 8)))
+        )
+    )
+    "#);
+    let values: Vec<_> = stdout
+        .chunks(4)
+        .map(|value| u32::from_le_bytes(value.try_into().unwrap()))
+        .collect();
+
+    // The last one is `Errno::Notty`
+    assert_eq!(values, vec![0, 10, 7, 59]);
+}
@@ -0,0 +1,99 @@
+use std::sync::Arc;
+
+use wasmer_wasix::{
+    fs::ReopenFailure,
+    journal::{BufferedJournal, JournalEntry, WritableJournal},
+};
+use wasmer_wasix_types::wasi::{Errno, Fdflags, Fdflagsext, Oflags, Rights};
+
+use super::{run_wat_with, try_run_wat_with};
+
+/// Writes the result of `fd_filestat_get` on the descriptor 5, which is
+/// restored from the journal, to stdout
+const FILESTAT_PROGRAM: &str = r#"
+(module
+    (import "wasi_snapshot_preview1" "fd_filestat_get" (func $fd_filestat_get (param i32 i32) (result i32)))
+    (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+
+    (memory 1)
+    (export "memory" (memory 0))
+
+    (func $main (export "_start")
+        (i32.store8 (i32.const 1024) (call $fd_filestat_get (i32.const 5) (i32.const 200)))
+        (i32.store (i32.const 0) (i32.const 1024))
+        (i32.store (i32.const 4) (i32.const 1))
+        (drop (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 8)))
+    )
+)
+"#;
+
+/// A journal in which the process opened `data.txt` as the descriptor 5,
+/// followed by `then`
+fn journal(then: Vec<JournalEntry<'static>>) -> BufferedJournal {
+    let journal = BufferedJournal::default();
+    journal
+        .write(JournalEntry::OpenFileDescriptorV2 {
+            fd: 5,
+            dirfd: 3,
+            dirflags: 0,
+            path: "data.txt".into(),
+            o_flags: Oflags::empty(),
+            fs_rights_base: Rights::FD_READ | Rights::FD_FILESTAT_GET,
+            fs_rights_inheriting: Rights::empty(),
+            fs_flags: Fdflags::empty(),
+            fd_flags: Fdflagsext::empty(),
+        })
+        .unwrap();
+    for entry in then {
+        journal.write(entry).unwrap();
+    }
+    journal
+}
+
+#[test]
+fn test_journal_restore_fails_when_a_file_is_gone() {
+    let journal = Arc::new(journal(Vec::new()));
+
+    let (result, stdout) = try_run_wat_with(FILESTAT_PROGRAM, |runner| {
+        runner.with_read_only_journal(journal);
+    });
+
+    assert!(result.is_err());
+    assert!(stdout.is_empty());
+}
+
+#[test]
+fn test_journal_restore_skips_the_files_that_are_gone() {
+    let journal = Arc::new(journal(Vec::new()));
+
+    let stdout = run_wat_with(FILESTAT_PROGRAM, |runner| {
+        runner
+            .with_read_only_journal(journal)
+            .with_journal_reopen_failure(ReopenFailure::Skip);
+    });
+
+    assert_eq!(stdout, [Errno::Badf as u8]);
+}
+
+#[test]
+fn test_journal_restore_reopens_a_file_created_later_in_the_journal() {
+    // The file only exists once the process created it through another
+    // descriptor, after it opened the descriptor 5
+    let journal = Arc::new(journal(vec![JournalEntry::OpenFileDescriptorV2 {
+        fd: 6,
+        dirfd: 3,
+        dirflags: 0,
+        path: "data.txt".into(),
+        o_flags: Oflags::CREATE,
+        fs_rights_base: Rights::FD_WRITE,
+        fs_rights_inheriting: Rights::empty(),
+        fs_flags: Fdflags::empty(),
+        fd_flags: Fdflagsext::empty(),
+    }]));
+
+    let stdout = run_wat_with(FILESTAT_PROGRAM, |runner| {
+        runner.with_read_only_journal(journal);
+    });
+
+    assert_eq!(stdout, [Errno::Success as u8]);
+}
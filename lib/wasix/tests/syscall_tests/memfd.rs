@@ -0,0 +1,75 @@
+use super::run_wat;
+
+#[test]
+fn test_memfd_is_shared_and_resizable() {
+    let stdout = run_wat(br#"
+    (module
+        (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "fd_pread" (func $fd_pread (param i32 i32 i32 i64 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "fd_allocate" (func $fd_allocate (param i32 i64 i64) (result i32)))
+        (import "wasi_snapshot_preview1" "fd_filestat_get" (func $fd_filestat_get (param i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "fd_filestat_set_size" (func $fd_filestat_set_size (param i32 i64) (result i32)))
+        (import "wasix_32v1" "fd_dup" (func $fd_dup (param i32 i32) (result i32)))
+        (import "wasix_32v1" "memfd_create" (func $memfd_create (param i32 i32 i32 i32) (result i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        (data (i32.const 100) "shm")
+        (data (i32.const 120) "hello")
+
+        (func $check (param i32)
+            (if (local.get 0) (then unreachable))
+        )
+
+        (func $main (export "_start")
+            (call $check (call $memfd_create (i32.const 100) (i32.const 3) (i32.const 0) (i32.const 200)))
+            (call $check (call $fd_dup (i32.load (i32.const 200)) (i32.const 204)))
+
+            ;; Write through one descriptor and grow the file
+            (i32.store (i32.const 0) (i32.const 120))
+            (i32.store (i32.const 4) (i32.const 5))
+            (call $check (call $fd_write (i32.load (i32.const 200)) (i32.const 0) (i32.const 1) (i32.const 8)))
+            (call $check (call $fd_allocate (i32.load (i32.const 200)) (i64.const 0) (i64.const 4096)))
+            (call $check (call $fd_filestat_get (i32.load (i32.const 204)) (i32.const 1024)))
+
+            ;; The other descriptor sees the data
+            (i32.store (i32.const 16) (i32.const 1100))
+            (i32.store (i32.const 20) (i32.const 5))
+            (call $check (call $fd_pread (i32.load (i32.const 204)) (i32.const 16) (i32.const 1) (i64.const 0) (i32.const 24)))
+
+            ;; Shrink the file and read it back
+            (call $check (call $fd_filestat_set_size (i32.load (i32.const 204)) (i64.const 3)))
+            (call $check (call $fd_filestat_get (i32.load (i32.const 200)) (i32.const 1152)))
+            (i32.store (i32.const 16) (i32.const 1300))
+            (i32.store (i32.const 20) (i32.const 5))
+            (call $check (call $fd_pread (i32.load (i32.const 200)) (i32.const 16) (i32.const 1) (i64.const 0) (i32.const 1308)))
+
+            ;; Send both stats and reads to stdout
+            (i32.store (i32.const 32) (i32.const 1024))
+            (i32.store (i32.const 36) (i32.const 64))
+            (i32.store (i32.const 40) (i32.const 1100))
+            (i32.store (i32.const 44) (i32.const 5))
+            (i32.store (i32.const 48) (i32.const 1152))
+            (i32.store (i32.const 52) (i32.const 64))
+            (i32.store (i32.const 56) (i32.const 1300))
+            (i32.store (i32.const 60) (i32.const 12))
+            (call $check (call $fd_write (i32.const 1) (i32.const 32) (i32.const 4) (i32.const 8)))
+        )
+    )
+    "#);
+    assert_eq!(stdout.len(), 64 + 5 + 64 + 12);
+
+    let size = |stat: &[u8]| u64::from_le_bytes(stat[32..40].try_into().unwrap());
+    let (grown, rest) = stdout.split_at(64);
+    let (read, rest) = rest.split_at(5);
+    let (shrunk, rest) = rest.split_at(64);
+    let (pread, nread) = rest.split_at(8);
+
+    assert_eq!(grown[16], 4, "memory files are regular files");
+    assert_eq!(size(grown), 4096);
+    assert_eq!(read, b"hello");
+    assert_eq!(size(shrunk), 3);
+    assert_eq!(&pread[..3], b"hel");
+    assert_eq!(u32::from_le_bytes(nread.try_into().unwrap()), 3);
+}
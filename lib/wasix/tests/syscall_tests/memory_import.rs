@@ -0,0 +1,65 @@
+use wasmer::{MemoryError, Pages};
+use wasmer_wasix::{SpawnError, WasiEnv};
+
+use super::TestRuntime;
+
+/// Imports a shared memory that can grow up to 16 pages
+const PROGRAM: &str = r#"
+(module
+    (import "env" "memory" (memory 1 16 shared))
+    (func (export "_start"))
+)
+"#;
+
+/// Runs the program and returns the error if it failed to spawn
+fn spawn_with_max_memory(max_memory_pages: Option<Pages>) -> Option<SpawnError> {
+    let runtime = TestRuntime::new();
+
+    let mut builder = WasiEnv::builder("main");
+    if let Some(max) = max_memory_pages {
+        builder.set_max_memory_pages(max);
+    }
+    let (env, _) = runtime.build_env(builder);
+
+    let mut task = match runtime.start(runtime.module(PROGRAM), env) {
+        Ok(task) => task,
+        Err(err) => return Some(err),
+    };
+    let exit_code = virtual_mio::block_on(task.wait_finished()).unwrap();
+    assert!(exit_code.is_success());
+    None
+}
+
+#[test]
+fn test_memory_import_within_the_limit_spawns() {
+    assert!(spawn_with_max_memory(Some(Pages(16))).is_none());
+}
+
+#[test]
+fn test_memory_import_without_a_limit_spawns() {
+    assert!(spawn_with_max_memory(None).is_none());
+}
+
+#[test]
+fn test_memory_import_over_the_limit_fails_to_spawn() {
+    let err = spawn_with_max_memory(Some(Pages(8))).unwrap();
+    match &err {
+        SpawnError::UnsatisfiableMemoryImport { requested, error } => {
+            assert_eq!(requested.minimum, Pages(1));
+            assert_eq!(requested.maximum, Some(Pages(16)));
+            assert_eq!(
+                *error,
+                MemoryError::MaximumMemoryTooLarge {
+                    max_requested: Pages(16),
+                    max_allowed: Pages(8),
+                }
+            );
+        }
+        err => panic!("unexpected error: {err}"),
+    }
+    assert_eq!(
+        err.to_string(),
+        "the module imports a shared (1 pages..16 pages) memory that the runtime can't provide: \
+         The maximum requested memory (16 pages) is greater than the maximum allowed memory (8 pages)"
+    );
+}
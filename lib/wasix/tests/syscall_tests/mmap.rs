@@ -0,0 +1,199 @@
+use std::sync::Arc;
+
+use virtual_fs::{AsyncReadExt, AsyncWriteExt, FileSystem, TmpFileSystem};
+use virtual_mio::block_on;
+use wasmer_wasix_types::wasi::Errno;
+
+use super::{run_wat, run_wat_with};
+
+/// Maps `world` out of `prog/data.txt` (which holds `hello world`) into a
+/// synced writable mapping that reaches past the end of the file, changes
+/// it and writes it back. Then the range is reused for a private mapping
+/// of `hello` whose changes never reach the file.
+const PROGRAM: &str = r#"
+(module
+    (import "wasi_snapshot_preview1" "path_open" (func $path_open (param i32 i32 i32 i32 i32 i64 i64 i32 i32) (result i32)))
+    (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+    (import "wasix_32v1" "fd_mmap" (func $fd_mmap (param i32 i64 i32 i32 i32) (result i32)))
+    (import "wasix_32v1" "fd_msync" (func $fd_msync (param i32 i32) (result i32)))
+    (import "wasix_32v1" "fd_munmap" (func $fd_munmap (param i32 i32) (result i32)))
+
+    (memory 1)
+    (export "memory" (memory 0))
+
+    (data (i32.const 100) "prog/data.txt")
+
+    (func $check (param i32)
+        (if (local.get 0) (then unreachable))
+    )
+
+    (func $main (export "_start")
+        (local $addr i32)
+        (call $check (call $path_open (i32.const 3) (i32.const 0) (i32.const 100) (i32.const 13)
+            (i32.const 0) (i64.const -1) (i64.const -1) (i32.const 0) (i32.const 200)))
+
+        ;; A synced writable mapping of `world` and 11 bytes beyond the end of the file
+        (i32.store (i32.const 300) (call $fd_mmap (i32.load (i32.const 200)) (i64.const 6) (i32.const 16) (i32.const 3) (i32.const 304)))
+        (local.set $addr (i32.load (i32.const 304)))
+        (i64.store (i32.const 308) (i64.load (local.get $addr)))
+        (i64.store (i32.const 316) (i64.load offset=8 (local.get $addr)))
+
+        (i32.store8 (local.get $addr) (i32.const 87))
+        (i32.store8 offset=10 (local.get $addr) (i32.const 88))
+        (i32.store (i32.const 324) (call $fd_msync (local.get $addr) (i32.const 1)))
+        ;; Nothing is mapped at the start of the memory
+        (i32.store (i32.const 328) (call $fd_msync (i32.const 0) (i32.const 16)))
+        ;; A mapping can only be removed as a whole
+        (i32.store (i32.const 332) (call $fd_munmap (i32.add (local.get $addr) (i32.const 1)) (i32.const 4)))
+        (i32.store (i32.const 336) (call $fd_munmap (local.get $addr) (i32.const 16)))
+
+        ;; A private mapping of `hello` gets the range that was released
+        (i32.store (i32.const 340) (call $fd_mmap (i32.load (i32.const 200)) (i64.const 0) (i32.const 5) (i32.const 1) (i32.const 344)))
+        (local.set $addr (i32.load (i32.const 344)))
+        (i64.store (i32.const 348) (i64.load (local.get $addr)))
+        (i64.store (i32.const 356) (i64.load offset=8 (local.get $addr)))
+        (i32.store8 (local.get $addr) (i32.const 74))
+        (i32.store (i32.const 364) (call $fd_munmap (local.get $addr) (i32.const 5)))
+
+        ;; Send the results to stdout
+        (i32.store (i32.const 0) (i32.const 300))
+        (i32.store (i32.const 4) (i32.const 68))
+        (call $check (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 8)))
+    )
+)
+"#;
+
+#[test]
+fn test_mmap_writes_synced_mappings_back() {
+    let prog = TmpFileSystem::new();
+    let mut file = prog
+        .new_open_options()
+        .create(true)
+        .write(true)
+        .open("/data.txt")
+        .unwrap();
+    block_on(file.write_all(b"hello world")).unwrap();
+
+    let stdout = run_wat_with(PROGRAM, |runner| {
+        runner.with_mount("/prog".to_string(), Arc::new(prog.clone()));
+    });
+    let value = |offset: usize| u32::from_le_bytes(stdout[offset..offset + 4].try_into().unwrap());
+
+    // The mapping starts after the single page of memory of the module
+    assert_eq!(value(0), Errno::Success as u32);
+    assert_eq!(value(4), 0x10000);
+    assert_eq!(&stdout[8..24], b"world\0\0\0\0\0\0\0\0\0\0\0");
+
+    assert_eq!(value(24), Errno::Success as u32);
+    assert_eq!(value(28), Errno::Nomem as u32);
+    assert_eq!(value(32), Errno::Inval as u32);
+    assert_eq!(value(36), Errno::Success as u32);
+
+    // The bytes that the previous mapping left beyond its end are cleared
+    assert_eq!(value(40), Errno::Success as u32);
+    assert_eq!(value(44), 0x10000);
+    assert_eq!(&stdout[48..64], b"hello\0\0\0\0\0\0\0\0\0\0\0");
+    assert_eq!(value(64), Errno::Success as u32);
+
+    let mut file = prog
+        .new_open_options()
+        .read(true)
+        .open("/data.txt")
+        .unwrap();
+    let mut contents = Vec::new();
+    block_on(file.read_to_end(&mut contents)).unwrap();
+    assert_eq!(contents, b"hello World");
+}
+
+/// Maps a memory file into a synced writable mapping and forks, then the
+/// child writes `child` at the start of the mapping while the parent writes
+/// `parent` further in. Once the child exited the parent syncs the mapping,
+/// which brings in the changes of the child and keeps its own.
+const FORK_PROGRAM: &str = r#"
+(module
+    (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+    (import "wasi_snapshot_preview1" "fd_filestat_set_size" (func $fd_filestat_set_size (param i32 i64) (result i32)))
+    (import "wasix_32v1" "memfd_create" (func $memfd_create (param i32 i32 i32 i32) (result i32)))
+    (import "wasix_32v1" "fd_mmap" (func $fd_mmap (param i32 i64 i32 i32 i32) (result i32)))
+    (import "wasix_32v1" "fd_msync" (func $fd_msync (param i32 i32) (result i32)))
+    (import "wasix_32v1" "proc_fork" (func $proc_fork (param i32 i32) (result i32)))
+    (import "wasix_32v1" "proc_join" (func $proc_join (param i32 i32 i32) (result i32)))
+
+    ;; Only a process with a shared memory can be forked
+    (import "env" "memory" (memory 1 16 shared))
+
+    (global $__stack_low (export "__stack_low") i32 (i32.const 16384))
+    (global $__stack_pointer (export "__stack_pointer") (mut i32) (i32.const 32768))
+
+    (data (i32.const 100) "segment")
+    (data (i32.const 110) "child")
+    (data (i32.const 120) "parent")
+
+    ;; `_start` is the only function that is ever unwound and it keeps
+    ;; nothing on the stack, so asyncify just needs to track its state
+    (global $asyncify_state (mut i32) (i32.const 0))
+    (func (export "asyncify_start_unwind") (param i32)
+        (global.set $asyncify_state (i32.const 1))
+    )
+    (func (export "asyncify_stop_unwind")
+        (global.set $asyncify_state (i32.const 0))
+    )
+    (func (export "asyncify_start_rewind") (param i32)
+        (global.set $asyncify_state (i32.const 2))
+    )
+    (func (export "asyncify_stop_rewind")
+        (global.set $asyncify_state (i32.const 0))
+    )
+    (func (export "asyncify_get_state") (result i32)
+        (global.get $asyncify_state)
+    )
+
+    (func $check (param i32)
+        (if (local.get 0) (then unreachable))
+    )
+
+    (func $main (export "_start")
+        (local $addr i32)
+
+        ;; The mapping is only made the first time around, not when `_start`
+        ;; is rewound
+        (if (i32.eqz (global.get $asyncify_state))
+            (then
+                (call $check (call $memfd_create (i32.const 100) (i32.const 7) (i32.const 0) (i32.const 200)))
+                (call $check (call $fd_filestat_set_size (i32.load (i32.const 200)) (i64.const 16)))
+                (call $check (call $fd_mmap (i32.load (i32.const 200)) (i64.const 0) (i32.const 16) (i32.const 3) (i32.const 204)))
+            )
+        )
+
+        (call $check (call $proc_fork (i32.const 1) (i32.const 208)))
+        (if (i32.eq (global.get $asyncify_state) (i32.const 1)) (then return))
+        (local.set $addr (i32.load (i32.const 204)))
+
+        ;; The child
+        (if (i32.eqz (i32.load (i32.const 208)))
+            (then
+                (memory.copy (local.get $addr) (i32.const 110) (i32.const 5))
+                (call $check (call $fd_msync (local.get $addr) (i32.const 16)))
+                (return)
+            )
+        )
+
+        ;; The parent
+        (memory.copy (i32.add (local.get $addr) (i32.const 8)) (i32.const 120) (i32.const 6))
+        (i32.store8 (i32.const 300) (i32.const 1))
+        (i32.store (i32.const 304) (i32.load (i32.const 208)))
+        (call $check (call $proc_join (i32.const 300) (i32.const 0) (i32.const 320)))
+        (call $check (call $fd_msync (local.get $addr) (i32.const 16)))
+
+        (i32.store (i32.const 0) (local.get $addr))
+        (i32.store (i32.const 4) (i32.const 16))
+        (call $check (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 8)))
+    )
+)
+"#;
+
+#[test]
+fn test_synced_mapping_survives_fork() {
+    let stdout = run_wat(FORK_PROGRAM);
+    assert_eq!(stdout, b"child\0\0\0parent\0\0");
+}
@@ -0,0 +1,244 @@
+//! Small WAT programs that exercise the WASIX syscalls, each module
+//! covering one syscall or feature.
+
+mod blocking_io_retry;
+mod call_hooks;
+mod clock_policy;
+mod cloexec;
+mod copy_file_range;
+mod core_dump;
+mod deep_sleep_stats;
+mod deterministic;
+mod deterministic_scheduling;
+mod entropy;
+mod exec_corrupt;
+mod exec_signals;
+mod fd_lock;
+mod fd_read;
+mod fd_read_deadline;
+mod fd_seek;
+mod filestat;
+mod fork;
+mod fork_append;
+mod fork_throttle;
+mod fs_watch;
+mod hostname;
+mod idle_eviction;
+mod ioctl;
+mod journal_restore;
+mod memfd;
+mod memory_import;
+mod mmap;
+mod net_interfaces;
+mod no_filesystem;
+mod path_open_exclusive;
+mod path_open_parent;
+mod path_open_reopen;
+mod path_open_rights;
+mod perf_counter;
+mod pipe_hangup;
+mod poll_oneoff_deadline;
+mod proc_flush;
+mod proc_title;
+mod proc_uptime;
+mod proc_wait_any;
+mod process_pause;
+mod process_template;
+mod rlimit;
+mod sandbox_paths;
+mod shebang;
+mod signal_interrupt;
+mod signalfd;
+mod sigpipe;
+mod single_threaded;
+mod snapshot_size_limit;
+mod sock_error;
+mod sock_fds;
+mod sock_multicast;
+mod sock_pair;
+mod sock_recv_flags;
+mod sock_send_file;
+mod sock_send_partial;
+mod sock_stats;
+mod sock_ttl;
+mod stack_overflow;
+mod stream_backed_file;
+mod syscall_log;
+mod syscall_rate_limit;
+mod sysinfo;
+mod timer_wheel;
+
+use std::sync::Arc;
+
+use virtual_fs::AsyncReadExt;
+use virtual_mio::block_on;
+use wasmer::Module;
+use wasmer_types::ModuleHash;
+use wasmer_wasix::{
+    Pipe, PluggableRuntime, SpawnError, VirtualTaskManager, WasiEnv, WasiEnvBuilder,
+    bin_factory::spawn_exec_module,
+    os::task::TaskJoinHandle,
+    runners::wasi::{RuntimeOrEngine, WasiRunner},
+    runtime::task_manager::tokio::TokioTaskManager,
+};
+use wasmer_wasix_types::wasi::ExitCode;
+
+/// Builds the tokio runtime the programs run on
+fn tokio_runtime() -> tokio::runtime::Runtime {
+    tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .unwrap()
+}
+
+/// Runs a WAT program with a [`WasiRunner`] and returns what it wrote to
+/// stdout
+pub(crate) fn run_wat(wat: impl AsRef<[u8]>) -> Vec<u8> {
+    run_wat_with(wat, |_| {})
+}
+
+/// Runs a WAT program with a [`WasiRunner`] that `configure` has set up,
+/// and returns what it wrote to stdout
+pub(crate) fn run_wat_with(
+    wat: impl AsRef<[u8]>,
+    configure: impl FnOnce(&mut WasiRunner),
+) -> Vec<u8> {
+    let (result, stdout) = try_run_wat_with(wat, configure);
+    result.unwrap();
+    stdout
+}
+
+/// Same as [`run_wat_with`], but hands back how the run ended instead of
+/// requiring it to succeed
+pub(crate) fn try_run_wat_with(
+    wat: impl AsRef<[u8]>,
+    configure: impl FnOnce(&mut WasiRunner),
+) -> (anyhow::Result<()>, Vec<u8>) {
+    let runtime = tokio_runtime();
+    let _guard = runtime.enter();
+
+    let engine = wasmer::Engine::default();
+    let module = Module::new(&engine, wat).unwrap();
+    run_module(RuntimeOrEngine::Engine(engine), module, configure)
+}
+
+fn run_module(
+    runtime: RuntimeOrEngine,
+    module: Module,
+    configure: impl FnOnce(&mut WasiRunner),
+) -> (anyhow::Result<()>, Vec<u8>) {
+    let (stdout_tx, mut stdout_rx) = Pipe::channel();
+    let result = {
+        let mut runner = WasiRunner::new();
+        runner.with_stdout(Box::new(stdout_tx));
+        configure(&mut runner);
+
+        runner.run_wasm(runtime, "command-name", module, ModuleHash::random())
+    };
+
+    let mut stdout = Vec::new();
+    block_on(stdout_rx.read_to_end(&mut stdout)).unwrap();
+    (result, stdout)
+}
+
+/// A [`PluggableRuntime`] whose tasks run on a tokio runtime of its own, for
+/// the programs that need a configured runtime or environment
+pub(crate) struct TestRuntime {
+    tokio: tokio::runtime::Runtime,
+    pub(crate) rt: PluggableRuntime,
+}
+
+impl TestRuntime {
+    pub(crate) fn new() -> Self {
+        Self::with_task_manager(|handle| Arc::new(TokioTaskManager::new(handle.clone())))
+    }
+
+    /// Runs the tasks on the task manager `tasks` creates on top of the
+    /// tokio runtime
+    pub(crate) fn with_task_manager(
+        tasks: impl FnOnce(&tokio::runtime::Handle) -> Arc<dyn VirtualTaskManager>,
+    ) -> Self {
+        let tokio = tokio_runtime();
+        let rt = {
+            let _guard = tokio.enter();
+            PluggableRuntime::new(tasks(tokio.handle()))
+        };
+        Self { tokio, rt }
+    }
+
+    pub(crate) fn handle(&self) -> &tokio::runtime::Handle {
+        self.tokio.handle()
+    }
+
+    pub(crate) fn enter(&self) -> tokio::runtime::EnterGuard<'_> {
+        self.tokio.enter()
+    }
+
+    /// Compiles a WAT program with the engine of the runtime
+    pub(crate) fn module(&self, wat: impl AsRef<[u8]>) -> Module {
+        Module::new(&self.rt.engine, wat).unwrap()
+    }
+
+    /// Runs a WAT program on the runtime with a [`WasiRunner`], returning
+    /// how the run ended and what it wrote to stdout
+    pub(crate) fn run_wat(&self, wat: impl AsRef<[u8]>) -> (anyhow::Result<()>, Vec<u8>) {
+        self.run_wat_with(wat, |_| {})
+    }
+
+    /// Runs a WAT program on the runtime with a [`WasiRunner`] that
+    /// `configure` has set up, returning how the run ended and what it wrote
+    /// to stdout
+    pub(crate) fn run_wat_with(
+        &self,
+        wat: impl AsRef<[u8]>,
+        configure: impl FnOnce(&mut WasiRunner),
+    ) -> (anyhow::Result<()>, Vec<u8>) {
+        let _guard = self.enter();
+        let rt = Arc::new(self.rt.clone());
+        run_module(RuntimeOrEngine::Runtime(rt), self.module(wat), configure)
+    }
+
+    /// Spawns a WAT program in the environment `builder` describes and waits
+    /// for it to exit, returning its exit code and what it wrote to stdout
+    pub(crate) fn spawn_wat(
+        &self,
+        wat: impl AsRef<[u8]>,
+        builder: WasiEnvBuilder,
+    ) -> (ExitCode, Vec<u8>) {
+        self.spawn(self.module(wat), builder)
+    }
+
+    /// Same as [`TestRuntime::spawn_wat`] for a module that is already
+    /// compiled
+    pub(crate) fn spawn(&self, module: Module, builder: WasiEnvBuilder) -> (ExitCode, Vec<u8>) {
+        let (env, mut stdout_rx) = self.build_env(builder);
+        let mut task = self.start(module, env).unwrap();
+        let exit_code = block_on(task.wait_finished()).unwrap();
+
+        let mut stdout = Vec::new();
+        block_on(stdout_rx.read_to_end(&mut stdout)).unwrap();
+        (exit_code, stdout)
+    }
+
+    /// Builds the environment `builder` describes on top of the runtime,
+    /// with its stdout going to the returned pipe
+    pub(crate) fn build_env(&self, builder: WasiEnvBuilder) -> (WasiEnv, Pipe) {
+        let _guard = self.enter();
+        let (stdout_tx, stdout_rx) = Pipe::channel();
+        let env = builder
+            .stdout(Box::new(stdout_tx))
+            .runtime(Arc::new(self.rt.clone()))
+            .build()
+            .unwrap();
+        (env, stdout_rx)
+    }
+
+    /// Spawns `module` in `env` without waiting for it, for the tests that
+    /// deal with the program while it runs
+    #[allow(clippy::result_large_err)]
+    pub(crate) fn start(&self, module: Module, env: WasiEnv) -> Result<TaskJoinHandle, SpawnError> {
+        let _guard = self.enter();
+        let rt = env.runtime.clone();
+        spawn_exec_module(module, env, &rt, Default::default())
+    }
+}
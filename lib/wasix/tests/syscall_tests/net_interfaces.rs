@@ -0,0 +1,130 @@
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+use virtual_net::{IpCidr, NetworkInterface, VirtualNetworking};
+use wasmer_wasix::WasiEnv;
+use wasmer_wasix_types::wasi::{Errno, Netifflags};
+
+use super::TestRuntime;
+
+/// Lists the interfaces into buffers that are big enough and then again
+/// into a buffer that only fits one interface. The results, the counts, the
+/// interfaces and the addresses are written to stdout.
+const PROGRAM: &str = r#"
+(module
+    (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+    (import "wasix_32v1" "net_interfaces" (func $net_interfaces (param i32 i32 i32 i32) (result i32)))
+
+    (memory 1)
+    (export "memory" (memory 0))
+
+    (func $check (param i32)
+        (if (local.get 0) (then unreachable))
+    )
+
+    (func $main (export "_start")
+        (i32.store (i32.const 908) (i32.const 4))
+        (i32.store (i32.const 912) (i32.const 8))
+        (i32.store (i32.const 900)
+            (call $net_interfaces (i32.const 1000) (i32.const 908) (i32.const 2000) (i32.const 912)))
+
+        (i32.store (i32.const 916) (i32.const 1))
+        (i32.store (i32.const 920) (i32.const 8))
+        (i32.store (i32.const 904)
+            (call $net_interfaces (i32.const 3000) (i32.const 916) (i32.const 4000) (i32.const 920)))
+
+        (i32.store (i32.const 0) (i32.const 900))
+        (i32.store (i32.const 4) (i32.const 24))
+        (i32.store (i32.const 8) (i32.const 1000))
+        (i32.store (i32.const 12) (i32.const 56))
+        (i32.store (i32.const 16) (i32.const 2000))
+        (i32.store (i32.const 20) (i32.const 57))
+        (call $check (call $fd_write (i32.const 1) (i32.const 0) (i32.const 3) (i32.const 24)))
+    )
+)
+"#;
+
+/// Network with an ethernet and a loopback interface
+#[derive(Debug)]
+struct MockNetworking;
+
+#[async_trait::async_trait]
+impl VirtualNetworking for MockNetworking {
+    async fn interfaces(&self) -> virtual_net::Result<Vec<NetworkInterface>> {
+        Ok(vec![
+            NetworkInterface {
+                name: "eth0".to_string(),
+                addrs: vec![
+                    IpCidr {
+                        ip: IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2)),
+                        prefix: 24,
+                    },
+                    IpCidr {
+                        ip: IpAddr::V6(Ipv6Addr::new(0xfe80, 0, 0, 0, 0, 0, 0, 1)),
+                        prefix: 64,
+                    },
+                ],
+                up: true,
+                loopback: false,
+                multicast: true,
+            },
+            NetworkInterface {
+                name: "lo".to_string(),
+                addrs: vec![IpCidr {
+                    ip: IpAddr::V4(Ipv4Addr::LOCALHOST),
+                    prefix: 8,
+                }],
+                up: true,
+                loopback: true,
+                multicast: false,
+            },
+        ])
+    }
+}
+
+#[test]
+fn test_net_interfaces_lists_the_interfaces_of_the_network() {
+    let mut runtime = TestRuntime::new();
+
+    runtime.rt.set_networking_implementation(MockNetworking);
+
+    let builder = WasiEnv::builder("main");
+    let (exit_code, stdout) = runtime.spawn_wat(PROGRAM, builder);
+    assert!(exit_code.is_success());
+
+    let word = |at: usize| u32::from_le_bytes(stdout[at..at + 4].try_into().unwrap());
+    let half = |at: usize| u16::from_le_bytes(stdout[at..at + 2].try_into().unwrap());
+
+    assert_eq!(word(0), Errno::Success as u32);
+    assert_eq!([word(8), word(12)], [2, 3]);
+
+    // The buffer for the interfaces is too small the second time around
+    assert_eq!(word(4), Errno::Overflow as u32);
+    assert_eq!([word(16), word(20)], [2, 3]);
+
+    // Each interface is a name, flags, the offset of its first address
+    // and the number of addresses
+    let eth0 = &stdout[24..52];
+    assert_eq!(&eth0[..16], b"eth0\0\0\0\0\0\0\0\0\0\0\0\0");
+    assert_eq!(
+        half(24 + 16),
+        (Netifflags::UP | Netifflags::MULTICAST).bits()
+    );
+    assert_eq!([word(24 + 20), word(24 + 24)], [0, 2]);
+    let lo = &stdout[52..80];
+    assert_eq!(&lo[..16], b"lo\0\0\0\0\0\0\0\0\0\0\0\0\0\0");
+    assert_eq!(
+        half(52 + 16),
+        (Netifflags::UP | Netifflags::LOOPBACK).bits()
+    );
+    assert_eq!([word(52 + 20), word(52 + 24)], [2, 1]);
+
+    // Each address is the family, a padding byte, the octets and the prefix
+    let addrs = &stdout[80..];
+    assert_eq!(&addrs[..7], &[1, 0, 10, 0, 0, 2, 24]);
+    assert_eq!(addrs[19], 2);
+    assert_eq!(
+        &addrs[21..38],
+        &[0xfe, 0x80, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 64]
+    );
+    assert_eq!(&addrs[38..45], &[1, 0, 127, 0, 0, 1, 8]);
+}
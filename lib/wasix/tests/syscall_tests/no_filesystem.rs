@@ -0,0 +1,96 @@
+use wasmer_wasix::WasiEnv;
+use wasmer_wasix_types::wasi::Errno;
+
+use super::TestRuntime;
+
+#[test]
+fn test_paths_are_refused_without_a_file_system() {
+    let runtime = TestRuntime::new();
+
+    // Looks for the root descriptor, polls stdout and sends a datagram
+    // between two sockets, then writes the results to stdout
+    let wat = r#"
+    (module
+        (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "fd_prestat_get" (func $fd_prestat_get (param i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "path_open" (func $path_open (param i32 i32 i32 i32 i32 i64 i64 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "poll_oneoff" (func $poll_oneoff (param i32 i32 i32 i32) (result i32)))
+        (import "wasix_32v1" "sock_open" (func $sock_open (param i32 i32 i32 i32) (result i32)))
+        (import "wasix_32v1" "sock_bind" (func $sock_bind (param i32 i32) (result i32)))
+        (import "wasix_32v1" "sock_addr_local" (func $sock_addr_local (param i32 i32) (result i32)))
+        (import "wasix_32v1" "sock_send_to" (func $sock_send_to (param i32 i32 i32 i32 i32 i32) (result i32)))
+        (import "wasix_32v1" "sock_recv" (func $sock_recv (param i32 i32 i32 i32 i32 i32) (result i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        (data (i32.const 100) "etc/passwd")
+        (data (i32.const 130) "ping")
+
+        ;; 127.0.0.1:0 for both the receiver and the sender
+        (data (i32.const 304) "\7f\00\00\01")
+        (data (i32.const 344) "\7f\00\00\01")
+
+        (func $main (export "_start")
+            ;; There is no root descriptor to open paths from
+            (i32.store (i32.const 1024) (call $fd_prestat_get (i32.const 3) (i32.const 700)))
+            (i32.store (i32.const 1028)
+                (call $path_open (i32.const 3) (i32.const 0) (i32.const 100) (i32.const 10) (i32.const 0) (i64.const -1) (i64.const -1) (i32.const 0) (i32.const 212)))
+
+            ;; Stdout can be polled
+            (i64.store (i32.const 400) (i64.const 0))
+            (i32.store8 (i32.const 408) (i32.const 2))
+            (i32.store (i32.const 416) (i32.const 1))
+            (i32.store (i32.const 1032)
+                (call $poll_oneoff (i32.const 400) (i32.const 800) (i32.const 1) (i32.const 1036)))
+
+            ;; A datagram makes it from one socket to the other, the receiver
+            ;; is bound to any port which is then read back
+            (i32.store (i32.const 1040) (call $sock_open (i32.const 1) (i32.const 2) (i32.const 17) (i32.const 200)))
+            (i32.store (i32.const 1044) (call $sock_open (i32.const 1) (i32.const 2) (i32.const 17) (i32.const 204)))
+            (i32.store16 (i32.const 300) (i32.const 1))
+            (i32.store (i32.const 1048) (call $sock_bind (i32.load (i32.const 200)) (i32.const 300)))
+            (i32.store16 (i32.const 340) (i32.const 1))
+            (i32.store (i32.const 1052) (call $sock_bind (i32.load (i32.const 204)) (i32.const 340)))
+            (i32.store (i32.const 1056) (call $sock_addr_local (i32.load (i32.const 200)) (i32.const 300)))
+            ;; The port comes back in network byte order
+            (i32.store16 (i32.const 302)
+                (i32.or
+                    (i32.shl (i32.load8_u (i32.const 302)) (i32.const 8))
+                    (i32.load8_u (i32.const 303))))
+            (i32.store (i32.const 0) (i32.const 130))
+            (i32.store (i32.const 4) (i32.const 4))
+            (i32.store (i32.const 1060)
+                (call $sock_send_to (i32.load (i32.const 204)) (i32.const 0) (i32.const 1) (i32.const 0) (i32.const 300) (i32.const 208)))
+            (i32.store (i32.const 16) (i32.const 1076))
+            (i32.store (i32.const 20) (i32.const 16))
+            (i32.store (i32.const 1064)
+                (call $sock_recv (i32.load (i32.const 200)) (i32.const 16) (i32.const 1) (i32.const 0) (i32.const 1068) (i32.const 1072)))
+
+            ;; Send the results to stdout
+            (i32.store (i32.const 0) (i32.const 1024))
+            (i32.store (i32.const 4) (i32.const 56))
+            (drop (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 8)))
+        )
+    )
+    "#;
+
+    let builder = WasiEnv::builder("network-only").no_filesystem();
+    let (exit_code, stdout) = runtime.spawn_wat(wat, builder);
+    assert!(exit_code.is_success());
+
+    assert_eq!(stdout.len(), 56);
+    let u32_at = |offset: usize| u32::from_le_bytes(stdout[offset..offset + 4].try_into().unwrap());
+
+    assert_eq!(u32_at(0), Errno::Badf as u32, "there is no fd 3");
+    assert_eq!(u32_at(4), Errno::Badf as u32);
+
+    assert_eq!(u32_at(8), Errno::Success as u32);
+    assert_eq!(u32_at(12), 1, "stdout is writable");
+
+    for offset in (16..44).step_by(4) {
+        assert_eq!(u32_at(offset), Errno::Success as u32);
+    }
+    assert_eq!(u32_at(44), 4);
+    assert_eq!(&stdout[52..56], b"ping");
+}
@@ -0,0 +1,226 @@
+use std::sync::Arc;
+
+use virtual_fs::FileSystem;
+use wasmer_wasix::{
+    WasiEnv,
+    journal::{BufferedJournal, JournalEntry, ReadableJournal},
+};
+use wasmer_wasix_types::wasi::Errno;
+
+use super::TestRuntime;
+
+/// Opens `/lock.txt` exclusively and forks, the child tries to open it
+/// exclusively as well and writes the result to stdout. Once the child is
+/// done the parent writes the result of its own open. When the first open
+/// fails the result is written right away without forking.
+const PROGRAM: &str = r#"
+(module
+    (import "wasix_32v1" "path_open2" (func $path_open2 (param i32 i32 i32 i32 i32 i64 i64 i32 i32 i32) (result i32)))
+    (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+    (import "wasix_32v1" "proc_fork" (func $proc_fork (param i32 i32) (result i32)))
+    (import "wasix_32v1" "proc_join" (func $proc_join (param i32 i32 i32) (result i32)))
+
+    ;; Only a process with a shared memory can be forked
+    (import "env" "memory" (memory 1 16 shared))
+
+    (global $__stack_low (export "__stack_low") i32 (i32.const 16384))
+    (global $__stack_pointer (export "__stack_pointer") (mut i32) (i32.const 32768))
+
+    (data (i32.const 100) "/lock.txt")
+
+    ;; `_start` is the only function that is ever unwound and it keeps
+    ;; nothing on the stack, so asyncify just needs to track its state
+    (global $asyncify_state (mut i32) (i32.const 0))
+    (func (export "asyncify_start_unwind") (param i32)
+        (global.set $asyncify_state (i32.const 1))
+    )
+    (func (export "asyncify_stop_unwind")
+        (global.set $asyncify_state (i32.const 0))
+    )
+    (func (export "asyncify_start_rewind") (param i32)
+        (global.set $asyncify_state (i32.const 2))
+    )
+    (func (export "asyncify_stop_rewind")
+        (global.set $asyncify_state (i32.const 0))
+    )
+    (func (export "asyncify_get_state") (result i32)
+        (global.get $asyncify_state)
+    )
+
+    (func $check (param i32)
+        (if (local.get 0) (then unreachable))
+    )
+
+    ;; Opens the file exclusively, the result goes to the byte at `$result`
+    (func $open (param $ret i32) (param $result i32)
+        (i32.store8 (local.get $result)
+            (call $path_open2 (i32.const 3) (i32.const 0) (i32.const 100) (i32.const 9)
+                (i32.const 1) (i64.const -1) (i64.const -1) (i32.const 0) (i32.const 2) (local.get $ret)))
+    )
+
+    (func $write (param $ptr i32)
+        (i32.store (i32.const 0) (local.get $ptr))
+        (i32.store (i32.const 4) (i32.const 1))
+        (call $check (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 8)))
+    )
+
+    (func $main (export "_start")
+        ;; The file is only opened the first time around, not when `_start`
+        ;; is rewound
+        (if (i32.eqz (global.get $asyncify_state))
+            (then
+                (call $open (i32.const 200) (i32.const 300))
+                (if (i32.load8_u (i32.const 300))
+                    (then
+                        (call $write (i32.const 300))
+                        (return)
+                    )
+                )
+            )
+        )
+
+        (call $check (call $proc_fork (i32.const 1) (i32.const 212)))
+        (if (i32.eq (global.get $asyncify_state) (i32.const 1)) (then return))
+
+        ;; The child
+        (if (i32.eqz (i32.load (i32.const 212)))
+            (then
+                (call $open (i32.const 216) (i32.const 301))
+                (call $write (i32.const 301))
+                (return)
+            )
+        )
+
+        ;; The parent
+        (i32.store8 (i32.const 400) (i32.const 1))
+        (i32.store (i32.const 404) (i32.load (i32.const 212)))
+        (call $check (call $proc_join (i32.const 400) (i32.const 0) (i32.const 420)))
+        (call $write (i32.const 300))
+    )
+)
+"#;
+
+#[test]
+fn test_exclusive_open_locks_the_host_file() {
+    let runtime = TestRuntime::new();
+    let module = runtime.module(PROGRAM);
+
+    let temp = tempfile::TempDir::new().unwrap();
+    let run = || {
+        let fs =
+            virtual_fs::host_fs::FileSystem::new(runtime.handle().clone(), temp.path()).unwrap();
+        let builder = WasiEnv::builder("main")
+            .fs(Arc::new(fs) as Arc<dyn FileSystem + Send + Sync>)
+            .preopen_dir("/")
+            .unwrap();
+        let (exit_code, stdout) = runtime.spawn(module.clone(), builder);
+        assert!(exit_code.is_success());
+        stdout
+    };
+
+    // The child can't open the file that the parent holds
+    assert_eq!(run(), [Errno::Busy as u8, Errno::Success as u8]);
+
+    // The lock went away with the processes, now the host takes it
+    let file = std::fs::File::open(temp.path().join("lock.txt")).unwrap();
+    file.try_lock().unwrap();
+    assert_eq!(run(), [Errno::Busy as u8]);
+
+    file.unlock().unwrap();
+    assert_eq!(run(), [Errno::Busy as u8, Errno::Success as u8]);
+}
+
+/// Opens `/lock.txt` exclusively and truncates it, with a descriptor that
+/// can only be written to, and writes the result to stdout
+const TRUNCATE_PROGRAM: &str = r#"
+    (module
+        (import "wasix_32v1" "path_open2" (func $path_open2 (param i32 i32 i32 i32 i32 i64 i64 i32 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        (data (i32.const 100) "/lock.txt")
+
+        (func $main (export "_start")
+            (i32.store8 (i32.const 300)
+                (call $path_open2 (i32.const 3) (i32.const 0) (i32.const 100) (i32.const 9)
+                    (i32.const 8) (i64.const 64) (i64.const 64) (i32.const 0) (i32.const 2) (i32.const 200)))
+
+            (i32.store (i32.const 0) (i32.const 300))
+            (i32.store (i32.const 4) (i32.const 1))
+            (drop (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 8)))
+        )
+    )
+"#;
+
+#[test]
+fn test_exclusive_open_truncates_the_file_once_it_is_locked() {
+    let runtime = TestRuntime::new();
+    let module = runtime.module(TRUNCATE_PROGRAM);
+
+    let temp = tempfile::TempDir::new().unwrap();
+    let path = temp.path().join("lock.txt");
+    std::fs::write(&path, b"data").unwrap();
+    let run = || {
+        let fs =
+            virtual_fs::host_fs::FileSystem::new(runtime.handle().clone(), temp.path()).unwrap();
+        let builder = WasiEnv::builder("main")
+            .fs(Arc::new(fs) as Arc<dyn FileSystem + Send + Sync>)
+            .preopen_dir("/")
+            .unwrap();
+        let (exit_code, stdout) = runtime.spawn(module.clone(), builder);
+        assert!(exit_code.is_success());
+        stdout
+    };
+
+    // The file that the host holds is left alone
+    let file = std::fs::File::open(&path).unwrap();
+    file.try_lock().unwrap();
+    assert_eq!(run(), [Errno::Busy as u8]);
+    assert_eq!(std::fs::read(&path).unwrap(), b"data");
+
+    // Once it is released it is truncated
+    file.unlock().unwrap();
+    assert_eq!(run(), [Errno::Success as u8]);
+    assert_eq!(std::fs::read(&path).unwrap(), b"");
+}
+
+#[test]
+fn test_exclusive_open_journals_the_truncation() {
+    let runtime = TestRuntime::new();
+    let module = runtime.module(TRUNCATE_PROGRAM);
+
+    let temp = tempfile::TempDir::new().unwrap();
+    std::fs::write(temp.path().join("lock.txt"), b"data").unwrap();
+    let fs = virtual_fs::host_fs::FileSystem::new(runtime.handle().clone(), temp.path()).unwrap();
+    let journal = Arc::new(BufferedJournal::default());
+    let mut builder = WasiEnv::builder("main")
+        .fs(Arc::new(fs) as Arc<dyn FileSystem + Send + Sync>)
+        .preopen_dir("/")
+        .unwrap();
+    builder.add_writable_journal(journal.clone());
+
+    let (exit_code, stdout) = runtime.spawn(module, builder);
+    assert!(exit_code.is_success());
+    assert_eq!(stdout, [Errno::Success as u8]);
+
+    // The open is followed by the truncation of the file it opened
+    let journal = journal.as_restarted().unwrap();
+    let mut opened = None;
+    let mut truncated = false;
+    while let Some(entry) = journal.read().unwrap() {
+        match entry.into_inner() {
+            JournalEntry::OpenFileDescriptorV2 { fd, path, .. } if path == "/lock.txt" => {
+                opened = Some(fd);
+            }
+            JournalEntry::FileDescriptorSetSizeV1 { fd, st_size } => {
+                assert_eq!(Some(fd), opened);
+                assert_eq!(st_size, 0);
+                truncated = true;
+            }
+            _ => {}
+        }
+    }
+    assert!(truncated);
+}
@@ -0,0 +1,151 @@
+use std::{path::Path, sync::Arc};
+
+use virtual_fs::{FileSystem, TmpFileSystem};
+use wasmer_wasix::WasiEnv;
+use wasmer_wasix_types::wasi::{Errno, Rights};
+
+use super::{TestRuntime, run_wat};
+
+#[test]
+fn test_path_open_parent() {
+    let stdout = run_wat(br#"
+    (module
+        (import "wasix_32v1" "path_open_parent" (func $path_open_parent (param i32 i32 i32 i64 i64 i32 i32 i32 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "path_open" (func $path_open (param i32 i32 i32 i32 i32 i64 i64 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "path_rename" (func $path_rename (param i32 i32 i32 i32 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "path_create_directory" (func $path_create_directory (param i32 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "path_filestat_get" (func $path_filestat_get (param i32 i32 i32 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        (data (i32.const 100) "tmp/sub")
+        (data (i32.const 120) "tmp/sub/new.txt")
+        (data (i32.const 140) "tmp/moved")
+        (data (i32.const 160) "tmp.new")
+        (data (i32.const 180) "tmp/moved/new.txt")
+        (data (i32.const 200) "/")
+
+        (func $check (param i32)
+            (if (local.get 0) (then unreachable))
+        )
+
+        (func $main (export "_start")
+            (call $check (call $path_create_directory (i32.const 3) (i32.const 100) (i32.const 7)))
+
+            ;; Open the parent of tmp/sub/new.txt, the name goes to 1056
+            (i32.store (i32.const 1024) (call $path_open_parent (i32.const 3) (i32.const 120) (i32.const 15)
+                (i64.const -1) (i64.const -1) (i32.const 0) (i32.const 1056) (i32.const 16) (i32.const 1028) (i32.const 300)))
+
+            ;; Move the directory out of the way, the descriptor still refers to it
+            (call $check (call $path_rename (i32.const 3) (i32.const 100) (i32.const 7) (i32.const 3) (i32.const 140) (i32.const 9)))
+
+            ;; Create a temporary file in it and rename it into place
+            (call $check (call $path_open (i32.load (i32.const 300)) (i32.const 0) (i32.const 160) (i32.const 7)
+                (i32.const 1) (i64.const -1) (i64.const -1) (i32.const 0) (i32.const 304)))
+            (call $check (call $path_rename (i32.load (i32.const 300)) (i32.const 160) (i32.const 7)
+                (i32.load (i32.const 300)) (i32.const 1056) (i32.load (i32.const 1028))))
+            (i32.store (i32.const 1032) (call $path_filestat_get (i32.const 3) (i32.const 0) (i32.const 180) (i32.const 17) (i32.const 2048)))
+            (i32.store (i32.const 1052) (call $path_filestat_get (i32.const 3) (i32.const 0) (i32.const 120) (i32.const 15) (i32.const 2048)))
+
+            ;; The root has no parent, the name goes to 1072
+            (i32.store (i32.const 1036) (call $path_open_parent (i32.const 3) (i32.const 200) (i32.const 1)
+                (i64.const -1) (i64.const -1) (i32.const 0) (i32.const 1072) (i32.const 16) (i32.const 1040) (i32.const 308)))
+
+            ;; A name that doesn't fit in the buffer
+            (i32.store (i32.const 1044) (call $path_open_parent (i32.const 3) (i32.const 120) (i32.const 15)
+                (i64.const -1) (i64.const -1) (i32.const 0) (i32.const 1088) (i32.const 2) (i32.const 1048) (i32.const 312)))
+
+            ;; Send the results to stdout
+            (i32.store (i32.const 0) (i32.const 1024))
+            (i32.store (i32.const 4) (i32.const 64))
+            (call $check (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 8)))
+        )
+    )
+    "#);
+    let value = |offset: usize| u32::from_le_bytes(stdout[offset..offset + 4].try_into().unwrap());
+
+    // The parent was opened and the name of the file returned
+    assert_eq!(value(0), Errno::Success as u32);
+    assert_eq!(value(4), 7);
+    assert_eq!(&stdout[32..39], b"new.txt");
+
+    // The file was created in the directory after it was moved
+    assert_eq!(value(8), Errno::Success as u32);
+    assert_eq!(value(28), Errno::Noent as u32);
+
+    // The root is its own parent
+    assert_eq!(value(12), Errno::Success as u32);
+    assert_eq!(value(16), 1);
+    assert_eq!(&stdout[48..49], b".");
+
+    // Nothing is opened when the name doesn't fit
+    assert_eq!(value(20), Errno::Range as u32);
+    assert_eq!(value(24), 7);
+}
+
+#[test]
+fn test_path_open_parent_keeps_a_read_only_preopen_read_only() {
+    let runtime = TestRuntime::new();
+
+    let fs = TmpFileSystem::new();
+    fs.create_dir(Path::new("/data")).unwrap();
+    fs.create_dir(Path::new("/data/sub")).unwrap();
+
+    // The directory is preopened for reading only
+    let builder = WasiEnv::builder("main")
+        .fs(Arc::new(fs) as Arc<dyn FileSystem + Send + Sync>)
+        .preopen_build(|p| p.directory("/data").read(true))
+        .unwrap();
+    let (exit_code, stdout) = runtime.spawn_wat(
+        br#"
+    (module
+        (import "wasix_32v1" "path_open_parent" (func $path_open_parent (param i32 i32 i32 i64 i64 i32 i32 i32 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "path_open" (func $path_open (param i32 i32 i32 i32 i32 i64 i64 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "fd_fdstat_get" (func $fd_fdstat_get (param i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        (data (i32.const 100) "sub/file.txt")
+
+        (func $check (param i32)
+            (if (local.get 0) (then unreachable))
+        )
+
+        (func $main (export "_start")
+            ;; Ask for every right on the parent of sub/file.txt
+            (call $check (call $path_open_parent (i32.const 4) (i32.const 100) (i32.const 12)
+                (i64.const -1) (i64.const -1) (i32.const 0) (i32.const 1056) (i32.const 16) (i32.const 1028) (i32.const 300)))
+            (call $check (call $fd_fdstat_get (i32.load (i32.const 300)) (i32.const 400)))
+            (i64.store (i32.const 1032) (i64.load (i32.const 408)))
+            (i64.store (i32.const 1040) (i64.load (i32.const 416)))
+
+            ;; Creating the file for writing through it is refused
+            (i32.store (i32.const 1024) (call $path_open (i32.load (i32.const 300)) (i32.const 0) (i32.const 1056) (i32.load (i32.const 1028))
+                (i32.const 1) (i64.const 64) (i64.const 64) (i32.const 0) (i32.const 304)))
+
+            (i32.store (i32.const 0) (i32.const 1024))
+            (i32.store (i32.const 4) (i32.const 24))
+            (call $check (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 8)))
+        )
+    )
+    "#,
+        builder,
+    );
+    assert!(exit_code.is_success());
+
+    let rights_at = |offset: usize| {
+        Rights::from_bits_truncate(u64::from_le_bytes(
+            stdout[offset..offset + 8].try_into().unwrap(),
+        ))
+    };
+    assert_eq!(
+        u32::from_le_bytes(stdout[0..4].try_into().unwrap()),
+        Errno::Notcapable as u32
+    );
+    assert!(!rights_at(8).contains(Rights::FD_WRITE));
+    assert!(!rights_at(16).contains(Rights::FD_WRITE));
+}
@@ -0,0 +1,69 @@
+use std::sync::Arc;
+
+use virtual_fs::FileSystem;
+use virtual_mio::block_on;
+use wasmer_wasix::WasiEnv;
+
+use super::TestRuntime;
+
+/// Writes `hello` to `/out.txt`, opens it again with `O_TRUNC` and writes
+/// `bye` through the second descriptor before syncing it.
+const PROGRAM: &str = r#"
+(module
+    (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+    (import "wasi_snapshot_preview1" "fd_sync" (func $fd_sync (param i32) (result i32)))
+    (import "wasi_snapshot_preview1" "path_open" (func $path_open (param i32 i32 i32 i32 i32 i64 i64 i32 i32) (result i32)))
+
+    (memory 1)
+    (export "memory" (memory 0))
+
+    (data (i32.const 100) "/out.txt")
+    (data (i32.const 120) "hello")
+    (data (i32.const 140) "bye")
+
+    (func $check (param i32)
+        (if (local.get 0) (then unreachable))
+    )
+
+    (func $open (param $oflags i32) (param $out i32)
+        (call $check (call $path_open (i32.const 3) (i32.const 0) (i32.const 100) (i32.const 8)
+            (local.get $oflags) (i64.const -1) (i64.const -1) (i32.const 0) (local.get $out)))
+    )
+
+    (func $write (param $fd i32) (param $ptr i32) (param $len i32)
+        (i32.store (i32.const 0) (local.get $ptr))
+        (i32.store (i32.const 4) (local.get $len))
+        (call $check (call $fd_write (local.get $fd) (i32.const 0) (i32.const 1) (i32.const 8)))
+    )
+
+    (func $main (export "_start")
+        (call $open (i32.const 1) (i32.const 200))
+        (call $write (i32.load (i32.const 200)) (i32.const 120) (i32.const 5))
+
+        (call $open (i32.const 8) (i32.const 204))
+        (call $write (i32.load (i32.const 204)) (i32.const 140) (i32.const 3))
+        (call $check (call $fd_sync (i32.load (i32.const 204))))
+    )
+)
+"#;
+
+#[test]
+fn test_reopening_a_buffered_file_writes_it_out_before_truncating() {
+    let runtime = TestRuntime::new();
+
+    let temp = tempfile::TempDir::new().unwrap();
+    let fs = virtual_fs::host_fs::FileSystem::new(runtime.handle().clone(), temp.path()).unwrap();
+
+    let builder = WasiEnv::builder("main")
+        .fs(Arc::new(fs) as Arc<dyn FileSystem + Send + Sync>)
+        .write_buffer_size(1024);
+    let (env, _stdout_rx) = runtime.build_env(builder);
+    let mut task = runtime.start(runtime.module(PROGRAM), env).unwrap();
+
+    let exit_code = block_on(task.wait_finished()).unwrap();
+    assert!(exit_code.is_success());
+
+    // The truncation comes after the first write, which must not land on
+    // top of the second one
+    assert_eq!(std::fs::read(temp.path().join("out.txt")).unwrap(), b"bye");
+}
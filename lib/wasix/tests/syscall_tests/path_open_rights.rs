@@ -0,0 +1,83 @@
+use std::sync::Arc;
+
+use virtual_fs::{AsyncWriteExt, FileSystem, TmpFileSystem};
+use virtual_mio::block_on;
+use wasmer_wasix::WasiEnv;
+use wasmer_wasix_types::wasi::{Errno, Rights};
+
+use super::TestRuntime;
+
+/// Opens `file.txt` in the preopened directory (fd 4) once for writing and
+/// once with `FD_READ | FD_SYNC`, then writes both results and the rights of
+/// the second file descriptor to stdout.
+const PROGRAM: &str = r#"
+(module
+    (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+    (import "wasi_snapshot_preview1" "fd_fdstat_get" (func $fd_fdstat_get (param i32 i32) (result i32)))
+    (import "wasi_snapshot_preview1" "path_open" (func $path_open (param i32 i32 i32 i32 i32 i64 i64 i32 i32) (result i32)))
+
+    (memory 1)
+    (export "memory" (memory 0))
+
+    (data (i32.const 100) "file.txt")
+
+    (func $check (param i32)
+        (if (local.get 0) (then unreachable))
+    )
+
+    (func $main (export "_start")
+        (i32.store (i32.const 1024)
+            (call $path_open (i32.const 4) (i32.const 0) (i32.const 100) (i32.const 8)
+                (i32.const 0) (i64.const 64) (i64.const 64) (i32.const 0) (i32.const 200)))
+        (i32.store (i32.const 1028)
+            (call $path_open (i32.const 4) (i32.const 0) (i32.const 100) (i32.const 8)
+                (i32.const 0) (i64.const 18) (i64.const 18) (i32.const 0) (i32.const 204)))
+        (call $check (call $fd_fdstat_get (i32.load (i32.const 204)) (i32.const 304)))
+        (i64.store (i32.const 1032) (i64.load (i32.const 312)))
+        (i64.store (i32.const 1040) (i64.load (i32.const 320)))
+
+        (i32.store (i32.const 0) (i32.const 1024))
+        (i32.store (i32.const 4) (i32.const 24))
+        (call $check (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 8)))
+    )
+)
+"#;
+
+#[test]
+fn test_path_open_is_limited_to_the_inheriting_rights() {
+    let runtime = TestRuntime::new();
+
+    let fs = TmpFileSystem::new();
+    fs.create_dir(std::path::Path::new("/data")).unwrap();
+    let mut file = fs
+        .new_open_options()
+        .create(true)
+        .write(true)
+        .open("/data/file.txt")
+        .unwrap();
+    block_on(file.write_all(b"data")).unwrap();
+
+    // The directory is preopened for reading only
+    let builder = WasiEnv::builder("main")
+        .fs(Arc::new(fs) as Arc<dyn FileSystem + Send + Sync>)
+        .preopen_build(|p| p.directory("/data").read(true))
+        .unwrap();
+    let (exit_code, stdout) = runtime.spawn_wat(PROGRAM, builder);
+    assert!(exit_code.is_success());
+
+    assert_eq!(stdout.len(), 24);
+
+    let u32_at = |offset: usize| u32::from_le_bytes(stdout[offset..offset + 4].try_into().unwrap());
+    let rights_at = |offset: usize| {
+        Rights::from_bits_truncate(u64::from_le_bytes(
+            stdout[offset..offset + 8].try_into().unwrap(),
+        ))
+    };
+
+    // Writing is refused, reading works without the rights the directory
+    // doesn't pass on
+    assert_eq!(u32_at(0), Errno::Notcapable as u32);
+    assert_eq!(u32_at(4), Errno::Success as u32);
+    assert_eq!(rights_at(8), Rights::FD_READ);
+    assert_eq!(rights_at(16), Rights::FD_READ);
+}
@@ -0,0 +1,86 @@
+use std::time::Duration;
+
+use wasmer_wasix_types::wasi::Errno;
+
+use super::run_wat_with;
+
+/// Reads the counter twice with a 50ms sleep in between, both reads are
+/// surrounded by reads of the monotonic clock
+const PERF_COUNTER_PROGRAM: &[u8] = br#"
+(module
+    (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+    (import "wasi_snapshot_preview1" "clock_time_get" (func $clock_time_get (param i32 i64 i32) (result i32)))
+    (import "wasix_32v1" "thread_sleep" (func $thread_sleep (param i64) (result i32)))
+    (import "wasix_32v1" "perf_counter_read" (func $perf_counter_read (param i32) (result i32)))
+    (import "wasix_32v1" "perf_counter_frequency" (func $perf_counter_frequency (param i32) (result i32)))
+
+    (memory 1)
+    (export "memory" (memory 0))
+
+    (func $check (param i32)
+        (if (local.get 0) (then unreachable))
+    )
+
+    (func $main (export "_start")
+        (call $check (call $clock_time_get (i32.const 1) (i64.const 1) (i32.const 1000)))
+        (i32.store (i32.const 1040) (call $perf_counter_read (i32.const 1008)))
+        (call $check (call $thread_sleep (i64.const 50000000)))
+        (i32.store (i32.const 1044) (call $perf_counter_read (i32.const 1016)))
+        (call $check (call $clock_time_get (i32.const 1) (i64.const 1) (i32.const 1024)))
+        (i32.store (i32.const 1048) (call $perf_counter_frequency (i32.const 1032)))
+
+        ;; Send the clocks, the counters and the results to stdout
+        (i32.store (i32.const 0) (i32.const 1000))
+        (i32.store (i32.const 4) (i32.const 52))
+        (call $check (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 8)))
+    )
+)
+"#;
+
+/// What the guest read
+#[derive(Debug)]
+struct Readings {
+    clocks: [u64; 2],
+    counters: [u64; 2],
+    frequency: u64,
+    errnos: [u32; 3],
+}
+
+fn run_perf_counter_program(allow_perf_counters: bool) -> Readings {
+    let stdout = run_wat_with(PERF_COUNTER_PROGRAM, |runner| {
+        runner.capabilities_mut().allow_perf_counters = allow_perf_counters;
+    });
+    let dword = |at: usize| u64::from_le_bytes(stdout[at..at + 8].try_into().unwrap());
+    let word = |at: usize| u32::from_le_bytes(stdout[at..at + 4].try_into().unwrap());
+    Readings {
+        clocks: [dword(0), dword(24)],
+        counters: [dword(8), dword(16)],
+        frequency: dword(32),
+        errnos: [word(40), word(44), word(48)],
+    }
+}
+
+#[test]
+fn test_perf_counter_is_monotonic_and_matches_its_frequency() {
+    let readings = run_perf_counter_program(true);
+    assert_eq!(readings.errnos, [Errno::Success as u32; 3]);
+    assert!(readings.frequency > 0);
+
+    let [first, second] = readings.counters;
+    assert!(second > first, "{readings:?}");
+
+    // The time that the counter measured fits the time between the clocks
+    let measured = (second - first) as u128 * 1_000_000_000 / readings.frequency as u128;
+    let measured = Duration::from_nanos(measured as u64);
+    let elapsed = Duration::from_nanos(readings.clocks[1] - readings.clocks[0]);
+    assert!(measured >= Duration::from_millis(45), "{readings:?}");
+    assert!(measured <= elapsed.mul_f64(1.05), "{readings:?}");
+}
+
+#[test]
+fn test_perf_counter_needs_the_capability() {
+    let readings = run_perf_counter_program(false);
+    assert_eq!(readings.errnos, [Errno::Perm as u32; 3]);
+    assert_eq!(readings.counters, [0, 0]);
+    assert_eq!(readings.frequency, 0);
+}
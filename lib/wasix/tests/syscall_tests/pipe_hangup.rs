@@ -0,0 +1,90 @@
+use wasmer_wasix_types::wasi::{EpollType, Eventrwflags};
+
+use super::run_wat;
+
+#[test]
+fn test_pipe_hangs_up_after_the_writer_closes() {
+    let stdout = run_wat(br#"
+    (module
+        (import "wasi_snapshot_preview1" "fd_read" (func $fd_read (param i32 i32 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "fd_close" (func $fd_close (param i32) (result i32)))
+        (import "wasi_snapshot_preview1" "poll_oneoff" (func $poll_oneoff (param i32 i32 i32 i32) (result i32)))
+        (import "wasix_32v1" "fd_pipe" (func $fd_pipe (param i32 i32) (result i32)))
+        (import "wasix_32v1" "epoll_create" (func $epoll_create (param i32) (result i32)))
+        (import "wasix_32v1" "epoll_ctl" (func $epoll_ctl (param i32 i32 i32 i32) (result i32)))
+        (import "wasix_32v1" "epoll_wait" (func $epoll_wait (param i32 i32 i32 i64 i32) (result i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        (data (i32.const 100) "abc")
+
+        (func $check (param i32)
+            (if (local.get 0) (then unreachable))
+        )
+
+        ;; Polls the read end of the pipe and copies the number of bytes and
+        ;; the flags of the event to `out`
+        (func $poll (param $out i32)
+            (i64.store (i32.const 400) (i64.const 0))
+            (i32.store8 (i32.const 408) (i32.const 1))
+            (i32.store (i32.const 416) (i32.load (i32.const 200)))
+            (call $check (call $poll_oneoff (i32.const 400) (i32.const 800) (i32.const 1) (i32.const 600)))
+            (i32.store (local.get $out) (i32.load (i32.const 816)))
+            (i32.store (i32.add (local.get $out) (i32.const 4)) (i32.load16_u (i32.const 824)))
+        )
+
+        (func $main (export "_start")
+            (call $check (call $fd_pipe (i32.const 200) (i32.const 204)))
+
+            ;; Write to the pipe and close the write end
+            (i32.store (i32.const 0) (i32.const 100))
+            (i32.store (i32.const 4) (i32.const 3))
+            (call $check (call $fd_write (i32.load (i32.const 204)) (i32.const 0) (i32.const 1) (i32.const 8)))
+            (call $check (call $fd_close (i32.load (i32.const 204))))
+
+            ;; The buffered bytes are still readable
+            (call $poll (i32.const 300))
+
+            ;; Drain them, afterwards the pipe hangs up
+            (i32.store (i32.const 16) (i32.const 700))
+            (i32.store (i32.const 20) (i32.const 16))
+            (call $check (call $fd_read (i32.load (i32.const 200)) (i32.const 16) (i32.const 1) (i32.const 308)))
+            (call $poll (i32.const 312))
+
+            ;; A reader that waits on a second pipe with epoll is woken up
+            ;; when its write end is closed
+            (call $check (call $fd_pipe (i32.const 208) (i32.const 212)))
+            (call $check (call $epoll_create (i32.const 216)))
+            (i32.store (i32.const 1000) (i32.const 1))
+            (call $check (call $epoll_ctl (i32.load (i32.const 216)) (i32.const 0) (i32.load (i32.const 208)) (i32.const 1000)))
+            (call $check (call $fd_close (i32.load (i32.const 212))))
+            (call $check (call $epoll_wait (i32.load (i32.const 216)) (i32.const 1104) (i32.const 1) (i64.const 1000000000) (i32.const 320)))
+            (i32.store (i32.const 324) (i32.load (i32.const 1104)))
+
+            ;; Send the results to stdout
+            (i32.store (i32.const 0) (i32.const 300))
+            (i32.store (i32.const 4) (i32.const 28))
+            (call $check (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 8)))
+        )
+    )
+    "#);
+    let value = |offset: usize| u32::from_le_bytes(stdout[offset..offset + 4].try_into().unwrap());
+
+    // Readable without a hangup while there is data left
+    assert_eq!(value(0), 3);
+    assert_eq!(value(4), 0);
+
+    // All of it is read, then the pipe hangs up
+    assert_eq!(value(8), 3);
+    assert_eq!(value(12), 0);
+    assert_eq!(value(16), Eventrwflags::FD_READWRITE_HANGUP.bits() as u32);
+
+    // The epoll reader got a hangup
+    assert_eq!(value(20), 1);
+    assert_ne!(
+        EpollType::from_bits_truncate(value(24)) & EpollType::EPOLLHUP,
+        EpollType::empty()
+    );
+}
@@ -0,0 +1,107 @@
+use std::time::Duration;
+
+use virtual_fs::{AsyncReadExt, AsyncWriteExt};
+use virtual_mio::block_on;
+use wasmer_wasix::{Pipe, WasiEnv};
+use wasmer_wasix_types::wasi::{Errno, Signal};
+
+use super::TestRuntime;
+
+/// Polls stdin with `SIGUSR1` blocked for the duration of the poll, after
+/// writing a `R` to stdout. The result of the poll and the number of events
+/// go to stdout afterwards.
+const PROGRAM: &[u8] = br#"
+(module
+    (import "wasix_32v1" "poll_oneoff_deadline" (func $poll_oneoff_deadline (param i32 i32 i32 i32 i32 i32) (result i32)))
+    (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+
+    ;; Only a thread with a shared memory can be resumed after a deep sleep
+    (import "env" "memory" (memory 1 16 shared))
+
+    (global $__stack_low (export "__stack_low") i32 (i32.const 16384))
+    (global $__stack_pointer (export "__stack_pointer") (mut i32) (i32.const 32768))
+
+    (data (i32.const 620) "R")
+
+    ;; `_start` is the only function that is ever unwound and it keeps
+    ;; nothing on the stack, so asyncify just needs to track its state
+    (global $asyncify_state (mut i32) (i32.const 0))
+    (func (export "asyncify_start_unwind") (param i32)
+        (global.set $asyncify_state (i32.const 1))
+    )
+    (func (export "asyncify_stop_unwind")
+        (global.set $asyncify_state (i32.const 0))
+    )
+    (func (export "asyncify_start_rewind") (param i32)
+        (global.set $asyncify_state (i32.const 2))
+    )
+    (func (export "asyncify_stop_rewind")
+        (global.set $asyncify_state (i32.const 0))
+    )
+    (func (export "asyncify_get_state") (result i32)
+        (global.get $asyncify_state)
+    )
+
+    (func $main (export "_start")
+        ;; The state is only set up the first time around, not when `_start`
+        ;; is rewound
+        (if (i32.eqz (global.get $asyncify_state))
+            (then
+                ;; Waits for stdin to be readable, without a deadline
+                (i32.store8 (i32.const 108) (i32.const 1))
+                (i32.store (i32.const 116) (i32.const 0))
+                (i32.store8 (i32.const 408) (i32.const 0))
+                (i64.store (i32.const 400) (i64.shl (i64.const 1) (i64.const 10)))
+
+                (i32.store (i32.const 0) (i32.const 620))
+                (i32.store (i32.const 4) (i32.const 1))
+                (drop (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 8)))
+            )
+        )
+        (i32.store (i32.const 500)
+            (call $poll_oneoff_deadline (i32.const 100) (i32.const 200) (i32.const 1) (i32.const 408) (i32.const 400) (i32.const 504)))
+        (if (i32.eq (global.get $asyncify_state) (i32.const 1)) (then return))
+
+        (i32.store (i32.const 0) (i32.const 500))
+        (i32.store (i32.const 4) (i32.const 8))
+        (drop (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 8)))
+    )
+)
+"#;
+
+#[test]
+fn test_signal_mask_stays_applied_during_a_deep_sleep() {
+    let runtime = TestRuntime::new();
+
+    let (mut stdin_tx, stdin_rx) = Pipe::channel();
+    let mut builder = WasiEnv::builder("main").stdin(Box::new(stdin_rx));
+    builder.capabilities_mut().threading.enable_deep_sleep = true;
+    let (env, mut stdout_rx) = runtime.build_env(builder);
+    let process = env.process.clone();
+    let thread = env.thread.clone();
+    let mut task = runtime.start(runtime.module(PROGRAM), env).unwrap();
+
+    let mut ready = [0u8; 1];
+    block_on(stdout_rx.read_exact(&mut ready)).unwrap();
+    assert_eq!(&ready, b"R");
+
+    // The signal arrives once the poll is in a deep sleep, where it is
+    // still blocked and doesn't interrupt the poll
+    std::thread::sleep(Duration::from_millis(200));
+    assert!(thread.is_signal_blocked(Signal::Sigusr1));
+    process.signal_process(Signal::Sigusr1);
+    std::thread::sleep(Duration::from_millis(100));
+    block_on(stdin_tx.write_all(b"hi")).unwrap();
+
+    let exit_code = block_on(task.wait_finished()).unwrap();
+    assert!(exit_code.is_success());
+    assert!(!thread.is_signal_blocked(Signal::Sigusr1));
+
+    let mut stdout = Vec::new();
+    block_on(stdout_rx.read_to_end(&mut stdout)).unwrap();
+    let results = stdout
+        .chunks(4)
+        .map(|value| u32::from_le_bytes(value.try_into().unwrap()))
+        .collect::<Vec<_>>();
+    assert_eq!(results, vec![Errno::Success as u32, 1]);
+}
@@ -0,0 +1,84 @@
+use std::sync::Arc;
+
+use virtual_fs::{AsyncReadExt, AsyncWriteExt, FileSystem};
+use virtual_mio::block_on;
+use wasmer_wasix::{Pipe, WasiEnv};
+
+use super::TestRuntime;
+
+/// Writes `hello` to `/out.txt` and waits on stdin, then flushes the process
+/// and waits on stdin again. A `.` is written to stdout every time it waits.
+const PROGRAM: &str = r#"
+(module
+    (import "wasi_snapshot_preview1" "fd_read" (func $fd_read (param i32 i32 i32 i32) (result i32)))
+    (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+    (import "wasi_snapshot_preview1" "path_open" (func $path_open (param i32 i32 i32 i32 i32 i64 i64 i32 i32) (result i32)))
+    (import "wasix_32v1" "proc_flush" (func $proc_flush (result i32)))
+
+    (memory 1)
+    (export "memory" (memory 0))
+
+    (data (i32.const 100) "/out.txt")
+    (data (i32.const 120) "hello")
+    (data (i32.const 140) ".")
+
+    (func $check (param i32)
+        (if (local.get 0) (then unreachable))
+    )
+
+    (func $write (param $fd i32) (param $ptr i32) (param $len i32)
+        (i32.store (i32.const 0) (local.get $ptr))
+        (i32.store (i32.const 4) (local.get $len))
+        (call $check (call $fd_write (local.get $fd) (i32.const 0) (i32.const 1) (i32.const 8)))
+    )
+
+    (func $wait
+        (call $write (i32.const 1) (i32.const 140) (i32.const 1))
+        (i32.store (i32.const 16) (i32.const 400))
+        (i32.store (i32.const 20) (i32.const 1))
+        (call $check (call $fd_read (i32.const 0) (i32.const 16) (i32.const 1) (i32.const 24)))
+    )
+
+    (func $main (export "_start")
+        (call $check (call $path_open (i32.const 3) (i32.const 0) (i32.const 100) (i32.const 8)
+            (i32.const 1) (i64.const -1) (i64.const -1) (i32.const 0) (i32.const 200)))
+        (call $write (i32.load (i32.const 200)) (i32.const 120) (i32.const 5))
+        (call $wait)
+
+        (call $check (call $proc_flush))
+        (call $wait)
+    )
+)
+"#;
+
+#[test]
+fn test_proc_flush_writes_out_the_buffered_files() {
+    let runtime = TestRuntime::new();
+
+    // The files that the guest writes to are buffered
+    let temp = tempfile::TempDir::new().unwrap();
+    let fs = virtual_fs::host_fs::FileSystem::new(runtime.handle().clone(), temp.path()).unwrap();
+
+    let (mut stdin_tx, stdin_rx) = Pipe::channel();
+    let builder = WasiEnv::builder("main")
+        .fs(Arc::new(fs) as Arc<dyn FileSystem + Send + Sync>)
+        .write_buffer_size(1024)
+        .stdin(Box::new(stdin_rx));
+    let (env, mut stdout_rx) = runtime.build_env(builder);
+    let mut task = runtime.start(runtime.module(PROGRAM), env).unwrap();
+    let wait = |stdout_rx: &mut Pipe| {
+        let mut buf = [0u8; 1];
+        block_on(stdout_rx.read_exact(&mut buf)).unwrap();
+        assert_eq!(&buf, b".");
+        std::fs::read(temp.path().join("out.txt")).unwrap()
+    };
+
+    // The write is still in the buffer until the process is flushed
+    assert_eq!(wait(&mut stdout_rx), b"");
+    block_on(stdin_tx.write_all(b"x")).unwrap();
+    assert_eq!(wait(&mut stdout_rx), b"hello");
+    block_on(stdin_tx.write_all(b"x")).unwrap();
+
+    let exit_code = block_on(task.wait_finished()).unwrap();
+    assert!(exit_code.is_success());
+}
@@ -0,0 +1,50 @@
+use wasmer_wasix_types::wasi::Errno;
+
+use super::run_wat;
+
+#[test]
+fn test_proc_title_defaults_to_the_spawn_name() {
+    let stdout = run_wat(br#"
+    (module
+        (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+        (import "wasix_32v1" "proc_get_title" (func $proc_get_title (param i32 i32 i32) (result i32)))
+        (import "wasix_32v1" "proc_set_title" (func $proc_set_title (param i32 i32) (result i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        (data (i32.const 100) "worker: idle")
+
+        (func $check (param i32)
+            (if (local.get 0) (then unreachable))
+        )
+
+        (func $main (export "_start")
+            ;; A buffer that is too small only gets the length of the title
+            (i32.store (i32.const 300) (call $proc_get_title (i32.const 400) (i32.const 4) (i32.const 304)))
+
+            ;; The title starts out as the name that the program was spawned with
+            (call $check (call $proc_get_title (i32.const 400) (i32.const 32) (i32.const 308)))
+
+            ;; Change it and read it back
+            (call $check (call $proc_set_title (i32.const 100) (i32.const 12)))
+            (call $check (call $proc_get_title (i32.const 432) (i32.const 32) (i32.const 312)))
+
+            ;; Send the results to stdout
+            (i32.store (i32.const 0) (i32.const 300))
+            (i32.store (i32.const 4) (i32.const 164))
+            (call $check (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 8)))
+        )
+    )
+    "#);
+    let value = |offset: usize| u32::from_le_bytes(stdout[offset..offset + 4].try_into().unwrap());
+
+    assert_eq!(value(0), Errno::Range as u32);
+    assert_eq!(value(4), 12);
+
+    assert_eq!(value(8), 12);
+    assert_eq!(&stdout[100..112], b"command-name");
+
+    assert_eq!(value(12), 12);
+    assert_eq!(&stdout[132..144], b"worker: idle");
+}
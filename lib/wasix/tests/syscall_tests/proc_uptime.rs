@@ -0,0 +1,40 @@
+use std::time::Duration;
+
+use super::run_wat;
+
+#[test]
+fn test_proc_uptime_increases() {
+    let stdout = run_wat(br#"
+    (module
+        (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+        (import "wasix_32v1" "proc_uptime" (func $proc_uptime (param i32) (result i32)))
+        (import "wasix_32v1" "thread_sleep" (func $thread_sleep (param i64) (result i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        (func $check (param i32)
+            (if (local.get 0) (then unreachable))
+        )
+
+        (func $main (export "_start")
+            ;; Reads the uptime twice, 10ms apart
+            (call $check (call $proc_uptime (i32.const 1024)))
+            (call $check (call $thread_sleep (i64.const 10000000)))
+            (call $check (call $proc_uptime (i32.const 1032)))
+
+            ;; Send both readings to stdout
+            (i32.store (i32.const 0) (i32.const 1024))
+            (i32.store (i32.const 4) (i32.const 16))
+            (call $check (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 8)))
+        )
+    )
+    "#);
+    let reading = |at: usize| u64::from_le_bytes(stdout[at..at + 8].try_into().unwrap());
+    let (first, second) = (reading(0), reading(8));
+    assert!(first > 0);
+    assert!(
+        Duration::from_nanos(second - first) >= Duration::from_millis(10),
+        "{first} {second}"
+    );
+}
@@ -0,0 +1,141 @@
+use std::collections::BTreeSet;
+
+use wasmer_wasix_types::wasi::{Errno, JoinStatusType};
+
+use super::run_wat;
+
+/// Checks that waiting without blocking fails while there are no children,
+/// then forks three children that exit with 10, 11 and 12 and reaps them
+/// with `proc_wait_any` until it fails again. It sends the number of
+/// children, the number of waits, the pids of the children and the results
+/// of every wait (24 bytes each, the results of the waits for the children
+/// followed by the one that failed and the one before the forks) to stdout
+const PROGRAM: &str = r#"
+(module
+    (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+    (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+    (import "wasix_32v1" "proc_fork" (func $proc_fork (param i32 i32) (result i32)))
+    (import "wasix_32v1" "proc_wait_any" (func $proc_wait_any (param i32 i32 i32) (result i32)))
+
+    ;; Only a process with a shared memory can be forked
+    (import "env" "memory" (memory 1 16 shared))
+
+    (global $__stack_low (export "__stack_low") i32 (i32.const 16384))
+    (global $__stack_pointer (export "__stack_pointer") (mut i32) (i32.const 32768))
+
+    ;; `_start` is the only function that is ever unwound and it keeps
+    ;; nothing on the stack (the loops count in memory), so asyncify just
+    ;; needs to track its state
+    (global $asyncify_state (mut i32) (i32.const 0))
+    (func (export "asyncify_start_unwind") (param i32)
+        (global.set $asyncify_state (i32.const 1))
+    )
+    (func (export "asyncify_stop_unwind")
+        (global.set $asyncify_state (i32.const 0))
+    )
+    (func (export "asyncify_start_rewind") (param i32)
+        (global.set $asyncify_state (i32.const 2))
+    )
+    (func (export "asyncify_stop_rewind")
+        (global.set $asyncify_state (i32.const 0))
+    )
+    (func (export "asyncify_get_state") (result i32)
+        (global.get $asyncify_state)
+    )
+
+    (func $check (param i32)
+        (if (local.get 0) (then unreachable))
+    )
+
+    (func $main (export "_start")
+        (local $result i32)
+
+        ;; There is nothing to wait on before the first fork
+        (if (i32.eqz (global.get $asyncify_state))
+            (then
+                (i32.store (i32.const 352)
+                    (call $proc_wait_any (i32.const 1) (i32.const 356) (i32.const 364)))
+            )
+        )
+
+        (block $forked
+            (loop $fork
+                (br_if $forked (i32.ge_u (i32.load (i32.const 200)) (i32.const 3)))
+                (call $check (call $proc_fork (i32.const 1) (i32.const 212)))
+                (if (i32.eq (global.get $asyncify_state) (i32.const 1)) (then return))
+
+                ;; The children exit right away
+                (if (i32.eqz (i32.load (i32.const 212)))
+                    (then
+                        (call $proc_exit (i32.add (i32.load (i32.const 200)) (i32.const 10)))
+                    )
+                )
+
+                (i32.store
+                    (i32.add (i32.const 216) (i32.shl (i32.load (i32.const 200)) (i32.const 2)))
+                    (i32.load (i32.const 212)))
+                (i32.store (i32.const 200) (i32.add (i32.load (i32.const 200)) (i32.const 1)))
+                (br $fork)
+            )
+        )
+
+        (block $reaped
+            (loop $reap
+                (local.set $result
+                    (i32.add (i32.const 256) (i32.mul (i32.load (i32.const 204)) (i32.const 24))))
+                (i32.store (local.get $result)
+                    (call $proc_wait_any (i32.const 0)
+                        (i32.add (local.get $result) (i32.const 4))
+                        (i32.add (local.get $result) (i32.const 12))))
+                (if (i32.eq (global.get $asyncify_state) (i32.const 1)) (then return))
+
+                (i32.store (i32.const 204) (i32.add (i32.load (i32.const 204)) (i32.const 1)))
+                (br_if $reaped (i32.load (local.get $result)))
+                (br_if $reaped (i32.ge_u (i32.load (i32.const 204)) (i32.const 4)))
+                (br $reap)
+            )
+        )
+
+        (i32.store (i32.const 0) (i32.const 200))
+        (i32.store (i32.const 4) (i32.const 176))
+        (call $check (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 8)))
+    )
+)
+"#;
+
+#[test]
+fn test_proc_wait_any_reaps_all_the_children() {
+    let stdout = run_wat(PROGRAM);
+    assert_eq!(stdout.len(), 176);
+    let word = |at: usize| u32::from_le_bytes(stdout[at..at + 4].try_into().unwrap());
+    let half = |at: usize| u16::from_le_bytes(stdout[at..at + 2].try_into().unwrap());
+
+    // Offsets of the fields of a wait result
+    let errno = |wait: usize| word(56 + wait * 24);
+    let pid = |wait: usize| (stdout[60 + wait * 24], word(64 + wait * 24));
+    let status = |wait: usize| (stdout[68 + wait * 24], half(70 + wait * 24));
+
+    // Every child was reaped once, then there were no children left
+    assert_eq!(word(0), 3);
+    assert_eq!(word(4), 4);
+    let children: BTreeSet<u32> = (0..3).map(|n| word(16 + n * 4)).collect();
+    assert_eq!(children.len(), 3);
+
+    let mut reaped = BTreeSet::new();
+    let mut exit_codes = BTreeSet::new();
+    for wait in 0..3 {
+        assert_eq!(errno(wait), Errno::Success as u32);
+        let (tag, pid) = pid(wait);
+        assert_eq!(tag, 1);
+        reaped.insert(pid);
+        let (tag, exit_code) = status(wait);
+        assert_eq!(tag, JoinStatusType::ExitNormal as u8);
+        exit_codes.insert(exit_code);
+    }
+    assert_eq!(reaped, children);
+    assert_eq!(exit_codes, BTreeSet::from([10, 11, 12]));
+
+    assert_eq!(errno(3), Errno::Child as u32);
+    assert_eq!(pid(3).0, 0);
+    assert_eq!(errno(4), Errno::Child as u32);
+}
@@ -0,0 +1,182 @@
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+    thread::JoinHandle,
+    time::{Duration, Instant},
+};
+
+use virtual_fs::{AsyncReadExt, AsyncWriteExt};
+use virtual_mio::block_on;
+use wasmer_wasix::{Pipe, WasiEnv, WasiProcess, WasiThreadId, os::task::TaskJoinHandle};
+use wasmer_wasix_types::wasi::{ExitCode, Signal};
+
+use super::TestRuntime;
+
+/// Writes a dot to stdout over and over again
+const SPINNER: &[u8] = br#"
+(module
+    (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+
+    (memory 1)
+    (export "memory" (memory 0))
+
+    (data (i32.const 100) ".")
+
+    (func $main (export "_start")
+        (i32.store (i32.const 0) (i32.const 100))
+        (i32.store (i32.const 4) (i32.const 1))
+        (loop $spin
+            (drop (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 8)))
+            (br $spin)
+        )
+    )
+)
+"#;
+
+/// Waits until the process has written more than `than` dots
+fn wait_for_more(written: &AtomicUsize, than: usize) {
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while written.load(Ordering::SeqCst) <= than {
+        assert!(Instant::now() < deadline, "the process made no progress");
+        std::thread::sleep(Duration::from_millis(10));
+    }
+}
+
+/// Starts the spinner and pauses it once it made some progress
+fn start_paused_spinner(
+    runtime: &TestRuntime,
+) -> (
+    WasiProcess,
+    WasiThreadId,
+    TaskJoinHandle,
+    Arc<AtomicUsize>,
+    JoinHandle<()>,
+) {
+    let (env, mut stdout_rx) = runtime.build_env(WasiEnv::builder("spinner"));
+    let process = env.process.clone();
+    let tid = env.thread.tid();
+
+    let written = Arc::new(AtomicUsize::new(0));
+    let reader = std::thread::spawn({
+        let written = written.clone();
+        move || {
+            let mut buf = [0u8; 4096];
+            while let Ok(amt) = block_on(stdout_rx.read(&mut buf))
+                && amt > 0
+            {
+                written.fetch_add(amt, Ordering::SeqCst);
+            }
+        }
+    });
+
+    let task = runtime.start(runtime.module(SPINNER), env).unwrap();
+
+    wait_for_more(&written, 0);
+    process.pause();
+    assert!(process.is_paused());
+    (process, tid, task, written, reader)
+}
+
+#[test]
+fn test_paused_process_makes_no_progress_until_resumed() {
+    let runtime = TestRuntime::new();
+    let (process, _, mut task, written, reader) = start_paused_spinner(&runtime);
+
+    // Give a write that was already under way the time to finish
+    std::thread::sleep(Duration::from_millis(100));
+    let paused_at = written.load(Ordering::SeqCst);
+    std::thread::sleep(Duration::from_millis(300));
+    assert_eq!(written.load(Ordering::SeqCst), paused_at);
+
+    process.resume();
+    assert!(!process.is_paused());
+    wait_for_more(&written, paused_at);
+
+    // A paused process can still be killed
+    process.pause();
+    process.signal_process(Signal::Sigkill);
+    let exit_code = block_on(task.wait_finished()).unwrap();
+    assert!(!exit_code.is_success());
+
+    reader.join().unwrap();
+}
+
+#[test]
+fn test_paused_thread_can_be_killed() {
+    let runtime = TestRuntime::new();
+    let (process, tid, mut task, _, reader) = start_paused_spinner(&runtime);
+    std::thread::sleep(Duration::from_millis(100));
+
+    process.signal_thread(&tid, Signal::Sigkill);
+    let exit_code = block_on(task.wait_finished()).unwrap();
+    assert!(!exit_code.is_success());
+
+    reader.join().unwrap();
+}
+
+#[test]
+fn test_paused_process_can_be_terminated() {
+    let runtime = TestRuntime::new();
+    let (process, _, mut task, _, reader) = start_paused_spinner(&runtime);
+    std::thread::sleep(Duration::from_millis(100));
+
+    process.terminate(ExitCode::from(3u16));
+    let exit_code = block_on(task.wait_finished()).unwrap();
+    assert_eq!(exit_code, ExitCode::from(3u16));
+
+    reader.join().unwrap();
+}
+
+/// Writes a dot to stdout, waits for a byte on stdin and exits with 42
+const EXIT_AFTER_INPUT: &[u8] = br#"
+(module
+    (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+    (import "wasi_snapshot_preview1" "fd_read" (func $fd_read (param i32 i32 i32 i32) (result i32)))
+    (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+
+    (memory 1)
+    (export "memory" (memory 0))
+
+    (data (i32.const 100) ".")
+
+    (func $main (export "_start")
+        (i32.store (i32.const 0) (i32.const 100))
+        (i32.store (i32.const 4) (i32.const 1))
+        (drop (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 8)))
+
+        (i32.store (i32.const 0) (i32.const 200))
+        (drop (call $fd_read (i32.const 0) (i32.const 0) (i32.const 1) (i32.const 8)))
+
+        (call $proc_exit (i32.const 42))
+    )
+)
+"#;
+
+#[test]
+fn test_paused_process_exits_with_its_code_once_resumed() {
+    let runtime = TestRuntime::new();
+    let (mut stdin_tx, stdin_rx) = Pipe::channel();
+    let (env, mut stdout_rx) =
+        runtime.build_env(WasiEnv::builder("exit").stdin(Box::new(stdin_rx)));
+    let process = env.process.clone();
+    let mut task = runtime
+        .start(runtime.module(EXIT_AFTER_INPUT), env)
+        .unwrap();
+
+    // The program is blocked on stdin when it is paused, the byte lets it
+    // go on to `proc_exit` where it stops
+    let mut buf = [0u8; 1];
+    block_on(stdout_rx.read_exact(&mut buf)).unwrap();
+    std::thread::sleep(Duration::from_millis(100));
+    process.pause();
+    block_on(stdin_tx.write_all(b"x")).unwrap();
+
+    std::thread::sleep(Duration::from_millis(300));
+    assert!(!task.status().is_finished());
+
+    process.resume();
+    let exit_code = block_on(task.wait_finished()).unwrap();
+    assert_eq!(exit_code, ExitCode::from(42u16));
+}
@@ -0,0 +1,85 @@
+use virtual_fs::AsyncReadExt;
+use virtual_mio::block_on;
+use wasmer_wasix::{Runtime, WasiEnv};
+
+use super::TestRuntime;
+
+/// `_initialize` counts how often it ran and sets a global, `_start` counts
+/// how often it ran in the same memory and writes both counters and the
+/// global to stdout
+fn program(memory: &str) -> String {
+    format!(
+        r#"
+(module
+    (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+    {memory}
+
+    (global $answer (mut i32) (i32.const 0))
+
+    (func (export "_initialize")
+        (i32.store (i32.const 1024) (i32.add (i32.load (i32.const 1024)) (i32.const 1)))
+        (global.set $answer (i32.const 42))
+    )
+
+    (func (export "_start")
+        (i32.store (i32.const 1028) (i32.add (i32.load (i32.const 1028)) (i32.const 1)))
+        (i32.store (i32.const 1032) (global.get $answer))
+
+        (i32.store (i32.const 0) (i32.const 1024))
+        (i32.store (i32.const 4) (i32.const 12))
+        (drop (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 8)))
+    )
+)
+"#
+    )
+}
+
+/// Clones the template twice and returns what the clones wrote to stdout
+fn run_clones(memory: &str) -> Vec<u8> {
+    let runtime = TestRuntime::new();
+    let _guard = runtime.enter();
+
+    let module = runtime.module(program(memory));
+    let (env, mut stdout_rx) = runtime.build_env(WasiEnv::builder("template"));
+    let control_plane = env.control_plane.clone();
+
+    let template = runtime.rt.capture_process_template(module, env).unwrap();
+    for _ in 0..2 {
+        let mut clone = runtime
+            .rt
+            .spawn_from_template(&template, None, None)
+            .unwrap();
+        let exit_code = block_on(clone.wait_finished()).unwrap();
+        assert!(exit_code.is_success());
+    }
+    drop(template);
+
+    // The clones freed their pids when they exited, the template when it
+    // was dropped
+    assert!(control_plane.processes().is_empty());
+
+    let mut stdout = Vec::new();
+    block_on(stdout_rx.read_to_end(&mut stdout)).unwrap();
+    stdout
+}
+
+/// Every clone starts from the initialized template (which initialized
+/// exactly once) with a memory of its own
+fn expected() -> Vec<u8> {
+    [1u32, 1, 42, 1, 1, 42]
+        .iter()
+        .flat_map(|value| value.to_le_bytes())
+        .collect()
+}
+
+#[test]
+fn test_clones_of_a_template_with_an_exported_memory() {
+    let stdout = run_clones(r#"(memory (export "memory") 1)"#);
+    assert_eq!(stdout, expected());
+}
+
+#[test]
+fn test_clones_of_a_template_with_an_imported_memory() {
+    let stdout = run_clones(r#"(import "env" "memory" (memory 1 1 shared))"#);
+    assert_eq!(stdout, expected());
+}
@@ -0,0 +1,91 @@
+use super::run_wat;
+
+#[test]
+fn test_nofile_limit() {
+    let stdout = run_wat(br#"
+    (module
+        (import "wasi_snapshot_preview1" "path_open" (func $path_open (param i32 i32 i32 i32 i32 i64 i64 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+        (import "wasix_32v1" "fd_dup" (func $fd_dup (param i32 i32) (result i32)))
+        (import "wasix_32v1" "proc_getrlimit" (func $proc_getrlimit (param i32 i32) (result i32)))
+        (import "wasix_32v1" "proc_setrlimit" (func $proc_setrlimit (param i32 i32) (result i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        (data (i32.const 100) "tmp/a.txt")
+
+        (func $check (param i32)
+            (if (local.get 0) (then unreachable))
+        )
+
+        (func $open (result i32)
+            (call $path_open (i32.const 3) (i32.const 0) (i32.const 100) (i32.const 9)
+                (i32.const 1) (i64.const -1) (i64.const -1) (i32.const 0) (i32.const 200))
+        )
+
+        ;; Sets the limits of RLIMIT_NOFILE and returns the error
+        (func $set_nofile (param i64 i64) (result i32)
+            (i64.store (i32.const 300) (local.get 0))
+            (i64.store (i32.const 308) (local.get 1))
+            (call $proc_setrlimit (i32.const 7) (i32.const 300))
+        )
+
+        (func $main (export "_start")
+            (local $fd i32)
+
+            ;; There is no limit to begin with
+            (call $check (call $proc_getrlimit (i32.const 7) (i32.const 1024)))
+
+            ;; Only two more descriptors can be opened after this one
+            (call $check (call $open))
+            (local.set $fd (i32.load (i32.const 200)))
+            (call $check (call $set_nofile
+                (i64.extend_i32_u (i32.add (local.get $fd) (i32.const 3)))
+                (i64.extend_i32_u (i32.add (local.get $fd) (i32.const 4)))))
+
+            ;; The soft limit can not go above the hard limit, which can not be raised
+            (i32.store (i32.const 1040) (call $set_nofile (i64.const 100) (i64.const 50)))
+            (i32.store (i32.const 1044) (call $set_nofile (i64.const 0) (i64.const -1)))
+
+            ;; Open and duplicate until the limit is hit
+            (call $check (call $open))
+            (i32.store (i32.const 1048) (i32.sub (i32.load (i32.const 200)) (local.get $fd)))
+            (call $check (call $fd_dup (local.get $fd) (i32.const 200)))
+            (i32.store (i32.const 1052) (i32.sub (i32.load (i32.const 200)) (local.get $fd)))
+            (i32.store (i32.const 1056) (call $open))
+            (i32.store (i32.const 1060) (call $fd_dup (local.get $fd) (i32.const 200)))
+
+            ;; Raising the soft limit up to the hard limit makes room for one more
+            (call $check (call $set_nofile
+                (i64.extend_i32_u (i32.add (local.get $fd) (i32.const 4)))
+                (i64.extend_i32_u (i32.add (local.get $fd) (i32.const 4)))))
+            (i32.store (i32.const 1064) (call $open))
+            (i32.store (i32.const 1068) (i32.sub (i32.load (i32.const 200)) (local.get $fd)))
+
+            ;; Send the results to stdout
+            (i32.store (i32.const 0) (i32.const 1024))
+            (i32.store (i32.const 4) (i32.const 48))
+            (call $check (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 8)))
+        )
+    )
+    "#);
+    assert_eq!(stdout.len(), 48);
+
+    let u64_at = |offset: usize| u64::from_le_bytes(stdout[offset..offset + 8].try_into().unwrap());
+    let u32_at = |offset: usize| u32::from_le_bytes(stdout[offset..offset + 4].try_into().unwrap());
+    const INVAL: u32 = 28;
+    const MFILE: u32 = 33;
+    const PERM: u32 = 63;
+
+    assert_eq!(u64_at(0), u64::MAX, "soft limit starts out unlimited");
+    assert_eq!(u64_at(8), u64::MAX, "hard limit starts out unlimited");
+    assert_eq!(u32_at(16), INVAL);
+    assert_eq!(u32_at(20), PERM);
+    assert_eq!(u32_at(24), 1);
+    assert_eq!(u32_at(28), 2);
+    assert_eq!(u32_at(32), MFILE);
+    assert_eq!(u32_at(36), MFILE);
+    assert_eq!(u32_at(40), 0);
+    assert_eq!(u32_at(44), 3);
+}
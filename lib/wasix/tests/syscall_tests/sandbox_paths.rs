@@ -0,0 +1,87 @@
+use std::{path::Path, sync::Arc};
+
+use virtual_fs::FileSystem;
+use wasmer_wasix::{WasiEnv, capabilities::SandboxPolicy};
+
+use super::TestRuntime;
+
+/// Touches `secret/file` with every kind of path syscall and `data/file`
+/// with one of them, and writes the errnos to stdout
+const PROGRAM: &str = r#"
+(module
+    (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+    (import "wasi_snapshot_preview1" "path_filestat_get" (func $path_filestat_get (param i32 i32 i32 i32 i32) (result i32)))
+    (import "wasi_snapshot_preview1" "path_create_directory" (func $path_create_directory (param i32 i32 i32) (result i32)))
+    (import "wasi_snapshot_preview1" "path_unlink_file" (func $path_unlink_file (param i32 i32 i32) (result i32)))
+    (import "wasi_snapshot_preview1" "path_rename" (func $path_rename (param i32 i32 i32 i32 i32 i32) (result i32)))
+    (import "wasi_snapshot_preview1" "path_symlink" (func $path_symlink (param i32 i32 i32 i32 i32) (result i32)))
+    (import "wasi_snapshot_preview1" "path_readlink" (func $path_readlink (param i32 i32 i32 i32 i32 i32) (result i32)))
+    (import "wasix_32v1" "chdir" (func $chdir (param i32 i32) (result i32)))
+
+    (memory 1)
+    (export "memory" (memory 0))
+
+    (data (i32.const 100) "secret/file")
+    (data (i32.const 120) "secret/dir")
+    (data (i32.const 140) "data/moved")
+    (data (i32.const 160) "secret/link")
+    (data (i32.const 180) "/secret")
+    (data (i32.const 200) "data/file")
+    (data (i32.const 220) "secret/dead")
+
+    (func $check (param i32)
+        (if (local.get 0) (then unreachable))
+    )
+
+    (func $main (export "_start")
+        (i32.store8 (i32.const 0) (call $path_filestat_get (i32.const 3) (i32.const 0) (i32.const 100) (i32.const 11) (i32.const 400)))
+        (i32.store8 (i32.const 1) (call $path_create_directory (i32.const 3) (i32.const 120) (i32.const 10)))
+        (i32.store8 (i32.const 2) (call $path_unlink_file (i32.const 3) (i32.const 100) (i32.const 11)))
+        (i32.store8 (i32.const 3) (call $path_rename (i32.const 3) (i32.const 100) (i32.const 11) (i32.const 3) (i32.const 140) (i32.const 10)))
+        (i32.store8 (i32.const 4) (call $path_symlink (i32.const 200) (i32.const 9) (i32.const 3) (i32.const 160) (i32.const 11)))
+        (i32.store8 (i32.const 5) (call $path_readlink (i32.const 3) (i32.const 220) (i32.const 11) (i32.const 400) (i32.const 64) (i32.const 480)))
+        (i32.store8 (i32.const 6) (call $chdir (i32.const 180) (i32.const 7)))
+        (i32.store8 (i32.const 7) (call $path_filestat_get (i32.const 3) (i32.const 0) (i32.const 200) (i32.const 9) (i32.const 400)))
+
+        (i32.store (i32.const 300) (i32.const 0))
+        (i32.store (i32.const 304) (i32.const 8))
+        (call $check (call $fd_write (i32.const 1) (i32.const 300) (i32.const 1) (i32.const 308)))
+    )
+)
+"#;
+
+/// `Errno::Notcapable`
+const ENOTCAPABLE: u8 = 76;
+
+#[test]
+fn test_allowed_paths_cover_every_path_syscall() {
+    let runtime = TestRuntime::new();
+
+    let fs = virtual_fs::mem_fs::FileSystem::default();
+    fs.create_dir(Path::new("/data")).unwrap();
+    fs.create_dir(Path::new("/secret")).unwrap();
+    for path in ["/data/file", "/secret/file"] {
+        fs.new_open_options()
+            .write(true)
+            .create(true)
+            .open(Path::new(path))
+            .unwrap();
+    }
+    fs.create_symlink(Path::new("/data/file"), Path::new("/secret/dead"))
+        .unwrap();
+
+    let mut builder = WasiEnv::builder("main")
+        .fs(Arc::new(fs.clone()) as Arc<dyn FileSystem + Send + Sync>)
+        .preopen_dir("/")
+        .unwrap();
+    builder.capabilities_mut().sandbox = SandboxPolicy::new().allow_path("/data");
+    let (exit_code, stdout) = runtime.spawn_wat(PROGRAM, builder);
+    assert!(exit_code.is_success());
+    assert_eq!(stdout, [[ENOTCAPABLE; 7].as_slice(), &[0]].concat());
+
+    // Nothing outside of the allowed paths was touched
+    assert!(fs.metadata(Path::new("/secret/file")).is_ok());
+    assert!(fs.metadata(Path::new("/secret/dir")).is_err());
+    assert!(fs.metadata(Path::new("/data/moved")).is_err());
+    assert!(fs.symlink_metadata(Path::new("/secret/link")).is_err());
+}
@@ -0,0 +1,103 @@
+use std::sync::Arc;
+
+use virtual_fs::{AsyncWriteExt, FileSystem, TmpFileSystem};
+use virtual_mio::block_on;
+use wasmer_wasix_types::wasi::Errno;
+
+use super::run_wat_with;
+
+/// The first image tries to exec a script whose interpreter doesn't exist
+/// and writes the result to stdout, then it execs a script that names this
+/// program as its interpreter. The second image writes its title and its
+/// arguments to stdout.
+const PROGRAM: &str = r#"
+(module
+    (import "wasi_snapshot_preview1" "args_sizes_get" (func $args_sizes_get (param i32 i32) (result i32)))
+    (import "wasi_snapshot_preview1" "args_get" (func $args_get (param i32 i32) (result i32)))
+    (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+    (import "wasix_32v1" "proc_exec3" (func $proc_exec3 (param i32 i32 i32 i32 i32 i32 i32 i32 i32) (result i32)))
+    (import "wasix_32v1" "proc_get_title" (func $proc_get_title (param i32 i32 i32) (result i32)))
+
+    (memory 1)
+    (export "memory" (memory 0))
+
+    (data (i32.const 100) "/prog/missing.sh")
+    (data (i32.const 120) "/prog/script.sh")
+    (data (i32.const 140) "script.sh\nhello")
+
+    (func $check (param i32)
+        (if (local.get 0) (then unreachable))
+    )
+
+    (func $main (export "_start")
+        (call $check (call $args_sizes_get (i32.const 16) (i32.const 20)))
+        (if (i32.eq (i32.load (i32.const 16)) (i32.const 1))
+            (then
+                (i32.store (i32.const 300) (call $proc_exec3 (i32.const 100) (i32.const 16) (i32.const 140) (i32.const 15)
+                    (i32.const 0) (i32.const 0) (i32.const 0) (i32.const 0) (i32.const 0)))
+                (i32.store (i32.const 0) (i32.const 300))
+                (i32.store (i32.const 4) (i32.const 4))
+                (call $check (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 8)))
+
+                (call $check (call $proc_exec3 (i32.const 120) (i32.const 15) (i32.const 140) (i32.const 15)
+                    (i32.const 0) (i32.const 0) (i32.const 0) (i32.const 0) (i32.const 0)))
+                unreachable
+            )
+        )
+
+        ;; The title is followed by a nul, like the arguments
+        (call $check (call $proc_get_title (i32.const 3000) (i32.const 64) (i32.const 24)))
+        (i32.store (i32.const 0) (i32.const 3000))
+        (i32.store (i32.const 4) (i32.add (i32.load (i32.const 24)) (i32.const 1)))
+        (call $check (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 8)))
+
+        (call $check (call $args_get (i32.const 1000) (i32.const 2000)))
+        (i32.store (i32.const 0) (i32.const 2000))
+        (i32.store (i32.const 4) (i32.load (i32.const 20)))
+        (call $check (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 8)))
+    )
+)
+"#;
+
+fn write_file(fs: &TmpFileSystem, path: &str, contents: &[u8]) {
+    let mut file = fs
+        .new_open_options()
+        .create(true)
+        .write(true)
+        .open(path)
+        .unwrap();
+    block_on(file.write_all(contents)).unwrap();
+}
+
+#[test]
+fn test_exec_runs_scripts_with_their_interpreter() {
+    let wasm = wasmer::wat2wasm(PROGRAM.as_bytes()).unwrap();
+    let prog = TmpFileSystem::new();
+    write_file(&prog, "/main.wasm", &wasm);
+    write_file(&prog, "/missing.sh", b"#!/prog/nothing\necho hello\n");
+    write_file(&prog, "/script.sh", b"#!/prog/main.wasm  -x \necho hello\n");
+
+    let stdout = run_wat_with(&wasm, |runner| {
+        runner.with_mount("/prog".to_string(), Arc::new(prog));
+    });
+
+    // The script without an interpreter doesn't exist
+    let errno = u32::from_le_bytes(stdout[..4].try_into().unwrap());
+    assert_eq!(errno, Errno::Noent as u32);
+
+    let mut strings = stdout[4..]
+        .split(|b| *b == 0)
+        .filter(|arg| !arg.is_empty())
+        .map(|arg| String::from_utf8_lossy(arg).into_owned());
+
+    // The process is named after the script rather than its interpreter
+    assert_eq!(strings.next().unwrap(), "/prog/script.sh");
+
+    // The interpreter got its argument and the path of the script ahead of
+    // the arguments of the script
+    let args: Vec<_> = strings.collect();
+    assert_eq!(
+        args,
+        vec!["/prog/main.wasm", "-x", "/prog/script.sh", "hello"]
+    );
+}
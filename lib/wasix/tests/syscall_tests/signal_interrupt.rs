@@ -0,0 +1,415 @@
+use std::{
+    io,
+    pin::Pin,
+    sync::{Arc, Condvar, Mutex},
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use tokio::io::{AsyncRead, AsyncSeek, AsyncWrite, ReadBuf};
+use virtual_fs::{AsyncWriteExt, VirtualFile};
+use virtual_mio::block_on;
+use wasmer_wasix::{Pipe, WasiEnv};
+use wasmer_wasix_types::wasi::{Errno, Signal};
+
+use super::{TestRuntime, run_wat};
+
+/// What a program did in the order it did it, which is what it wrote to
+/// stdout with a `W` each time it had to wait for stdin
+#[derive(Clone, Debug, Default)]
+struct Transcript(Arc<(Mutex<Vec<u8>>, Condvar)>);
+
+impl Transcript {
+    fn push(&self, bytes: &[u8]) {
+        let (transcript, changed) = &*self.0;
+        transcript.lock().unwrap().extend_from_slice(bytes);
+        changed.notify_all();
+    }
+
+    /// Waits until `done` returns something for the transcript
+    fn wait_until<T>(&self, done: impl Fn(&[u8]) -> Option<T>) -> T {
+        let (transcript, changed) = &*self.0;
+        let mut transcript = transcript.lock().unwrap();
+        loop {
+            if let Some(res) = done(&transcript) {
+                return res;
+            }
+            let timeout;
+            (transcript, timeout) = changed
+                .wait_timeout(transcript, Duration::from_secs(10))
+                .unwrap();
+            assert!(!timeout.timed_out(), "the program got stuck");
+        }
+    }
+
+    fn take(&self) -> Vec<u8> {
+        std::mem::take(&mut self.0.0.lock().unwrap())
+    }
+}
+
+/// Stdio that reads from a pipe and writes to a transcript, recording
+/// when the program waits for the pipe
+#[derive(Debug)]
+struct RecordedStdio {
+    pipe: Pipe,
+    transcript: Transcript,
+}
+
+impl RecordedStdio {
+    fn waited<T>(&self, res: Poll<T>) -> Poll<T> {
+        if res.is_pending() {
+            self.transcript.push(b"W");
+        }
+        res
+    }
+}
+
+impl AsyncRead for RecordedStdio {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let res = Pin::new(&mut self.pipe).poll_read(cx, buf);
+        self.waited(res)
+    }
+}
+
+impl AsyncWrite for RecordedStdio {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        self.transcript.push(buf);
+        Poll::Ready(Ok(buf.len()))
+    }
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+    fn poll_shutdown(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl AsyncSeek for RecordedStdio {
+    fn start_seek(self: Pin<&mut Self>, _position: io::SeekFrom) -> io::Result<()> {
+        Ok(())
+    }
+    fn poll_complete(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<u64>> {
+        Poll::Ready(Ok(0))
+    }
+}
+
+impl VirtualFile for RecordedStdio {
+    fn last_accessed(&self) -> u64 {
+        0
+    }
+    fn last_modified(&self) -> u64 {
+        0
+    }
+    fn created_time(&self) -> u64 {
+        0
+    }
+    fn size(&self) -> u64 {
+        0
+    }
+    fn set_len(&mut self, _new_size: u64) -> virtual_fs::Result<()> {
+        Ok(())
+    }
+    fn unlink(&mut self) -> virtual_fs::Result<()> {
+        Ok(())
+    }
+    fn poll_read_ready(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<usize>> {
+        let res = Pin::new(&mut self.pipe).poll_read_ready(cx);
+        self.waited(res)
+    }
+    fn poll_write_ready(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<usize>> {
+        Poll::Ready(Ok(8192))
+    }
+}
+
+/// Builds the environment of a program whose stdin is fed through the
+/// returned pipe and whose stdout goes to the returned transcript
+fn recorded_env(runtime: &TestRuntime) -> (WasiEnv, Pipe, Transcript) {
+    let _guard = runtime.enter();
+    let (stdin_tx, stdin_rx) = Pipe::channel();
+    let transcript = Transcript::default();
+    let stdio = |pipe| {
+        Box::new(RecordedStdio {
+            pipe,
+            transcript: transcript.clone(),
+        })
+    };
+    let env = WasiEnv::builder("main")
+        .stdin(stdio(stdin_rx))
+        .stdout(stdio(Pipe::new()))
+        .runtime(Arc::new(runtime.rt.clone()))
+        .build()
+        .unwrap();
+    (env, stdin_tx, transcript)
+}
+
+/// The numbers that a program wrote to stdout
+fn results(stdout: &[u8]) -> Vec<u32> {
+    stdout
+        .chunks(4)
+        .map(|value| u32::from_le_bytes(value.try_into().unwrap()))
+        .collect()
+}
+
+/// Reads stdin twice with a signal handler installed, `SIGUSR1` restarts
+/// the syscalls it interrupts for the second read only. A `R` is written
+/// to stdout right before each read and a `H` when the handler runs.
+const PROGRAM: &[u8] = br#"
+(module
+    (import "wasi_snapshot_preview1" "fd_read" (func $fd_read (param i32 i32 i32 i32) (result i32)))
+    (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+    (import "wasix_32v1" "callback_signal" (func $callback_signal (param i32 i32)))
+    (import "wasix_32v1" "proc_sigrestart" (func $proc_sigrestart (param i32 i32) (result i32)))
+
+    (memory 1)
+    (export "memory" (memory 0))
+
+    (data (i32.const 100) "handler")
+    (data (i32.const 120) "RH")
+
+    (func $check (param i32)
+        (if (local.get 0) (then unreachable))
+    )
+
+    ;; Counts the signals that were handled and writes a `H` to stdout
+    (func $handler (export "handler") (param i32)
+        (i32.store (i32.const 312) (i32.add (i32.load (i32.const 312)) (i32.const 1)))
+        (i32.store (i32.const 24) (i32.const 121))
+        (i32.store (i32.const 28) (i32.const 1))
+        (call $check (call $fd_write (i32.const 1) (i32.const 24) (i32.const 1) (i32.const 32)))
+    )
+
+    (func $ready
+        (i32.store (i32.const 0) (i32.const 120))
+        (i32.store (i32.const 4) (i32.const 1))
+        (call $check (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 8)))
+    )
+
+    ;; Reads stdin into the 16 bytes at 400, the number of bytes that
+    ;; were read goes to `nread`
+    (func $read (param $nread i32) (result i32)
+        (i32.store (i32.const 16) (i32.const 400))
+        (i32.store (i32.const 20) (i32.const 16))
+        (call $fd_read (i32.const 0) (i32.const 16) (i32.const 1) (local.get $nread))
+    )
+
+    (func $main (export "_start")
+        (call $callback_signal (i32.const 100) (i32.const 7))
+
+        (call $ready)
+        (i32.store (i32.const 300) (call $read (i32.const 304)))
+
+        (call $check (call $proc_sigrestart (i32.const 10) (i32.const 1)))
+        (call $ready)
+        (i32.store (i32.const 304) (call $read (i32.const 308)))
+
+        ;; Send the results to stdout
+        (i32.store (i32.const 0) (i32.const 300))
+        (i32.store (i32.const 4) (i32.const 16))
+        (call $check (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 8)))
+    )
+)
+"#;
+
+/// Polls stdin twice with a signal handler installed, `SIGUSR1` restarts
+/// the syscalls it interrupts for the second poll only. A `R` is written
+/// to stdout right before each poll and a `H` when the handler runs.
+const POLL_PROGRAM: &[u8] = br#"
+(module
+    (import "wasi_snapshot_preview1" "poll_oneoff" (func $poll_oneoff (param i32 i32 i32 i32) (result i32)))
+    (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+    (import "wasix_32v1" "callback_signal" (func $callback_signal (param i32 i32)))
+    (import "wasix_32v1" "proc_sigrestart" (func $proc_sigrestart (param i32 i32) (result i32)))
+
+    (memory 1)
+    (export "memory" (memory 0))
+
+    (data (i32.const 100) "handler")
+    (data (i32.const 120) "RH")
+
+    (func $check (param i32)
+        (if (local.get 0) (then unreachable))
+    )
+
+    ;; Counts the signals that were handled and writes a `H` to stdout
+    (func $handler (export "handler") (param i32)
+        (i32.store (i32.const 312) (i32.add (i32.load (i32.const 312)) (i32.const 1)))
+        (i32.store (i32.const 24) (i32.const 121))
+        (i32.store (i32.const 28) (i32.const 1))
+        (call $check (call $fd_write (i32.const 1) (i32.const 24) (i32.const 1) (i32.const 32)))
+    )
+
+    (func $ready
+        (i32.store (i32.const 0) (i32.const 120))
+        (i32.store (i32.const 4) (i32.const 1))
+        (call $check (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 8)))
+    )
+
+    ;; Waits for stdin to be readable with the subscription at 200, the
+    ;; number of events goes to `nevents`
+    (func $poll (param $nevents i32) (result i32)
+        (i32.store8 (i32.const 208) (i32.const 1))
+        (i32.store (i32.const 216) (i32.const 0))
+        (call $poll_oneoff (i32.const 200) (i32.const 248) (i32.const 1) (local.get $nevents))
+    )
+
+    (func $main (export "_start")
+        (call $callback_signal (i32.const 100) (i32.const 7))
+
+        (call $ready)
+        (i32.store (i32.const 300) (call $poll (i32.const 304)))
+
+        (call $check (call $proc_sigrestart (i32.const 10) (i32.const 1)))
+        (call $ready)
+        (i32.store (i32.const 304) (call $poll (i32.const 308)))
+
+        ;; Send the results to stdout
+        (i32.store (i32.const 0) (i32.const 300))
+        (i32.store (i32.const 4) (i32.const 16))
+        (call $check (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 8)))
+    )
+)
+"#;
+
+/// Runs `program`, sends it `SIGUSR1` once each of its two blocking
+/// syscalls waits for stdin and some data on stdin once the handler ran for
+/// the second one and returns the four numbers it writes to stdout
+fn run_interrupted(program: &[u8]) -> Vec<u32> {
+    let runtime = TestRuntime::new();
+
+    let (env, mut stdin_tx, transcript) = recorded_env(&runtime);
+    let process = env.process.clone();
+    let mut task = runtime.start(runtime.module(program), env).unwrap();
+
+    // Where the transcript of the syscall that is interrupted next starts
+    let mut start = 0;
+    for _ in 0..2 {
+        transcript.wait_until(|transcript| transcript[start..].starts_with(b"RW").then_some(()));
+        process.signal_process(Signal::Sigusr1);
+
+        // The handler runs while the syscall is still blocked
+        start = transcript.wait_until(|transcript| {
+            let handled = transcript[start..].iter().position(|b| *b == b'H');
+            handled.map(|at| start + at + 1)
+        });
+    }
+    block_on(stdin_tx.write_all(b"hi")).unwrap();
+
+    let exit_code = block_on(task.wait_finished()).unwrap();
+    assert!(exit_code.is_success());
+
+    let transcript = transcript.take();
+    results(&transcript[transcript.len() - 16..])
+}
+
+#[test]
+fn test_signal_interrupts_a_blocking_stdin_read() {
+    // The first read fails as soon as the signal was handled, the second
+    // one is restarted and gets the data that comes afterwards
+    assert_eq!(
+        run_interrupted(PROGRAM),
+        vec![Errno::Intr as u32, Errno::Success as u32, 2, 2]
+    );
+}
+
+#[test]
+fn test_signal_interrupts_a_poll() {
+    // The first poll fails as soon as the signal was handled, the second
+    // one carries on waiting and sees stdin becoming readable
+    assert_eq!(
+        run_interrupted(POLL_PROGRAM),
+        vec![Errno::Intr as u32, Errno::Success as u32, 1, 2]
+    );
+}
+
+#[test]
+fn test_signal_without_a_handler_does_not_interrupt_a_poll() {
+    let runtime = TestRuntime::new();
+
+    // Polls stdin without a signal handler, a `R` is written to stdout
+    // right before the poll
+    let program = br#"
+    (module
+        (import "wasi_snapshot_preview1" "poll_oneoff" (func $poll_oneoff (param i32 i32 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        (data (i32.const 120) "R")
+
+        (func $main (export "_start")
+            (i32.store (i32.const 0) (i32.const 120))
+            (i32.store (i32.const 4) (i32.const 1))
+            (drop (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 8)))
+
+            (i32.store8 (i32.const 208) (i32.const 1))
+            (i32.store (i32.const 216) (i32.const 0))
+            (i32.store (i32.const 300) (call $poll_oneoff (i32.const 200) (i32.const 248) (i32.const 1) (i32.const 304)))
+
+            (i32.store (i32.const 0) (i32.const 300))
+            (i32.store (i32.const 4) (i32.const 8))
+            (drop (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 8)))
+        )
+    )
+    "#;
+
+    let (env, mut stdin_tx, transcript) = recorded_env(&runtime);
+    let process = env.process.clone();
+    let mut task = runtime.start(runtime.module(program), env).unwrap();
+
+    transcript.wait_until(|transcript| transcript.starts_with(b"RW").then_some(()));
+    process.signal_process(Signal::Sigusr1);
+
+    // The signal wakes the poll up, which sees that nothing observes the
+    // signal and goes on waiting for stdin
+    transcript.wait_until(|transcript| transcript.starts_with(b"RWW").then_some(()));
+    block_on(stdin_tx.write_all(b"hi")).unwrap();
+
+    let exit_code = block_on(task.wait_finished()).unwrap();
+    assert!(exit_code.is_success());
+
+    let transcript = transcript.take();
+    assert_eq!(
+        results(&transcript[transcript.len() - 8..]),
+        vec![Errno::Success as u32, 1]
+    );
+}
+
+#[test]
+fn test_sigkill_and_sigstop_never_restart_syscalls() {
+    let stdout = run_wat(
+        br#"
+    (module
+        (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+        (import "wasix_32v1" "proc_sigrestart" (func $proc_sigrestart (param i32 i32) (result i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        (func $main (export "_start")
+            (i32.store (i32.const 100) (call $proc_sigrestart (i32.const 9) (i32.const 1)))
+            (i32.store (i32.const 104) (call $proc_sigrestart (i32.const 19) (i32.const 1)))
+
+            (i32.store (i32.const 0) (i32.const 100))
+            (i32.store (i32.const 4) (i32.const 8))
+            (drop (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 8)))
+        )
+    )
+    "#,
+    );
+    assert_eq!(
+        stdout
+            .chunks(4)
+            .map(|value| u32::from_le_bytes(value.try_into().unwrap()))
+            .collect::<Vec<_>>(),
+        vec![Errno::Inval as u32, Errno::Inval as u32]
+    );
+}
@@ -0,0 +1,187 @@
+use std::time::Duration;
+
+use virtual_fs::AsyncReadExt;
+use virtual_mio::block_on;
+use wasmer_wasix::WasiEnv;
+use wasmer_wasix_types::wasi::{Errno, Eventtype, Signal};
+
+use super::TestRuntime;
+
+/// Polls a signal descriptor for `SIGUSR1` and `SIGUSR2`, reads the signal
+/// that arrived and then reads again without blocking. A `R` is written to
+/// stdout right before the poll.
+const PROGRAM: &[u8] = br#"
+(module
+    (import "wasi_snapshot_preview1" "fd_read" (func $fd_read (param i32 i32 i32 i32) (result i32)))
+    (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+    (import "wasi_snapshot_preview1" "fd_fdstat_set_flags" (func $fd_fdstat_set_flags (param i32 i32) (result i32)))
+    (import "wasi_snapshot_preview1" "poll_oneoff" (func $poll_oneoff (param i32 i32 i32 i32) (result i32)))
+    (import "wasix_32v1" "fd_signal" (func $fd_signal (param i64 i32) (result i32)))
+
+    (memory 1)
+    (export "memory" (memory 0))
+
+    (data (i32.const 120) "R")
+
+    (func $check (param i32)
+        (if (local.get 0) (then unreachable))
+    )
+
+    ;; Reads the signal descriptor into the 4 bytes at `buf`
+    (func $read (param $buf i32) (param $nread i32) (result i32)
+        (i32.store (i32.const 16) (local.get $buf))
+        (i32.store (i32.const 20) (i32.const 4))
+        (call $fd_read (i32.load (i32.const 200)) (i32.const 16) (i32.const 1) (local.get $nread))
+    )
+
+    (func $main (export "_start")
+        (call $check (call $fd_signal (i64.const 5120) (i32.const 200)))
+
+        (i32.store (i32.const 0) (i32.const 120))
+        (i32.store (i32.const 4) (i32.const 1))
+        (call $check (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 8)))
+
+        ;; Wait for the descriptor to become readable
+        (i64.store (i32.const 400) (i64.const 7))
+        (i32.store8 (i32.const 408) (i32.const 1))
+        (i32.store (i32.const 416) (i32.load (i32.const 200)))
+        (i32.store (i32.const 1024) (call $poll_oneoff (i32.const 400) (i32.const 512) (i32.const 1) (i32.const 1028)))
+        (i32.store (i32.const 1032) (i32.load (i32.const 512)))
+        (i32.store (i32.const 1036) (i32.load8_u (i32.const 522)))
+
+        ;; Take the signal, then there is nothing left
+        (i32.store (i32.const 1040) (call $read (i32.const 1048) (i32.const 1044)))
+        (call $check (call $fd_fdstat_set_flags (i32.load (i32.const 200)) (i32.const 4)))
+        (i32.store (i32.const 1052) (call $read (i32.const 600) (i32.const 604)))
+
+        ;; Send the results to stdout
+        (i32.store (i32.const 0) (i32.const 1024))
+        (i32.store (i32.const 4) (i32.const 32))
+        (call $check (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 8)))
+    )
+)
+"#;
+
+#[test]
+fn test_signal_descriptor_reports_pending_signals() {
+    let runtime = TestRuntime::new();
+
+    let builder = WasiEnv::builder("main");
+    let (env, mut stdout_rx) = runtime.build_env(builder);
+    let process = env.process.clone();
+    let mut task = runtime.start(runtime.module(PROGRAM), env).unwrap();
+
+    let mut ready = [0u8; 1];
+    block_on(stdout_rx.read_exact(&mut ready)).unwrap();
+    assert_eq!(&ready, b"R");
+    std::thread::sleep(Duration::from_millis(100));
+    process.signal_process(Signal::Sigusr2);
+
+    let exit_code = block_on(task.wait_finished()).unwrap();
+    assert!(exit_code.is_success());
+
+    let mut stdout = Vec::new();
+    block_on(stdout_rx.read_to_end(&mut stdout)).unwrap();
+    let results = stdout
+        .chunks(4)
+        .map(|value| u32::from_le_bytes(value.try_into().unwrap()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        results,
+        vec![
+            // The poll reports the descriptor as readable
+            Errno::Success as u32,
+            1,
+            7,
+            Eventtype::FdRead as u32,
+            // A read takes the signal
+            Errno::Success as u32,
+            4,
+            Signal::Sigusr2 as u32,
+            Errno::Again as u32,
+        ]
+    );
+}
+
+/// Creates a non blocking signal descriptor for `SIGUSR1` and forks with
+/// `proc_fork_env`. The child raises `SIGUSR1`, reads it from the inherited
+/// descriptor and writes the errno, the length and the signal to stdout
+/// before it exits. The parent then reads the descriptor as well and writes
+/// the errno of that read.
+const FORK_PROGRAM: &[u8] = br#"
+(module
+    (import "wasi_snapshot_preview1" "fd_read" (func $fd_read (param i32 i32 i32 i32) (result i32)))
+    (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+    (import "wasi_snapshot_preview1" "fd_fdstat_set_flags" (func $fd_fdstat_set_flags (param i32 i32) (result i32)))
+    (import "wasi_snapshot_preview1" "proc_raise" (func $proc_raise (param i32) (result i32)))
+    (import "wasix_32v1" "fd_signal" (func $fd_signal (param i64 i32) (result i32)))
+    (import "wasix_32v1" "proc_fork_env" (func $proc_fork_env (param i32) (result i32)))
+    (import "wasix_32v1" "proc_exit2" (func $proc_exit2 (param i32)))
+
+    (memory 1)
+    (export "memory" (memory 0))
+
+    (func $check (param i32)
+        (if (local.get 0) (then unreachable))
+    )
+
+    ;; Reads the signal descriptor into the 4 bytes at `buf`
+    (func $read (param $buf i32) (param $nread i32) (result i32)
+        (i32.store (i32.const 16) (local.get $buf))
+        (i32.store (i32.const 20) (i32.const 4))
+        (call $fd_read (i32.load (i32.const 200)) (i32.const 16) (i32.const 1) (local.get $nread))
+    )
+
+    (func $write (param $ptr i32) (param $len i32)
+        (i32.store (i32.const 0) (local.get $ptr))
+        (i32.store (i32.const 4) (local.get $len))
+        (call $check (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 8)))
+    )
+
+    (func $main (export "_start")
+        (call $check (call $fd_signal (i64.const 1024) (i32.const 200)))
+        (call $check (call $fd_fdstat_set_flags (i32.load (i32.const 200)) (i32.const 4)))
+        (call $check (call $proc_fork_env (i32.const 204)))
+
+        ;; The child
+        (call $check (call $proc_raise (i32.const 10)))
+        (i32.store (i32.const 1024) (call $read (i32.const 1032) (i32.const 1028)))
+        (call $write (i32.const 1024) (i32.const 12))
+        (call $proc_exit2 (i32.const 0))
+
+        ;; The parent
+        (i32.store (i32.const 1036) (call $read (i32.const 600) (i32.const 604)))
+        (call $write (i32.const 1036) (i32.const 4))
+    )
+)
+"#;
+
+#[test]
+fn test_signal_descriptor_reports_the_signals_of_a_forked_child() {
+    let runtime = TestRuntime::new();
+
+    let builder = WasiEnv::builder("main");
+    let (env, mut stdout_rx) = runtime.build_env(builder);
+    let mut task = runtime.start(runtime.module(FORK_PROGRAM), env).unwrap();
+
+    let exit_code = block_on(task.wait_finished()).unwrap();
+    assert!(exit_code.is_success());
+
+    let mut stdout = Vec::new();
+    block_on(stdout_rx.read_to_end(&mut stdout)).unwrap();
+    let results = stdout
+        .chunks(4)
+        .map(|value| u32::from_le_bytes(value.try_into().unwrap()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        results,
+        vec![
+            // The child reads the signal that it raised
+            Errno::Success as u32,
+            4,
+            Signal::Sigusr1 as u32,
+            // which never reached the parent
+            Errno::Again as u32,
+        ]
+    );
+}
@@ -0,0 +1,69 @@
+use wasmer_wasix::WasiEnv;
+use wasmer_wasix_types::wasi::{Disposition, Errno, ExitCode, Signal, SignalDisposition};
+
+use super::TestRuntime;
+
+/// Writes to a pipe whose read end is closed and sends the error of the
+/// write to stdout
+const WRITE_TO_CLOSED_PIPE: &[u8] = br#"
+(module
+    (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+    (import "wasi_snapshot_preview1" "fd_close" (func $fd_close (param i32) (result i32)))
+    (import "wasix_32v1" "fd_pipe" (func $fd_pipe (param i32 i32) (result i32)))
+
+    (memory 1)
+    (export "memory" (memory 0))
+
+    (data (i32.const 100) "abc")
+
+    (func $check (param i32)
+        (if (local.get 0) (then unreachable))
+    )
+
+    (func $main (export "_start")
+        (call $check (call $fd_pipe (i32.const 200) (i32.const 204)))
+        (call $check (call $fd_close (i32.load (i32.const 200))))
+
+        (i32.store (i32.const 0) (i32.const 100))
+        (i32.store (i32.const 4) (i32.const 3))
+        (i32.store (i32.const 300)
+            (call $fd_write (i32.load (i32.const 204)) (i32.const 0) (i32.const 1) (i32.const 8)))
+
+        (i32.store (i32.const 0) (i32.const 300))
+        (i32.store (i32.const 4) (i32.const 4))
+        (call $check (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 8)))
+    )
+)
+"#;
+
+/// Runs the module with the given disposition for `SIGPIPE` and returns
+/// its exit code and what it wrote to stdout
+fn run(disposition: Option<Disposition>) -> (ExitCode, Vec<u8>) {
+    let mut builder = WasiEnv::builder("sigpipe");
+    if let Some(disp) = disposition {
+        builder = builder.signal(SignalDisposition {
+            sig: Signal::Sigpipe,
+            disp,
+        });
+    }
+    TestRuntime::new().spawn_wat(WRITE_TO_CLOSED_PIPE, builder)
+}
+
+#[test]
+fn test_sigpipe_terminates_the_process_by_default() {
+    let (exit_code, stdout) = run(None);
+    assert!(!exit_code.is_success());
+    assert!(stdout.is_empty());
+
+    // Same when the default disposition is set explicitly
+    let (exit_code, stdout) = run(Some(Disposition::Default));
+    assert!(!exit_code.is_success());
+    assert!(stdout.is_empty());
+}
+
+#[test]
+fn test_ignored_sigpipe_fails_the_write_with_epipe() {
+    let (exit_code, stdout) = run(Some(Disposition::Ignore));
+    assert!(exit_code.is_success());
+    assert_eq!(stdout, (Errno::Pipe as u32).to_le_bytes());
+}
@@ -0,0 +1,81 @@
+use std::sync::Arc;
+
+use wasmer_wasix::{VirtualTaskManager, runtime::task_manager::tokio::TokioTaskManager};
+
+use super::TestRuntime;
+
+#[test]
+fn test_single_threaded_task_manager() {
+    let runtime = TestRuntime::with_task_manager(|handle| {
+        let tasks = TokioTaskManager::new_single_threaded(handle.clone());
+        assert!(tasks.is_single_threaded());
+        assert_eq!(tasks.thread_parallelism().unwrap(), 1);
+        Arc::new(tasks)
+    });
+
+    let (result, stdout) = runtime.run_wat(
+        br#"
+    (module
+        (import "env" "memory" (memory 2 2 shared))
+        (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+        (import "wasix_32v1" "thread_spawn_v2" (func $thread_spawn (param i32 i32) (result i32)))
+        (import "wasix_32v1" "sched_yield" (func $sched_yield (result i32)))
+
+        (global $__stack_low (export "__stack_low") i32 (i32.const 16384))
+        (global $__stack_pointer (export "__stack_pointer") (mut i32) (i32.const 32768))
+
+        (data (i32.const 256) "thread\n")
+        (data (i32.const 272) "main\n")
+
+        ;; `_start` is the only function that is ever unwound and it keeps
+        ;; nothing on the stack, so asyncify just needs to track its state
+        (global $asyncify_state (mut i32) (i32.const 0))
+        (func (export "asyncify_start_unwind") (param i32)
+            (global.set $asyncify_state (i32.const 1))
+        )
+        (func (export "asyncify_stop_unwind")
+            (global.set $asyncify_state (i32.const 0))
+        )
+        (func (export "asyncify_start_rewind") (param i32)
+            (global.set $asyncify_state (i32.const 2))
+        )
+        (func (export "asyncify_stop_rewind")
+            (global.set $asyncify_state (i32.const 0))
+        )
+        (func (export "asyncify_get_state") (result i32)
+            (global.get $asyncify_state)
+        )
+
+        (func $print (param $ptr i32) (param $len i32)
+            (i32.store (i32.const 0) (local.get $ptr))
+            (i32.store (i32.const 4) (local.get $len))
+            (drop (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 8)))
+        )
+
+        (func (export "wasi_thread_start") (param i32 i32)
+            (call $print (i32.const 256) (i32.const 7))
+        )
+
+        (func $main (export "_start")
+            ;; The thread is only spawned the first time around, not when
+            ;; `_start` is rewound
+            (if (i32.eqz (global.get $asyncify_state))
+                (then
+                    (i32.store (i32.const 1024) (i32.const 65536))
+                    (i32.store (i32.const 1080) (i32.const 16384))
+                    (if (call $thread_spawn (i32.const 1024) (i32.const 1100)) (then unreachable))
+                )
+            )
+
+            ;; The thread can only run once this one gives up the thread
+            (drop (call $sched_yield))
+            (if (i32.eq (global.get $asyncify_state) (i32.const 1)) (then return))
+
+            (call $print (i32.const 272) (i32.const 5))
+        )
+    )
+    "#,
+    );
+    result.unwrap();
+    assert_eq!(stdout, b"thread\nmain\n");
+}
@@ -0,0 +1,37 @@
+use wasmer_wasix_types::wasi::Errno;
+
+use super::TestRuntime;
+
+#[test]
+fn test_stack_checkpoint_fails_when_the_stack_is_too_large_to_snapshot() {
+    let mut runtime = TestRuntime::new();
+    runtime.rt.set_snapshot_size_limit(128);
+
+    let (result, stdout) = runtime.run_wat(
+        br#"
+    (module
+        (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+        (import "wasix_32v1" "stack_checkpoint" (func $stack_checkpoint (param i32 i32) (result i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        (global $__stack_pointer (export "__stack_pointer") (mut i32) (i32.const 65536))
+
+        (func $main (export "_start")
+            ;; The program has 256 bytes on its stack when it takes the
+            ;; snapshot, which is more than the runtime allows
+            (global.set $__stack_pointer (i32.sub (global.get $__stack_pointer) (i32.const 256)))
+            (i32.store8 (i32.const 100) (call $stack_checkpoint (i32.const 200) (i32.const 300)))
+
+            (i32.store (i32.const 0) (i32.const 100))
+            (i32.store (i32.const 4) (i32.const 1))
+            (drop (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 8)))
+        )
+    )
+    "#,
+    );
+
+    result.unwrap();
+    assert_eq!(stdout, [Errno::Nomem as u8]);
+}
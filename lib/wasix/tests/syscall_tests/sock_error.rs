@@ -0,0 +1,82 @@
+use wasmer_wasix_types::wasi::{Errno, Sockstatus};
+
+use super::run_wat;
+
+#[test]
+fn test_nonblocking_connect_refused() {
+    // Nothing listens on a port that was just released
+    let port = std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+
+    let stdout = run_wat(format!(
+        r#"
+    (module
+        (import "wasi_snapshot_preview1" "fd_fdstat_set_flags" (func $fd_fdstat_set_flags (param i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "poll_oneoff" (func $poll_oneoff (param i32 i32 i32 i32) (result i32)))
+        (import "wasix_32v1" "sock_open" (func $sock_open (param i32 i32 i32 i32) (result i32)))
+        (import "wasix_32v1" "sock_connect" (func $sock_connect (param i32 i32) (result i32)))
+        (import "wasix_32v1" "sock_status" (func $sock_status (param i32 i32) (result i32)))
+        (import "wasix_32v1" "sock_get_opt_size" (func $sock_get_opt_size (param i32 i32 i32) (result i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        ;; 127.0.0.1:{port}
+        (data (i32.const 304) "\7f\00\00\01")
+
+        (func $check (param i32)
+            (if (local.get 0) (then unreachable))
+        )
+
+        (func $main (export "_start")
+            (local $fd i32)
+
+            ;; Open a non-blocking TCP socket
+            (call $check (call $sock_open (i32.const 1) (i32.const 1) (i32.const 6) (i32.const 200)))
+            (local.set $fd (i32.load (i32.const 200)))
+            (call $check (call $fd_fdstat_set_flags (local.get $fd) (i32.const 4)))
+
+            ;; The connect is reported as in progress
+            (i32.store16 (i32.const 300) (i32.const 1))
+            (i32.store16 (i32.const 302) (i32.const {port}))
+            (i32.store (i32.const 1024) (call $sock_connect (local.get $fd) (i32.const 300)))
+            (call $check (call $sock_status (local.get $fd) (i32.const 400)))
+            (i32.store (i32.const 1028) (i32.load8_u (i32.const 400)))
+
+            ;; Wait for the socket to become writable
+            (i32.store8 (i32.const 508) (i32.const 2))
+            (i32.store (i32.const 516) (local.get $fd))
+            (call $check (call $poll_oneoff (i32.const 500) (i32.const 600) (i32.const 1) (i32.const 700)))
+            (i32.store (i32.const 1032) (i32.load (i32.const 700)))
+            (i32.store (i32.const 1036) (i32.load8_u (i32.const 610)))
+
+            ;; SO_ERROR returns the failure once
+            (call $check (call $sock_get_opt_size (local.get $fd) (i32.const 11) (i32.const 800)))
+            (i64.store (i32.const 1040) (i64.load (i32.const 800)))
+            (call $check (call $sock_get_opt_size (local.get $fd) (i32.const 11) (i32.const 800)))
+            (i64.store (i32.const 1048) (i64.load (i32.const 800)))
+
+            ;; Send the results to stdout
+            (i32.store (i32.const 0) (i32.const 1024))
+            (i32.store (i32.const 4) (i32.const 32))
+            (call $check (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 8)))
+        )
+    )
+    "#
+    ));
+    assert_eq!(stdout.len(), 32);
+
+    let u64_at = |offset: usize| u64::from_le_bytes(stdout[offset..offset + 8].try_into().unwrap());
+    let u32_at = |offset: usize| u32::from_le_bytes(stdout[offset..offset + 4].try_into().unwrap());
+
+    assert_eq!(u32_at(0), Errno::Inprogress as u32);
+    assert_eq!(u32_at(4), Sockstatus::Failed as u32);
+    assert_eq!(u32_at(8), 1, "the failed socket is ready");
+    assert_eq!(u32_at(12), 2, "it is reported as writable");
+    assert_eq!(u64_at(16), Errno::Connrefused as u64);
+    assert_eq!(u64_at(24), 0, "reading SO_ERROR clears it");
+}
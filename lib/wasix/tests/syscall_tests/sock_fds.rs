@@ -0,0 +1,197 @@
+use super::run_wat;
+
+/// Opens `tmp/a.txt`, sends it twice over a socket pair and closes it, after
+/// which it can't be sent anymore. Then it receives it on the other end with
+/// only room for one descriptor. It writes the number of received
+/// descriptors, the output flags, the number of bytes received, the error of
+/// sending the closed descriptor and what was read through the received
+/// descriptor to stdout.
+const PROGRAM: &str = r#"
+(module
+    (import "wasi_snapshot_preview1" "path_open" (func $path_open (param i32 i32 i32 i32 i32 i64 i64 i32 i32) (result i32)))
+    (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+    (import "wasi_snapshot_preview1" "fd_pread" (func $fd_pread (param i32 i32 i32 i64 i32) (result i32)))
+    (import "wasi_snapshot_preview1" "fd_close" (func $fd_close (param i32) (result i32)))
+    (import "wasix_32v1" "sock_pair" (func $sock_pair (param i32 i32 i32 i32 i32) (result i32)))
+    (import "wasix_32v1" "sock_send_fds" (func $sock_send_fds (param i32 i32 i32 i32 i32 i32 i32) (result i32)))
+    (import "wasix_32v1" "sock_recv_fds" (func $sock_recv_fds (param i32 i32 i32 i32 i32 i32 i32 i32 i32) (result i32)))
+
+    (memory 1)
+    (export "memory" (memory 0))
+
+    (data (i32.const 100) "tmp/a.txt")
+    (data (i32.const 120) "passed")
+
+    (func $check (param i32)
+        (if (local.get 0) (then unreachable))
+    )
+
+    (func $main (export "_start")
+        (call $check (call $path_open (i32.const 3) (i32.const 0) (i32.const 100) (i32.const 9)
+            (i32.const 1) (i64.const -1) (i64.const -1) (i32.const 0) (i32.const 200)))
+        (i32.store (i32.const 0) (i32.const 120))
+        (i32.store (i32.const 4) (i32.const 6))
+        (call $check (call $fd_write (i32.load (i32.const 200)) (i32.const 0) (i32.const 1) (i32.const 8)))
+
+        ;; An AF_UNIX stream socket pair
+        (call $check (call $sock_pair (i32.const 3) (i32.const 1) (i32.const 0) (i32.const 204) (i32.const 208)))
+
+        ;; Pass the file twice and close it, the passed ones keep it open
+        (i32.store (i32.const 212) (i32.load (i32.const 200)))
+        (i32.store (i32.const 216) (i32.load (i32.const 200)))
+        (i32.store (i32.const 4) (i32.const 1))
+        (call $check (call $sock_send_fds (i32.load (i32.const 204)) (i32.const 0) (i32.const 1) (i32.const 0)
+            (i32.const 212) (i32.const 2) (i32.const 8)))
+        (call $check (call $fd_close (i32.load (i32.const 200))))
+
+        ;; A closed descriptor can't be passed
+        (i32.store (i32.const 312) (call $sock_send_fds (i32.load (i32.const 204)) (i32.const 0) (i32.const 1) (i32.const 0)
+            (i32.const 212) (i32.const 1) (i32.const 8)))
+
+        ;; Only one of them fits
+        (i32.store (i32.const 16) (i32.const 500))
+        (i32.store (i32.const 20) (i32.const 16))
+        (call $check (call $sock_recv_fds (i32.load (i32.const 208)) (i32.const 16) (i32.const 1) (i32.const 0)
+            (i32.const 220) (i32.const 1) (i32.const 300) (i32.const 308) (i32.const 304)))
+
+        ;; The received descriptor refers to the file
+        (i32.store (i32.const 16) (i32.const 316))
+        (i32.store (i32.const 20) (i32.const 6))
+        (call $check (call $fd_pread (i32.load (i32.const 220)) (i32.const 16) (i32.const 1) (i64.const 0) (i32.const 8)))
+
+        (i32.store (i32.const 0) (i32.const 300))
+        (i32.store (i32.const 4) (i32.const 22))
+        (call $check (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 8)))
+    )
+)
+"#;
+
+#[test]
+fn test_pass_fds_over_socket_pair() {
+    let stdout = run_wat(PROGRAM);
+    let (values, contents) = stdout.split_at(16);
+    let values: Vec<_> = values
+        .chunks(4)
+        .map(|value| u32::from_le_bytes(value.try_into().unwrap()))
+        .collect();
+
+    // One descriptor with `__WASI_SOCK_RECV_OUTPUT_FDS_TRUNCATED` set, one
+    // byte of data and `Errno::Badf` for the closed descriptor
+    assert_eq!(values, vec![1, 2, 1, 8]);
+    assert_eq!(contents, b"passed");
+}
+
+/// Lowers `RLIMIT_NOFILE` right above the descriptors of a socket pair and
+/// passes one of them over it, then writes the number of received
+/// descriptors and the output flags to stdout.
+const OVER_LIMIT: &str = r#"
+(module
+    (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+    (import "wasix_32v1" "sock_pair" (func $sock_pair (param i32 i32 i32 i32 i32) (result i32)))
+    (import "wasix_32v1" "sock_send_fds" (func $sock_send_fds (param i32 i32 i32 i32 i32 i32 i32) (result i32)))
+    (import "wasix_32v1" "sock_recv_fds" (func $sock_recv_fds (param i32 i32 i32 i32 i32 i32 i32 i32 i32) (result i32)))
+    (import "wasix_32v1" "proc_setrlimit" (func $proc_setrlimit (param i32 i32) (result i32)))
+
+    (memory 1)
+    (export "memory" (memory 0))
+
+    (func $check (param i32)
+        (if (local.get 0) (then unreachable))
+    )
+
+    (func $main (export "_start")
+        (call $check (call $sock_pair (i32.const 3) (i32.const 1) (i32.const 0) (i32.const 204) (i32.const 208)))
+
+        ;; RLIMIT_NOFILE, no other descriptor can be opened
+        (i64.store (i32.const 400) (i64.extend_i32_u (i32.add (i32.const 1)
+            (select (i32.load (i32.const 204)) (i32.load (i32.const 208))
+                (i32.gt_u (i32.load (i32.const 204)) (i32.load (i32.const 208)))))))
+        (i64.store (i32.const 408) (i64.load (i32.const 400)))
+        (call $check (call $proc_setrlimit (i32.const 7) (i32.const 400)))
+
+        (i32.store (i32.const 212) (i32.load (i32.const 204)))
+        (i32.store (i32.const 0) (i32.const 120))
+        (i32.store (i32.const 4) (i32.const 1))
+        (call $check (call $sock_send_fds (i32.load (i32.const 204)) (i32.const 0) (i32.const 1) (i32.const 0)
+            (i32.const 212) (i32.const 1) (i32.const 8)))
+
+        (i32.store (i32.const 16) (i32.const 500))
+        (i32.store (i32.const 20) (i32.const 16))
+        (call $check (call $sock_recv_fds (i32.load (i32.const 208)) (i32.const 16) (i32.const 1) (i32.const 0)
+            (i32.const 220) (i32.const 1) (i32.const 300) (i32.const 308) (i32.const 304)))
+
+        (i32.store (i32.const 0) (i32.const 300))
+        (i32.store (i32.const 4) (i32.const 6))
+        (call $check (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 8)))
+    )
+)
+"#;
+
+#[test]
+fn test_passed_fds_respect_the_fd_limit() {
+    let stdout = run_wat(OVER_LIMIT);
+    let nfds = u32::from_le_bytes(stdout[0..4].try_into().unwrap());
+    let flags = u16::from_le_bytes(stdout[4..6].try_into().unwrap());
+
+    // Nothing was installed and `__WASI_SOCK_RECV_OUTPUT_FDS_TRUNCATED` is set
+    assert_eq!(nfds, 0);
+    assert_eq!(flags, 2);
+}
+
+/// Passes a descriptor with the message `a` and sends the message `b`
+/// without any, then reads `a` with `fd_read` and `b` with `sock_recv_fds`.
+/// It writes the number of descriptors that came with `b` and what was
+/// read to stdout.
+const READ_WITHOUT_FDS: &str = r#"
+(module
+    (import "wasi_snapshot_preview1" "fd_read" (func $fd_read (param i32 i32 i32 i32) (result i32)))
+    (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+    (import "wasix_32v1" "sock_pair" (func $sock_pair (param i32 i32 i32 i32 i32) (result i32)))
+    (import "wasix_32v1" "sock_send_fds" (func $sock_send_fds (param i32 i32 i32 i32 i32 i32 i32) (result i32)))
+    (import "wasix_32v1" "sock_recv_fds" (func $sock_recv_fds (param i32 i32 i32 i32 i32 i32 i32 i32 i32) (result i32)))
+
+    (memory 1)
+    (export "memory" (memory 0))
+
+    (data (i32.const 120) "ab")
+
+    (func $check (param i32)
+        (if (local.get 0) (then unreachable))
+    )
+
+    (func $main (export "_start")
+        (call $check (call $sock_pair (i32.const 3) (i32.const 1) (i32.const 0) (i32.const 204) (i32.const 208)))
+
+        ;; `a` comes with a descriptor, `b` without
+        (i32.store (i32.const 212) (i32.load (i32.const 204)))
+        (i32.store (i32.const 0) (i32.const 120))
+        (i32.store (i32.const 4) (i32.const 1))
+        (call $check (call $sock_send_fds (i32.load (i32.const 204)) (i32.const 0) (i32.const 1) (i32.const 0)
+            (i32.const 212) (i32.const 1) (i32.const 8)))
+        (i32.store (i32.const 0) (i32.const 121))
+        (call $check (call $fd_write (i32.load (i32.const 204)) (i32.const 0) (i32.const 1) (i32.const 8)))
+
+        ;; The descriptor of `a` is closed as it is read without them
+        (i32.store (i32.const 16) (i32.const 304))
+        (i32.store (i32.const 20) (i32.const 1))
+        (call $check (call $fd_read (i32.load (i32.const 208)) (i32.const 16) (i32.const 1) (i32.const 8)))
+
+        (i32.store (i32.const 16) (i32.const 305))
+        (call $check (call $sock_recv_fds (i32.load (i32.const 208)) (i32.const 16) (i32.const 1) (i32.const 0)
+            (i32.const 220) (i32.const 1) (i32.const 300) (i32.const 308) (i32.const 312)))
+
+        (i32.store (i32.const 0) (i32.const 300))
+        (i32.store (i32.const 4) (i32.const 6))
+        (call $check (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 8)))
+    )
+)
+"#;
+
+#[test]
+fn test_passed_fds_stay_with_their_message() {
+    let stdout = run_wat(READ_WITHOUT_FDS);
+    let nfds = u32::from_le_bytes(stdout[0..4].try_into().unwrap());
+
+    assert_eq!(nfds, 0);
+    assert_eq!(&stdout[4..6], b"ab");
+}
@@ -0,0 +1,69 @@
+use wasmer_wasix_types::wasi::Errno;
+
+use super::run_wat;
+
+#[test]
+fn test_sock_join_and_leave_multicast() {
+    let stdout = run_wat(br#"
+    (module
+        (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+        (import "wasix_32v1" "sock_open" (func $sock_open (param i32 i32 i32 i32) (result i32)))
+        (import "wasix_32v1" "sock_bind" (func $sock_bind (param i32 i32) (result i32)))
+        (import "wasix_32v1" "sock_join_multicast_v4" (func $sock_join_multicast_v4 (param i32 i32 i32) (result i32)))
+        (import "wasix_32v1" "sock_leave_multicast_v4" (func $sock_leave_multicast_v4 (param i32 i32 i32) (result i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        ;; The group 239.255.0.1 on any interface
+        (data (i32.const 320) "\ef\ff\00\01")
+        (data (i32.const 324) "\00\00\00\00")
+
+        (func $check (param i32)
+            (if (local.get 0) (then unreachable))
+        )
+
+        (func $main (export "_start")
+            ;; A UDP socket bound to 0.0.0.0:0
+            (call $check (call $sock_open (i32.const 1) (i32.const 2) (i32.const 17) (i32.const 200)))
+            (i32.store16 (i32.const 300) (i32.const 1))
+            (call $check (call $sock_bind (i32.load (i32.const 200)) (i32.const 300)))
+
+            ;; Join the group twice, then leave it twice
+            (i32.store (i32.const 1024)
+                (call $sock_join_multicast_v4 (i32.load (i32.const 200)) (i32.const 320) (i32.const 324)))
+            (i32.store (i32.const 1028)
+                (call $sock_join_multicast_v4 (i32.load (i32.const 200)) (i32.const 320) (i32.const 324)))
+            (i32.store (i32.const 1032)
+                (call $sock_leave_multicast_v4 (i32.load (i32.const 200)) (i32.const 320) (i32.const 324)))
+            (i32.store (i32.const 1036)
+                (call $sock_leave_multicast_v4 (i32.load (i32.const 200)) (i32.const 320) (i32.const 324)))
+
+            ;; A TCP socket can't join a group
+            (call $check (call $sock_open (i32.const 1) (i32.const 1) (i32.const 6) (i32.const 204)))
+            (i32.store (i32.const 1040)
+                (call $sock_join_multicast_v4 (i32.load (i32.const 204)) (i32.const 320) (i32.const 324)))
+
+            ;; Send the results to stdout
+            (i32.store (i32.const 0) (i32.const 1024))
+            (i32.store (i32.const 4) (i32.const 20))
+            (call $check (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 8)))
+        )
+    )
+    "#);
+    let results = stdout
+        .chunks(4)
+        .map(|value| u32::from_le_bytes(value.try_into().unwrap()))
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        results,
+        vec![
+            Errno::Success as u32,
+            Errno::Addrinuse as u32,
+            Errno::Success as u32,
+            Errno::Addrnotavail as u32,
+            Errno::Notsup as u32,
+        ]
+    );
+}
@@ -0,0 +1,209 @@
+use wasmer_wasix::capabilities::SandboxPolicy;
+use wasmer_wasix_types::wasi::Errno;
+
+use super::{run_wat, run_wat_with};
+
+#[test]
+fn test_sock_pair_round_trip() {
+    let stdout = run_wat(br#"
+    (module
+        (import "wasi_snapshot_preview1" "fd_read" (func $fd_read (param i32 i32 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "fd_close" (func $fd_close (param i32) (result i32)))
+        (import "wasi_snapshot_preview1" "poll_oneoff" (func $poll_oneoff (param i32 i32 i32 i32) (result i32)))
+        (import "wasix_32v1" "sock_pair" (func $sock_pair (param i32 i32 i32 i32 i32) (result i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        (data (i32.const 100) "ping")
+        (data (i32.const 104) "pong")
+
+        (func $check (param i32)
+            (if (local.get 0) (then unreachable))
+        )
+
+        ;; Writes `len` bytes at `ptr` to `fd`
+        (func $write (param $fd i32) (param $ptr i32) (param $len i32) (result i32)
+            (i32.store (i32.const 0) (local.get $ptr))
+            (i32.store (i32.const 4) (local.get $len))
+            (call $fd_write (local.get $fd) (i32.const 0) (i32.const 1) (i32.const 8))
+        )
+
+        ;; Reads up to `len` bytes from `fd` to `ptr` and returns how many were read
+        (func $read_n (param $fd i32) (param $ptr i32) (param $len i32) (result i32)
+            (i32.store (i32.const 0) (local.get $ptr))
+            (i32.store (i32.const 4) (local.get $len))
+            (call $check (call $fd_read (local.get $fd) (i32.const 0) (i32.const 1) (i32.const 8)))
+            (i32.load (i32.const 8))
+        )
+
+        (func $read (param $fd i32) (param $ptr i32) (result i32)
+            (call $read_n (local.get $fd) (local.get $ptr) (i32.const 16))
+        )
+
+        (func $main (export "_start")
+            (local $a i32)
+            (local $b i32)
+            (local $c i32)
+            (local $d i32)
+
+            ;; An AF_INET stream socket pair
+            (call $check (call $sock_pair (i32.const 1) (i32.const 1) (i32.const 0) (i32.const 200) (i32.const 204)))
+            (local.set $a (i32.load (i32.const 200)))
+            (local.set $b (i32.load (i32.const 204)))
+
+            ;; Data goes both ways
+            (call $check (call $write (local.get $a) (i32.const 100) (i32.const 4)))
+            (i32.store (i32.const 1024) (call $read (local.get $b) (i32.const 1028)))
+            (call $check (call $write (local.get $b) (i32.const 104) (i32.const 4)))
+            (i32.store (i32.const 1032) (call $read (local.get $a) (i32.const 1036)))
+
+            ;; Closing one end hangs up the other one
+            (call $check (call $fd_close (local.get $a)))
+            (i32.store8 (i32.const 508) (i32.const 1))
+            (i32.store (i32.const 516) (local.get $b))
+            (call $check (call $poll_oneoff (i32.const 500) (i32.const 600) (i32.const 1) (i32.const 700)))
+            (i32.store (i32.const 1040) (i32.load (i32.const 700)))
+            (i32.store (i32.const 1044) (i32.load16_u (i32.const 624)))
+            (i32.store (i32.const 1048) (call $read (local.get $b) (i32.const 1060)))
+
+            ;; A datagram pair keeps the messages apart and truncates them
+            (call $check (call $sock_pair (i32.const 1) (i32.const 2) (i32.const 0) (i32.const 208) (i32.const 212)))
+            (local.set $c (i32.load (i32.const 208)))
+            (local.set $d (i32.load (i32.const 212)))
+            (call $check (call $write (local.get $c) (i32.const 100) (i32.const 4)))
+            (call $check (call $write (local.get $c) (i32.const 104) (i32.const 4)))
+            (i32.store (i32.const 1052) (call $read_n (local.get $d) (i32.const 1056) (i32.const 2)))
+            (i32.store (i32.const 1060) (call $read (local.get $d) (i32.const 1064)))
+
+            ;; Send the results to stdout
+            (i32.store (i32.const 0) (i32.const 1024))
+            (i32.store (i32.const 4) (i32.const 44))
+            (call $check (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 8)))
+        )
+    )
+    "#);
+    assert_eq!(stdout.len(), 44);
+
+    let u32_at = |offset: usize| u32::from_le_bytes(stdout[offset..offset + 4].try_into().unwrap());
+    const HANGUP: u32 = 1;
+
+    assert_eq!(u32_at(0), 4);
+    assert_eq!(&stdout[4..8], b"ping");
+    assert_eq!(u32_at(8), 4);
+    assert_eq!(&stdout[12..16], b"pong");
+    assert_eq!(u32_at(16), 1, "the remaining end is ready");
+    assert_eq!(u32_at(20) & HANGUP, HANGUP, "and reports the hangup");
+    assert_eq!(u32_at(24), 0, "reading from it gives EOF");
+    assert_eq!(u32_at(28), 2, "the first datagram is truncated");
+    assert_eq!(&stdout[32..36], b"pi\0\0");
+    assert_eq!(u32_at(36), 4, "the second datagram is read on its own");
+    assert_eq!(&stdout[40..44], b"pong");
+}
+
+#[test]
+fn test_sock_pair_datagram_is_read_once_over_all_iovecs() {
+    let stdout = run_wat(br#"
+    (module
+        (import "wasi_snapshot_preview1" "fd_read" (func $fd_read (param i32 i32 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+        (import "wasix_32v1" "sock_pair" (func $sock_pair (param i32 i32 i32 i32 i32) (result i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        (data (i32.const 100) "ping")
+        (data (i32.const 104) "pong")
+
+        (func $check (param i32)
+            (if (local.get 0) (then unreachable))
+        )
+
+        ;; Writes `len` bytes at `ptr` to `fd`
+        (func $write (param $fd i32) (param $ptr i32) (param $len i32) (result i32)
+            (i32.store (i32.const 0) (local.get $ptr))
+            (i32.store (i32.const 4) (local.get $len))
+            (call $fd_write (local.get $fd) (i32.const 0) (i32.const 1) (i32.const 8))
+        )
+
+        (func $main (export "_start")
+            (local $c i32)
+            (local $d i32)
+
+            (call $check (call $sock_pair (i32.const 1) (i32.const 2) (i32.const 0) (i32.const 200) (i32.const 204)))
+            (local.set $c (i32.load (i32.const 200)))
+            (local.set $d (i32.load (i32.const 204)))
+            (call $check (call $write (local.get $c) (i32.const 100) (i32.const 4)))
+            (call $check (call $write (local.get $c) (i32.const 104) (i32.const 4)))
+
+            ;; The first datagram is spread over three buffers that have room
+            ;; for more than it holds
+            (i32.store (i32.const 300) (i32.const 1028))
+            (i32.store (i32.const 304) (i32.const 2))
+            (i32.store (i32.const 308) (i32.const 1030))
+            (i32.store (i32.const 312) (i32.const 1))
+            (i32.store (i32.const 316) (i32.const 1031))
+            (i32.store (i32.const 320) (i32.const 4))
+            (call $check (call $fd_read (local.get $d) (i32.const 300) (i32.const 3) (i32.const 1024)))
+
+            ;; The second one is truncated to the two buffers it is read into
+            (i32.store (i32.const 300) (i32.const 1040))
+            (i32.store (i32.const 304) (i32.const 1))
+            (i32.store (i32.const 308) (i32.const 1041))
+            (i32.store (i32.const 312) (i32.const 1))
+            (call $check (call $fd_read (local.get $d) (i32.const 300) (i32.const 2) (i32.const 1036)))
+
+            ;; Send the results to stdout
+            (i32.store (i32.const 0) (i32.const 1024))
+            (i32.store (i32.const 4) (i32.const 20))
+            (call $check (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 8)))
+        )
+    )
+    "#);
+    assert_eq!(stdout.len(), 20);
+
+    let u32_at = |offset: usize| u32::from_le_bytes(stdout[offset..offset + 4].try_into().unwrap());
+
+    assert_eq!(u32_at(0), 4, "a single datagram is read");
+    assert_eq!(&stdout[4..11], b"ping\0\0\0");
+    assert_eq!(u32_at(12), 2, "the next datagram is truncated");
+    assert_eq!(&stdout[16..18], b"po");
+}
+
+#[test]
+fn test_sock_pair_without_networking() {
+    // A socket pair never leaves the process, so it is not networking
+    let stdout = run_wat_with(
+        br#"
+    (module
+        (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+        (import "wasix_32v1" "sock_pair" (func $sock_pair (param i32 i32 i32 i32 i32) (result i32)))
+        (import "wasix_32v1" "sock_open" (func $sock_open (param i32 i32 i32 i32) (result i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        (func $main (export "_start")
+            (i32.store8 (i32.const 1024)
+                (call $sock_pair (i32.const 1) (i32.const 1) (i32.const 0) (i32.const 200) (i32.const 204)))
+            (i32.store8 (i32.const 1025)
+                (call $sock_open (i32.const 1) (i32.const 1) (i32.const 0) (i32.const 208)))
+
+            ;; Send the results to stdout
+            (i32.store (i32.const 0) (i32.const 1024))
+            (i32.store (i32.const 4) (i32.const 2))
+            (drop (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 8)))
+        )
+    )
+    "#,
+        |runner| {
+            runner
+                .capabilities_mut()
+                .sandbox
+                .update(SandboxPolicy::new().with_network(false));
+        },
+    );
+
+    assert_eq!(stdout, [Errno::Success as u8, Errno::Perm as u8]);
+}
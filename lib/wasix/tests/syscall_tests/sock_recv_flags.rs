@@ -0,0 +1,91 @@
+use wasmer_wasix_types::types::__WASI_SOCK_RECV_OUTPUT_DATA_TRUNCATED;
+
+use super::run_wat;
+
+#[test]
+fn test_truncated_datagrams_are_flagged() {
+    // Nothing else uses a port that was just released
+    let port = std::net::UdpSocket::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+
+    let stdout = run_wat(format!(
+        r#"
+    (module
+        (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+        (import "wasix_32v1" "sock_open" (func $sock_open (param i32 i32 i32 i32) (result i32)))
+        (import "wasix_32v1" "sock_bind" (func $sock_bind (param i32 i32) (result i32)))
+        (import "wasix_32v1" "sock_send_to" (func $sock_send_to (param i32 i32 i32 i32 i32 i32) (result i32)))
+        (import "wasix_32v1" "sock_recv" (func $sock_recv (param i32 i32 i32 i32 i32 i32) (result i32)))
+        (import "wasix_32v1" "sock_recv_from" (func $sock_recv_from (param i32 i32 i32 i32 i32 i32 i32) (result i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        (data (i32.const 100) "0123456789abcdef")
+
+        ;; 127.0.0.1:{port} for the receiver and 127.0.0.1:0 for the sender
+        (data (i32.const 304) "\7f\00\00\01")
+        (data (i32.const 344) "\7f\00\00\01")
+
+        (func $check (param i32)
+            (if (local.get 0) (then unreachable))
+        )
+
+        ;; Sends `len` bytes from the sender to the receiver
+        (func $send (param $len i32)
+            (i32.store (i32.const 0) (i32.const 100))
+            (i32.store (i32.const 4) (local.get $len))
+            (call $check (call $sock_send_to (i32.load (i32.const 204)) (i32.const 0) (i32.const 1) (i32.const 0) (i32.const 300) (i32.const 208)))
+        )
+
+        (func $main (export "_start")
+            (call $check (call $sock_open (i32.const 1) (i32.const 2) (i32.const 17) (i32.const 200)))
+            (call $check (call $sock_open (i32.const 1) (i32.const 2) (i32.const 17) (i32.const 204)))
+            (i32.store16 (i32.const 300) (i32.const 1))
+            (i32.store16 (i32.const 302) (i32.const {port}))
+            (call $check (call $sock_bind (i32.load (i32.const 200)) (i32.const 300)))
+            (i32.store16 (i32.const 340) (i32.const 1))
+            (call $check (call $sock_bind (i32.load (i32.const 204)) (i32.const 340)))
+
+            ;; The receive buffer only has room for 8 bytes
+            (i32.store (i32.const 16) (i32.const 500))
+            (i32.store (i32.const 20) (i32.const 8))
+
+            ;; A datagram that is too long is truncated
+            (call $send (i32.const 16))
+            (call $check (call $sock_recv (i32.load (i32.const 200)) (i32.const 16) (i32.const 1) (i32.const 0) (i32.const 1024) (i32.const 1028)))
+
+            ;; One that fits is not
+            (call $send (i32.const 4))
+            (call $check (call $sock_recv (i32.load (i32.const 200)) (i32.const 16) (i32.const 1) (i32.const 0) (i32.const 1032) (i32.const 1036)))
+
+            ;; Same for sock_recv_from
+            (call $send (i32.const 16))
+            (call $check (call $sock_recv_from (i32.load (i32.const 200)) (i32.const 16) (i32.const 1) (i32.const 0) (i32.const 1040) (i32.const 1044) (i32.const 400)))
+
+            (i64.store (i32.const 1048) (i64.load (i32.const 500)))
+
+            ;; Send the results to stdout
+            (i32.store (i32.const 0) (i32.const 1024))
+            (i32.store (i32.const 4) (i32.const 32))
+            (call $check (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 8)))
+        )
+    )
+    "#
+    ));
+    assert_eq!(stdout.len(), 32);
+
+    let u32_at = |offset: usize| u32::from_le_bytes(stdout[offset..offset + 4].try_into().unwrap());
+    let truncated = __WASI_SOCK_RECV_OUTPUT_DATA_TRUNCATED as u32;
+
+    assert_eq!(u32_at(0), 8, "only the part that fits is received");
+    assert_eq!(u32_at(4), truncated);
+    assert_eq!(u32_at(8), 4);
+    assert_eq!(u32_at(12), 0, "a datagram that fits is not truncated");
+    assert_eq!(u32_at(16), 8);
+    assert_eq!(u32_at(20), truncated);
+    assert_eq!(&stdout[24..32], b"01234567");
+}
@@ -0,0 +1,96 @@
+use std::sync::Arc;
+
+use virtual_fs::{AsyncWriteExt, FileSystem, TmpFileSystem};
+use virtual_mio::block_on;
+use wasmer_wasix_types::wasi::Errno;
+
+use super::run_wat_with;
+
+#[test]
+fn test_sock_send_file_streams_the_file_into_the_socket() {
+    // Nothing else uses a port that was just released
+    let port = std::net::UdpSocket::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+
+    let program = format!(
+        r#"
+    (module
+        (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "path_open" (func $path_open (param i32 i32 i32 i32 i32 i64 i64 i32 i32) (result i32)))
+        (import "wasix_32v1" "sock_open" (func $sock_open (param i32 i32 i32 i32) (result i32)))
+        (import "wasix_32v1" "sock_bind" (func $sock_bind (param i32 i32) (result i32)))
+        (import "wasix_32v1" "sock_connect" (func $sock_connect (param i32 i32) (result i32)))
+        (import "wasix_32v1" "sock_recv" (func $sock_recv (param i32 i32 i32 i32 i32 i32) (result i32)))
+        (import "wasix_32v1" "sock_send_file" (func $sock_send_file (param i32 i32 i64 i64 i32) (result i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        (data (i32.const 100) "data/file.txt")
+
+        ;; 127.0.0.1:{port} for the receiver and 127.0.0.1:0 for the sender
+        (data (i32.const 304) "\7f\00\00\01")
+        (data (i32.const 344) "\7f\00\00\01")
+
+        (func $check (param i32)
+            (if (local.get 0) (then unreachable))
+        )
+
+        (func $main (export "_start")
+            (call $check (call $path_open (i32.const 3) (i32.const 0) (i32.const 100) (i32.const 13)
+                (i32.const 0) (i64.const -1) (i64.const -1) (i32.const 0) (i32.const 208)))
+
+            (call $check (call $sock_open (i32.const 1) (i32.const 2) (i32.const 17) (i32.const 200)))
+            (call $check (call $sock_open (i32.const 1) (i32.const 2) (i32.const 17) (i32.const 204)))
+            (i32.store16 (i32.const 300) (i32.const 1))
+            (i32.store16 (i32.const 302) (i32.const {port}))
+            (call $check (call $sock_bind (i32.load (i32.const 200)) (i32.const 300)))
+            (i32.store16 (i32.const 340) (i32.const 1))
+            (call $check (call $sock_bind (i32.load (i32.const 204)) (i32.const 340)))
+            (call $check (call $sock_connect (i32.load (i32.const 204)) (i32.const 300)))
+
+            ;; Asking for more than what is left of the file sends the rest of it
+            (i32.store (i32.const 1032)
+                (call $sock_send_file (i32.load (i32.const 204)) (i32.load (i32.const 208)) (i64.const 2) (i64.const 1000) (i32.const 1024)))
+            (i32.store (i32.const 16) (i32.const 1044))
+            (i32.store (i32.const 20) (i32.const 16))
+            (call $check (call $sock_recv (i32.load (i32.const 200)) (i32.const 16) (i32.const 1) (i32.const 0) (i32.const 1040) (i32.const 400)))
+
+            ;; The file can't be sent to something that is not a socket
+            (i32.store (i32.const 1036)
+                (call $sock_send_file (i32.load (i32.const 208)) (i32.load (i32.const 208)) (i64.const 0) (i64.const 4) (i32.const 404)))
+
+            ;; Send the results to stdout
+            (i32.store (i32.const 0) (i32.const 1024))
+            (i32.store (i32.const 4) (i32.const 28))
+            (call $check (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 8)))
+        )
+    )
+    "#
+    );
+
+    let data = TmpFileSystem::new();
+    let mut file = data
+        .new_open_options()
+        .create(true)
+        .write(true)
+        .open("/file.txt")
+        .unwrap();
+    block_on(file.write_all(b"0123456789")).unwrap();
+
+    let stdout = run_wat_with(program, |runner| {
+        runner.with_mount("/data".to_string(), Arc::new(data));
+    });
+    assert_eq!(stdout.len(), 28);
+
+    let u32_at = |offset: usize| u32::from_le_bytes(stdout[offset..offset + 4].try_into().unwrap());
+
+    assert_eq!(u64::from_le_bytes(stdout[0..8].try_into().unwrap()), 8);
+    assert_eq!(u32_at(8), Errno::Success as u32);
+    assert_eq!(u32_at(12), Errno::Notsock as u32);
+    assert_eq!(u32_at(16), 8);
+    assert_eq!(&stdout[20..28], b"23456789");
+}
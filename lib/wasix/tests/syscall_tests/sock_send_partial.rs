@@ -0,0 +1,115 @@
+use std::{
+    mem::MaybeUninit,
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+};
+
+use virtual_net::{
+    VirtualConnectedSocket, VirtualNetworking, VirtualTcpSocket, tcp_pair::TcpSocketHalf,
+};
+use wasmer_wasix::WasiEnv;
+use wasmer_wasix_types::wasi::Errno;
+
+use super::TestRuntime;
+
+/// Connects a non-blocking socket and sends `hello`, `world` and `!` down
+/// it, writing the result and the number of bytes sent of every send to
+/// stdout
+const PROGRAM: &str = r#"
+(module
+    (import "wasi_snapshot_preview1" "fd_fdstat_set_flags" (func $fd_fdstat_set_flags (param i32 i32) (result i32)))
+    (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+    (import "wasix_32v1" "sock_open" (func $sock_open (param i32 i32 i32 i32) (result i32)))
+    (import "wasix_32v1" "sock_connect" (func $sock_connect (param i32 i32) (result i32)))
+    (import "wasix_32v1" "sock_send" (func $sock_send (param i32 i32 i32 i32 i32) (result i32)))
+
+    (memory 1)
+    (export "memory" (memory 0))
+
+    (data (i32.const 100) "helloworld!")
+
+    ;; 10.0.0.1:80
+    (data (i32.const 304) "\0a\00\00\01")
+
+    (func $check (param i32)
+        (if (local.get 0) (then unreachable))
+    )
+
+    ;; Sends `len` bytes from `ptr` and stores the result and the number of
+    ;; bytes sent at `out`
+    (func $send (param $fd i32) (param $ptr i32) (param $len i32) (param $out i32)
+        (i32.store (i32.const 0) (local.get $ptr))
+        (i32.store (i32.const 4) (local.get $len))
+        (i32.store (i32.const 8) (i32.const 0))
+        (i32.store (local.get $out)
+            (call $sock_send (local.get $fd) (i32.const 0) (i32.const 1) (i32.const 0) (i32.const 8)))
+        (i32.store (i32.add (local.get $out) (i32.const 4)) (i32.load (i32.const 8)))
+    )
+
+    (func $main (export "_start")
+        (local $fd i32)
+
+        (call $check (call $sock_open (i32.const 1) (i32.const 1) (i32.const 6) (i32.const 200)))
+        (local.set $fd (i32.load (i32.const 200)))
+        (i32.store16 (i32.const 300) (i32.const 1))
+        (i32.store16 (i32.const 302) (i32.const 80))
+        (call $check (call $sock_connect (local.get $fd) (i32.const 300)))
+        (call $check (call $fd_fdstat_set_flags (local.get $fd) (i32.const 4)))
+
+        (call $send (local.get $fd) (i32.const 100) (i32.const 5) (i32.const 1024))
+        (call $send (local.get $fd) (i32.const 105) (i32.const 5) (i32.const 1032))
+        (call $send (local.get $fd) (i32.const 110) (i32.const 1) (i32.const 1040))
+
+        (i32.store (i32.const 0) (i32.const 1024))
+        (i32.store (i32.const 4) (i32.const 24))
+        (call $check (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 8)))
+    )
+)
+"#;
+
+/// Network whose connections have a send buffer of 8 bytes that nobody
+/// reads from, the other ends of the connections are kept in `peers`
+#[derive(Debug, Default)]
+struct SmallBufferNetworking {
+    peers: Arc<Mutex<Vec<TcpSocketHalf>>>,
+}
+
+#[async_trait::async_trait]
+impl VirtualNetworking for SmallBufferNetworking {
+    async fn connect_tcp(
+        &self,
+        addr: SocketAddr,
+        peer: SocketAddr,
+    ) -> virtual_net::Result<Box<dyn VirtualTcpSocket + Sync>> {
+        let (local, remote) = TcpSocketHalf::channel(8, addr, peer);
+        self.peers.lock().unwrap().push(remote);
+        Ok(Box::new(local))
+    }
+}
+
+#[test]
+fn test_sock_send_reports_partial_sends() {
+    let mut runtime = TestRuntime::new();
+    let networking = SmallBufferNetworking::default();
+    let peers = networking.peers.clone();
+    runtime.rt.set_networking_implementation(networking);
+
+    let (exit_code, stdout) = runtime.spawn_wat(PROGRAM, WasiEnv::builder("main"));
+    assert!(exit_code.is_success());
+
+    let word = |at: usize| u32::from_le_bytes(stdout[at..at + 4].try_into().unwrap());
+
+    // The first send fits, the second only partly and the third not at all
+    assert_eq!([word(0), word(4)], [Errno::Success as u32, 5]);
+    assert_eq!([word(8), word(12)], [Errno::Success as u32, 3]);
+    assert_eq!([word(16), word(20)], [Errno::Again as u32, 0]);
+
+    let mut peers = peers.lock().unwrap();
+    let mut buf = [MaybeUninit::new(0u8); 16];
+    let read = peers[0].try_recv(&mut buf, false).unwrap();
+    let received: Vec<u8> = buf[..read]
+        .iter()
+        .map(|b| unsafe { b.assume_init() })
+        .collect();
+    assert_eq!(received, b"hellowor");
+}
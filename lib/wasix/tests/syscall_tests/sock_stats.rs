@@ -0,0 +1,96 @@
+use wasmer_wasix_types::wasi::Sockstatus;
+
+use super::run_wat;
+
+#[test]
+fn test_sock_stats_count_the_bytes_through_a_connection() {
+    // Nothing else uses a port that was just released
+    let port = std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+
+    let stdout = run_wat(format!(
+        r#"
+    (module
+        (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+        (import "wasix_32v1" "sock_open" (func $sock_open (param i32 i32 i32 i32) (result i32)))
+        (import "wasix_32v1" "sock_bind" (func $sock_bind (param i32 i32) (result i32)))
+        (import "wasix_32v1" "sock_listen" (func $sock_listen (param i32 i32) (result i32)))
+        (import "wasix_32v1" "sock_connect" (func $sock_connect (param i32 i32) (result i32)))
+        (import "wasix_32v1" "sock_accept_v2" (func $sock_accept_v2 (param i32 i32 i32 i32) (result i32)))
+        (import "wasix_32v1" "sock_send" (func $sock_send (param i32 i32 i32 i32 i32) (result i32)))
+        (import "wasix_32v1" "sock_recv" (func $sock_recv (param i32 i32 i32 i32 i32 i32) (result i32)))
+        (import "wasix_32v1" "sock_stats" (func $sock_stats (param i32 i32) (result i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        (data (i32.const 100) "hello")
+
+        ;; 127.0.0.1:{port}
+        (data (i32.const 304) "\7f\00\00\01")
+
+        (func $check (param i32)
+            (if (local.get 0) (then unreachable))
+        )
+
+        (func $main (export "_start")
+            ;; A listener, a client that connects to it and the connection
+            ;; that the listener accepts
+            (call $check (call $sock_open (i32.const 1) (i32.const 1) (i32.const 6) (i32.const 200)))
+            (i32.store16 (i32.const 300) (i32.const 1))
+            (i32.store16 (i32.const 302) (i32.const {port}))
+            (call $check (call $sock_bind (i32.load (i32.const 200)) (i32.const 300)))
+            (call $check (call $sock_listen (i32.load (i32.const 200)) (i32.const 8)))
+            (call $check (call $sock_open (i32.const 1) (i32.const 1) (i32.const 6) (i32.const 204)))
+            (call $check (call $sock_connect (i32.load (i32.const 204)) (i32.const 300)))
+            (call $check (call $sock_stats (i32.load (i32.const 200)) (i32.const 600)))
+            (call $check (call $sock_accept_v2 (i32.load (i32.const 200)) (i32.const 0) (i32.const 208) (i32.const 400)))
+
+            ;; The client sends `hello`, which the connection reads after
+            ;; looking at its stats
+            (i32.store (i32.const 0) (i32.const 100))
+            (i32.store (i32.const 4) (i32.const 5))
+            (call $check (call $sock_send (i32.load (i32.const 204)) (i32.const 0) (i32.const 1) (i32.const 0) (i32.const 8)))
+            (call $check (call $sock_stats (i32.load (i32.const 208)) (i32.const 648)))
+            (i32.store (i32.const 16) (i32.const 500))
+            (i32.store (i32.const 20) (i32.const 16))
+            (call $check (call $sock_recv (i32.load (i32.const 208)) (i32.const 16) (i32.const 1) (i32.const 0) (i32.const 24) (i32.const 28)))
+            (if (i32.ne (i32.load (i32.const 24)) (i32.const 5)) (then unreachable))
+
+            (call $check (call $sock_stats (i32.load (i32.const 204)) (i32.const 696)))
+            (call $check (call $sock_stats (i32.load (i32.const 208)) (i32.const 744)))
+
+            ;; Send the stats to stdout
+            (i32.store (i32.const 0) (i32.const 600))
+            (i32.store (i32.const 4) (i32.const 192))
+            (call $check (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 8)))
+        )
+    )
+    "#
+    ));
+    let stats = |n: usize| &stdout[n * 48..(n + 1) * 48];
+    let field = |n: usize, at: usize| u64::from_le_bytes(stats(n)[at..at + 8].try_into().unwrap());
+    let (bytes_sent, bytes_received, recv_queued, accept_queued) = (0, 8, 24, 32);
+    let (listener, accepted, client, accepted_after_recv) = (0, 1, 2, 3);
+
+    for n in 0..4 {
+        assert_eq!(stats(n)[40], Sockstatus::Opened as u8);
+    }
+
+    // The bytes are counted on both ends once they went through
+    assert_eq!(field(accepted, bytes_received), 0);
+    assert_eq!(field(client, bytes_sent), 5);
+    assert_eq!(field(client, bytes_received), 0);
+    assert_eq!(field(accepted_after_recv, bytes_received), 5);
+    assert_eq!(field(accepted_after_recv, bytes_sent), 0);
+
+    // The host networking only knows about the queues on Linux
+    if cfg!(target_os = "linux") {
+        assert_eq!(field(listener, accept_queued), 1);
+        assert_eq!(field(accepted, recv_queued), 5);
+        assert_eq!(field(accepted_after_recv, recv_queued), 0);
+    }
+}
@@ -0,0 +1,103 @@
+use wasmer_wasix_types::wasi::Errno;
+
+use super::run_wat;
+
+#[test]
+fn test_sock_ttl_is_set_and_received() {
+    let stdout = run_wat(
+        br#"
+    (module
+        (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+        (import "wasix_32v1" "sock_open" (func $sock_open (param i32 i32 i32 i32) (result i32)))
+        (import "wasix_32v1" "sock_bind" (func $sock_bind (param i32 i32) (result i32)))
+        (import "wasix_32v1" "sock_addr_local" (func $sock_addr_local (param i32 i32) (result i32)))
+        (import "wasix_32v1" "sock_set_opt_size" (func $sock_set_opt_size (param i32 i32 i64) (result i32)))
+        (import "wasix_32v1" "sock_get_opt_size" (func $sock_get_opt_size (param i32 i32 i32) (result i32)))
+        (import "wasix_32v1" "sock_set_opt_flag" (func $sock_set_opt_flag (param i32 i32 i32) (result i32)))
+        (import "wasix_32v1" "sock_get_opt_flag" (func $sock_get_opt_flag (param i32 i32 i32) (result i32)))
+        (import "wasix_32v1" "sock_send_to" (func $sock_send_to (param i32 i32 i32 i32 i32 i32) (result i32)))
+        (import "wasix_32v1" "sock_recv_from_ttl" (func $sock_recv_from_ttl (param i32 i32 i32 i32 i32 i32 i32 i32) (result i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        (data (i32.const 100) "ping")
+
+        ;; 127.0.0.1:0 for both the receiver and the sender
+        (data (i32.const 304) "\7f\00\00\01")
+        (data (i32.const 344) "\7f\00\00\01")
+
+        (func $check (param i32)
+            (if (local.get 0) (then unreachable))
+        )
+
+        ;; Sends a datagram from the sender to the receiver and receives it,
+        ;; storing the TTL that it arrived with at `ttl`
+        (func $ping (param $ttl i32)
+            (i32.store (i32.const 0) (i32.const 100))
+            (i32.store (i32.const 4) (i32.const 4))
+            (call $check (call $sock_send_to (i32.load (i32.const 204)) (i32.const 0) (i32.const 1) (i32.const 0) (i32.const 300) (i32.const 208)))
+            (i32.store (i32.const 16) (i32.const 500))
+            (i32.store (i32.const 20) (i32.const 16))
+            (call $check (call $sock_recv_from_ttl (i32.load (i32.const 200)) (i32.const 16) (i32.const 1) (i32.const 0)
+                (i32.const 1100) (i32.const 1104) (i32.const 400) (local.get $ttl)))
+        )
+
+        (func $main (export "_start")
+            (call $check (call $sock_open (i32.const 1) (i32.const 2) (i32.const 17) (i32.const 200)))
+            (call $check (call $sock_open (i32.const 1) (i32.const 2) (i32.const 17) (i32.const 204)))
+            (i32.store16 (i32.const 300) (i32.const 1))
+            (call $check (call $sock_bind (i32.load (i32.const 200)) (i32.const 300)))
+            (i32.store16 (i32.const 340) (i32.const 1))
+            (call $check (call $sock_bind (i32.load (i32.const 204)) (i32.const 340)))
+
+            ;; The datagrams are sent to the port the receiver was bound to,
+            ;; which comes back in network byte order
+            (call $check (call $sock_addr_local (i32.load (i32.const 200)) (i32.const 300)))
+            (i32.store16 (i32.const 302)
+                (i32.or
+                    (i32.shl (i32.load8_u (i32.const 302)) (i32.const 8))
+                    (i32.load8_u (i32.const 303))))
+
+            ;; The sender sends with a TTL of 7
+            (i32.store (i32.const 1024)
+                (call $sock_set_opt_size (i32.load (i32.const 204)) (i32.const 23) (i64.const 7)))
+            (call $check (call $sock_get_opt_size (i32.load (i32.const 204)) (i32.const 23) (i32.const 1048)))
+
+            ;; The receiver asks for the TTL of the datagrams
+            (i32.store (i32.const 1028)
+                (call $sock_set_opt_flag (i32.load (i32.const 200)) (i32.const 28) (i32.const 1)))
+            (drop (call $sock_get_opt_flag (i32.load (i32.const 200)) (i32.const 28) (i32.const 1032)))
+            (call $ping (i32.const 1036))
+
+            ;; Then stops asking for it
+            (drop (call $sock_set_opt_flag (i32.load (i32.const 200)) (i32.const 28) (i32.const 0)))
+            (i32.store (i32.const 1040) (i32.const -1))
+            (call $ping (i32.const 1040))
+
+            ;; Send the results to stdout
+            (i32.store (i32.const 0) (i32.const 1024))
+            (i32.store (i32.const 4) (i32.const 32))
+            (call $check (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 8)))
+        )
+    )
+    "#,
+    );
+    let word = |at: usize| u32::from_le_bytes(stdout[at..at + 4].try_into().unwrap());
+
+    // The TTL reads back as it was set
+    assert_eq!(word(0), Errno::Success as u32);
+    assert_eq!(u64::from_le_bytes(stdout[24..32].try_into().unwrap()), 7);
+
+    // A datagram over the loopback arrives with the TTL it was sent with,
+    // once the receiver stops asking for it the TTL is no longer known
+    if cfg!(any(target_os = "linux", target_os = "android")) {
+        assert_eq!(word(4), Errno::Success as u32);
+        assert_eq!(word(8), 1);
+        assert_eq!(word(12), 7);
+    } else {
+        assert_eq!(word(4), Errno::Notsup as u32);
+        assert_eq!(word(12), 0);
+    }
+    assert_eq!(word(16), 0);
+}
@@ -0,0 +1,108 @@
+use virtual_fs::AsyncReadExt;
+use virtual_mio::block_on;
+use wasmer_wasix::Pipe;
+
+use super::try_run_wat_with;
+
+#[test]
+fn test_thread_stack_overflow() {
+    let (stderr_tx, mut stderr_rx) = Pipe::channel();
+
+    // The failing thread takes the whole process down, the exit code that
+    // the main thread ends up with is not what matters here
+    let _ = try_run_wat_with(
+        br#"
+    (module
+        (import "env" "memory" (memory 2 2 shared))
+        (import "wasix_32v1" "thread_spawn_v2" (func $thread_spawn (param i32 i32) (result i32)))
+        (import "wasix_32v1" "thread_sleep" (func $thread_sleep (param i64) (result i32)))
+
+        (global $__stack_pointer (export "__stack_pointer") (mut i32) (i32.const 65536))
+
+        ;; Every call takes another 64 bytes of stack and never returns
+        (func $recurse (param $depth i32)
+            (global.set $__stack_pointer (i32.sub (global.get $__stack_pointer) (i32.const 64)))
+            (i32.store (global.get $__stack_pointer) (local.get $depth))
+            (call $recurse (i32.add (local.get $depth) (i32.const 1)))
+        )
+
+        (func (export "wasi_thread_start") (param i32 i32)
+            (global.set $__stack_pointer (i32.load (local.get 1)))
+            (call $recurse (i32.const 0))
+        )
+
+        (func $main (export "_start")
+            ;; The thread gets a stack of 16 KiB right below 128 KiB
+            (i32.store (i32.const 1024) (i32.const 131072))
+            (i32.store (i32.const 1080) (i32.const 16384))
+            (if (call $thread_spawn (i32.const 1024) (i32.const 1100)) (then unreachable))
+
+            (loop $wait
+                (drop (call $thread_sleep (i64.const 1000000000)))
+                (br $wait)
+            )
+        )
+    )
+    "#,
+        |runner| {
+            runner.with_stderr(Box::new(stderr_tx));
+        },
+    );
+
+    let mut stderr = String::new();
+    block_on(stderr_rx.read_to_string(&mut stderr)).unwrap();
+    assert!(stderr.ends_with("failed: stack overflow\n"), "{stderr}");
+}
+
+#[test]
+fn test_thread_stack_overflow_is_caught_by_syscalls() {
+    let (stdout_tx, mut stdout_rx) = Pipe::channel();
+    let (stderr_tx, mut stderr_rx) = Pipe::channel();
+
+    let _ = try_run_wat_with(
+        br#"
+    (module
+        (import "env" "memory" (memory 2 2 shared))
+        (import "wasix_32v1" "thread_spawn_v2" (func $thread_spawn (param i32 i32) (result i32)))
+        (import "wasix_32v1" "thread_sleep" (func $thread_sleep (param i64) (result i32)))
+        (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+
+        (global $__stack_pointer (export "__stack_pointer") (mut i32) (i32.const 65536))
+
+        (data (i32.const 2000) "X")
+
+        ;; The stack pointer goes way past the bottom of the stack into memory
+        ;; that is still accessible, so nothing traps until the syscall
+        (func (export "wasi_thread_start") (param i32 i32)
+            (global.set $__stack_pointer (i32.sub (i32.load (local.get 1)) (i32.const 32768)))
+            (i32.store (i32.const 2100) (i32.const 2000))
+            (i32.store (i32.const 2104) (i32.const 1))
+            (drop (call $fd_write (i32.const 1) (i32.const 2100) (i32.const 1) (i32.const 2108)))
+        )
+
+        (func $main (export "_start")
+            ;; The thread gets a stack of 16 KiB right below 128 KiB
+            (i32.store (i32.const 1024) (i32.const 131072))
+            (i32.store (i32.const 1080) (i32.const 16384))
+            (if (call $thread_spawn (i32.const 1024) (i32.const 1100)) (then unreachable))
+
+            ;; The failing thread should take the process down well before this
+            (drop (call $thread_sleep (i64.const 5000000000)))
+        )
+    )
+    "#,
+        |runner| {
+            runner
+                .with_stdout(Box::new(stdout_tx))
+                .with_stderr(Box::new(stderr_tx));
+        },
+    );
+
+    let mut stderr = String::new();
+    block_on(stderr_rx.read_to_string(&mut stderr)).unwrap();
+    assert!(stderr.ends_with("failed: stack overflow\n"), "{stderr}");
+
+    let mut stdout = Vec::new();
+    block_on(stdout_rx.read_to_end(&mut stdout)).unwrap();
+    assert!(stdout.is_empty());
+}
@@ -0,0 +1,63 @@
+use std::{io, time::Duration};
+
+use bytes::Bytes;
+use futures::{SinkExt, StreamExt, channel::mpsc};
+use wasmer_wasix::StreamBackedFile;
+
+use super::run_wat_with;
+
+#[test]
+fn test_stdio_backed_by_streams() {
+    let (stdin_tx, stdin_rx) = mpsc::unbounded::<io::Result<Bytes>>();
+    let (stdout_tx, stdout_rx) = mpsc::unbounded::<Bytes>();
+
+    // The data only arrives once the guest is already waiting for it
+    let sender = std::thread::spawn(move || {
+        std::thread::sleep(Duration::from_millis(100));
+        stdin_tx.unbounded_send(Ok(Bytes::from("ping"))).unwrap();
+    });
+
+    run_wat_with(
+        br#"
+    (module
+        (import "wasi_snapshot_preview1" "fd_read" (func $fd_read (param i32 i32 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "poll_oneoff" (func $poll_oneoff (param i32 i32 i32 i32) (result i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        (func $check (param i32)
+            (if (local.get 0) (then unreachable))
+        )
+
+        (func $main (export "_start")
+            ;; Wait for stdin to become readable
+            (i32.store8 (i32.const 508) (i32.const 1))
+            (i32.store (i32.const 516) (i32.const 0))
+            (call $check (call $poll_oneoff (i32.const 500) (i32.const 600) (i32.const 1) (i32.const 700)))
+            (call $check (i32.ne (i32.load (i32.const 700)) (i32.const 1)))
+            (call $check (i32.load16_u (i32.const 608)))
+
+            ;; Echo what was read to stdout
+            (i32.store (i32.const 0) (i32.const 1024))
+            (i32.store (i32.const 4) (i32.const 64))
+            (call $check (call $fd_read (i32.const 0) (i32.const 0) (i32.const 1) (i32.const 8)))
+            (i32.store (i32.const 4) (i32.load (i32.const 8)))
+            (call $check (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 8)))
+        )
+    )
+    "#,
+        |runner| {
+            runner
+                .with_stdin(Box::new(StreamBackedFile::from_input(stdin_rx)))
+                .with_stdout(Box::new(StreamBackedFile::from_output(
+                    stdout_tx.sink_map_err(io::Error::other),
+                )));
+        },
+    );
+    sender.join().unwrap();
+
+    let stdout: Vec<Bytes> = futures::executor::block_on(stdout_rx.collect());
+    assert_eq!(stdout.concat(), b"ping");
+}
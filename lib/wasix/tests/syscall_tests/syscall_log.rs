@@ -0,0 +1,87 @@
+use std::sync::{Arc, Mutex};
+
+use virtual_fs::AsyncWriteExt;
+use virtual_mio::block_on;
+use wasmer_wasix::{
+    Pipe, WasiEnv,
+    runtime::syscall_log::{SyscallLog, SyscallRecorder, SyscallReplayer, read_syscall_log},
+};
+
+use super::TestRuntime;
+
+/// Reads a byte from stdin, gets a random byte and exits with the sum of
+/// the two.
+const PROGRAM: &str = r#"
+(module
+    (import "wasi_snapshot_preview1" "fd_read" (func $fd_read (param i32 i32 i32 i32) (result i32)))
+    (import "wasi_snapshot_preview1" "random_get" (func $random_get (param i32 i32) (result i32)))
+    (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+
+    (memory 1)
+    (export "memory" (memory 0))
+
+    (func $check (param i32)
+        (if (local.get 0) (then unreachable))
+    )
+
+    (func $main (export "_start")
+        (i32.store (i32.const 0) (i32.const 100))
+        (i32.store (i32.const 4) (i32.const 1))
+        (call $check (call $fd_read (i32.const 0) (i32.const 0) (i32.const 1) (i32.const 8)))
+        (call $check (call $random_get (i32.const 101) (i32.const 1)))
+
+        (call $proc_exit (i32.and
+            (i32.add (i32.load8_u (i32.const 100)) (i32.load8_u (i32.const 101)))
+            (i32.const 127)))
+        unreachable
+    )
+)
+"#;
+
+#[derive(Clone, Default)]
+struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+impl std::io::Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+fn run(log: SyscallLog, stdin: &[u8]) -> i32 {
+    let mut runtime = TestRuntime::new();
+    runtime.rt.set_syscall_log(log);
+
+    let (mut stdin_tx, stdin_rx) = Pipe::channel();
+    block_on(stdin_tx.write_all(stdin)).unwrap();
+    drop(stdin_tx);
+
+    let builder = WasiEnv::builder("main").stdin(Box::new(stdin_rx));
+    let (exit_code, _) = runtime.spawn_wat(PROGRAM, builder);
+    exit_code.raw()
+}
+
+#[test]
+fn test_syscall_log_replays_the_recorded_run() {
+    let buffer = SharedBuffer::default();
+    let recorder = SyscallRecorder::new(buffer.clone()).unwrap();
+    let recorded_code = run(SyscallLog::Record(Arc::new(recorder)), b"hello");
+
+    let log = buffer.0.lock().unwrap().clone();
+    let names = read_syscall_log(log.as_slice())
+        .unwrap()
+        .into_iter()
+        .map(|record| record.name)
+        .collect::<Vec<_>>();
+    assert_eq!(names, vec!["fd_read", "random_get", "proc_exit"]);
+
+    // The replay neither reads stdin nor asks for new random bytes
+    let replayer = Arc::new(SyscallReplayer::from_reader(log.as_slice()).unwrap());
+    let replayed_code = run(SyscallLog::Replay(replayer.clone()), b"");
+    assert_eq!(replayed_code, recorded_code);
+    assert_eq!(replayer.remaining(), 0);
+}
@@ -0,0 +1,49 @@
+use std::{
+    num::NonZeroU32,
+    time::{Duration, Instant},
+};
+
+use super::run_wat_with;
+
+/// Calls `fd_fdstat_get` on stdout 150 times in a tight loop
+const PROGRAM: &[u8] = br#"
+(module
+    (import "wasi_snapshot_preview1" "fd_fdstat_get" (func $fd_fdstat_get (param i32 i32) (result i32)))
+
+    (memory 1)
+    (export "memory" (memory 0))
+
+    (func $main (export "_start")
+        (local $i i32)
+        (loop $again
+            (if (call $fd_fdstat_get (i32.const 1) (i32.const 104)) (then unreachable))
+            (local.set $i (i32.add (local.get $i) (i32.const 1)))
+            (br_if $again (i32.lt_u (local.get $i) (i32.const 150)))
+        )
+    )
+)
+"#;
+
+fn run(max_syscalls_per_second: Option<NonZeroU32>) -> Duration {
+    let start = Instant::now();
+    run_wat_with(PROGRAM, |runner| {
+        if let Some(limit) = max_syscalls_per_second {
+            runner.with_max_syscalls_per_second(limit);
+        }
+    });
+    start.elapsed()
+}
+
+#[test]
+fn test_syscall_loop_is_slowed_to_the_rate_limit() {
+    // The first 100 syscalls are a burst that runs at full speed, the other
+    // 50 are spread out over half a second
+    let limited = run(NonZeroU32::new(100));
+    assert!(
+        limited >= Duration::from_millis(450),
+        "the syscalls took {limited:?}"
+    );
+
+    let unlimited = run(None);
+    assert!(unlimited < limited, "the syscalls took {unlimited:?}");
+}
@@ -0,0 +1,57 @@
+use std::time::Duration;
+
+use wasmer_wasix::{SystemInfo, WasiEnv};
+
+use super::TestRuntime;
+
+/// Writes what `sysinfo` returns to stdout.
+const PROGRAM: &str = r#"
+(module
+    (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+    (import "wasix_32v1" "sysinfo" (func $sysinfo (param i32) (result i32)))
+
+    (memory 1)
+    (export "memory" (memory 0))
+
+    (func $check (param i32)
+        (if (local.get 0) (then unreachable))
+    )
+
+    (func $main (export "_start")
+        (call $check (call $sysinfo (i32.const 1024)))
+
+        (i32.store (i32.const 0) (i32.const 1024))
+        (i32.store (i32.const 4) (i32.const 32))
+        (call $check (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 8)))
+    )
+)
+"#;
+
+#[test]
+fn test_sysinfo_reports_the_overridden_values() {
+    let runtime = TestRuntime::new();
+
+    let builder = WasiEnv::builder("main").system_info(
+        SystemInfo::new()
+            .with_cpus(3)
+            .with_page_size(4096)
+            .with_total_memory(512 << 20)
+            .with_available_memory(128 << 20)
+            .with_uptime(Duration::from_secs(1000)),
+    );
+    let (exit_code, stdout) = runtime.spawn_wat(PROGRAM, builder);
+    assert!(exit_code.is_success());
+
+    assert_eq!(stdout.len(), 32);
+
+    let u32_at = |offset: usize| u32::from_le_bytes(stdout[offset..offset + 4].try_into().unwrap());
+    let u64_at = |offset: usize| u64::from_le_bytes(stdout[offset..offset + 8].try_into().unwrap());
+    assert_eq!(u32_at(0), 3);
+    assert_eq!(u32_at(4), 4096);
+    assert_eq!(u64_at(8), 512 << 20);
+    assert_eq!(u64_at(16), 128 << 20);
+
+    let uptime = Duration::from_nanos(u64_at(24));
+    assert!(uptime >= Duration::from_secs(1000));
+    assert!(uptime < Duration::from_secs(1060));
+}
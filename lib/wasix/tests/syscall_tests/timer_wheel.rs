@@ -0,0 +1,84 @@
+use std::{
+    sync::{Arc, Mutex},
+    task::Waker,
+    time::{Duration, Instant},
+};
+
+use wasmer_wasix::{WasiEnv, runtime::timer_wheel::TimerWheel};
+
+use super::TestRuntime;
+
+/// Sleeps twice for 20 milliseconds.
+const PROGRAM: &str = r#"
+(module
+    (import "wasix_32v1" "thread_sleep" (func $thread_sleep (param i64) (result i32)))
+
+    (memory 1)
+    (export "memory" (memory 0))
+
+    (func $check (param i32)
+        (if (local.get 0) (then unreachable))
+    )
+
+    (func $main (export "_start")
+        (call $check (call $thread_sleep (i64.const 20000000)))
+        (call $check (call $thread_sleep (i64.const 20000000)))
+    )
+)
+"#;
+
+/// Wakes up the sleepers whose deadline has passed on every tick of a
+/// background thread.
+#[derive(Debug, Clone, Default)]
+struct TickingWheel {
+    sleepers: Arc<Mutex<Vec<(Instant, Waker)>>>,
+    registrations: Arc<Mutex<u32>>,
+}
+
+impl TickingWheel {
+    fn tick(&self) {
+        let now = Instant::now();
+        self.sleepers.lock().unwrap().retain(|(deadline, waker)| {
+            if *deadline <= now {
+                waker.wake_by_ref();
+                false
+            } else {
+                true
+            }
+        });
+    }
+}
+
+impl TimerWheel for TickingWheel {
+    fn register(&self, deadline: Instant, waker: &Waker) {
+        *self.registrations.lock().unwrap() += 1;
+        self.sleepers
+            .lock()
+            .unwrap()
+            .push((deadline, waker.clone()));
+    }
+}
+
+#[test]
+fn test_sleeps_use_the_timer_wheel_of_the_runtime() {
+    let mut runtime = TestRuntime::new();
+    let wheel = TickingWheel::default();
+    {
+        let wheel = wheel.clone();
+        std::thread::spawn(move || {
+            while Arc::strong_count(&wheel.sleepers) > 1 {
+                wheel.tick();
+                std::thread::sleep(Duration::from_millis(5));
+            }
+        });
+    }
+
+    runtime.rt.set_timer_wheel(wheel.clone());
+
+    let started = Instant::now();
+    let (exit_code, _) = runtime.spawn_wat(PROGRAM, WasiEnv::builder("main"));
+    assert!(exit_code.is_success());
+
+    assert!(started.elapsed() >= Duration::from_millis(40));
+    assert!(*wheel.registrations.lock().unwrap() >= 2);
+}
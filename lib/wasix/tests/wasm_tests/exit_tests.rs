@@ -6,6 +6,7 @@
 //! - exit-*-in-fficall: Exit from an FFI callback
 //! - exit-*-in-dyncall-thread: Exit from dynamically called thread
 //! - exit-*-in-fficall-thread: Exit from FFI callback in thread
+//! - waitpid-nohang: Non-blocking join of a forked child and its exit status
 
 use super::{run_build_script, run_wasm};
 
@@ -99,3 +100,10 @@ fn test_exit_nonzero_in_dyncall_thread() {
         "exit-nonzero-in-dyncall-thread should fail with non-zero exit code"
     );
 }
+
+#[test]
+fn test_waitpid_nohang() {
+    let wasm_path = run_build_script(file!(), "waitpid-nohang").unwrap();
+    let test_dir = wasm_path.parent().unwrap();
+    run_wasm(&wasm_path, test_dir).unwrap();
+}
@@ -0,0 +1,44 @@
+#include <assert.h>
+#include <errno.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+int main()
+{
+    int pipefd[2];
+    assert(pipe(pipefd) == 0);
+
+    pid_t pid = fork();
+    assert(pid >= 0);
+    if (pid == 0)
+    {
+        // Wait until the parent has checked that we are still running
+        char c;
+        close(pipefd[1]);
+        read(pipefd[0], &c, 1);
+        exit(7);
+    }
+    close(pipefd[0]);
+
+    // The child is blocked so WNOHANG must return immediately
+    int status = 0;
+    assert(waitpid(pid, &status, WNOHANG) == 0);
+    assert(waitpid(-1, &status, WNOHANG) == 0);
+
+    // Let the child exit and reap it
+    assert(write(pipefd[1], "x", 1) == 1);
+    close(pipefd[1]);
+    assert(waitpid(pid, &status, 0) == pid);
+    assert(WIFEXITED(status));
+    assert(WEXITSTATUS(status) == 7);
+
+    // The child was already reaped
+    assert(waitpid(pid, &status, WNOHANG) == -1);
+    assert(errno == ECHILD);
+    assert(waitpid(-1, &status, WNOHANG) == -1);
+    assert(errno == ECHILD);
+
+    return 0;
+}
@@ -5,3 +5,9 @@ fn test_fd_allocate() {
     let wasm = run_build_script(file!(), "fd-allocate").unwrap();
     run_wasm(&wasm, wasm.parent().unwrap()).unwrap();
 }
+
+#[test]
+fn test_fd_append_concurrent() {
+    let wasm = run_build_script(file!(), "fd-append-concurrent").unwrap();
+    run_wasm(&wasm, wasm.parent().unwrap()).unwrap();
+}
@@ -0,0 +1,63 @@
+#include <assert.h>
+#include <fcntl.h>
+#include <pthread.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/stat.h>
+#include <unistd.h>
+
+#define WRITES_PER_THREAD 200
+#define RECORD_LEN 8
+
+static const char *path = "fd_append_concurrent";
+
+static void *appender(void *arg) {
+    char record[RECORD_LEN];
+    memset(record, *(const char *)arg, sizeof(record));
+
+    int fd = open(path, O_WRONLY | O_APPEND);
+    assert(fd >= 0);
+    for (int i = 0; i < WRITES_PER_THREAD; i++) {
+        assert(write(fd, record, sizeof(record)) == sizeof(record));
+    }
+    close(fd);
+    return NULL;
+}
+
+int main(void) {
+    unlink(path);
+    int fd = open(path, O_CREAT | O_TRUNC | O_RDWR, 0644);
+    assert(fd >= 0);
+    close(fd);
+
+    char a = 'a', b = 'b';
+    pthread_t t1, t2;
+    assert(pthread_create(&t1, NULL, appender, &a) == 0);
+    assert(pthread_create(&t2, NULL, appender, &b) == 0);
+    assert(pthread_join(t1, NULL) == 0);
+    assert(pthread_join(t2, NULL) == 0);
+
+    struct stat st;
+    assert(stat(path, &st) == 0);
+    assert(st.st_size == 2 * WRITES_PER_THREAD * RECORD_LEN);
+
+    // Every record must be intact, i.e. no append overwrote part of another
+    fd = open(path, O_RDONLY);
+    assert(fd >= 0);
+    int counts[2] = {0, 0};
+    char record[RECORD_LEN];
+    while (read(fd, record, sizeof(record)) == sizeof(record)) {
+        for (int i = 1; i < RECORD_LEN; i++) {
+            assert(record[i] == record[0]);
+        }
+        counts[record[0] - 'a']++;
+    }
+    close(fd);
+    assert(counts[0] == WRITES_PER_THREAD);
+    assert(counts[1] == WRITES_PER_THREAD);
+
+    assert(unlink(path) == 0);
+
+    printf("All tests passed!\n");
+    return 0;
+}
@@ -0,0 +1,76 @@
+// Checks that path_open reconciles O_DIRECTORY with the kind of the target:
+// files can't be opened as directories, and directories can't be opened for
+// writing.
+
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <sys/stat.h>
+#include <sys/types.h>
+#include <unistd.h>
+
+void error(const char *message)
+{
+    perror(message);
+    exit(-1);
+}
+
+void expect_errno(const char *message, int fd, int expected)
+{
+    if (fd >= 0)
+    {
+        fprintf(stderr, "%s: open unexpectedly succeeded\n", message);
+        exit(-1);
+    }
+    if (errno != expected)
+    {
+        fprintf(stderr, "%s: expected %s, got %s\n", message, strerror(expected), strerror(errno));
+        exit(-1);
+    }
+}
+
+int main()
+{
+    if (mkdir("test-dir", S_IRWXU))
+    {
+        error("mkdir test-dir");
+    }
+
+    int fd = open("test-file", O_CREAT | O_WRONLY, S_IRUSR | S_IWUSR);
+    if (fd < 0)
+    {
+        error("create test-file");
+    }
+    close(fd);
+
+    // A regular file opened with O_DIRECTORY must fail with ENOTDIR
+    fd = open("test-file", O_RDONLY | O_DIRECTORY);
+    expect_errno("open file with O_DIRECTORY", fd, ENOTDIR);
+
+    // A directory opened for writing must fail with EISDIR
+    fd = open("test-dir", O_WRONLY);
+    expect_errno("open directory for writing", fd, EISDIR);
+
+    fd = open("test-dir", O_RDWR);
+    expect_errno("open directory for reading and writing", fd, EISDIR);
+
+    // A directory opened for reading works with and without O_DIRECTORY
+    fd = open("test-dir", O_RDONLY | O_DIRECTORY);
+    if (fd < 0)
+    {
+        error("open directory with O_DIRECTORY");
+    }
+    close(fd);
+
+    fd = open("test-dir", O_RDONLY);
+    if (fd < 0)
+    {
+        error("open directory without O_DIRECTORY");
+    }
+    close(fd);
+
+    printf("0");
+    return 0;
+}
@@ -13,3 +13,19 @@ fn test_epoll_create_ctl_wait() {
     assert_eq!(String::from_utf8_lossy(&result.stdout).trim(), "0");
     assert_eq!(result.exit_code, Some(0));
 }
+
+#[test]
+fn test_poll_oneoff_deadline() {
+    let wasm = run_build_script(file!(), "poll-oneoff-deadline").unwrap();
+    let result = run_wasm_with_result(&wasm, wasm.parent().unwrap()).unwrap();
+    assert_eq!(String::from_utf8_lossy(&result.stdout).trim(), "0");
+    assert_eq!(result.exit_code, Some(0));
+}
+
+#[test]
+fn test_clock_nanosleep() {
+    let wasm = run_build_script(file!(), "clock-nanosleep").unwrap();
+    let result = run_wasm_with_result(&wasm, wasm.parent().unwrap()).unwrap();
+    assert_eq!(String::from_utf8_lossy(&result.stdout).trim(), "0");
+    assert_eq!(result.exit_code, Some(0));
+}
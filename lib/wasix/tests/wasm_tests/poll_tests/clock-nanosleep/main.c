@@ -0,0 +1,80 @@
+#include <assert.h>
+#include <pthread.h>
+#include <signal.h>
+#include <stdint.h>
+#include <stdio.h>
+#include <wasi/api_wasi.h>
+
+#define ABSTIME 1
+#define MS 1000000ULL
+
+__wasi_errno_t wasix_clock_nanosleep(__wasi_clockid_t clock_id,
+                                     uint32_t flags,
+                                     __wasi_timestamp_t request,
+                                     __wasi_timestamp_t *remain)
+    __attribute__((__import_module__("wasix_32v1"), __import_name__("clock_nanosleep")));
+
+__wasi_errno_t wasix_thread_sleep(__wasi_timestamp_t duration)
+    __attribute__((__import_module__("wasix_32v1"), __import_name__("thread_sleep")));
+
+static volatile sig_atomic_t handled = 0;
+
+static void on_signal(int sig) { handled = 1; }
+
+static __wasi_timestamp_t now(__wasi_clockid_t clock_id) {
+    __wasi_timestamp_t t = 0;
+    assert(__wasi_clock_time_get(clock_id, 1, &t) == __WASI_ERRNO_SUCCESS);
+    return t;
+}
+
+static void *interrupter(void *arg) {
+    pthread_t target = *(pthread_t *)arg;
+    wasix_clock_nanosleep(__WASI_CLOCKID_MONOTONIC, 0, 50 * MS, NULL);
+    pthread_kill(target, SIGUSR1);
+    return NULL;
+}
+
+int main(void) {
+    // Relative sleeps on both clocks
+    __wasi_timestamp_t start = now(__WASI_CLOCKID_MONOTONIC);
+    assert(wasix_clock_nanosleep(__WASI_CLOCKID_MONOTONIC, 0, 20 * MS, NULL) == __WASI_ERRNO_SUCCESS);
+    assert(wasix_clock_nanosleep(__WASI_CLOCKID_REALTIME, 0, 20 * MS, NULL) == __WASI_ERRNO_SUCCESS);
+    assert(now(__WASI_CLOCKID_MONOTONIC) - start >= 30 * MS);
+
+    // Absolute sleeps until a point in time, or not at all if it already passed
+    __wasi_timestamp_t deadline = now(__WASI_CLOCKID_REALTIME) + 20 * MS;
+    assert(wasix_clock_nanosleep(__WASI_CLOCKID_REALTIME, ABSTIME, deadline, NULL) == __WASI_ERRNO_SUCCESS);
+    assert(now(__WASI_CLOCKID_REALTIME) >= deadline);
+    assert(wasix_clock_nanosleep(__WASI_CLOCKID_MONOTONIC, ABSTIME, 0, NULL) == __WASI_ERRNO_SUCCESS);
+
+    // Only the monotonic and realtime clocks are supported
+    assert(wasix_clock_nanosleep(__WASI_CLOCKID_PROCESS_CPUTIME_ID, 0, MS, NULL) == __WASI_ERRNO_INVAL);
+
+    // A signal interrupts the sleep after its handler ran, and the time
+    // that was left is reported back
+    signal(SIGUSR1, on_signal);
+    pthread_t self = pthread_self();
+    pthread_t thread;
+    assert(pthread_create(&thread, NULL, interrupter, &self) == 0);
+
+    __wasi_timestamp_t remain = 0;
+    start = now(__WASI_CLOCKID_MONOTONIC);
+    assert(wasix_clock_nanosleep(__WASI_CLOCKID_MONOTONIC, 0, 10000 * MS, &remain) == __WASI_ERRNO_INTR);
+    __wasi_timestamp_t slept = now(__WASI_CLOCKID_MONOTONIC) - start;
+    assert(handled);
+    assert(remain > 0 && remain < 10000 * MS);
+    assert(remain + slept >= 9000 * MS);
+    pthread_join(thread, NULL);
+
+    // thread_sleep runs the handler too but sleeps for the whole duration
+    handled = 0;
+    assert(pthread_create(&thread, NULL, interrupter, &self) == 0);
+    start = now(__WASI_CLOCKID_MONOTONIC);
+    assert(wasix_thread_sleep(200 * MS) == __WASI_ERRNO_SUCCESS);
+    assert(handled);
+    assert(now(__WASI_CLOCKID_MONOTONIC) - start >= 200 * MS);
+    pthread_join(thread, NULL);
+
+    printf("0");
+    return 0;
+}
@@ -0,0 +1,70 @@
+#include <assert.h>
+#include <stdint.h>
+#include <stdio.h>
+#include <unistd.h>
+#include <wasi/api_wasi.h>
+
+typedef struct {
+    uint8_t tag;
+    __wasi_timestamp_t u;
+} option_timestamp_t;
+
+__wasi_errno_t poll_oneoff_deadline(const __wasi_subscription_t *in,
+                                    __wasi_event_t *out,
+                                    __wasi_size_t nsubscriptions,
+                                    const option_timestamp_t *deadline,
+                                    const uint64_t *sigmask,
+                                    __wasi_size_t *nevents)
+    __attribute__((__import_module__("wasix_32v1"), __import_name__("poll_oneoff_deadline")));
+
+static __wasi_timestamp_t now(void) {
+    __wasi_timestamp_t t = 0;
+    assert(__wasi_clock_time_get(__WASI_CLOCKID_MONOTONIC, 1, &t) == __WASI_ERRNO_SUCCESS);
+    return t;
+}
+
+int main(void) {
+    int fds[2];
+    assert(pipe(fds) == 0);
+
+    __wasi_subscription_t sub = {0};
+    sub.userdata = 42;
+    sub.u.tag = __WASI_EVENTTYPE_FD_READ;
+    sub.u.u.fd_read.file_descriptor = fds[0];
+
+    __wasi_event_t out = {0};
+    __wasi_size_t nevents = 99;
+
+    // Nothing to read, so the deadline passes without any events
+    __wasi_timestamp_t start = now();
+    option_timestamp_t deadline = {1, start + 50000000ULL};
+    uint64_t sigmask = 0;
+    assert(poll_oneoff_deadline(&sub, &out, 1, &deadline, &sigmask, &nevents) == __WASI_ERRNO_SUCCESS);
+    assert(nevents == 0);
+    assert(now() - start >= 40000000ULL);
+
+    // A deadline in the past returns immediately
+    deadline.u = start;
+    nevents = 99;
+    assert(poll_oneoff_deadline(&sub, &out, 1, &deadline, NULL, &nevents) == __WASI_ERRNO_SUCCESS);
+    assert(nevents == 0);
+
+    // Once there is data the fd event is reported
+    assert(write(fds[1], "x", 1) == 1);
+    deadline.u = now() + 10000000000ULL;
+    assert(poll_oneoff_deadline(&sub, &out, 1, &deadline, &sigmask, &nevents) == __WASI_ERRNO_SUCCESS);
+    assert(nevents == 1);
+    assert(out.userdata == 42);
+    assert(out.type == __WASI_EVENTTYPE_FD_READ);
+
+    // Without a deadline it behaves like poll_oneoff
+    deadline.tag = 0;
+    assert(poll_oneoff_deadline(&sub, &out, 1, &deadline, NULL, &nevents) == __WASI_ERRNO_SUCCESS);
+    assert(nevents == 1);
+
+    // Without subscriptions or a deadline it would block forever
+    assert(poll_oneoff_deadline(&sub, &out, 0, &deadline, NULL, &nevents) == __WASI_ERRNO_INVAL);
+
+    printf("0");
+    return 0;
+}